@@ -1,33 +1,101 @@
 use std::hint::black_box;
 
-use criterion::{Criterion, criterion_group, criterion_main};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
 use zpack::package::version::Version;
 
-fn criterion_benchmark(c: &mut Criterion) {
-    let bench_suite = [
-        "1.9.0",
-        "v1.10.0",
-        "v1.11.0",
-        "1.0.0-alpha",
-        "v1.0.0-alpha.1",
-        "v1.0.0-0.3.7",
-        "1.0.0-x.7.z.92",
-        "v1.0.0-x-y-z",
-        "1.0.0-alpha+001",
-        "v1.0.0+20130313144700",
-        "1.0.0-beta+exp.sha.5114f85",
-        "v1.0.0+21AF26D3-x.y.x-117B344092BD",
-        "v123456789.123456789.123456789-123456789+0123456789",
-    ];
-
-    for input in bench_suite.into_iter() {
+/// Versions of the kinds packages declare, grouped by style
+const CORPORA: [(&str, &[&str]); 5] = [
+    (
+        "release",
+        &[
+            "1.9.0", "2.3", "1.2.11", "1.2.13", "0.3.21", "0.3.26", "3.0.8",
+            "3.1.4", "2.6.4", "4.1.5", "5.0.3", "12.1.1", "12.4.0", "1.14.1",
+            "1.82.0", "2.7.18", "3.12.4", "10.2.0", "6.0", "8.4.1",
+        ],
+    ),
+    (
+        "prerelease",
+        &[
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "2.0.0-beta.2",
+            "5.4.0-rc1",
+            "3.0.0-rc.3",
+            "1.2.3-dev",
+            "4.0.0-beta+exp.sha.5114f85",
+            "1.0.0+20130313144700",
+        ],
+    ),
+    (
+        "calendar",
+        &["2023.10.1", "2024.1.0", "2025.06", "2022.05.18", "2021.3", "2024.2"],
+    ),
+    ("epoch", &["1:2.0", "2:1.4.0", "1:9.18.28", "3:2.7", "*:1.4.0"]),
+    (
+        "named",
+        &["stable", "latest", "master", "main", "develop", "git", "v1", "v2"],
+    ),
+];
+
+/// The examples from semver.org, with and without a leading `v`
+const SEMVER: [&str; 13] = [
+    "1.9.0",
+    "v1.10.0",
+    "v1.11.0",
+    "1.0.0-alpha",
+    "v1.0.0-alpha.1",
+    "v1.0.0-0.3.7",
+    "1.0.0-x.7.z.92",
+    "v1.0.0-x-y-z",
+    "1.0.0-alpha+001",
+    "v1.0.0+20130313144700",
+    "1.0.0-beta+exp.sha.5114f85",
+    "v1.0.0+21AF26D3-x.y.x-117B344092BD",
+    "v123456789.123456789.123456789-123456789+0123456789",
+];
+
+fn semver(c: &mut Criterion) {
+    for input in SEMVER {
         assert!(Version::new(input).is_ok(), "Invalid version specifier");
 
-        c.bench_function(&format!("semver.org '{}'", input), |b| {
-            b.iter(|| black_box(Version::new(input)))
+        c.bench_function(&format!("semver.org '{input}'"), |b| {
+            b.iter(|| black_box(Version::new(black_box(input))));
+        });
+    }
+}
+
+fn corpora(c: &mut Criterion) {
+    let mut group = c.benchmark_group("corpus");
+
+    for (name, corpus) in CORPORA {
+        for input in corpus {
+            assert!(Version::new(input).is_ok(), "Invalid version '{input}'");
+        }
+
+        group.throughput(Throughput::Elements(corpus.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for input in corpus {
+                    let _ = black_box(Version::new(black_box(input)));
+                }
+            });
         });
     }
+
+    // Every corpus at once, as when loading a repository
+    let all = CORPORA.iter().flat_map(|(_, c)| c.iter()).collect::<Vec<_>>();
+
+    group.throughput(Throughput::Elements(all.len() as u64));
+    group.bench_function("all", |b| {
+        b.iter(|| {
+            for input in &all {
+                let _ = black_box(Version::new(black_box(input)));
+            }
+        });
+    });
+
+    group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, semver, corpora);
 criterion_main!(benches);
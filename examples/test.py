@@ -1,6 +1,6 @@
 import zpack
 from zpack.constraint import *
-from zpack.package import Version, PackageOutline
+from zpack.package import Version, PackageOutline, define
 
 
 zpack.init_tracing()
@@ -23,13 +23,10 @@ class Hpl:
 class Blas:
     def outline(self):
         constraints = [
-            SpecOption("blas", "openblas").if_then(Depends("openblas")),
-            SpecOption("blas", "mkl").if_then(Depends("mkl")),
+            option("blas", "openblas").if_then(depends("openblas")),
+            option("blas", "mkl").if_then(depends("mkl")),
 
-            NumOf([
-                SpecOption("blas", "openblas"),
-                SpecOption("blas", "mkl"),
-            ]) == 1
+            num_of(option("blas:openblas"), option("blas:mkl")) == 1
         ]
 
         outline = PackageOutline("blas")
@@ -132,16 +129,8 @@ class OpenMpi:
         return outline
 
 
-class Mpich:
-    def outline(self):
-        constraints = [
-            Depends("c-compiler")
-        ]
-
-        outline = PackageOutline("mpich")
-        outline.push_constraints(constraints)
-
-        return outline
+with define("mpich") as p:
+    p.depends_on("c-compiler")
 
 
 class IntelMpi:
@@ -207,7 +196,6 @@ def zpack_packages():
         Mkl(),
         OpenMpi(),
         IntelMpi(),
-        Mpich(),
         OpenPmix(),
         OpenPrrte(),
         HwLoc(),
@@ -0,0 +1,72 @@
+/*
+ * The zpack C interface. See src/ffi.rs for details.
+ *
+ * Functions returning int return 0 on success and -1 on failure, and
+ * functions returning pointers return NULL on failure. zpack_last_error()
+ * gives the reason for the last failure on the calling thread.
+ *
+ * No function lets a panic unwind into the caller. A panic inside zpack, such
+ * as in the solver, is caught and reported as a failure like any other, with
+ * the panic message from zpack_last_error().
+ */
+
+#ifndef ZPACK_H
+#define ZPACK_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A set of package outlines to concretize against */
+typedef struct ZpackOutlines ZpackOutlines;
+
+/* Create an empty outline set. Free it with zpack_outlines_free() */
+ZpackOutlines *zpack_outlines_new(void);
+
+/* Free an outline set. Accepts NULL */
+void zpack_outlines_free(ZpackOutlines *set);
+
+/* Add a package given as a JSON serialized package outline */
+int zpack_outlines_add_json(ZpackOutlines *set, const char *json);
+
+/* Add a package with no constraints */
+int zpack_outlines_add_package(ZpackOutlines *set, const char *name);
+
+/*
+ * Add a constraint, written in constraint syntax, to a package in the set.
+ * +name and ~name refer to the options of that package.
+ */
+int zpack_outlines_add_constraint(
+    ZpackOutlines *set,
+    const char *package,
+    const char *constraint
+);
+
+/*
+ * Concretize roots against the outline set, returning the concrete spec as
+ * JSON. Free the result with zpack_string_free()
+ */
+char *zpack_solve(
+    const ZpackOutlines *set,
+    const char *const *roots,
+    size_t n_roots,
+    bool deterministic
+);
+
+/*
+ * The reason the last call on this thread failed, or NULL if none has. Owned
+ * by zpack and valid until the next failing call on this thread
+ */
+const char *zpack_last_error(void);
+
+/* Free a string returned by zpack. Accepts NULL */
+void zpack_string_free(char *ptr);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ZPACK_H */
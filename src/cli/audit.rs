@@ -0,0 +1,163 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anstyle::AnsiColor;
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
+use serde::Serialize;
+
+use crate::{
+    cli::{CliError, SolveOptions, concretize, load_repo_outlines, ui},
+    package::outline::PackageOutline,
+};
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuditStatus {
+    Ok,
+    Unsatisfiable { conflicts: Vec<String> },
+    Error { message: String },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditResult {
+    pub package: String,
+
+    #[serde(flatten)]
+    pub status: AuditStatus,
+}
+
+pub fn command() -> Command {
+    Command::new("audit")
+        .about("Check that every package in the repository concretizes")
+        .long_about(
+            "Attempt to concretize every package in --repo on its own, \
+             reporting the packages which cannot be satisfied and why.\n\n\
+             Exits with a non-zero status if any package fails.",
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .help("Number of packages to concretize in parallel")
+                .value_parser(value_parser!(NonZeroUsize)),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the results as JSON"),
+        )
+}
+
+fn audit_one(
+    outlines: &[PackageOutline],
+    package: &str,
+    options: &SolveOptions,
+) -> AuditResult {
+    let roots = [package.to_string()];
+
+    let status = match concretize(outlines.to_vec(), &roots, options) {
+        Ok(_) => AuditStatus::Ok,
+        Err(CliError::Unsatisfiable(conflicts)) => AuditStatus::Unsatisfiable {
+            conflicts: conflicts.iter().map(ToString::to_string).collect(),
+        },
+        Err(e) => AuditStatus::Error { message: e.to_string() },
+    };
+
+    AuditResult { package: package.to_string(), status }
+}
+
+/// Concretize every package in `outlines` as a root on its own.
+///
+/// Results are returned in the same order as `outlines`.
+///
+/// * `jobs`: The maximum number of packages to concretize at once
+/// * `options`: How to concretize each package
+#[must_use]
+pub fn audit(
+    outlines: &[PackageOutline],
+    jobs: usize,
+    options: &SolveOptions,
+) -> Vec<AuditResult> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; outlines.len()]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(outlines.len()) {
+            scope.spawn(|| {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+
+                    let Some(outline) = outlines.get(idx) else {
+                        break;
+                    };
+
+                    tracing::info!("auditing '{}'", outline.name);
+                    let res = audit_one(outlines, &outline.name, options);
+
+                    results.lock().unwrap()[idx] = Some(res);
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let outlines = load_repo_outlines(matches)?;
+
+    let jobs = matches.get_one::<NonZeroUsize>("jobs").map_or(1, |j| j.get());
+
+    let options = SolveOptions::load(matches)?;
+
+    let results = audit(&outlines, jobs, &options);
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        for result in &results {
+            match &result.status {
+                AuditStatus::Ok => {
+                    println!(
+                        "{} {}",
+                        ui::status(AnsiColor::Green, "ok"),
+                        result.package
+                    );
+                }
+                AuditStatus::Unsatisfiable { conflicts } => {
+                    println!(
+                        "{} {}",
+                        ui::status(AnsiColor::Red, "unsatisfiable"),
+                        result.package
+                    );
+
+                    for conflict in conflicts {
+                        println!("    - {conflict}");
+                    }
+                }
+                AuditStatus::Error { message } => {
+                    println!(
+                        "{} {}: {message}",
+                        ui::status(AnsiColor::Red, "error"),
+                        result.package
+                    );
+                }
+            }
+        }
+    }
+
+    let failures =
+        results.iter().filter(|r| !matches!(r.status, AuditStatus::Ok)).count();
+
+    if failures > 0 {
+        return Err(CliError::AuditFailed(failures));
+    }
+
+    Ok(())
+}
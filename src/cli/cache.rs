@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+
+use crate::{
+    cli::CliError,
+    fetch::store::DownloadCache,
+    interface::cache::OutlineCache,
+    package::provider::VersionCache,
+    util::disk::{self, Usage},
+};
+
+/// Every cache zpack keeps, by name, and the directory it is stored in
+#[must_use]
+pub fn caches() -> [(&'static str, PathBuf); 3] {
+    [
+        ("downloads", DownloadCache::default().dir().to_path_buf()),
+        ("outlines", OutlineCache::default().dir().to_path_buf()),
+        ("versions", VersionCache::default().dir().to_path_buf()),
+    ]
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CacheStats {
+    pub name: &'static str,
+    pub path: PathBuf,
+
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+pub fn command() -> Command {
+    Command::new("cache")
+        .about("Inspect zpack's caches")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("stats")
+                .about("Show the disk space used by each cache")
+                .long_about(
+                    "Show the number of files and disk space used by each \
+                     cache: verified downloads, package outlines extracted \
+                     from package files and listed versions. Use 'zpack \
+                     clean' to remove them.",
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the statistics as JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+}
+
+fn stats(matches: &ArgMatches) -> Result<(), CliError> {
+    let stats = caches()
+        .into_iter()
+        .map(|(name, path)| {
+            Ok(CacheStats { name, usage: disk::usage(&path)?, path })
+        })
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    let mut total = Usage::default();
+
+    for cache in &stats {
+        println!(
+            "{:<10} {:<28} {}",
+            cache.name,
+            cache.usage.to_string(),
+            cache.path.display()
+        );
+
+        total += cache.usage;
+    }
+
+    println!("{:<10} {total}", "total");
+
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("stats", sub)) => stats(sub),
+        _ => unreachable!("subcommand required"),
+    }
+}
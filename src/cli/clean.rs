@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    cli::{CliError, cache::caches},
+    util::{
+        disk::{self, Usage},
+        parsers,
+    },
+};
+
+pub fn command() -> Command {
+    Command::new("clean")
+        .about("Remove cached data")
+        .long_about(
+            "Remove the selected caches, or every cache if none are selected. \
+             With --older-than, only entries which have not been used for at \
+             least AGE are removed.\n\n\
+             AGE is a whole number followed by a unit: s, m, h, d or w, such \
+             as '30d'. Use 'zpack cache stats' to see how much space each \
+             cache uses.",
+        )
+        .arg(
+            Arg::new("downloads")
+                .long("downloads")
+                .help("Clean the cache of verified downloads")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("outlines")
+                .long("outlines")
+                .help("Clean the cache of extracted package outlines")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("versions")
+                .long("versions")
+                .help("Clean the cache of listed versions")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("older-than")
+                .long("older-than")
+                .value_name("AGE")
+                .help("Only remove entries which have not been used for AGE")
+                .value_parser(parsers::parse_duration),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let older_than = matches.get_one::<Duration>("older-than").copied();
+
+    let selected = caches()
+        .into_iter()
+        .filter(|(name, _)| matches.get_flag(name))
+        .collect::<Vec<_>>();
+
+    let selected =
+        if selected.is_empty() { caches().to_vec() } else { selected };
+
+    let mut total = Usage::default();
+
+    for (name, dir) in selected {
+        let removed = match older_than {
+            Some(age) => disk::remove_unused(&dir, age)?,
+            None => disk::remove_all(&dir)?,
+        };
+
+        println!("{name}: removed {removed}");
+        total += removed;
+    }
+
+    println!("Freed {}", disk::format_bytes(total.bytes));
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! `zpack complete`: dynamic completion of package names and options.
+//!
+//! The completions generated by `--generate` only know zpack's flags. Shells
+//! call `zpack complete -- WORDS...` with the command line up to the cursor
+//! (without the leading `zpack`) to complete specs from the loaded
+//! repositories (see [`crate::spec::complete`]), printing one candidate per
+//! line. Nothing is printed if the word is not a spec, so the shell can fall
+//! back to the static completions.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    cli::{CliError, build_cli, global_args, load_repos},
+    spec::complete,
+};
+
+/// Positional arguments which take specs or package names
+const SPEC_ARGS: [&str; 5] = ["specs", "spec", "package", "old", "new"];
+
+/// Registers `zpack complete` with bash, falling back to the completions
+/// generated by `--generate bash` if they have been loaded
+const BASH_SCRIPT: &str = r#"_zpack_dynamic() {
+    local cur words cword
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n =: cur words cword
+    else
+        cur=${COMP_WORDS[COMP_CWORD]} words=("${COMP_WORDS[@]}")
+        cword=$COMP_CWORD
+    fi
+
+    cur=${cur#[\"\']}
+    local word=${COMP_WORDS[COMP_CWORD]#[\"\']}
+
+    local IFS=$'\n'
+    local candidates=($(zpack complete -- "${words[@]:1:cword-1}" "$cur" 2>/dev/null))
+
+    if (( ${#candidates[@]} )); then
+        # Only the text after the last word break is replaced
+        local drop=${cur:0:${#cur}-${#word}}
+        COMPREPLY=("${candidates[@]/#"$drop"/}")
+        compopt -o nospace
+    elif declare -F _zpack >/dev/null; then
+        _zpack "$@"
+    fi
+}
+
+complete -F _zpack_dynamic zpack
+"#;
+
+pub fn command() -> Command {
+    Command::new("complete")
+        .about("Complete package names and options for shells")
+        .long_about(
+            "Print completions for the last of WORDS, the command line up to \
+             the cursor without the leading 'zpack', one per line. Package \
+             names, option names and option values are completed from the \
+             repositories given on the command line and in the \
+             configuration. Nothing is printed for words which are not \
+             specs.\n\n\
+             For bash, add the following to ~/.bashrc after loading the \
+             completions from 'zpack --generate bash':\n\n    \
+             eval \"$(zpack complete --bash)\"",
+        )
+        .arg(
+            Arg::new("bash").long("bash").action(ArgAction::SetTrue).help(
+                "Print a script registering dynamic completion with bash",
+            ),
+        )
+        .arg(
+            Arg::new("words")
+                .num_args(0..)
+                .last(true)
+                .allow_hyphen_values(true)
+                .help("The command line up to the cursor"),
+        )
+}
+
+/// Whether the last of `words` is a spec, and the global arguments given
+/// before it, with their values.
+fn classify(words: &[String]) -> (bool, Vec<String>) {
+    let globals = global_args()
+        .iter()
+        .map(|arg| arg.get_id().to_string())
+        .collect::<Vec<_>>();
+
+    let mut cmd = build_cli();
+    cmd.build();
+
+    let mut forwarded = Vec::new();
+    let mut positional = 0;
+    let mut iter = words[..words.len() - 1].iter();
+
+    while let Some(word) = iter.next() {
+        if word == "--" {
+            continue;
+        }
+
+        let Some(flag) = word.strip_prefix('-') else {
+            if let Some(sub) = cmd.find_subcommand(word) {
+                cmd = sub.clone();
+                positional = 0;
+            } else {
+                positional += 1;
+            }
+
+            continue;
+        };
+
+        let (name, inline) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (flag, None),
+        };
+
+        let arg = match name.strip_prefix('-') {
+            Some(long) => {
+                cmd.get_arguments().find(|a| a.get_long() == Some(long))
+            }
+            None => cmd
+                .get_arguments()
+                .find(|a| a.get_short().is_some_and(|s| name.starts_with(s))),
+        };
+
+        let Some(arg) = arg else { continue };
+        let global = globals.iter().any(|id| id == arg.get_id().as_str());
+
+        if global {
+            forwarded.push(word.clone());
+        }
+
+        if arg.get_action().takes_values() && inline.is_none() {
+            let value = iter.next();
+
+            if global {
+                forwarded.extend(value.cloned());
+            }
+        }
+    }
+
+    let current = words.last().map(String::as_str).unwrap_or_default();
+
+    let positionals = cmd.get_positionals().collect::<Vec<_>>();
+    // Every further positional is given to a trailing list of specs
+    let arg = positionals.get(positional).or_else(|| {
+        positionals
+            .last()
+            .filter(|a| matches!(a.get_action(), ArgAction::Append))
+    });
+
+    let is_spec = !current.starts_with('-')
+        && arg.is_some_and(|a| SPEC_ARGS.contains(&a.get_id().as_str()));
+
+    (is_spec, forwarded)
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    if matches.get_flag("bash") {
+        print!("{BASH_SCRIPT}");
+        return Ok(());
+    }
+
+    let words = matches
+        .get_many::<String>("words")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let Some(word) = words.last() else {
+        return Ok(());
+    };
+
+    let (is_spec, forwarded) = classify(&words);
+
+    if !is_spec {
+        return Ok(());
+    }
+
+    // Load the repositories as the completed command would
+    let args = ["zpack".to_string(), "complete".to_string()]
+        .into_iter()
+        .chain(forwarded);
+
+    let repos = match build_cli().try_get_matches_from(args) {
+        Ok(m) => m
+            .subcommand_matches("complete")
+            .map_or_else(|| Err(CliError::MissingRepository), load_repos),
+        Err(e) => {
+            tracing::warn!("cannot parse the command line: {e}");
+            return Ok(());
+        }
+    };
+
+    match repos {
+        Ok(repos) => {
+            for candidate in complete::complete(&repos, word) {
+                println!("{candidate}");
+            }
+        }
+        Err(e) => tracing::warn!("cannot load repositories: {e}"),
+    }
+
+    Ok(())
+}
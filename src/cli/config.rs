@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{CliError, load_config},
+    config::Config,
+};
+
+pub fn command() -> Command {
+    Command::new("config")
+        .about("Read or change the configuration")
+        .long_about(
+            "Read or change options in the configuration file. Each option is \
+             named by its path, with nested keys separated by '.', such as \
+             'install_tree.padded_length'.",
+        )
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("get")
+                .about("Show the value of an option")
+                .long_about(
+                    "Show the value of KEY in effect, including defaults and \
+                     overrides from the environment and command line.",
+                )
+                .arg(Arg::new("key").required(true).help("The option to show")),
+        )
+        .subcommand(
+            Command::new("set")
+                .about("Set an option in the configuration file")
+                .long_about(
+                    "Set KEY to VALUE in the file given by --config, or the \
+                     user configuration file, creating it if needed. VALUE is \
+                     parsed as YAML, so '128' is a number and '[a, b]' a \
+                     list. The file is left unchanged if the result would be \
+                     invalid.",
+                )
+                .arg(Arg::new("key").required(true).help("The option to set"))
+                .arg(
+                    Arg::new("value")
+                        .required(true)
+                        .allow_hyphen_values(true)
+                        .help("The value to set it to"),
+                ),
+        )
+}
+
+fn get(matches: &ArgMatches) -> Result<(), CliError> {
+    let key = matches.get_one::<String>("key").unwrap();
+
+    match load_config(matches)?.get(key)? {
+        serde_json::Value::String(value) => println!("{value}"),
+        value => println!("{}", serde_json::to_string_pretty(&value)?),
+    }
+
+    Ok(())
+}
+
+fn set(matches: &ArgMatches) -> Result<(), CliError> {
+    let key = matches.get_one::<String>("key").unwrap();
+    let value = matches.get_one::<String>("value").unwrap();
+
+    let path = Config::set(
+        matches.get_one::<PathBuf>("config").map(PathBuf::as_path),
+        key,
+        value,
+    )?;
+
+    println!("Set {key} in {}", path.display());
+
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("get", sub)) => get(sub),
+        Some(("set", sub)) => set(sub),
+        _ => unreachable!("subcommand required"),
+    }
+}
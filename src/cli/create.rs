@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+
+use crate::{
+    cli::{CliError, edit::open_in_editor, load_config},
+    fetch::{self, Fetcher},
+    package::{
+        template::{self, Scaffold, Template},
+        version::Version,
+    },
+    util::digest::{self, Algorithm, Checksum},
+};
+
+pub fn command() -> Command {
+    Command::new("create")
+        .about("Create a package file for a new package")
+        .long_about(
+            "Write a package file defining NAME, with the build dependencies \
+             of --template. With --url, the archive is downloaded to fill in \
+             its checksum, and its version is guessed from the file name \
+             unless --version is given.\n\n\
+             The file defines a single package and can be used with --repo \
+             or added to the 'repos' configuration option. It is opened in \
+             $VISUAL or $EDITOR once written, unless --skip-editor is given.",
+        )
+        .arg(Arg::new("name").required(true).help("Name of the package"))
+        .arg(
+            Arg::new("template")
+                .long("template")
+                .value_name("TEMPLATE")
+                .default_value("generic")
+                .value_parser(|s: &str| s.parse::<Template>())
+                .help(
+                    "Build system of the package: generic, autotools, cmake, \
+                     meson or python",
+                ),
+        )
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .value_hint(ValueHint::Url)
+                .help("URL of a source archive of the package"),
+        )
+        .arg(
+            Arg::new("version")
+                .long("version")
+                .requires("url")
+                .value_parser(|s: &str| {
+                    Version::new(s)
+                        .map(|_| s.to_string())
+                        .map_err(|e| format!("invalid version '{s}': {e:?}"))
+                })
+                .help(
+                    "Version of the archive at --url. Guessed from the URL by \
+                     default",
+                ),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath)
+                .help(
+                    "File to write the package to. Defaults to NAME.py in \
+                     the current directory",
+                ),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .help("Overwrite the output file if it already exists"),
+        )
+        .arg(
+            Arg::new("skip-editor")
+                .long("skip-editor")
+                .action(ArgAction::SetTrue)
+                .help("Do not open the new file in an editor"),
+        )
+}
+
+/// Download the archive at `url` into a temporary directory and return its
+/// checksum.
+fn checksum(
+    matches: &ArgMatches,
+    package: &str,
+    url: &str,
+) -> Result<Checksum, CliError> {
+    let fetcher = Fetcher::new(&load_config(matches)?);
+    let dir = tempfile::tempdir()?;
+
+    println!("Fetching {url}");
+
+    let path = fetcher.fetch(
+        package,
+        url,
+        None,
+        &dir.path().join(fetch::url_file_name(url)),
+    )?;
+
+    Ok(digest::digest_file(&path, Algorithm::Sha256)?)
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let name = matches.get_one::<String>("name").unwrap();
+    let template = *matches.get_one::<Template>("template").unwrap();
+
+    let output = matches
+        .get_one::<PathBuf>("output")
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from(format!("{name}.py")));
+
+    if output.exists() && !matches.get_flag("force") {
+        return Err(CliError::FileExists(output));
+    }
+
+    let mut scaffold = Scaffold::new(name, template);
+
+    if let Some(url) = matches.get_one::<String>("url") {
+        scaffold.version = matches
+            .get_one::<String>("version")
+            .cloned()
+            .or_else(|| template::guess_version(url).map(str::to_string));
+
+        if scaffold.version.is_none() {
+            eprintln!(
+                "warning: could not determine the version of '{url}'; pass \
+                 --version or fill it in by hand"
+            );
+        }
+
+        scaffold.checksum = Some(checksum(matches, name, url)?);
+        scaffold.url = Some(url.clone());
+    }
+
+    std::fs::write(&output, scaffold.render())?;
+    println!("Created {}", output.display());
+
+    if !matches.get_flag("skip-editor") {
+        open_in_editor(&output, None)?;
+    }
+
+    Ok(())
+}
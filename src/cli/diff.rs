@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    cli::{CliError, concretize_repo, ui},
+    spec::{ConcreteSpec, diff::SpecDiff},
+};
+
+pub fn command() -> Command {
+    Command::new("diff")
+        .about("Show the differences between two concrete specs")
+        .long_about(
+            "Show the differences between two concrete specs.\n\nEach spec is \
+             either a path to a concrete spec JSON file or the name of a \
+             package to concretize against --repo.",
+        )
+        .arg(Arg::new("old").required(true).help("The original spec"))
+        .arg(Arg::new("new").required(true).help("The updated spec"))
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the diff as JSON"),
+        )
+}
+
+fn load_spec(
+    matches: &ArgMatches,
+    spec: &str,
+) -> Result<ConcreteSpec, CliError> {
+    let path = Path::new(spec);
+
+    if path.is_file() {
+        tracing::info!("loading concrete spec from '{spec}'");
+        Ok(ConcreteSpec::from_json(&std::fs::read_to_string(path)?)?)
+    } else {
+        tracing::info!("concretizing '{spec}'");
+        concretize_repo(matches, &[spec.to_string()])
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let old = load_spec(matches, matches.get_one::<String>("old").unwrap())?;
+    let new = load_spec(matches, matches.get_one::<String>("new").unwrap())?;
+
+    let diff = SpecDiff::new(&old, &new);
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else if diff.is_empty() {
+        println!("No differences");
+    } else {
+        print!("{}", diff.render(ui::color()));
+    }
+
+    Ok(())
+}
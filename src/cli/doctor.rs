@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use anstyle::AnsiColor;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+
+use crate::{
+    cli::{CliError, load_config, ui},
+    package::{
+        doctor::{self, Problem},
+        install::InstallDb,
+    },
+};
+
+/// What `zpack doctor --json` outputs
+#[derive(Clone, Debug, Serialize)]
+struct DoctorReport {
+    root: PathBuf,
+    problems: Vec<Problem>,
+
+    /// Whether the problems were repaired
+    repaired: bool,
+}
+
+pub fn command() -> Command {
+    Command::new("doctor")
+        .about("Find and repair problems with the install root")
+        .long_about(
+            "Check the install database against the install root: installs \
+             which were interrupted, leaving a staging directory or partial \
+             prefix behind, packages recorded as installed whose prefix has \
+             since been removed or whose spec file does not match the \
+             database, and directories named like prefixes which the \
+             database does not know about.\n\n\
+             With --repair, interrupted installs are cleaned up and \
+             mismatched prefixes quarantined, each recorded as failed so it \
+             is retried by the next install, missing packages are forgotten \
+             and orphaned directories removed. Otherwise, exits with a \
+             non-zero status if any problem is found.",
+        )
+        .arg(
+            Arg::new("repair")
+                .long("repair")
+                .action(ArgAction::SetTrue)
+                .help("Repair the problems found"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the problems found as JSON"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let config = load_config(matches)?;
+    let mut db = InstallDb::open(&config.install_tree)?;
+
+    let problems = doctor::check(&db)?;
+    let repair = matches.get_flag("repair");
+
+    if repair {
+        for problem in &problems {
+            doctor::repair(&mut db, problem)?;
+        }
+    }
+
+    let found = problems.len();
+
+    if matches.get_flag("json") {
+        let report = DoctorReport {
+            root: db.root().to_path_buf(),
+            problems,
+            repaired: repair,
+        };
+
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if problems.is_empty() {
+        println!("No problems found in {}", db.root().display());
+    } else {
+        let status = if repair {
+            ui::status(AnsiColor::Green, "repaired")
+        } else {
+            ui::status(AnsiColor::Yellow, "problem")
+        };
+
+        for problem in &problems {
+            println!("{status} {problem}");
+        }
+    }
+
+    if repair || found == 0 {
+        Ok(())
+    } else {
+        Err(CliError::DoctorFailed(found))
+    }
+}
@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use clap::{Arg, ArgMatches, Command};
+
+use crate::{
+    cli::{CliError, repositories},
+    util::suggest,
+};
+
+pub fn command() -> Command {
+    Command::new("edit")
+        .about("Open a package's definition in an editor")
+        .long_about(
+            "Open the package file defining PACKAGE in $VISUAL or $EDITOR, \
+             at the line the package is defined. The highest-priority \
+             repository defining the package is used, unless the name is \
+             qualified with a namespace, such as 'builtin.openmpi'. Without \
+             PACKAGE, the highest-priority repository is opened.\n\n\
+             Package files are searched rather than executed, so a file \
+             which currently fails to load can still be edited.",
+        )
+        .arg(Arg::new("package").help("Package to edit"))
+}
+
+/// Editors which accept `+LINE` before a file to open it at that line
+const LINE_ARG_EDITORS: [&str; 10] = [
+    "vi",
+    "vim",
+    "nvim",
+    "view",
+    "nano",
+    "emacs",
+    "emacsclient",
+    "kak",
+    "micro",
+    "joe",
+];
+
+/// Open `path` in the user's editor, at `line` if the editor supports it,
+/// and wait for it to exit.
+///
+/// The editor is taken from `$VISUAL`, then `$EDITOR`, falling back to `vi`.
+/// It may include arguments, such as `code --wait`.
+///
+/// # Errors
+/// Errors if the editor cannot be run or exits unsuccessfully.
+pub fn open_in_editor(
+    path: &Path,
+    line: Option<usize>,
+) -> Result<(), CliError> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(words);
+
+    let name = Path::new(program).file_name().and_then(|n| n.to_str());
+
+    if let Some(line) = line
+        && name.is_some_and(|name| LINE_ARG_EDITORS.contains(&name))
+    {
+        cmd.arg(format!("+{line}"));
+    }
+
+    tracing::info!("opening {} with '{editor}'", path.display());
+
+    let status = cmd.arg(path).status().map_err(|e| {
+        CliError::Editor(format!("could not run '{program}': {e}"))
+    })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CliError::Editor(format!("'{program}' exited with {status}")))
+    }
+}
+
+/// Every package defined in the package file `source`, with the line its
+/// `PackageOutline` is created on, counting from one.
+fn definitions(source: &str) -> Vec<(&str, usize)> {
+    let mut res = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        for (start, _) in line.match_indices("PackageOutline(") {
+            let rest = line[start + "PackageOutline(".len()..].trim_start();
+
+            let Some(quote) =
+                rest.chars().next().filter(|c| matches!(c, '"' | '\''))
+            else {
+                continue;
+            };
+
+            if let Some((name, _)) = rest[1..].split_once(quote) {
+                res.push((name, idx + 1));
+            }
+        }
+    }
+
+    res
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let repos = repositories(matches)?;
+
+    let Some(name) = matches.get_one::<String>("package") else {
+        return open_in_editor(&repos[0].path, None);
+    };
+
+    let (namespace, bare) = match name.split_once('.') {
+        Some((namespace, rest))
+            if repos.iter().any(|repo| repo.namespace == namespace) =>
+        {
+            (Some(namespace), rest)
+        }
+        _ => (None, name.as_str()),
+    };
+
+    let mut known = Vec::new();
+
+    for repo in repos
+        .iter()
+        .filter(|repo| namespace.is_none_or(|ns| repo.namespace == ns))
+    {
+        let source = std::fs::read_to_string(&repo.path)?;
+        let defs = definitions(&source);
+
+        if let Some((_, line)) = defs.iter().find(|(def, _)| *def == bare) {
+            return open_in_editor(&repo.path, Some(*line));
+        }
+
+        known.extend(defs.into_iter().map(|(def, _)| def.to_string()));
+    }
+
+    tracing::error!("no repository defines package '{name}'");
+
+    Err(CliError::UnknownPackage {
+        name: name.clone(),
+        suggestion: suggest::closest(bare, known.iter().map(String::as_str))
+            .map(str::to_string),
+    })
+}
@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+
+use crate::{
+    cli::{CliError, concretize_repo, load_config},
+    spec::conda::{CondaExport, CondaFormat},
+};
+
+pub fn command() -> Command {
+    Command::new("export")
+        .about("Export concretized specs as a conda or pixi environment")
+        .long_about(
+            "Concretize one or more specs together against --repo and write \
+             the packages in the result which are available from conda \
+             channels as a conda environment.yml or a pixi.toml manifest, \
+             each pinned to its concretized version.\n\n\
+             A package is assumed to be available under its own name, \
+             without any 'py-' prefix, unless the 'conda.packages' \
+             configuration option maps it to another name or to null. \
+             Packages without a version are never exported. Packages which \
+             are not exported are listed at the top of the file, so they can \
+             be built with zpack.",
+        )
+        .arg(
+            Arg::new("specs")
+                .required(true)
+                .action(ArgAction::Append)
+                .help("Root specs to concretize"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value(CondaFormat::Conda.name())
+                .value_parser(CondaFormat::ALL.map(CondaFormat::name))
+                .help(
+                    "The file to write: a conda environment or pixi manifest",
+                ),
+        )
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .value_name("NAME")
+                .help("Name of the environment. Defaults to the first spec"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath)
+                .help(
+                    "File to write the environment to. Defaults to standard \
+                     output",
+                ),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let specs = matches
+        .get_many::<String>("specs")
+        .unwrap()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let format = matches
+        .get_one::<String>("format")
+        .unwrap()
+        .parse::<CondaFormat>()
+        .map_err(CliError::InvalidSpec)?;
+
+    let config = load_config(matches)?;
+    let concrete = concretize_repo(matches, &specs)?;
+
+    let name =
+        matches.get_one::<String>("name").cloned().unwrap_or_else(|| {
+            concrete.roots.first().cloned().unwrap_or_else(|| "zpack".into())
+        });
+
+    let export = CondaExport::new(name, &concrete, &config.conda);
+    let rendered = export.render(format, &config.platform);
+
+    match matches.get_one::<PathBuf>("output") {
+        Some(path) => {
+            std::fs::write(path, rendered)?;
+
+            println!(
+                "Wrote {} of {} package(s) to '{}'",
+                export.packages.len(),
+                concrete.packages.len(),
+                path.display()
+            );
+        }
+        None => print!("{rendered}"),
+    }
+
+    if !export.skipped.is_empty() {
+        eprintln!(
+            "warning: {} package(s) were not exported and are left to zpack: \
+             {}",
+            export.skipped.len(),
+            export.skipped.join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::path::Path;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+
+use crate::{
+    cli::{CliError, load_config, parse_specs, ui},
+    package::install::{InstallDb, Installed},
+    spec::{concrete::ConcretePackage, parse::is_name_char},
+};
+
+/// An installed package, as output by `zpack find --json`
+#[derive(Clone, Debug, Serialize)]
+struct Found<'a> {
+    hash: &'a str,
+    prefix: &'a Path,
+    package: &'a ConcretePackage,
+
+    /// Everything the package depends on, with `--deps`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<Vec<Dependency<'a>>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Dependency<'a> {
+    hash: &'a str,
+    package: &'a ConcretePackage,
+}
+
+pub fn command() -> Command {
+    Command::new("find")
+        .about("Find installed packages")
+        .long_about(
+            "List the packages installed to the install root which match any \
+             of the given specs, or every installed package if none are \
+             given. A package matches a spec if it has every version and \
+             option the spec gives, and each package given with '^' matches \
+             something it depends on. Versions may be given as ranges, such \
+             as 'openmpi@5: +internal-pmix ^hwloc@:2.9'.\n\n\
+             Specs may be given as separate words: each word starting with a \
+             package name begins a new spec, and every other word belongs to \
+             the spec before it.",
+        )
+        .arg(
+            Arg::new("specs")
+                .action(ArgAction::Append)
+                .allow_hyphen_values(true)
+                .help("Specs to match installed packages against"),
+        )
+        .arg(
+            Arg::new("paths")
+                .short('p')
+                .long("paths")
+                .action(ArgAction::SetTrue)
+                .help("Show the install prefix of each package"),
+        )
+        .arg(
+            Arg::new("deps")
+                .short('d')
+                .long("deps")
+                .action(ArgAction::SetTrue)
+                .help("Show the dependencies of each package"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the packages found as JSON"),
+        )
+}
+
+/// Join command line words into specs, starting a new spec at each word
+/// which starts with a package name rather than a version or option.
+fn group_specs<'a>(words: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let mut specs = Vec::<String>::new();
+
+    for word in words {
+        let starts_spec = word.starts_with(is_name_char)
+            && !word.split_whitespace().next().is_some_and(|w| w.contains('='));
+
+        match specs.last_mut() {
+            Some(spec) if !starts_spec => {
+                spec.push(' ');
+                spec.push_str(word);
+            }
+            _ => specs.push(word.clone()),
+        }
+    }
+
+    specs
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let specs =
+        group_specs(matches.get_many::<String>("specs").unwrap_or_default());
+    let queries = parse_specs(&specs)?;
+
+    let config = load_config(matches)?;
+    let db = InstallDb::open(&config.install_tree)?;
+
+    let found = db.find(&queries);
+    let deps = matches.get_flag("deps");
+
+    if matches.get_flag("json") {
+        let found = found
+            .iter()
+            .filter_map(|installed| {
+                Some(Found {
+                    hash: &installed.hash,
+                    prefix: &installed.record.prefix,
+                    package: installed.package()?,
+                    dependencies: deps.then(|| dependencies(installed)),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        println!("{}", serde_json::to_string_pretty(&found)?);
+        return Ok(());
+    }
+
+    if found.is_empty() {
+        println!("No installed packages found in {}", db.root().display());
+        return Ok(());
+    }
+
+    let paths = matches.get_flag("paths");
+
+    // The prefix of a package in the spec file of an installed package,
+    // which the database records under the hash the spec file gives it
+    let prefix = |installed: &Installed, package: &ConcretePackage| {
+        let hash = installed.spec.hashes.get(&package.name)?;
+        let record = db.get(hash)?;
+
+        paths.then(|| record.prefix.display().to_string())
+    };
+
+    for installed in &found {
+        let spec = &installed.spec.spec;
+
+        if deps {
+            print!(
+                "{}",
+                ui::tree_with(spec, |package| prefix(installed, package))
+            );
+        } else if let Some(package) = installed.package() {
+            let suffix = prefix(installed, package);
+            println!(
+                "{}",
+                ui::package_line(spec, package, 0, suffix.as_deref())
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The packages `installed` depends on, excluding itself
+fn dependencies(installed: &Installed) -> Vec<Dependency<'_>> {
+    installed
+        .spec
+        .spec
+        .packages
+        .values()
+        .filter(|package| package.name != installed.spec.root)
+        .filter_map(|package| {
+            Some(Dependency {
+                hash: installed.spec.hashes.get(&package.name)?,
+                package,
+            })
+        })
+        .collect()
+}
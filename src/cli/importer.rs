@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+use pyo3::Python;
+
+use crate::{
+    cli::{CliError, load_config},
+    interface,
+    package::spack,
+};
+
+pub fn command() -> Command {
+    Command::new("import")
+        .about("Convert packages from other package managers")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("spack")
+                .about("Convert Spack packages into a package file")
+                .long_about(
+                    "Convert the Spack package.py files in DIR into a single \
+                     package file. DIR may be a Spack repository, its \
+                     'packages' directory or the directory of one package.\n\n\
+                     The conversion is best-effort: versions, variants, \
+                     dependencies, conflicts, requirements, providers and \
+                     licenses are converted, and everything else, such as \
+                     patches, compiler constraints and build instructions, \
+                     is skipped with a warning. Package files are executed \
+                     without Spack, so should be from a trusted source.",
+                )
+                .arg(
+                    Arg::new("dir")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf))
+                        .value_hint(ValueHint::DirPath)
+                        .help("Spack repository or package directory"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("PATH")
+                        .value_parser(value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath)
+                        .help(
+                            "File to write the packages to. Defaults to \
+                             spack_packages.py in the current directory",
+                        ),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .action(ArgAction::SetTrue)
+                        .help("Overwrite the output file if it already exists"),
+                ),
+        )
+}
+
+fn import_spack(matches: &ArgMatches) -> Result<(), CliError> {
+    let dir = matches.get_one::<PathBuf>("dir").unwrap();
+
+    let output = matches
+        .get_one::<PathBuf>("output")
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("spack_packages.py"));
+
+    if output.exists() && !matches.get_flag("force") {
+        return Err(CliError::FileExists(output));
+    }
+
+    let sandbox = load_config(matches)?.sandbox;
+
+    let (packages, mut warnings) =
+        Python::attach(|py| interface::spack::read_repo(py, &sandbox, dir))?;
+
+    let import = spack::convert(&packages);
+    warnings.extend(import.warnings.iter().cloned());
+
+    for warning in &warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    std::fs::write(&output, import.render())?;
+
+    println!(
+        "Imported {} package(s) into {} with {} warning(s)",
+        import.outlines.len(),
+        output.display(),
+        warnings.len()
+    );
+
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("spack", sub)) => import_spack(sub),
+        _ => unreachable!("subcommand required"),
+    }
+}
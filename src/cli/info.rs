@@ -0,0 +1,144 @@
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    cli::{CliError, load_config, load_repos},
+    package::outline::PackageOutline,
+    util::suggest,
+};
+
+pub fn command() -> Command {
+    Command::new("info")
+        .about("Show a package's metadata")
+        .long_about(
+            "Show the metadata of PACKAGE as defined in --repo: its \
+             namespace, license, options and their defaults, the type, \
+             valid values and description of each declared option, possible \
+             dependencies, sources, patches and smoke tests. The \
+             highest-priority repository defining the package is used, \
+             unless the name is qualified with a namespace, such as \
+             'builtin.openmpi'.\n\n\
+             A license forbidden by the 'licenses' configuration option is \
+             marked as such.",
+        )
+        .arg(Arg::new("package").required(true).help("Package to show"))
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the package outline as JSON"),
+        )
+}
+
+fn print_list(label: &str, items: &[String]) {
+    if items.is_empty() {
+        println!("{label:<14}none");
+    } else {
+        println!("{label:<14}{}", items.join(", "));
+    }
+}
+
+fn print_info(outline: &PackageOutline, forbidden: bool) {
+    match &outline.namespace {
+        Some(namespace) => println!("{} ({namespace})", outline.name),
+        None => println!("{}", outline.name),
+    }
+
+    let license = match &outline.license {
+        Some(license) if forbidden => format!("{license} (forbidden)"),
+        Some(license) => license.clone(),
+        None => "unknown".to_string(),
+    };
+
+    println!("{:<14}{license}", "License:");
+
+    if let Some(source) = &outline.version_source {
+        println!("{:<14}{source}", "Versions:");
+    }
+
+    let mut defaults = outline
+        .set_defaults
+        .iter()
+        .map(|(name, value)| {
+            value
+                .as_ref()
+                .map_or_else(|| name.clone(), |value| format!("{name}={value}"))
+        })
+        .collect::<Vec<_>>();
+    defaults.sort();
+
+    print_list("Options:", &defaults);
+
+    for (name, schema) in outline.sorted_schema() {
+        print!("  {name:<12}{}", schema.dtype);
+
+        if !schema.values.is_empty() {
+            let values = schema
+                .values
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            print!(", one of {}", values.join(", "));
+        }
+
+        match &schema.description {
+            Some(description) => println!(": {description}"),
+            None => println!(),
+        }
+    }
+
+    print_list("Dependencies:", &outline.dependencies());
+
+    print_list(
+        "Sources:",
+        &outline
+            .sources
+            .iter()
+            .map(|source| {
+                source.version.as_ref().map_or_else(
+                    || source.url.clone(),
+                    |version| format!("{} (@{version})", source.url),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    println!("{:<14}{}", "Patches:", outline.patches.len());
+
+    print_list(
+        "Tests:",
+        &outline.tests.iter().map(|t| t.name.clone()).collect::<Vec<_>>(),
+    );
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let name = matches.get_one::<String>("package").unwrap();
+
+    let repos = load_repos(matches)?;
+    let (outlines, names) = repos.resolve(std::slice::from_ref(name))?;
+
+    let Some(outline) = outlines.iter().find(|o| o.name == names[0]) else {
+        tracing::error!("no repository defines package '{name}'");
+
+        return Err(CliError::UnknownPackage {
+            name: name.clone(),
+            suggestion: suggest::closest(
+                &names[0],
+                outlines.iter().map(|o| o.name.as_str()),
+            )
+            .map(str::to_string),
+        });
+    };
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(outline)?);
+        return Ok(());
+    }
+
+    let policy = load_config(matches)?.licenses;
+    let forbidden =
+        outline.license.as_ref().is_some_and(|license| !policy.allows(license));
+
+    print_info(outline, forbidden);
+
+    Ok(())
+}
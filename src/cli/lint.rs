@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use anstyle::{AnsiColor, Style};
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+
+use crate::{
+    cli::{CliError, load_outlines, load_repo_outlines, ui},
+    package::lint::{self, Severity},
+};
+
+pub fn command() -> Command {
+    Command::new("lint")
+        .about("Check package definitions for mistakes")
+        .long_about(
+            "Check package definitions for mistakes without solving them.\n\n\
+             Exits with a non-zero status if any errors are found, making it \
+             suitable for use in a repository's CI.",
+        )
+        .arg(
+            Arg::new("file")
+                .help("Package file to lint. Defaults to --repo")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the diagnostics as JSON"),
+        )
+        .arg(
+            Arg::new("deny-warnings")
+                .long("deny-warnings")
+                .action(ArgAction::SetTrue)
+                .help("Treat warnings as errors"),
+        )
+}
+
+const fn severity_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Info => AnsiColor::BrightBlue.on_default().bold(),
+        Severity::Warning => AnsiColor::Yellow.on_default().bold(),
+        Severity::Error => AnsiColor::Red.on_default().bold(),
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let outlines = match matches.get_one::<PathBuf>("file") {
+        Some(path) => load_outlines(matches, path)?,
+        None => load_repo_outlines(matches)?,
+    };
+
+    let diagnostics = lint::lint(&outlines);
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        for diagnostic in &diagnostics {
+            println!(
+                "{}[{}] {}: {}",
+                ui::paint(
+                    severity_style(diagnostic.severity),
+                    diagnostic.severity
+                ),
+                diagnostic.code,
+                diagnostic.package,
+                diagnostic.message
+            );
+        }
+    }
+
+    let threshold = if matches.get_flag("deny-warnings") {
+        Severity::Warning
+    } else {
+        Severity::Error
+    };
+
+    let failures =
+        diagnostics.iter().filter(|d| d.severity >= threshold).count();
+
+    if failures > 0 {
+        return Err(CliError::LintFailed(failures));
+    }
+
+    Ok(())
+}
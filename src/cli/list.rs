@@ -0,0 +1,123 @@
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde_json::json;
+
+use crate::{
+    cli::{CliError, load_repos, ui},
+    package::repo::RepoStack,
+};
+
+pub fn command() -> Command {
+    Command::new("list")
+        .about("List the packages defined in the repositories")
+        .long_about(
+            "List every package visible from --repo and the configured \
+             repositories, with the namespace it is taken from and its \
+             license. A package defined in several repositories is listed \
+             once, from the highest-priority repository. With --shadowed, \
+             the definitions hidden by a higher-priority repository are \
+             listed instead.",
+        )
+        .arg(
+            Arg::new("filter")
+                .help("Only list packages whose name contains FILTER"),
+        )
+        .arg(
+            Arg::new("shadowed")
+                .long("shadowed")
+                .action(ArgAction::SetTrue)
+                .help(
+                    "List the packages hidden by higher-priority repositories",
+                ),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the packages as JSON"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let filter = matches.get_one::<String>("filter");
+    let repos = load_repos(matches)?;
+
+    if matches.get_flag("shadowed") {
+        return shadowed(matches, &repos);
+    }
+
+    let mut outlines = repos
+        .outlines()
+        .into_iter()
+        .filter(|o| filter.is_none_or(|f| o.name.contains(f.as_str())))
+        .collect::<Vec<_>>();
+
+    outlines.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if matches.get_flag("json") {
+        let packages = outlines
+            .iter()
+            .map(|o| {
+                json!({
+                    "name": o.name,
+                    "namespace": o.namespace,
+                    "license": o.license,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        println!("{}", serde_json::to_string_pretty(&packages)?);
+        return Ok(());
+    }
+
+    if outlines.is_empty() {
+        println!("No packages found");
+        return Ok(());
+    }
+
+    let rows = outlines
+        .iter()
+        .map(|o| {
+            [
+                o.name.clone(),
+                o.namespace.clone().unwrap_or_else(|| "-".to_string()),
+                o.license.clone().unwrap_or_else(|| "unknown".to_string()),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    print!("{}", ui::table(["Package", "Namespace", "License"], &rows));
+
+    Ok(())
+}
+
+/// List the definitions `repos` hides, matching the filter.
+fn shadowed(matches: &ArgMatches, repos: &RepoStack) -> Result<(), CliError> {
+    let filter = matches.get_one::<String>("filter");
+
+    let mut shadowed = repos
+        .shadowed()
+        .into_iter()
+        .filter(|s| filter.is_none_or(|f| s.name.contains(f.as_str())))
+        .collect::<Vec<_>>();
+
+    shadowed.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&shadowed)?);
+        return Ok(());
+    }
+
+    if shadowed.is_empty() {
+        println!("No packages are shadowed");
+        return Ok(());
+    }
+
+    let rows = shadowed
+        .into_iter()
+        .map(|s| [s.name, s.namespace, s.by])
+        .collect::<Vec<_>>();
+
+    print!("{}", ui::table(["Package", "Namespace", "Shadowed by"], &rows));
+
+    Ok(())
+}
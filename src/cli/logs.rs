@@ -0,0 +1,215 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+
+use crate::{
+    cli::{CliError, concretize_repo, install_prefix},
+    package::log::{BUILD_OUTPUT_FILE, BuildRecord, shell_quote},
+};
+
+/// How often `--follow` checks the log for new output
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+pub fn command() -> Command {
+    Command::new("logs")
+        .about("Show the log of the last build of a package")
+        .long_about(
+            "Concretize SPEC against --repo and show the output of the last \
+             build of its root package installed to --prefix. The output of \
+             every configure, build and install command is recorded in \
+             .zpack/build-out.txt inside the prefix, preceded by the phase, \
+             working directory and command line.\n\n\
+             The commands and the exact environment each one ran in are \
+             recorded in .zpack/build.json. Use --env to show them as shell \
+             commands which reproduce the build.",
+        )
+        .arg(Arg::new("spec").required(true).help("The package to show"))
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::DirPath)
+                .help(
+                    "Directory the package is installed to. Defaults to its \
+                     prefix in the configured install root",
+                ),
+        )
+        .arg(
+            Arg::new("tail")
+                .long("tail")
+                .short('n')
+                .value_name("N")
+                .value_parser(value_parser!(usize))
+                .help("Only show the last N lines"),
+        )
+        .arg(
+            Arg::new("follow")
+                .long("follow")
+                .short('f')
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["env", "json"])
+                .help("Keep showing output as it is written"),
+        )
+        .arg(
+            Arg::new("env")
+                .long("env")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("json")
+                .help("Show the environment and command line of each command"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the recorded commands as JSON"),
+        )
+}
+
+fn not_found(what: &str, prefix: &Path) -> CliError {
+    CliError::Io(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("no {what} recorded in '{}'", prefix.display()),
+    ))
+}
+
+/// The last `n` lines of `txt`
+fn tail(txt: &str, n: usize) -> &str {
+    if n == 0 {
+        return "";
+    }
+
+    let trimmed = txt.strip_suffix('\n').unwrap_or(txt);
+
+    trimmed
+        .rmatch_indices('\n')
+        .nth(n - 1)
+        .map_or(txt, |(idx, _)| &txt[idx + 1..])
+}
+
+fn print_env(record: &BuildRecord) {
+    for (idx, command) in record.commands.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+
+        let status = command
+            .status
+            .map_or_else(|| "did not exit".to_string(), |s| s.to_string());
+
+        println!(
+            "# [{}] status {status}, {:.2}s",
+            command.phase,
+            Duration::from_millis(command.duration_ms).as_secs_f64()
+        );
+
+        println!("cd {}", shell_quote(&command.cwd.to_string_lossy()));
+
+        for (key, value) in &command.env {
+            println!("export {key}={}", shell_quote(value));
+        }
+
+        println!("{}", command.command_line());
+    }
+}
+
+fn follow(path: &Path, mut offset: u64) -> Result<(), CliError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut stdout = std::io::stdout();
+    let mut buf = Vec::new();
+
+    loop {
+        // A new build truncates the log, so start again from the beginning
+        if file.metadata()?.len() < offset {
+            offset = 0;
+        }
+
+        file.seek(SeekFrom::Start(offset))?;
+        buf.clear();
+        offset += file.read_to_end(&mut buf)? as u64;
+
+        if !buf.is_empty() {
+            stdout.write_all(&buf)?;
+            stdout.flush()?;
+        }
+
+        std::thread::sleep(FOLLOW_INTERVAL);
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let spec = matches.get_one::<String>("spec").unwrap();
+    let concrete = concretize_repo(matches, std::slice::from_ref(spec))?;
+
+    let package =
+        concrete.roots.first().and_then(|root| concrete.get(root)).ok_or_else(
+            || CliError::InvalidSpec(format!("'{spec}' has no root")),
+        )?;
+
+    let prefix = &install_prefix(matches, &concrete, package)?;
+
+    let record =
+        BuildRecord::load(prefix)?.ok_or_else(|| not_found("build", prefix))?;
+
+    if record.package != package.name {
+        tracing::warn!(
+            "'{}' contains a build of {}, not {}",
+            prefix.display(),
+            record.package,
+            package.name
+        );
+    } else if record.hash != package.dag_hash(&concrete) {
+        tracing::warn!(
+            "the last build of {} in '{}' does not match '{spec}'",
+            package.name,
+            prefix.display()
+        );
+    }
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&record)?);
+        return Ok(());
+    }
+
+    if matches.get_flag("env") {
+        print_env(&record);
+        return Ok(());
+    }
+
+    let path = prefix.join(BUILD_OUTPUT_FILE);
+
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(not_found("build output", prefix));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let txt = String::from_utf8_lossy(&bytes);
+
+    let shown = match matches.get_one::<usize>("tail") {
+        Some(&n) => tail(&txt, n),
+        None => &txt,
+    };
+
+    print!("{shown}");
+
+    if matches.get_flag("follow") {
+        std::io::stdout().flush()?;
+        follow(&path, bytes.len() as u64)?;
+    } else if let Some(failed) = record.failure() {
+        eprintln!(
+            "{} failed during {}: {}",
+            record.package,
+            failed.phase,
+            failed.command_line()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::HashSet,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+use serde::Serialize;
+
+use crate::{
+    cli::{CliError, concretize_roots, load_config},
+    fetch::{self, FetchRequest, Fetcher, report::FetchReport},
+    package::patch::PatchSource,
+};
+
+/// A file `zpack mirror create --json` tried to fetch
+#[derive(Clone, Debug, Serialize)]
+struct MirroredFile<'a> {
+    package: &'a str,
+    url: &'a str,
+    path: Option<PathBuf>,
+    error: Option<String>,
+}
+
+/// What `zpack mirror create --json` outputs
+#[derive(Clone, Debug, Serialize)]
+struct MirrorReport<'a> {
+    dir: &'a Path,
+    files: Vec<MirroredFile<'a>>,
+    fetch: FetchReport,
+}
+
+pub fn command() -> Command {
+    Command::new("mirror")
+        .about("Manage source mirrors")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("create")
+                .about("Download the sources needed to install a set of specs")
+                .long_about(
+                    "Concretize the given specs against --repo and download \
+                     every source archive and patch they require into DIR. \
+                     DIR can then be used as a mirror, for example on a \
+                     machine without network access.\n\n\
+                     Finishes with a report of how the downloads went: \
+                     retries, cache hits, bytes fetched and how often each \
+                     mirror failed, to help tune the order of mirrors.",
+                )
+                .arg(
+                    Arg::new("dir")
+                        .required(true)
+                        .help("Directory to create the mirror in")
+                        .value_parser(value_parser!(PathBuf))
+                        .value_hint(ValueHint::DirPath),
+                )
+                .arg(
+                    Arg::new("specs")
+                        .required(true)
+                        .action(ArgAction::Append)
+                        .help("Root specs to mirror the sources of"),
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .help(
+                            "Number of files to download at once. Defaults \
+                             to fetch.jobs from the configuration",
+                        )
+                        .value_parser(value_parser!(NonZeroUsize)),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the files fetched and the report as JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+}
+
+fn create(matches: &ArgMatches) -> Result<(), CliError> {
+    let dir = matches.get_one::<PathBuf>("dir").unwrap();
+    let roots = matches
+        .get_many::<String>("specs")
+        .unwrap()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let specs = concretize_roots(matches, &roots)?;
+
+    let mut config = load_config(matches)?;
+
+    if let Some(jobs) = matches.get_one::<NonZeroUsize>("jobs") {
+        config.fetch.jobs = *jobs;
+    }
+
+    let fetcher = Fetcher::new(&config);
+
+    // Independently concretized specs may share packages
+    let mut seen = HashSet::new();
+    let mut requests = Vec::new();
+
+    for package in specs.iter().flat_map(|spec| spec.packages.values()) {
+        let urls =
+            package.source.iter().map(|s| (s.url.as_str(), &s.checksum)).chain(
+                package.patches.iter().filter_map(|p| match &p.source {
+                    PatchSource::Url(url) => Some((url.as_str(), &p.checksum)),
+                    PatchSource::File(_) => None,
+                }),
+            );
+
+        for (url, checksum) in urls {
+            let dest = dir.join(fetch::mirror_path(package.base_name(), url));
+
+            if seen.insert(dest.clone()) {
+                requests.push(FetchRequest {
+                    package: &package.name,
+                    url,
+                    checksum: Some(checksum),
+                    dest,
+                });
+            }
+        }
+    }
+
+    let results = fetcher.fetch_many(&requests)?;
+    let report = fetcher.report();
+
+    if matches.get_flag("json") {
+        let files = requests
+            .iter()
+            .zip(&results)
+            .map(|(request, res)| MirroredFile {
+                package: request.package,
+                url: request.url,
+                path: res.as_ref().ok().cloned(),
+                error: res.as_ref().err().map(ToString::to_string),
+            })
+            .collect();
+
+        let out = MirrorReport { dir, files, fetch: report };
+        println!("{}", serde_json::to_string_pretty(&out)?);
+    } else {
+        for (request, res) in requests.iter().zip(&results) {
+            if let Ok(path) = res {
+                println!("{}: {}", request.package, path.display());
+            }
+        }
+
+        print!("\n{report}");
+    }
+
+    // Every file was attempted, so report the first failure
+    results.into_iter().try_for_each(|res| res.map(drop))?;
+
+    if !matches.get_flag("json") {
+        println!("Mirrored {} file(s) to {}", seen.len(), dir.display());
+    }
+
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("create", sub)) => create(sub),
+        _ => unreachable!("subcommand required"),
+    }
+}
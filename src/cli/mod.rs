@@ -1,25 +1,357 @@
-#[derive(Debug, Clone, Copy)]
-pub enum CliError {
-    Idfk,
-}
+mod audit;
+mod cache;
+mod clean;
+mod complete;
+mod config;
+mod create;
+mod diff;
+mod doctor;
+mod edit;
+mod export;
+mod find;
+mod importer;
+mod info;
+mod lint;
+mod list;
+mod logs;
+mod mirror;
+mod plugin;
+mod sbom;
+mod serve;
+mod solve;
+mod stubs;
+mod test;
+#[cfg(feature = "tui")]
+mod tui;
+mod ui;
+mod why;
 
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anstyle::AnsiColor;
 use clap::{
-    Arg, ArgAction, Command, ValueHint, builder::styling::Styles,
+    Arg, ArgAction, ArgMatches, Command, ValueHint, builder::styling::Styles,
     crate_description, crate_version, value_parser,
 };
 use clap_complete::{
     Generator,
     aot::{Shell, generate},
 };
-use pyo3::prelude::*;
 
-use crate::package::outline::{PackageOutline, SpecOutline};
+use crate::{
+    config::{Config, ConfigError},
+    fetch::{FetchError, Fetcher},
+    hooks::{HookConfig, HookError, HookPayload},
+    interface::{
+        cache::OutlineCache,
+        plugins,
+        reader::{self, ReadError},
+    },
+    package::{
+        install,
+        license::LicensePolicy,
+        outline::{PackageOutline, SolverError, SpecOutline},
+        policy::PolicyConstraint,
+        provider::{self, VersionCache},
+        repo::{RepoError, RepoStack, Repository},
+        resolver::{
+            AutoResolver, Conflict, Resolution, Resolver, ResolverKind,
+            SatResolver, Z3Resolver,
+        },
+        stats::SolveStats,
+    },
+    spec::{
+        ConcreteSpec,
+        concrete::ConcretePackage,
+        document::DocumentError,
+        parse::{SpecParseError, SpecRequest},
+        platform::{Platform, PlatformKey},
+    },
+    util::{suggest, timings},
+};
+
+#[derive(Debug)]
+pub enum CliError {
+    Read(ReadError),
+    Solver(Box<SolverError>),
+    Unsatisfiable(Vec<Conflict>),
+
+    /// The solver gave up, for the reason given
+    UnknownSolverResult(String),
+    MissingRepository,
+
+    /// No repository defines the package
+    UnknownPackage {
+        name: String,
+        suggestion: Option<String>,
+    },
+
+    /// The file would be overwritten
+    FileExists(PathBuf),
+
+    /// The editor could not be run or exited unsuccessfully
+    Editor(String),
+    InvalidSpec(String),
+    LintFailed(usize),
+    AuditFailed(usize),
+
+    /// The number of smoke tests which failed
+    TestsFailed(usize),
+
+    /// The number of problems found by `zpack doctor`
+    DoctorFailed(usize),
+
+    /// The number of type stubs which are missing or out of date
+    StaleStubs(usize),
+    Repo(RepoError),
+    Config(ConfigError),
+    Fetch(FetchError),
+    Hook(HookError),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Document(DocumentError),
+}
+
+impl From<ReadError> for CliError {
+    fn from(value: ReadError) -> Self {
+        Self::Read(value)
+    }
+}
+
+impl From<Box<SolverError>> for CliError {
+    fn from(value: Box<SolverError>) -> Self {
+        Self::Solver(value)
+    }
+}
+
+impl From<RepoError> for CliError {
+    fn from(value: RepoError) -> Self {
+        Self::Repo(value)
+    }
+}
+
+impl From<ConfigError> for CliError {
+    fn from(value: ConfigError) -> Self {
+        Self::Config(value)
+    }
+}
+
+impl From<FetchError> for CliError {
+    fn from(value: FetchError) -> Self {
+        Self::Fetch(value)
+    }
+}
+
+impl From<HookError> for CliError {
+    fn from(value: HookError) -> Self {
+        Self::Hook(value)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<DocumentError> for CliError {
+    fn from(value: DocumentError) -> Self {
+        Self::Document(value)
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "failed to read package file: {e:?}"),
+            Self::Solver(e) => write!(f, "solver error: {e}"),
+            Self::Unsatisfiable(core) => {
+                writeln!(f, "Conflicting Constraints:")?;
+                core.iter().try_for_each(|c| writeln!(f, "- {c}"))
+            }
+            Self::UnknownSolverResult(reason) => write!(
+                f,
+                "the solver could not determine satisfiability: {reason}"
+            ),
+            Self::MissingRepository => {
+                f.write_str("no package repository given; pass one with --repo")
+            }
+            Self::UnknownPackage { name, suggestion } => write!(
+                f,
+                "no repository defines package '{name}'{}",
+                suggest::did_you_mean(suggestion.as_deref())
+            ),
+            Self::FileExists(path) => write!(
+                f,
+                "'{}' already exists; pass --force to overwrite it",
+                path.display()
+            ),
+            Self::Editor(e) => write!(f, "editor failed: {e}"),
+            Self::InvalidSpec(report) => f.write_str(report),
+            Self::LintFailed(n) => write!(f, "lint failed with {n} problem(s)"),
+            Self::AuditFailed(n) => {
+                write!(f, "{n} package(s) failed to concretize")
+            }
+            Self::TestsFailed(n) => write!(f, "{n} test(s) failed"),
+            Self::DoctorFailed(n) => write!(
+                f,
+                "{n} problem(s) found; run 'zpack doctor --repair' to fix them"
+            ),
+            Self::StaleStubs(n) => write!(
+                f,
+                "{n} stub(s) are out of date; run 'zpack stubs' to regenerate \
+                 them"
+            ),
+            Self::Repo(e) => write!(f, "{e}"),
+            Self::Config(e) => write!(f, "{e}"),
+            Self::Fetch(e) => write!(f, "{e}"),
+            Self::Hook(e) => write!(f, "{e}"),
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Json(e) => write!(f, "invalid json: {e}"),
+            Self::Document(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Arguments accepted by every subcommand
+fn global_args() -> [Arg; 15] {
+    [
+        Arg::new("repo")
+            .short('r')
+            .long("repo")
+            .value_name("[NAMESPACE=]PATH")
+            .help(
+                "Python file containing package definitions. May be given \
+                 more than once; earlier repositories take priority",
+            )
+            .global(true)
+            .action(ArgAction::Append)
+            .value_parser(|s: &str| {
+                s.parse::<Repository>().map_err(|e| e.to_string())
+            })
+            .value_hint(ValueHint::FilePath),
+        Arg::new("config")
+            .long("config")
+            .help("Configuration file to use instead of the default")
+            .global(true)
+            .value_parser(value_parser!(PathBuf))
+            .value_hint(ValueHint::FilePath),
+        Arg::new("no-sandbox")
+            .long("no-sandbox")
+            .help("Execute package files without any restrictions")
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("no-plugins")
+            .long("no-plugins")
+            .help("Do not load plugins from Python entry points")
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("no-cache")
+            .long("no-cache")
+            .help(
+                "Always re-extract package outlines from package files and \
+                 re-list versions from remote indexes",
+            )
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("unify")
+            .long("unify")
+            .value_name("BOOL")
+            .help(
+                "Concretize root specs together (true) or independently \
+                 (false). Defaults to the 'unify' configuration option",
+            )
+            .global(true)
+            .value_parser(value_parser!(bool)),
+        Arg::new("platform")
+            .long("platform")
+            .value_name("KEY=VALUE")
+            .help(
+                "Override a fact about the platform to concretize for, \
+                 such as os=linux or arch=aarch64. May be given more \
+                 than once",
+            )
+            .global(true)
+            .action(ArgAction::Append)
+            .value_parser(|s: &str| {
+                Platform::parse_fact(s).map_err(|e| e.to_string())
+            }),
+        Arg::new("offline")
+            .long("offline")
+            .help("Fail instead of accessing the network")
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("require-signed")
+            .long("require-signed")
+            .help(
+                "Reject fetched resources which are not signed by a signer \
+                 trusted for their mirror",
+            )
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("deterministic")
+            .long("deterministic")
+            .help(
+                "Fix the solver's random seeds so identical inputs always \
+                 produce identical concretizations",
+            )
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("permissive")
+            .long("permissive")
+            .help(
+                "Allow specs and packages to refer to options no package \
+                 declares, creating a new option for each, rather than \
+                 failing with a suggestion",
+            )
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("resolver")
+            .long("resolver")
+            .value_name("BACKEND")
+            .help(
+                "Solver backend to concretize with: auto, sat or z3. \
+                 Defaults to the 'resolver' configuration option",
+            )
+            .global(true)
+            .value_parser(|s: &str| s.parse::<ResolverKind>()),
+        Arg::new("solver-stats")
+            .long("solver-stats")
+            .help(
+                "Print the time spent in each phase of concretization and \
+                 the statistics reported by the solver",
+            )
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("timings")
+            .long("timings")
+            .help("Print the time spent in each stage of the command")
+            .global(true)
+            .action(ArgAction::SetTrue),
+        Arg::new("color")
+            .long("color")
+            .value_name("WHEN")
+            .help(
+                "Colour output: auto, always or never. With auto, output is \
+                 coloured if it is a terminal and NO_COLOR is not set",
+            )
+            .global(true)
+            .value_parser(|s: &str| s.parse::<ui::ColorChoice>()),
+    ]
+}
 
 fn build_cli() -> Command {
-    Command::new("zpack")
+    let cmd = Command::new("zpack")
         .long_version(format!("{}\n{}", crate_version!(), crate_description!()))
         .arg(
             Arg::new("test")
@@ -28,6 +360,7 @@ fn build_cli() -> Command {
                 .value_parser(value_parser!(PathBuf))
                 .value_hint(ValueHint::FilePath),
         )
+        .args(global_args())
         .subcommand(
             Command::new("print").about("Print something").arg(
                 Arg::new("file")
@@ -36,6 +369,30 @@ fn build_cli() -> Command {
                     .value_hint(ValueHint::ExecutablePath),
             ),
         )
+        .subcommand(audit::command())
+        .subcommand(cache::command())
+        .subcommand(clean::command())
+        .subcommand(complete::command())
+        .subcommand(config::command())
+        .subcommand(create::command())
+        .subcommand(diff::command())
+        .subcommand(doctor::command())
+        .subcommand(edit::command())
+        .subcommand(export::command())
+        .subcommand(find::command())
+        .subcommand(importer::command())
+        .subcommand(info::command())
+        .subcommand(lint::command())
+        .subcommand(list::command())
+        .subcommand(logs::command())
+        .subcommand(mirror::command())
+        .subcommand(plugin::command())
+        .subcommand(sbom::command())
+        .subcommand(serve::command())
+        .subcommand(solve::command())
+        .subcommand(stubs::command())
+        .subcommand(test::command())
+        .subcommand(why::command())
         .arg(
             Arg::new("generator")
                 .long("generate")
@@ -49,7 +406,12 @@ fn build_cli() -> Command {
                 .literal(AnsiColor::BrightCyan.on_default().bold())
                 .placeholder(AnsiColor::BrightCyan.on_default())
                 .context(AnsiColor::Yellow.on_default()),
-        )
+        );
+
+    #[cfg(feature = "tui")]
+    let cmd = cmd.subcommand(tui::command());
+
+    cmd
 }
 
 fn print_completions<G: Generator>(generator: G, cmd: &mut Command) {
@@ -61,91 +423,614 @@ fn print_completions<G: Generator>(generator: G, cmd: &mut Command) {
     );
 }
 
-/// # Panics
-/// Because I haven't finished this yet
-fn parse<I, T>(args: I)
+/// Load every package outline defined in a Python package file.
+///
+/// See [`load_many_outlines`].
+///
+/// # Errors
+/// Errors if the file cannot be read, does not define valid packages or
+/// violates the sandbox.
+pub(crate) fn load_outlines(
+    matches: &ArgMatches,
+    path: &Path,
+) -> Result<Vec<PackageOutline>, CliError> {
+    let mut res = load_many_outlines(matches, &[path])?;
+    Ok(res.pop().unwrap_or_default())
+}
+
+/// Load every package outline defined in each Python package file in `paths`.
+///
+/// Files are executed in the configured sandbox unless `--no-sandbox` is
+/// given. Outlines are cached between runs unless `--no-cache` is given. Every
+/// file which is not cached is read in parallel and extracted in a single
+/// Python attachment (see [`reader::extract_outlines`]).
+///
+/// The versions of packages with a version source are then discovered (see
+/// [`provider`]). Listed versions are cached for a day, or queried every time
+/// with `--no-cache`.
+///
+/// # Errors
+/// Errors if a file cannot be read, does not define valid packages or
+/// violates the sandbox.
+pub(crate) fn load_many_outlines(
+    matches: &ArgMatches,
+    paths: &[&Path],
+) -> Result<Vec<Vec<PackageOutline>>, CliError> {
+    let config = load_config(matches)?;
+    let fetcher = Fetcher::new(&config);
+    let mut sandbox = config.sandbox;
+
+    if matches.get_flag("no-sandbox") {
+        sandbox.enabled = false;
+    }
+
+    let extract = |paths: &[&Path]| {
+        timings::time(timings::PYTHON_EXTRACTION, || {
+            reader::extract_outlines(&sandbox, paths)
+        })
+    };
+
+    let no_cache = matches.get_flag("no-cache");
+
+    let loaded = if config.cache_outlines && !no_cache {
+        OutlineCache::default().get_or_load_many(paths, extract)
+    } else {
+        extract(paths)
+    };
+
+    let mut versions = VersionCache::default();
+
+    if no_cache {
+        versions = versions.with_ttl(Duration::ZERO);
+    }
+
+    loaded
+        .into_iter()
+        .map(|outlines| {
+            let mut outlines = outlines?;
+            provider::discover_versions(&mut outlines, &versions, &fetcher);
+            Ok(outlines)
+        })
+        .collect()
+}
+
+/// The repositories from the `--repo` arguments, followed by those in the
+/// configuration, in priority order.
+///
+/// # Errors
+/// Errors if no repository was given or the configuration is invalid.
+pub(crate) fn repositories(
+    matches: &ArgMatches,
+) -> Result<Vec<Repository>, CliError> {
+    let repos = matches
+        .get_many::<Repository>("repo")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .chain(load_config(matches)?.repos)
+        .collect::<Vec<_>>();
+
+    if repos.is_empty() {
+        return Err(CliError::MissingRepository);
+    }
+
+    Ok(repos)
+}
+
+/// Load every repository from [`repositories`].
+///
+/// # Errors
+/// Errors if no repository was given or one cannot be loaded.
+pub(crate) fn load_repos(matches: &ArgMatches) -> Result<RepoStack, CliError> {
+    let mut stack = RepoStack::new();
+
+    let repos = repositories(matches)?;
+    let paths = repos.iter().map(|r| r.path.as_path()).collect::<Vec<_>>();
+
+    let loaded = timings::time(timings::REPO_LOAD, || {
+        load_many_outlines(matches, &paths)
+    })?;
+
+    for (repo, outlines) in repos.iter().zip(loaded) {
+        stack.push(&repo.namespace, outlines)?;
+    }
+
+    Ok(stack)
+}
+
+/// Load the outlines visible from the repositories given by `--repo`, with
+/// higher-priority repositories shadowing lower-priority ones.
+///
+/// # Errors
+/// Errors if no repository was given or one cannot be loaded.
+pub(crate) fn load_repo_outlines(
+    matches: &ArgMatches,
+) -> Result<Vec<PackageOutline>, CliError> {
+    Ok(load_repos(matches)?.outlines())
+}
+
+/// Load the configuration, applying any overrides given on the command line.
+///
+/// # Errors
+/// Errors if the configuration is invalid.
+pub(crate) fn load_config(matches: &ArgMatches) -> Result<Config, CliError> {
+    let mut config = Config::load(
+        matches.get_one::<PathBuf>("config").map(PathBuf::as_path),
+    )?;
+
+    if matches.get_flag("offline") {
+        config.offline = true;
+    }
+
+    if matches.get_flag("require-signed") {
+        config.signatures.require_signed = true;
+    }
+
+    if matches.get_flag("deterministic") {
+        config.deterministic = true;
+    }
+
+    if matches.get_flag("permissive") {
+        config.permissive = true;
+    }
+
+    if matches.get_flag("no-plugins") {
+        config.plugins.enabled = false;
+    }
+
+    if let Some(resolver) = matches.get_one::<ResolverKind>("resolver") {
+        config.resolver = *resolver;
+    }
+
+    for (key, value) in matches
+        .get_many::<(PlatformKey, String)>("platform")
+        .into_iter()
+        .flatten()
+    {
+        config.platform.set(*key, value.clone());
+    }
+
+    Ok(config)
+}
+
+/// The prefix `package` of `spec` is installed to: `--prefix` if given, and
+/// otherwise its prefix inside the configured install root.
+///
+/// # Errors
+/// Errors if the configuration is invalid or the prefix does not exist.
+pub(crate) fn install_prefix(
+    matches: &ArgMatches,
+    spec: &ConcreteSpec,
+    package: &ConcretePackage,
+) -> Result<PathBuf, CliError> {
+    let prefix = match matches.get_one::<PathBuf>("prefix") {
+        Some(prefix) => prefix.clone(),
+        None => install::prefix(
+            &load_config(matches)?.install_tree.padded_root(),
+            package,
+            &package.dag_hash(spec),
+        ),
+    };
+
+    if !prefix.is_dir() {
+        return Err(CliError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("install prefix '{}' does not exist", prefix.display()),
+        )));
+    }
+
+    Ok(prefix)
+}
+
+/// Options controlling how specs are concretized
+#[derive(Clone, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct SolveOptions {
+    /// The platform to concretize for
+    pub platform: Platform,
+
+    /// Fix the solver's random seeds
+    pub deterministic: bool,
+
+    /// Allow references to options no package declares
+    pub permissive: bool,
+
+    /// The solver backend to concretize with
+    pub resolver: ResolverKind,
+
+    /// Print a profile of each solver phase to stderr
+    pub stats: bool,
+
+    /// Write the solver to this file in SMT-LIB2 format before checking it
+    pub dump_smt: Option<PathBuf>,
+
+    /// Print a proof of any conflict to stderr
+    pub explain_proof: bool,
+
+    /// The `pre_solve` and `post_solve` hooks to run
+    pub hooks: HookConfig,
+
+    /// Site-wide constraints added to every solve, including those of the
+    /// package settings
+    pub policies: Vec<PolicyConstraint>,
+
+    /// The options fixed by the package settings, by package
+    pub configured: HashMap<String, HashSet<String>>,
+
+    /// Licenses which may not be used
+    pub licenses: LicensePolicy,
+}
+
+impl SolveOptions {
+    /// Read the solve options from the configuration and command line.
+    ///
+    /// # Errors
+    /// Errors if the configuration is invalid.
+    pub fn load(matches: &ArgMatches) -> Result<Self, CliError> {
+        let config = load_config(matches)?;
+
+        let mut policies = config
+            .policies
+            .iter()
+            .map(|policy| {
+                policy.parse().map_err(|error| ConfigError::Policy {
+                    name: policy.name.clone(),
+                    error,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut configured = HashMap::new();
+
+        for (name, settings) in &config.packages {
+            let package = settings.parse(name).map_err(|error| {
+                ConfigError::Package { name: name.clone(), error }
+            })?;
+
+            policies.extend(package.policies);
+            configured.insert(name.clone(), package.options);
+        }
+
+        Ok(Self {
+            platform: config.platform,
+            deterministic: config.deterministic,
+            permissive: config.permissive,
+            resolver: config.resolver,
+            stats: matches.get_flag("solver-stats"),
+            dump_smt: matches
+                .try_get_one::<PathBuf>("dump-smt")
+                .ok()
+                .flatten()
+                .cloned(),
+            explain_proof: matches
+                .try_get_one::<bool>("explain-proof")
+                .ok()
+                .flatten()
+                .copied()
+                .unwrap_or_default(),
+            hooks: config.hooks,
+            policies,
+            configured,
+            licenses: config.licenses,
+        })
+    }
+
+    /// These options with the SMT-LIB dump, if any, numbered with `idx`, so
+    /// specs concretized separately do not overwrite each other's dumps.
+    fn numbered(&self, idx: usize) -> Self {
+        let dump_smt = self.dump_smt.as_ref().map(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+            let mut name = format!("{stem}-{idx}");
+
+            if let Some(ext) = path.extension() {
+                name.push('.');
+                name.push_str(&ext.to_string_lossy());
+            }
+
+            path.with_file_name(name)
+        });
+
+        Self { dump_smt, ..self.clone() }
+    }
+
+    /// The resolver selected by these options. Dumping the solver requires
+    /// z3, so `auto` always uses z3 when an SMT-LIB dump is requested.
+    fn resolver(&self) -> Box<dyn Resolver> {
+        let z3 = Z3Resolver { dump_smt: self.dump_smt.clone() };
+
+        match self.resolver {
+            ResolverKind::Auto if self.dump_smt.is_none() => {
+                Box::new(AutoResolver { sat: SatResolver, z3 })
+            }
+            ResolverKind::Auto | ResolverKind::Z3 => Box::new(z3),
+            ResolverKind::Sat => Box::new(SatResolver),
+        }
+    }
+}
+
+/// Render a spec error, with the offending parts of the spec underlined.
+fn spec_error(err: SpecParseError<'_>) -> CliError {
+    CliError::InvalidSpec(
+        err.build()
+            .map(|e| e.to_string().unwrap_or_else(|e| e))
+            .unwrap_or_default(),
+    )
+}
+
+/// Select the outlines for parsed specs from a set of repositories and apply
+/// the specs to them, returning the outlines and the root package names.
+///
+/// # Errors
+/// Errors if a spec does not match its package.
+pub(crate) fn apply_requests(
+    repos: &RepoStack,
+    requests: &[SpecRequest],
+) -> Result<(Vec<PackageOutline>, Vec<String>), CliError> {
+    let names = requests.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+    let (mut outlines, roots) = repos.resolve(&names)?;
+
+    for (request, name) in requests.iter().zip(&roots) {
+        request.validate(name, &outlines).map_err(spec_error)?;
+    }
+
+    for (request, name) in requests.iter().zip(&roots) {
+        request.apply(name, &mut outlines);
+    }
+
+    Ok((outlines, roots))
+}
+
+/// Concretize parsed specs together against a set of repositories.
+///
+/// # Errors
+/// Errors if a spec does not match its package or the specs cannot be
+/// satisfied.
+pub(crate) fn concretize_requests(
+    repos: &RepoStack,
+    requests: &[SpecRequest],
+    options: &SolveOptions,
+) -> Result<ConcreteSpec, CliError> {
+    let (outlines, roots) = apply_requests(repos, requests)?;
+
+    concretize(outlines, &roots, options)
+}
+
+/// Parse spec strings (see [`crate::spec::parse`]).
+///
+/// # Errors
+/// Errors if a spec is invalid.
+pub(crate) fn parse_specs(
+    specs: &[String],
+) -> Result<Vec<SpecRequest>, CliError> {
+    specs
+        .iter()
+        .map(|spec| SpecRequest::parse(spec))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(spec_error)
+}
+
+/// Concretize spec strings (see [`crate::spec::parse`]) together against the
+/// repositories given by `--repo`. Package names may be qualified with a
+/// namespace.
+///
+/// # Errors
+/// Errors if a spec is invalid, the repositories cannot be loaded or the specs
+/// cannot be satisfied.
+pub(crate) fn concretize_repo(
+    matches: &ArgMatches,
+    specs: &[String],
+) -> Result<ConcreteSpec, CliError> {
+    let requests = parse_specs(specs)?;
+    let options = SolveOptions::load(matches)?;
+
+    concretize_requests(&load_repos(matches)?, &requests, &options)
+}
+
+/// Concretize several root specs, either together or independently depending
+/// on `--unify` and the `unify` configuration option.
+///
+/// When unified, a single concrete spec is returned in which every package
+/// has one configuration shared by all roots. Otherwise, each root is solved
+/// on its own and may, for example, use a different version of a common
+/// dependency.
+///
+/// # Errors
+/// Errors if a spec is invalid, the repositories cannot be loaded or any
+/// root cannot be satisfied.
+pub(crate) fn concretize_roots(
+    matches: &ArgMatches,
+    specs: &[String],
+) -> Result<Vec<ConcreteSpec>, CliError> {
+    let unify = match matches.get_one::<bool>("unify") {
+        Some(unify) => *unify,
+        None => load_config(matches)?.unify,
+    };
+
+    let options = SolveOptions::load(matches)?;
+    let requests = parse_specs(specs)?;
+    let repos = load_repos(matches)?;
+
+    if unify {
+        Ok(vec![concretize_requests(&repos, &requests, &options)?])
+    } else {
+        requests
+            .iter()
+            .enumerate()
+            .map(|(idx, request)| {
+                tracing::info!("concretizing '{request}' separately");
+                concretize_requests(
+                    &repos,
+                    std::slice::from_ref(request),
+                    &options.numbered(idx),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Concretize `roots` against the given outlines.
+///
+/// # Errors
+/// Errors if the outlines are invalid or the roots cannot be satisfied.
+pub(crate) fn concretize(
+    outlines: Vec<PackageOutline>,
+    roots: &[String],
+    options: &SolveOptions,
+) -> Result<ConcreteSpec, CliError> {
+    options.hooks.run(&HookPayload::pre_solve(roots))?;
+
+    let mut stats = SolveStats::new();
+    let res = concretize_profiled(outlines, roots, options, &mut stats);
+
+    timings::record_solve(&stats);
+
+    if options.stats {
+        eprintln!("solver profile for {}:\n{stats}", roots.join(", "));
+    }
+
+    let spec = res?;
+    options.licenses.check(&spec);
+    options.hooks.run(&HookPayload::post_solve(&spec))?;
+
+    Ok(spec)
+}
+
+/// Concretize `roots` against the given outlines, recording the time spent
+/// in each phase and the solver's statistics in `stats`.
+///
+/// # Errors
+/// Errors if the outlines are invalid, the roots cannot be satisfied or the
+/// solver cannot be dumped.
+pub(crate) fn concretize_profiled(
+    outlines: Vec<PackageOutline>,
+    roots: &[String],
+    options: &SolveOptions,
+    stats: &mut SolveStats,
+) -> Result<ConcreteSpec, CliError> {
+    let licenses = options.licenses.constraints(&outlines);
+
+    let mut outline = SpecOutline::new(outlines)?;
+    outline.required.extend(roots.iter().cloned());
+    outline.platform = options.platform.clone();
+    outline.deterministic = options.deterministic;
+    outline.permissive = options.permissive;
+    outline.policies.clone_from(&options.policies);
+    outline.policies.extend(licenses);
+    outline.configured.clone_from(&options.configured);
+
+    stats.time("default propagation", || outline.propagate_defaults())?;
+
+    let resolver = options.resolver();
+    tracing::info!("concretizing with the {} resolver", resolver.name());
+
+    match resolver.resolve(&outline, stats)? {
+        Resolution::Sat(spec) => Ok(spec),
+        Resolution::Unsat(core) => {
+            if options.explain_proof {
+                match outline.explain_proof()? {
+                    Some(proof) => eprint!("proof of the conflict:\n{proof}"),
+                    None => eprintln!("z3 could not prove the conflict"),
+                }
+            }
+
+            Err(CliError::Unsatisfiable(core))
+        }
+        Resolution::Unknown(reason) => {
+            Err(CliError::UnknownSolverResult(reason))
+        }
+    }
+}
+
+fn parse<I, T>(args: I) -> Result<(), CliError>
 where
     I: IntoIterator<Item = T>,
     T: Into<std::ffi::OsString> + Clone,
 {
     let matches = build_cli().get_matches_from(args);
 
-    if let Some(path) = matches.get_one::<PathBuf>("test") {
-        println!("Testing {}", path.display());
-
-        Python::attach(|py| {
-            let packages =
-                crate::interface::reader::process_file(py, path).unwrap();
-            let mut outlines = Vec::new();
+    ui::set_color(
+        matches
+            .get_one::<ui::ColorChoice>("color")
+            .copied()
+            .unwrap_or(ui::ColorChoice::Auto),
+    );
 
-            for package in packages {
-                let outline: PackageOutline =
-                    crate::interface::reader::read_from_class0(
-                        package, "outline",
-                    )
-                    .unwrap();
+    if matches.get_flag("timings") {
+        timings::enable();
+    }
 
-                println!("{outline:?}");
-                outlines.push(outline);
-            }
+    let res = dispatch(&matches);
 
-            let mut outline = SpecOutline::new(outlines).unwrap();
-            outline.required.push("hpl".to_string());
+    if let Some(timings) = timings::take() {
+        eprint!("{timings}");
+    }
 
-            outline.propagate_defaults().unwrap();
+    res
+}
 
-            let (optimizer, registry) = outline.gen_spec_solver().unwrap();
+fn dispatch(matches: &ArgMatches) -> Result<(), CliError> {
+    // Plugins may provide fetchers and constraint factories used while
+    // loading package files, so must be loaded first
+    plugins::load(&load_config(matches)?.plugins);
 
-            println!("\n\n");
+    if let Some(path) = matches.get_one::<PathBuf>("test") {
+        println!("Testing {}", path.display());
 
-            println!("Optimizer: {optimizer}");
-            println!("Registry: {registry:#?}");
+        let outlines = load_outlines(matches, path)?;
 
-            println!("\n\n");
+        for outline in &outlines {
+            println!("{outline:?}");
+        }
 
-            match optimizer.check(&[]) {
-                z3::SatResult::Unsat => {
-                    tracing::info!("unsat");
+        let options = SolveOptions::load(matches)?;
 
-                    println!("Conflicting Constraints:");
-                    for lit in optimizer.get_unsat_core() {
-                        println!(
-                            "- {}",
-                            registry
-                                .constraint_description(&lit)
-                                .cloned()
-                                .unwrap_or_else(|| lit.to_string())
-                        );
-                    }
-                }
-                z3::SatResult::Unknown => {
-                    tracing::info!("unknown");
-                    todo!();
-                }
-                z3::SatResult::Sat => {
-                    tracing::info!("sat");
-
-                    let model = optimizer.get_model().unwrap();
-                    for &(package, option) in registry.spec_option_names() {
-                        println!(
-                            "{}:{:?} -> {:?}",
-                            package,
-                            option,
-                            registry.eval_option(
-                                package, option, &model, &registry
-                            )
-                        );
-                    }
-                }
+        match concretize(outlines, &["hpl".to_string()], &options) {
+            Ok(concrete) => print!("{concrete}"),
+            Err(CliError::Unsatisfiable(core)) => {
+                print!("{}", CliError::Unsatisfiable(core));
             }
-        });
+            Err(e) => return Err(e),
+        }
     } else if let Some(generator) =
         matches.get_one::<Shell>("generator").copied()
     {
         let mut cmd = build_cli();
         eprintln!("Generating completion file for {generator}...");
         print_completions(generator, &mut cmd);
+    } else {
+        match matches.subcommand() {
+            Some(("audit", sub)) => audit::run(sub)?,
+            Some(("cache", sub)) => cache::run(sub)?,
+            Some(("clean", sub)) => clean::run(sub)?,
+            Some(("complete", sub)) => complete::run(sub)?,
+            Some(("config", sub)) => config::run(sub)?,
+            Some(("create", sub)) => create::run(sub)?,
+            Some(("diff", sub)) => diff::run(sub)?,
+            Some(("doctor", sub)) => doctor::run(sub)?,
+            Some(("edit", sub)) => edit::run(sub)?,
+            Some(("export", sub)) => export::run(sub)?,
+            Some(("find", sub)) => find::run(sub)?,
+            Some(("import", sub)) => importer::run(sub)?,
+            Some(("info", sub)) => info::run(sub)?,
+            Some(("lint", sub)) => lint::run(sub)?,
+            Some(("list", sub)) => list::run(sub)?,
+            Some(("logs", sub)) => logs::run(sub)?,
+            Some(("mirror", sub)) => mirror::run(sub)?,
+            Some(("plugin", sub)) => plugin::run(sub)?,
+            Some(("sbom", sub)) => sbom::run(sub)?,
+            Some(("serve", sub)) => serve::run(sub)?,
+            Some(("solve", sub)) => solve::run(sub)?,
+            Some(("stubs", sub)) => stubs::run(sub)?,
+            Some(("test", sub)) => test::run(sub)?,
+            #[cfg(feature = "tui")]
+            Some(("tui", sub)) => tui::run(sub)?,
+            Some(("why", sub)) => why::run(sub)?,
+            _ => (),
+        }
     }
+
+    Ok(())
 }
 
 /// Main entrypoint into zpack.
@@ -155,7 +1040,5 @@ where
 /// or another, returned here.
 pub fn entry(is_python: bool) -> Result<(), CliError> {
     let args = std::env::args().skip(usize::from(is_python));
-    parse(args);
-
-    Ok(())
+    parse(args)
 }
@@ -0,0 +1,81 @@
+use anstyle::AnsiColor;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    cli::{CliError, ui},
+    interface::plugins::{self, PluginStatus},
+};
+
+pub fn command() -> Command {
+    Command::new("plugin")
+        .about("Inspect plugins")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("list")
+                .about("List installed plugins")
+                .long_about(
+                    "List every plugin declared in the 'zpack.plugins' entry \
+                     point group, whether it was loaded and the hooks it \
+                     registered.",
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the plugins as JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+}
+
+fn list(matches: &ArgMatches) -> Result<(), CliError> {
+    let plugins = plugins::loaded();
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(plugins)?);
+        return Ok(());
+    }
+
+    if plugins.is_empty() {
+        println!("No plugins installed");
+        return Ok(());
+    }
+
+    for plugin in plugins {
+        match &plugin.status {
+            PluginStatus::Loaded => {
+                println!("{} {plugin}", ui::status(AnsiColor::Green, "loaded"));
+
+                for (kind, name) in plugin.hooks() {
+                    println!("    - {kind} '{name}'");
+                }
+            }
+            PluginStatus::Disabled => {
+                println!(
+                    "{} {plugin}",
+                    ui::status(AnsiColor::Yellow, "disabled")
+                );
+            }
+            PluginStatus::Incompatible { requires } => {
+                println!(
+                    "{} {plugin}: requires zpack {requires}",
+                    ui::status(AnsiColor::Red, "incompatible"),
+                );
+            }
+            PluginStatus::Failed { error } => {
+                println!(
+                    "{} {plugin}: {error}",
+                    ui::status(AnsiColor::Red, "failed")
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("list", sub)) => list(sub),
+        _ => unreachable!("subcommand required"),
+    }
+}
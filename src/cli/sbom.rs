@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+
+use crate::{
+    cli::{CliError, concretize_repo},
+    spec::sbom::{self, SbomFormat},
+};
+
+pub fn command() -> Command {
+    Command::new("sbom")
+        .about("Export a software bill of materials for concretized specs")
+        .long_about(
+            "Concretize one or more specs together against --repo and write \
+             a software bill of materials (SBOM) of every package in the \
+             result, as CycloneDX 1.5 or SPDX 2.3 JSON.\n\n\
+             Each package is reported with its version, license, the \
+             checksum and URL of its source archive, its hash and the \
+             packages it depends on. Packages without a license are reported \
+             as NOASSERTION in SPDX and without one in CycloneDX.",
+        )
+        .arg(
+            Arg::new("specs")
+                .required(true)
+                .action(ArgAction::Append)
+                .help("Root specs to concretize"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value(SbomFormat::CycloneDx.name())
+                .value_parser(SbomFormat::ALL.map(SbomFormat::name))
+                .help("The SBOM format to write"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath)
+                .help("File to write the SBOM to. Defaults to standard output"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let specs = matches
+        .get_many::<String>("specs")
+        .unwrap()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let format = matches
+        .get_one::<String>("format")
+        .unwrap()
+        .parse::<SbomFormat>()
+        .map_err(CliError::InvalidSpec)?;
+
+    let concrete = concretize_repo(matches, &specs)?;
+
+    let json = sbom::to_json(&concrete, format)?;
+
+    match matches.get_one::<PathBuf>("output") {
+        Some(path) => {
+            std::fs::write(path, json + "\n")?;
+            println!("Wrote {format} SBOM to '{}'", path.display());
+        }
+        None => println!("{json}"),
+    }
+
+    Ok(())
+}
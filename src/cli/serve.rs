@@ -0,0 +1,391 @@
+//! `zpack serve`: a JSON-RPC server for tools driving zpack.
+//!
+//! The server listens on a Unix socket and speaks
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification), one request or
+//! response per line. Each connection is handled on its own thread, so
+//! several clients can solve at once. Repositories are loaded when the server
+//! starts and again on `reload`.
+//!
+//! Methods:
+//!
+//! - `version()`: `{"protocol": PROTOCOL_VERSION, "zpack": "x.y.z"}`
+//! - `packages()`: every package, as `[{"name", "namespace"}]`
+//! - `info({"package"})`: the package's outline
+//! - `solve({"specs"})`: the specs concretized together, as a concrete spec
+//! - `reload()`: reload the repositories, returning the number of packages
+//!
+//! Failures are reported with the standard JSON-RPC error codes, or with
+//! [`UNSATISFIABLE`] (with the conflicting constraints as `data`),
+//! [`UNKNOWN_PACKAGE`] or [`FAILED`]. [`PROTOCOL_VERSION`] is incremented
+//! whenever a method or result changes incompatibly.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use clap::{Arg, ArgMatches, Command, ValueHint, value_parser};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    cli::{
+        CliError, SolveOptions, concretize_requests, load_repos, parse_specs,
+    },
+    package::repo::RepoStack,
+    util::{paths, suggest},
+};
+
+/// The version of the protocol, returned by the `version` method
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The request is not valid JSON
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// The request was valid but could not be completed
+pub const FAILED: i64 = -32000;
+
+/// The specs cannot be satisfied
+pub const UNSATISFIABLE: i64 = -32001;
+
+/// No repository defines the package
+pub const UNKNOWN_PACKAGE: i64 = -32002;
+
+pub fn command() -> Command {
+    Command::new("serve")
+        .about("Serve solve and query requests over a local socket")
+        .long_about(
+            "Listen on a Unix socket for JSON-RPC 2.0 requests, one per line, \
+             so editors, web interfaces and other tools can drive zpack \
+             without running it for every request. Connections are handled \
+             concurrently.\n\n\
+             The methods are 'version', 'packages', 'info' (with a \
+             'package' parameter), 'solve' (with a 'specs' parameter) and \
+             'reload'. Repositories are loaded from --repo and the \
+             configuration when the server starts and on 'reload'.",
+        )
+        .arg(
+            Arg::new("socket")
+                .long("socket")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath)
+                .help(
+                    "Socket to listen on. Defaults to zpack.sock in \
+                     $XDG_RUNTIME_DIR/zpack, or $ZPACK_SOCKET if set",
+                ),
+        )
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+
+    /// Absent for notifications, which are not answered
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct InfoParams {
+    package: String,
+}
+
+#[derive(Deserialize)]
+struct SolveParams {
+    specs: Vec<String>,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+}
+
+impl From<CliError> for RpcError {
+    fn from(value: CliError) -> Self {
+        match value {
+            CliError::Unsatisfiable(core) => Self {
+                code: UNSATISFIABLE,
+                message: "the specs cannot be satisfied".into(),
+                data: Some(json!(
+                    core.iter().map(ToString::to_string).collect::<Vec<_>>()
+                )),
+            },
+            e @ CliError::UnknownPackage { .. } => {
+                Self::new(UNKNOWN_PACKAGE, e.to_string())
+            }
+            e => Self::new(FAILED, e.to_string()),
+        }
+    }
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value(value: &impl serde::Serialize) -> Result<Value, RpcError> {
+    serde_json::to_value(value)
+        .map_err(|e| RpcError::new(FAILED, e.to_string()))
+}
+
+struct Server {
+    matches: ArgMatches,
+    options: SolveOptions,
+    repos: RwLock<RepoStack>,
+}
+
+impl Server {
+    fn repos(&self) -> std::sync::RwLockReadGuard<'_, RepoStack> {
+        // A panic while reloading leaves the previous repositories intact
+        self.repos.read().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "version" => Ok(json!({
+                "protocol": PROTOCOL_VERSION,
+                "zpack": env!("CARGO_PKG_VERSION"),
+            })),
+            "packages" => self.packages(),
+            "info" => self.info(&self::params::<InfoParams>(params)?.package),
+            "solve" => self.solve(&self::params::<SolveParams>(params)?.specs),
+            "reload" => self.reload(),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method '{method}'"),
+            )),
+        }
+    }
+
+    fn packages(&self) -> Result<Value, RpcError> {
+        let repos = self.repos();
+        let (outlines, _) = repos.resolve(&[]).map_err(CliError::from)?;
+
+        let mut packages = outlines
+            .iter()
+            .map(|o| json!({ "name": o.name, "namespace": o.namespace }))
+            .collect::<Vec<_>>();
+
+        packages.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+        Ok(Value::Array(packages))
+    }
+
+    fn info(&self, name: &str) -> Result<Value, RpcError> {
+        let repos = self.repos();
+        let (outlines, names) = repos
+            .resolve(std::slice::from_ref(&name.to_string()))
+            .map_err(CliError::from)?;
+
+        let outline =
+            outlines.iter().find(|o| o.name == names[0]).ok_or_else(|| {
+                CliError::UnknownPackage {
+                    name: name.to_string(),
+                    suggestion: suggest::closest(
+                        &names[0],
+                        outlines.iter().map(|o| o.name.as_str()),
+                    )
+                    .map(str::to_string),
+                }
+            })?;
+
+        to_value(outline)
+    }
+
+    fn solve(&self, specs: &[String]) -> Result<Value, RpcError> {
+        if specs.is_empty() {
+            return Err(RpcError::new(INVALID_PARAMS, "no specs given"));
+        }
+
+        let requests = parse_specs(specs)?;
+        let spec =
+            concretize_requests(&self.repos(), &requests, &self.options)?;
+
+        to_value(&spec)
+    }
+
+    fn reload(&self) -> Result<Value, RpcError> {
+        let repos = load_repos(&self.matches)?;
+        let count = repos.outlines().len();
+
+        *self
+            .repos
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = repos;
+
+        tracing::info!("reloaded {count} packages");
+
+        Ok(json!(count))
+    }
+
+    /// Answer a single line of input. Returns `None` for notifications.
+    fn handle(&self, line: &str) -> Option<Value> {
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(value) => value,
+            Err(e) => {
+                return Some(response(
+                    Value::Null,
+                    Err(RpcError::new(PARSE_ERROR, e.to_string())),
+                ));
+            }
+        };
+
+        let request = match serde_json::from_value::<Request>(request) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            Ok(_) => {
+                return Some(response(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, "expected jsonrpc 2.0")),
+                ));
+            }
+            Err(e) => {
+                return Some(response(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, e.to_string())),
+                ));
+            }
+        };
+
+        tracing::info!("handling '{}'", request.method);
+
+        let res = self.call(&request.method, request.params);
+
+        if let Err(e) = &res {
+            tracing::warn!("'{}' failed: {}", request.method, e.message);
+        }
+
+        request.id.map(|id| response(id, res))
+    }
+}
+
+fn response(id: Value, res: Result<Value, RpcError>) -> Value {
+    match res {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => {
+            let mut error = json!({ "code": e.code, "message": e.message });
+
+            if let Some(data) = e.data {
+                error["data"] = data;
+            }
+
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serve(
+    server: &Arc<Server>,
+    socket: &std::path::Path,
+) -> Result<(), CliError> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if socket.exists() {
+        // A socket nothing is listening on is left over from a server which
+        // did not shut down cleanly
+        if UnixStream::connect(socket).is_ok() {
+            return Err(CliError::Io(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!(
+                    "a server is already listening on '{}'",
+                    socket.display()
+                ),
+            )));
+        }
+
+        std::fs::remove_file(socket)?;
+    }
+
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let listener = UnixListener::bind(socket)?;
+    println!("Listening on {}", socket.display());
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!("failed to accept a connection: {e}");
+                continue;
+            }
+        };
+
+        let server = Arc::clone(server);
+
+        std::thread::spawn(move || {
+            tracing::info!("client connected");
+
+            if let Err(e) = handle_connection(&server, stream) {
+                tracing::warn!("connection closed: {e}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve(_: &Arc<Server>, _: &std::path::Path) -> Result<(), CliError> {
+    Err(CliError::Io(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "zpack serve requires Unix sockets",
+    )))
+}
+
+/// Answer requests on `stream` until the client disconnects.
+#[cfg(unix)]
+fn handle_connection(
+    server: &Server,
+    stream: std::os::unix::net::UnixStream,
+) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(response) = server.handle(&line) {
+            writeln!(writer, "{response}")?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let socket = matches
+        .get_one::<PathBuf>("socket")
+        .cloned()
+        .unwrap_or_else(paths::socket_file);
+
+    let server = Arc::new(Server {
+        matches: matches.clone(),
+        options: SolveOptions::load(matches)?,
+        repos: RwLock::new(load_repos(matches)?),
+    });
+
+    serve(&server, &socket)
+}
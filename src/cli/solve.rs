@@ -0,0 +1,178 @@
+use std::{io::Write, path::PathBuf};
+
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
+
+use crate::{
+    cli::{CliError, concretize_roots, ui},
+    spec::{
+        ConcreteSpec,
+        concrete::VERSION_OPTION,
+        document::{DocumentFormat, SolveDocument},
+    },
+    util::suggest,
+};
+
+pub fn command() -> Command {
+    Command::new("solve")
+        .about("Concretize specs and print the result")
+        .long_about(
+            "Concretize one or more specs against --repo and print the \
+             resulting concrete spec. With --unify=false, each spec is \
+             concretized independently and printed separately.\n\n\
+             Each spec is a package name, optionally qualified with a \
+             repository namespace, followed by a version and options, such \
+             as 'hpl@2.3 +static blas_impl=openblas'.",
+        )
+        .arg(
+            Arg::new("specs")
+                .required(true)
+                .action(ArgAction::Append)
+                .help("Root specs to concretize"),
+        )
+        .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).help(
+            "Output the concrete specs as a JSON array, even if there is \
+             only one. See also --format",
+        ))
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(DocumentFormat::ALL.map(DocumentFormat::name))
+                .conflicts_with("json")
+                .help(
+                    "Output the concrete specs in a stable, versioned schema \
+                     intended for other tools and language bindings. msgpack \
+                     is written to standard output as binary",
+                ),
+        )
+        .arg(
+            Arg::new("dump-smt")
+                .long("dump-smt")
+                .value_name("PATH")
+                .value_parser(value_parser!(PathBuf))
+                .help(
+                    "Write the solver to PATH in SMT-LIB2 format, for \
+                     replaying with z3. With --unify=false, the index of \
+                     each spec is appended to the file name",
+                ),
+        )
+        .arg(
+            Arg::new("explain-proof")
+                .long("explain-proof")
+                .action(ArgAction::SetTrue)
+                .help(
+                    "If the specs conflict, also print z3's proof of the \
+                     conflict, with each step described in terms of packages \
+                     and constraints. Proofs of deep conflicts can be long",
+                ),
+        )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .value_name("PACKAGE:OPTION")
+                .action(ArgAction::Append)
+                .value_parser(|s: &str| {
+                    s.split_once(':')
+                        .filter(|(p, o)| !p.is_empty() && !o.is_empty())
+                        .map(|(p, o)| (p.to_string(), o.to_string()))
+                        .ok_or_else(|| {
+                            format!("expected PACKAGE:OPTION, found '{s}'")
+                        })
+                })
+                .help(
+                    "Explain why an option, such as 'hpl:version', has its \
+                     value: requested, set by the package, an inherited \
+                     default or chosen by the solver. May be given more than \
+                     once",
+                ),
+        )
+}
+
+/// Print why `package`'s value of `option` is what it is, in every spec
+/// containing the package.
+fn explain(
+    specs: &[ConcreteSpec],
+    package: &str,
+    option: &str,
+) -> Result<(), CliError> {
+    let packages =
+        specs.iter().filter_map(|spec| spec.get(package)).collect::<Vec<_>>();
+
+    if packages.is_empty() {
+        return Err(CliError::InvalidSpec(format!(
+            "'{package}' is not in the concrete spec{}",
+            suggest::did_you_mean(suggest::closest(
+                package,
+                specs
+                    .iter()
+                    .flat_map(|s| s.packages.keys())
+                    .map(String::as_str)
+            ))
+        )));
+    }
+
+    for concrete in packages {
+        let Some((value, why)) = concrete.explain(option) else {
+            return Err(CliError::InvalidSpec(format!(
+                "'{package}' has no option '{option}'{}",
+                suggest::did_you_mean(suggest::closest(
+                    option,
+                    concrete.options.keys().map(String::as_str).chain(
+                        concrete.version.as_ref().map(|_| VERSION_OPTION)
+                    )
+                ))
+            )));
+        };
+
+        match why {
+            Some(why) => println!("{package}:{option} = {value}: {why}"),
+            None => println!("{package}:{option} = {value}"),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let specs = matches
+        .get_many::<String>("specs")
+        .unwrap()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let concrete = concretize_roots(matches, &specs)?;
+
+    if let Some(queries) = matches.get_many::<(String, String)>("explain") {
+        for (package, option) in queries {
+            explain(&concrete, package, option)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(format) = matches.get_one::<String>("format") {
+        let format =
+            format.parse::<DocumentFormat>().map_err(CliError::InvalidSpec)?;
+
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&SolveDocument::new(&concrete).to_bytes(format)?)?;
+
+        if format == DocumentFormat::Json {
+            writeln!(stdout)?;
+        }
+
+        stdout.flush()?;
+    } else if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&concrete)?);
+    } else {
+        for (i, spec) in concrete.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+
+            print!("{}", ui::tree(spec));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! `zpack stubs`: regenerate the Python type stubs (see
+//! [`crate::interface::stubs`]).
+
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+use pyo3::Python;
+
+use crate::{
+    cli::CliError,
+    interface::stubs::{self, STUBS_PACKAGE},
+};
+
+pub fn command() -> Command {
+    Command::new("stubs")
+        .about("Generate type stubs for the zpack Python module")
+        .long_about(
+            "Introspect the zpack Python module and write a type stub (.pyi) \
+             for it and each of its submodules to DIR, a stub-only package \
+             which is shipped in the wheel so editors can complete package \
+             files. Run this after changing the Python API.\n\n\
+             With --check, nothing is written; the command fails if any stub \
+             in DIR is missing or out of date.",
+        )
+        .arg(
+            Arg::new("dir")
+                .value_name("DIR")
+                .default_value(STUBS_PACKAGE)
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::DirPath)
+                .help("Directory to write the stubs to"),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .action(ArgAction::SetTrue)
+                .help("Check the stubs are up to date instead of writing them"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let dir = matches.get_one::<PathBuf>("dir").expect("dir has a default");
+    let check = matches.get_flag("check");
+
+    let stubs = Python::attach(stubs::generate)?;
+
+    if check {
+        let stale = stubs
+            .iter()
+            .map(|stub| dir.join(&stub.path))
+            .zip(&stubs)
+            .filter(|(path, stub)| {
+                std::fs::read_to_string(path).ok().as_deref()
+                    != Some(stub.text.as_str())
+            })
+            .map(|(path, stub)| {
+                println!("{} ({}) is out of date", path.display(), stub.module);
+                path
+            })
+            .collect::<Vec<_>>();
+
+        return if stale.is_empty() {
+            println!("{} stub(s) are up to date", stubs.len());
+            Ok(())
+        } else {
+            Err(CliError::StaleStubs(stale.len()))
+        };
+    }
+
+    std::fs::create_dir_all(dir)?;
+
+    for stub in &stubs {
+        let path = dir.join(&stub.path);
+        std::fs::write(&path, &stub.text)?;
+        println!("Wrote {} ({})", path.display(), stub.module);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use anstyle::AnsiColor;
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint, value_parser};
+
+use crate::{
+    cli::{CliError, concretize_repo, install_prefix, ui},
+    package::test::{self, TestReport},
+};
+
+pub fn command() -> Command {
+    Command::new("test")
+        .about("Run the smoke tests of an installed package")
+        .long_about(
+            "Concretize SPEC against --repo and run the smoke tests which \
+             apply to its root package. Each test is run from --prefix in the \
+             runtime environment of the package, with the prefix's bin and \
+             library directories added to the search paths.\n\n\
+             The results are recorded in .zpack/tests.json inside the prefix, \
+             replacing those of any earlier run. Exits with a non-zero status \
+             if any test fails.",
+        )
+        .arg(Arg::new("spec").required(true).help("The package to test"))
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::DirPath)
+                .help(
+                    "Directory the package is installed to. Defaults to its \
+                     prefix in the configured install root",
+                ),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the results as JSON"),
+        )
+}
+
+fn print_report(report: &TestReport) {
+    for result in &report.results {
+        if result.passed {
+            println!(
+                "{} {} ({:.2}s)",
+                ui::status(AnsiColor::Green, "pass"),
+                result.name,
+                result.duration
+            );
+        } else {
+            println!(
+                "{} {} ({:.2}s)",
+                ui::status(AnsiColor::Red, "fail"),
+                result.name,
+                result.duration
+            );
+
+            for line in result.output.lines() {
+                println!("    {line}");
+            }
+        }
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let spec = matches.get_one::<String>("spec").unwrap();
+    let concrete = concretize_repo(matches, std::slice::from_ref(spec))?;
+
+    let package =
+        concrete.roots.first().and_then(|root| concrete.get(root)).ok_or_else(
+            || CliError::InvalidSpec(format!("'{spec}' has no root")),
+        )?;
+
+    let prefix = &install_prefix(matches, &concrete, package)?;
+
+    let report = test::run_tests(package, package.dag_hash(&concrete), prefix);
+    let path = report.record(prefix)?;
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if report.results.is_empty() {
+        println!("{} declares no smoke tests", package.name);
+    } else {
+        print_report(&report);
+        println!("Results recorded in {}", path.display());
+    }
+
+    match report.failures() {
+        0 => Ok(()),
+        n => Err(CliError::TestsFailed(n)),
+    }
+}
@@ -0,0 +1,554 @@
+//! `zpack tui`: browse a concretization and re-solve it interactively.
+//!
+//! The left panel shows the dependency tree of the concrete spec, and the
+//! right panel the options of the selected package with the reason each has
+//! its value, and the description of each declared option. Toggling a boolean option pins it for that package, as if it
+//! had been requested, and concretizes again in the background. The status
+//! bar shows whether the solver is running and how long the last solve took.
+//! If the specs conflict, the conflicting constraints are listed with the
+//! descriptions of the options they refer to.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, mpsc},
+    time::{Duration, Instant},
+};
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+};
+
+use crate::{
+    cli::{
+        CliError, SolveOptions, apply_requests, concretize_profiled,
+        load_repos, parse_specs,
+    },
+    package::{repo::RepoStack, resolver::Conflict, stats::SolveStats},
+    spec::{
+        ConcreteSpec, SpecOptionValue, concrete::VERSION_OPTION,
+        parse::SpecRequest,
+    },
+};
+
+/// How often the screen is redrawn while waiting for input
+const TICK: Duration = Duration::from_millis(100);
+
+pub fn command() -> Command {
+    Command::new("tui")
+        .about("Browse and re-solve concretizations interactively")
+        .long_about(
+            "Concretize specs against --repo and show the result in an \
+             interactive terminal interface: the dependency tree, the \
+             options of the selected package and why each has its value, \
+             and the status of the solver.\n\n\
+             Select an option and press space to toggle it. The option is \
+             pinned for that package and the specs are concretized again. \
+             Press r to clear every pinned option, tab to switch panels and \
+             q to quit.",
+        )
+        .arg(
+            Arg::new("specs")
+                .required(true)
+                .action(ArgAction::Append)
+                .help("Root specs to concretize"),
+        )
+}
+
+/// Options pinned by the user, by package and option name
+type Overrides = BTreeMap<(String, String), SpecOptionValue>;
+
+/// Descriptions of declared options, by package and option name
+type Descriptions = HashMap<(String, String), String>;
+
+enum Status {
+    Solving(Instant),
+    Solved(Duration),
+    Unsatisfiable(Vec<Conflict>),
+    Failed(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Tree,
+    Options,
+}
+
+/// A row of the dependency tree
+struct Node {
+    depth: usize,
+    name: String,
+
+    /// The package was already expanded higher up the tree
+    repeated: bool,
+}
+
+/// The result of a solve, tagged with the generation it was started in
+type Outcome = (u64, Duration, Result<ConcreteSpec, Status>);
+
+struct App {
+    repos: Arc<RepoStack>,
+    requests: Arc<Vec<SpecRequest>>,
+    options: Arc<SolveOptions>,
+    descriptions: Descriptions,
+
+    overrides: Overrides,
+    spec: Option<ConcreteSpec>,
+    tree: Vec<Node>,
+    status: Status,
+
+    /// Incremented for every solve, so stale results are discarded
+    generation: u64,
+    results: mpsc::Sender<Outcome>,
+
+    focus: Focus,
+    tree_state: ListState,
+    option_state: ListState,
+}
+
+/// The description of every declared option of a package in `repos`.
+fn descriptions(repos: &RepoStack) -> Descriptions {
+    repos
+        .outlines()
+        .iter()
+        .flat_map(|outline| {
+            outline.sorted_schema().filter_map(|(option, schema)| {
+                let description = schema.description.clone()?;
+                Some(((outline.name.clone(), option.clone()), description))
+            })
+        })
+        .collect()
+}
+
+fn solve(
+    repos: &RepoStack,
+    requests: &[SpecRequest],
+    options: &SolveOptions,
+    overrides: &Overrides,
+) -> Result<ConcreteSpec, CliError> {
+    let (mut outlines, roots) = apply_requests(repos, requests)?;
+
+    for ((package, option), value) in overrides {
+        if let Some(outline) = outlines.iter_mut().find(|o| o.name == *package)
+        {
+            outline.set_options.insert(option.clone(), value.clone());
+            outline.requested_options.insert(option.clone());
+        }
+    }
+
+    concretize_profiled(outlines, &roots, options, &mut SolveStats::new())
+}
+
+/// The dependency tree of `spec`, with each package expanded only once.
+fn tree(spec: &ConcreteSpec) -> Vec<Node> {
+    fn visit(
+        spec: &ConcreteSpec,
+        name: &str,
+        depth: usize,
+        seen: &mut HashSet<String>,
+        nodes: &mut Vec<Node>,
+    ) {
+        let repeated = !seen.insert(name.to_string());
+        nodes.push(Node { depth, name: name.to_string(), repeated });
+
+        if repeated {
+            return;
+        }
+
+        if let Some(package) = spec.get(name) {
+            for dep in &package.dependencies {
+                visit(spec, dep, depth + 1, seen, nodes);
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut nodes = Vec::new();
+
+    for root in &spec.roots {
+        visit(spec, root, 0, &mut seen, &mut nodes);
+    }
+
+    nodes
+}
+
+impl App {
+    /// Concretize again with the current overrides, in the background.
+    fn resolve(&mut self) {
+        self.generation += 1;
+        self.status = Status::Solving(Instant::now());
+
+        let generation = self.generation;
+        let repos = Arc::clone(&self.repos);
+        let requests = Arc::clone(&self.requests);
+        let options = Arc::clone(&self.options);
+        let overrides = self.overrides.clone();
+        let results = self.results.clone();
+
+        std::thread::spawn(move || {
+            let start = Instant::now();
+
+            let res =
+                solve(&repos, &requests, &options, &overrides).map_err(|e| {
+                    match e {
+                        CliError::Unsatisfiable(core) => {
+                            Status::Unsatisfiable(core)
+                        }
+                        e => Status::Failed(e.to_string()),
+                    }
+                });
+
+            // The interface may have exited while solving
+            let _ = results.send((generation, start.elapsed(), res));
+        });
+    }
+
+    fn receive(&mut self, (generation, elapsed, res): Outcome) {
+        if generation != self.generation {
+            return;
+        }
+
+        match res {
+            Ok(spec) => {
+                self.tree = tree(&spec);
+                self.spec = Some(spec);
+                self.status = Status::Solved(elapsed);
+
+                let selected = self.tree_state.selected().unwrap_or_default();
+                self.tree_state.select(Some(
+                    selected.min(self.tree.len().saturating_sub(1)),
+                ));
+            }
+            Err(status) => self.status = status,
+        }
+    }
+
+    /// The selected package's options, including its version, in order.
+    fn options(&self) -> Vec<(String, SpecOptionValue)> {
+        let Some(package) = self
+            .tree_state
+            .selected()
+            .and_then(|idx| self.tree.get(idx))
+            .and_then(|node| self.spec.as_ref()?.get(&node.name))
+        else {
+            return Vec::new();
+        };
+
+        package
+            .version
+            .iter()
+            .map(|v| {
+                (
+                    VERSION_OPTION.to_string(),
+                    SpecOptionValue::Version(v.clone()),
+                )
+            })
+            .chain(package.options.iter().map(|(n, v)| (n.clone(), v.clone())))
+            .collect()
+    }
+
+    /// Toggle the selected option, if it is a boolean.
+    fn toggle(&mut self) {
+        let Some(node) =
+            self.tree_state.selected().and_then(|idx| self.tree.get(idx))
+        else {
+            return;
+        };
+
+        let Some(package) =
+            self.spec.as_ref().and_then(|spec| spec.get(&node.name))
+        else {
+            return;
+        };
+
+        let options = self.options();
+
+        let Some((option, SpecOptionValue::Bool(value))) =
+            self.option_state.selected().and_then(|idx| options.get(idx))
+        else {
+            return;
+        };
+
+        self.overrides.insert(
+            (package.base_name().to_string(), option.clone()),
+            SpecOptionValue::Bool(!value),
+        );
+
+        self.resolve();
+    }
+
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        let state = match self.focus {
+            Focus::Tree => &mut self.tree_state,
+            Focus::Options => &mut self.option_state,
+        };
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                self.focus = match self.focus {
+                    Focus::Tree => Focus::Options,
+                    Focus::Options => Focus::Tree,
+                };
+
+                if self.option_state.selected().is_none() {
+                    self.option_state.select_first();
+                }
+            }
+            KeyCode::Char(' ') | KeyCode::Enter
+                if self.focus == Focus::Options =>
+            {
+                self.toggle();
+            }
+            KeyCode::Char('r') if !self.overrides.is_empty() => {
+                self.overrides.clear();
+                self.resolve();
+            }
+            _ => {}
+        }
+
+        if self.focus == Focus::Tree && code != KeyCode::Tab {
+            self.option_state.select_first();
+        }
+
+        true
+    }
+
+    fn block(&self, title: &str, focus: Focus) -> Block<'static> {
+        let block = Block::bordered().title(format!(" {title} "));
+
+        if self.focus == focus {
+            block.border_style(Style::new().fg(Color::Cyan))
+        } else {
+            block
+        }
+    }
+
+    fn draw_tree(&mut self, frame: &mut Frame, area: Rect) {
+        let items = self
+            .tree
+            .iter()
+            .map(|node| {
+                let label = self
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.get(&node.name))
+                    .and_then(|p| p.version.as_ref())
+                    .map_or_else(
+                        || node.name.clone(),
+                        |version| format!("{}@{version}", node.name),
+                    );
+
+                let mut line = vec![Span::raw("  ".repeat(node.depth))];
+
+                if node.repeated {
+                    line.push(Span::raw(label).dim());
+                } else {
+                    line.push(Span::raw(label));
+                }
+
+                ListItem::new(Line::from(line))
+            })
+            .collect::<Vec<_>>();
+
+        let list = List::new(items)
+            .block(self.block("Packages", Focus::Tree))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(list, area, &mut self.tree_state);
+    }
+
+    fn draw_options(&mut self, frame: &mut Frame, area: Rect) {
+        let block = self.block("Options", Focus::Options);
+
+        if let Status::Unsatisfiable(core) = &self.status {
+            let mut lines = vec![Line::from("Conflicting constraints:")];
+
+            for c in core {
+                lines.push(Line::from(format!("- {c}")));
+
+                let mut described = self
+                    .descriptions
+                    .iter()
+                    .filter(|((package, option), _)| {
+                        c.description
+                            .contains(&format!("option({package}:{option})"))
+                    })
+                    .collect::<Vec<_>>();
+                described.sort();
+
+                for ((package, option), description) in described {
+                    lines.push(
+                        Line::from(format!(
+                            "    {package}:{option}: {description}"
+                        ))
+                        .dim(),
+                    );
+                }
+            }
+
+            frame.render_widget(Paragraph::new(lines).block(block), area);
+            return;
+        }
+
+        let package = self
+            .tree_state
+            .selected()
+            .and_then(|idx| self.tree.get(idx))
+            .and_then(|node| self.spec.as_ref()?.get(&node.name));
+
+        let items = self
+            .options()
+            .into_iter()
+            .map(|(name, value)| {
+                let pinned = package.is_some_and(|p| {
+                    self.overrides.contains_key(&(
+                        p.base_name().to_string(),
+                        name.clone(),
+                    ))
+                });
+
+                let why = package
+                    .and_then(|p| p.provenance.get(&name))
+                    .map(ToString::to_string)
+                    .unwrap_or_default();
+
+                let mut line = vec![
+                    Span::raw(format!("{name} = {value}")).bold(),
+                    Span::raw(format!("  {why}")).dim(),
+                ];
+
+                if let Some(description) = package.and_then(|p| {
+                    self.descriptions
+                        .get(&(p.base_name().to_string(), name.clone()))
+                }) {
+                    line.push(Span::raw(format!("  {description}")).italic());
+                }
+
+                if pinned {
+                    line.insert(0, Span::raw("* ").fg(Color::Yellow));
+                }
+
+                ListItem::new(Line::from(line))
+            })
+            .collect::<Vec<_>>();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(list, area, &mut self.option_state);
+    }
+
+    fn draw_status(&self, frame: &mut Frame, area: Rect) {
+        let status = match &self.status {
+            Status::Solving(start) => Span::raw(format!(
+                "solving... {:.1}s",
+                start.elapsed().as_secs_f64()
+            ))
+            .fg(Color::Yellow),
+            Status::Solved(elapsed) => {
+                Span::raw(format!("solved in {:.2}s", elapsed.as_secs_f64()))
+                    .fg(Color::Green)
+            }
+            Status::Unsatisfiable(_) => {
+                Span::raw("unsatisfiable").fg(Color::Red)
+            }
+            Status::Failed(e) => {
+                Span::raw(format!("failed: {e}")).fg(Color::Red)
+            }
+        };
+
+        let line = Line::from(vec![
+            status,
+            Span::raw(format!(
+                " | {} pinned | space: toggle  r: reset  tab: switch  q: quit",
+                self.overrides.len()
+            ))
+            .dim(),
+        ]);
+
+        frame.render_widget(Paragraph::new(line), area);
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+                .areas(frame.area());
+
+        let [tree_area, options_area] = Layout::horizontal([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ])
+        .areas(main);
+
+        self.draw_tree(frame, tree_area);
+        self.draw_options(frame, options_area);
+        self.draw_status(frame, status);
+    }
+
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        results: &mpsc::Receiver<Outcome>,
+    ) -> std::io::Result<()> {
+        loop {
+            while let Ok(outcome) = results.try_recv() {
+                self.receive(outcome);
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(TICK)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key.code)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let specs = matches
+        .get_many::<String>("specs")
+        .unwrap()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let (sender, receiver) = mpsc::channel();
+
+    let repos = load_repos(matches)?;
+
+    let mut app = App {
+        descriptions: descriptions(&repos),
+        repos: Arc::new(repos),
+        requests: Arc::new(parse_specs(&specs)?),
+        options: Arc::new(SolveOptions::load(matches)?),
+        overrides: Overrides::new(),
+        spec: None,
+        tree: Vec::new(),
+        status: Status::Solving(Instant::now()),
+        generation: 0,
+        results: sender,
+        focus: Focus::Tree,
+        tree_state: ListState::default().with_selected(Some(0)),
+        option_state: ListState::default(),
+    };
+
+    app.resolve();
+
+    let mut terminal = ratatui::init();
+    let res = app.run(&mut terminal, &receiver);
+    ratatui::restore();
+
+    Ok(res?)
+}
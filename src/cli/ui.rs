@@ -0,0 +1,206 @@
+//! Rendering of command output: colour, trees of concrete specs and tables.
+//!
+//! Whether output is coloured is decided once per run by `--color`. With
+//! `auto`, the default, output is coloured if standard output is a terminal
+//! and the `NO_COLOR` environment variable is unset or empty (see
+//! <https://no-color.org>).
+
+use std::{collections::HashSet, io::IsTerminal, sync::OnceLock};
+
+use anstyle::{AnsiColor, Style};
+
+use crate::spec::{ConcretePackage, ConcreteSpec};
+
+/// When to colour output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub const ALL: [Self; 3] = [Self::Auto, Self::Always, Self::Never];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+
+    /// Whether output should be coloured under this choice
+    fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|c| c.name() == s).ok_or_else(|| {
+            format!(
+                "unknown color choice '{s}', expected auto, always or never"
+            )
+        })
+    }
+}
+
+static COLOR: OnceLock<bool> = OnceLock::new();
+
+/// Decide whether output is coloured. Only the first call has any effect.
+pub fn set_color(choice: ColorChoice) {
+    let _ = COLOR.set(choice.enabled());
+}
+
+/// Whether output is coloured
+pub fn color() -> bool {
+    *COLOR.get_or_init(|| ColorChoice::Auto.enabled())
+}
+
+/// `txt` in `style`, if output is coloured.
+pub fn paint(style: Style, txt: impl std::fmt::Display) -> String {
+    if color() {
+        format!("{}{txt}{}", style.render(), style.render_reset())
+    } else {
+        txt.to_string()
+    }
+}
+
+/// A status label, such as `ok` or `failed`, in bold `ansi`.
+pub fn status(ansi: AnsiColor, txt: &str) -> String {
+    paint(ansi.on_default().bold(), txt)
+}
+
+/// A line describing `package`, indented by `depth` and followed by
+/// `suffix`, such as its install prefix
+pub fn package_line(
+    spec: &ConcreteSpec,
+    package: &ConcretePackage,
+    depth: usize,
+    suffix: Option<&str>,
+) -> String {
+    let hash = &package.dag_hash(spec)[..7];
+    let txt = package.to_string();
+    let rest = txt.strip_prefix(package.name.as_str()).unwrap_or_default();
+
+    let indent = if depth == 0 {
+        String::new()
+    } else {
+        format!("{}^", "    ".repeat(depth))
+    };
+
+    let suffix = suffix.map_or_else(String::new, |suffix| {
+        format!("  {}", paint(Style::new().dimmed(), suffix))
+    });
+
+    format!(
+        "{}  {indent}{}{rest}{suffix}",
+        paint(Style::new().dimmed(), format!("[{hash}]")),
+        paint(AnsiColor::BrightCyan.on_default().bold(), &package.name),
+    )
+}
+
+/// Render `spec` as a tree, like `spack spec`. Each root is followed by its
+/// dependencies, indented by depth and prefixed with `^`. A package is only
+/// shown the first time it appears.
+#[must_use]
+pub fn tree(spec: &ConcreteSpec) -> String {
+    tree_with(spec, |_| None)
+}
+
+/// [`tree`], with each package followed by its `suffix`.
+#[must_use]
+pub fn tree_with(
+    spec: &ConcreteSpec,
+    suffix: impl Fn(&ConcretePackage) -> Option<String>,
+) -> String {
+    fn visit(
+        spec: &ConcreteSpec,
+        name: &str,
+        depth: usize,
+        seen: &mut HashSet<String>,
+        lines: &mut Vec<String>,
+        suffix: &dyn Fn(&ConcretePackage) -> Option<String>,
+    ) {
+        let Some(package) = spec.get(name) else { return };
+
+        if !seen.insert(name.to_string()) {
+            return;
+        }
+
+        let txt = suffix(package);
+        lines.push(package_line(spec, package, depth, txt.as_deref()));
+
+        for dep in &package.dependencies {
+            visit(spec, dep, depth + 1, seen, lines, suffix);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut lines = Vec::new();
+
+    // Packages which no root reaches are shown at the top level, so nothing
+    // in the spec is hidden
+    for name in spec.roots.iter().chain(spec.packages.keys()) {
+        visit(spec, name, 0, &mut seen, &mut lines, &suffix);
+    }
+
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+/// Render `rows` under `header`, with every column but the last padded to its
+/// widest cell.
+#[must_use]
+pub fn table<const N: usize>(
+    header: [&str; N],
+    rows: &[[String; N]],
+) -> String {
+    let mut widths = header.map(|h| h.chars().count());
+
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: [&str; N], style: Style| {
+        let mut out = String::new();
+
+        for (idx, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            if idx + 1 == N {
+                out.push_str(&paint(style, cell));
+            } else {
+                let pad = width - cell.chars().count() + 2;
+                out.push_str(&paint(style, cell));
+                out.push_str(&" ".repeat(pad));
+            }
+        }
+
+        out.trim_end().to_string() + "\n"
+    };
+
+    let mut out = line(header, Style::new().bold());
+
+    for row in rows {
+        out.push_str(&line(row.each_ref().map(String::as_str), Style::new()));
+    }
+
+    out
+}
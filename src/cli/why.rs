@@ -0,0 +1,222 @@
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+
+use crate::{
+    cli::{
+        CliError, SolveOptions, concretize_requests, load_repos, parse_specs,
+    },
+    constraint::{Constraint, ConstraintUtils},
+    package::outline::{self, PackageOutline},
+    spec::ConcreteSpec,
+    util::suggest,
+};
+
+/// The most paths listed, since the number of paths can grow exponentially
+/// with the depth of the DAG
+const MAX_PATHS: usize = 64;
+
+/// A constraint of a dependent which can introduce a dependency
+#[derive(Clone, Debug, Serialize)]
+pub struct EdgeReason {
+    pub constraint: String,
+
+    /// Whether the condition of an `IfThen` held. `None` for unconditional
+    /// constraints, or if the condition could not be evaluated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub reasons: Vec<EdgeReason>,
+}
+
+/// A path from a root to the package, as the edges along it
+#[derive(Clone, Debug, Serialize)]
+pub struct DependencyPath {
+    pub packages: Vec<String>,
+    pub edges: Vec<Edge>,
+}
+
+pub fn command() -> Command {
+    Command::new("why")
+        .about("Explain why a package is part of a concretization")
+        .long_about(
+            "Concretize SPECS together against --repo and print every path \
+             from a root to PACKAGE in the result. Each step along a path is \
+             annotated with the constraints of the dependent which can \
+             introduce the dependency and, for conditional dependencies, \
+             whether the condition held.\n\n\
+             PACKAGE matches duplicate nodes too, so 'gcc' also explains \
+             'gcc#build'.",
+        )
+        .arg(Arg::new("package").required(true).help("The package to explain"))
+        .arg(
+            Arg::new("specs")
+                .required(true)
+                .action(ArgAction::Append)
+                .help("Root specs to concretize"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Output the paths as JSON"),
+        )
+}
+
+/// Every path from `node` to a node in `targets`, depth first in name order.
+fn find_paths(
+    spec: &ConcreteSpec,
+    node: &str,
+    targets: &[&str],
+    path: &mut Vec<String>,
+    res: &mut Vec<Vec<String>>,
+) {
+    if res.len() >= MAX_PATHS || path.iter().any(|p| p == node) {
+        return;
+    }
+
+    path.push(node.to_string());
+
+    if targets.contains(&node) {
+        res.push(path.clone());
+    } else if let Some(package) = spec.get(node) {
+        for dep in &package.dependencies {
+            find_paths(spec, dep, targets, path, res);
+        }
+    }
+
+    path.pop();
+}
+
+/// The constraints of `dependent` which mention `dependency`
+fn edge_reasons(
+    spec: &ConcreteSpec,
+    dependent: Option<&PackageOutline>,
+    dependency: &str,
+) -> Vec<EdgeReason> {
+    let Some(dependent) = dependent else {
+        return Vec::new();
+    };
+
+    dependent
+        .constraints
+        .iter()
+        .filter(|c| c.extract_dependencies().contains(dependency))
+        .map(|c| EdgeReason {
+            constraint: c.to_string(),
+            condition: match c {
+                Constraint::IfThen(if_then) => spec.holds(&if_then.cond),
+                _ => None,
+            },
+        })
+        .collect()
+}
+
+fn print_path(idx: usize, path: &DependencyPath) {
+    println!("{}. {}", idx + 1, path.packages.join(" -> "));
+
+    for edge in &path.edges {
+        println!("   {} -> {}", edge.from, edge.to);
+
+        if edge.reasons.is_empty() {
+            println!("     (no constraint found)");
+        }
+
+        for reason in &edge.reasons {
+            let condition = match reason.condition {
+                Some(true) => " [condition holds]",
+                Some(false) => " [condition does not hold]",
+                None => "",
+            };
+
+            println!("     {}{condition}", reason.constraint);
+        }
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let package = matches.get_one::<String>("package").unwrap();
+
+    let specs = matches
+        .get_many::<String>("specs")
+        .unwrap()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let requests = parse_specs(&specs)?;
+    let repos = load_repos(matches)?;
+    let options = SolveOptions::load(matches)?;
+
+    let spec = concretize_requests(&repos, &requests, &options)?;
+
+    let names = requests.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+    let (outlines, _) = repos.resolve(&names)?;
+
+    let targets = spec
+        .packages
+        .values()
+        .filter(|p| p.name == *package || p.base_name() == package)
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+
+    if targets.is_empty() {
+        return Err(CliError::InvalidSpec(format!(
+            "'{package}' is not in the concrete spec{}",
+            suggest::did_you_mean(suggest::closest(
+                package,
+                spec.packages.keys().map(String::as_str)
+            ))
+        )));
+    }
+
+    let mut found = Vec::new();
+
+    for root in &spec.roots {
+        find_paths(&spec, root, &targets, &mut Vec::new(), &mut found);
+    }
+
+    let paths = found
+        .into_iter()
+        .map(|packages| {
+            let edges = packages
+                .windows(2)
+                .map(|pair| {
+                    let dependent = outlines
+                        .iter()
+                        .find(|o| o.name == outline::split_node(&pair[0]).0);
+
+                    Edge {
+                        from: pair[0].clone(),
+                        to: pair[1].clone(),
+                        reasons: edge_reasons(&spec, dependent, &pair[1]),
+                    }
+                })
+                .collect();
+
+            DependencyPath { packages, edges }
+        })
+        .collect::<Vec<_>>();
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&paths)?);
+        return Ok(());
+    }
+
+    for (idx, path) in paths.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+
+        print_path(idx, path);
+    }
+
+    if paths.len() >= MAX_PATHS {
+        println!("\nOnly the first {MAX_PATHS} paths are shown");
+    }
+
+    Ok(())
+}
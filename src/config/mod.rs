@@ -0,0 +1,394 @@
+//! User configuration.
+//!
+//! Configuration is read from a YAML file (see [`paths::config_file`]) and
+//! can be overridden with `ZPACK_`-prefixed environment variables. For
+//! example, `ZPACK_OFFLINE=true` or `ZPACK_MIRRORS=/a/mirror:https://b/mirror`.
+//! Since `:` separates list items, paths in `ZPACK_REPOS` cannot contain one.
+//!
+//! Every field has a default, so a missing configuration file is not an error.
+//! The file is validated against [`schema::config`] before it is used, so
+//! unknown keys and values of the wrong type are reported with their path.
+//!
+//! Options are read with [`Config::get`] and written to the file with
+//! [`Config::set`], each named by its `.` separated path, such as
+//! `install_tree.padded_length`.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use saphyr::{LoadableYamlNode, Mapping, Scalar, Yaml, YamlEmitter};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "python")]
+use crate::interface::{plugins::PluginConfig, sandbox::Sandbox};
+use crate::{
+    fetch::{FetchConfig, network::NetworkConfig, signature::SignatureConfig},
+    hooks::HookConfig,
+    package::{
+        environment::IsolationConfig, flags::RpathMode,
+        install::InstallTreeConfig, license::LicensePolicy, policy::Policy,
+        repo::Repository, resolver::ResolverKind, settings::PackageSettings,
+    },
+    spec::{conda::CondaConfig, platform::Platform},
+    util::paths,
+};
+
+pub mod schema;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    /// Package repositories, highest priority first, each written as
+    /// `[NAMESPACE=]PATH`. Repositories given with `--repo` take priority
+    pub repos: Vec<Repository>,
+
+    /// Mirrors to try, in order, before fetching from upstream. Each is
+    /// either a URL or a local directory
+    pub mirrors: Vec<String>,
+
+    /// Fail instead of accessing the network
+    pub offline: bool,
+
+    /// Concurrency, retries and bandwidth limits for downloads
+    pub fetch: FetchConfig,
+
+    /// Shell commands and Python functions run at points in zpack's
+    /// lifecycle, such as after concretizing or installing a package
+    pub hooks: HookConfig,
+
+    /// Restrictions applied when executing package files
+    #[cfg(feature = "python")]
+    pub sandbox: Sandbox,
+
+    /// Which Python entry point plugins to load at startup
+    #[cfg(feature = "python")]
+    pub plugins: PluginConfig,
+
+    /// Cache package outlines extracted from package files between runs
+    pub cache_outlines: bool,
+
+    /// Concretize multiple root specs into a single, consistent DAG. If false,
+    /// each root is concretized independently
+    pub unify: bool,
+
+    /// The platform to concretize for. Facts which are not given default to
+    /// those of the host, and can be overridden with `--platform KEY=VALUE`
+    pub platform: Platform,
+
+    /// Fix the solver's random seeds so identical inputs always produce
+    /// identical concretizations
+    pub deterministic: bool,
+
+    /// Allow specs and packages to refer to options no package declares,
+    /// creating a new option for each. By default such references are errors
+    pub permissive: bool,
+
+    /// Site-wide constraints added to every solve, such as requiring a
+    /// minimum version of a package or forbidding one entirely
+    pub policies: Vec<Policy>,
+
+    /// Versions and options to force for individual packages, and whether
+    /// each may be built, by package name
+    pub packages: BTreeMap<String, PackageSettings>,
+
+    /// Licenses packages may not be used under, and whether to exclude such
+    /// packages from solves or only warn about them
+    pub licenses: LicensePolicy,
+
+    /// Channels and package names used when exporting an environment for
+    /// conda or pixi with `zpack export`
+    pub conda: CondaConfig,
+
+    /// The solver backend used to concretize specs. `auto` uses the built-in
+    /// SAT solver where it supports the packages involved and z3 otherwise
+    pub resolver: ResolverKind,
+
+    /// How packages built into an environment find the libraries of their
+    /// installed dependencies: embedded as an `rpath` or a `runpath`, or
+    /// `none` to rely on `LD_LIBRARY_PATH`
+    pub rpath: RpathMode,
+
+    /// Which parts of the user's environment builds may see
+    pub isolation: IsolationConfig,
+
+    /// Where packages are installed, and how far to pad the install root so
+    /// installed binaries can be relocated to a longer one
+    pub install_tree: InstallTreeConfig,
+
+    /// The signers trusted to sign resources fetched from each mirror, and
+    /// whether every fetched resource must be signed
+    pub signatures: SignatureConfig,
+
+    /// The proxies and certificate authorities used to reach remote
+    /// locations, overall and per mirror
+    pub network: NetworkConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            repos: Vec::new(),
+            mirrors: Vec::new(),
+            offline: false,
+            fetch: FetchConfig::default(),
+            hooks: HookConfig::default(),
+            #[cfg(feature = "python")]
+            sandbox: Sandbox::default(),
+            #[cfg(feature = "python")]
+            plugins: PluginConfig::default(),
+            cache_outlines: true,
+            unify: true,
+            platform: Platform::host(),
+            deterministic: false,
+            permissive: false,
+            policies: Vec::new(),
+            packages: BTreeMap::new(),
+            licenses: LicensePolicy::default(),
+            conda: CondaConfig::default(),
+            resolver: ResolverKind::default(),
+            rpath: RpathMode::default(),
+            isolation: IsolationConfig::default(),
+            install_tree: InstallTreeConfig::default(),
+            signatures: SignatureConfig::default(),
+            network: NetworkConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Load(::config::ConfigError),
+
+    /// The configuration file does not match [`schema::config`]
+    Schema {
+        path: PathBuf,
+        errors: Vec<schema::SchemaError>,
+    },
+
+    /// The constraint of a policy is invalid
+    Policy {
+        name: String,
+        error: String,
+    },
+
+    /// The settings of a package under `packages` are invalid
+    Package {
+        name: String,
+        error: String,
+    },
+
+    /// No option has the given path
+    UnknownKey(String),
+
+    /// The configuration file could not be read or written
+    Io(PathBuf, std::io::Error),
+
+    /// The configuration file or a value to set is not valid YAML
+    Yaml(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Load(e) => write!(f, "invalid configuration: {e}"),
+            Self::Schema { path, errors } => {
+                write!(f, "invalid configuration in {}:", path.display())?;
+
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+
+                Ok(())
+            }
+            Self::Policy { name, error } => {
+                write!(f, "invalid constraint in policy '{name}':\n{error}")
+            }
+            Self::Package { name, error } => {
+                write!(f, "invalid settings for package '{name}':\n{error}")
+            }
+            Self::UnknownKey(key) => {
+                write!(f, "unknown configuration option '{key}'")
+            }
+            Self::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Yaml(e) => write!(f, "invalid YAML: {e}"),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration.
+    ///
+    /// * `path`: The configuration file to read. Defaults to
+    ///   [`paths::config_file`]
+    ///
+    /// # Errors
+    /// Errors if the configuration file does not match [`schema::config`] or
+    /// the environment variables are invalid.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let path = path.map_or_else(paths::config_file, Path::to_path_buf);
+
+        tracing::info!("loading configuration from {}", path.display());
+
+        let file = ::config::File::from(path.as_path())
+            .format(::config::FileFormat::Yaml)
+            .required(false);
+
+        // Environment variables are not validated since unrelated `ZPACK_`
+        // variables, such as `ZPACK_CACHE_DIR`, appear as unknown keys
+        validate(file.clone(), path)?;
+
+        ::config::Config::builder()
+            .add_source(file)
+            .add_source(
+                ::config::Environment::with_prefix("ZPACK")
+                    .try_parsing(true)
+                    .list_separator(":")
+                    .with_list_parse_key("mirrors")
+                    .with_list_parse_key("repos"),
+            )
+            .build()
+            .and_then(::config::Config::try_deserialize)
+            .map_err(ConfigError::Load)
+    }
+
+    /// The value of the option `key`, a `.` separated path such as
+    /// `install_tree.root`.
+    ///
+    /// # Errors
+    /// Errors if there is no such option.
+    pub fn get(&self, key: &str) -> Result<serde_json::Value, ConfigError> {
+        let pointer = format!("/{}", key.replace('.', "/"));
+
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|value| value.pointer(&pointer).cloned())
+            .ok_or_else(|| ConfigError::UnknownKey(key.to_string()))
+    }
+
+    /// Set the option `key`, a `.` separated path such as
+    /// `install_tree.padded_length`, to `value` in a configuration file,
+    /// creating the file if it does not exist. Returns the path of the file.
+    ///
+    /// `value` is parsed as YAML, so `128` is a number, `true` a boolean and
+    /// `[a, b]` a list. Other options in the file are kept, but comments are
+    /// not.
+    ///
+    /// * `path`: The configuration file to write. Defaults to
+    ///   [`paths::config_file`]
+    ///
+    /// # Errors
+    /// Errors if the file cannot be read or written, the file or `value` is
+    /// not valid YAML, or the file would not match [`schema::config`], in
+    /// which case it is left unchanged.
+    pub fn set(
+        path: Option<&Path>,
+        key: &str,
+        value: &str,
+    ) -> Result<PathBuf, ConfigError> {
+        let path = path.map_or_else(paths::config_file, Path::to_path_buf);
+
+        let txt = match std::fs::read_to_string(&path) {
+            Ok(txt) => txt,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(ConfigError::Io(path, e)),
+        };
+
+        let mut doc = load_yaml(&txt)?;
+        let keys = key.split('.').collect::<Vec<_>>();
+
+        set_yaml(&mut doc, &keys, load_yaml(value)?)
+            .map_err(ConfigError::Yaml)?;
+
+        let mut res = String::new();
+        YamlEmitter::new(&mut res)
+            .dump(&doc)
+            .map_err(|e| ConfigError::Yaml(e.to_string()))?;
+        res.push('\n');
+
+        validate(
+            ::config::File::from_str(&res, ::config::FileFormat::Yaml),
+            path.clone(),
+        )?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ConfigError::Io(path.clone(), e))?;
+        }
+
+        std::fs::write(&path, res)
+            .map_err(|e| ConfigError::Io(path.clone(), e))?;
+
+        Ok(path)
+    }
+}
+
+/// Check the configuration in `source`, read from `path`, against
+/// [`schema::config`].
+fn validate(
+    source: impl ::config::Source + Send + Sync + 'static,
+    path: PathBuf,
+) -> Result<(), ConfigError> {
+    let value = ::config::Config::builder()
+        .add_source(source)
+        .build()
+        .and_then(::config::Config::try_deserialize::<::config::Value>)
+        .map_err(ConfigError::Load)?;
+
+    let errors = schema::config().validate(&value);
+
+    if !errors.is_empty() {
+        for error in &errors {
+            tracing::error!("{error}");
+        }
+
+        return Err(ConfigError::Schema { path, errors });
+    }
+
+    Ok(())
+}
+
+/// Parse a single YAML document. An empty document is null.
+fn load_yaml(txt: &str) -> Result<Yaml<'_>, ConfigError> {
+    let mut docs = Yaml::load_from_str(txt)
+        .map_err(|e| ConfigError::Yaml(e.to_string()))?;
+
+    match docs.len() {
+        0 => Ok(Yaml::Value(Scalar::Null)),
+        1 => Ok(docs.remove(0)),
+        n => Err(ConfigError::Yaml(format!(
+            "expected a single document, found {n}"
+        ))),
+    }
+}
+
+/// Set the value at the path `keys` in `doc`, creating mappings as needed.
+fn set_yaml<'a>(
+    doc: &mut Yaml<'a>,
+    keys: &[&'a str],
+    value: Yaml<'a>,
+) -> Result<(), String> {
+    let Some((key, rest)) = keys.split_first() else {
+        *doc = value;
+        return Ok(());
+    };
+
+    if matches!(doc, Yaml::Value(Scalar::Null)) {
+        *doc = Yaml::Mapping(Mapping::new());
+    }
+
+    let Yaml::Mapping(mapping) = doc else {
+        return Err(format!(
+            "cannot set '{key}' in a value which is not a map"
+        ));
+    };
+
+    let child = mapping
+        .entry(Yaml::Value(Scalar::String(Cow::Borrowed(key))))
+        .or_insert(Yaml::Value(Scalar::Null));
+
+    set_yaml(child, rest, value)
+}
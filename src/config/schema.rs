@@ -0,0 +1,378 @@
+//! Validation of configuration files against a typed schema.
+//!
+//! Deserializing the configuration directly ignores unknown keys and reports
+//! type errors without saying where they occurred. Instead, the parsed file
+//! is first checked against a [`Schema`], which reports every problem
+//! together with the dotted path of the offending value, such as
+//! `platform.os must be a string` or `unknown key 'ofline'; did you mean
+//! 'offline'?`.
+
+use ::config::{Value, ValueKind};
+
+use crate::{
+    hooks::HookEvent,
+    package::{
+        flags::RpathMode, license::LicenseAction, resolver::ResolverKind,
+    },
+    spec::platform::PlatformKey,
+    util::suggest,
+};
+
+/// The expected shape of a configuration value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schema {
+    Bool,
+    Integer,
+
+    /// An integer or a float
+    Number,
+    String,
+
+    /// One of a fixed set of strings
+    OneOf(Vec<&'static str>),
+
+    /// The inner schema, or null
+    Optional(Box<Self>),
+
+    /// A list whose items all match the inner schema
+    List(Box<Self>),
+
+    /// A mapping from arbitrary names to values matching the inner schema
+    Map(Box<Self>),
+
+    /// A mapping with a fixed set of keys, any of which may be omitted
+    Record(Vec<(&'static str, Self)>),
+}
+
+/// Where a value is located in the configuration, as a sequence of keys and
+/// list indices
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchemaPath(Vec<PathSegment>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl SchemaPath {
+    fn key(&self, key: &str) -> Self {
+        let mut path = self.clone();
+        path.0.push(PathSegment::Key(key.to_string()));
+        path
+    }
+
+    fn index(&self, idx: usize) -> Self {
+        let mut path = self.clone();
+        path.0.push(PathSegment::Index(idx));
+        path
+    }
+}
+
+impl std::fmt::Display for SchemaPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("the configuration");
+        }
+
+        for (idx, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if idx == 0 => write!(f, "{key}")?,
+                PathSegment::Key(key) => write!(f, ".{key}")?,
+                PathSegment::Index(i) => write!(f, "[{i}]")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaError {
+    /// The value at `path` does not have the expected type
+    Mismatch { path: SchemaPath, expected: String },
+
+    /// A record contains a key it does not define
+    UnknownKey { path: SchemaPath, suggestion: Option<String> },
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch { path, expected } => {
+                write!(f, "{path} must be {expected}")
+            }
+            Self::UnknownKey { path, suggestion } => write!(
+                f,
+                "unknown key '{path}'{}",
+                suggest::did_you_mean(suggestion.as_deref())
+            ),
+        }
+    }
+}
+
+impl Schema {
+    /// A description of the values matching this schema, used in errors
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Bool => "a boolean".into(),
+            Self::Integer => "an integer".into(),
+            Self::Number => "a number".into(),
+            Self::String => "a string".into(),
+            Self::OneOf(choices) => format!(
+                "one of {}",
+                choices
+                    .iter()
+                    .map(|c| format!("'{c}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Optional(inner) => format!("{} or null", inner.describe()),
+            Self::List(_) => "a list".into(),
+            Self::Map(_) | Self::Record(_) => "a mapping".into(),
+        }
+    }
+
+    /// Check `value` against this schema, returning every violation found.
+    ///
+    /// Keys are checked in sorted order so errors are reported
+    /// deterministically.
+    #[must_use]
+    pub fn validate(&self, value: &Value) -> Vec<SchemaError> {
+        let mut errors = Vec::new();
+        self.validate_at(value, &SchemaPath::default(), &mut errors);
+        errors
+    }
+
+    fn validate_at(
+        &self,
+        value: &Value,
+        path: &SchemaPath,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        match (self, &value.kind) {
+            (Self::Optional(_), ValueKind::Nil)
+            | (Self::Bool, ValueKind::Boolean(_))
+            | (
+                Self::Integer | Self::Number,
+                ValueKind::I64(_)
+                | ValueKind::I128(_)
+                | ValueKind::U64(_)
+                | ValueKind::U128(_),
+            )
+            | (Self::Number, ValueKind::Float(_))
+            | (Self::String, ValueKind::String(_)) => {}
+
+            (Self::OneOf(choices), ValueKind::String(s))
+                if choices.contains(&s.as_str()) => {}
+
+            (Self::Optional(inner), _) => {
+                inner.validate_at(value, path, errors);
+            }
+
+            (Self::List(inner), ValueKind::Array(items)) => {
+                for (idx, item) in items.iter().enumerate() {
+                    inner.validate_at(item, &path.index(idx), errors);
+                }
+            }
+
+            (Self::Map(inner), ValueKind::Table(table)) => {
+                let mut keys = table.keys().collect::<Vec<_>>();
+                keys.sort();
+
+                for key in keys {
+                    inner.validate_at(&table[key], &path.key(key), errors);
+                }
+            }
+
+            (Self::Record(fields), ValueKind::Table(table)) => {
+                let mut keys = table.keys().collect::<Vec<_>>();
+                keys.sort();
+
+                for key in keys {
+                    if let Some((_, field)) =
+                        fields.iter().find(|(name, _)| name == key)
+                    {
+                        field.validate_at(&table[key], &path.key(key), errors);
+                    } else {
+                        errors.push(SchemaError::UnknownKey {
+                            path: path.key(key),
+                            suggestion: suggest::closest(
+                                key,
+                                fields.iter().map(|(name, _)| *name),
+                            )
+                            .map(str::to_string),
+                        });
+                    }
+                }
+            }
+
+            _ => errors.push(SchemaError::Mismatch {
+                path: path.clone(),
+                expected: self.describe(),
+            }),
+        }
+    }
+}
+
+/// The schema of the configuration file, matching
+/// [`Config`](super::Config).
+#[must_use]
+#[allow(clippy::too_many_lines)]
+pub fn config() -> Schema {
+    let platform = PlatformKey::ALL
+        .into_iter()
+        .map(|key| (key.as_str(), Schema::String))
+        .collect();
+
+    let action = Schema::Record(vec![
+        ("command", Schema::String),
+        ("python", Schema::String),
+    ]);
+
+    let hooks = HookEvent::ALL
+        .into_iter()
+        .map(|event| (event.name(), Schema::List(Box::new(action.clone()))))
+        .collect();
+
+    #[allow(unused_mut)]
+    let mut fields = vec![
+        ("repos", Schema::List(Box::new(Schema::String))),
+        ("mirrors", Schema::List(Box::new(Schema::String))),
+        ("offline", Schema::Bool),
+        (
+            "fetch",
+            Schema::Record(vec![
+                ("jobs", Schema::Integer),
+                ("retries", Schema::Integer),
+                ("backoff", Schema::Number),
+                ("timeout", Schema::Number),
+                ("rate_limit", Schema::Optional(Box::new(Schema::Integer))),
+                ("cache", Schema::Bool),
+            ]),
+        ),
+        ("hooks", Schema::Record(hooks)),
+        ("cache_outlines", Schema::Bool),
+        ("unify", Schema::Bool),
+        ("platform", Schema::Record(platform)),
+        ("deterministic", Schema::Bool),
+        ("permissive", Schema::Bool),
+        (
+            "policies",
+            Schema::List(Box::new(Schema::Record(vec![
+                ("name", Schema::String),
+                ("constraint", Schema::String),
+                ("reason", Schema::String),
+            ]))),
+        ),
+        (
+            "packages",
+            Schema::Map(Box::new(Schema::Record(vec![
+                ("version", Schema::Optional(Box::new(Schema::String))),
+                ("options", Schema::Optional(Box::new(Schema::String))),
+                ("buildable", Schema::Bool),
+            ]))),
+        ),
+        (
+            "licenses",
+            Schema::Record(vec![
+                ("forbidden", Schema::List(Box::new(Schema::String))),
+                (
+                    "action",
+                    Schema::OneOf(
+                        LicenseAction::ALL.map(LicenseAction::name).to_vec(),
+                    ),
+                ),
+            ]),
+        ),
+        (
+            "conda",
+            Schema::Record(vec![
+                ("channels", Schema::List(Box::new(Schema::String))),
+                (
+                    "packages",
+                    Schema::Map(Box::new(Schema::Optional(Box::new(
+                        Schema::String,
+                    )))),
+                ),
+            ]),
+        ),
+        (
+            "resolver",
+            Schema::OneOf(ResolverKind::ALL.map(ResolverKind::name).to_vec()),
+        ),
+        ("rpath", Schema::OneOf(RpathMode::ALL.map(RpathMode::name).to_vec())),
+        (
+            "isolation",
+            Schema::Record(vec![
+                ("enabled", Schema::Bool),
+                ("keep", Schema::List(Box::new(Schema::String))),
+                ("system_path", Schema::List(Box::new(Schema::String))),
+            ]),
+        ),
+        (
+            "install_tree",
+            Schema::Record(vec![
+                ("root", Schema::String),
+                ("padded_length", Schema::Optional(Box::new(Schema::Integer))),
+                ("lock_timeout", Schema::Optional(Box::new(Schema::Integer))),
+            ]),
+        ),
+        (
+            "signatures",
+            Schema::Record(vec![
+                ("require_signed", Schema::Bool),
+                (
+                    "trust",
+                    Schema::Map(Box::new(Schema::Record(vec![
+                        ("keyring", Schema::Optional(Box::new(Schema::String))),
+                        (
+                            "identity",
+                            Schema::Optional(Box::new(Schema::String)),
+                        ),
+                        ("issuer", Schema::Optional(Box::new(Schema::String))),
+                    ]))),
+                ),
+            ]),
+        ),
+        (
+            "network",
+            Schema::Record(vec![
+                ("proxy", Schema::Optional(Box::new(Schema::String))),
+                ("ca_bundle", Schema::Optional(Box::new(Schema::String))),
+                (
+                    "overrides",
+                    Schema::Map(Box::new(Schema::Record(vec![
+                        ("proxy", Schema::Optional(Box::new(Schema::String))),
+                        (
+                            "ca_bundle",
+                            Schema::Optional(Box::new(Schema::String)),
+                        ),
+                    ]))),
+                ),
+            ]),
+        ),
+    ];
+
+    #[cfg(feature = "python")]
+    fields.extend([
+        (
+            "sandbox",
+            Schema::Record(vec![
+                ("enabled", Schema::Bool),
+                ("allowed_imports", Schema::List(Box::new(Schema::String))),
+                ("timeout", Schema::Optional(Box::new(Schema::Number))),
+            ]),
+        ),
+        (
+            "plugins",
+            Schema::Record(vec![
+                ("enabled", Schema::Bool),
+                ("disabled", Schema::List(Box::new(Schema::String))),
+            ]),
+        ),
+    ]);
+
+    Schema::Record(fields)
+}
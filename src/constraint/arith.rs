@@ -0,0 +1,450 @@
+//! Arithmetic on numeric constraints.
+//!
+//! [`Arith`] adds, subtracts or multiplies two constraints which evaluate to
+//! numbers, such as options, constants and [`NumOf`](super::NumOf), so
+//! relations between options can be asserted:
+//!
+//! ```text
+//! option(app:io_threads) <= option(app:threads) - option(app:compute_threads)
+//! ```
+//!
+//! Both operands must have the same type, `Int` or `Float`, which is the type
+//! of the result. An operand of unknown type, such as an option no other
+//! constraint types, takes the type of the other. Integers are converted to
+//! z3 integer arithmetic, and floats to IEEE arithmetic rounding to nearest,
+//! as floats are stored.
+
+use std::collections::HashSet;
+
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    exceptions::PyNotImplementedError,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "solver-z3")]
+use z3::ast::{Float, Int, RoundingMode};
+
+#[cfg(feature = "solver-z3")]
+use crate::constraint::CmpType;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError, registry::Registry};
+#[cfg(feature = "python")]
+use crate::{constraint::Cmp, interface::pickle};
+use crate::{
+    constraint::{Constraint, ConstraintUtils, syntax},
+    spec::{self, SpecOptionType},
+};
+
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", eq, eq_int, hash, frozen)
+)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl ArithOp {
+    /// How tightly the operator binds. `*` binds tighter than `+` and `-`
+    #[must_use]
+    pub const fn precedence(self) -> u8 {
+        match self {
+            Self::Add | Self::Sub => 0,
+            Self::Mul => 1,
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl ArithOp {
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
+
+impl std::fmt::Display for ArithOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+        })
+    }
+}
+
+/// `lhs op rhs`, for numeric `lhs` and `rhs`
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Arith {
+    pub lhs: Constraint,
+
+    pub rhs: Constraint,
+
+    pub op: ArithOp,
+}
+
+impl Arith {
+    #[must_use]
+    pub fn new(
+        lhs: impl Into<Constraint>,
+        op: ArithOp,
+        rhs: impl Into<Constraint>,
+    ) -> Self {
+        Self { lhs: lhs.into(), rhs: rhs.into(), op }
+    }
+
+    /// Whether values of type `t` support arithmetic
+    #[must_use]
+    pub const fn is_numeric(t: SpecOptionType) -> bool {
+        matches!(t, SpecOptionType::Int | SpecOptionType::Float)
+    }
+
+    /// The type of the result given the types of the operands: the known one
+    /// if either is unknown, otherwise `lhs`, which type checking ensures is
+    /// the same as `rhs`
+    const fn result_type(
+        lhs: Option<SpecOptionType>,
+        rhs: Option<SpecOptionType>,
+    ) -> Option<SpecOptionType> {
+        match lhs {
+            Some(SpecOptionType::Unknown) => rhs,
+            _ => lhs,
+        }
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn py_arith_helper(
+        lhs: Constraint,
+        op: ArithOp,
+        rhs: Constraint,
+    ) -> PyResult<Constraint> {
+        let (Some(lhs_type), Some(rhs_type)) =
+            (lhs.get_value_type_default(), rhs.get_value_type_default())
+        else {
+            return Err(PyNotImplementedError::new_err(format!(
+                "Cannot apply '{op}' to {lhs} and {rhs}, which must both \
+                 return a value"
+            )));
+        };
+
+        let valid = |t| t == SpecOptionType::Unknown || Self::is_numeric(t);
+
+        let is_unknown = lhs_type == SpecOptionType::Unknown
+            || rhs_type == SpecOptionType::Unknown;
+
+        if valid(lhs_type)
+            && valid(rhs_type)
+            && (lhs_type == rhs_type || is_unknown)
+        {
+            Ok(Self { lhs, rhs, op }.into())
+        } else {
+            Err(PyNotImplementedError::new_err(format!(
+                "Cannot apply '{op}' to type {lhs_type:?} from constraint {lhs} and type {rhs_type:?} from constraint {rhs}"
+            )))
+        }
+    }
+}
+
+impl ConstraintUtils for Arith {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
+        Self::result_type(
+            self.lhs.get_value_type_default(),
+            self.rhs.get_value_type_default(),
+        )
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn get_value_type<V>(
+        &self,
+        registry: Option<&Registry<V>>,
+    ) -> Option<SpecOptionType> {
+        Self::result_type(
+            self.lhs.get_value_type(registry),
+            self.rhs.get_value_type(registry),
+        )
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+        value_type: SpecOptionType,
+    ) {
+        for operand in [&self.lhs, &self.rhs] {
+            if operand.get_value_type(Some(wip_registry))
+                == Some(SpecOptionType::Unknown)
+            {
+                operand.set_value_type(wip_registry, value_type);
+            }
+        }
+    }
+
+    #[cfg(feature = "solver-z3")]
+    #[tracing::instrument(skip(self, wip_registry))]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        let Some(lhs_type) = self.lhs.get_value_type(Some(wip_registry)) else {
+            return Err(Box::new(SolverError::InvalidNonValueConstraint));
+        };
+
+        let Some(rhs_type) = self.rhs.get_value_type(Some(wip_registry)) else {
+            return Err(Box::new(SolverError::InvalidNonValueConstraint));
+        };
+
+        let value_type = match (lhs_type, rhs_type) {
+            (SpecOptionType::Unknown, SpecOptionType::Unknown) => {
+                let msg = format!("cannot infer the types used by '{self}'");
+                tracing::error!("{msg}");
+                return Err(Box::new(SolverError::InvalidConstraint(msg)));
+            }
+            (SpecOptionType::Unknown, known) => {
+                self.lhs.set_value_type(wip_registry, known);
+                known
+            }
+            (known, SpecOptionType::Unknown) => {
+                self.rhs.set_value_type(wip_registry, known);
+                known
+            }
+            (lhs_known, rhs_known) => {
+                if lhs_known != rhs_known {
+                    tracing::error!(
+                        "Cannot apply '{}' to differing types {lhs_known:?} \
+                         and {rhs_known:?}",
+                        self.op
+                    );
+
+                    return Err(Box::new(SolverError::IncorrectValueType {
+                        expected: lhs_known,
+                        received: rhs_known,
+                    }));
+                }
+
+                lhs_known
+            }
+        };
+
+        if !Self::is_numeric(value_type) {
+            let msg =
+                format!("Cannot apply '{}' to type {value_type:?}", self.op);
+            tracing::error!("{msg}");
+            return Err(Box::new(SolverError::InvalidConstraint(msg)));
+        }
+
+        self.lhs.type_check(wip_registry)?;
+        self.rhs.type_check(wip_registry)
+    }
+
+    fn extract_spec_options(&self) -> Vec<(&str, &str, spec::SpecOption)> {
+        let mut res = Vec::new();
+        res.extend(self.lhs.extract_spec_options());
+        res.extend(self.rhs.extract_spec_options());
+        res
+    }
+
+    fn extract_dependencies(&self) -> HashSet<String> {
+        let mut res = HashSet::new();
+        res.extend(self.lhs.extract_dependencies());
+        res.extend(self.rhs.extract_dependencies());
+        res
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn cmp_to_z3(
+        &self,
+        other: &Constraint,
+        op: CmpType,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<z3::ast::Dynamic, Box<SolverError>> {
+        let s = self.to_z3_clauses(registry)?.remove(0);
+        let other_clauses = other.to_z3_clauses(registry)?;
+
+        if other_clauses.len() != 1 {
+            return Err(Box::new(SolverError::InvalidNumberOfClauses(
+                other_clauses.len(),
+            )));
+        }
+
+        let o = &other_clauses[0];
+
+        macro_rules! cmp_op {
+            ($conv:ident) => {{
+                // Safe to unwrap since both sides have been type checked
+                let s = s.$conv().unwrap();
+                let o = o.$conv().unwrap();
+
+                match op {
+                    CmpType::Less => s.lt(o).into(),
+                    CmpType::LessOrEqual => s.le(o).into(),
+                    CmpType::NotEqual => s.ne(o).into(),
+                    CmpType::Equal => s.eq(o).into(),
+                    CmpType::GreaterOrEqual => s.ge(o).into(),
+                    CmpType::Greater => s.gt(o).into(),
+                }
+            }};
+        }
+
+        Ok(if s.as_int().is_some() {
+            cmp_op!(as_int)
+        } else {
+            cmp_op!(as_float)
+        })
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn to_z3_clauses(
+        &self,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
+        let mut operand = |c: &Constraint| {
+            let mut clauses = c.to_z3_clauses(registry)?;
+
+            if clauses.len() == 1 {
+                Ok(clauses.remove(0))
+            } else {
+                Err(Box::new(SolverError::InvalidNumberOfClauses(
+                    clauses.len(),
+                )))
+            }
+        };
+
+        let lhs = operand(&self.lhs)?;
+        let rhs = operand(&self.rhs)?;
+
+        if let (Some(lhs), Some(rhs)) = (lhs.as_int(), rhs.as_int()) {
+            let res = match self.op {
+                ArithOp::Add => Int::add(&[lhs, rhs]),
+                ArithOp::Sub => Int::sub(&[lhs, rhs]),
+                ArithOp::Mul => Int::mul(&[lhs, rhs]),
+            };
+
+            Ok(vec![res.into()])
+        } else if let (Some(lhs), Some(rhs)) = (lhs.as_float(), rhs.as_float())
+        {
+            let rounding = RoundingMode::round_nearest_ties_to_even();
+
+            let res: Float = match self.op {
+                ArithOp::Add => lhs.add_with_rounding_mode(rhs, &rounding),
+                ArithOp::Sub => lhs.sub_with_rounding_mode(rhs, &rounding),
+                ArithOp::Mul => lhs.mul_with_rounding_mode(rhs, &rounding),
+            };
+
+            Ok(vec![res.into()])
+        } else {
+            let msg = format!(
+                "Cannot apply '{}' to {:?} and {:?}",
+                self.op,
+                lhs.sort_kind(),
+                rhs.sort_kind()
+            );
+            tracing::error!("{msg}");
+            Err(Box::new(SolverError::InvalidConstraint(msg)))
+        }
+    }
+
+    #[cfg(feature = "python")]
+    fn to_python_any<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.clone().into_bound_py_any(py)
+    }
+}
+
+impl From<Arith> for Constraint {
+    fn from(val: Arith) -> Self {
+        Self::Arith(Box::new(val))
+    }
+}
+
+impl std::fmt::Display for Arith {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        syntax::write_arith_operand(f, &self.lhs, self.op, false)?;
+        write!(f, " {} ", self.op)?;
+        syntax::write_arith_operand(f, &self.rhs, self.op, true)
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Arith {
+    #[new]
+    const fn py_new(lhs: Constraint, rhs: Constraint, op: ArithOp) -> Self {
+        Self { lhs, rhs, op }
+    }
+
+    fn __richcmp__(
+        &self,
+        rhs: Constraint,
+        op: CompareOp,
+    ) -> Result<Constraint, PyErr> {
+        Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
+    }
+
+    fn __add__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Self::py_arith_helper(self.clone().into(), ArithOp::Add, rhs)
+    }
+
+    fn __radd__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Self::py_arith_helper(lhs, ArithOp::Add, self.clone().into())
+    }
+
+    fn __sub__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Self::py_arith_helper(self.clone().into(), ArithOp::Sub, rhs)
+    }
+
+    fn __rsub__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Self::py_arith_helper(lhs, ArithOp::Sub, self.clone().into())
+    }
+
+    fn __mul__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Self::py_arith_helper(self.clone().into(), ArithOp::Mul, rhs)
+    }
+
+    fn __rmul__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Self::py_arith_helper(lhs, ArithOp::Mul, self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
@@ -1,18 +1,34 @@
 use std::collections::HashSet;
 
+#[cfg(feature = "python")]
 use pyo3::{
-    IntoPyObjectExt, basic::CompareOp, exceptions::PyNotImplementedError,
+    IntoPyObjectExt,
+    basic::CompareOp,
+    exceptions::{PyNotImplementedError, PyValueError},
     prelude::*,
+    types::{PyBytes, PyType},
 };
+use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
 use crate::{
-    constraint::{Constraint, ConstraintUtils, IfThen},
-    package::{self, outline::SolverError},
+    constraint::{Constraint, ConstraintUtils, syntax},
     spec::{self, SpecOptionType},
 };
+#[cfg(feature = "python")]
+use crate::{
+    constraint::{IfThen, NumOf, Value},
+    interface::pickle,
+    package::version::Version,
+    spec::{SpecOptionValue, concrete::VERSION_OPTION},
+};
 
-#[pyclass]
-#[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", eq, eq_int, hash, frozen)
+)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CmpType {
     Less,
     LessOrEqual,
@@ -22,6 +38,7 @@ pub enum CmpType {
     Greater,
 }
 
+#[cfg(feature = "python")]
 impl From<CompareOp> for CmpType {
     fn from(value: CompareOp) -> Self {
         match value {
@@ -35,6 +52,22 @@ impl From<CompareOp> for CmpType {
     }
 }
 
+#[cfg(feature = "python")]
+#[pymethods]
+impl CmpType {
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
+
 impl std::fmt::Display for CmpType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
@@ -48,20 +81,29 @@ impl std::fmt::Display for CmpType {
     }
 }
 
-#[pyclass]
-#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cmp {
-    #[pyo3(get, set)]
     pub lhs: Constraint,
 
-    #[pyo3(get, set)]
     pub rhs: Constraint,
 
-    #[pyo3(get, set)]
     pub op: CmpType,
 }
 
 impl Cmp {
+    #[must_use]
+    pub fn new(
+        lhs: impl Into<Constraint>,
+        op: CmpType,
+        rhs: impl Into<Constraint>,
+    ) -> Self {
+        Self { lhs: lhs.into(), rhs: rhs.into(), op }
+    }
+
     #[must_use]
     pub const fn can_cmp(t: SpecOptionType, op: CmpType) -> bool {
         match op {
@@ -82,11 +124,15 @@ impl Cmp {
         }
     }
 
+    #[cfg(feature = "python")]
     pub(crate) fn py_richcmp_helper(
         lhs: Constraint,
         rhs: Constraint,
         op: CmpType,
     ) -> Result<Constraint, PyErr> {
+        let lhs = Self::py_version_operand(lhs, &rhs)?;
+        let rhs = Self::py_version_operand(rhs, &lhs)?;
+
         let Some(lhs_type) = lhs.get_value_type_default() else {
             return Err(PyNotImplementedError::new_err(format!(
                 "Constraint {lhs} does not return a value"
@@ -112,17 +158,138 @@ impl Cmp {
     }
 }
 
+#[cfg(feature = "python")]
+impl Cmp {
+    /// `operand`, parsed as a version if it is a string compared with the
+    /// version of a package, so `option("gcc", "version") >= "12"` compares
+    /// versions rather than strings
+    fn py_version_operand(
+        operand: Constraint,
+        other: &Constraint,
+    ) -> PyResult<Constraint> {
+        if let Constraint::SpecOption(option) = other
+            && option.option_name == VERSION_OPTION
+            && let Constraint::Value(value) = &operand
+            && let SpecOptionValue::Str(txt) = &value.value
+        {
+            let version = Version::new(txt).map_err(|e| {
+                PyValueError::new_err(format!("invalid version '{txt}': {e:?}"))
+            })?;
+
+            Ok(Value { value: SpecOptionValue::Version(version) }.into())
+        } else {
+            Ok(operand)
+        }
+    }
+
+    /// Errors unless `constraint` can be true or false, so can be combined
+    /// with `&`, `|` and `~`
+    fn py_condition(constraint: &Constraint) -> PyResult<()> {
+        match constraint.get_value_type_default() {
+            Some(SpecOptionType::Bool | SpecOptionType::Unknown) => Ok(()),
+            _ => Err(PyNotImplementedError::new_err(format!(
+                "Constraint {constraint} is not a condition"
+            ))),
+        }
+    }
+
+    /// The number of constraints which must hold for `num_of(...) op count`
+    /// to be the conjunction (`all`) or disjunction of them
+    const fn py_join_op(all: bool, len: usize) -> (CmpType, usize) {
+        if all { (CmpType::Equal, len) } else { (CmpType::GreaterOrEqual, 1) }
+    }
+
+    /// The constraints joined by `constraint`, if it is a conjunction (`all`)
+    /// or disjunction built by [`Self::py_join`]
+    fn py_joined(constraint: Constraint, all: bool) -> Vec<Constraint> {
+        if let Constraint::Cmp(cmp) = &constraint
+            && let Constraint::NumOf(num_of) = &cmp.lhs
+            && let Constraint::Value(value) = &cmp.rhs
+            && let SpecOptionValue::Int(count) = value.value
+            && let Ok(count) = usize::try_from(count)
+            && (cmp.op, count) == Self::py_join_op(all, num_of.of.len())
+        {
+            num_of.of.clone()
+        } else {
+            vec![constraint]
+        }
+    }
+
+    /// `lhs & rhs` if `all`, otherwise `lhs | rhs`. There is no conjunction
+    /// or disjunction constraint, so they are built from [`NumOf`]:
+    /// `num_of(lhs, rhs) == 2` and `num_of(lhs, rhs) >= 1`. Chains such as
+    /// `a & b & c` are flattened into a single [`NumOf`].
+    pub(crate) fn py_join(
+        lhs: Constraint,
+        rhs: Constraint,
+        all: bool,
+    ) -> PyResult<Constraint> {
+        Self::py_condition(&lhs)?;
+        Self::py_condition(&rhs)?;
+
+        let mut of = Self::py_joined(lhs, all);
+        of.extend(Self::py_joined(rhs, all));
+
+        let (op, count) = Self::py_join_op(all, of.len());
+        let count = i64::try_from(count)
+            .map_err(|_| PyValueError::new_err("too many constraints"))?;
+
+        Ok(Self::new(NumOf { of }, op, Value { value: count.into() }).into())
+    }
+
+    /// `~constraint`, which holds if `constraint` does not
+    pub(crate) fn py_invert(constraint: Constraint) -> PyResult<Constraint> {
+        Self::py_condition(&constraint)?;
+
+        let no = Value { value: false.into() };
+        Ok(Self::new(constraint, CmpType::Equal, no).into())
+    }
+}
+
+#[cfg(feature = "solver-z3")]
+impl Cmp {
+    /// The error for comparing operands of the types `lhs` and `rhs`, naming
+    /// the option if an option is compared with a value.
+    fn mismatch_error(
+        &self,
+        lhs: SpecOptionType,
+        rhs: SpecOptionType,
+    ) -> Box<SolverError> {
+        let error = match (&self.lhs, &self.rhs) {
+            (Constraint::SpecOption(option), Constraint::Value(value)) => {
+                SolverError::OptionValueType {
+                    package: option.package_name.clone(),
+                    option: option.option_name.clone(),
+                    expected: lhs,
+                    value: value.value.clone(),
+                }
+            }
+            (Constraint::Value(value), Constraint::SpecOption(option)) => {
+                SolverError::OptionValueType {
+                    package: option.package_name.clone(),
+                    option: option.option_name.clone(),
+                    expected: rhs,
+                    value: value.value.clone(),
+                }
+            }
+            _ => {
+                SolverError::IncorrectValueType { expected: lhs, received: rhs }
+            }
+        };
+
+        Box::new(error)
+    }
+}
+
 impl ConstraintUtils for Cmp {
-    fn get_value_type<'a, V>(
-        &'a self,
-        _registry: Option<&package::registry::Registry<'a, V>>,
-    ) -> Option<spec::SpecOptionType> {
+    fn get_value_type_default(&self) -> Option<spec::SpecOptionType> {
         Some(spec::SpecOptionType::Bool)
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        _wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
         value_type: spec::SpecOptionType,
     ) {
         assert_eq!(
@@ -132,10 +299,11 @@ impl ConstraintUtils for Cmp {
         );
     }
 
+    #[cfg(feature = "solver-z3")]
     #[tracing::instrument(skip(self, wip_registry))]
-    fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         let Some(lhs_type) = self.lhs.get_value_type(Some(wip_registry)) else {
             return Err(Box::new(SolverError::InvalidNonValueConstraint));
@@ -147,7 +315,10 @@ impl ConstraintUtils for Cmp {
 
         match (lhs_type, rhs_type) {
             (SpecOptionType::Unknown, SpecOptionType::Unknown) => {
-                panic!("Double blind comparison")
+                let msg =
+                    format!("cannot infer the types compared by '{self}'");
+                tracing::error!("{msg}");
+                Err(Box::new(SolverError::InvalidConstraint(msg)))
             }
             (SpecOptionType::Unknown, known) => {
                 self.lhs.set_value_type(wip_registry, known);
@@ -165,10 +336,7 @@ impl ConstraintUtils for Cmp {
                         "Cannot compare differing types {lhs_type:?} and {rhs_type:?}"
                     );
 
-                    Err(Box::new(SolverError::IncorrectValueType {
-                        expected: lhs_type,
-                        received: rhs_type,
-                    }))
+                    Err(self.mismatch_error(lhs_type, rhs_type))
                 }
             }
         }?;
@@ -210,13 +378,15 @@ impl ConstraintUtils for Cmp {
         res
     }
 
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
         Ok(vec![self.lhs.cmp_to_z3(&self.rhs, self.op, registry)?])
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: pyo3::Python<'py>,
@@ -233,10 +403,13 @@ impl From<Cmp> for Constraint {
 
 impl std::fmt::Display for Cmp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[ {} ] {} [ {} ]", self.lhs, self.op, self.rhs)
+        syntax::write_operand(f, &self.lhs, true)?;
+        write!(f, " {} ", self.op)?;
+        syntax::write_operand(f, &self.rhs, true)
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl Cmp {
     #[new]
@@ -258,4 +431,48 @@ impl Cmp {
     ) -> Result<Constraint, PyErr> {
         Self::py_richcmp_helper(self.clone().into(), rhs, op.into())
     }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Self::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Self::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Self::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Self::py_join(lhs, self.clone().into(), false)
+    }
+
+    fn __invert__(&self) -> PyResult<Constraint> {
+        Self::py_invert(self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
@@ -0,0 +1,289 @@
+//! Constraint kinds defined outside zpack.
+//!
+//! [`Constraint`] is a closed enum, so a downstream crate cannot add a variant
+//! to it. Instead, it implements [`CustomConstraint`] for its own type and
+//! wraps it in a [`Custom`], which is stored as [`Constraint::Custom`] and is
+//! handled like any other constraint by the solver.
+//!
+//! Custom constraints are serialized as their [`CustomConstraint::kind`]
+//! together with [`CustomConstraint::to_json`]. Deserializing one, or
+//! extracting one from a Python object, requires its kind to have been
+//! registered with [`register`] first:
+//!
+//! ```rust,ignore
+//! zpack::constraint::custom::register(CustomKind {
+//!     name: "my-crate.at-most",
+//!     deserialize: |json| {
+//!         let at_most: AtMost =
+//!             serde_json::from_value(json).map_err(|e| e.to_string())?;
+//!         Ok(Arc::new(at_most))
+//!     },
+//!     extract: Some(|obj| Ok(Arc::new(obj.extract::<AtMost>()?))),
+//! });
+//! ```
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
+use crate::{
+    constraint::{Constraint, ConstraintUtils},
+    spec::{self, SpecOptionType},
+};
+
+/// A constraint kind implemented outside zpack.
+///
+/// Only [`Self::kind`], [`Self::value_type`], [`Self::to_z3_clauses`],
+/// [`Self::to_json`] and [`Self::to_python_any`] are required. The remaining
+/// methods default to a constraint which references no options or packages.
+pub trait CustomConstraint:
+    Send + Sync + std::fmt::Debug + std::fmt::Display
+{
+    /// Unique name of this constraint kind, used to find its deserializer.
+    /// Should be namespaced by the defining crate, such as `my-crate.at-most`
+    fn kind(&self) -> &'static str;
+
+    /// The type of value this constraint evaluates to
+    fn value_type(&self) -> Option<SpecOptionType>;
+
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
+        _value_type: SpecOptionType,
+    ) {
+    }
+
+    /// See [`ConstraintUtils::type_check`].
+    ///
+    /// # Errors
+    /// Errors if the constraint variable types are invalid.
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        Ok(())
+    }
+
+    fn extract_spec_options(&self) -> Vec<(&str, &str, spec::SpecOption)> {
+        Vec::new()
+    }
+
+    fn extract_dependencies(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+
+    /// See [`ConstraintUtils::to_z3_clauses`].
+    ///
+    /// # Errors
+    /// Errors if the constraint cannot be converted to Z3 clauses.
+    #[cfg(feature = "solver-z3")]
+    fn to_z3_clauses(
+        &self,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>>;
+
+    /// The data needed to reconstruct this constraint with
+    /// [`CustomKind::deserialize`]
+    fn to_json(&self) -> serde_json::Value;
+
+    /// Convert this constraint into a Python object.
+    ///
+    /// # Errors
+    /// Errors if the object cannot be created.
+    #[cfg(feature = "python")]
+    fn to_python_any<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyAny>>;
+
+    /// A copy of this constraint with every reference to an option of the
+    /// package `from` replaced with the same option of `to`, or `None` if
+    /// the constraint references no options.
+    fn rename_package(
+        &self,
+        _from: &str,
+        _to: &str,
+    ) -> Option<Arc<dyn CustomConstraint>> {
+        None
+    }
+}
+
+/// Reconstructs a custom constraint from [`CustomConstraint::to_json`]
+pub type DeserializeFn =
+    fn(serde_json::Value) -> Result<Arc<dyn CustomConstraint>, String>;
+
+/// Extracts a custom constraint from a Python object, failing if the object
+/// is not of this kind
+#[cfg(feature = "python")]
+pub type ExtractFn =
+    fn(&Bound<'_, PyAny>) -> PyResult<Arc<dyn CustomConstraint>>;
+
+/// A registered custom constraint kind
+#[derive(Clone, Copy, Debug)]
+pub struct CustomKind {
+    /// Must match [`CustomConstraint::kind`]
+    pub name: &'static str,
+
+    pub deserialize: DeserializeFn,
+
+    /// `None` if the kind cannot be created from Python
+    #[cfg(feature = "python")]
+    pub extract: Option<ExtractFn>,
+}
+
+static KINDS: LazyLock<RwLock<Vec<CustomKind>>> =
+    LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Register a custom constraint kind, replacing any existing kind with the
+/// same name.
+///
+/// # Panics
+/// Panics if the registry lock is poisoned.
+pub fn register(kind: CustomKind) {
+    let mut kinds = KINDS.write().expect("custom constraint lock poisoned");
+
+    kinds.retain(|k| k.name != kind.name);
+    kinds.push(kind);
+}
+
+/// The registered kind called `name`.
+///
+/// # Panics
+/// Panics if the registry lock is poisoned.
+#[must_use]
+pub fn lookup(name: &str) -> Option<CustomKind> {
+    KINDS
+        .read()
+        .expect("custom constraint lock poisoned")
+        .iter()
+        .find(|k| k.name == name)
+        .copied()
+}
+
+/// Try each registered kind's extractor on `obj`, in registration order.
+///
+/// # Panics
+/// Panics if the registry lock is poisoned.
+#[cfg(feature = "python")]
+#[must_use]
+pub fn extract(obj: &Bound<'_, PyAny>) -> Option<Custom> {
+    // Copied so the lock is released before calling into Python, which may
+    // register further kinds
+    let kinds = KINDS.read().expect("custom constraint lock poisoned").clone();
+
+    kinds.iter().filter_map(|k| k.extract).find_map(|f| f(obj).ok().map(Custom))
+}
+
+/// A [`CustomConstraint`] stored in a [`Constraint`].
+#[derive(Clone, Debug)]
+pub struct Custom(pub Arc<dyn CustomConstraint>);
+
+impl Custom {
+    #[must_use]
+    pub fn new(constraint: impl CustomConstraint + 'static) -> Self {
+        Self(Arc::new(constraint))
+    }
+}
+
+impl std::fmt::Display for Custom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Repr<Kind> {
+    kind: Kind,
+    data: serde_json::Value,
+}
+
+impl Serialize for Custom {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Repr { kind: self.0.kind(), data: self.0.to_json() }
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Custom {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let repr = Repr::<String>::deserialize(deserializer)?;
+
+        let kind = lookup(&repr.kind).ok_or_else(|| {
+            D::Error::custom(format!(
+                "unknown custom constraint kind '{}'",
+                repr.kind
+            ))
+        })?;
+
+        (kind.deserialize)(repr.data).map(Self).map_err(D::Error::custom)
+    }
+}
+
+impl ConstraintUtils for Custom {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
+        self.0.value_type()
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+        value_type: SpecOptionType,
+    ) {
+        self.0.set_value_type(wip_registry, value_type);
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        self.0.type_check(wip_registry)
+    }
+
+    fn extract_spec_options(&self) -> Vec<(&str, &str, spec::SpecOption)> {
+        self.0.extract_spec_options()
+    }
+
+    fn extract_dependencies(&self) -> HashSet<String> {
+        self.0.extract_dependencies()
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn to_z3_clauses(
+        &self,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
+        self.0.to_z3_clauses(registry)
+    }
+
+    #[cfg(feature = "python")]
+    fn to_python_any<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.0.to_python_any(py)
+    }
+}
+
+impl From<Custom> for Constraint {
+    fn from(val: Custom) -> Self {
+        Self::Custom(val)
+    }
+}
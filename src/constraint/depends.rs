@@ -1,18 +1,29 @@
 use std::collections::HashSet;
 
-use pyo3::{IntoPyObjectExt, basic::CompareOp, prelude::*};
-
-use super::ConstraintUtils;
-use crate::{
-    constraint::{Cmp, Constraint},
-    package::{self, outline::SolverError},
-    spec::SpecOptionType,
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    prelude::*,
+    types::{PyBytes, PyType},
 };
+use serde::{Deserialize, Serialize};
 
-#[pyclass]
-#[derive(Clone, Debug)]
+use super::ConstraintUtils;
+#[cfg(feature = "python")]
+use crate::constraint::Cmp;
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
+use crate::{constraint::Constraint, spec::SpecOptionType};
+
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Depends {
-    #[pyo3(get, set)]
     on: String,
 }
 
@@ -21,27 +32,32 @@ impl Depends {
     pub const fn new(on: String) -> Self {
         Self { on }
     }
+
+    /// The package depended on
+    #[must_use]
+    pub fn on(&self) -> &str {
+        &self.on
+    }
 }
 
 impl ConstraintUtils for Depends {
-    fn get_value_type<'a, V>(
-        &'a self,
-        _registry: Option<&package::registry::Registry<'a, V>>,
-    ) -> Option<SpecOptionType> {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
         Some(SpecOptionType::Bool)
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        _wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
         _value_type: crate::spec::SpecOptionType,
     ) {
         // Nothing to set
     }
 
+    #[cfg(feature = "solver-z3")]
     fn type_check(
         &self,
-        _wip_registry: &mut package::WipRegistry<'_>,
+        _wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         // Nothing to type-check
         Ok(())
@@ -57,16 +73,15 @@ impl ConstraintUtils for Depends {
         HashSet::from([self.on.clone()])
     }
 
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
         let Some(idx) = registry.lookup_option(&self.on, None) else {
             tracing::error!("package '{}' has no activation variable", self.on);
 
-            return Err(Box::new(SolverError::MissingPackage {
-                name: self.on.clone(),
-            }));
+            return Err(Box::new(registry.missing_error(&self.on, None)));
         };
 
         let Some(dynamic) = &registry.spec_options()[idx].1 else {
@@ -81,6 +96,7 @@ impl ConstraintUtils for Depends {
         Ok(vec![dynamic.clone()])
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: Python<'py>,
@@ -97,10 +113,11 @@ impl From<Depends> for Constraint {
 
 impl std::fmt::Display for Depends {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Depends( {} )", self.on)
+        write!(f, "depends({})", self.on)
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl Depends {
     #[new]
@@ -116,4 +133,48 @@ impl Depends {
     ) -> Result<Constraint, PyErr> {
         Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
     }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    fn __invert__(&self) -> PyResult<Constraint> {
+        Cmp::py_invert(self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
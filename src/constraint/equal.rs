@@ -0,0 +1,272 @@
+//! Comparisons of an option with a constant.
+//!
+//! [`Equal`] and [`NotEqual`] are shorthand for the most common [`Cmp`]:
+//! `Equal::new("mpi", "shared", false)` is `option(mpi:shared) == false`.
+//! They are converted to that comparison when used as a [`Constraint`], so
+//! every resolver, the type checker and the constraint syntax treat them
+//! identically, and a comparison of an option with a value of the wrong type
+//! is reported with the option's name (see
+//! [`SolverError::OptionValueType`](crate::package::outline::SolverError::OptionValueType)).
+
+#[cfg(feature = "python")]
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraint::{Cmp, CmpType, Constraint, SpecOption, Value},
+    spec::SpecOptionValue,
+};
+#[cfg(feature = "python")]
+use crate::{
+    interface::pickle, package::version::Version,
+    spec::concrete::VERSION_OPTION,
+};
+
+/// True if an option of a package has a value, such as `mpi:shared` being
+/// `false`. Shorthand for comparing the option with `==`
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Equal {
+    pub option: SpecOption,
+
+    pub value: SpecOptionValue,
+}
+
+impl Equal {
+    #[must_use]
+    pub fn new(
+        package_name: impl Into<String>,
+        option_name: impl Into<String>,
+        value: impl Into<SpecOptionValue>,
+    ) -> Self {
+        Self {
+            option: SpecOption::new(package_name, option_name),
+            value: value.into(),
+        }
+    }
+
+    /// The comparison this is shorthand for
+    #[must_use]
+    pub fn to_cmp(&self) -> Cmp {
+        let value = Value::new(self.value.clone());
+        Cmp::new(self.option.clone(), CmpType::Equal, value)
+    }
+}
+
+impl From<Equal> for Constraint {
+    fn from(val: Equal) -> Self {
+        val.to_cmp().into()
+    }
+}
+
+impl std::fmt::Display for Equal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_cmp())
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Equal {
+    /// * `package`: The package the option belongs to
+    /// * `option`: The name of the option
+    /// * `value`: The value to compare the option with. Strings are
+    ///   parsed as versions if `option` is `"version"`
+    ///
+    /// # Errors
+    /// Errors if `value` is not a valid version for the `version`
+    /// option.
+    #[new]
+    fn py_new(
+        package: String,
+        option: String,
+        value: SpecOptionValue,
+    ) -> PyResult<Self> {
+        let option = SpecOption::new(package, option);
+        let value = py_option_value(&option, value)?;
+        Ok(Self { option, value })
+    }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    /// The opposite comparison
+    fn __invert__(&self) -> NotEqual {
+        NotEqual { option: self.option.clone(), value: self.value.clone() }
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
+
+/// True if an option of a package does not have a value. Shorthand for
+/// comparing the option with `!=`
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NotEqual {
+    pub option: SpecOption,
+
+    pub value: SpecOptionValue,
+}
+
+impl NotEqual {
+    #[must_use]
+    pub fn new(
+        package_name: impl Into<String>,
+        option_name: impl Into<String>,
+        value: impl Into<SpecOptionValue>,
+    ) -> Self {
+        Self {
+            option: SpecOption::new(package_name, option_name),
+            value: value.into(),
+        }
+    }
+
+    /// The comparison this is shorthand for
+    #[must_use]
+    pub fn to_cmp(&self) -> Cmp {
+        let value = Value::new(self.value.clone());
+        Cmp::new(self.option.clone(), CmpType::NotEqual, value)
+    }
+}
+
+impl From<NotEqual> for Constraint {
+    fn from(val: NotEqual) -> Self {
+        val.to_cmp().into()
+    }
+}
+
+impl std::fmt::Display for NotEqual {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_cmp())
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl NotEqual {
+    /// * `package`: The package the option belongs to
+    /// * `option`: The name of the option
+    /// * `value`: The value to compare the option with. Strings are
+    ///   parsed as versions if `option` is `"version"`
+    ///
+    /// # Errors
+    /// Errors if `value` is not a valid version for the `version`
+    /// option.
+    #[new]
+    fn py_new(
+        package: String,
+        option: String,
+        value: SpecOptionValue,
+    ) -> PyResult<Self> {
+        let option = SpecOption::new(package, option);
+        let value = py_option_value(&option, value)?;
+        Ok(Self { option, value })
+    }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    /// The opposite comparison
+    fn __invert__(&self) -> Equal {
+        Equal { option: self.option.clone(), value: self.value.clone() }
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
+
+/// `value`, parsed as a version if it is a string and `option` is the
+/// version of its package.
+#[cfg(feature = "python")]
+pub(super) fn py_option_value(
+    option: &SpecOption,
+    value: SpecOptionValue,
+) -> PyResult<SpecOptionValue> {
+    match value {
+        SpecOptionValue::Str(txt) if option.option_name == VERSION_OPTION => {
+            Version::new(&txt).map(SpecOptionValue::Version).map_err(|e| {
+                PyValueError::new_err(format!("invalid version '{txt}': {e:?}"))
+            })
+        }
+        value => Ok(value),
+    }
+}
@@ -1,44 +1,67 @@
 use std::collections::HashSet;
 
-use pyo3::{IntoPyObjectExt, basic::CompareOp, prelude::*};
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "solver-z3")]
 use z3::SortKind;
 
 use super::ConstraintUtils;
+#[cfg(feature = "python")]
+use crate::constraint::Cmp;
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
 use crate::{
-    constraint::{Cmp, Constraint},
-    package::{self, outline::SolverError},
+    constraint::{Constraint, syntax},
     spec::{self, SpecOptionType},
 };
 
-#[pyclass(unsendable)]
-#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct IfThen {
-    #[pyo3(get, set)]
     pub cond: Constraint,
 
-    #[pyo3(get, set)]
     pub then: Constraint,
 }
 
+impl IfThen {
+    #[must_use]
+    pub fn new(
+        cond: impl Into<Constraint>,
+        then: impl Into<Constraint>,
+    ) -> Self {
+        Self { cond: cond.into(), then: then.into() }
+    }
+}
+
 impl ConstraintUtils for IfThen {
-    fn get_value_type<'a, V>(
-        &'a self,
-        _registry: Option<&package::registry::Registry<'a, V>>,
-    ) -> Option<spec::SpecOptionType> {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
         None
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        _wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
         _value_type: spec::SpecOptionType,
     ) {
         panic!("Cannot set value type of IfThen");
     }
 
-    fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         let Some(value_type) = self.cond.get_value_type(Some(wip_registry))
         else {
@@ -82,10 +105,11 @@ impl ConstraintUtils for IfThen {
             .collect()
     }
 
+    #[cfg(feature = "solver-z3")]
     #[tracing::instrument]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
         tracing::info!("(If '{:?}' then '{:?}')", self.cond, self.then);
 
@@ -115,6 +139,7 @@ impl ConstraintUtils for IfThen {
         Ok(vec![cond.implies(then).into()])
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: Python<'py>,
@@ -131,10 +156,13 @@ impl From<IfThen> for Constraint {
 
 impl std::fmt::Display for IfThen {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "If( {} ) Then [ {} ]", self.cond, self.then)
+        syntax::write_operand(f, &self.then, false)?;
+        f.write_str(" when ")?;
+        syntax::write_operand(f, &self.cond, false)
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl IfThen {
     #[new]
@@ -149,4 +177,28 @@ impl IfThen {
     ) -> Result<Constraint, PyErr> {
         Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
     }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
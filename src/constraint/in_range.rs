@@ -0,0 +1,194 @@
+//! Ranges of option values.
+//!
+//! [`InRange`] is shorthand for bounding an option from both sides, such as
+//! `threads` being between 1 and 64. It is converted to a constraint which
+//! holds when both comparisons do, so resolvers and the type checker need no
+//! special handling, and is written in spec strings as `threads=1:64` (see
+//! [`crate::spec::parse`]).
+
+#[cfg(feature = "python")]
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "python")]
+use crate::{constraint::equal::py_option_value, interface::pickle};
+use crate::{
+    constraint::{Cmp, CmpType, Constraint, NumOf, SpecOption, Value},
+    spec::SpecOptionValue,
+};
+
+/// True if an option of a package is between `min` and `max`, including
+/// them if `inclusive`
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InRange {
+    pub option: SpecOption,
+
+    pub min: SpecOptionValue,
+
+    pub max: SpecOptionValue,
+
+    /// Whether the option may be equal to `min` and `max`
+    pub inclusive: bool,
+}
+
+impl InRange {
+    #[must_use]
+    pub fn new(
+        package_name: impl Into<String>,
+        option_name: impl Into<String>,
+        min: impl Into<SpecOptionValue>,
+        max: impl Into<SpecOptionValue>,
+        inclusive: bool,
+    ) -> Self {
+        Self {
+            option: SpecOption::new(package_name, option_name),
+            min: min.into(),
+            max: max.into(),
+            inclusive,
+        }
+    }
+
+    /// The comparisons of the option with `min` and `max`
+    #[must_use]
+    pub fn bounds(&self) -> [Cmp; 2] {
+        let (lower, upper) = if self.inclusive {
+            (CmpType::GreaterOrEqual, CmpType::LessOrEqual)
+        } else {
+            (CmpType::Greater, CmpType::Less)
+        };
+
+        let bound = |op, value: &SpecOptionValue| {
+            Cmp::new(self.option.clone(), op, Value::new(value.clone()))
+        };
+
+        [bound(lower, &self.min), bound(upper, &self.max)]
+    }
+
+    /// The constraint this is shorthand for, which holds when both
+    /// [`Self::bounds`] do
+    #[must_use]
+    pub fn to_constraint(&self) -> Constraint {
+        let bounds = self.bounds().map(Constraint::from);
+        let all = Value::new(2_i64);
+
+        Cmp::new(NumOf::new(bounds), CmpType::Equal, all).into()
+    }
+
+    /// Whether `min` and `max` can bound an option: they must be of the same
+    /// type, which must be ordered.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        let dtype = self.min.to_type();
+
+        dtype == self.max.to_type() && Cmp::can_cmp(dtype, CmpType::LessOrEqual)
+    }
+}
+
+impl From<InRange> for Constraint {
+    fn from(val: InRange) -> Self {
+        val.to_constraint()
+    }
+}
+
+impl std::fmt::Display for InRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_constraint())
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl InRange {
+    /// * `package`: The package the option belongs to
+    /// * `option`: The name of the option
+    /// * `min`, `max`: The bounds of the option. Strings are parsed as
+    ///   versions if `option` is `"version"`
+    /// * `inclusive`: Whether the option may be equal to the bounds
+    ///
+    /// # Errors
+    /// Errors if the bounds are of different or unordered types, or are not
+    /// valid versions for the `version` option.
+    #[new]
+    #[pyo3(signature = (package, option, min, max, inclusive = true))]
+    fn py_new(
+        package: String,
+        option: String,
+        min: SpecOptionValue,
+        max: SpecOptionValue,
+        inclusive: bool,
+    ) -> PyResult<Self> {
+        let option = SpecOption::new(package, option);
+
+        let res = Self {
+            min: py_option_value(&option, min)?,
+            max: py_option_value(&option, max)?,
+            option,
+            inclusive,
+        };
+
+        if res.is_valid() {
+            Ok(res)
+        } else {
+            Err(PyValueError::new_err(format!(
+                "cannot bound '{}' by {} ({}) and {} ({})",
+                res.option,
+                res.min,
+                res.min.to_type(),
+                res.max,
+                res.max.to_type()
+            )))
+        }
+    }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    fn __invert__(&self) -> PyResult<Constraint> {
+        Cmp::py_invert(self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
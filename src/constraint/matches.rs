@@ -0,0 +1,349 @@
+//! Patterns over string options.
+//!
+//! [`Matches`] holds if a string option matches a regular expression, so
+//! packages can constrain free-form options such as a comma-separated list of
+//! fabrics:
+//!
+//! ```text
+//! matches(option(mpi:fabrics), "(ofi|ucx)(,(ofi|ucx))*")
+//! ```
+//!
+//! The whole value must match, so patterns need no anchors. Patterns are
+//! written in the syntax of the `regex` crate, limited to what z3's regular
+//! expressions over strings can express: literals, classes, alternation,
+//! grouping and repetition. Anchors, word boundaries and non-ASCII literals
+//! are rejected, and classes such as `.` only match ASCII characters in the
+//! solver.
+
+use std::collections::HashSet;
+
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use regex_syntax::hir::{Hir, HirKind};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
+#[cfg(feature = "python")]
+use crate::{constraint::Cmp, interface::pickle};
+use crate::{
+    constraint::{Constraint, ConstraintUtils, SpecOption},
+    spec::{self, SpecOptionType},
+};
+
+/// True if a string option of a package matches `pattern`
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Matches {
+    pub option: SpecOption,
+
+    /// A regular expression the whole value must match
+    pub pattern: String,
+}
+
+impl Matches {
+    #[must_use]
+    pub fn new(
+        package_name: impl Into<String>,
+        option_name: impl Into<String>,
+        pattern: impl Into<String>,
+    ) -> Self {
+        Self {
+            option: SpecOption::new(package_name, option_name),
+            pattern: pattern.into(),
+        }
+    }
+
+    /// Parse the pattern.
+    ///
+    /// # Errors
+    /// Errors if the pattern is not a valid regular expression, or uses a
+    /// feature which is not supported (see [`crate::constraint::matches`]).
+    pub fn parse_pattern(pattern: &str) -> Result<Hir, String> {
+        let hir = regex_syntax::Parser::new()
+            .parse(pattern)
+            .map_err(|e| e.to_string())?;
+
+        check_supported(&hir)?;
+
+        Ok(hir)
+    }
+
+    /// Whether the whole of `value` matches the pattern, or `None` if the
+    /// pattern is invalid
+    #[must_use]
+    pub fn is_match(&self, value: &str) -> Option<bool> {
+        Self::parse_pattern(&self.pattern).ok()?;
+
+        regex::Regex::new(&format!("^(?:{})$", self.pattern))
+            .ok()
+            .map(|re| re.is_match(value))
+    }
+}
+
+/// Errors if `hir` uses a feature which cannot be converted to z3
+fn check_supported(hir: &Hir) -> Result<(), String> {
+    match hir.kind() {
+        HirKind::Look(_) => Err("anchors and word boundaries are not \
+                                 supported; the whole value must match"
+            .to_string()),
+        HirKind::Literal(lit) if !lit.0.is_ascii() => {
+            Err("only ASCII characters are supported".to_string())
+        }
+        HirKind::Repetition(rep) => check_supported(&rep.sub),
+        HirKind::Capture(capture) => check_supported(&capture.sub),
+        HirKind::Concat(subs) | HirKind::Alternation(subs) => {
+            subs.iter().try_for_each(check_supported)
+        }
+        HirKind::Empty | HirKind::Literal(_) | HirKind::Class(_) => Ok(()),
+    }
+}
+
+/// The z3 regular expression matching the same ASCII strings as `hir`, which
+/// has been checked by [`check_supported`]
+#[cfg(feature = "solver-z3")]
+fn to_regexp(hir: &Hir) -> z3::ast::Regexp {
+    use std::fmt::Write;
+
+    use regex_syntax::hir::Class;
+    use z3::ast::Regexp;
+
+    // Z3 strings cannot contain NUL, and non-ASCII characters are not
+    // encoded consistently, so classes are clamped to these
+    const FIRST: u32 = 0x01;
+    const LAST: u32 = 0x7F;
+
+    let range = |start: u32, end: u32| {
+        let start = char::from_u32(start.max(FIRST))?;
+        let end = char::from_u32(end.min(LAST))?;
+        (start <= end).then(|| Regexp::range(&start, &end))
+    };
+
+    match hir.kind() {
+        HirKind::Empty => Regexp::literal(""),
+        HirKind::Literal(lit) => {
+            // Escape everything but letters and digits, so z3 does not read
+            // them as escapes
+            let txt = lit.0.iter().fold(String::new(), |mut txt, &b| {
+                if b.is_ascii_alphanumeric() {
+                    txt.push(char::from(b));
+                } else {
+                    let _ = write!(txt, "\\u{{{b:x}}}");
+                }
+                txt
+            });
+
+            Regexp::literal(&txt)
+        }
+        HirKind::Class(class) => {
+            let ranges = match class {
+                Class::Unicode(class) => class
+                    .ranges()
+                    .iter()
+                    .filter_map(|r| range(r.start().into(), r.end().into()))
+                    .collect::<Vec<_>>(),
+                Class::Bytes(class) => class
+                    .ranges()
+                    .iter()
+                    .filter_map(|r| range(r.start().into(), r.end().into()))
+                    .collect::<Vec<_>>(),
+            };
+
+            if ranges.is_empty() {
+                Regexp::empty()
+            } else {
+                Regexp::union(&ranges)
+            }
+        }
+        HirKind::Look(_) => unreachable!("rejected by check_supported"),
+        HirKind::Repetition(rep) => {
+            let sub = to_regexp(&rep.sub);
+
+            match (rep.min, rep.max) {
+                (0, None) => sub.star(),
+                (1, None) => sub.plus(),
+                (0, Some(1)) => sub.option(),
+                (min, Some(max)) => sub.r#loop(min, max),
+                (min, None) => {
+                    Regexp::concat(&[sub.r#loop(min, min), sub.star()])
+                }
+            }
+        }
+        HirKind::Capture(capture) => to_regexp(&capture.sub),
+        HirKind::Concat(subs) => {
+            Regexp::concat(&subs.iter().map(to_regexp).collect::<Vec<_>>())
+        }
+        HirKind::Alternation(subs) => {
+            Regexp::union(&subs.iter().map(to_regexp).collect::<Vec<_>>())
+        }
+    }
+}
+
+impl ConstraintUtils for Matches {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
+        Some(SpecOptionType::Bool)
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
+        value_type: SpecOptionType,
+    ) {
+        assert_eq!(
+            value_type,
+            SpecOptionType::Bool,
+            "Matches constraint always returns a Boolean result"
+        );
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        if let Err(e) = Self::parse_pattern(&self.pattern) {
+            let msg = format!("invalid pattern in '{self}': {e}");
+            tracing::error!("{msg}");
+            return Err(Box::new(SolverError::InvalidConstraint(msg)));
+        }
+
+        match self.option.get_value_type(Some(wip_registry)) {
+            Some(SpecOptionType::Str) => Ok(()),
+            Some(SpecOptionType::Unknown) => {
+                self.option.set_value_type(wip_registry, SpecOptionType::Str);
+                Ok(())
+            }
+            Some(received) => Err(Box::new(SolverError::IncorrectValueType {
+                expected: SpecOptionType::Str,
+                received,
+            })),
+            None => Err(Box::new(SolverError::InvalidNonValueConstraint)),
+        }
+    }
+
+    fn extract_spec_options(&self) -> Vec<(&str, &str, spec::SpecOption)> {
+        self.option.extract_spec_options()
+    }
+
+    fn extract_dependencies(&self) -> HashSet<String> {
+        HashSet::default()
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn to_z3_clauses(
+        &self,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
+        let hir = Self::parse_pattern(&self.pattern)
+            .map_err(|e| Box::new(SolverError::InvalidConstraint(e)))?;
+
+        let clauses = self.option.to_z3_clauses(registry)?;
+
+        let [value] = clauses.as_slice() else {
+            return Err(Box::new(SolverError::InvalidNumberOfClauses(
+                clauses.len(),
+            )));
+        };
+
+        // Safe to unwrap since the option has been type checked
+        let value = value.as_string().unwrap();
+
+        Ok(vec![value.regex_matches(&to_regexp(&hir)).into()])
+    }
+
+    #[cfg(feature = "python")]
+    fn to_python_any<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.clone().into_bound_py_any(py)
+    }
+}
+
+impl From<Matches> for Constraint {
+    fn from(val: Matches) -> Self {
+        Self::Matches(Box::new(val))
+    }
+}
+
+impl std::fmt::Display for Matches {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "matches({}, {:?})", self.option, self.pattern)
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Matches {
+    /// * `package`: The package the option belongs to
+    /// * `option`: The name of the option, which must be a string
+    /// * `pattern`: A regular expression the whole value must match
+    ///
+    /// # Errors
+    /// Errors if `pattern` is invalid or unsupported.
+    #[new]
+    fn py_new(
+        package: String,
+        option: String,
+        pattern: String,
+    ) -> PyResult<Self> {
+        Self::parse_pattern(&pattern).map_err(|e| {
+            PyValueError::new_err(format!("invalid pattern '{pattern}': {e}"))
+        })?;
+
+        Ok(Self::new(package, option, pattern))
+    }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    fn __invert__(&self) -> PyResult<Constraint> {
+        Cmp::py_invert(self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
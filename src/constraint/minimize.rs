@@ -1,42 +1,63 @@
 use std::collections::HashSet;
 
-use pyo3::{IntoPyObjectExt, basic::CompareOp, prelude::*};
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "solver-z3")]
 use z3::{Optimize, SortKind, ast::Bool};
 
 use super::ConstraintUtils;
+#[cfg(feature = "python")]
+use crate::constraint::Cmp;
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, BuiltRegistry, outline::SolverError};
 use crate::{
-    constraint::{Cmp, Constraint},
-    package::{self, BuiltRegistry, outline::SolverError},
+    constraint::Constraint,
     spec::{SpecOption, SpecOptionType},
 };
 
-#[pyclass]
-#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Minimize {
-    #[pyo3(get, set)]
     pub item: Constraint,
 }
 
+impl Minimize {
+    #[must_use]
+    pub fn new(item: impl Into<Constraint>) -> Self {
+        Self { item: item.into() }
+    }
+}
+
 impl ConstraintUtils for Minimize {
-    fn get_value_type<'a, V>(
-        &'a self,
-        _registry: Option<&package::registry::Registry<'a, V>>,
-    ) -> Option<crate::spec::SpecOptionType> {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
         None
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        wip_registry: &mut package::WipRegistry,
         value_type: crate::spec::SpecOptionType,
     ) {
         self.item.set_value_type(wip_registry, value_type);
     }
 
+    #[cfg(feature = "solver-z3")]
     #[tracing::instrument(skip(wip_registry))]
-    fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         self.item.type_check(wip_registry)?;
 
@@ -71,20 +92,22 @@ impl ConstraintUtils for Minimize {
         self.item.extract_dependencies()
     }
 
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        _registry: &mut package::BuiltRegistry<'_>,
+        _registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
         let msg = "cannot convert Minimize constraint into Z3 clause";
         tracing::error!(msg);
         Err(Box::new(SolverError::InvalidConstraint(msg.to_string())))
     }
 
+    #[cfg(feature = "solver-z3")]
     fn add_to_solver(
         &self,
         _toggle: &Bool,
         optimizer: &Optimize,
-        registry: &mut BuiltRegistry<'_>,
+        registry: &mut BuiltRegistry,
     ) -> Result<(), Box<SolverError>> {
         for item in self.item.to_z3_clauses(registry)? {
             if matches!(
@@ -98,6 +121,7 @@ impl ConstraintUtils for Minimize {
         Ok(())
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: Python<'py>,
@@ -114,10 +138,11 @@ impl From<Minimize> for Constraint {
 
 impl std::fmt::Display for Minimize {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Minimize( {} )", self.item)
+        write!(f, "minimize({})", self.item)
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl Minimize {
     #[new]
@@ -132,4 +157,28 @@ impl Minimize {
     ) -> Result<Constraint, PyErr> {
         Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
     }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
@@ -1,49 +1,78 @@
 use std::collections::HashSet;
 
+#[cfg(feature = "python")]
 use pyo3::{exceptions::PyTypeError, prelude::*};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "solver-z3")]
 use z3::{Optimize, ast::Bool};
 
-use crate::{
-    package::{
-        self, BuiltRegistry,
-        outline::SolverError,
-        registry::{BuiltVersionRegistry, Registry},
-        version::Version,
-    },
-    spec::{self, SpecOptionType, SpecOptionValue},
+#[cfg(feature = "solver-z3")]
+use crate::package::{
+    self, BuiltRegistry,
+    outline::SolverError,
+    registry::Registry,
+    resolver::{Conflict, ConflictKind},
 };
+use crate::spec::{self, SpecOptionType};
+#[cfg(feature = "python")]
+use crate::{package::version::Version, spec::SpecOptionValue};
 
 pub const SOFT_PACKAGE_WEIGHT: usize = 1;
+pub const SOFT_TARGET_WEIGHT: usize = 1;
 
+mod arith;
 mod cmp;
+pub mod custom;
 mod depends;
+mod equal;
 mod if_then;
+mod in_range;
+mod matches;
 mod maximize;
 mod minimize;
+mod normalize;
 mod num_of;
+mod optional_depends;
+mod prefer;
 mod spec_option;
+pub mod syntax;
 mod value;
+mod when_platform;
 
+pub use arith::{Arith, ArithOp};
 pub use cmp::{Cmp, CmpType};
+pub use custom::{Custom, CustomConstraint};
 pub use depends::Depends;
+pub use equal::{Equal, NotEqual};
 pub use if_then::IfThen;
+pub use in_range::InRange;
+pub use matches::Matches;
 pub use maximize::Maximize;
 pub use minimize::Minimize;
+pub use normalize::{Normalized, normalize};
 pub use num_of::NumOf;
+pub use optional_depends::OptionalDepends;
+pub use prefer::Prefer;
 pub use spec_option::SpecOption;
 pub use value::Value;
+pub use when_platform::WhenPlatform;
 
 macro_rules! constraint_inner {
     ($constraint:ident, $inner:ident => $code:block) => {
         match $constraint {
+            Constraint::Arith($inner) => $code,
             Constraint::Cmp($inner) => $code,
+            Constraint::Custom($inner) => $code,
             Constraint::Depends($inner) => $code,
             Constraint::IfThen($inner) => $code,
+            Constraint::Matches($inner) => $code,
             Constraint::Maximize($inner) => $code,
             Constraint::Minimize($inner) => $code,
             Constraint::NumOf($inner) => $code,
+            Constraint::Prefer($inner) => $code,
             Constraint::SpecOption($inner) => $code,
             Constraint::Value($inner) => $code,
+            Constraint::WhenPlatform($inner) => $code,
         }
     };
 }
@@ -51,18 +80,24 @@ macro_rules! constraint_inner {
 pub trait ConstraintUtils:
     Send + Sync + std::fmt::Debug + std::fmt::Display + Into<Constraint>
 {
-    fn get_value_type<'a, V>(
-        &'a self,
-        registry: Option<&Registry<'a, V>>,
-    ) -> Option<SpecOptionType>;
-
-    fn get_value_type_default(&self) -> Option<SpecOptionType> {
-        self.get_value_type::<BuiltVersionRegistry>(None)
+    /// The type of value this constraint evaluates to, as far as it is known
+    /// without a registry. Option types are [`SpecOptionType::Unknown`]
+    fn get_value_type_default(&self) -> Option<SpecOptionType>;
+
+    /// [`Self::get_value_type_default`], looking up option types in
+    /// `registry` if given.
+    #[cfg(feature = "solver-z3")]
+    fn get_value_type<V>(
+        &self,
+        _registry: Option<&Registry<V>>,
+    ) -> Option<SpecOptionType> {
+        self.get_value_type_default()
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        wip_registry: &mut package::WipRegistry,
         value_type: SpecOptionType,
     );
 
@@ -70,9 +105,10 @@ pub trait ConstraintUtils:
     ///
     /// # Errors
     /// Errors if the constraint variable types are invalid.
-    fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>>;
 
     fn extract_spec_options(&self) -> Vec<(&str, &str, spec::SpecOption)>;
@@ -84,12 +120,13 @@ pub trait ConstraintUtils:
     /// # Errors
     /// Errors if the [`Constraint`] inputs cannot be compared or converted to
     /// Z3 clauses.
+    #[cfg(feature = "solver-z3")]
     #[tracing::instrument]
     fn cmp_to_z3(
         &self,
         other: &Constraint,
         op: CmpType,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<z3::ast::Dynamic, Box<SolverError>> {
         let s = self.to_z3_clauses(registry)?;
         let o = other.to_z3_clauses(registry)?;
@@ -136,46 +173,75 @@ pub trait ConstraintUtils:
         }
     }
 
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>>;
 
+    #[cfg(feature = "solver-z3")]
     fn add_to_solver(
         &self,
         toggle: &Bool,
         optimizer: &Optimize,
-        registry: &mut BuiltRegistry<'_>,
+        registry: &mut BuiltRegistry,
     ) -> Result<(), Box<SolverError>> {
-        for clause in self.to_z3_clauses(registry)? {
-            let assertion = toggle.implies(clause.as_bool().unwrap());
-
-            let boolean = z3::ast::Bool::new_const(
-                registry.new_constraint_id(self.to_string()),
-            );
-
-            optimizer.assert_and_track(&assertion, &boolean);
-        }
+        let clauses = self.to_z3_clauses(registry)?;
+        assert_clauses(self, clauses, toggle, optimizer, registry);
 
         Ok(())
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: Python<'py>,
     ) -> PyResult<Bound<'py, PyAny>>;
 }
 
-#[derive(Debug, Clone)]
+/// Assert that each of `clauses` holds if `toggle` does, tracked with the
+/// description of `constraint`
+#[cfg(feature = "solver-z3")]
+fn assert_clauses(
+    constraint: &impl std::fmt::Display,
+    clauses: Vec<z3::ast::Dynamic>,
+    toggle: &Bool,
+    optimizer: &Optimize,
+    registry: &mut BuiltRegistry,
+) {
+    for clause in clauses {
+        let assertion = toggle.implies(clause.as_bool().unwrap());
+
+        let boolean = z3::ast::Bool::new_const(registry.new_constraint_id(
+            Conflict::new(
+                ConflictKind::Constraint,
+                &[],
+                constraint.to_string(),
+            ),
+        ));
+
+        optimizer.assert_and_track(&assertion, &boolean);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum Constraint {
+    Arith(Box<Arith>),
     Cmp(Box<Cmp>),
     Depends(Box<Depends>),
     IfThen(Box<IfThen>),
+    Matches(Box<Matches>),
     Maximize(Box<Maximize>),
     Minimize(Box<Minimize>),
     NumOf(Box<NumOf>),
+    Prefer(Box<Prefer>),
     SpecOption(Box<SpecOption>),
     Value(Box<Value>),
+    WhenPlatform(Box<WhenPlatform>),
+
+    /// A constraint kind defined outside zpack. See [`custom`]
+    Custom(Custom),
 }
 
 impl std::fmt::Display for Constraint {
@@ -184,19 +250,96 @@ impl std::fmt::Display for Constraint {
     }
 }
 
+impl Constraint {
+    /// Replace every reference to an option of the package `from` with the
+    /// same option of `to`. Dependencies are not changed.
+    pub fn rename_package(&mut self, from: &str, to: &str) {
+        match self {
+            Self::SpecOption(opt) => {
+                if opt.package_name == from {
+                    opt.package_name = to.to_string();
+                }
+            }
+            Self::Matches(matches) => {
+                if matches.option.package_name == from {
+                    matches.option.package_name = to.to_string();
+                }
+            }
+            Self::Arith(arith) => {
+                arith.lhs.rename_package(from, to);
+                arith.rhs.rename_package(from, to);
+            }
+            Self::Cmp(cmp) => {
+                cmp.lhs.rename_package(from, to);
+                cmp.rhs.rename_package(from, to);
+            }
+            Self::IfThen(if_then) => {
+                if_then.cond.rename_package(from, to);
+                if_then.then.rename_package(from, to);
+            }
+            Self::NumOf(num_of) => {
+                for c in &mut num_of.of {
+                    c.rename_package(from, to);
+                }
+            }
+            Self::Custom(custom) => {
+                if let Some(renamed) = custom.0.rename_package(from, to) {
+                    *custom = Custom(renamed);
+                }
+            }
+            Self::Maximize(m) => m.item.rename_package(from, to),
+            Self::Minimize(m) => m.item.rename_package(from, to),
+            Self::Prefer(p) => p.item.rename_package(from, to),
+            Self::Depends(_) | Self::Value(_) | Self::WhenPlatform(_) => (),
+        }
+    }
+
+    /// Whether this constraint, or any constraint within it, is [`Custom`]
+    #[must_use]
+    pub fn contains_custom(&self) -> bool {
+        match self {
+            Self::Custom(_) => true,
+            Self::Arith(arith) => {
+                arith.lhs.contains_custom() || arith.rhs.contains_custom()
+            }
+            Self::Cmp(cmp) => {
+                cmp.lhs.contains_custom() || cmp.rhs.contains_custom()
+            }
+            Self::IfThen(if_then) => {
+                if_then.cond.contains_custom() || if_then.then.contains_custom()
+            }
+            Self::NumOf(num_of) => num_of.of.iter().any(Self::contains_custom),
+            Self::Maximize(m) => m.item.contains_custom(),
+            Self::Minimize(m) => m.item.contains_custom(),
+            Self::Prefer(p) => p.item.contains_custom(),
+            Self::Depends(_)
+            | Self::Matches(_)
+            | Self::SpecOption(_)
+            | Self::Value(_)
+            | Self::WhenPlatform(_) => false,
+        }
+    }
+}
+
 impl ConstraintUtils for Constraint {
-    fn get_value_type<'a, V>(
-        &'a self,
-        registry: Option<&Registry<'a, V>>,
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
+        constraint_inner!(self, inner => { inner.get_value_type_default() })
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn get_value_type<V>(
+        &self,
+        registry: Option<&Registry<V>>,
     ) -> Option<SpecOptionType> {
         constraint_inner!(self, inner => {
             inner.get_value_type(registry)
         })
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        wip_registry: &mut package::WipRegistry,
         value_type: SpecOptionType,
     ) {
         constraint_inner!(self, inner => {
@@ -204,9 +347,10 @@ impl ConstraintUtils for Constraint {
         });
     }
 
-    fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         constraint_inner!(self, inner => { inner.type_check(wip_registry)})
     }
@@ -219,35 +363,75 @@ impl ConstraintUtils for Constraint {
         constraint_inner!(self, inner => { inner.extract_dependencies()})
     }
 
+    #[cfg(feature = "solver-z3")]
     fn cmp_to_z3(
         &self,
         other: &Constraint,
         op: CmpType,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<z3::ast::Dynamic, Box<SolverError>> {
         constraint_inner!(self, inner => {
             inner.cmp_to_z3(other, op, registry)
         })
     }
 
+    /// Built-in constraints are converted once per registry and the clauses
+    /// reused, keyed by their canonical syntax. Custom constraints may not
+    /// display uniquely, so are always converted.
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
-        constraint_inner!(self, inner => { inner.to_z3_clauses(registry)})
+        if self.contains_custom() {
+            return constraint_inner!(self, inner => {
+                inner.to_z3_clauses(registry)
+            });
+        }
+
+        let key = self.to_string();
+
+        if let Some(clauses) = registry.cached_clauses(&key) {
+            return Ok(clauses.to_vec());
+        }
+
+        let clauses = constraint_inner!(self, inner => {
+            inner.to_z3_clauses(registry)
+        })?;
+
+        registry.cache_clauses(key, clauses.clone());
+
+        Ok(clauses)
     }
 
+    #[cfg(feature = "solver-z3")]
     fn add_to_solver(
         &self,
         toggle: &Bool,
         optimizer: &Optimize,
-        registry: &mut BuiltRegistry<'_>,
+        registry: &mut BuiltRegistry,
     ) -> Result<(), Box<SolverError>> {
-        constraint_inner!(self, inner => {
-            inner.add_to_solver(toggle, optimizer, registry)
-        })
+        match self {
+            // Objectives and preferences are not clauses, and custom
+            // constraints may add themselves differently
+            Self::Maximize(_)
+            | Self::Minimize(_)
+            | Self::Prefer(_)
+            | Self::Custom(_) => {
+                constraint_inner!(self, inner => {
+                    inner.add_to_solver(toggle, optimizer, registry)
+                })
+            }
+            _ => {
+                let clauses = self.to_z3_clauses(registry)?;
+                assert_clauses(self, clauses, toggle, optimizer, registry);
+
+                Ok(())
+            }
+        }
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: Python<'py>,
@@ -256,6 +440,7 @@ impl ConstraintUtils for Constraint {
     }
 }
 
+#[cfg(feature = "python")]
 impl<'a, 'py> FromPyObject<'a, 'py> for Constraint {
     type Error = PyErr;
 
@@ -286,13 +471,17 @@ impl<'a, 'py> FromPyObject<'a, 'py> for Constraint {
             })))
         }
 
-        extract_constraint::<Cmp, _, _>(&obj, Constraint::Cmp)
+        extract_constraint::<Arith, _, _>(&obj, Constraint::Arith)
+            .or_else(|_| extract_constraint::<Cmp, _, _>(&obj, Constraint::Cmp))
             .or_else(|_| {
                 extract_constraint::<Depends, _, _>(&obj, Constraint::Depends)
             })
             .or_else(|_| {
                 extract_constraint::<IfThen, _, _>(&obj, Constraint::IfThen)
             })
+            .or_else(|_| {
+                extract_constraint::<Matches, _, _>(&obj, Constraint::Matches)
+            })
             .or_else(|_| {
                 extract_constraint::<Maximize, _, _>(&obj, Constraint::Maximize)
             })
@@ -302,6 +491,9 @@ impl<'a, 'py> FromPyObject<'a, 'py> for Constraint {
             .or_else(|_| {
                 extract_constraint::<NumOf, _, _>(&obj, Constraint::NumOf)
             })
+            .or_else(|_| {
+                extract_constraint::<Prefer, _, _>(&obj, Constraint::Prefer)
+            })
             .or_else(|_| {
                 extract_constraint::<SpecOption, _, _>(
                     &obj,
@@ -312,6 +504,19 @@ impl<'a, 'py> FromPyObject<'a, 'py> for Constraint {
                 extract_constraint::<Value, _, _>(&obj, Constraint::Value)
             })
             .or_else(|_| {
+                extract_constraint::<WhenPlatform, _, _>(
+                    &obj,
+                    Constraint::WhenPlatform,
+                )
+            })
+            .or_else(|_| obj.extract::<Equal>().map(Self::from))
+            .or_else(|_| obj.extract::<NotEqual>().map(Self::from))
+            .or_else(|_| obj.extract::<InRange>().map(Self::from))
+            .or_else(|_| obj.extract::<OptionalDepends>().map(Self::from))
+            .or_else(|_| {
+                custom::extract(&obj).map(Constraint::Custom).ok_or(())
+            })
+            .or_else(|()| {
                 extract_value::<bool, _, _>(&obj, SpecOptionValue::Bool)
             })
             .or_else(|_| extract_value::<i64, _, _>(&obj, SpecOptionValue::Int))
@@ -336,6 +541,7 @@ impl<'a, 'py> FromPyObject<'a, 'py> for Constraint {
     }
 }
 
+#[cfg(feature = "python")]
 impl<'py> IntoPyObject<'py> for Constraint {
     type Target = PyAny;
     type Output = Bound<'py, Self::Target>;
@@ -346,14 +552,19 @@ impl<'py> IntoPyObject<'py> for Constraint {
         py: Python<'py>,
     ) -> Result<Self::Output, Self::Error> {
         match self {
+            Self::Arith(val) => val.to_python_any(py),
             Self::Cmp(val) => val.to_python_any(py),
             Self::Depends(val) => val.to_python_any(py),
             Self::IfThen(val) => val.to_python_any(py),
+            Self::Matches(val) => val.to_python_any(py),
             Self::Maximize(val) => val.to_python_any(py),
             Self::Minimize(val) => val.to_python_any(py),
             Self::NumOf(val) => val.to_python_any(py),
+            Self::Prefer(val) => val.to_python_any(py),
             Self::SpecOption(val) => val.to_python_any(py),
             Self::Value(val) => val.to_python_any(py),
+            Self::WhenPlatform(val) => val.to_python_any(py),
+            Self::Custom(val) => val.to_python_any(py),
         }
     }
 }
@@ -0,0 +1,186 @@
+//! Normalization of package constraints before they are grounded.
+//!
+//! Repositories repeat themselves: many packages declare the same constraint,
+//! and templates generate constraints which are trivially true. Each
+//! constraint is first simplified (see [`Constraint::simplify`]), then
+//! constraints which are always true are dropped and identical constraints are
+//! merged, so each is only asserted once, guarded by every package which
+//! declares it.
+//!
+//! Constraints are identified by their canonical syntax. [`Custom`]
+//! constraints may not display uniquely, so are never merged.
+//!
+//! [`Custom`]: super::Custom
+
+use std::collections::{HashMap, hash_map::Entry};
+
+use crate::{
+    constraint::{Constraint, Value},
+    spec::{SpecOptionValue, eval},
+};
+
+/// A normalized constraint and the packages which declare it. It must hold
+/// if any of them is active.
+#[derive(Clone, Debug)]
+pub struct Normalized<'a> {
+    pub constraint: Constraint,
+    pub packages: Vec<&'a str>,
+}
+
+/// The key identifying `constraint`, or `None` if it cannot be identified
+fn key(constraint: &Constraint) -> Option<String> {
+    (!constraint.contains_custom()).then(|| constraint.to_string())
+}
+
+impl Constraint {
+    /// The value of this constraint, if it is a boolean constant
+    const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Value(value) => match value.value {
+                SpecOptionValue::Bool(b) => Some(b),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// An equivalent constraint, where:
+    /// - comparisons and arithmetic between two values are replaced by their
+    ///   result
+    /// - `then when true` is replaced by `then`
+    /// - `then when false` and `true when cond` are replaced by `true`
+    /// - `(then when cond) when cond` is replaced by `then when cond`
+    ///
+    /// Values which cannot be compared are left for the type checker to
+    /// report. Custom constraints are left as they are.
+    #[must_use]
+    pub fn simplify(self) -> Self {
+        match self {
+            Self::Arith(mut arith) => {
+                arith.lhs = arith.lhs.simplify();
+                arith.rhs = arith.rhs.simplify();
+
+                if let (Self::Value(lhs), Self::Value(rhs)) =
+                    (&arith.lhs, &arith.rhs)
+                    && let Some(res) =
+                        eval::arith(&lhs.value, arith.op, &rhs.value)
+                {
+                    return Value::new(res).into();
+                }
+
+                Self::Arith(arith)
+            }
+
+            Self::Cmp(mut cmp) => {
+                cmp.lhs = cmp.lhs.simplify();
+                cmp.rhs = cmp.rhs.simplify();
+
+                if let (Self::Value(lhs), Self::Value(rhs)) =
+                    (&cmp.lhs, &cmp.rhs)
+                    && let Some(holds) =
+                        eval::compare(&lhs.value, cmp.op, &rhs.value)
+                {
+                    return Value::new(holds).into();
+                }
+
+                Self::Cmp(cmp)
+            }
+
+            Self::IfThen(mut if_then) => {
+                if_then.cond = if_then.cond.simplify();
+                if_then.then = if_then.then.simplify();
+
+                match (if_then.cond.as_bool(), if_then.then.as_bool()) {
+                    (Some(true), _) => return if_then.then,
+                    (Some(false), _) | (_, Some(true)) => {
+                        return Value::new(true).into();
+                    }
+                    _ => (),
+                }
+
+                if let Self::IfThen(inner) = &if_then.then
+                    && key(&inner.cond).is_some_and(|inner| {
+                        key(&if_then.cond).is_some_and(|cond| inner == cond)
+                    })
+                {
+                    if_then.then = inner.then.clone();
+                }
+
+                Self::IfThen(if_then)
+            }
+
+            Self::NumOf(mut num_of) => {
+                num_of.of = num_of.of.into_iter().map(Self::simplify).collect();
+                Self::NumOf(num_of)
+            }
+
+            Self::Maximize(mut m) => {
+                m.item = m.item.simplify();
+                Self::Maximize(m)
+            }
+
+            Self::Minimize(mut m) => {
+                m.item = m.item.simplify();
+                Self::Minimize(m)
+            }
+
+            Self::Prefer(mut p) => {
+                p.item = p.item.simplify();
+                Self::Prefer(p)
+            }
+
+            Self::Custom(_)
+            | Self::Depends(_)
+            | Self::Matches(_)
+            | Self::SpecOption(_)
+            | Self::Value(_)
+            | Self::WhenPlatform(_) => self,
+        }
+    }
+}
+
+/// Simplify each of `constraints`, declared by the package with the paired
+/// name, drop those which always hold and merge those which are identical.
+///
+/// Constraints are returned in the order they are first declared.
+pub fn normalize<'a>(
+    constraints: impl IntoIterator<Item = (&'a str, &'a Constraint)>,
+) -> Vec<Normalized<'a>> {
+    let mut res: Vec<Normalized<'a>> = Vec::new();
+    let mut seen = HashMap::<String, usize>::new();
+    let mut total = 0;
+
+    for (package, constraint) in constraints {
+        total += 1;
+
+        let constraint = constraint.clone().simplify();
+
+        if constraint.as_bool() == Some(true) {
+            tracing::debug!("dropping constraint of {package} which holds");
+            continue;
+        }
+
+        let Some(key) = key(&constraint) else {
+            res.push(Normalized { constraint, packages: vec![package] });
+            continue;
+        };
+
+        match seen.entry(key) {
+            Entry::Occupied(idx) => {
+                let packages = &mut res[*idx.get()].packages;
+
+                if !packages.contains(&package) {
+                    packages.push(package);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(res.len());
+                res.push(Normalized { constraint, packages: vec![package] });
+            }
+        }
+    }
+
+    tracing::info!("normalized {total} constraints to {}", res.len());
+
+    res
+}
@@ -1,41 +1,64 @@
 use std::collections::HashSet;
 
-use pyo3::{IntoPyObjectExt, basic::CompareOp, prelude::*};
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "solver-z3")]
 use z3::ast::Int;
 
 use super::ConstraintUtils;
+#[cfg(feature = "solver-z3")]
+use crate::constraint::CmpType;
+#[cfg(feature = "python")]
+use crate::constraint::{Arith, ArithOp, Cmp};
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
 use crate::{
-    constraint::{Cmp, CmpType, Constraint},
-    package::{self, outline::SolverError},
+    constraint::Constraint,
     spec::{SpecOption, SpecOptionType},
 };
 
-#[pyclass]
-#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NumOf {
-    #[pyo3(get, set)]
     pub of: Vec<Constraint>,
 }
 
+impl NumOf {
+    #[must_use]
+    pub fn new(of: impl IntoIterator<Item = Constraint>) -> Self {
+        Self { of: of.into_iter().collect() }
+    }
+}
+
 impl ConstraintUtils for NumOf {
-    fn get_value_type<'a, V>(
-        &'a self,
-        _registry: Option<&package::registry::Registry<'a, V>>,
-    ) -> Option<SpecOptionType> {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
         Some(SpecOptionType::Int)
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        _wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
         _value_type: SpecOptionType,
     ) {
         panic!("Cannot set value type of NumOf");
     }
 
-    fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         self.of.iter().try_for_each(|c| {
             tracing::warn!("Type checking {c}");
@@ -73,11 +96,12 @@ impl ConstraintUtils for NumOf {
             .collect()
     }
 
+    #[cfg(feature = "solver-z3")]
     fn cmp_to_z3(
         &self,
         other: &Constraint,
         op: CmpType,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<z3::ast::Dynamic, Box<SolverError>> {
         // Safe to unwrap since we've already type checked everything
         // Self to index on `s` as this always returns one clause
@@ -103,9 +127,10 @@ impl ConstraintUtils for NumOf {
         })
     }
 
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
         let mut clauses = Vec::new();
 
@@ -139,6 +164,7 @@ impl ConstraintUtils for NumOf {
         Ok(vec![Int::add(&refs).into()])
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: Python<'py>,
@@ -155,14 +181,15 @@ impl From<NumOf> for Constraint {
 
 impl std::fmt::Display for NumOf {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("NumOf( ")?;
+        f.write_str("num_of(")?;
         self.of.iter().enumerate().try_for_each(|(idx, of)| {
-            write!(f, "{}{}", of, if idx == self.of.len() { "" } else { ", " })
+            write!(f, "{}{of}", if idx == 0 { "" } else { ", " })
         })?;
-        f.write_str(" )")
+        f.write_str(")")
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl NumOf {
     #[new]
@@ -177,4 +204,52 @@ impl NumOf {
     ) -> Result<Constraint, PyErr> {
         Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
     }
+
+    fn __add__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Add, rhs)
+    }
+
+    fn __radd__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Add, self.clone().into())
+    }
+
+    fn __sub__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Sub, rhs)
+    }
+
+    fn __rsub__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Sub, self.clone().into())
+    }
+
+    fn __mul__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Mul, rhs)
+    }
+
+    fn __rmul__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Mul, self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
@@ -0,0 +1,141 @@
+//! Dependencies enabled by a boolean option.
+//!
+//! A dependency which a package only needs for an optional feature, such as
+//! `cuda` for `+cuda`, takes three parts: a boolean option, a default of
+//! `false` so the feature is off unless asked for, and a dependency when the
+//! option is enabled. [`OptionalDepends`] describes all three.
+//! [`PackageOutline::push_optional_depends`] adds them to a package, and as a
+//! [`Constraint`] it is the dependency alone:
+//!
+//! ```text
+//! depends(cuda) when option(hpl:cuda) == true
+//! ```
+
+#[cfg(feature = "python")]
+use pyo3::{
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+use crate::{
+    constraint::{Constraint, Depends, IfThen, SpecOption},
+    package::{outline::PackageOutline, schema::OptionSchema},
+    spec::{SpecOptionType, SpecOptionValue},
+};
+
+/// A dependency on `package` if the boolean `option` is enabled, which is off
+/// by default
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OptionalDepends {
+    pub option: SpecOption,
+
+    /// The package depended on
+    pub package: String,
+}
+
+impl OptionalDepends {
+    #[must_use]
+    pub fn new(
+        package_name: impl Into<String>,
+        option_name: impl Into<String>,
+        dependency: impl Into<String>,
+    ) -> Self {
+        Self {
+            option: SpecOption::new(package_name, option_name),
+            package: dependency.into(),
+        }
+    }
+
+    /// The dependency this is shorthand for, which only applies if the
+    /// option is enabled
+    #[must_use]
+    pub fn to_if_then(&self) -> IfThen {
+        IfThen::new(
+            self.option.clone().equals(true),
+            Depends::new(self.package.clone()),
+        )
+    }
+}
+
+impl From<OptionalDepends> for Constraint {
+    fn from(val: OptionalDepends) -> Self {
+        val.to_if_then().into()
+    }
+}
+
+impl std::fmt::Display for OptionalDepends {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_if_then())
+    }
+}
+
+impl PackageOutline {
+    /// Add the option of `dep`, off by default, and the dependency when it is
+    /// enabled.
+    ///
+    /// If the package declares its options (see [`crate::package::schema`])
+    /// the option is declared as a `Bool` with a default of `false`,
+    /// otherwise it is only given the default. An existing default or
+    /// declaration is kept, and nothing is declared if the option belongs to
+    /// another package.
+    pub fn push_optional_depends(&mut self, dep: OptionalDepends) {
+        if dep.option.package_name == self.name {
+            let name = dep.option.option_name.clone();
+
+            if self.schema.is_empty() {
+                self.set_defaults
+                    .entry(name)
+                    .or_insert(Some(SpecOptionValue::Bool(false)));
+            } else if !self.schema.contains_key(&name) {
+                let schema =
+                    OptionSchema::new(SpecOptionType::Bool).with_default(false);
+                self.declare_option(name, schema);
+            }
+        }
+
+        self.constraints.push(dep.into());
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl OptionalDepends {
+    /// * `package`: The package the option belongs to
+    /// * `option`: The name of the boolean option
+    /// * `dependency`: The package depended on if the option is enabled
+    #[new]
+    fn py_new(package: String, option: String, dependency: String) -> Self {
+        Self::new(package, option, dependency)
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
@@ -0,0 +1,215 @@
+//! Soft constraints.
+//!
+//! [`Prefer`] asks the solver to satisfy a boolean constraint if it can,
+//! rather than requiring it, so packages and site policies (see
+//! [`crate::package::policy`]) can nudge a solve without making it
+//! unsatisfiable:
+//!
+//! ```text
+//! prefer(option(mpi:openmpi) == true, 10)
+//! prefer(option(hpl:static) == false)
+//! ```
+//!
+//! Each preference is weighted, 1 by default. The solver minimizes the total
+//! weight of the preferences it breaks, so a preference only loses to
+//! heavier ones, and never to a requested spec or a hard constraint. A
+//! preference of a package only applies while the package is active, and a
+//! preference must be a whole constraint rather than part of one.
+
+use std::collections::HashSet;
+
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "solver-z3")]
+use z3::{Optimize, SortKind, ast::Bool};
+
+use super::ConstraintUtils;
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, BuiltRegistry, outline::SolverError};
+use crate::{
+    constraint::Constraint,
+    spec::{SpecOption, SpecOptionType},
+};
+
+/// Prefer solutions where `item` holds, with a `weight` relative to other
+/// preferences
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Prefer {
+    pub item: Constraint,
+
+    /// How much breaking this preference costs, which must be positive
+    pub weight: u64,
+}
+
+impl Prefer {
+    #[must_use]
+    pub fn new(item: impl Into<Constraint>, weight: u64) -> Self {
+        Self { item: item.into(), weight }
+    }
+}
+
+impl ConstraintUtils for Prefer {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
+        None
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
+        _value_type: SpecOptionType,
+    ) {
+        panic!("Cannot set value type of Prefer");
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        if self.weight == 0 {
+            let msg = format!("'{self}' must have a positive weight");
+            tracing::error!("{msg}");
+            return Err(Box::new(SolverError::InvalidConstraint(msg)));
+        }
+
+        match self.item.get_value_type(Some(wip_registry)) {
+            Some(SpecOptionType::Bool) => (),
+            Some(SpecOptionType::Unknown) => {
+                self.item.set_value_type(wip_registry, SpecOptionType::Bool);
+            }
+            Some(received) => {
+                return Err(Box::new(SolverError::IncorrectValueType {
+                    expected: SpecOptionType::Bool,
+                    received,
+                }));
+            }
+            None => {
+                return Err(Box::new(SolverError::InvalidNonValueConstraint));
+            }
+        }
+
+        self.item.type_check(wip_registry)
+    }
+
+    fn extract_spec_options(&self) -> Vec<(&str, &str, SpecOption)> {
+        self.item.extract_spec_options()
+    }
+
+    fn extract_dependencies(&self) -> HashSet<String> {
+        self.item.extract_dependencies()
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn to_z3_clauses(
+        &self,
+        _registry: &mut package::BuiltRegistry,
+    ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
+        let msg = format!("'{self}' cannot be part of another constraint");
+        tracing::error!("{msg}");
+        Err(Box::new(SolverError::InvalidConstraint(msg)))
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn add_to_solver(
+        &self,
+        toggle: &Bool,
+        optimizer: &Optimize,
+        registry: &mut BuiltRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        for clause in self.item.to_z3_clauses(registry)? {
+            let Some(clause) = clause.as_bool() else {
+                return Err(Box::new(SolverError::IncorrectSolverType {
+                    expected: SortKind::Bool,
+                    received: clause.sort_kind(),
+                }));
+            };
+
+            optimizer.assert_soft(&toggle.implies(&clause), self.weight, None);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "python")]
+    fn to_python_any<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.clone().into_bound_py_any(py)
+    }
+}
+
+impl From<Prefer> for Constraint {
+    fn from(val: Prefer) -> Self {
+        Self::Prefer(Box::new(val))
+    }
+}
+
+impl std::fmt::Display for Prefer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.weight == 1 {
+            write!(f, "prefer({})", self.item)
+        } else {
+            write!(f, "prefer({}, {})", self.item, self.weight)
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Prefer {
+    /// * `item`: The boolean constraint to prefer
+    /// * `weight`: How much breaking the preference costs, relative to other
+    ///   preferences
+    ///
+    /// # Errors
+    /// Errors if `weight` is zero.
+    #[new]
+    #[pyo3(signature = (item, weight = 1))]
+    pub(crate) fn py_new(item: Constraint, weight: u64) -> PyResult<Self> {
+        if weight == 0 {
+            return Err(PyValueError::new_err(
+                "the weight of a preference must be positive",
+            ));
+        }
+
+        Ok(Self { item, weight })
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
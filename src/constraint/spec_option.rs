@@ -1,45 +1,82 @@
 use std::collections::HashSet;
 
-use pyo3::{IntoPyObjectExt, basic::CompareOp, prelude::*};
-
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "python")]
+use crate::constraint::{Arith, ArithOp, IfThen};
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
 use crate::{
-    constraint::{Cmp, CmpType, Constraint, ConstraintUtils, IfThen, Value},
-    package::{self, outline::SolverError},
+    constraint::{Cmp, CmpType, Constraint, ConstraintUtils, Value},
     spec::{self, SpecOptionValue},
 };
 
-#[pyclass]
-#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SpecOption {
-    #[pyo3(get, set)]
     pub package_name: String,
 
-    #[pyo3(get, set)]
     pub option_name: String,
 }
 
+impl SpecOption {
+    #[must_use]
+    pub fn new(
+        package_name: impl Into<String>,
+        option_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            package_name: package_name.into(),
+            option_name: option_name.into(),
+        }
+    }
+
+    /// A constraint which holds if this option equals `value`.
+    #[must_use]
+    pub fn equals(self, value: impl Into<SpecOptionValue>) -> Cmp {
+        Cmp::new(self, CmpType::Equal, Value::new(value))
+    }
+}
+
 impl ConstraintUtils for SpecOption {
-    fn get_value_type<'a, V>(
-        &'a self,
-        registry: Option<&package::registry::Registry<'a, V>>,
+    fn get_value_type_default(&self) -> Option<spec::SpecOptionType> {
+        Some(spec::SpecOptionType::Unknown)
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn get_value_type<V>(
+        &self,
+        registry: Option<&package::registry::Registry<V>>,
     ) -> Option<spec::SpecOptionType> {
-        match registry {
-            Some(r) => {
-                let Some(idx) = r
-                    .lookup_option(&self.package_name, Some(&self.option_name))
-                else {
-                    return Some(spec::SpecOptionType::Unknown);
-                };
+        let Some(r) = registry else {
+            return self.get_value_type_default();
+        };
 
-                Some(r.spec_options()[idx].0)
-            }
-            None => Some(spec::SpecOptionType::Unknown),
-        }
+        let Some(idx) =
+            r.lookup_option(&self.package_name, Some(&self.option_name))
+        else {
+            return Some(spec::SpecOptionType::Unknown);
+        };
+
+        Some(r.spec_options()[idx].0)
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        wip_registry: &mut package::WipRegistry,
         value_type: spec::SpecOptionType,
     ) {
         wip_registry
@@ -52,9 +89,10 @@ impl ConstraintUtils for SpecOption {
             .expect("Internal solver error");
     }
 
+    #[cfg(feature = "solver-z3")]
     fn type_check(
         &self,
-        _wip_registry: &mut package::WipRegistry<'_>,
+        _wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         // Nothing to type check
         Ok(())
@@ -73,11 +111,12 @@ impl ConstraintUtils for SpecOption {
         HashSet::default()
     }
 
+    #[cfg(feature = "solver-z3")]
     fn cmp_to_z3(
         &self,
         other: &Constraint,
         op: CmpType,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<z3::ast::Dynamic, Box<SolverError>> {
         let value_type =
             self.get_value_type(Some(registry)).expect("Internal solver error");
@@ -172,10 +211,10 @@ impl ConstraintUtils for SpecOption {
                     &self.package_name,
                     Some(self.option_name.as_ref()),
                 ) else {
-                    return Err(Box::new(SolverError::MissingVariable {
-                        package: self.package_name.clone(),
-                        name: self.option_name.clone(),
-                    }));
+                    return Err(Box::new(registry.missing_error(
+                        &self.package_name,
+                        Some(&self.option_name),
+                    )));
                 };
 
                 let res = version.cmp_dynamic(op, vars, v_reg);
@@ -184,32 +223,26 @@ impl ConstraintUtils for SpecOption {
         }
     }
 
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
         tracing::info!("{}:{}", self.package_name, self.option_name);
 
         let Some(idx) = registry
             .lookup_option(&self.package_name, Some(self.option_name.as_ref()))
         else {
-            if registry.lookup_option(&self.package_name, None).is_some() {
-                tracing::error!(
-                    "Missing variable {}:{}",
-                    self.package_name,
-                    self.option_name
-                );
-
-                return Err(Box::new(SolverError::MissingVariable {
-                    package: self.package_name.clone(),
-                    name: self.option_name.clone(),
-                }));
-            }
-            tracing::error!("Missing package {}", self.package_name);
-
-            return Err(Box::new(SolverError::MissingPackage {
-                name: self.package_name.clone(),
-            }));
+            tracing::error!(
+                "Missing variable {}:{}",
+                self.package_name,
+                self.option_name
+            );
+
+            return Err(Box::new(
+                registry
+                    .missing_error(&self.package_name, Some(&self.option_name)),
+            ));
         };
 
         let value_type =
@@ -235,6 +268,7 @@ impl ConstraintUtils for SpecOption {
         }
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: pyo3::Python<'py>,
@@ -251,14 +285,11 @@ impl From<SpecOption> for Constraint {
 
 impl std::fmt::Display for SpecOption {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Package '{}' -> Option '{}'",
-            self.package_name, self.option_name
-        )
+        write!(f, "option({}:{})", self.package_name, self.option_name)
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl SpecOption {
     #[new]
@@ -274,6 +305,50 @@ impl SpecOption {
         Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
     }
 
+    fn __add__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Add, rhs)
+    }
+
+    fn __radd__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Add, self.clone().into())
+    }
+
+    fn __sub__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Sub, rhs)
+    }
+
+    fn __rsub__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Sub, self.clone().into())
+    }
+
+    fn __mul__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Mul, rhs)
+    }
+
+    fn __rmul__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Mul, self.clone().into())
+    }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    fn __invert__(&self) -> PyResult<Constraint> {
+        Cmp::py_invert(self.clone().into())
+    }
+
     fn if_then(&self, then: Constraint) -> IfThen {
         IfThen {
             cond: Cmp {
@@ -285,4 +360,28 @@ impl SpecOption {
             then,
         }
     }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
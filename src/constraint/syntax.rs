@@ -0,0 +1,518 @@
+//! The textual syntax of constraints.
+//!
+//! Every built-in constraint is displayed in a canonical syntax which
+//! [`Constraint::parse`] reads back, so constraints can be written in
+//! manifests and error messages show what a user would write:
+//!
+//! ```text
+//! depends(openmpi) when option(hpl:mpi) == true
+//! option(mpi:openmpi_off) == false
+//! num_of(depends(openblas), depends(mkl)) == 1
+//! option(hpl:version) >= @2.3 when platform(os=linux)
+//! option(app:io_threads) <= option(app:threads) - 2
+//! ```
+//!
+//! - `depends(name)` holds if the package `name` is active
+//! - `option(package:name)` is the value of an option
+//! - `platform(key=value)` holds if the platform fact `key` is `value` (see
+//!   [`crate::spec::platform`])
+//! - `matches(option(package:name), "pattern")` holds if a string option
+//!   matches a regular expression (see [`Matches`])
+//! - `num_of(a, b, ...)` counts the constraints which hold. A trailing comma
+//!   is allowed
+//! - `maximize(x)` and `minimize(x)` are objectives
+//! - `prefer(c)` or `prefer(c, weight)` prefers solutions where `c` holds
+//!   (see [`Prefer`])
+//! - `true`, `false`, integers, floats, `"strings"` and `@versions` are values
+//! - `a + b`, `a - b` and `a * b` are arithmetic on numbers, where `*` binds
+//!   tighter than `+` and `-`, and all bind tighter than comparisons
+//! - `a op b` compares two constraints, where `op` is one of `==`, `!=`, `<`,
+//!   `<=`, `>` or `>=`
+//! - `then when cond` requires `then` if `cond` holds, and binds loosest
+//! - parentheses group a constraint
+//!
+//! Within a package, `+name` and `~name` are shorthand for
+//! `option(package:name) == true` and `== false`, as in spec strings (see
+//! [`Constraint::parse_in`]).
+//!
+//! [`Custom`](super::Custom) constraints are displayed by their own
+//! implementation and cannot be parsed.
+//!
+//! To store constraints as strings with serde, use this module with
+//! `#[serde(with = "zpack::constraint::syntax")]`.
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::{
+    constraint::{
+        Arith, ArithOp, Cmp, CmpType, Constraint, Depends, IfThen, Matches,
+        Maximize, Minimize, NumOf, Prefer, SpecOption, Value, WhenPlatform,
+    },
+    package::version::Version,
+    spec::{SpecOptionValue, parse::is_name_char, platform::PlatformKey},
+    util::{error::ParserErrorWrapper, num::Number, parse::Cursor, suggest},
+};
+
+/// A constraint syntax error, ready to be rendered with
+/// [`ParserErrorWrapper::build`].
+pub type ConstraintParseError<'a> =
+    ParserErrorWrapper<'a, ariadne::Source<&'a str>>;
+
+/// The constraint functions, as written before their arguments
+const FUNCTIONS: [&str; 8] = [
+    "depends", "option", "platform", "matches", "num_of", "maximize",
+    "minimize", "prefer",
+];
+
+/// Comparison operators, longest first so `<=` is not read as `<`
+const OPERATORS: [(&str, CmpType); 6] = [
+    ("==", CmpType::Equal),
+    ("!=", CmpType::NotEqual),
+    ("<=", CmpType::LessOrEqual),
+    (">=", CmpType::GreaterOrEqual),
+    ("<", CmpType::Less),
+    (">", CmpType::Greater),
+];
+
+/// Arithmetic operators, grouped by [`ArithOp::precedence`], loosest first
+const ARITH_OPERATORS: [&[(char, ArithOp)]; 2] =
+    [&[('+', ArithOp::Add), ('-', ArithOp::Sub)], &[('*', ArithOp::Mul)]];
+
+/// Whether `txt` can be written without quotes
+fn is_bare(txt: &str) -> bool {
+    !txt.is_empty() && txt.chars().all(is_name_char)
+}
+
+/// Write `constraint` as an operand, in parentheses if it contains an
+/// operator which would otherwise bind differently.
+///
+/// * `cmp`: Whether comparisons must be parenthesized
+pub(crate) fn write_operand(
+    f: &mut std::fmt::Formatter<'_>,
+    constraint: &Constraint,
+    cmp: bool,
+) -> std::fmt::Result {
+    match constraint {
+        Constraint::IfThen(_) => write!(f, "({constraint})"),
+        Constraint::Cmp(_) if cmp => write!(f, "({constraint})"),
+        _ => write!(f, "{constraint}"),
+    }
+}
+
+/// Write `constraint` as an operand of the arithmetic operator `op`, in
+/// parentheses if it would otherwise bind differently.
+///
+/// * `rhs`: Whether `constraint` is the right operand, so an operator of the
+///   same precedence must be parenthesized
+pub(crate) fn write_arith_operand(
+    f: &mut std::fmt::Formatter<'_>,
+    constraint: &Constraint,
+    op: ArithOp,
+    rhs: bool,
+) -> std::fmt::Result {
+    match constraint {
+        Constraint::IfThen(_) | Constraint::Cmp(_) => {
+            write!(f, "({constraint})")
+        }
+        Constraint::Arith(arith)
+            if arith.op.precedence() < op.precedence()
+                || rhs && arith.op.precedence() == op.precedence() =>
+        {
+            write!(f, "({constraint})")
+        }
+        _ => write!(f, "{constraint}"),
+    }
+}
+
+/// Write `value` in constraint syntax
+pub(crate) fn write_value(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &SpecOptionValue,
+) -> std::fmt::Result {
+    match value {
+        SpecOptionValue::Bool(b) => write!(f, "{b}"),
+        SpecOptionValue::Int(i) => write!(f, "{i}"),
+        SpecOptionValue::Float(x) => write!(f, "{x:?}"),
+        SpecOptionValue::Str(s) => write!(f, "{s:?}"),
+        SpecOptionValue::Version(v) => write!(f, "@{v}"),
+    }
+}
+
+/// Write a platform fact's value, quoted unless it is a plain name
+pub(crate) fn write_platform_value(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &str,
+) -> std::fmt::Result {
+    if is_bare(value) { f.write_str(value) } else { write!(f, "{value:?}") }
+}
+
+/// The state of the constraint parser
+struct State<'a> {
+    /// The package `+name` and `~name` refer to
+    package: Option<&'a str>,
+}
+
+type Parser<'a> = Cursor<'a, State<'a>>;
+
+impl Parser<'_> {
+    /// A package, option or platform fact name
+    fn name(&mut self, what: &str) -> Option<String> {
+        self.skip_whitespace();
+
+        let (name, _) = self.take_while(is_name_char);
+
+        if name.is_empty() {
+            self.expected(what);
+            return None;
+        }
+
+        Some(name)
+    }
+
+    /// `then when cond`, or a comparison
+    fn constraint(&mut self) -> Option<Constraint> {
+        let then = self.cmp()?;
+
+        self.skip_whitespace();
+
+        if self.at("when")
+            && !self.chars.get(self.pos + 4).copied().is_some_and(is_name_char)
+        {
+            self.pos += 4;
+
+            let cond = self.cmp()?;
+            return Some(IfThen::new(cond, then).into());
+        }
+
+        Some(then)
+    }
+
+    /// `lhs op rhs`, or an arithmetic expression
+    fn cmp(&mut self) -> Option<Constraint> {
+        let lhs = self.arith(0)?;
+
+        for (txt, op) in OPERATORS {
+            if self.eat(txt) {
+                let rhs = self.arith(0)?;
+                return Some(Cmp::new(lhs, op, rhs).into());
+            }
+        }
+
+        Some(lhs)
+    }
+
+    /// Operands joined by the arithmetic operators of `precedence` or
+    /// tighter, left to right, or an atom
+    fn arith(&mut self, precedence: usize) -> Option<Constraint> {
+        let Some(ops) = ARITH_OPERATORS.get(precedence) else {
+            return self.atom();
+        };
+
+        let mut lhs = self.arith(precedence + 1)?;
+
+        loop {
+            self.skip_whitespace();
+
+            let Some(&(_, op)) =
+                ops.iter().find(|(c, _)| self.peek() == Some(*c))
+            else {
+                return Some(lhs);
+            };
+
+            self.pos += 1;
+
+            let rhs = self.arith(precedence + 1)?;
+            lhs = Arith::new(lhs, op, rhs).into();
+        }
+    }
+
+    fn atom(&mut self) -> Option<Constraint> {
+        self.skip_whitespace();
+
+        let start = self.pos;
+
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let res = self.constraint()?;
+                self.expect(')')?;
+                Some(res)
+            }
+            Some('"') => self.string().map(|s| Value::new(s).into()),
+            Some('@') => self.version(start),
+            Some(c @ ('+' | '~')) => self.flag(c, start),
+            Some(c) if c.is_ascii_digit() || c == '-' => self
+                .number()
+                .map(|n| Value::new(SpecOptionValue::from(n)).into()),
+            Some(c) if is_name_char(c) => self.function(),
+            Some(c) => {
+                self.pos += 1;
+                self.push(start..self.pos, format!("unexpected '{c}'"));
+                None
+            }
+            None => {
+                self.expected("a constraint");
+                None
+            }
+        }
+    }
+
+    /// `@version`
+    fn version(&mut self, start: usize) -> Option<Constraint> {
+        self.pos += 1;
+
+        let (txt, span) =
+            self.take_while(|c| !c.is_whitespace() && !matches!(c, ')' | ','));
+
+        let span = start..span.end;
+
+        if txt.is_empty() {
+            self.push(span, "expected a version after '@'");
+            return None;
+        }
+
+        match Version::new(&txt) {
+            Ok(v) => Some(Value::new(v).into()),
+            Err(e) => {
+                self.push(span, format!("invalid version: {e:?}"));
+                None
+            }
+        }
+    }
+
+    /// `+name` or `~name`
+    fn flag(&mut self, c: char, start: usize) -> Option<Constraint> {
+        self.pos += 1;
+
+        let name = self.name("an option name")?;
+
+        let Some(package) = self.state.package else {
+            self.push(
+                start..self.pos,
+                format!(
+                    "'{c}{name}' is only valid within a package; write \
+                     option(package:{name}) == {} instead",
+                    c == '+'
+                ),
+            );
+            return None;
+        };
+
+        Some(SpecOption::new(package, name).equals(c == '+').into())
+    }
+
+    /// A boolean, or a function such as `depends(name)`
+    fn function(&mut self) -> Option<Constraint> {
+        let (name, span) = self.take_while(is_name_char);
+
+        match name.as_str() {
+            "true" => return Some(Value::new(true).into()),
+            "false" => return Some(Value::new(false).into()),
+            _ if FUNCTIONS.contains(&name.as_str()) => (),
+            _ => {
+                self.push(
+                    span,
+                    format!(
+                        "unknown constraint '{name}'{}",
+                        suggest::did_you_mean(suggest::closest(
+                            &name, FUNCTIONS
+                        ))
+                    ),
+                );
+                return None;
+            }
+        }
+
+        self.delimited_by('(', ')', |p| {
+            Some(match name.as_str() {
+                "depends" => Depends::new(p.name("a package name")?).into(),
+                "option" => {
+                    let package = p.name("a package name")?;
+                    p.expect(':')?;
+                    let option = p.name("an option name")?;
+
+                    SpecOption::new(package, option).into()
+                }
+                "platform" => p.platform()?,
+                "matches" => p.matches()?,
+                "num_of" => {
+                    NumOf::new(p.separated_by(',', ')', Self::constraint)?)
+                        .into()
+                }
+                "maximize" => Maximize::new(p.constraint()?).into(),
+                "minimize" => Minimize::new(p.constraint()?).into(),
+                "prefer" => p.prefer()?,
+                _ => unreachable!("'{name}' is in FUNCTIONS"),
+            })
+        })
+    }
+
+    /// The `option(package:name), "pattern"` of `matches(...)`
+    fn matches(&mut self) -> Option<Constraint> {
+        self.skip_whitespace();
+
+        let start = self.pos;
+
+        let Constraint::SpecOption(option) = self.atom()? else {
+            self.push(
+                start..self.pos,
+                "expected an option, such as option(mpi:fabrics)",
+            );
+            return None;
+        };
+
+        self.expect(',')?;
+        self.skip_whitespace();
+
+        if self.peek() != Some('"') {
+            self.expected("a quoted pattern");
+            return None;
+        }
+
+        let start = self.pos;
+        let pattern = self.string()?;
+
+        if let Err(e) = Matches::parse_pattern(&pattern) {
+            self.push(start..self.pos, format!("invalid pattern: {e}"));
+            return None;
+        }
+
+        Some(Matches { option: *option, pattern }.into())
+    }
+
+    /// The `constraint, weight` of `prefer(...)`, where the weight is
+    /// optional
+    fn prefer(&mut self) -> Option<Constraint> {
+        let item = self.constraint()?;
+
+        if !self.eat(",") {
+            return Some(Prefer::new(item, 1).into());
+        }
+
+        self.skip_whitespace();
+
+        let start = self.pos;
+
+        match self.number()? {
+            Number::Integer(weight) if weight > 0 => {
+                Some(Prefer::new(item, weight.unsigned_abs()).into())
+            }
+            _ => {
+                self.push(
+                    start..self.pos,
+                    "expected a positive integer weight",
+                );
+                None
+            }
+        }
+    }
+
+    /// The `key=value` of `platform(key=value)`
+    fn platform(&mut self) -> Option<Constraint> {
+        self.skip_whitespace();
+
+        let key_start = self.pos;
+        let key_name = self.name("a platform fact")?;
+
+        let key = match key_name.parse::<PlatformKey>() {
+            Ok(key) => key,
+            Err(e) => {
+                self.push(key_start..self.pos, e);
+                return None;
+            }
+        };
+
+        self.expect('=')?;
+        self.skip_whitespace();
+
+        let value = if self.peek() == Some('"') {
+            self.string()?
+        } else {
+            self.name("a platform value")?
+        };
+
+        Some(WhenPlatform::new(key, value).into())
+    }
+}
+
+impl Constraint {
+    /// Parse a constraint written in the syntax described in
+    /// [`crate::constraint::syntax`].
+    ///
+    /// # Errors
+    /// Errors if `txt` is not a valid constraint. Only the first problem is
+    /// reported.
+    pub fn parse(txt: &str) -> Result<Self, ConstraintParseError<'_>> {
+        Self::parse_in(txt, None)
+    }
+
+    /// [`Self::parse`], where `+name` and `~name` refer to the options of
+    /// `package`.
+    ///
+    /// # Errors
+    /// Errors if `txt` is not a valid constraint. Only the first problem is
+    /// reported.
+    pub fn parse_in<'a>(
+        txt: &'a str,
+        package: Option<&'a str>,
+    ) -> Result<Self, ConstraintParseError<'a>> {
+        let mut parser = Parser::new(txt, State { package });
+
+        let res = parser.constraint();
+
+        parser.skip_whitespace();
+
+        if res.is_some()
+            && let Some(c) = parser.peek()
+        {
+            parser.push(
+                parser.pos..parser.chars.len(),
+                format!("unexpected '{c}' after the constraint"),
+            );
+        }
+
+        match res {
+            Some(res) if parser.errors.is_empty() => Ok(res),
+            _ => Err(ParserErrorWrapper::new(
+                "constraint",
+                ariadne::Source::from(txt),
+                parser.errors,
+            )),
+        }
+    }
+}
+
+/// Render a parse error as a plain message
+pub(crate) fn render(err: ConstraintParseError<'_>) -> String {
+    err.build().map(|e| e.to_string().unwrap_or_else(|e| e)).unwrap_or_default()
+}
+
+impl std::str::FromStr for Constraint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).map_err(render)
+    }
+}
+
+/// Serialize a constraint as a string in constraint syntax.
+///
+/// # Errors
+/// Errors if the serializer fails.
+pub fn serialize<S: Serializer>(
+    constraint: &Constraint,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(constraint)
+}
+
+/// Deserialize a constraint from a string in constraint syntax.
+///
+/// # Errors
+/// Errors if the value is not a string or is not a valid constraint.
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Constraint, D::Error> {
+    let txt = String::deserialize(deserializer)?;
+    txt.parse().map_err(serde::de::Error::custom)
+}
@@ -1,39 +1,61 @@
 use std::collections::HashSet;
 
-use pyo3::{IntoPyObjectExt, basic::CompareOp, prelude::*};
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "solver-z3")]
+use crate::constraint::CmpType;
+#[cfg(feature = "python")]
+use crate::constraint::{Arith, ArithOp, Cmp};
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
 use crate::{
-    constraint::{Cmp, CmpType, Constraint, ConstraintUtils},
-    package::{self, outline::SolverError},
+    constraint::{Constraint, ConstraintUtils, syntax},
     spec::{self, SpecOptionValue},
 };
 
-#[pyclass]
-#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Value {
-    #[pyo3(get, set)]
     pub value: SpecOptionValue,
 }
 
+impl Value {
+    #[must_use]
+    pub fn new(value: impl Into<SpecOptionValue>) -> Self {
+        Self { value: value.into() }
+    }
+}
+
 impl ConstraintUtils for Value {
-    fn get_value_type<'a, V>(
-        &'a self,
-        _registry: Option<&package::registry::Registry<'a, V>>,
-    ) -> Option<spec::SpecOptionType> {
+    fn get_value_type_default(&self) -> Option<spec::SpecOptionType> {
         Some(self.value.to_type())
     }
 
-    fn set_value_type<'a>(
-        &'a self,
-        _wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
         _value_type: spec::SpecOptionType,
     ) {
         tracing::error!("Cannot change datatype of Value constraint");
     }
 
-    fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         match &self.value {
             SpecOptionValue::Bool(_)
@@ -56,22 +78,25 @@ impl ConstraintUtils for Value {
         HashSet::default()
     }
 
+    #[cfg(feature = "solver-z3")]
     fn cmp_to_z3(
         &self,
         _other: &Constraint,
         _op: CmpType,
-        _registry: &mut package::BuiltRegistry<'_>,
+        _registry: &mut package::BuiltRegistry,
     ) -> Result<z3::ast::Dynamic, Box<SolverError>> {
         todo!()
     }
 
+    #[cfg(feature = "solver-z3")]
     fn to_z3_clauses(
         &self,
-        registry: &mut package::BuiltRegistry<'_>,
+        registry: &mut package::BuiltRegistry,
     ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
         Ok(self.value.to_z3_dynamic(registry))
     }
 
+    #[cfg(feature = "python")]
     fn to_python_any<'py>(
         &self,
         py: pyo3::Python<'py>,
@@ -88,12 +113,19 @@ impl From<Value> for Constraint {
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+        syntax::write_value(f, &self.value)
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl Value {
+    /// A constant such as `True`, `3`, `"shared"` or `Version("1.2")`
+    #[new]
+    const fn py_new(value: SpecOptionValue) -> Self {
+        Self { value }
+    }
+
     fn __richcmp__(
         &self,
         rhs: Constraint,
@@ -101,4 +133,72 @@ impl Value {
     ) -> Result<Constraint, PyErr> {
         Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
     }
+
+    fn __add__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Add, rhs)
+    }
+
+    fn __radd__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Add, self.clone().into())
+    }
+
+    fn __sub__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Sub, rhs)
+    }
+
+    fn __rsub__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Sub, self.clone().into())
+    }
+
+    fn __mul__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(self.clone().into(), ArithOp::Mul, rhs)
+    }
+
+    fn __rmul__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Arith::py_arith_helper(lhs, ArithOp::Mul, self.clone().into())
+    }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    fn __invert__(&self) -> PyResult<Constraint> {
+        Cmp::py_invert(self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
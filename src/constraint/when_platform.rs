@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+
+#[cfg(feature = "python")]
+use pyo3::{
+    IntoPyObjectExt,
+    basic::CompareOp,
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+
+use super::ConstraintUtils;
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, outline::SolverError};
+#[cfg(feature = "python")]
+use crate::{constraint::Cmp, spec::platform::PlatformError};
+use crate::{
+    constraint::{Constraint, syntax},
+    spec::{SpecOptionType, platform::PlatformKey},
+};
+
+/// True if the platform fact `key` is `value`, such as `os` being `linux`.
+///
+/// Platform facts are fixed before solving (see
+/// [`Platform`](crate::spec::platform::Platform)), so this is effectively a
+/// constant which can be used as the condition of an
+/// [`IfThen`](super::IfThen).
+#[cfg_attr(
+    feature = "python",
+    pyclass(module = "zpack.constraint", get_all, set_all)
+)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WhenPlatform {
+    pub key: PlatformKey,
+
+    pub value: String,
+}
+
+impl WhenPlatform {
+    #[must_use]
+    pub const fn new(key: PlatformKey, value: String) -> Self {
+        Self { key, value }
+    }
+}
+
+impl ConstraintUtils for WhenPlatform {
+    fn get_value_type_default(&self) -> Option<SpecOptionType> {
+        Some(SpecOptionType::Bool)
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn set_value_type(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
+        _value_type: SpecOptionType,
+    ) {
+        // Nothing to set
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn type_check(
+        &self,
+        _wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        // Nothing to type-check
+        Ok(())
+    }
+
+    fn extract_spec_options(
+        &self,
+    ) -> Vec<(&str, &str, crate::spec::SpecOption)> {
+        Vec::new()
+    }
+
+    fn extract_dependencies(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+
+    #[cfg(feature = "solver-z3")]
+    fn to_z3_clauses(
+        &self,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<Vec<z3::ast::Dynamic>, Box<SolverError>> {
+        Ok(vec![registry.platform_fact(self.key, &self.value).into()])
+    }
+
+    #[cfg(feature = "python")]
+    fn to_python_any<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> pyo3::PyResult<pyo3::Bound<'py, pyo3::PyAny>> {
+        self.clone().into_bound_py_any(py)
+    }
+}
+
+impl From<WhenPlatform> for Constraint {
+    fn from(val: WhenPlatform) -> Self {
+        Self::WhenPlatform(Box::new(val))
+    }
+}
+
+impl std::fmt::Display for WhenPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "platform({}=", self.key)?;
+        syntax::write_platform_value(f, &self.value)?;
+        f.write_str(")")
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl WhenPlatform {
+    /// * `key`: The name of the platform fact, such as `"os"` or `"arch"`
+    /// * `value`: The value the fact must have
+    ///
+    /// # Errors
+    /// Errors if `key` is not a valid platform fact.
+    #[new]
+    pub fn py_new(key: &str, value: String) -> PyResult<Self> {
+        let key = key
+            .parse()
+            .map_err(|e: PlatformError| PyValueError::new_err(e.to_string()))?;
+
+        Ok(Self::new(key, value))
+    }
+
+    fn __richcmp__(
+        &self,
+        rhs: Constraint,
+        op: CompareOp,
+    ) -> Result<Constraint, PyErr> {
+        Cmp::py_richcmp_helper(self.clone().into(), rhs, op.into())
+    }
+
+    fn __and__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, true)
+    }
+
+    fn __rand__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), true)
+    }
+
+    fn __or__(&self, rhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(self.clone().into(), rhs, false)
+    }
+
+    fn __ror__(&self, lhs: Constraint) -> PyResult<Constraint> {
+        Cmp::py_join(lhs, self.clone().into(), false)
+    }
+
+    fn __invert__(&self) -> PyResult<Constraint> {
+        Cmp::py_invert(self.clone().into())
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
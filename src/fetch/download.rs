@@ -0,0 +1,298 @@
+//! HTTP downloads with retries and resumption.
+//!
+//! A request which fails with a transient error, such as a connection failure,
+//! a timeout, a `429` or `5xx` response or a connection dropped part way
+//! through the body, is retried with exponential backoff (see
+//! [`FetchConfig::retries`] and [`FetchConfig::backoff`]). A `Retry-After`
+//! header is honoured if the server sends one.
+//!
+//! When resuming is allowed, a partial download is continued with an HTTP
+//! range request rather than starting again, both between retries and between
+//! runs. The bytes already on disk are hashed first, so the checksum of the
+//! whole file is still computed. Servers which ignore the range are handled by
+//! starting from the beginning.
+//!
+//! Failures report the full chain of errors behind them, with a hint when a
+//! certificate is not trusted or a proxy could not be reached. Certificate
+//! errors are not retried.
+
+use std::{path::Path, time::Duration};
+
+use reqwest::{Client, Response, StatusCode, header};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    fetch::{FetchConfig, rate::RateLimiter},
+    util::digest::{Algorithm, Checksum, Hasher},
+};
+
+/// The longest delay before a retry, including one requested by the server
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Why an attempt failed
+pub enum Failure {
+    /// The attempt may succeed if repeated, after `after` if the server asked
+    /// for a delay
+    Retry { reason: String, after: Option<Duration> },
+
+    /// Repeating the attempt will not help
+    Fail(String),
+}
+
+impl Failure {
+    fn io(e: &std::io::Error) -> Self {
+        Self::Fail(e.to_string())
+    }
+}
+
+/// `e` followed by each error which caused it, as reqwest's own message only
+/// names the URL.
+fn describe(e: &reqwest::Error) -> String {
+    let mut res = e.to_string();
+    let mut source = std::error::Error::source(e);
+
+    while let Some(cause) = source {
+        let txt = cause.to_string();
+
+        if !res.contains(&txt) {
+            res = format!("{res}: {txt}");
+        }
+
+        source = cause.source();
+    }
+
+    res
+}
+
+impl From<reqwest::Error> for Failure {
+    fn from(e: reqwest::Error) -> Self {
+        let mut reason = describe(&e);
+        let lower = reason.to_lowercase();
+
+        // Retrying will not make an untrusted certificate trusted
+        if lower.contains("certificate") {
+            return Self::Fail(format!(
+                "{reason}; if the server uses a private certificate \
+                 authority, set 'network.ca_bundle', a per-mirror override or \
+                 SSL_CERT_FILE to a PEM bundle containing it"
+            ));
+        }
+
+        if lower.contains("proxy") || lower.contains("tunnel") {
+            reason.push_str(
+                "; check 'network.proxy' and the HTTP_PROXY, HTTPS_PROXY and \
+                 NO_PROXY environment variables",
+            );
+        }
+
+        // A connection dropped part way through the body is reported as a
+        // decoding error
+        let transient = e.is_timeout()
+            || e.is_connect()
+            || e.is_body()
+            || e.is_decode()
+            || e.status().is_some_and(|s| s.is_server_error());
+
+        if transient {
+            Self::Retry { reason, after: None }
+        } else {
+            Self::Fail(reason)
+        }
+    }
+}
+
+/// The delay before retry number `attempt`, counting from zero.
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_BACKOFF)
+}
+
+/// Run `attempt` until it succeeds, fails permanently or has been retried
+/// [`FetchConfig::retries`] times, adding each retry to `retries`.
+///
+/// # Errors
+/// Errors with the reason for the last failure.
+pub async fn retry<T, F: Future<Output = Result<T, Failure>>>(
+    options: &FetchConfig,
+    url: &str,
+    retries: &mut u32,
+    mut attempt: impl FnMut() -> F,
+) -> Result<T, String> {
+    let mut count = 0;
+
+    loop {
+        match attempt().await {
+            Ok(res) => return Ok(res),
+            Err(Failure::Retry { reason, after })
+                if count < options.retries =>
+            {
+                let delay = after.map_or_else(
+                    || backoff(options.backoff, count),
+                    |after| after.min(MAX_BACKOFF),
+                );
+
+                tracing::warn!(
+                    "failed to fetch '{url}': {reason}; retrying in {:.1}s",
+                    delay.as_secs_f64()
+                );
+
+                tokio::time::sleep(delay).await;
+                count += 1;
+                *retries += 1;
+            }
+            Err(Failure::Retry { reason, .. } | Failure::Fail(reason)) => {
+                return Err(reason);
+            }
+        }
+    }
+}
+
+/// Request `url`, starting from byte `from` if it is not zero.
+///
+/// A `416 Range Not Satisfiable` response is returned rather than treated as
+/// an error, so the caller can restart the download.
+///
+/// # Errors
+/// Errors if the request fails or the response is an error.
+pub async fn send(
+    client: &Client,
+    url: &str,
+    from: u64,
+) -> Result<Response, Failure> {
+    let mut request = client.get(url);
+
+    if from > 0 {
+        request = request.header(header::RANGE, format!("bytes={from}-"));
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+
+    if status.is_success()
+        || (from > 0 && status == StatusCode::RANGE_NOT_SATISFIABLE)
+    {
+        return Ok(response);
+    }
+
+    let reason = format!("HTTP status {status}");
+
+    if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        let after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+            .map(Duration::from_secs);
+
+        Err(Failure::Retry { reason, after })
+    } else {
+        Err(Failure::Fail(reason))
+    }
+}
+
+/// Hash the partial download at `path`, returning the hasher so the rest of
+/// the download can be added to it.
+async fn hash_prefix(
+    path: &Path,
+    algorithm: Algorithm,
+) -> Result<Hasher, Failure> {
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Hasher::new(algorithm);
+        std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        Ok(hasher)
+    })
+    .await
+    .map_err(|e| Failure::Fail(e.to_string()))?
+    .map_err(|e| Failure::io(&e))
+}
+
+/// Make one attempt at downloading `url` to `dest`.
+async fn download_once(
+    client: &Client,
+    limiter: &RateLimiter,
+    url: &str,
+    dest: &Path,
+    algorithm: Algorithm,
+    resume: bool,
+) -> Result<Checksum, Failure> {
+    let mut from = if resume {
+        tokio::fs::metadata(dest).await.map_or(0, |m| m.len())
+    } else {
+        0
+    };
+
+    let mut response = send(client, url, from).await?;
+
+    if from > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        tracing::info!("cannot resume '{url}'; restarting");
+        from = 0;
+        response = send(client, url, from).await?;
+    }
+
+    let (mut file, mut hasher) = if from > 0
+        && response.status() == StatusCode::PARTIAL_CONTENT
+    {
+        tracing::info!("resuming '{url}' from byte {from}");
+
+        let hasher = hash_prefix(dest, algorithm).await?;
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(dest)
+            .await
+            .map_err(|e| Failure::io(&e))?;
+
+        (file, hasher)
+    } else {
+        let file =
+            tokio::fs::File::create(dest).await.map_err(|e| Failure::io(&e))?;
+
+        (file, Hasher::new(algorithm))
+    };
+
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                // Keep what was received so the next attempt can resume
+                let _ = file.flush().await;
+                return Err(e.into());
+            }
+        };
+
+        limiter.acquire(chunk.len()).await;
+
+        file.write_all(&chunk).await.map_err(|e| Failure::io(&e))?;
+        hasher.update(&chunk);
+    }
+
+    file.sync_all().await.map_err(|e| Failure::io(&e))?;
+
+    Ok(hasher.finalize())
+}
+
+/// Download `url` to `dest`, retrying transient failures.
+///
+/// * `resume`: Whether an existing partial download at `dest` may be continued.
+///   It is otherwise overwritten
+/// * `retries`: Incremented for each retry
+///
+/// # Errors
+/// Errors with the reason the last attempt failed.
+#[allow(clippy::too_many_arguments)]
+pub async fn download(
+    client: &Client,
+    limiter: &RateLimiter,
+    options: &FetchConfig,
+    url: &str,
+    dest: &Path,
+    algorithm: Algorithm,
+    resume: bool,
+    retries: &mut u32,
+) -> Result<Checksum, String> {
+    retry(options, url, retries, || {
+        download_once(client, limiter, url, dest, algorithm, resume)
+    })
+    .await
+}
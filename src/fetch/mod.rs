@@ -0,0 +1,854 @@
+//! Downloading package sources and other remote resources.
+//!
+//! A [`Fetcher`] tries every configured mirror, in order, before falling back
+//! to the upstream URL. Mirrors share a layout: a resource for `package` is
+//! stored at `<mirror>/<package>/<file name>`, where the file name is the last
+//! component of the upstream URL (see [`mirror_path`]). Mirrors may be local
+//! directories, `file://` URLs or remote URLs.
+//!
+//! Downloads are hashed while they are streamed to disk (see
+//! [`HashingWriter`]), so verifying a large archive does not require reading
+//! it back.
+//!
+//! Fetching runs on an async runtime, so [`Fetcher::fetch_many`] can download
+//! up to [`FetchConfig::jobs`] resources at once. Transient failures are
+//! retried with backoff and partial downloads are resumed (see [`download`]),
+//! and every download shares one bandwidth limit (see [`RateLimiter`]).
+//!
+//! Verified downloads are kept in a cache keyed by checksum (see
+//! [`DownloadCache`]), so a resource is only downloaded once however many
+//! installations use it.
+//!
+//! URLs whose scheme has a fetcher registered by a plugin (see
+//! [`plugins`](crate::interface::plugins)) are fetched by that plugin instead.
+//!
+//! Resources fetched from a location with a trust store configured must be
+//! signed by a trusted signer, and with `--require-signed` every resource must
+//! be (see [`signature`]). The cache records which resources were signed, so
+//! a resource which must be signed is only restored from the cache if its
+//! signature was verified, and is fetched again rather than reused from its
+//! destination.
+//!
+//! Proxies and certificate authorities can be configured for every location or
+//! per mirror, and otherwise follow the usual environment variables (see
+//! [`network`]).
+//!
+//! Every fetcher keeps a [`FetchReport`] of how its fetches went, such as how
+//! many were retried, restored from the cache or only fetched after a mirror
+//! failed (see [`Fetcher::report`]).
+//!
+//! In offline mode, only local mirrors are consulted and any fetch which would
+//! require network access fails immediately with [`FetchError::Offline`].
+
+use std::{
+    io::Read,
+    num::{NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use futures_util::{StreamExt, stream};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+
+#[cfg(feature = "python")]
+use crate::interface::plugins;
+use crate::{
+    config::Config,
+    fetch::{
+        network::NetworkConfig,
+        rate::RateLimiter,
+        report::{FetchReport, UPSTREAM},
+        signature::{SignatureConfig, SignatureKind},
+        store::DownloadCache,
+    },
+    util::{
+        digest::{Algorithm, Checksum, HashingWriter},
+        timings,
+    },
+};
+
+mod download;
+pub mod network;
+pub mod rate;
+pub mod report;
+pub mod signature;
+pub mod store;
+
+/// How downloads are scheduled
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FetchConfig {
+    /// The most downloads in progress at once
+    pub jobs: NonZeroUsize,
+
+    /// How many times a download which fails with a transient error, such as
+    /// a timeout or a server error, is retried
+    pub retries: u32,
+
+    /// The delay before the first retry, doubled before each subsequent one
+    #[serde(with = "secs")]
+    pub backoff: Duration,
+
+    /// How long connecting may take, or a download may receive nothing,
+    /// before the attempt fails and is retried
+    #[serde(with = "secs")]
+    pub timeout: Duration,
+
+    /// The most bytes downloaded per second, shared between every download.
+    /// `None` disables the limit
+    pub rate_limit: Option<NonZeroU64>,
+
+    /// Keep verified downloads in a cache shared between installations
+    pub cache: bool,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            jobs: NonZeroUsize::new(4).unwrap(),
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            timeout: Duration::from_secs(30),
+            rate_limit: None,
+            cache: true,
+        }
+    }
+}
+
+/// Serialize a duration as a number of seconds
+mod secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    // The signature is dictated by `#[serde(with)]`
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        Duration::try_from_secs_f64(f64::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    /// The resource is not available locally and the fetcher is offline
+    Offline {
+        url: String,
+    },
+
+    Io(std::io::Error),
+
+    ChecksumMismatch {
+        url: String,
+        expected: Checksum,
+        actual: Checksum,
+    },
+
+    /// Every candidate location failed. Contains each location tried and the
+    /// reason it failed
+    NotFound {
+        url: String,
+        attempts: Vec<(String, String)>,
+    },
+
+    /// The resource was fetched but could not be parsed
+    InvalidResponse {
+        url: String,
+        error: String,
+    },
+
+    /// The resource's signature is invalid, or it is unsigned and signatures
+    /// are required
+    Signature {
+        url: String,
+        error: String,
+    },
+
+    /// An HTTP client could not be created from the network configuration
+    Network(String),
+}
+
+impl From<std::io::Error> for FetchError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Offline { url } => write!(
+                f,
+                "'{url}' is not available from a local mirror and network \
+                 access is disabled (--offline)"
+            ),
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::ChecksumMismatch { url, expected, actual } => write!(
+                f,
+                "checksum mismatch for '{url}': expected {expected}, found \
+                 {actual}"
+            ),
+            Self::NotFound { url, attempts } => {
+                writeln!(f, "failed to fetch '{url}':")?;
+                attempts.iter().try_for_each(|(loc, reason)| {
+                    writeln!(f, "- {loc}: {reason}")
+                })
+            }
+            Self::InvalidResponse { url, error } => {
+                write!(f, "invalid response from '{url}': {error}")
+            }
+            Self::Signature { url, error } => {
+                write!(f, "cannot verify the signature of '{url}': {error}")
+            }
+            Self::Network(e) => write!(f, "invalid network configuration: {e}"),
+        }
+    }
+}
+
+/// The last path component of a URL, ignoring any query or fragment.
+#[must_use]
+pub fn url_file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+
+    match path.trim_end_matches('/').rsplit('/').next() {
+        Some(name) if !name.is_empty() => name,
+        _ => "download",
+    }
+}
+
+/// Location of a resource relative to the root of a mirror.
+#[must_use]
+pub fn mirror_path(package: &str, url: &str) -> String {
+    format!("{package}/{}", url_file_name(url))
+}
+
+/// Sent with every request, as some APIs reject requests without one
+const USER_AGENT: &str = concat!("zpack/", env!("CARGO_PKG_VERSION"));
+
+/// Whether `location` is `prefix` or under it.
+fn under_prefix(location: &str, prefix: &str) -> bool {
+    location
+        .strip_prefix(prefix.trim_end_matches('/'))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Returns the local path for `location` if it does not require the network.
+fn local_path(location: &str) -> Option<PathBuf> {
+    if location.contains("://") && !location.starts_with("file://") {
+        None
+    } else {
+        Some(PathBuf::from(location.trim_start_matches("file://")))
+    }
+}
+
+/// `path` with `suffix` appended to its file name. Unlike
+/// [`Path::with_extension`], files which differ only in their extension stay
+/// distinct, so concurrent fetches never share a partial download.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut res = path.as_os_str().to_owned();
+    res.push(suffix);
+    PathBuf::from(res)
+}
+
+/// A resource to fetch with [`Fetcher::fetch_many`]
+#[derive(Clone, Debug)]
+pub struct FetchRequest<'a> {
+    /// The package the resource belongs to, which locates it on mirrors
+    pub package: &'a str,
+    pub url: &'a str,
+    pub checksum: Option<&'a Checksum>,
+    pub dest: PathBuf,
+}
+
+/// The runtime and HTTP client, created on first use since most commands
+/// never fetch anything
+struct Session {
+    runtime: Runtime,
+    client: reqwest::Client,
+
+    /// Clients for locations under each prefix with a network override
+    overrides: Vec<(String, reqwest::Client)>,
+}
+
+impl Session {
+    /// The client to request `location` with.
+    fn client(&self, location: &str) -> &reqwest::Client {
+        self.overrides
+            .iter()
+            .filter(|(prefix, _)| under_prefix(location, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(&self.client, |(_, client)| client)
+    }
+}
+
+pub struct Fetcher {
+    mirrors: Vec<String>,
+    offline: bool,
+    options: FetchConfig,
+    signatures: SignatureConfig,
+    network: NetworkConfig,
+    limiter: RateLimiter,
+    cache: Option<DownloadCache>,
+    session: OnceLock<Session>,
+    report: Mutex<FetchReport>,
+}
+
+impl Fetcher {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        Self {
+            mirrors: config.mirrors.clone(),
+            offline: config.offline,
+            options: config.fetch.clone(),
+            signatures: config.signatures.clone(),
+            network: config.network.clone(),
+            limiter: RateLimiter::new(config.fetch.rate_limit),
+            cache: config.fetch.cache.then(DownloadCache::default),
+            session: OnceLock::new(),
+            report: Mutex::default(),
+        }
+    }
+
+    #[must_use]
+    pub const fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// How every fetch made so far went.
+    ///
+    /// # Panics
+    /// Panics if a thread panicked while recording a fetch.
+    #[must_use]
+    pub fn report(&self) -> FetchReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Update the report.
+    fn record(&self, f: impl FnOnce(&mut FetchReport)) {
+        f(&mut self.report.lock().unwrap());
+    }
+
+    fn session(&self) -> Result<&Session, FetchError> {
+        if let Some(session) = self.session.get() {
+            return Ok(session);
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let timeout = self.options.timeout;
+
+        let client = self
+            .network
+            .default_connection()
+            .client(USER_AGENT, timeout)
+            .map_err(FetchError::Network)?;
+
+        let overrides = self
+            .network
+            .override_connections()
+            .map(|(prefix, conn)| {
+                let client = conn.client(USER_AGENT, timeout).map_err(|e| {
+                    FetchError::Network(format!("override for '{prefix}': {e}"))
+                })?;
+
+                Ok((prefix.to_string(), client))
+            })
+            .collect::<Result<_, FetchError>>()?;
+
+        Ok(self.session.get_or_init(|| Session { runtime, client, overrides }))
+    }
+
+    /// Every location `url` may be fetched from, in the order they are tried.
+    #[must_use]
+    pub fn candidates(&self, package: &str, url: &str) -> Vec<String> {
+        let rel = mirror_path(package, url);
+
+        self.mirrors
+            .iter()
+            .map(|mirror| format!("{}/{rel}", mirror.trim_end_matches('/')))
+            .chain(std::iter::once(url.to_string()))
+            .collect()
+    }
+
+    /// Fetch the JSON document at `url`. Mirrors are not consulted.
+    ///
+    /// # Errors
+    /// Errors if the fetcher is offline, the request fails or the response is
+    /// not valid JSON.
+    pub fn get_json(&self, url: &str) -> Result<serde_json::Value, FetchError> {
+        if self.offline {
+            return Err(FetchError::Offline { url: url.to_string() });
+        }
+
+        tracing::info!("fetching '{url}'");
+
+        let session = self.session()?;
+
+        let txt = session
+            .runtime
+            .block_on(download::retry(&self.options, url, &mut 0, || async {
+                Ok(download::send(session.client(url), url, 0)
+                    .await?
+                    .text()
+                    .await?)
+            }))
+            .map_err(|reason| FetchError::NotFound {
+                url: url.to_string(),
+                attempts: vec![(url.to_string(), reason)],
+            })?;
+
+        serde_json::from_str(&txt).map_err(|e| FetchError::InvalidResponse {
+            url: url.to_string(),
+            error: e.to_string(),
+        })
+    }
+
+    /// Fetch `url` into `dest`, verifying it against `checksum` if given.
+    ///
+    /// If `dest` already exists and matches the checksum, nothing is fetched.
+    /// The download is written to a temporary file next to `dest` and only
+    /// moved into place once it has been verified.
+    ///
+    /// # Errors
+    /// Errors if the resource cannot be fetched from any location or does not
+    /// match the checksum.
+    pub fn fetch(
+        &self,
+        package: &str,
+        url: &str,
+        checksum: Option<&Checksum>,
+        dest: &Path,
+    ) -> Result<PathBuf, FetchError> {
+        let request =
+            FetchRequest { package, url, checksum, dest: dest.to_path_buf() };
+
+        timings::time(timings::FETCH, || {
+            let session = self.session()?;
+            session.runtime.block_on(self.fetch_one(session, &request))
+        })
+    }
+
+    /// [`Self::fetch`] every request concurrently, with at most
+    /// [`FetchConfig::jobs`] in progress at once.
+    ///
+    /// Results are returned in the same order as `requests`, so one failure
+    /// does not prevent the others from completing.
+    ///
+    /// # Errors
+    /// Errors if the async runtime cannot be started.
+    pub fn fetch_many(
+        &self,
+        requests: &[FetchRequest<'_>],
+    ) -> Result<Vec<Result<PathBuf, FetchError>>, FetchError> {
+        timings::time(timings::FETCH, || {
+            let session = self.session()?;
+
+            Ok(session.runtime.block_on(
+                stream::iter(requests)
+                    .map(|request| self.fetch_one(session, request))
+                    .buffered(self.options.jobs.get())
+                    .collect(),
+            ))
+        })
+    }
+
+    async fn fetch_one(
+        &self,
+        session: &Session,
+        request: &FetchRequest<'_>,
+    ) -> Result<PathBuf, FetchError> {
+        self.record(|report| report.requests += 1);
+
+        let res = self.try_fetch(session, request).await;
+
+        if res.is_err() {
+            self.record(|report| report.failed += 1);
+        }
+
+        res
+    }
+
+    /// Whether the resource for `request` must be signed: with
+    /// `--require-signed`, or if any location it may be fetched from has a
+    /// trust store.
+    fn needs_signature(&self, request: &FetchRequest<'_>) -> bool {
+        self.signatures.require_signed
+            || self
+                .candidates(request.package, request.url)
+                .iter()
+                .any(|location| self.signatures.trust_store(location).is_some())
+    }
+
+    /// Whether the resource for `request` is already at its destination, or
+    /// has been restored there from the download cache.
+    ///
+    /// A resource which must be signed (see [`Self::needs_signature`]) is
+    /// only restored from a cache entry recorded as signed, and is never
+    /// taken from its destination, which records nothing.
+    async fn present(
+        &self,
+        request: &FetchRequest<'_>,
+    ) -> Result<bool, FetchError> {
+        let FetchRequest { checksum, dest, .. } = request;
+        let signed = self.needs_signature(request);
+
+        if !signed
+            && dest.is_file()
+            && let Some(expected) = checksum
+        {
+            let (expected, path) = ((*expected).clone(), dest.clone());
+
+            if blocking(move || expected.check_file(&path)).await??.0 {
+                tracing::info!("'{}' already fetched", dest.display());
+                self.record(|report| report.already_fetched += 1);
+                return Ok(true);
+            }
+        }
+
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        if let Some(cache) = &self.cache
+            && let Some(expected) = checksum
+        {
+            let (cache, expected, path) =
+                (cache.clone(), (*expected).clone(), dest.clone());
+
+            if blocking(move || cache.restore(&expected, &path, signed))
+                .await??
+            {
+                self.record(|report| report.cache_hits += 1);
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    async fn try_fetch(
+        &self,
+        session: &Session,
+        request: &FetchRequest<'_>,
+    ) -> Result<PathBuf, FetchError> {
+        let FetchRequest { package, url, checksum, dest } = request;
+
+        if self.present(request).await? {
+            return Ok(dest.clone());
+        }
+
+        let mut attempts = Vec::new();
+        let mut needs_network = false;
+
+        let tmp = with_suffix(dest, ".part");
+
+        // Candidates are each mirror, in order, then upstream
+        let origins = self
+            .mirrors
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(UPSTREAM));
+
+        for (location, origin) in
+            self.candidates(package, url).into_iter().zip(origins)
+        {
+            if local_path(&location).is_none() && self.offline {
+                needs_network = true;
+                continue;
+            }
+
+            tracing::info!("fetching '{location}'");
+
+            let mut retries = 0;
+
+            let res = self
+                .fetch_location(
+                    session,
+                    &location,
+                    *checksum,
+                    &tmp,
+                    &mut retries,
+                )
+                .await;
+
+            let actual = match res {
+                Ok(actual) => actual,
+                Err(reason) => {
+                    self.record(|report| report.attempt(origin, retries, None));
+                    tracing::warn!("failed to fetch '{location}': {reason}");
+                    attempts.push((location, reason));
+                    continue;
+                }
+            };
+
+            if let Some(expected) = checksum
+                && actual != **expected
+            {
+                tracing::error!("checksum mismatch for '{location}'");
+                self.record(|report| report.attempt(origin, retries, None));
+                let _ = tokio::fs::remove_file(&tmp).await;
+
+                return Err(FetchError::ChecksumMismatch {
+                    url: location,
+                    expected: (*expected).clone(),
+                    actual,
+                });
+            }
+
+            let signed = match self
+                .verify_signature(session, &location, &tmp)
+                .await
+            {
+                Ok(signed) => signed,
+                Err(error) => {
+                    tracing::error!("rejected '{location}': {error}");
+                    self.record(|report| {
+                        report.attempt(origin, retries, None);
+                    });
+                    let _ = tokio::fs::remove_file(&tmp).await;
+
+                    return Err(FetchError::Signature { url: location, error });
+                }
+            };
+
+            let bytes = tokio::fs::metadata(&tmp).await?.len();
+
+            self.record(|report| {
+                report.attempt(origin, retries, Some(bytes));
+                report.fetched += 1;
+
+                if !attempts.is_empty() {
+                    report.fallbacks += 1;
+                }
+            });
+
+            tokio::fs::rename(&tmp, dest).await?;
+
+            if let Some(cache) = &self.cache
+                && let Some(expected) = checksum
+            {
+                let (cache, expected, path) =
+                    (cache.clone(), (*expected).clone(), dest.clone());
+
+                match blocking(move || cache.insert(&expected, &path, signed))
+                    .await
+                {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) | Err(e) => {
+                        tracing::warn!("failed to cache '{location}': {e}");
+                    }
+                }
+            }
+
+            return Ok(dest.clone());
+        }
+
+        if needs_network {
+            Err(FetchError::Offline { url: url.to_string() })
+        } else {
+            Err(FetchError::NotFound { url: url.to_string(), attempts })
+        }
+    }
+
+    /// Check the signature of the resource fetched from `location` to `path`
+    /// against the trust store for `location`, fetching the signature from
+    /// next to it. Returns whether a signature was verified, which is only
+    /// not the case for a location with no trust store.
+    async fn verify_signature(
+        &self,
+        session: &Session,
+        location: &str,
+        path: &Path,
+    ) -> Result<bool, String> {
+        let Some(store) = self.signatures.trust_store(location) else {
+            return if self.signatures.require_signed {
+                Err("no trust store is configured for it".to_string())
+            } else {
+                Ok(false)
+            };
+        };
+
+        let sig_path = with_suffix(path, ".sig.part");
+
+        for kind in store.kinds() {
+            for suffix in kind.suffixes() {
+                let sig_location = format!("{location}{suffix}");
+
+                if self
+                    .fetch_location(
+                        session,
+                        &sig_location,
+                        None,
+                        &sig_path,
+                        &mut 0,
+                    )
+                    .await
+                    .is_err()
+                {
+                    continue;
+                }
+
+                let (store, file, sig) =
+                    (store.clone(), path.to_path_buf(), sig_path.clone());
+
+                let res = match blocking(move || {
+                    signature::verify(kind, &store, &file, &sig)
+                })
+                .await
+                {
+                    Ok(res) => res,
+                    Err(e) => Err(e.to_string()),
+                };
+
+                let _ = tokio::fs::remove_file(&sig_path).await;
+
+                if res.is_ok() {
+                    tracing::info!(
+                        "verified the {kind} signature of '{location}'"
+                    );
+                }
+
+                return res.map(|()| true);
+            }
+        }
+
+        let kinds = store
+            .kinds()
+            .map(SignatureKind::name)
+            .collect::<Vec<_>>()
+            .join(" or ");
+
+        // Removing the signature must not be a way around verifying it
+        Err(format!("no {kinds} signature was found"))
+    }
+
+    /// Fetch the resource at `location` to `tmp`, returning its checksum and
+    /// adding any retries to `retries`.
+    ///
+    /// Partial downloads are only kept and resumed when `checksum` is known,
+    /// so resumed data is always verified. Otherwise, and for local copies and
+    /// plugin fetchers, a failed attempt leaves nothing behind.
+    async fn fetch_location(
+        &self,
+        session: &Session,
+        location: &str,
+        checksum: Option<&Checksum>,
+        tmp: &Path,
+        retries: &mut u32,
+    ) -> Result<Checksum, String> {
+        let algorithm = checksum.map_or(Algorithm::Sha256, Checksum::algorithm);
+        let resume = checksum.is_some();
+
+        let (url, dest) = (location.to_string(), tmp.to_path_buf());
+
+        let res = match local_path(location) {
+            Some(path) => {
+                Some(blocking(move || copy_to(&path, &dest, algorithm)).await)
+            }
+            None => blocking(move || plugin_fetch(&url, &dest, algorithm))
+                .await
+                .transpose(),
+        };
+
+        let (res, partial) = match res {
+            Some(res) => (res.unwrap_or_else(|e| Err(e.to_string())), false),
+            None => (
+                download::download(
+                    session.client(location),
+                    &self.limiter,
+                    &self.options,
+                    location,
+                    tmp,
+                    algorithm,
+                    resume,
+                    retries,
+                )
+                .await,
+                resume,
+            ),
+        };
+
+        if res.is_err() && !partial {
+            let _ = tokio::fs::remove_file(tmp).await;
+        }
+
+        res
+    }
+}
+
+/// Run `f` on the runtime's blocking thread pool.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> std::io::Result<T> {
+    tokio::task::spawn_blocking(f).await.map_err(std::io::Error::other)
+}
+
+/// Stream `reader` into `dest`, hashing it on the way.
+fn write_hashed(
+    mut reader: impl Read,
+    dest: &Path,
+    algorithm: Algorithm,
+) -> std::io::Result<Checksum> {
+    let mut writer =
+        HashingWriter::new(std::fs::File::create(dest)?, algorithm);
+
+    std::io::copy(&mut reader, &mut writer)?;
+
+    let (checksum, file) = writer.finalize();
+    file.sync_all()?;
+
+    Ok(checksum)
+}
+
+fn copy_to(
+    src: &Path,
+    dest: &Path,
+    algorithm: Algorithm,
+) -> Result<Checksum, String> {
+    let file = std::fs::File::open(src).map_err(|e| e.to_string())?;
+    write_hashed(file, dest, algorithm).map_err(|e| e.to_string())
+}
+
+/// Fetch `url` to `dest` with the fetcher a plugin registered for its
+/// scheme, or `None` if there is no such fetcher.
+#[cfg(feature = "python")]
+fn plugin_fetch(
+    url: &str,
+    dest: &Path,
+    algorithm: Algorithm,
+) -> Option<Result<Checksum, String>> {
+    let hook = plugins::fetcher(url)?;
+
+    let res = Python::attach(|py| hook.call1(py, (url, dest)))
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            crate::util::digest::digest_file(dest, algorithm)
+                .map_err(|e| e.to_string())
+        });
+
+    Some(res)
+}
+
+#[cfg(not(feature = "python"))]
+const fn plugin_fetch(
+    _url: &str,
+    _dest: &Path,
+    _algorithm: Algorithm,
+) -> Option<Result<Checksum, String>> {
+    None
+}
@@ -0,0 +1,152 @@
+//! Proxies and certificate authorities used to reach remote locations.
+//!
+//! By default, requests go through the proxies named by the standard
+//! `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` environment variables, or
+//! their lowercase forms, except for hosts listed in `NO_PROXY`. Server
+//! certificates are checked against the built-in roots, plus the PEM bundle
+//! named by `SSL_CERT_FILE` if it is set.
+//!
+//! [`NetworkConfig`] overrides both, either for every location or only for
+//! locations under a prefix such as the root of a mirror (see
+//! [`NetworkConfig::overrides`]). Each distinct configuration gets its own
+//! HTTP client, built by [`Connection::client`].
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+
+/// The proxy value which disables proxying, even if the proxy environment
+/// variables are set
+pub const DIRECT: &str = "direct";
+
+/// How to connect to a location
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Connection {
+    /// The proxy to connect through, such as `http://proxy.example.com:3128`,
+    /// or [`DIRECT`] to connect without one. Unset uses the proxy environment
+    /// variables
+    pub proxy: Option<String>,
+
+    /// A PEM bundle of certificate authorities trusted in addition to the
+    /// built-in roots. Unset uses `SSL_CERT_FILE`, if it is set
+    pub ca_bundle: Option<PathBuf>,
+}
+
+impl Connection {
+    /// `self`, with anything unset taken from `fallback`
+    #[must_use]
+    pub fn or(&self, fallback: &Self) -> Self {
+        Self {
+            proxy: self.proxy.clone().or_else(|| fallback.proxy.clone()),
+            ca_bundle: self
+                .ca_bundle
+                .clone()
+                .or_else(|| fallback.ca_bundle.clone()),
+        }
+    }
+
+    /// The CA bundle to trust: [`Self::ca_bundle`], or `SSL_CERT_FILE`
+    fn ca_bundle(&self) -> Option<PathBuf> {
+        self.ca_bundle.clone().or_else(|| {
+            std::env::var_os("SSL_CERT_FILE")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+        })
+    }
+
+    /// Build an HTTP client which connects as configured.
+    ///
+    /// * `user_agent`: Sent with every request
+    /// * `timeout`: How long connecting may take, or a response may receive
+    ///   nothing, before the request fails
+    ///
+    /// # Errors
+    /// Errors if the proxy URL is invalid or the CA bundle cannot be read or
+    /// contains no valid certificates.
+    pub fn client(
+        &self,
+        user_agent: &str,
+        timeout: Duration,
+    ) -> Result<Client, String> {
+        let mut builder = Client::builder()
+            .user_agent(user_agent)
+            .connect_timeout(timeout)
+            .read_timeout(timeout);
+
+        match self.proxy.as_deref() {
+            None => {}
+            Some(DIRECT) => builder = builder.no_proxy(),
+            Some(url) => {
+                let proxy = Proxy::all(url)
+                    .map_err(|e| format!("invalid proxy '{url}': {e}"))?
+                    .no_proxy(NoProxy::from_env());
+
+                builder = builder.proxy(proxy);
+            }
+        }
+
+        if let Some(path) = self.ca_bundle() {
+            let pem = std::fs::read(&path).map_err(|e| {
+                format!("cannot read CA bundle '{}': {e}", path.display())
+            })?;
+
+            let certs = Certificate::from_pem_bundle(&pem).map_err(|e| {
+                format!("invalid CA bundle '{}': {e}", path.display())
+            })?;
+
+            if certs.is_empty() {
+                return Err(format!(
+                    "CA bundle '{}' contains no certificates",
+                    path.display()
+                ));
+            }
+
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+/// Proxies and certificate authorities to use, for every location and for
+/// locations under particular prefixes
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// The proxy to connect through. See [`Connection::proxy`]
+    pub proxy: Option<String>,
+
+    /// A PEM bundle of certificate authorities to trust. See
+    /// [`Connection::ca_bundle`]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Settings for locations under a prefix, such as the root of a mirror,
+    /// overriding those above. The longest matching prefix applies
+    pub overrides: BTreeMap<String, Connection>,
+}
+
+impl NetworkConfig {
+    /// How to connect to locations with no override
+    #[must_use]
+    pub fn default_connection(&self) -> Connection {
+        Connection {
+            proxy: self.proxy.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+        }
+    }
+
+    /// Each prefix with an override and how to connect to locations under it
+    pub fn override_connections(
+        &self,
+    ) -> impl Iterator<Item = (&str, Connection)> {
+        let default = self.default_connection();
+
+        self.overrides
+            .iter()
+            .map(move |(prefix, conn)| (prefix.as_str(), conn.or(&default)))
+    }
+}
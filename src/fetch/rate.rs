@@ -0,0 +1,79 @@
+//! A bandwidth limit shared by every download.
+//!
+//! [`RateLimiter`] is a token bucket measured in bytes. Each chunk of a
+//! download is paid for before it is written, so concurrent downloads share
+//! the limit between them rather than each receiving it in full. The bucket
+//! holds at most one second of tokens, so an idle period allows only a short
+//! burst above the limit.
+
+use std::{
+    num::NonZeroU64,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes which may be downloaded without waiting. Negative once the limit
+    /// has been exceeded, in which case it is the debt still to be waited out
+    available: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Bytes per second, or `None` for no limit
+    rate: Option<NonZeroU64>,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    #[must_use]
+    pub fn new(rate: Option<NonZeroU64>) -> Self {
+        Self {
+            rate,
+            bucket: Mutex::new(Bucket {
+                available: 0.0,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// How long to wait before `bytes` more may be downloaded. The bytes are
+    /// taken from the bucket immediately, so later callers wait behind them.
+    // Rates and chunk sizes are far too small to lose precision as an `f64`
+    #[allow(clippy::cast_precision_loss)]
+    fn reserve(&self, bytes: usize) -> Duration {
+        let Some(rate) = self.rate else {
+            return Duration::ZERO;
+        };
+
+        let rate = rate.get() as f64;
+        let now = Instant::now();
+
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * rate;
+        bucket.available = (bucket.available + refill).min(rate);
+        bucket.updated = now;
+        bucket.available -= bytes as f64;
+
+        if bucket.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.available / rate)
+        }
+    }
+
+    /// Wait until `bytes` more may be downloaded.
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.reserve(bytes);
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! How reliably resources were fetched.
+//!
+//! A [`Fetcher`](crate::fetch::Fetcher) records the outcome of every fetch in
+//! a [`FetchReport`]: whether the resource was already present, restored from
+//! the download cache or downloaded, how many retries it took, and whether it
+//! was only fetched after an earlier location failed. Outcomes are also
+//! broken down by mirror, so mirrors which are often tried and fail can be
+//! moved later in the configured order, or removed.
+//!
+//! Nothing is sent anywhere: the report is only printed or written to the
+//! command's JSON output.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::util::disk::format_bytes;
+
+/// The key in [`FetchReport::locations`] for upstream URLs
+pub const UPSTREAM: &str = "upstream";
+
+/// The fetches attempted from one mirror, or from upstream
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LocationStats {
+    /// Resources fetched from this location
+    pub fetched: usize,
+
+    /// Attempts which failed, including resources which did not match their
+    /// checksum or signature
+    pub failed: usize,
+
+    /// Requests retried after a transient error
+    pub retries: u32,
+
+    /// Bytes fetched from this location
+    pub bytes: u64,
+}
+
+/// A summary of every fetch made by a fetcher
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FetchReport {
+    /// Resources requested
+    pub requests: usize,
+
+    /// Resources which were already present and matched their checksum
+    pub already_fetched: usize,
+
+    /// Resources restored from the download cache
+    pub cache_hits: usize,
+
+    /// Resources fetched from a mirror or upstream
+    pub fetched: usize,
+
+    /// Resources only fetched after an earlier location failed
+    pub fallbacks: usize,
+
+    /// Resources which could not be fetched
+    pub failed: usize,
+
+    /// Requests retried after a transient error, across every location
+    pub retries: u32,
+
+    /// Bytes fetched, across every location
+    pub bytes: u64,
+
+    /// Attempts by mirror, as configured, or [`UPSTREAM`]
+    pub locations: BTreeMap<String, LocationStats>,
+}
+
+impl FetchReport {
+    /// Record an attempt to fetch from `location`, which failed or fetched
+    /// `bytes` after `retries` retries.
+    pub(crate) fn attempt(
+        &mut self,
+        location: &str,
+        retries: u32,
+        bytes: Option<u64>,
+    ) {
+        let stats = self.locations.entry(location.to_string()).or_default();
+
+        stats.retries += retries;
+        self.retries += retries;
+
+        if let Some(bytes) = bytes {
+            stats.fetched += 1;
+            stats.bytes += bytes;
+            self.bytes += bytes;
+        } else {
+            stats.failed += 1;
+        }
+    }
+}
+
+impl std::fmt::Display for FetchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} resource(s): {} already present, {} from the cache, {} \
+             fetched ({} after a fallback), {} failed",
+            self.requests,
+            self.already_fetched,
+            self.cache_hits,
+            self.fetched,
+            self.fallbacks,
+            self.failed
+        )?;
+
+        writeln!(
+            f,
+            "{} fetched, {} retries",
+            format_bytes(self.bytes),
+            self.retries
+        )?;
+
+        for (location, stats) in &self.locations {
+            writeln!(
+                f,
+                "- {location}: {} fetched, {} failed, {} retries, {}",
+                stats.fetched,
+                stats.failed,
+                stats.retries,
+                format_bytes(stats.bytes)
+            )?;
+        }
+
+        Ok(())
+    }
+}
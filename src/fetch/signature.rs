@@ -0,0 +1,192 @@
+//! Verification of signatures on fetched resources.
+//!
+//! A resource fetched from a location covered by a [`TrustStore`] must be
+//! signed by one of the keys or identities the store trusts. Trust stores are
+//! keyed by location prefix, typically the root of a mirror, and the longest
+//! matching prefix applies (see [`SignatureConfig::trust_store`]).
+//!
+//! Signatures are published next to the resource they sign:
+//!
+//! - a detached GPG signature at `<location>.sig` or `<location>.asc`,
+//!   verified with `gpgv` against [`TrustStore::keyring`]
+//! - a sigstore bundle at `<location>.sigstore.json`, verified with `cosign
+//!   verify-blob` against [`TrustStore::identity`] and
+//!   [`TrustStore::issuer`]
+//!
+//! A signature which fails to verify is always an error, as is a resource
+//! with no signature fetched from a location with a trust store. A resource
+//! fetched from a location with no trust store is only accepted unless
+//! [`SignatureConfig::require_signed`] is set.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fetch::under_prefix;
+
+/// A kind of signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureKind {
+    Gpg,
+    Sigstore,
+}
+
+impl SignatureKind {
+    pub const ALL: [Self; 2] = [Self::Gpg, Self::Sigstore];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Gpg => "gpg",
+            Self::Sigstore => "sigstore",
+        }
+    }
+
+    /// Suffixes added to the location of a resource to find its signature
+    #[must_use]
+    pub const fn suffixes(self) -> &'static [&'static str] {
+        match self {
+            Self::Gpg => &[".sig", ".asc"],
+            Self::Sigstore => &[".sigstore.json"],
+        }
+    }
+}
+
+impl std::fmt::Display for SignatureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SignatureKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|kind| kind.name() == s).ok_or_else(|| {
+            format!("unknown signature kind '{s}', expected gpg or sigstore")
+        })
+    }
+}
+
+/// The signers trusted for resources fetched from a location
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrustStore {
+    /// Keyring holding the GPG keys trusted to sign resources
+    pub keyring: Option<PathBuf>,
+
+    /// The identity sigstore certificates must be issued to, such as an email
+    /// address or a CI workflow URL
+    pub identity: Option<String>,
+
+    /// The OIDC issuer which must have issued sigstore certificates
+    pub issuer: Option<String>,
+}
+
+impl TrustStore {
+    /// The kinds of signature this store can verify
+    pub fn kinds(&self) -> impl Iterator<Item = SignatureKind> {
+        let gpg = self.keyring.is_some().then_some(SignatureKind::Gpg);
+        let sigstore = (self.identity.is_some() && self.issuer.is_some())
+            .then_some(SignatureKind::Sigstore);
+
+        sigstore.into_iter().chain(gpg)
+    }
+}
+
+/// Which signatures fetched resources must carry
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignatureConfig {
+    /// Reject resources fetched from a location with no trust store.
+    /// Resources fetched from a location with one must always be signed
+    pub require_signed: bool,
+
+    /// Trust stores, keyed by the location prefix they apply to, such as the
+    /// root of a mirror
+    pub trust: BTreeMap<String, TrustStore>,
+}
+
+impl SignatureConfig {
+    /// The trust store for resources fetched from `location`: that of the
+    /// longest prefix of `location`, if any.
+    #[must_use]
+    pub fn trust_store(&self, location: &str) -> Option<&TrustStore> {
+        self.trust
+            .iter()
+            .filter(|(prefix, _)| under_prefix(location, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, store)| store)
+    }
+}
+
+/// Run `command`, failing with its output if it does not succeed.
+fn run(mut command: Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run '{program}': {e}"))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{program}' rejected the signature: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Verify that `signature`, of kind `kind`, is a valid signature of `file`
+/// by a signer trusted by `store`.
+///
+/// # Errors
+/// Errors if `store` cannot verify signatures of this kind, the verifier
+/// cannot be run or the signature is not valid.
+pub fn verify(
+    kind: SignatureKind,
+    store: &TrustStore,
+    file: &Path,
+    signature: &Path,
+) -> Result<(), String> {
+    match kind {
+        SignatureKind::Gpg => {
+            let keyring =
+                store.keyring.as_ref().ok_or("no GPG keyring is configured")?;
+
+            let mut command = Command::new("gpgv");
+            command.arg("--keyring").arg(keyring).arg(signature).arg(file);
+
+            run(command)
+        }
+        SignatureKind::Sigstore => {
+            let (Some(identity), Some(issuer)) =
+                (&store.identity, &store.issuer)
+            else {
+                return Err("no sigstore identity and issuer are configured"
+                    .to_string());
+            };
+
+            let mut command = Command::new("cosign");
+            command
+                .arg("verify-blob")
+                .arg("--bundle")
+                .arg(signature)
+                .arg("--certificate-identity")
+                .arg(identity)
+                .arg("--certificate-oidc-issuer")
+                .arg(issuer)
+                .arg(file);
+
+            run(command)
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! A download cache shared by every installation, keyed by checksum.
+//!
+//! Once a resource has been fetched and verified, it is added to the cache at
+//! `<algorithm>/<first two digits>/<digest>`. Any later fetch of a resource
+//! with the same checksum, from any URL and into any destination, is served
+//! from the cache instead, including when offline. Since entries are only
+//! added once verified, they are not hashed again when used.
+//!
+//! An entry whose signature was verified (see [`crate::fetch::signature`]) is
+//! recorded as signed, by an empty file next to it with a `.signed`
+//! extension. A resource which must be signed is only restored from a signed
+//! entry. Since an entry is keyed by checksum, a signature verified once holds
+//! for every later use, so an entry stays signed once it is.
+//!
+//! Using an entry updates its modification time, so entries which have not
+//! been used for a while can be removed with
+//! [`remove_unused`](crate::util::disk::remove_unused).
+
+use std::path::{Path, PathBuf};
+
+use crate::util::{digest::Checksum, disk, paths};
+
+#[derive(Clone, Debug)]
+pub struct DownloadCache {
+    dir: PathBuf,
+}
+
+impl Default for DownloadCache {
+    fn default() -> Self {
+        Self::new(paths::cache_dir().join("downloads"))
+    }
+}
+
+/// Hard link `src` to `dest`, copying it if they are on different file
+/// systems. `dest` is replaced if it exists.
+fn link_or_copy(src: &Path, dest: &Path) -> std::io::Result<()> {
+    let tmp = dest.with_extension("link");
+    let _ = std::fs::remove_file(&tmp);
+
+    if std::fs::hard_link(src, &tmp).is_err() {
+        std::fs::copy(src, &tmp)?;
+    }
+
+    std::fs::rename(tmp, dest)
+}
+
+impl DownloadCache {
+    #[must_use]
+    pub const fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, checksum: &Checksum) -> PathBuf {
+        let hex = checksum.hex();
+
+        self.dir.join(checksum.algorithm().name()).join(&hex[..2]).join(hex)
+    }
+
+    fn signed_path(entry: &Path) -> PathBuf {
+        entry.with_extension("signed")
+    }
+
+    /// Place the cached resource with `checksum` at `dest`, returning whether
+    /// there was one.
+    ///
+    /// * `signed`: Only use an entry recorded as signed
+    ///
+    /// # Errors
+    /// Errors if the resource is cached but cannot be placed at `dest`.
+    pub fn restore(
+        &self,
+        checksum: &Checksum,
+        dest: &Path,
+        signed: bool,
+    ) -> std::io::Result<bool> {
+        let entry = self.entry_path(checksum);
+
+        if !entry.is_file() {
+            return Ok(false);
+        }
+
+        if signed && !Self::signed_path(&entry).is_file() {
+            tracing::info!(
+                "not using cached download for {checksum}, which was not \
+                 signed"
+            );
+            return Ok(false);
+        }
+
+        tracing::info!("using cached download for {checksum}");
+
+        disk::touch(&entry);
+        disk::touch(&Self::signed_path(&entry));
+        link_or_copy(&entry, dest)?;
+
+        Ok(true)
+    }
+
+    /// Add the verified resource at `src`, whose checksum is `checksum`.
+    ///
+    /// * `signed`: Whether the signature of the resource was verified. An
+    ///   entry already recorded as signed stays signed
+    ///
+    /// # Errors
+    /// Errors if the resource cannot be added to the cache.
+    pub fn insert(
+        &self,
+        checksum: &Checksum,
+        src: &Path,
+        signed: bool,
+    ) -> std::io::Result<()> {
+        let entry = self.entry_path(checksum);
+
+        if let Some(parent) = entry.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        link_or_copy(src, &entry)?;
+
+        if signed {
+            std::fs::write(Self::signed_path(&entry), "")?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove every cached entry.
+    ///
+    /// # Errors
+    /// Errors if the cache directory exists but cannot be removed.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+}
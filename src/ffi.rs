@@ -0,0 +1,324 @@
+//! A C interface to the concretizer, for embedding zpack in C and C++ tools.
+//!
+//! Packages are collected in a [`ZpackOutlines`] set, either as JSON
+//! serialized [`PackageOutline`]s or by name, and constraints are added to
+//! them in constraint syntax (see [`crate::constraint::syntax`]). Solving
+//! returns the [`ConcreteSpec`] as JSON.
+//!
+//! ```c
+//! ZpackOutlines *set = zpack_outlines_new();
+//! zpack_outlines_add_package(set, "hello");
+//! zpack_outlines_add_constraint(set, "hello", "depends(zlib)");
+//! zpack_outlines_add_package(set, "zlib");
+//!
+//! const char *roots[] = {"hello"};
+//! char *spec = zpack_solve(set, roots, 1, true);
+//!
+//! if (spec) {
+//!     puts(spec);
+//!     zpack_string_free(spec);
+//! } else {
+//!     fprintf(stderr, "%s\n", zpack_last_error());
+//! }
+//!
+//! zpack_outlines_free(set);
+//! ```
+//!
+//! Functions returning `int` return `0` on success and `-1` on failure, and
+//! functions returning pointers return null on failure. The reason for the
+//! last failure on the calling thread is given by [`zpack_last_error`].
+//! Panics never unwind into the caller: a function which panics fails in the
+//! same way, with the panic message as its error. `include/zpack.h` declares
+//! the interface.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char, c_int},
+    panic::{AssertUnwindSafe, catch_unwind},
+};
+
+use crate::{
+    cli::SolveOptions,
+    constraint::{Constraint, syntax},
+    package::outline::PackageOutline,
+    spec::ConcreteSpec,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Record `msg` as the last error on this thread.
+fn set_error(msg: impl Into<String>) {
+    // Interior nul bytes would truncate the message, so replace them
+    let msg = msg.into().replace('\0', "\u{FFFD}");
+
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(msg).ok());
+}
+
+/// Run `f`, turning a panic into an error, since unwinding across the C
+/// boundary would abort the caller.
+fn guarded<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+
+        Err(format!("zpack panicked: {msg}"))
+    })
+}
+
+/// Run `f`, recording its error and returning `-1` if it fails or panics.
+fn status(f: impl FnOnce() -> Result<(), String>) -> c_int {
+    match guarded(f) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+/// Run `f`, recording its error and returning null if it fails or panics.
+fn pointer<T>(f: impl FnOnce() -> Result<*mut T, String>) -> *mut T {
+    match guarded(f) {
+        Ok(ptr) => ptr,
+        Err(e) => {
+            set_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Borrow a nul-terminated string passed in from C.
+///
+/// # Safety
+/// `ptr` must be null or point to a nul-terminated string which outlives the
+/// returned reference.
+unsafe fn str_arg<'a>(
+    ptr: *const c_char,
+    what: &str,
+) -> Result<&'a str, String> {
+    if ptr.is_null() {
+        return Err(format!("{what} is null"));
+    }
+
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|e| format!("{what} is not valid UTF-8: {e}"))
+}
+
+/// A set of package outlines to concretize against
+#[derive(Default)]
+pub struct ZpackOutlines {
+    outlines: Vec<PackageOutline>,
+}
+
+impl ZpackOutlines {
+    fn push(&mut self, outline: PackageOutline) -> Result<(), String> {
+        if self.outlines.iter().any(|o| o.name == outline.name) {
+            return Err(format!(
+                "package '{}' is already defined",
+                outline.name
+            ));
+        }
+
+        self.outlines.push(outline);
+        Ok(())
+    }
+
+    fn solve(
+        &self,
+        roots: &[String],
+        deterministic: bool,
+    ) -> Result<ConcreteSpec, String> {
+        let options = SolveOptions { deterministic, ..Default::default() };
+        let mut stats = crate::package::stats::SolveStats::new();
+
+        crate::cli::concretize_profiled(
+            self.outlines.clone(),
+            roots,
+            &options,
+            &mut stats,
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Create an empty outline set. Free it with [`zpack_outlines_free`].
+#[unsafe(no_mangle)]
+pub extern "C" fn zpack_outlines_new() -> *mut ZpackOutlines {
+    pointer(|| Ok(Box::into_raw(Box::default())))
+}
+
+/// Free an outline set.
+///
+/// # Safety
+/// `set` must be null or returned by [`zpack_outlines_new`] and not already
+/// freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn zpack_outlines_free(set: *mut ZpackOutlines) {
+    status(|| {
+        if !set.is_null() {
+            drop(unsafe { Box::from_raw(set) });
+        }
+
+        Ok(())
+    });
+}
+
+/// Add a package given as a JSON serialized [`PackageOutline`].
+///
+/// # Safety
+/// `set` must be a live outline set and `json` a nul-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn zpack_outlines_add_json(
+    set: *mut ZpackOutlines,
+    json: *const c_char,
+) -> c_int {
+    status(|| {
+        let set = unsafe { set.as_mut() }.ok_or("outline set is null")?;
+        let json = unsafe { str_arg(json, "json") }?;
+
+        let outline = serde_json::from_str::<PackageOutline>(json)
+            .map_err(|e| format!("invalid package outline: {e}"))?;
+
+        set.push(outline)
+    })
+}
+
+/// Add a package with no constraints, to which constraints can be added with
+/// [`zpack_outlines_add_constraint`].
+///
+/// # Safety
+/// `set` must be a live outline set and `name` a nul-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn zpack_outlines_add_package(
+    set: *mut ZpackOutlines,
+    name: *const c_char,
+) -> c_int {
+    status(|| {
+        let set = unsafe { set.as_mut() }.ok_or("outline set is null")?;
+        let name = unsafe { str_arg(name, "package name") }?;
+
+        set.push(PackageOutline {
+            name: name.to_string(),
+            ..Default::default()
+        })
+    })
+}
+
+/// Add a constraint, written in constraint syntax, to a package in the set.
+/// `+name` and `~name` refer to the options of that package.
+///
+/// # Safety
+/// `set` must be a live outline set and `package` and `constraint`
+/// nul-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn zpack_outlines_add_constraint(
+    set: *mut ZpackOutlines,
+    package: *const c_char,
+    constraint: *const c_char,
+) -> c_int {
+    status(|| {
+        let set = unsafe { set.as_mut() }.ok_or("outline set is null")?;
+        let package = unsafe { str_arg(package, "package name") }?;
+        let txt = unsafe { str_arg(constraint, "constraint") }?;
+
+        let outline = set
+            .outlines
+            .iter_mut()
+            .find(|o| o.name == package)
+            .ok_or_else(|| format!("unknown package '{package}'"))?;
+
+        let constraint =
+            Constraint::parse_in(txt, Some(package)).map_err(syntax::render)?;
+
+        outline.constraints.push(constraint);
+        Ok(())
+    })
+}
+
+/// Concretize `roots` against the outline set, returning the concrete spec as
+/// JSON. Free the result with [`zpack_string_free`].
+///
+/// * `deterministic`: Fix the solver's random seeds
+///
+/// # Safety
+/// `set` must be a live outline set and `roots` must point to `n_roots`
+/// nul-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn zpack_solve(
+    set: *const ZpackOutlines,
+    roots: *const *const c_char,
+    n_roots: usize,
+    deterministic: bool,
+) -> *mut c_char {
+    pointer(|| {
+        let set = unsafe { set.as_ref() }.ok_or("outline set is null")?;
+
+        if roots.is_null() && n_roots > 0 {
+            return Err("roots is null".to_string());
+        }
+
+        let roots = (0..n_roots)
+            .map(|idx| {
+                unsafe { str_arg(*roots.add(idx), "root") }.map(str::to_string)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let spec = set.solve(&roots, deterministic)?;
+
+        let json = serde_json::to_string(&spec).map_err(|e| e.to_string())?;
+        CString::new(json).map(CString::into_raw).map_err(|e| e.to_string())
+    })
+}
+
+/// The reason the last call on this thread failed, or null if none has. The
+/// string is owned by zpack and valid until the next failing call on this
+/// thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn zpack_last_error() -> *const c_char {
+    catch_unwind(|| {
+        LAST_ERROR.with(|e| {
+            e.borrow().as_ref().map_or(std::ptr::null(), |e| e.as_ptr())
+        })
+    })
+    .unwrap_or(std::ptr::null())
+}
+
+/// Free a string returned by zpack.
+///
+/// # Safety
+/// `ptr` must be null or returned by zpack and not already freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn zpack_string_free(ptr: *mut c_char) {
+    status(|| {
+        if !ptr.is_null() {
+            drop(unsafe { CString::from_raw(ptr) });
+        }
+
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_errors() {
+        assert_eq!(status(|| panic!("boom")), -1);
+
+        let msg = unsafe { CStr::from_ptr(zpack_last_error()) };
+        assert_eq!(msg.to_str(), Ok("zpack panicked: boom"));
+
+        let ptr = pointer::<c_char>(|| panic!("{}", String::from("again")));
+        assert!(ptr.is_null());
+
+        let msg = unsafe { CStr::from_ptr(zpack_last_error()) };
+        assert_eq!(msg.to_str(), Ok("zpack panicked: again"));
+    }
+}
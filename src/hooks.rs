@@ -0,0 +1,283 @@
+//! Site hooks run at points in zpack's lifecycle.
+//!
+//! Hooks are configured under `hooks` in the configuration file, as a list of
+//! actions for each [`HookEvent`]. Each action is either a shell command or a
+//! Python function, written as `module:function`:
+//!
+//! ```yaml
+//! hooks:
+//!   post_solve:
+//!     - python: site_policy:check
+//!   post_install:
+//!     - command: /opt/site/bin/scan-binaries
+//! ```
+//!
+//! Every action receives a [`HookPayload`] describing the event as JSON,
+//! including the concrete spec once there is one. Shell commands are run with
+//! `sh -c`, read the payload from standard input and also have the event, and
+//! the package and prefix being installed, in `ZPACK_HOOK_EVENT`,
+//! `ZPACK_PACKAGE` and `ZPACK_PREFIX`. Python functions are called with the
+//! payload as a dictionary.
+//!
+//! Actions run in order and stop at the first failure, which aborts whatever
+//! triggered the event: a failing `post_solve` hook rejects the
+//! concretization and a failing `post_install` hook fails the installation.
+//!
+//! Like plugins, hooks run without any sandbox restrictions.
+
+use std::{io::Write, path::Path, process::Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::spec::concrete::ConcreteSpec;
+
+/// A point in zpack's lifecycle at which hooks are run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    /// Before concretizing, with the requested roots
+    PreSolve,
+
+    /// After concretizing, with the concrete spec
+    PostSolve,
+
+    /// Before installing each package into its (empty) prefix
+    PreInstall,
+
+    /// After installing each package
+    PostInstall,
+}
+
+/// Something to run when an event occurs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    /// A shell command
+    Command(String),
+
+    /// A Python function, written as `module:function`
+    Python(String),
+}
+
+/// The actions to run for each event
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookConfig {
+    pub pre_solve: Vec<HookAction>,
+    pub post_solve: Vec<HookAction>,
+    pub pre_install: Vec<HookAction>,
+    pub post_install: Vec<HookAction>,
+}
+
+/// What a hook is told about the event which triggered it
+#[derive(Clone, Debug, Serialize)]
+pub struct HookPayload<'a> {
+    pub event: HookEvent,
+
+    /// The roots being concretized or installed
+    pub roots: &'a [String],
+
+    /// The concrete spec, for every event except [`HookEvent::PreSolve`]
+    pub spec: Option<&'a ConcreteSpec>,
+
+    /// The package being installed
+    pub package: Option<&'a str>,
+
+    /// The prefix the package is installed to
+    pub prefix: Option<&'a Path>,
+}
+
+#[derive(Debug)]
+pub struct HookError {
+    pub event: HookEvent,
+    pub action: HookAction,
+    pub reason: String,
+}
+
+impl HookEvent {
+    pub const ALL: [Self; 4] =
+        [Self::PreSolve, Self::PostSolve, Self::PreInstall, Self::PostInstall];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::PreSolve => "pre_solve",
+            Self::PostSolve => "post_solve",
+            Self::PreInstall => "pre_install",
+            Self::PostInstall => "post_install",
+        }
+    }
+}
+
+impl std::fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::fmt::Display for HookAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Command(command) => write!(f, "command '{command}'"),
+            Self::Python(func) => write!(f, "python function '{func}'"),
+        }
+    }
+}
+
+impl std::fmt::Display for HookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} hook {} failed: {}", self.event, self.action, self.reason)
+    }
+}
+
+impl std::error::Error for HookError {}
+
+impl<'a> HookPayload<'a> {
+    /// The payload of [`HookEvent::PreSolve`]
+    #[must_use]
+    pub const fn pre_solve(roots: &'a [String]) -> Self {
+        Self {
+            event: HookEvent::PreSolve,
+            roots,
+            spec: None,
+            package: None,
+            prefix: None,
+        }
+    }
+
+    /// The payload of [`HookEvent::PostSolve`]
+    #[must_use]
+    pub fn post_solve(spec: &'a ConcreteSpec) -> Self {
+        Self {
+            event: HookEvent::PostSolve,
+            roots: &spec.roots,
+            spec: Some(spec),
+            package: None,
+            prefix: None,
+        }
+    }
+
+    /// The payload of [`HookEvent::PreInstall`] or [`HookEvent::PostInstall`]
+    #[must_use]
+    pub fn install(
+        event: HookEvent,
+        spec: &'a ConcreteSpec,
+        package: &'a str,
+        prefix: &'a Path,
+    ) -> Self {
+        Self {
+            event,
+            roots: &spec.roots,
+            spec: Some(spec),
+            package: Some(package),
+            prefix: Some(prefix),
+        }
+    }
+}
+
+impl HookConfig {
+    /// The actions run for `event`
+    #[must_use]
+    pub fn actions(&self, event: HookEvent) -> &[HookAction] {
+        match event {
+            HookEvent::PreSolve => &self.pre_solve,
+            HookEvent::PostSolve => &self.post_solve,
+            HookEvent::PreInstall => &self.pre_install,
+            HookEvent::PostInstall => &self.post_install,
+        }
+    }
+
+    /// Run every action for the event of `payload`, in order.
+    ///
+    /// # Errors
+    /// Errors with the first action which fails.
+    pub fn run(&self, payload: &HookPayload<'_>) -> Result<(), HookError> {
+        let actions = self.actions(payload.event);
+
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        let json = serde_json::to_string(payload).map_err(|e| HookError {
+            event: payload.event,
+            action: actions[0].clone(),
+            reason: format!("failed to serialize the payload: {e}"),
+        })?;
+
+        for action in actions {
+            tracing::info!("running {} hook {action}", payload.event);
+
+            let res = match action {
+                HookAction::Command(command) => {
+                    run_command(command, payload, &json)
+                }
+                HookAction::Python(func) => run_python(func, &json),
+            };
+
+            res.map_err(|reason| HookError {
+                event: payload.event,
+                action: action.clone(),
+                reason,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+fn run_command(
+    command: &str,
+    payload: &HookPayload<'_>,
+    json: &str,
+) -> Result<(), String> {
+    let mut cmd = std::process::Command::new("sh");
+
+    cmd.arg("-c")
+        .arg(command)
+        .env("ZPACK_HOOK_EVENT", payload.event.name())
+        .stdin(Stdio::piped());
+
+    if let Some(package) = payload.package {
+        cmd.env("ZPACK_PACKAGE", package);
+    }
+
+    if let Some(prefix) = payload.prefix {
+        cmd.env("ZPACK_PREFIX", prefix);
+    }
+
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The hook need not read its input, so a closed pipe is not an error
+        if let Err(e) = stdin.write_all(json.as_bytes())
+            && e.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            return Err(e.to_string());
+        }
+    }
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+
+    if status.success() { Ok(()) } else { Err(format!("exited with {status}")) }
+}
+
+#[cfg(feature = "python")]
+fn run_python(func: &str, json: &str) -> Result<(), String> {
+    use pyo3::prelude::*;
+
+    let Some((module, name)) = func.split_once(':') else {
+        return Err("expected 'module:function'".into());
+    };
+
+    Python::attach(|py| -> PyResult<()> {
+        let payload = py.import("json")?.call_method1("loads", (json,))?;
+        py.import(module)?.getattr(name)?.call1((payload,))?;
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "python"))]
+fn run_python(_func: &str, _json: &str) -> Result<(), String> {
+    Err("zpack was built without Python support".into())
+}
@@ -0,0 +1,238 @@
+//! Cache of package outlines extracted from Python files.
+//!
+//! Executing a package file and calling `outline()` on every package is slow
+//! for large repositories, and the result only depends on the contents of the
+//! file. Extracted outlines are serialized to the cache directory, keyed by the
+//! path of the package file, and reused while the file is unchanged.
+//!
+//! An entry is valid if the file's modification time and size match those
+//! recorded. If they do not, the file is hashed and the entry is still reused
+//! if the contents are identical (for example, after a `touch` or a fresh
+//! checkout). Entries written by a different version of zpack are ignored.
+//!
+//! Package files may only import allow-listed modules when sandboxed (see
+//! [`super::sandbox`]), so the contents of the file itself are a sufficient
+//! cache key. Without the sandbox, a package file could import a local module
+//! whose changes would not be noticed, so `--no-cache` should be used as well.
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interface::reader::ReadError,
+    package::outline::PackageOutline,
+    util::{
+        digest::{Algorithm, Checksum, digest_file},
+        disk, paths,
+    },
+};
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    zpack_version: String,
+    modified: Option<SystemTime>,
+    len: u64,
+    source: Checksum,
+    outlines: Vec<PackageOutline>,
+}
+
+/// The result of looking up a package file in the cache. A miss holds the
+/// entry to fill in once the file has been loaded.
+enum Lookup {
+    Hit(Vec<PackageOutline>),
+    Miss(Entry),
+}
+
+pub struct OutlineCache {
+    dir: PathBuf,
+}
+
+impl Default for OutlineCache {
+    fn default() -> Self {
+        Self::new(paths::cache_dir().join("outlines"))
+    }
+}
+
+impl OutlineCache {
+    #[must_use]
+    pub const fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let key = blake3::hash(path.as_os_str().as_encoded_bytes());
+
+        self.dir.join(format!("{}.json", &key.to_hex()[..32]))
+    }
+
+    fn read_entry(&self, path: &Path) -> Option<Entry> {
+        let txt = std::fs::read_to_string(self.entry_path(path)).ok()?;
+
+        match serde_json::from_str::<Entry>(&txt) {
+            Ok(entry) if entry.zpack_version == env!("CARGO_PKG_VERSION") => {
+                Some(entry)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("ignoring invalid outline cache entry: {e}");
+                None
+            }
+        }
+    }
+
+    fn write_entry(&self, path: &Path, entry: &Entry) -> std::io::Result<()> {
+        let dest = self.entry_path(path);
+        let tmp = dest.with_extension("tmp");
+
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        std::fs::rename(tmp, dest)
+    }
+
+    /// Look up the entry for the package file at `path`. An entry whose file
+    /// was touched but is unchanged is rewritten with the new metadata and
+    /// counts as a hit.
+    fn lookup(&self, path: &Path) -> Result<Lookup, ReadError> {
+        let metadata = std::fs::metadata(path).map_err(ReadError::IoError)?;
+        let modified = metadata.modified().ok();
+        let len = metadata.len();
+
+        let cached = match self.read_entry(path) {
+            Some(entry)
+                if modified.is_some()
+                    && entry.modified == modified
+                    && entry.len == len =>
+            {
+                tracing::info!(
+                    "using cached outlines for '{}'",
+                    path.display()
+                );
+                disk::touch(&self.entry_path(path));
+                return Ok(Lookup::Hit(entry.outlines));
+            }
+            cached => cached,
+        };
+
+        let source =
+            digest_file(path, Algorithm::Blake3).map_err(ReadError::IoError)?;
+
+        match cached {
+            Some(entry) if entry.source == source => {
+                tracing::info!(
+                    "'{}' touched but unchanged; using cached outlines",
+                    path.display()
+                );
+
+                let entry = Entry { modified, len, ..entry };
+                self.store(path, &entry);
+
+                Ok(Lookup::Hit(entry.outlines))
+            }
+            _ => {
+                tracing::info!("extracting outlines from '{}'", path.display());
+
+                Ok(Lookup::Miss(Entry {
+                    zpack_version: env!("CARGO_PKG_VERSION").to_string(),
+                    modified,
+                    len,
+                    source,
+                    outlines: Vec::new(),
+                }))
+            }
+        }
+    }
+
+    fn store(&self, path: &Path, entry: &Entry) {
+        if let Err(e) = self.write_entry(path, entry) {
+            tracing::warn!("failed to write outline cache: {e}");
+        }
+    }
+
+    /// Return the cached outlines for the package file at `path`, calling
+    /// `load` and caching the result if there is no valid entry.
+    ///
+    /// Failing to read or write the cache is not an error; the outlines are
+    /// loaded as if there were no cache.
+    ///
+    /// # Errors
+    /// Errors if the file cannot be read or `load` fails.
+    pub fn get_or_load(
+        &self,
+        path: &Path,
+        load: impl FnOnce() -> Result<Vec<PackageOutline>, ReadError>,
+    ) -> Result<Vec<PackageOutline>, ReadError> {
+        match self.lookup(path)? {
+            Lookup::Hit(outlines) => Ok(outlines),
+            Lookup::Miss(entry) => {
+                let entry = Entry { outlines: load()?, ..entry };
+                self.store(path, &entry);
+                Ok(entry.outlines)
+            }
+        }
+    }
+
+    /// [`Self::get_or_load`] for many package files, calling `load` once with
+    /// every file which has no valid entry.
+    ///
+    /// `load` must return one result per path it is given, in the same order.
+    /// Results are returned in the same order as `paths`.
+    pub fn get_or_load_many(
+        &self,
+        paths: &[&Path],
+        load: impl FnOnce(&[&Path]) -> Vec<Result<Vec<PackageOutline>, ReadError>>,
+    ) -> Vec<Result<Vec<PackageOutline>, ReadError>> {
+        let mut res = Vec::with_capacity(paths.len());
+        let mut misses = Vec::new();
+
+        for (idx, path) in paths.iter().enumerate() {
+            match self.lookup(path) {
+                Ok(Lookup::Hit(outlines)) => res.push(Ok(outlines)),
+                Ok(Lookup::Miss(entry)) => {
+                    res.push(Ok(Vec::new()));
+                    misses.push((idx, entry));
+                }
+                Err(e) => res.push(Err(e)),
+            }
+        }
+
+        if misses.is_empty() {
+            return res;
+        }
+
+        let miss_paths =
+            misses.iter().map(|(idx, _)| paths[*idx]).collect::<Vec<_>>();
+
+        for ((idx, entry), outlines) in
+            misses.into_iter().zip(load(&miss_paths))
+        {
+            res[idx] = outlines.map(|outlines| {
+                let entry = Entry { outlines, ..entry };
+                self.store(paths[idx], &entry);
+                entry.outlines
+            });
+        }
+
+        res
+    }
+
+    /// Remove every cached entry.
+    ///
+    /// # Errors
+    /// Errors if the cache directory exists but cannot be removed.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+}
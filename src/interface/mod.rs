@@ -1 +1,7 @@
+pub mod cache;
+pub mod pickle;
+pub mod plugins;
 pub mod reader;
+pub mod sandbox;
+pub mod spack;
+pub mod stubs;
@@ -0,0 +1,63 @@
+//! Pickling of Python classes through their serde representation.
+//!
+//! The state of an object, returned by `__getstate__`, is its `MessagePack`
+//! serialization. Most classes cannot be created without constructor
+//! arguments, so pickle's default of calling `cls.__new__(cls)` and then
+//! `__setstate__` does not work for them. Instead, `__reduce__` (see
+//! [`reduce`]) rebuilds the object by passing its state to the class method
+//! `_from_state`, which every pickleable class defines.
+//!
+//! Pickle stores classes by reference, so pickleable classes must set `module`
+//! in their `pyclass` attribute to the module they are exported from.
+
+use pyo3::{PyClass, exceptions::PyValueError, prelude::*, types::PyBytes};
+use serde::{Serialize, de::DeserializeOwned};
+
+/// What `__reduce__` returns: a callable and the arguments to call it with
+pub type Reduced<'py> = (Bound<'py, PyAny>, (Bound<'py, PyBytes>,));
+
+/// The pickled state of `value`.
+///
+/// # Errors
+/// Errors if `value` cannot be serialized.
+pub fn state<'py, T: Serialize>(
+    py: Python<'py>,
+    value: &T,
+) -> PyResult<Bound<'py, PyBytes>> {
+    rmp_serde::to_vec_named(value)
+        .map(|bytes| PyBytes::new(py, &bytes))
+        .map_err(|e| PyValueError::new_err(format!("cannot pickle: {e}")))
+}
+
+/// The value whose pickled state is `state`.
+///
+/// # Errors
+/// Errors if `state` is not a valid state of a `T`.
+pub fn from_state<T: DeserializeOwned>(state: &[u8]) -> PyResult<T> {
+    rmp_serde::from_slice(state)
+        .map_err(|e| PyValueError::new_err(format!("cannot unpickle: {e}")))
+}
+
+/// Reduce `slf` to its class's `_from_state` and its state.
+///
+/// # Errors
+/// Errors if `slf` cannot be serialized or its class has no `_from_state`.
+pub fn reduce<'py, T: PyClass + Serialize>(
+    slf: &Bound<'py, T>,
+) -> PyResult<Reduced<'py>> {
+    let state = state(slf.py(), &*slf.borrow())?;
+    Ok((slf.as_any().get_type().getattr("_from_state")?, (state,)))
+}
+
+/// Whether `lhs` and `rhs` have the same serde representation.
+///
+/// # Errors
+/// Errors if either cannot be serialized.
+pub fn same_state<T: Serialize>(lhs: &T, rhs: &T) -> PyResult<bool> {
+    let value = |v: &T| {
+        serde_json::to_value(v)
+            .map_err(|e| PyValueError::new_err(format!("cannot compare: {e}")))
+    };
+
+    Ok(value(lhs)? == value(rhs)?)
+}
@@ -0,0 +1,374 @@
+//! Plugins discovered from Python entry points.
+//!
+//! A plugin is an installed Python distribution which declares an entry point
+//! in the [`PLUGIN_GROUP`] group:
+//!
+//! ```toml
+//! [project.entry-points."zpack.plugins"]
+//! s3 = "zpack_s3:plugin"
+//! ```
+//!
+//! Plugins are loaded once, at startup. Each entry point must resolve to an
+//! object, usually a module, with a `register(registry)` function. This is
+//! called with a [`PluginRegistry`] through which the plugin adds hooks:
+//!
+//! - `registry.fetcher(scheme, func)`: fetch URLs such as `scheme://...` by
+//!   calling `func(url, dest)`, which must write the file to `dest`
+//! - `registry.builder(name, func)`: a build system, looked up with [`builder`]
+//! - `registry.constraint(name, factory)`: a constraint factory, which package
+//!   files call with `zpack.plugin_constraint(name, *args, **kwargs)`
+//!
+//! A plugin may also set `ZPACK_REQUIRES` to a version pattern, such as
+//! `"0.1.>"`, which is matched exactly, so `"0.1"` only matches `0.1`. Plugins
+//! whose pattern does not match this version of zpack are not registered.
+//! Plugins which fail to load are reported by `zpack plugin list` rather than
+//! aborting zpack.
+//!
+//! Plugins run without any sandbox restrictions, so installing one grants it
+//! the same trust as zpack itself.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock, OnceLock, RwLock},
+};
+
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{constraint::CmpType, package::version::Version};
+
+/// The entry point group plugins are registered under
+pub const PLUGIN_GROUP: &str = "zpack.plugins";
+
+/// The attribute holding the versions of zpack a plugin supports
+pub const REQUIRES_ATTR: &str = "ZPACK_REQUIRES";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Whether to load plugins at all
+    pub enabled: bool,
+
+    /// Names of entry points which should not be loaded
+    pub disabled: Vec<String>,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self { enabled: true, disabled: Vec::new() }
+    }
+}
+
+/// What a plugin can provide
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum HookKind {
+    Fetcher,
+    Builder,
+    Constraint,
+}
+
+impl std::fmt::Display for HookKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Fetcher => "fetcher",
+            Self::Builder => "builder",
+            Self::Constraint => "constraint",
+        })
+    }
+}
+
+/// A callable registered by a plugin
+#[derive(Clone, Debug)]
+pub struct Hook {
+    /// The entry point name of the plugin which registered the hook
+    pub plugin: String,
+    pub func: Arc<Py<PyAny>>,
+}
+
+static HOOKS: LazyLock<RwLock<BTreeMap<(HookKind, String), Hook>>> =
+    LazyLock::new(|| RwLock::new(BTreeMap::new()));
+
+static PLUGINS: OnceLock<Vec<Plugin>> = OnceLock::new();
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PluginStatus {
+    Loaded,
+
+    /// Disabled by the configuration or `--no-plugins`
+    Disabled,
+
+    /// The plugin's [`REQUIRES_ATTR`] does not match this version of zpack
+    Incompatible {
+        requires: String,
+    },
+
+    /// Loading or registering the plugin raised an exception
+    Failed {
+        error: String,
+    },
+}
+
+/// An entry point in [`PLUGIN_GROUP`]
+#[derive(Clone, Debug, Serialize)]
+pub struct Plugin {
+    /// The entry point name
+    pub name: String,
+
+    /// The object the entry point refers to, such as `module:attr`
+    pub value: String,
+
+    /// The name of the distribution declaring the entry point
+    pub distribution: Option<String>,
+
+    /// The version of that distribution
+    pub version: Option<String>,
+
+    #[serde(flatten)]
+    pub status: PluginStatus,
+}
+
+impl Plugin {
+    /// The hooks this plugin registered, in order
+    ///
+    /// # Panics
+    /// Panics if the hook lock is poisoned.
+    #[must_use]
+    pub fn hooks(&self) -> Vec<(HookKind, String)> {
+        HOOKS
+            .read()
+            .expect("plugin hook lock poisoned")
+            .iter()
+            .filter(|(_, hook)| hook.plugin == self.name)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if let Some(dist) = &self.distribution {
+            write!(f, " ({dist}")?;
+
+            if let Some(version) = &self.version {
+                write!(f, " {version}")?;
+            }
+
+            f.write_str(")")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Registers the hooks of a single plugin. Passed to the plugin's `register`
+/// function
+#[pyclass(frozen)]
+pub struct PluginRegistry {
+    plugin: String,
+}
+
+impl PluginRegistry {
+    fn add(&self, kind: HookKind, name: &str, func: Py<PyAny>) {
+        let hook = Hook { plugin: self.plugin.clone(), func: Arc::new(func) };
+
+        let previous = HOOKS
+            .write()
+            .expect("plugin hook lock poisoned")
+            .insert((kind, name.to_string()), hook);
+
+        if let Some(previous) = previous {
+            tracing::warn!(
+                "plugin '{}' replaced {kind} '{name}' registered by '{}'",
+                self.plugin,
+                previous.plugin
+            );
+        }
+    }
+}
+
+#[pymethods]
+impl PluginRegistry {
+    /// Fetch URLs starting with `scheme://` by calling `func(url, dest)`
+    #[pyo3(name = "fetcher")]
+    fn py_fetcher(&self, scheme: &str, func: Py<PyAny>) {
+        self.add(HookKind::Fetcher, scheme, func);
+    }
+
+    /// Register a build system called `name`
+    #[pyo3(name = "builder")]
+    fn py_builder(&self, name: &str, func: Py<PyAny>) {
+        self.add(HookKind::Builder, name, func);
+    }
+
+    /// Register a constraint factory, called by package files with
+    /// `zpack.plugin_constraint(name, ...)`
+    #[pyo3(name = "constraint")]
+    fn py_constraint(&self, name: &str, factory: Py<PyAny>) {
+        self.add(HookKind::Constraint, name, factory);
+    }
+
+    #[getter]
+    fn plugin(&self) -> &str {
+        &self.plugin
+    }
+}
+
+fn hook(kind: HookKind, name: &str) -> Option<Arc<Py<PyAny>>> {
+    HOOKS
+        .read()
+        .expect("plugin hook lock poisoned")
+        .get(&(kind, name.to_string()))
+        .map(|hook| hook.func.clone())
+}
+
+/// The fetcher registered for the scheme of `url`, if any.
+///
+/// # Panics
+/// Panics if the hook lock is poisoned.
+#[must_use]
+pub fn fetcher(url: &str) -> Option<Arc<Py<PyAny>>> {
+    let (scheme, _) = url.split_once("://")?;
+    hook(HookKind::Fetcher, scheme)
+}
+
+/// The builder registered as `name`, if any.
+///
+/// # Panics
+/// Panics if the hook lock is poisoned.
+#[must_use]
+pub fn builder(name: &str) -> Option<Arc<Py<PyAny>>> {
+    hook(HookKind::Builder, name)
+}
+
+/// Create a constraint with the factory a plugin registered as `name`.
+///
+/// # Errors
+/// Errors if no plugin registered the factory or the factory raises.
+#[pyfunction]
+#[pyo3(signature = (name, *args, **kwargs))]
+pub fn plugin_constraint<'py>(
+    py: Python<'py>,
+    name: &str,
+    args: &Bound<'py, PyTuple>,
+    kwargs: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let factory = hook(HookKind::Constraint, name).ok_or_else(|| {
+        PyKeyError::new_err(format!(
+            "no plugin provides the constraint '{name}'"
+        ))
+    })?;
+
+    factory.bind(py).call(args, kwargs)
+}
+
+/// This version of zpack, as matched against [`REQUIRES_ATTR`]
+fn zpack_version() -> Version {
+    Version::new(env!("CARGO_PKG_VERSION"))
+        .expect("crate version is a valid version")
+}
+
+/// Import a plugin, check its version requirement and call its `register`
+/// function.
+fn register(
+    py: Python<'_>,
+    entry_point: &Bound<'_, PyAny>,
+    name: &str,
+) -> PyResult<PluginStatus> {
+    let plugin = entry_point.call_method0("load")?;
+
+    if let Some(requires) = plugin.getattr_opt(REQUIRES_ATTR)? {
+        let requires = requires.extract::<String>()?;
+
+        let pattern = Version::new(&requires).map_err(|e| {
+            PyValueError::new_err(format!(
+                "invalid {REQUIRES_ATTR} '{requires}': {e:?}"
+            ))
+        })?;
+
+        if !zpack_version().satisfies(CmpType::Equal, &pattern) {
+            tracing::warn!(
+                "plugin '{name}' requires zpack {requires}, not {}",
+                env!("CARGO_PKG_VERSION")
+            );
+
+            return Ok(PluginStatus::Incompatible { requires });
+        }
+    }
+
+    let registry = PluginRegistry { plugin: name.to_string() };
+    plugin.call_method1("register", (Py::new(py, registry)?,))?;
+
+    tracing::info!("loaded plugin '{name}'");
+
+    Ok(PluginStatus::Loaded)
+}
+
+/// Describe and, unless disabled, load the entry point `entry_point`.
+fn load_entry_point(
+    py: Python<'_>,
+    entry_point: &Bound<'_, PyAny>,
+    config: &PluginConfig,
+) -> PyResult<Plugin> {
+    let name = entry_point.getattr("name")?.extract::<String>()?;
+    let value = entry_point.getattr("value")?.extract::<String>()?;
+
+    let dist = entry_point.getattr("dist")?;
+    let dist_attr = |attr: &str| {
+        dist.getattr(attr).and_then(|a| a.extract::<String>()).ok()
+    };
+
+    let status = if !config.enabled || config.disabled.contains(&name) {
+        PluginStatus::Disabled
+    } else {
+        register(py, entry_point, &name).unwrap_or_else(|e| {
+            tracing::error!("failed to load plugin '{name}': {e}");
+            PluginStatus::Failed { error: e.to_string() }
+        })
+    };
+
+    Ok(Plugin {
+        distribution: dist_attr("name"),
+        version: dist_attr("version"),
+        name,
+        value,
+        status,
+    })
+}
+
+fn discover(py: Python<'_>, config: &PluginConfig) -> PyResult<Vec<Plugin>> {
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("group", PLUGIN_GROUP)?;
+
+    py.import("importlib.metadata")?
+        .call_method("entry_points", (), Some(&kwargs))?
+        .try_iter()?
+        .map(|entry_point| load_entry_point(py, &entry_point?, config))
+        .collect()
+}
+
+/// Discover and load every plugin, returning them in discovery order. Only
+/// the first call has any effect; later calls return the same plugins.
+pub fn load(config: &PluginConfig) -> &'static [Plugin] {
+    PLUGINS.get_or_init(|| {
+        Python::attach(|py| discover(py, config)).unwrap_or_else(|e| {
+            tracing::error!("failed to discover plugins: {e}");
+            Vec::new()
+        })
+    })
+}
+
+/// The plugins found by [`load`], or none if it has not been called
+#[must_use]
+pub fn loaded() -> &'static [Plugin] {
+    PLUGINS.get().map_or(&[], Vec::as_slice)
+}
@@ -1,6 +1,19 @@
-use std::path::{Path, PathBuf};
+use std::{
+    ffi::CString,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
-use pyo3::{call::PyCallArgs, prelude::*};
+use pyo3::{call::PyCallArgs, exceptions::PyAttributeError, prelude::*};
+
+use crate::{
+    interface::sandbox::Sandbox,
+    package::{define, outline::PackageOutline},
+};
 
 #[derive(Debug)]
 pub enum ReadError {
@@ -10,6 +23,13 @@ pub enum ReadError {
     NotAFile(PathBuf),
     IoError(std::io::Error),
     NotCString,
+
+    /// The package file did something the sandbox does not allow
+    SandboxViolation(String),
+
+    /// The introspection data `PyO3` embeds in the extension module could not
+    /// be read
+    Introspection(String),
 }
 
 pub fn read_from_class<'py, T, Args>(
@@ -37,43 +57,180 @@ where
     T: for<'a> FromPyObject<'a, 'py>,
     for<'a> <T as FromPyObject<'a, 'py>>::Error: std::fmt::Display,
 {
-    println!("instance = {instance:?}");
+    tracing::debug!("calling {method} on {instance:?}");
 
     let res = instance
         .call_method0(method)
         .map_err(|e| ReadError::PyErr(e.to_string()))?;
 
-    println!("res = {res:?}");
+    tracing::debug!("{method} returned {res:?}");
 
     res.extract::<T>().map_err(|e| ReadError::PyErr(e.to_string()))
 }
 
-pub fn process_file<'py>(
-    py: Python<'py>,
-    path: &Path,
-) -> Result<Vec<Bound<'py, PyAny>>, ReadError> {
-    if !path.exists() {
-        return Err(ReadError::PathDoesNotExist(path.to_path_buf()));
-    }
+/// The contents of a package file, read ahead of executing it so no file I/O
+/// happens while attached to Python.
+#[derive(Debug)]
+pub struct PackageFile {
+    pub path: PathBuf,
+    pub code: CString,
+}
 
-    if !path.is_file() {
-        return Err(ReadError::PathDoesNotExist(path.to_path_buf()));
+impl PackageFile {
+    /// Read the package file at `path`.
+    ///
+    /// # Errors
+    /// Errors if `path` is not a file, cannot be read or contains a nul byte.
+    pub fn read(path: &Path) -> Result<Self, ReadError> {
+        if !path.exists() {
+            return Err(ReadError::PathDoesNotExist(path.to_path_buf()));
+        }
+
+        if !path.is_file() {
+            return Err(ReadError::NotAFile(path.to_path_buf()));
+        }
+
+        let contents =
+            std::fs::read_to_string(path).map_err(ReadError::IoError)?;
+        let code = CString::new(contents).map_err(|_| ReadError::NotCString)?;
+
+        Ok(Self { path: path.to_path_buf(), code })
     }
+}
 
-    let contents = std::fs::read_to_string(path).map_err(ReadError::IoError)?;
-    let cstr =
-        std::ffi::CString::new(contents).map_err(|_| ReadError::NotCString)?;
+/// Read every package file in `paths` in parallel.
+///
+/// Results are returned in the same order as `paths`.
+///
+/// # Panics
+/// Panics if a reading thread panics.
+#[must_use]
+pub fn read_files(paths: &[&Path]) -> Vec<Result<PackageFile, ReadError>> {
+    let jobs = std::thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(paths.len());
 
-    let module = PyModule::from_code(py, &cstr, c"package.py", c"package")
-        .map_err(|e| ReadError::PyErr(e.to_string()))?;
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(
+        std::iter::repeat_with(|| None).take(paths.len()).collect::<Vec<_>>(),
+    );
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+
+                    let Some(path) = paths.get(idx) else {
+                        break;
+                    };
+
+                    let res = PackageFile::read(path);
+                    results.lock().unwrap()[idx] = Some(res);
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().flatten().collect()
+}
 
-    let packages_fn = module
-        .getattr("zpack_packages")
+/// The packages of a package file which has just been executed: the result
+/// of its `zpack_packages()` function, if it defines one, followed by the
+/// packages it defined in `with` blocks (see [`crate::package::define`]).
+///
+/// # Errors
+/// Errors if `zpack_packages()` fails or returns something other than a
+/// list, or if the file defines no packages at all.
+pub(crate) fn collect_packages<'py>(
+    py: Python<'py>,
+    packages_fn: Option<Bound<'py, PyAny>>,
+) -> PyResult<Vec<Bound<'py, PyAny>>> {
+    let defined = define::take_defined(py);
+
+    let Some(packages_fn) = packages_fn else {
+        if defined.is_empty() {
+            return Err(PyAttributeError::new_err(
+                "package file does not define 'zpack_packages' or any \
+                 packages",
+            ));
+        }
+
+        return Ok(defined);
+    };
+
+    let mut packages: Vec<_> = packages_fn.call0()?.extract()?;
+    packages.extend(defined);
+    Ok(packages)
+}
+
+/// Execute a package file which has already been read and return its
+/// packages (see [`collect_packages`]).
+///
+/// # Errors
+/// Errors if the file raises an exception or defines no packages.
+pub fn process_source<'py>(
+    py: Python<'py>,
+    file: &PackageFile,
+) -> Result<Vec<Bound<'py, PyAny>>, ReadError> {
+    // Discard packages left over from a file which failed part way through
+    define::take_defined(py);
+
+    let module = PyModule::from_code(py, &file.code, c"package.py", c"package")
         .map_err(|e| ReadError::PyErr(e.to_string()))?;
 
-    packages_fn
-        .call0()
-        .map_err(|e| ReadError::PyErr(e.to_string()))?
-        .extract()
-        .map_err(|e: PyErr| ReadError::PyErr(e.to_string()))
+    let packages_fn = module.getattr("zpack_packages").ok();
+
+    collect_packages(py, packages_fn)
+        .map_err(|e| ReadError::PyErr(e.to_string()))
+}
+
+/// Read and execute a package file, returning its packages (see
+/// [`collect_packages`]).
+///
+/// # Errors
+/// Errors if the file cannot be read, raises an exception or defines no
+/// packages.
+pub fn process_file<'py>(
+    py: Python<'py>,
+    path: &Path,
+) -> Result<Vec<Bound<'py, PyAny>>, ReadError> {
+    process_source(py, &PackageFile::read(path)?)
+}
+
+/// Extract the outline of every package defined in each package file.
+///
+/// Loading many files one at a time attaches to Python once per file, and
+/// holds the GIL while reading each one from disk. Instead, every file is read
+/// in parallel with the GIL released, then executed and extracted within a
+/// single attachment. Each file is executed in `sandbox`.
+///
+/// Results are returned in the same order as `paths`, so one invalid file does
+/// not prevent the others from loading.
+#[must_use]
+pub fn extract_outlines(
+    sandbox: &Sandbox,
+    paths: &[&Path],
+) -> Vec<Result<Vec<PackageOutline>, ReadError>> {
+    Python::attach(|py| {
+        let files = py.detach(|| read_files(paths));
+
+        files
+            .into_iter()
+            .map(|file| {
+                let packages = sandbox.process_source(py, &file?)?;
+
+                sandbox.run(py, || {
+                    packages
+                        .into_iter()
+                        .map(|package| {
+                            Ok(package
+                                .call_method0("outline")?
+                                .extract::<PackageOutline>()?)
+                        })
+                        .collect()
+                })
+            })
+            .collect()
+    })
 }
@@ -0,0 +1,60 @@
+# Helpers for executing package files in a restricted environment. This file is
+# embedded into zpack and loaded by `interface::sandbox`; it is not part of the
+# public Python API.
+
+import builtins
+import sys
+import time
+
+
+# Derives from BaseException so package code cannot accidentally swallow it
+# with a bare `except Exception:`
+class SandboxViolation(BaseException):
+    pass
+
+
+def make_builtins(allowed_imports, denied_builtins):
+    real_import = builtins.__import__
+    allowed_imports = frozenset(allowed_imports)
+
+    def guarded_import(name, globals=None, locals=None, fromlist=(), level=0):
+        root = name.split(".")[0]
+
+        if level != 0 or root not in allowed_imports:
+            raise SandboxViolation(f"import of '{name}' is not allowed")
+
+        return real_import(name, globals, locals, fromlist, level)
+
+    def denied(name):
+        def inner(*args, **kwargs):
+            raise SandboxViolation(f"use of '{name}' is not allowed")
+
+        return inner
+
+    safe = dict(vars(builtins))
+
+    for name in denied_builtins:
+        safe[name] = denied(name)
+
+    safe["__import__"] = guarded_import
+
+    return safe
+
+
+def set_deadline(seconds):
+    if seconds is None:
+        sys.settrace(None)
+        return
+
+    deadline = time.monotonic() + seconds
+
+    def tracer(frame, event, arg):
+        if time.monotonic() > deadline:
+            sys.settrace(None)
+            raise SandboxViolation(
+                f"package definition exceeded the {seconds}s time limit"
+            )
+
+        return tracer
+
+    sys.settrace(tracer)
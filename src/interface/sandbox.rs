@@ -0,0 +1,248 @@
+//! Restricted execution of Python package files.
+//!
+//! Package files are arbitrary Python. When they come from a third-party
+//! repository, a [`Sandbox`] limits what they can do while being loaded:
+//!
+//! - Only modules in an allow-list can be imported
+//! - Builtins which touch the outside world (`open`, `exec`, `input`, ...) are
+//!   replaced with functions which raise an error
+//! - Execution is aborted once a time limit is exceeded
+//!
+//! Any of these produce a [`ReadError::SandboxViolation`].
+//!
+//! This protects against careless or accidental misuse, such as a package
+//! reading files at import time or hanging in an infinite loop. It is *not* a
+//! security boundary: determined Python code can escape a restricted
+//! `__builtins__`, so untrusted repositories should still be reviewed.
+
+use std::{ffi::CString, path::Path, time::Duration};
+
+use pyo3::{prelude::*, sync::PyOnceLock, types::PyDict};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interface::reader::{self, PackageFile, ReadError},
+    package::define,
+};
+
+const PRELUDE: &str = include_str!("sandbox.py");
+
+/// The prelude is only loaded once so every sandbox shares the same
+/// `SandboxViolation` class
+static PRELUDE_MODULE: PyOnceLock<Py<PyModule>> = PyOnceLock::new();
+
+/// Modules package files may import by default
+pub const DEFAULT_ALLOWED_IMPORTS: [&str; 12] = [
+    "zpack",
+    "collections",
+    "dataclasses",
+    "enum",
+    "functools",
+    "itertools",
+    "math",
+    "operator",
+    "re",
+    "string",
+    "typing",
+    "abc",
+];
+
+/// Builtins package files may not call
+pub const DENIED_BUILTINS: [&str; 9] = [
+    "open",
+    "exec",
+    "eval",
+    "compile",
+    "input",
+    "breakpoint",
+    "exit",
+    "quit",
+    "help",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sandbox {
+    /// Whether to restrict package files at all
+    pub enabled: bool,
+
+    /// Top-level modules which may be imported, in addition to
+    /// [`DEFAULT_ALLOWED_IMPORTS`]
+    pub allowed_imports: Vec<String>,
+
+    /// Maximum time spent executing a single package file. `None` disables
+    /// the limit
+    #[serde(with = "timeout_secs")]
+    pub timeout: Option<Duration>,
+}
+
+/// Serialize the timeout as a number of seconds
+mod timeout_secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    // The signature is dictated by `#[serde(with)]`
+    #[allow(clippy::ref_option)]
+    pub fn serialize<S: Serializer>(
+        timeout: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match timeout {
+            Some(t) => serializer.serialize_some(&t.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allowed_imports: Vec::new(),
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl Sandbox {
+    /// A sandbox which does not restrict anything
+    #[must_use]
+    pub const fn disabled() -> Self {
+        Self { enabled: false, allowed_imports: Vec::new(), timeout: None }
+    }
+
+    fn prelude(py: Python<'_>) -> Result<Bound<'_, PyModule>, ReadError> {
+        PRELUDE_MODULE
+            .get_or_try_init(py, || {
+                let code =
+                    CString::new(PRELUDE).map_err(|_| ReadError::NotCString)?;
+
+                PyModule::from_code(
+                    py,
+                    &code,
+                    c"zpack_sandbox.py",
+                    c"zpack_sandbox",
+                )
+                .map(Bound::unbind)
+                .map_err(|e| ReadError::PyErr(e.to_string()))
+            })
+            .map(|module| module.bind(py).clone())
+    }
+
+    /// Convert a Python exception, distinguishing sandbox violations.
+    fn map_err(prelude: &Bound<'_, PyModule>, err: &PyErr) -> ReadError {
+        let py = prelude.py();
+
+        match prelude.getattr("SandboxViolation") {
+            Ok(cls) if err.get_type(py).is(&cls) => {
+                let msg = err.value(py).to_string();
+                tracing::error!("sandbox violation: {msg}");
+                ReadError::SandboxViolation(msg)
+            }
+            _ => ReadError::PyErr(err.to_string()),
+        }
+    }
+
+    /// Run `f` with the time limit applied.
+    ///
+    /// This should wrap every call into package code, not just loading the
+    /// file, since methods such as `outline()` run arbitrary code too.
+    ///
+    /// # Errors
+    /// Errors if `f` errors or the time limit is exceeded.
+    pub fn run<T>(
+        &self,
+        py: Python<'_>,
+        f: impl FnOnce() -> PyResult<T>,
+    ) -> Result<T, ReadError> {
+        if !self.enabled {
+            return f().map_err(|e| ReadError::PyErr(e.to_string()));
+        }
+
+        let prelude = Self::prelude(py)?;
+        let set_deadline = prelude
+            .getattr("set_deadline")
+            .map_err(|e| ReadError::PyErr(e.to_string()))?;
+
+        set_deadline
+            .call1((self.timeout.map(|t| t.as_secs_f64()),))
+            .map_err(|e| ReadError::PyErr(e.to_string()))?;
+
+        let res = f();
+
+        // Always remove the trace function, even if `f` failed
+        set_deadline
+            .call1((None::<f64>,))
+            .map_err(|e| ReadError::PyErr(e.to_string()))?;
+
+        res.map_err(|e| Self::map_err(&prelude, &e))
+    }
+
+    /// Execute a package file and return its packages (see
+    /// [`reader::collect_packages`]).
+    ///
+    /// # Errors
+    /// Errors if the file cannot be read, raises an exception or violates the
+    /// sandbox.
+    pub fn process_file<'py>(
+        &self,
+        py: Python<'py>,
+        path: &Path,
+    ) -> Result<Vec<Bound<'py, PyAny>>, ReadError> {
+        self.process_source(py, &PackageFile::read(path)?)
+    }
+
+    /// [`Self::process_file`] for a package file which has already been read.
+    ///
+    /// # Errors
+    /// Errors if the file raises an exception or violates the sandbox.
+    pub fn process_source<'py>(
+        &self,
+        py: Python<'py>,
+        file: &PackageFile,
+    ) -> Result<Vec<Bound<'py, PyAny>>, ReadError> {
+        if !self.enabled {
+            return reader::process_source(py, file);
+        }
+
+        let prelude = Self::prelude(py)?;
+
+        let allowed = DEFAULT_ALLOWED_IMPORTS
+            .iter()
+            .map(|s| (*s).to_string())
+            .chain(self.allowed_imports.iter().cloned())
+            .collect::<Vec<_>>();
+
+        let builtins = prelude
+            .getattr("make_builtins")
+            .and_then(|f| f.call1((allowed, DENIED_BUILTINS.to_vec())))
+            .map_err(|e| ReadError::PyErr(e.to_string()))?;
+
+        let globals = PyDict::new(py);
+        let setup = globals
+            .set_item("__builtins__", builtins)
+            .and_then(|()| globals.set_item("__name__", "package"));
+        setup.map_err(|e| ReadError::PyErr(e.to_string()))?;
+
+        tracing::info!("executing '{}' in sandbox", file.path.display());
+
+        // Discard packages left over from a file which failed part way through
+        define::take_defined(py);
+
+        self.run(py, || {
+            py.run(&file.code, Some(&globals), None)?;
+
+            reader::collect_packages(py, globals.get_item("zpack_packages")?)
+        })
+    }
+}
@@ -0,0 +1,280 @@
+# Stand-ins for Spack, used to execute Spack package.py files and record
+# their directives. This file is embedded into zpack and loaded by
+# `interface::spack`; it is not part of the public Python API.
+#
+# Package files are executed against stub `spack`, `spack_repo` and `llnl`
+# modules. Directives called in a class body are recorded, in order, with the
+# conditions of any enclosing `with when(...)` blocks. Every other name the
+# file uses which does not exist resolves to an inert stub, so build system
+# base classes, helper functions and decorators need not be known. Methods are
+# never called.
+
+import builtins
+import importlib.abc
+import importlib.machinery
+import json
+import sys
+import types
+
+STUB_ROOTS = ("spack", "spack_repo", "llnl")
+
+# Directives which are recorded. Anything else called in a class body is a
+# stub, and reported as unknown
+DIRECTIVES = (
+    "version",
+    "variant",
+    "depends_on",
+    "conflicts",
+    "provides",
+    "license",
+    "patch",
+    "resource",
+    "extends",
+    "requires",
+    "build_system",
+    "redistribute",
+    "can_splice",
+    "maintainers",
+)
+
+_pending = []
+_when_stack = []
+_defaults_stack = []
+_classes = []
+_unknown = set()
+
+
+class Stub:
+    """Stands in for any object Spack would provide."""
+
+    def __init__(self, name):
+        self._name = name
+
+    def __repr__(self):
+        return f"<{self._name}>"
+
+    def __call__(self, *args, **kwargs):
+        # Used as a decorator, keep the decorated function
+        if len(args) == 1 and not kwargs and callable(args[0]):
+            return args[0]
+
+        return Stub(f"{self._name}()")
+
+    def __getattr__(self, name):
+        if name.startswith("__"):
+            raise AttributeError(name)
+
+        return Stub(f"{self._name}.{name}")
+
+    def __iter__(self):
+        return iter(())
+
+    def __bool__(self):
+        return False
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *args):
+        return False
+
+    def __mro_entries__(self, bases):
+        # Build system base classes are expected to be unknown
+        _unknown.discard(self._name)
+        return (_stub_class(self._name),)
+
+
+class PackageBase:
+    def __init_subclass__(cls, **kwargs):
+        super().__init_subclass__(**kwargs)
+
+        if cls.__dict__.get("_zpack_stub"):
+            return
+
+        cls._zpack_directives = list(_pending)
+        _pending.clear()
+        _classes.append(cls)
+
+
+_stub_classes = {}
+
+
+def _stub_class(name):
+    name = name.rsplit(".", 1)[-1]
+
+    if name not in _stub_classes:
+        _stub_classes[name] = type(name, (PackageBase,), {"_zpack_stub": True})
+
+    return _stub_classes[name]
+
+
+def _directive(name):
+    def record(*args, **kwargs):
+        for defaults in _defaults_stack:
+            for key, value in defaults.items():
+                kwargs.setdefault(key, value)
+
+        _pending.append((name, args, kwargs, list(_when_stack)))
+
+    record.__name__ = name
+    return record
+
+
+class when:
+    """`with when(spec):` and `@when(spec)`."""
+
+    def __init__(self, spec):
+        self.spec = spec
+
+    def __enter__(self):
+        _when_stack.append(self.spec)
+        return self
+
+    def __exit__(self, *args):
+        _when_stack.pop()
+        return False
+
+    def __call__(self, func):
+        return func
+
+
+class default_args:
+    """`with default_args(type="build"):`."""
+
+    def __init__(self, **kwargs):
+        self.kwargs = kwargs
+
+    def __enter__(self):
+        _defaults_stack.append(self.kwargs)
+        return self
+
+    def __exit__(self, *args):
+        _defaults_stack.pop()
+        return False
+
+
+NAMES = {name: _directive(name) for name in DIRECTIVES}
+NAMES["when"] = when
+NAMES["default_args"] = default_args
+
+
+def _module_getattr(module_name):
+    def getattr_(name):
+        if name.startswith("__"):
+            raise AttributeError(name)
+
+        if name in NAMES:
+            return NAMES[name]
+
+        return Stub(f"{module_name}.{name}")
+
+    return getattr_
+
+
+class StubFinder(importlib.abc.MetaPathFinder, importlib.abc.Loader):
+    def find_spec(self, fullname, path, target=None):
+        if fullname.split(".")[0] in STUB_ROOTS:
+            return importlib.machinery.ModuleSpec(
+                fullname, self, is_package=True
+            )
+
+        return None
+
+    def create_module(self, spec):
+        module = types.ModuleType(spec.name)
+        module.__path__ = []
+        module.__getattr__ = _module_getattr(spec.name)
+        module.__all__ = list(NAMES)
+        return module
+
+    def exec_module(self, module):
+        pass
+
+
+class Fallback(dict):
+    """Builtins which resolve every unknown name to a stub."""
+
+    def __missing__(self, name):
+        if name.startswith("__"):
+            raise KeyError(name)
+
+        _unknown.add(name)
+        return Stub(name)
+
+
+def _jsonable(value):
+    if isinstance(value, (str, bool, int, float)) or value is None:
+        return value
+
+    if isinstance(value, (list, tuple, set, frozenset)):
+        return [_jsonable(v) for v in value]
+
+    if isinstance(value, dict):
+        return {str(k): _jsonable(v) for k, v in value.items()}
+
+    return repr(value)
+
+
+def _attribute(cls, name):
+    value = getattr(cls, name, None)
+    return value if isinstance(value, str) else None
+
+
+def load(code, filename):
+    """Execute a package file, returning its classes and directives as JSON."""
+
+    _pending.clear()
+    _when_stack.clear()
+    _defaults_stack.clear()
+    _classes.clear()
+    _unknown.clear()
+
+    finder = StubFinder()
+    saved = {
+        name: module
+        for name, module in sys.modules.items()
+        if name.split(".")[0] in STUB_ROOTS
+    }
+
+    for name in saved:
+        del sys.modules[name]
+
+    sys.meta_path.insert(0, finder)
+
+    try:
+        globals_ = {
+            "__builtins__": Fallback(vars(builtins)),
+            "__name__": "spack.pkg.imported",
+            "__file__": filename,
+        }
+        globals_.update(NAMES)
+
+        exec(compile(code, filename, "exec"), globals_)
+    finally:
+        sys.meta_path.remove(finder)
+
+        for name in list(sys.modules):
+            if name.split(".")[0] in STUB_ROOTS:
+                del sys.modules[name]
+
+        sys.modules.update(saved)
+
+    classes = [
+        {
+            "name": cls.__name__,
+            "url": _attribute(cls, "url"),
+            "homepage": _attribute(cls, "homepage"),
+            "directives": [
+                {
+                    "name": name,
+                    "args": _jsonable(args),
+                    "kwargs": _jsonable(kwargs),
+                    "when": list(when),
+                }
+                for name, args, kwargs, when in cls._zpack_directives
+            ],
+        }
+        for cls in _classes
+    ]
+
+    return json.dumps({"classes": classes, "unknown": sorted(_unknown)})
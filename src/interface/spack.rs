@@ -0,0 +1,172 @@
+//! Reading Spack package repositories.
+//!
+//! Each `package.py` is executed against stand-in Spack modules defined in
+//! `spack.py`, which record the directives called in every package class (see
+//! [`crate::package::spack`] for how they are converted). Names Spack would
+//! provide resolve to inert stubs, so packages load without Spack installed.
+//!
+//! Package files are not [sandboxed](crate::interface::sandbox), since they
+//! routinely import modules such as `os`, but the sandbox's time limit still
+//! applies. Only class bodies are executed, never methods.
+
+use std::{
+    ffi::CString,
+    path::{Path, PathBuf},
+};
+
+use pyo3::{prelude::*, sync::PyOnceLock};
+use serde::Deserialize;
+
+use crate::{
+    interface::{
+        reader::{PackageFile, ReadError},
+        sandbox::Sandbox,
+    },
+    package::{
+        spack::{ImportWarning, SpackClass, SpackPackage},
+        template,
+    },
+};
+
+const SHIM: &str = include_str!("spack.py");
+
+static SHIM_MODULE: PyOnceLock<Py<PyModule>> = PyOnceLock::new();
+
+/// The output of the shim's `load` function
+#[derive(Deserialize)]
+struct Loaded {
+    classes: Vec<SpackClass>,
+    unknown: Vec<String>,
+}
+
+fn shim(py: Python<'_>) -> Result<Bound<'_, PyModule>, ReadError> {
+    SHIM_MODULE
+        .get_or_try_init(py, || {
+            let code = CString::new(SHIM).map_err(|_| ReadError::NotCString)?;
+
+            PyModule::from_code(py, &code, c"zpack_spack.py", c"zpack_spack")
+                .map(Bound::unbind)
+                .map_err(|e| ReadError::PyErr(e.to_string()))
+        })
+        .map(|module| module.bind(py).clone())
+}
+
+/// The package files in the Spack repository `dir`, with the name of the
+/// package each defines, sorted by name.
+///
+/// `dir` may be a repository (containing `packages/`), its `packages`
+/// directory, or the directory of a single package.
+///
+/// # Errors
+/// Errors if `dir` cannot be read or contains no package files.
+pub fn package_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, ReadError> {
+    if !dir.is_dir() {
+        return Err(ReadError::PathDoesNotExist(dir.to_path_buf()));
+    }
+
+    let name_of = |dir: &Path| {
+        dir.file_name()
+            .map(|name| name.to_string_lossy().replace('_', "-"))
+            .unwrap_or_default()
+    };
+
+    let single = dir.join("package.py");
+
+    if single.is_file() {
+        return Ok(vec![(name_of(dir), single)]);
+    }
+
+    let packages = dir.join("packages");
+    let packages = if packages.is_dir() { packages } else { dir.to_path_buf() };
+
+    let mut res = Vec::new();
+
+    for entry in std::fs::read_dir(&packages).map_err(ReadError::IoError)? {
+        let path = entry.map_err(ReadError::IoError)?.path();
+        let file = path.join("package.py");
+
+        if file.is_file() {
+            res.push((name_of(&path), file));
+        }
+    }
+
+    if res.is_empty() {
+        return Err(ReadError::PathDoesNotExist(packages.join("*/package.py")));
+    }
+
+    res.sort();
+
+    Ok(res)
+}
+
+/// Execute the Spack package file `path`, defining the package `name`.
+///
+/// # Errors
+/// Errors if the file cannot be read, raises an exception, exceeds the time
+/// limit of `sandbox`, or defines no package class.
+pub fn read_package(
+    py: Python<'_>,
+    sandbox: &Sandbox,
+    name: &str,
+    path: &Path,
+) -> Result<SpackPackage, ReadError> {
+    let file = PackageFile::read(path)?;
+    let code = file.code.to_string_lossy().into_owned();
+    let load = shim(py)?
+        .getattr("load")
+        .map_err(|e| ReadError::PyErr(e.to_string()))?;
+
+    let json: String = sandbox.run(py, || {
+        load.call1((code, path.display().to_string()))?.extract()
+    })?;
+
+    let loaded: Loaded = serde_json::from_str(&json)
+        .map_err(|e| ReadError::PyErr(e.to_string()))?;
+
+    // Spack names the class after the package, but files may define helper
+    // classes too
+    let expected = template::class_name(name);
+
+    let mut classes = loaded.classes;
+    let idx = classes
+        .iter()
+        .position(|c| c.name == expected)
+        .or_else(|| classes.len().checked_sub(1))
+        .ok_or(ReadError::InvalidInstance)?;
+
+    Ok(SpackPackage {
+        name: name.to_string(),
+        path: path.to_path_buf(),
+        class: classes.swap_remove(idx),
+        unknown: loaded.unknown,
+    })
+}
+
+/// Read every package in the Spack repository `dir` (see
+/// [`package_files`]). Packages which cannot be read are reported as warnings
+/// rather than failing the import.
+///
+/// # Errors
+/// Errors if `dir` contains no package files.
+pub fn read_repo(
+    py: Python<'_>,
+    sandbox: &Sandbox,
+    dir: &Path,
+) -> Result<(Vec<SpackPackage>, Vec<ImportWarning>), ReadError> {
+    let mut packages = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (name, path) in package_files(dir)? {
+        tracing::info!("reading spack package '{}'", path.display());
+
+        match read_package(py, sandbox, &name, &path) {
+            Ok(package) => packages.push(package),
+            Err(e) => warnings.push(ImportWarning {
+                package: name,
+                message: format!("could not be read: {e:?}"),
+            }),
+        }
+    }
+
+    Ok((packages, warnings))
+}
@@ -0,0 +1,343 @@
+# Type stub generation for the zpack extension module. This file is embedded
+# into zpack and loaded by `interface::stubs`; it is not part of the public
+# Python API.
+#
+# The stubs are built by introspecting the imported modules: every class,
+# function and method with its signature and docstring. Python cannot see
+# the types of parameters and return values, so they are taken from the
+# introspection data PyO3 embeds in the extension module, which
+# `interface::stubs` reads and passes to `generate`. Types are written
+# relative to the module the stub describes, importing any other module they
+# refer to.
+
+import inspect
+import re
+import types as types_
+
+HEADER = "# Generated by `zpack stubs`. Do not edit.\n"
+
+# Attributes every class has which are not part of its interface
+SKIPPED = {
+    "__class__",
+    "__dict__",
+    "__doc__",
+    "__module__",
+    "__new__",
+    "__weakref__",
+    "__match_args__",
+    "__qualname__",
+}
+
+# Attributes shown as properties
+PROPERTIES = (
+    property,
+    types_.GetSetDescriptorType,
+    types_.MemberDescriptorType,
+)
+
+# Return types of special methods, which are the same for every class
+RETURNS = {
+    "__init__": "None",
+    "__bool__": "bool",
+    "__hash__": "int",
+    "__len__": "int",
+    "__repr__": "str",
+    "__str__": "str",
+}
+
+# A name in a type, such as `int`, `Version` or `zpack.constraint.SpecOption`
+NAME = re.compile(r"[A-Za-z_][\w.]*")
+
+
+def _private(name):
+    return name.startswith("_") and not name.startswith("__")
+
+
+def _qualified(module):
+    """The full name of the module called `module` by the introspection data"""
+
+    return module if module == "zpack" else f"zpack.{module}"
+
+
+class _Types:
+    """The types of the functions and methods of one module, written relative
+    to it, and the modules they import"""
+
+    def __init__(self, introspection, module):
+        self.classes = introspection["classes"]
+        self.functions = introspection["functions"]
+        self.module = _qualified(module.__name__)
+        self.imports = set()
+
+    def signature(self, owner, name):
+        """The types of `name`, a function of the module or a method of the
+        class `owner`"""
+
+        empty = {"parameters": {}, "returns": None}
+        return self.functions.get((owner, name), empty)
+
+    def annotation(self, text):
+        return NAME.sub(self._name, text)
+
+    def returns(self, name, signature):
+        returns = RETURNS.get(name)
+
+        if returns is None and signature["returns"] is not None:
+            returns = self.annotation(signature["returns"])
+
+        return f" -> {returns}" if returns else ""
+
+    def _name(self, match):
+        name = match.group()
+
+        # PyO3 names classes without a `module` by their name alone
+        if name in self.classes:
+            name = f"{_qualified(self.classes[name])}.{name}"
+
+        module, _, attr = name.rpartition(".")
+
+        if not module:
+            return name
+
+        if module == self.module:
+            return attr
+
+        self.imports.add(module)
+        return name
+
+
+def _docstring(obj, indent):
+    doc = inspect.getdoc(obj)
+
+    if not doc or doc == inspect.getdoc(type(obj)):
+        return []
+
+    doc = doc.replace("\\", "\\\\").replace('"""', '\\"\\"\\"')
+    lines = doc.splitlines()
+    pad = " " * indent
+
+    if len(lines) == 1:
+        return [f'{pad}"""{lines[0]}"""']
+
+    body = [f"{pad}{line}" if line else "" for line in lines[1:]]
+    return [f'{pad}"""{lines[0]}', *body, f'{pad}"""']
+
+
+def _parameters(obj, types, signature, first=None):
+    """The parameter list of `obj`, with the types in `signature` and
+    defaults elided as `...`"""
+
+    try:
+        sig = inspect.signature(obj)
+    except (TypeError, ValueError):
+        return ", ".join(filter(None, [first, "*args", "**kwargs"]))
+
+    params = []
+    positional_only = False
+    keyword_only = False
+
+    for i, param in enumerate(sig.parameters.values()):
+        # PyO3 marks `self` as positional only, which is not worth showing
+        if param.kind == param.POSITIONAL_ONLY:
+            positional_only = positional_only or i > 0 or not first
+        elif positional_only:
+            params.append("/")
+            positional_only = False
+
+        if param.kind == param.KEYWORD_ONLY and not keyword_only:
+            params.append("*")
+            keyword_only = True
+
+        name = param.name
+        annotation = signature["parameters"].get(name)
+
+        if name != first and annotation is not None:
+            name = f"{name}: {types.annotation(annotation)}"
+
+        if param.kind == param.VAR_POSITIONAL:
+            keyword_only = True
+            params.append(f"*{name}")
+        elif param.kind == param.VAR_KEYWORD:
+            params.append(f"**{name}")
+        elif param.default is not param.empty:
+            sep = " = " if ":" in name else "="
+            params.append(f"{name}{sep}...")
+        else:
+            params.append(name)
+
+    if positional_only:
+        params.append("/")
+
+    # Unbound methods include `self`, but class methods do not include `cls`
+    if first and (not params or params[0] != first):
+        params.insert(0, first)
+
+    return ", ".join(params)
+
+
+def _function(name, obj, indent, types, owner, first=None, decorator=None):
+    pad = " " * indent
+    lines = [f"{pad}@{decorator}"] if decorator else []
+
+    signature = types.signature(owner, name)
+    params = _parameters(obj, types, signature, first)
+    arrow = types.returns(name, signature)
+
+    doc = _docstring(obj, indent + 4)
+    header = f"{pad}def {name}({params}){arrow}:"
+
+    if doc:
+        return [*lines, header, *doc]
+
+    return [*lines, f"{header} ..."]
+
+
+def _property(name, cls, indent, types):
+    """A property of `cls`, with its setter if it has one"""
+
+    pad = " " * indent
+    getter = types.signature(cls.__name__, name)
+
+    lines = [
+        f"{pad}@property",
+        f"{pad}def {name}(self){types.returns(name, getter)}: ...",
+    ]
+
+    setter = types.signature(cls.__name__, f"{name}.setter")
+
+    for param, annotation in setter["parameters"].items():
+        if param == "self":
+            continue
+
+        annotation = types.annotation(annotation)
+        lines.extend(
+            [
+                f"{pad}@{name}.setter",
+                f"{pad}def {name}(self, {param}: {annotation}) -> None: ...",
+            ]
+        )
+
+    return lines
+
+
+def _class(name, cls, indent, types):
+    pad = " " * indent
+    bases = [base.__name__ for base in cls.__bases__ if base is not object]
+    bases = f"({', '.join(bases)})" if bases else ""
+
+    lines = [f"{pad}class {name}{bases}:"]
+    lines.extend(_docstring(cls, indent + 4))
+
+    body = []
+    owner = cls.__name__
+
+    if cls.__text_signature__ is not None:
+        body.append(_init(cls, indent + 4, types))
+
+    for attr, value in sorted(vars(cls).items()):
+        if attr in SKIPPED or _private(attr):
+            continue
+
+        if isinstance(value, cls):
+            # A variant of a simple enum
+            body.append([f"{pad}    {attr}: {name}"])
+        elif isinstance(value, type):
+            # A variant of a complex enum
+            body.append(_class(attr, value, indent + 4, types))
+        elif isinstance(value, (classmethod, types_.ClassMethodDescriptorType)):
+            method = getattr(cls, attr)
+            body.append(
+                _function(
+                    attr, method, indent + 4, types, owner, "cls", "classmethod"
+                )
+            )
+        elif isinstance(value, staticmethod):
+            method = getattr(cls, attr)
+            body.append(
+                _function(
+                    attr, method, indent + 4, types, owner, None, "staticmethod"
+                )
+            )
+        elif isinstance(value, PROPERTIES):
+            body.append(_property(attr, cls, indent + 4, types))
+        elif callable(value):
+            body.append(_function(attr, value, indent + 4, types, owner, "self"))
+
+    if not body and len(lines) == 1:
+        body.append([f"{pad}    ..."])
+
+    for item in body:
+        lines.extend(item)
+
+    return lines
+
+
+def _init(cls, indent, types):
+    """`__init__`, from the signature of the class's constructor"""
+
+    pad = " " * indent
+    signature = types.signature(cls.__name__, "__new__")
+    params = _parameters(cls, types, signature, "self")
+
+    return [f"{pad}def __init__({params}) -> None: ..."]
+
+
+def module_stub(module, introspection, submodules=()):
+    """The stub file of `module`, which imports `submodules`"""
+
+    types = _Types(introspection, module)
+    body = []
+
+    for name, value in sorted(vars(module).items()):
+        if name.startswith("_") or isinstance(value, types_.ModuleType):
+            continue
+
+        if isinstance(value, type):
+            body.append("")
+            body.extend(_class(name, value, 0, types))
+            body.append("")
+        elif callable(value):
+            body.append("")
+            body.extend(_function(name, value, 0, types, module.__name__))
+            body.append("")
+
+    lines = [HEADER]
+    lines.extend(f"import {name}" for name in sorted(types.imports))
+
+    if types.imports:
+        lines.append("")
+
+    for sub in submodules:
+        lines.append(f"from . import {sub} as {sub}")
+
+    if submodules:
+        lines.append("")
+
+    text = "\n".join(lines + body).rstrip() + "\n"
+
+    while "\n\n\n\n" in text:
+        text = text.replace("\n\n\n\n", "\n\n\n")
+
+    return text
+
+
+def generate(root, introspection):
+    """The stubs of the module `root` and its submodules, as a list of
+    `(name, text)`, where `name` is the module's name relative to `root`, or
+    an empty string for `root` itself. `introspection` holds the types of
+    their functions and methods"""
+
+    # PyO3 names submodules without the name of their parent
+    submodules = sorted(
+        name
+        for name, value in vars(root).items()
+        if isinstance(value, types_.ModuleType)
+        and value.__name__ in (name, f"{root.__name__}.{name}")
+    )
+
+    stubs = [("", module_stub(root, introspection, submodules))]
+
+    for name in submodules:
+        stubs.append((name, module_stub(getattr(root, name), introspection)))
+
+    return stubs
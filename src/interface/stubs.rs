@@ -0,0 +1,312 @@
+//! Type stubs (`.pyi` files) for the Python extension module.
+//!
+//! `PyO3` does not emit stubs, so they are generated by introspecting the
+//! imported `zpack` module with `stubs.py`: every class, function and method
+//! of `zpack` and its submodules, with its parameters and docstring.
+//!
+//! Python cannot see the types of parameters and return values, but `PyO3`
+//! records them in the extension module itself (its `experimental-inspect`
+//! feature), as a JSON fragment per class, function and method in an exported
+//! `PYO3_INTROSPECTION_1_*` symbol. [`introspect`] reads these fragments from
+//! the module's shared library, and the generator annotates each parameter
+//! and return value with the type found there. Types `PyO3` cannot name, such
+//! as arguments taken as any Python object, are annotated as `typing.Any`.
+//!
+//! The stubs form a stub-only package (PEP 561), `zpack-stubs`, which is
+//! included in the wheel alongside the extension module so editors can
+//! complete package files. `zpack stubs` regenerates it after the Python API
+//! changes, and `zpack stubs --check` reports stubs which are out of date.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::CString,
+    path::{Path, PathBuf},
+};
+
+use object::{Object, ObjectSection};
+use pyo3::prelude::*;
+use serde::Deserialize;
+
+use crate::interface::reader::ReadError;
+
+const GENERATOR: &str = include_str!("stubs.py");
+
+/// The name of the stub-only package
+pub const STUBS_PACKAGE: &str = "zpack-stubs";
+
+/// Prefix of the symbols holding `PyO3`'s introspection fragments
+const INTROSPECTION_PREFIX: &str = "PYO3_INTROSPECTION_1_";
+
+/// A generated stub file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stub {
+    /// The module the stub describes, such as `zpack.constraint`
+    pub module: String,
+
+    /// Where the stub belongs, relative to the stub package
+    pub path: PathBuf,
+
+    /// The contents of the stub
+    pub text: String,
+}
+
+/// The types of a function or method, as recorded by `PyO3`
+#[derive(Clone, Debug, Default, PartialEq, Eq, IntoPyObject)]
+pub struct Signature {
+    /// The type of each parameter which has one, by name
+    pub parameters: BTreeMap<String, String>,
+
+    /// The return type
+    pub returns: Option<String>,
+}
+
+/// The types recorded in an extension module. See the
+/// [module documentation](self)
+#[derive(Clone, Debug, Default, PartialEq, Eq, IntoPyObject)]
+pub struct Introspection {
+    /// The module each class is exported from, by class name. The module is
+    /// `zpack` or the name of a submodule, such as `constraint`
+    pub classes: BTreeMap<String, String>,
+
+    /// Every function and method, by the name of the class or module it
+    /// belongs to and its own name. Property setters are named `name.setter`
+    pub functions: BTreeMap<(String, String), Signature>,
+}
+
+/// A fragment of `PyO3`'s introspection data
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Fragment {
+    Module {
+        name: String,
+        members: Vec<String>,
+    },
+    Class {
+        id: String,
+        name: String,
+    },
+    Function {
+        id: Option<String>,
+        name: String,
+        parent: Option<String>,
+        #[serde(default)]
+        arguments: Box<Arguments>,
+        returns: Option<String>,
+        #[serde(default)]
+        decorators: Vec<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Arguments {
+    posonlyargs: Vec<Argument>,
+    args: Vec<Argument>,
+    vararg: Option<Argument>,
+    kwonlyargs: Vec<Argument>,
+    kwarg: Option<Argument>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Argument {
+    name: String,
+    annotation: Option<String>,
+}
+
+fn introspection_error(path: &Path, msg: impl std::fmt::Display) -> ReadError {
+    ReadError::Introspection(format!("{}: {msg}", path.display()))
+}
+
+/// Read the introspection fragments exported by the shared library at
+/// `path`.
+fn fragments(path: &Path) -> Result<Vec<Fragment>, ReadError> {
+    let data = std::fs::read(path).map_err(ReadError::IoError)?;
+    let file = object::File::parse(&*data)
+        .map_err(|e| introspection_error(path, e))?;
+
+    let exports = file.exports().map_err(|e| introspection_error(path, e))?;
+    let mut res = Vec::new();
+
+    for export in exports {
+        // Mach-O prefixes symbols with an underscore
+        let name = String::from_utf8_lossy(export.name());
+        if !name.trim_start_matches('_').starts_with(INTROSPECTION_PREFIX) {
+            continue;
+        }
+
+        // The fragment is a `u32` length followed by that many bytes of JSON
+        let bytes = file
+            .sections()
+            .find_map(|section| {
+                let offset = export.address().checked_sub(section.address())?;
+                let data = section.data().ok()?;
+                data.get(usize::try_from(offset).ok()?..)
+            })
+            .ok_or_else(|| {
+                introspection_error(path, format!("no data for {name}"))
+            })?;
+
+        let (len, json) = bytes
+            .split_first_chunk::<4>()
+            .map(|(len, json)| {
+                let len = if file.is_little_endian() {
+                    u32::from_le_bytes(*len)
+                } else {
+                    u32::from_be_bytes(*len)
+                };
+
+                (usize::try_from(len).unwrap_or(usize::MAX), json)
+            })
+            .ok_or_else(|| {
+                introspection_error(path, format!("{name} is empty"))
+            })?;
+
+        let json = json.get(..len).ok_or_else(|| {
+            introspection_error(path, format!("{name} is truncated"))
+        })?;
+
+        res.push(
+            serde_json::from_slice(json).map_err(|e| {
+                introspection_error(path, format!("{name}: {e}"))
+            })?,
+        );
+    }
+
+    if res.is_empty() {
+        return Err(introspection_error(
+            path,
+            "no introspection data; was zpack built without PyO3's \
+             experimental-inspect feature?",
+        ));
+    }
+
+    Ok(res)
+}
+
+/// The types recorded in the extension module at `path`. See the
+/// [module documentation](self)
+///
+/// # Errors
+/// Errors if `path` cannot be read, is not a shared library or holds no
+/// introspection data.
+pub fn introspect(path: &Path) -> Result<Introspection, ReadError> {
+    let fragments = fragments(path)?;
+
+    // Classes by id, and the module each class or function is a member of
+    let mut classes = HashMap::new();
+    let mut modules = HashMap::new();
+
+    for fragment in &fragments {
+        match fragment {
+            Fragment::Class { id, name } => {
+                classes.insert(id.clone(), name.clone());
+            }
+            Fragment::Module { name, members, .. } => {
+                for member in members {
+                    modules.insert(member.clone(), name.clone());
+                }
+            }
+            Fragment::Function { .. } | Fragment::Other => (),
+        }
+    }
+
+    let mut res = Introspection::default();
+
+    for (id, class) in &classes {
+        if let Some(module) = modules.get(id) {
+            res.classes.insert(class.clone(), module.clone());
+        }
+    }
+
+    for fragment in fragments {
+        let Fragment::Function {
+            id,
+            name,
+            parent,
+            arguments,
+            returns,
+            decorators,
+        } = fragment
+        else {
+            continue;
+        };
+
+        let owner = match (&parent, &id) {
+            (Some(parent), _) => classes.get(parent.as_str()),
+            (None, Some(id)) => modules.get(id.as_str()),
+            (None, None) => None,
+        };
+
+        let Some(owner) = owner else { continue };
+
+        let name = if decorators.iter().any(|d| d.ends_with(".setter")) {
+            format!("{name}.setter")
+        } else {
+            name
+        };
+
+        let Arguments { posonlyargs, args, vararg, kwonlyargs, kwarg } =
+            *arguments;
+
+        let parameters = posonlyargs
+            .into_iter()
+            .chain(args)
+            .chain(vararg)
+            .chain(kwonlyargs)
+            .chain(kwarg)
+            .filter_map(|arg| Some((arg.name, arg.annotation?)))
+            .collect();
+
+        res.functions
+            .insert((owner.clone(), name), Signature { parameters, returns });
+    }
+
+    Ok(res)
+}
+
+/// Generate the stubs of `zpack` and each of its submodules.
+///
+/// # Errors
+/// Errors if `zpack` cannot be imported or introspected.
+pub fn generate(py: Python<'_>) -> Result<Vec<Stub>, ReadError> {
+    let code = CString::new(GENERATOR).map_err(|_| ReadError::NotCString)?;
+
+    let root = py
+        .import("zpack")
+        .map_err(|e: PyErr| ReadError::PyErr(e.to_string()))?;
+
+    let path: PathBuf = root
+        .getattr("__file__")
+        .and_then(|file| file.extract())
+        .map_err(|e: PyErr| ReadError::PyErr(e.to_string()))?;
+
+    let types = introspect(&path)?;
+
+    let stubs: Vec<(String, String)> =
+        PyModule::from_code(py, &code, c"zpack_stubs.py", c"zpack_stubs")
+            .and_then(|generator| {
+                generator.getattr("generate")?.call1((root, types))?.extract()
+            })
+            .map_err(|e: PyErr| ReadError::PyErr(e.to_string()))?;
+
+    Ok(stubs
+        .into_iter()
+        .map(|(name, text)| {
+            if name.is_empty() {
+                Stub {
+                    module: "zpack".to_string(),
+                    path: PathBuf::from("__init__.pyi"),
+                    text,
+                }
+            } else {
+                Stub {
+                    module: format!("zpack.{name}"),
+                    path: PathBuf::from(format!("{name}.pyi")),
+                    text,
+                }
+            }
+        })
+        .collect())
+}
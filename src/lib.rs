@@ -1,22 +1,51 @@
+//! zpack: a fast, configurable package manager which builds programs from
+//! source.
+//!
+//! The core data model (package outlines, specs, versions and their parsers)
+//! has no required native dependencies. The rest is enabled by features, both
+//! of which are on by default:
+//!
+//! - `python`: the Python bindings, loading package files and plugins. Requires
+//!   a Python interpreter
+//! - `solver-z3`: concretizing specs with the Z3 solver
+//!
+//! The command line interface, the Python extension module and the C interface
+//! ([`ffi`]) require both. The `tui` feature adds `zpack tui`, an interactive
+//! terminal interface, to the command line interface.
+
 #![warn(clippy::pedantic, clippy::nursery)]
 
+#[cfg(all(feature = "python", feature = "solver-z3"))]
 use pyo3::prelude::*;
 
+#[cfg(all(feature = "python", feature = "solver-z3"))]
 pub mod cli;
+pub mod config;
 pub mod constraint;
+pub mod fetch;
+#[cfg(all(feature = "python", feature = "solver-z3"))]
+pub mod ffi;
+pub mod hooks;
+#[cfg(feature = "python")]
 pub mod interface;
 pub mod package;
 pub mod spec;
 pub mod util;
 
+#[cfg(all(feature = "python", feature = "solver-z3"))]
 fn gen_init(m: &Bound<'_, PyModule>, name: &str) -> PyResult<()> {
     Python::attach(|py| py.import("sys")?.getattr("modules")?.set_item(name, m))
 }
 
+#[cfg(all(feature = "python", feature = "solver-z3"))]
 #[pymodule(name = "constraint")]
 pub mod py_constraint {
-    use pyo3::prelude::*;
+    use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
 
+    #[pymodule_export]
+    pub use crate::constraint::Arith;
+    #[pymodule_export]
+    pub use crate::constraint::ArithOp;
     #[pymodule_export]
     pub use crate::constraint::Cmp;
     #[pymodule_export]
@@ -24,17 +53,148 @@ pub mod py_constraint {
     #[pymodule_export]
     pub use crate::constraint::Depends;
     #[pymodule_export]
+    pub use crate::constraint::Equal;
+    #[pymodule_export]
     pub use crate::constraint::IfThen;
     #[pymodule_export]
+    pub use crate::constraint::InRange;
+    #[pymodule_export]
+    pub use crate::constraint::Matches;
+    #[pymodule_export]
     pub use crate::constraint::Maximize;
     #[pymodule_export]
     pub use crate::constraint::Minimize;
     #[pymodule_export]
+    pub use crate::constraint::NotEqual;
+    #[pymodule_export]
     pub use crate::constraint::NumOf;
     #[pymodule_export]
+    pub use crate::constraint::OptionalDepends;
+    #[pymodule_export]
+    pub use crate::constraint::Prefer;
+    #[pymodule_export]
     pub use crate::constraint::SpecOption;
     #[pymodule_export]
     pub use crate::constraint::Value;
+    #[pymodule_export]
+    pub use crate::constraint::WhenPlatform;
+    use crate::{
+        constraint::{Constraint, syntax},
+        spec::SpecOptionValue,
+    };
+
+    /// Parse a constraint written in constraint syntax, such as
+    /// `"depends(openmpi) when +mpi"` (see [`crate::constraint::syntax`]).
+    ///
+    /// * `package`: The package `+name` and `~name` refer to
+    ///
+    /// # Errors
+    /// Errors if `txt` is not a valid constraint.
+    #[pyfunction]
+    #[pyo3(signature = (txt, package = None))]
+    pub fn parse(txt: &str, package: Option<&str>) -> PyResult<Constraint> {
+        Constraint::parse_in(txt, package)
+            .map_err(|e| PyValueError::new_err(syntax::render(e)))
+    }
+
+    /// The option `name` of `package`, as in `option("mpi", "shared")`, or
+    /// `option("mpi:shared")`. Compare it to a value to build a constraint:
+    /// `option("mpi", "shared") == False`.
+    ///
+    /// # Errors
+    /// Errors if `name` is not given and `package` has no `:`.
+    #[pyfunction]
+    #[pyo3(signature = (package, name = None))]
+    pub fn option(package: &str, name: Option<&str>) -> PyResult<SpecOption> {
+        let (package, name) = match name {
+            Some(name) => (package, name),
+            None => package.split_once(':').ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "expected 'package:option' or a separate option name, \
+                     got '{package}'"
+                ))
+            })?,
+        };
+
+        Ok(SpecOption {
+            package_name: package.to_string(),
+            option_name: name.to_string(),
+        })
+    }
+
+    /// A dependency on the package `name`
+    #[pyfunction]
+    #[must_use]
+    pub const fn depends(name: String) -> Depends {
+        Depends::new(name)
+    }
+
+    /// A constant, such as `value(3)` or `value(Version("1.2"))`
+    #[pyfunction]
+    #[must_use]
+    pub const fn value(value: SpecOptionValue) -> Value {
+        Value { value }
+    }
+
+    /// The number of the given constraints which hold, as in
+    /// `num_of(option("mpi", "openmpi"), option("mpi", "mpich")) == 1`
+    #[pyfunction]
+    #[pyo3(signature = (*of))]
+    #[must_use]
+    pub const fn num_of(of: Vec<Constraint>) -> NumOf {
+        NumOf { of }
+    }
+
+    /// `then` must hold if `cond` holds
+    #[pyfunction]
+    #[must_use]
+    pub const fn if_then(cond: Constraint, then: Constraint) -> IfThen {
+        IfThen { cond, then }
+    }
+
+    /// Prefer solutions where `item` is as large as possible
+    #[pyfunction]
+    #[must_use]
+    pub const fn maximize(item: Constraint) -> Maximize {
+        Maximize { item }
+    }
+
+    /// Prefer solutions where `item` is as small as possible
+    #[pyfunction]
+    #[must_use]
+    pub const fn minimize(item: Constraint) -> Minimize {
+        Minimize { item }
+    }
+
+    /// Prefer solutions where `item` holds, without requiring it, as in
+    /// `prefer(option("mpi", "openmpi") == True, 10)`
+    ///
+    /// # Errors
+    /// Errors if `weight` is zero.
+    #[pyfunction]
+    #[pyo3(signature = (item, weight = 1))]
+    pub fn prefer(item: Constraint, weight: u64) -> PyResult<Prefer> {
+        Prefer::py_new(item, weight)
+    }
+
+    /// True on a platform with the given fact, as in `when_platform(os="linux")`
+    ///
+    /// # Errors
+    /// Errors unless exactly one fact is given, or if the fact is unknown.
+    #[pyfunction]
+    #[pyo3(signature = (**fact))]
+    pub fn when_platform(
+        fact: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<WhenPlatform> {
+        match fact.map(|fact| (fact.len(), fact.iter().next())) {
+            Some((1, Some((key, value)))) => {
+                WhenPlatform::py_new(key.extract()?, value.extract()?)
+            }
+            _ => Err(PyValueError::new_err(
+                "expected exactly one platform fact, such as os=\"linux\"",
+            )),
+        }
+    }
 
     /// Hacky workaround from <https://github.com/PyO3/pyo3/issues/759>
     ///
@@ -46,15 +206,46 @@ pub mod py_constraint {
     }
 }
 
+#[cfg(all(feature = "python", feature = "solver-z3"))]
 #[pymodule(name = "package")]
 pub mod py_package {
     use pyo3::prelude::*;
 
+    #[pymodule_export]
+    pub use crate::package::define::PackageDefinition;
+    #[pymodule_export]
+    pub use crate::package::flags::FlagMapping;
     #[pymodule_export]
     pub use crate::package::outline::PackageOutline;
     #[pymodule_export]
+    pub use crate::package::patch::Patch;
+    #[pymodule_export]
+    pub use crate::package::provider::VersionSource;
+    #[pymodule_export]
+    pub use crate::package::resolver::Conflict;
+    #[pymodule_export]
+    pub use crate::package::resolver::ConflictKind;
+    #[pymodule_export]
+    pub use crate::package::resolver::SolveResult;
+    #[pymodule_export]
+    pub use crate::package::solver::SpecSolver;
+    #[pymodule_export]
+    pub use crate::package::source::Source;
+    #[pymodule_export]
+    pub use crate::package::stats::SolveStats;
+    #[pymodule_export]
+    pub use crate::package::test::SmokeTest;
+    #[pymodule_export]
     pub use crate::package::version::Version;
 
+    /// Define the package `name` in a `with` block, as in
+    /// `with define("openmpi") as p:`. See [`crate::package::define`]
+    #[pyfunction]
+    #[must_use]
+    pub fn define(name: String) -> PackageDefinition {
+        PackageDefinition::new(name)
+    }
+
     /// Hacky workaround from <https://github.com/PyO3/pyo3/issues/759>
     ///
     /// # Errors
@@ -65,6 +256,136 @@ pub mod py_package {
     }
 }
 
+#[cfg(all(feature = "python", feature = "solver-z3"))]
+#[pymodule(name = "spec")]
+pub mod py_spec {
+    use pyo3::{exceptions::PyRuntimeError, prelude::*};
+
+    #[pymodule_export]
+    pub use crate::spec::ConcretePackage;
+    #[pymodule_export]
+    pub use crate::spec::ConcreteSpec;
+    #[pymodule_export]
+    pub use crate::spec::platform::Platform;
+    #[pymodule_export]
+    pub use crate::spec::platform::PlatformKey;
+    use crate::{
+        cli::{CliError, SolveOptions},
+        package::{
+            outline::PackageOutline, resolver::SolveResult, stats::SolveStats,
+        },
+    };
+
+    /// Concretize `roots` against `outlines`, returning the concrete spec and
+    /// statistics about how it was solved.
+    ///
+    /// * `platform`: The platform to concretize for. Defaults to the host
+    /// * `deterministic`: Fix the solver's random seeds
+    /// * `permissive`: Allow references to options no package declares
+    ///
+    /// The GIL is released while solving, so other Python threads keep
+    /// running and solves may be run in background threads.
+    ///
+    /// # Errors
+    /// Errors if the outlines are invalid or the roots cannot be satisfied.
+    #[pyfunction]
+    #[pyo3(signature = (
+        outlines, roots, platform = None, deterministic = false,
+        permissive = false
+    ))]
+    pub fn concretize(
+        py: Python<'_>,
+        outlines: Vec<PackageOutline>,
+        roots: Vec<String>,
+        platform: Option<Platform>,
+        deterministic: bool,
+        permissive: bool,
+    ) -> PyResult<(ConcreteSpec, SolveStats)> {
+        let mut stats = SolveStats::new();
+        let options = solve_options(platform, deterministic, permissive);
+
+        let spec = py
+            .detach(|| {
+                crate::cli::concretize_profiled(
+                    outlines, &roots, &options, &mut stats,
+                )
+            })
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+
+        Ok((spec, stats))
+    }
+
+    /// Concretize `roots` against `outlines`, returning the outcome.
+    ///
+    /// The outcome is a `SolveResult.Sat` with the concrete spec, a
+    /// `SolveResult.Unsat` with the conflicting constraints or a
+    /// `SolveResult.Unknown` with the reason the solver gave up. Each also
+    /// holds statistics about the solve. Takes the same arguments as
+    /// [`concretize`], and likewise releases the GIL while solving.
+    ///
+    /// # Errors
+    /// Errors if the outlines are invalid. Unsatisfiable roots are not an
+    /// error.
+    #[pyfunction]
+    #[pyo3(signature = (
+        outlines, roots, platform = None, deterministic = false,
+        permissive = false
+    ))]
+    // Arguments from Python are extracted by value
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn solve(
+        py: Python<'_>,
+        outlines: Vec<PackageOutline>,
+        roots: Vec<String>,
+        platform: Option<Platform>,
+        deterministic: bool,
+        permissive: bool,
+    ) -> PyResult<SolveResult> {
+        let mut stats = SolveStats::new();
+        let options = solve_options(platform, deterministic, permissive);
+
+        let res = py.detach(|| {
+            crate::cli::concretize_profiled(
+                outlines, &roots, &options, &mut stats,
+            )
+        });
+
+        match res {
+            Ok(spec) => Ok(SolveResult::Sat { spec, stats }),
+            Err(CliError::Unsatisfiable(conflicts)) => {
+                Ok(SolveResult::Unsat { conflicts, stats })
+            }
+            Err(CliError::UnknownSolverResult(reason)) => {
+                Ok(SolveResult::Unknown { reason, stats })
+            }
+            Err(e) => Err(PyRuntimeError::new_err(e.to_string())),
+        }
+    }
+
+    fn solve_options(
+        platform: Option<Platform>,
+        deterministic: bool,
+        permissive: bool,
+    ) -> SolveOptions {
+        SolveOptions {
+            platform: platform.unwrap_or_default(),
+            deterministic,
+            permissive,
+            ..Default::default()
+        }
+    }
+
+    /// Hacky workaround from <https://github.com/PyO3/pyo3/issues/759>
+    ///
+    /// # Errors
+    /// May error if sys.modules is not loadable
+    #[pymodule_init]
+    pub fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        super::gen_init(m, "zpack.spec")
+    }
+}
+
+#[cfg(all(feature = "python", feature = "solver-z3"))]
 #[pymodule(name = "zpack")]
 pub mod py_zpack {
     use pyo3::{exceptions::PyRuntimeError, prelude::*};
@@ -73,6 +394,12 @@ pub mod py_zpack {
     pub use super::py_constraint;
     #[pymodule_export]
     pub use super::py_package;
+    #[pymodule_export]
+    pub use super::py_spec;
+    #[pymodule_export]
+    pub use crate::interface::plugins::PluginRegistry;
+    #[pymodule_export]
+    pub use crate::interface::plugins::plugin_constraint;
 
     /// The main python entry point
     ///
@@ -81,10 +408,13 @@ pub mod py_zpack {
     /// It may also be worth calling
     /// [`zpack.init_tracing()`](py_tracing::init_tracing), as additional
     /// information is logged throughout the execution of `zpack`.
+    ///
+    /// The GIL is only held while Python code runs, such as package files and
+    /// hooks, and not while solving.
     #[pyfunction]
-    pub fn main_entry() -> PyResult<()> {
-        crate::cli::entry(true)
-            .map_err(|e| PyRuntimeError::new_err(format!("{e:?}")))
+    pub fn main_entry(py: Python<'_>) -> PyResult<()> {
+        py.detach(|| crate::cli::entry(true).map_err(|e| e.to_string()))
+            .map_err(PyRuntimeError::new_err)
     }
 
     /// Initialize the tracing subscriber in Python so internal logs are printed
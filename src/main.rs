@@ -105,7 +105,7 @@ zpack:
 
 #[allow(clippy::too_many_lines)]
 fn test_outline() {
-    use std::collections::HashMap;
+    use std::collections::{BTreeSet, HashMap};
 
     use zpack::{
         constraint::{Depends, IfThen, SpecOption, Value},
@@ -113,125 +113,34 @@ fn test_outline() {
         spec::SpecOptionValue,
     };
 
-    let hpl_outline = PackageOutline {
-        name: "hpl".into(),
-        constraints: vec![
-            Depends::new("blas".into()).into(),
-            Depends::new("mpi".into()).into(),
-            Depends::new("gcc".into()).into(),
-            // Cmp {
-            //     lhs: SpecOption {
-            //         package_name: "openmpi".into(),
-            //         option_name: "version".into(),
-            //     }
-            //     .into(),
-            //     rhs: Value {
-            //         value: SpecOptionValue::Version(
-            //             package::version::Version::new("1.0.*").unwrap(),
-            //         ),
-            //     }
-            //     .into(),
-            //     op: CmpType::Equal,
-            // }
-            // .into(),
-            // Cmp {
-            //     lhs: SpecOption {
-            //         package_name: "openmpi".into(),
-            //         option_name: "version".into(),
-            //     }
-            //     .into(),
-            //     rhs: Value {
-            //         value: SpecOptionValue::Version(
-            //             package::version::Version::new("*.0.49").unwrap(),
-            //         ),
-            //     }
-            //     .into(),
-            //     op: CmpType::Equal,
-            // }
-            // .into(),
-        ],
-        set_options: HashMap::default(),
-        set_defaults: HashMap::from([(
-            "static".into(),
-            Some(SpecOptionValue::Bool(true)),
-        )]),
-    };
-
-    let blas_outline = PackageOutline {
-        name: "blas".into(),
-
-        constraints: vec![
-            Cmp {
-                lhs: NumOf {
-                    of: vec![
-                        Cmp {
-                            lhs: SpecOption {
-                                package_name: "blas".into(),
-                                option_name: "openblas".into(),
-                            }
-                            .into(),
-                            rhs: Value { value: SpecOptionValue::Bool(true) }
-                                .into(),
-                            op: CmpType::Equal,
-                        }
-                        .into(),
-                        Cmp {
-                            lhs: SpecOption {
-                                package_name: "blas".into(),
-                                option_name: "mkl".into(),
-                            }
-                            .into(),
-                            rhs: Value { value: SpecOptionValue::Bool(true) }
-                                .into(),
-                            op: CmpType::Equal,
-                        }
-                        .into(),
-                    ],
-                }
-                .into(),
-                rhs: Value { value: SpecOptionValue::Int(1) }.into(),
-                op: CmpType::Equal,
-            }
-            .into(),
-            IfThen {
-                cond: Cmp {
-                    lhs: SpecOption {
-                        package_name: "blas".into(),
-                        option_name: "openblas".into(),
-                    }
-                    .into(),
-                    rhs: Value { value: SpecOptionValue::Bool(true) }.into(),
-                    op: CmpType::Equal,
-                }
-                .into(),
-                then: Depends::new("openblas".into()).into(),
-            }
-            .into(),
-            IfThen {
-                cond: Cmp {
-                    lhs: SpecOption {
-                        package_name: "blas".into(),
-                        option_name: "mkl".into(),
-                    }
-                    .into(),
-                    rhs: Value { value: SpecOptionValue::Bool(true) }.into(),
-                    op: CmpType::Equal,
-                }
-                .into(),
-                then: Depends::new("mkl".into()).into(),
-            }
-            .into(),
-        ],
-
-        set_options: HashMap::from([(
-            "openblas".into(),
-            SpecOptionValue::Bool(true),
-        )]),
-        set_defaults: HashMap::from([("something".into(), None)]),
+    let hpl_outline = PackageOutline::builder("hpl")
+        .depends("blas")
+        .depends("mpi")
+        .depends("gcc")
+        .option_default("static", true)
+        .build();
+
+    let blas_outline = zpack::package! {
+        "blas" {
+            one_of [openblas, mkl];
+            depends_on "openblas" when "openblas";
+            depends_on "mkl" when "mkl";
+            option openblas = true;
+            clear_default something;
+        }
     };
 
     let mpi_outline = PackageOutline {
         name: "mpi".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
 
         constraints: vec![
             Cmp {
@@ -330,6 +239,15 @@ fn test_outline() {
 
     let openblas_outline = PackageOutline {
         name: "openblas".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![Depends::new("gcc".into()).into()],
         set_options: HashMap::default(),
         set_defaults: HashMap::default(),
@@ -337,6 +255,15 @@ fn test_outline() {
 
     let mkl_outline = PackageOutline {
         name: "mkl".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![Depends::new("gcc".into()).into()],
         set_options: HashMap::default(),
         set_defaults: HashMap::default(),
@@ -382,6 +309,15 @@ fn test_outline() {
 
     let openmpi_outline = PackageOutline {
         name: "openmpi".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![
             Cmp {
                 lhs: NumOf { of: openmpi_versions }.into(),
@@ -442,6 +378,15 @@ fn test_outline() {
 
     let mpich_outline = PackageOutline {
         name: "mpich".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![Depends::new("gcc".into()).into()],
         set_options: HashMap::default(),
         set_defaults: HashMap::new(),
@@ -449,6 +394,15 @@ fn test_outline() {
 
     let intelmpi_outline = PackageOutline {
         name: "intelmpi".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![Depends::new("gcc".into()).into()],
         set_options: HashMap::default(),
         set_defaults: HashMap::new(),
@@ -456,6 +410,15 @@ fn test_outline() {
 
     let openpmix_outline = PackageOutline {
         name: "openpmix".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![Depends::new("gcc".into()).into()],
         set_options: HashMap::default(),
         set_defaults: HashMap::default(),
@@ -463,6 +426,15 @@ fn test_outline() {
 
     let openprrte_outline = PackageOutline {
         name: "openprrte".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![Depends::new("gcc".into()).into()],
         set_options: HashMap::default(),
         set_defaults: HashMap::default(),
@@ -491,6 +463,15 @@ fn test_outline() {
 
     let hwloc_outline = PackageOutline {
         name: "hwloc".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: vec![
             // Cmp {
             //     lhs: NumOf { of: hwloc_versions }.into(),
@@ -506,6 +487,15 @@ fn test_outline() {
 
     let gcc_outline = PackageOutline {
         name: "gcc".into(),
+        patches: Vec::new(),
+        flags: Vec::new(),
+        tests: Vec::new(),
+        requested_options: BTreeSet::new(),
+        sources: Vec::new(),
+        namespace: None,
+        version_source: None,
+        license: None,
+        schema: HashMap::new(),
         constraints: Vec::new(),
         set_options: HashMap::default(),
         set_defaults: HashMap::from([(
@@ -562,7 +552,7 @@ fn test_outline() {
             tracing::info!("sat");
 
             let model = optimizer.get_model().unwrap();
-            for &(package, option) in registry.spec_option_names() {
+            for (package, option) in registry.spec_option_names() {
                 println!(
                     "{}:{:?} -> {:?}",
                     package,
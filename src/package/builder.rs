@@ -0,0 +1,385 @@
+//! Fluent construction of [`PackageOutline`]s in Rust.
+//!
+//! ```rust,ignore
+//! use zpack::{constraint::SpecOption, package::outline::PackageOutline};
+//!
+//! let blas = PackageOutline::builder("blas")
+//!     .one_of(["openblas", "mkl"])
+//!     .depends_if("openblas", "openblas")
+//!     .depends_if("mkl", "mkl")
+//!     .option("openblas", true)
+//!     .build();
+//!
+//! let hpl = PackageOutline::builder("hpl")
+//!     .depends("blas")
+//!     .option_default("static", true)
+//!     .constraint(SpecOption::new("blas", "openblas").equals(true))
+//!     .build();
+//! ```
+//!
+//! The [`package!`](crate::package!) macro expands a declarative description
+//! into the same builder calls:
+//!
+//! ```rust,ignore
+//! let hpl = zpack::package! {
+//!     "hpl" {
+//!         versions ["2.3", "2.2"];
+//!         variant static = true;
+//!         depends_on "blas";
+//!         depends_on "mpi";
+//!         depends_on "openmp" when "openmp";
+//!         optional "magma" when magma;
+//!         depends_on "cuda" when (SpecOption::new("blas", "cuda").equals(true));
+//!     }
+//! };
+//! ```
+
+use crate::{
+    constraint::{
+        Cmp, CmpType, Constraint, Depends, IfThen, NumOf, OptionalDepends,
+        Prefer, SpecOption, Value,
+    },
+    package::{
+        flags::FlagMapping, outline::PackageOutline, patch::Patch,
+        provider::VersionSource, schema::OptionSchema, source::Source,
+        test::SmokeTest, version::Version,
+    },
+    spec::SpecOptionValue,
+};
+
+/// Builds a [`PackageOutline`]. Created with [`PackageOutline::builder`]
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct PackageOutlineBuilder {
+    outline: PackageOutline,
+}
+
+impl PackageOutlineBuilder {
+    pub(crate) fn new(name: impl Into<String>) -> Self {
+        Self {
+            outline: PackageOutline { name: name.into(), ..Default::default() },
+        }
+    }
+
+    /// The repository namespace the package belongs to
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.outline.namespace = Some(namespace.into());
+        self
+    }
+
+    /// The license of the package, as an SPDX license expression
+    pub fn license(mut self, license: impl Into<String>) -> Self {
+        self.outline.license = Some(license.into());
+        self
+    }
+
+    /// Add an arbitrary constraint.
+    pub fn constraint(mut self, constraint: impl Into<Constraint>) -> Self {
+        self.outline.constraints.push(constraint.into());
+        self
+    }
+
+    /// Depend on `package` unconditionally.
+    pub fn depends(self, package: impl Into<String>) -> Self {
+        self.constraint(Depends::new(package.into()))
+    }
+
+    /// Depend on `package` if `when` holds.
+    pub fn depends_when(
+        self,
+        package: impl Into<String>,
+        when: impl Into<Constraint>,
+    ) -> Self {
+        self.constraint(IfThen::new(when, Depends::new(package.into())))
+    }
+
+    /// Depend on `package` if the boolean option `option` of this package is
+    /// enabled.
+    pub fn depends_if(
+        self,
+        option: impl Into<String>,
+        package: impl Into<String>,
+    ) -> Self {
+        let when = self.own_option(option).equals(true);
+        self.depends_when(package, when)
+    }
+
+    /// Add the boolean option `option` to this package, disabled by default,
+    /// and depend on `package` if it is enabled. See
+    /// [`PackageOutline::push_optional_depends`]
+    pub fn optional_depends(
+        mut self,
+        option: impl Into<String>,
+        package: impl Into<String>,
+    ) -> Self {
+        let dep =
+            OptionalDepends::new(self.outline.name.clone(), option, package);
+        self.outline.push_optional_depends(dep);
+        self
+    }
+
+    /// Require exactly one of the boolean options in `options` to be
+    /// enabled.
+    pub fn one_of<S: Into<String>>(
+        self,
+        options: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let of = options
+            .into_iter()
+            .map(|option| self.own_option(option).equals(true).into())
+            .collect::<Vec<_>>();
+
+        self.constraint(Cmp::new(
+            NumOf::new(of),
+            CmpType::Equal,
+            Value::new(1_i64),
+        ))
+    }
+
+    /// Prefer solutions where `constraint` holds, without requiring it. See
+    /// [`Prefer`]
+    pub fn prefer(
+        self,
+        constraint: impl Into<Constraint>,
+        weight: u64,
+    ) -> Self {
+        self.constraint(Prefer::new(constraint, weight))
+    }
+
+    /// Require the package's version to be exactly one of `versions`.
+    pub fn versions(
+        mut self,
+        versions: impl IntoIterator<Item = Version>,
+    ) -> Self {
+        self.outline.push_version_domain(versions);
+        self
+    }
+
+    /// Discover the package's versions from `source` when it is loaded.
+    pub fn version_source(mut self, source: VersionSource) -> Self {
+        self.outline.version_source = Some(source);
+        self
+    }
+
+    /// Set the value of an option of this package.
+    pub fn option(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<SpecOptionValue>,
+    ) -> Self {
+        self.outline.set_options.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the default value of an option, which is inherited by
+    /// dependencies.
+    pub fn option_default(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<SpecOptionValue>,
+    ) -> Self {
+        self.outline.set_defaults.insert(name.into(), Some(value.into()));
+        self
+    }
+
+    /// Declare the option `name`. See [`crate::package::schema`]
+    pub fn declare(
+        mut self,
+        name: impl Into<String>,
+        schema: OptionSchema,
+    ) -> Self {
+        self.outline.declare_option(name, schema);
+        self
+    }
+
+    /// Stop an inherited default from propagating to this package.
+    pub fn clear_default(mut self, name: impl Into<String>) -> Self {
+        self.outline.set_defaults.insert(name.into(), None);
+        self
+    }
+
+    pub fn patch(mut self, patch: Patch) -> Self {
+        self.outline.patches.push(patch);
+        self
+    }
+
+    pub fn source(mut self, source: Source) -> Self {
+        self.outline.sources.push(source);
+        self
+    }
+
+    pub fn flags(mut self, flags: FlagMapping) -> Self {
+        self.outline.flags.push(flags);
+        self
+    }
+
+    pub fn test(mut self, test: SmokeTest) -> Self {
+        self.outline.tests.push(test);
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> PackageOutline {
+        self.outline
+    }
+
+    fn own_option(&self, option: impl Into<String>) -> SpecOption {
+        SpecOption::new(self.outline.name.clone(), option)
+    }
+}
+
+/// Define a [`PackageOutline`] declaratively.
+///
+/// The body is a list of `;` terminated statements, each of which maps onto a
+/// [`PackageOutlineBuilder`] method:
+///
+/// | Statement                            | Builder method                     |
+/// |--------------------------------------|------------------------------------|
+/// | `namespace "ns";`                    | [`namespace`]                      |
+/// | `license "MIT";`                     | [`license`]                        |
+/// | `versions ["1.0", "2.0"];`           | [`versions`]                       |
+/// | `variant name = value;`              | [`option_default`]                 |
+/// | `option name = value;`               | [`option`]                         |
+/// | `one_of ["a", "b"];`                 | [`one_of`]                         |
+/// | `depends_on "pkg";`                  | [`depends`]                        |
+/// | `depends_on "pkg" when "option";`    | [`depends_if`]                     |
+/// | `depends_on "pkg" when (condition);` | [`depends_when`]                   |
+/// | `optional "pkg" when option;`        | [`optional_depends`]               |
+/// | `constraint expr;`                   | [`constraint`]                     |
+///
+/// Option names may be identifiers or string literals.
+///
+/// # Panics
+/// Panics if a version literal is not a valid version.
+///
+/// [`namespace`]: PackageOutlineBuilder::namespace
+/// [`license`]: PackageOutlineBuilder::license
+/// [`versions`]: PackageOutlineBuilder::versions
+/// [`option_default`]: PackageOutlineBuilder::option_default
+/// [`option`]: PackageOutlineBuilder::option
+/// [`clear_default`]: PackageOutlineBuilder::clear_default
+/// [`one_of`]: PackageOutlineBuilder::one_of
+/// [`depends`]: PackageOutlineBuilder::depends
+/// [`depends_if`]: PackageOutlineBuilder::depends_if
+/// [`depends_when`]: PackageOutlineBuilder::depends_when
+/// [`optional_depends`]: PackageOutlineBuilder::optional_depends
+/// [`constraint`]: PackageOutlineBuilder::constraint
+#[macro_export]
+macro_rules! package {
+    ($name:literal { $($body:tt)* }) => {
+        $crate::__package_body!(
+            $crate::package::outline::PackageOutline::builder($name);
+            $($body)*
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __package_body {
+    ($builder:expr;) => {
+        $builder.build()
+    };
+
+    ($builder:expr; namespace $namespace:literal; $($rest:tt)*) => {
+        $crate::__package_body!($builder.namespace($namespace); $($rest)*)
+    };
+
+    ($builder:expr; license $license:literal; $($rest:tt)*) => {
+        $crate::__package_body!($builder.license($license); $($rest)*)
+    };
+
+    ($builder:expr; versions [$($version:literal),* $(,)?]; $($rest:tt)*) => {
+        $crate::__package_body!(
+            $builder.versions([$(
+                $crate::package::version::Version::new($version)
+                    .expect(concat!("invalid version '", $version, "'"))
+            ),*]);
+            $($rest)*
+        )
+    };
+
+    ($builder:expr; variant $option:tt = $value:expr; $($rest:tt)*) => {
+        $crate::__package_body!(
+            $builder.option_default($crate::__package_name!($option), $value);
+            $($rest)*
+        )
+    };
+
+    ($builder:expr; option $option:tt = $value:expr; $($rest:tt)*) => {
+        $crate::__package_body!(
+            $builder.option($crate::__package_name!($option), $value);
+            $($rest)*
+        )
+    };
+
+    ($builder:expr; clear_default $option:tt; $($rest:tt)*) => {
+        $crate::__package_body!(
+            $builder.clear_default($crate::__package_name!($option));
+            $($rest)*
+        )
+    };
+
+    ($builder:expr; one_of [$($option:tt),* $(,)?]; $($rest:tt)*) => {
+        $crate::__package_body!(
+            $builder.one_of([$($crate::__package_name!($option)),*]);
+            $($rest)*
+        )
+    };
+
+    ($builder:expr; depends_on $package:literal; $($rest:tt)*) => {
+        $crate::__package_body!($builder.depends($package); $($rest)*)
+    };
+
+    (
+        $builder:expr;
+        depends_on $package:literal when $option:literal;
+        $($rest:tt)*
+    ) => {
+        $crate::__package_body!(
+            $builder.depends_if($option, $package);
+            $($rest)*
+        )
+    };
+
+    (
+        $builder:expr;
+        depends_on $package:literal when ($when:expr);
+        $($rest:tt)*
+    ) => {
+        $crate::__package_body!(
+            $builder.depends_when($package, $when);
+            $($rest)*
+        )
+    };
+
+    (
+        $builder:expr;
+        optional $package:literal when $option:tt;
+        $($rest:tt)*
+    ) => {
+        $crate::__package_body!(
+            $builder.optional_depends(
+                $crate::__package_name!($option),
+                $package,
+            );
+            $($rest)*
+        )
+    };
+
+    ($builder:expr; constraint $constraint:expr; $($rest:tt)*) => {
+        $crate::__package_body!($builder.constraint($constraint); $($rest)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __package_name {
+    ($name:ident) => {
+        stringify!($name)
+    };
+
+    ($name:literal) => {
+        $name
+    };
+}
@@ -0,0 +1,273 @@
+//! Defining packages in Python with a `with` block.
+//!
+//! ```python
+//! from zpack.constraint import when_platform
+//! from zpack.package import define
+//!
+//! with define("openmpi") as p:
+//!     p.version("5.0.3", "4.1.6")
+//!     p.variant("cuda", False, description="Build with CUDA support")
+//!     p.depends_on("hwloc")
+//!     p.depends_on("cuda", when="+cuda")
+//!     p.optional_depends("ucx", "ucx", description="Use the UCX transport")
+//!     p.conflicts("+cuda", when=when_platform(os="macos"))
+//!     p.prefer("~cuda", weight=5)
+//! ```
+//!
+//! Each block builds a [`PackageOutline`] through a [`PackageOutlineBuilder`]
+//! and registers it when the block ends without an exception. A package file
+//! which defines its packages this way does not need a `zpack_packages()`
+//! function: the reader returns every package registered while executing the
+//! file (see [`take_defined`]).
+//!
+//! Conditions (`when=`) and the constraints given to
+//! [`PackageDefinition::conflicts`] may be constraints or strings in
+//! [constraint syntax](crate::constraint::syntax), where `+name` and `~name`
+//! refer to the options of the package being defined.
+
+use std::sync::Mutex;
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyString};
+
+use crate::{
+    constraint::{Cmp, Constraint, IfThen, Prefer, SpecOption, syntax},
+    package::{
+        builder::PackageOutlineBuilder, outline::PackageOutline,
+        schema::OptionSchema, version::Version,
+    },
+    spec::{SpecOptionType, SpecOptionValue},
+};
+
+/// Packages defined by `with` blocks which have not been collected yet
+static DEFINED: Mutex<Vec<Py<PackageDefinition>>> = Mutex::new(Vec::new());
+
+/// Take every package defined since the last call, in the order they were
+/// defined.
+///
+/// # Panics
+/// Panics if the registry lock is poisoned.
+pub fn take_defined(py: Python<'_>) -> Vec<Bound<'_, PyAny>> {
+    std::mem::take(&mut *DEFINED.lock().expect("package registry poisoned"))
+        .into_iter()
+        .map(|package| package.into_bound(py).into_any())
+        .collect()
+}
+
+/// A package being defined in a `with` block. Created with
+/// `zpack.package.define(name)`
+#[pyclass(module = "zpack.package")]
+pub struct PackageDefinition {
+    name: String,
+    builder: PackageOutlineBuilder,
+
+    /// Versions given to [`Self::version`], which must all be allowed by one
+    /// version constraint
+    versions: Vec<Version>,
+}
+
+impl PackageDefinition {
+    /// Apply `f` to the builder.
+    fn update(
+        &mut self,
+        f: impl FnOnce(PackageOutlineBuilder) -> PackageOutlineBuilder,
+    ) {
+        self.builder = f(std::mem::take(&mut self.builder));
+    }
+
+    /// `when`, parsed as constraint syntax if it is a string.
+    fn condition(&self, when: &Bound<'_, PyAny>) -> PyResult<Constraint> {
+        if let Ok(txt) = when.cast::<PyString>() {
+            Constraint::parse_in(txt.to_str()?, Some(&self.name))
+                .map_err(|e| PyValueError::new_err(syntax::render(e)))
+        } else {
+            when.extract()
+        }
+    }
+}
+
+#[pymethods]
+impl PackageDefinition {
+    #[new]
+    #[must_use]
+    pub fn new(name: String) -> Self {
+        let builder = PackageOutline::builder(name.clone());
+        Self { name, builder, versions: Vec::new() }
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    const fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Register the package, unless the block raised an exception.
+    ///
+    /// # Panics
+    /// Panics if the registry lock is poisoned.
+    #[allow(clippy::needless_pass_by_value)]
+    // Arguments from Python are extracted by value
+    fn __exit__(
+        slf: Bound<'_, Self>,
+        exc_type: Option<Bound<'_, PyAny>>,
+        _exc_value: Option<Bound<'_, PyAny>>,
+        _traceback: Option<Bound<'_, PyAny>>,
+    ) -> bool {
+        if exc_type.is_none() {
+            DEFINED
+                .lock()
+                .expect("package registry poisoned")
+                .push(slf.unbind());
+        }
+
+        false
+    }
+
+    /// Allow the package to have the versions `versions`.
+    ///
+    /// # Errors
+    /// Errors if a version is invalid.
+    #[pyo3(signature = (*versions))]
+    fn version(&mut self, versions: Vec<String>) -> PyResult<()> {
+        for version in versions {
+            self.versions.push(Version::new(&version).map_err(|e| {
+                PyValueError::new_err(format!(
+                    "invalid version '{version}': {e:?}"
+                ))
+            })?);
+        }
+
+        Ok(())
+    }
+
+    /// Declare the option `name`, with the type of `default`.
+    ///
+    /// * `values`: The values the option may take. Any value of the right
+    ///   type if not given
+    /// * `description`: What the option does
+    #[pyo3(signature = (name, default, values = None, description = None))]
+    fn variant(
+        &mut self,
+        name: String,
+        default: SpecOptionValue,
+        values: Option<Vec<SpecOptionValue>>,
+        description: Option<String>,
+    ) {
+        let mut schema = OptionSchema::new(default.to_type())
+            .with_default(default)
+            .with_values(values.unwrap_or_default());
+        schema.description = description;
+
+        self.update(|builder| builder.declare(name, schema));
+    }
+
+    /// Depend on `package`, or only if `when` holds.
+    ///
+    /// # Errors
+    /// Errors if `when` is not a valid constraint.
+    #[pyo3(signature = (package, when = None))]
+    fn depends_on(
+        &mut self,
+        package: String,
+        when: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<()> {
+        let when = when.map(|when| self.condition(when)).transpose()?;
+
+        self.update(|builder| match when {
+            Some(when) => builder.depends_when(package, when),
+            None => builder.depends(package),
+        });
+
+        Ok(())
+    }
+
+    /// Declare the boolean option `option`, disabled by default, and depend
+    /// on `package` if it is enabled.
+    ///
+    /// * `description`: What the option does
+    #[pyo3(signature = (option, package, description = None))]
+    fn optional_depends(
+        &mut self,
+        option: String,
+        package: String,
+        description: Option<String>,
+    ) {
+        let mut schema =
+            OptionSchema::new(SpecOptionType::Bool).with_default(false);
+        schema.description = description;
+
+        self.update(|builder| {
+            builder
+                .declare(option.clone(), schema)
+                .optional_depends(option, package)
+        });
+    }
+
+    /// Forbid `constraint` from holding, or only if `when` holds.
+    ///
+    /// # Errors
+    /// Errors if `constraint` or `when` is not a valid condition.
+    #[pyo3(signature = (constraint, when = None))]
+    fn conflicts(
+        &mut self,
+        constraint: &Bound<'_, PyAny>,
+        when: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<()> {
+        let forbidden = Cmp::py_invert(self.condition(constraint)?)?;
+        let when = when.map(|when| self.condition(when)).transpose()?;
+
+        self.update(|builder| match when {
+            Some(when) => builder.constraint(IfThen::new(when, forbidden)),
+            None => builder.constraint(forbidden),
+        });
+
+        Ok(())
+    }
+
+    /// Prefer solutions where `constraint` holds, without requiring it. See
+    /// [`Prefer`]
+    ///
+    /// * `weight`: How much breaking the preference costs, relative to other
+    ///   preferences
+    ///
+    /// # Errors
+    /// Errors if `constraint` is not a valid condition, or `weight` is zero.
+    #[pyo3(signature = (constraint, weight = 1))]
+    fn prefer(
+        &mut self,
+        constraint: &Bound<'_, PyAny>,
+        weight: u64,
+    ) -> PyResult<()> {
+        let prefer = Prefer::py_new(self.condition(constraint)?, weight)?;
+        self.update(|builder| builder.constraint(prefer));
+        Ok(())
+    }
+
+    /// Add an arbitrary constraint.
+    ///
+    /// # Errors
+    /// Errors if `constraint` is not a valid constraint.
+    fn constraint(&mut self, constraint: &Bound<'_, PyAny>) -> PyResult<()> {
+        let constraint = self.condition(constraint)?;
+        self.update(|builder| builder.constraint(constraint));
+        Ok(())
+    }
+
+    /// The option `name` of this package, to build constraints from
+    fn option(&self, name: &str) -> SpecOption {
+        SpecOption::new(&self.name, name)
+    }
+
+    /// The outline of the package defined so far
+    fn outline(&self) -> PackageOutline {
+        let mut builder = self.builder.clone();
+
+        if !self.versions.is_empty() {
+            builder = builder.versions(self.versions.iter().cloned());
+        }
+
+        builder.build()
+    }
+}
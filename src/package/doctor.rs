@@ -0,0 +1,228 @@
+//! Consistency checks between an [`InstallDb`] and its install root.
+//!
+//! [`check`] compares what the database records with what is on disk and
+//! reports each [`Problem`] found:
+//!
+//! - installs which were interrupted, leaving a staging directory or partial
+//!   prefix behind
+//! - packages recorded as installed whose prefix has been removed
+//! - installed prefixes whose [spec file](crate::spec::spec_file) is missing,
+//!   invalid or describes a different package than the database records
+//! - directories in the install root which look like prefixes, staging
+//!   directories or quarantined prefixes but which the database does not
+//!   know about
+//!
+//! Every problem can be repaired with [`repair`]. Only directories named like
+//! those zpack creates are ever considered orphaned, so an install root
+//! shared with other files is safe to repair.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    package::install::{
+        InstallDb, InstallState, QUARANTINE_SUFFIX, STAGING_SUFFIX,
+        prefix_lock_path,
+    },
+    spec::spec_file::{self, SpecFile},
+    util::lock::FileLock,
+};
+
+/// Something wrong with an install root
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Problem {
+    /// The install of a package was interrupted
+    Interrupted { name: String, hash: String },
+
+    /// A package is recorded as installed but its prefix does not exist
+    MissingPrefix { name: String, hash: String, prefix: PathBuf },
+
+    /// The spec file of an installed package is missing, invalid or records
+    /// a different hash
+    HashMismatch { name: String, hash: String, prefix: PathBuf, reason: String },
+
+    /// A directory in the install root which the database does not know
+    /// about
+    Orphaned { path: PathBuf },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Interrupted { name, .. } => {
+                write!(f, "the install of {name} was interrupted")
+            }
+            Self::MissingPrefix { name, prefix, .. } => write!(
+                f,
+                "{name} is installed but its prefix '{}' is missing",
+                prefix.display()
+            ),
+            Self::HashMismatch { name, prefix, reason, .. } => write!(
+                f,
+                "{name} in '{}' does not match the database: {reason}",
+                prefix.display()
+            ),
+            Self::Orphaned { path } => {
+                write!(f, "'{}' is not in the database", path.display())
+            }
+        }
+    }
+}
+
+/// `name` without the staging or quarantine suffix, if it has one
+fn strip_suffix(name: &str) -> &str {
+    [STAGING_SUFFIX, QUARANTINE_SUFFIX]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix)?.strip_suffix('.'))
+        .unwrap_or(name)
+}
+
+/// Whether `name` is the name of a directory zpack creates in an install
+/// root: `<package>-[<version>-]<hash>`, optionally followed by the staging
+/// or quarantine suffix.
+fn is_prefix_name(name: &str) -> bool {
+    strip_suffix(name).rsplit_once('-').is_some_and(|(package, hash)| {
+        !package.is_empty()
+            && hash.len() == 16
+            && hash.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Why the spec file in the prefix of the package with `hash` does not match
+/// it, if it does not.
+fn spec_file_mismatch(name: &str, hash: &str, prefix: &Path) -> Option<String> {
+    let file = match SpecFile::load(&spec_file::path(prefix)) {
+        Ok(file) => file,
+        Err(e) => return Some(format!("cannot read its spec file: {e}")),
+    };
+
+    if file.root != name {
+        return Some(format!("its spec file describes {}", file.root));
+    }
+
+    match file.hashes.get(&file.root) {
+        Some(found) if found == hash => None,
+        Some(found) => Some(format!("its spec file records hash {found}")),
+        None => Some("its spec file records no hash".to_string()),
+    }
+}
+
+/// Directories in the install root of `db` which look like they were created
+/// by zpack but are not referenced by the database.
+fn orphans(db: &InstallDb) -> std::io::Result<Vec<PathBuf>> {
+    if !db.root().is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let known = db
+        .packages
+        .values()
+        .flat_map(|record| {
+            [
+                Some(&record.prefix),
+                record.stage.as_ref(),
+                record.quarantine.as_ref(),
+            ]
+        })
+        .flatten()
+        .collect::<HashSet<_>>();
+
+    let mut res = Vec::new();
+
+    for entry in std::fs::read_dir(db.root())? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir()
+            && is_prefix_name(&entry.file_name().to_string_lossy())
+            && !known.contains(&path)
+        {
+            res.push(path);
+        }
+    }
+
+    res.sort();
+
+    Ok(res)
+}
+
+/// Every problem with the install root of `db`.
+///
+/// # Errors
+/// Errors if the install root cannot be read.
+pub fn check(db: &InstallDb) -> std::io::Result<Vec<Problem>> {
+    let mut res = db
+        .interrupted()
+        .map(|(hash, record)| Problem::Interrupted {
+            name: record.name.clone(),
+            hash: hash.clone(),
+        })
+        .chain(db.missing().map(|(hash, record)| Problem::MissingPrefix {
+            name: record.name.clone(),
+            hash: hash.clone(),
+            prefix: record.prefix.clone(),
+        }))
+        .collect::<Vec<_>>();
+
+    for (hash, record) in &db.packages {
+        if record.state != InstallState::Installed || !record.prefix.is_dir() {
+            continue;
+        }
+
+        if let Some(reason) =
+            spec_file_mismatch(&record.name, hash, &record.prefix)
+        {
+            res.push(Problem::HashMismatch {
+                name: record.name.clone(),
+                hash: hash.clone(),
+                prefix: record.prefix.clone(),
+                reason,
+            });
+        }
+    }
+
+    res.extend(orphans(db)?.into_iter().map(|path| Problem::Orphaned { path }));
+
+    Ok(res)
+}
+
+/// Repair `problem`:
+///
+/// - an interrupted install is cleaned up and recorded as failed, so it is
+///   retried by the next install
+/// - a package whose prefix is missing is forgotten
+/// - a prefix which does not match the database is quarantined and recorded
+///   as failed
+/// - an orphaned directory is removed
+///
+/// # Errors
+/// Errors if a prefix is locked by another process, or files cannot be moved
+/// or removed or the database written.
+pub fn repair(db: &mut InstallDb, problem: &Problem) -> std::io::Result<()> {
+    match problem {
+        Problem::Interrupted { hash, .. }
+        | Problem::MissingPrefix { hash, .. } => db.repair(hash),
+        Problem::HashMismatch { hash, reason, .. } => {
+            db.invalidate(hash, &format!("the prefix did not match: {reason}"))
+        }
+        Problem::Orphaned { path } => {
+            let prefix = path.with_file_name(strip_suffix(
+                &path.file_name().unwrap_or_default().to_string_lossy(),
+            ));
+
+            // A process installing to the prefix may not have recorded it yet
+            let _lock = FileLock::exclusive(
+                &prefix_lock_path(&prefix),
+                Some(Duration::ZERO),
+            )?;
+
+            std::fs::remove_dir_all(path)
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Isolated environments for building packages.
+//!
+//! A build should depend only on its package's dependencies, not on whatever
+//! happens to be set in the environment of the user running zpack. When
+//! isolation is enabled, a build's environment starts from nothing but the
+//! variables in [`IsolationConfig::keep`] and a `PATH` of
+//! [`IsolationConfig::system_path`]. In either case, the prefixes of the
+//! package's dependencies are then injected:
+//!
+//! - `PATH` starts with the `bin` directory of each dependency
+//! - `CMAKE_PREFIX_PATH` starts with each dependency's prefix
+//! - `PKG_CONFIG_PATH` starts with the `pkgconfig` directories of each
+//!   dependency (see [`PKG_CONFIG_DIRS`])
+//!
+//! The resulting environment is recorded by the [build
+//! log](crate::package::log), so a build can be reproduced exactly.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The directories of an install prefix holding `pkg-config` files
+pub const PKG_CONFIG_DIRS: [&str; 3] =
+    ["lib/pkgconfig", "lib64/pkgconfig", "share/pkgconfig"];
+
+/// Which parts of the user's environment builds may see
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IsolationConfig {
+    /// Start builds from a minimal environment rather than the user's
+    pub enabled: bool,
+
+    /// Variables passed through from the user's environment when isolated
+    pub keep: Vec<String>,
+
+    /// Directories searched for tools after those of the dependencies when
+    /// isolated
+    pub system_path: Vec<PathBuf>,
+}
+
+impl Default for IsolationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keep: vec!["HOME".to_string(), "TERM".to_string()],
+            system_path: ["/usr/local/bin", "/usr/bin", "/bin"]
+                .map(PathBuf::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// Join `dirs` into a search path, such as `PATH`.
+fn join_paths<'a>(dirs: impl IntoIterator<Item = &'a Path>) -> String {
+    dirs.into_iter()
+        .map(|dir| dir.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Prepend `dirs` to the search path `name` in `env`.
+fn prepend(env: &mut BTreeMap<String, String>, name: &str, dirs: &[PathBuf]) {
+    if dirs.is_empty() {
+        return;
+    }
+
+    let mut value = join_paths(dirs.iter().map(PathBuf::as_path));
+
+    if let Some(rest) = env.get(name).filter(|rest| !rest.is_empty()) {
+        value.push(':');
+        value.push_str(rest);
+    }
+
+    env.insert(name.to_string(), value);
+}
+
+impl IsolationConfig {
+    /// The environment to build a package whose dependencies are installed to
+    /// `prefixes` in.
+    ///
+    /// * `host`: The user's environment, such as [`std::env::vars`]
+    #[must_use]
+    pub fn environment(
+        &self,
+        host: impl IntoIterator<Item = (String, String)>,
+        prefixes: &[PathBuf],
+    ) -> BTreeMap<String, String> {
+        let mut env = if self.enabled {
+            let mut env = host
+                .into_iter()
+                .filter(|(name, _)| self.keep.contains(name))
+                .collect::<BTreeMap<_, _>>();
+
+            env.insert(
+                "PATH".to_string(),
+                join_paths(self.system_path.iter().map(PathBuf::as_path)),
+            );
+
+            env
+        } else {
+            host.into_iter().collect()
+        };
+
+        let existing = |dirs: &[&str]| {
+            prefixes
+                .iter()
+                .flat_map(|prefix| dirs.iter().map(|dir| prefix.join(dir)))
+                .filter(|dir| dir.is_dir())
+                .collect::<Vec<_>>()
+        };
+
+        prepend(&mut env, "PATH", &existing(&["bin"]));
+        prepend(&mut env, "CMAKE_PREFIX_PATH", prefixes);
+        prepend(&mut env, "PKG_CONFIG_PATH", &existing(&PKG_CONFIG_DIRS));
+
+        env
+    }
+}
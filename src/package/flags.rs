@@ -0,0 +1,308 @@
+//! Compiler and linker flags for packages.
+//!
+//! A [`FlagMapping`] is declared on a [`PackageOutline`] to translate solved
+//! options into flags, such as adding `-fopenmp` to `CFLAGS` when `+openmp` is
+//! enabled. As with [`Patch`](crate::package::patch::Patch)es, the condition
+//! is an ordinary [`Constraint`] evaluated by the solver. The flags of every
+//! mapping whose condition holds are recorded on the
+//! [`ConcretePackage`], and therefore contribute to its hash.
+//!
+//! [`build_env`] assembles the environment variables for building a concrete
+//! package from its flags and its microarchitecture target.
+//! [`build_env_with_rpaths`] also embeds the library directories of the
+//! package's installed dependencies in what it links, as an `RPATH` or a
+//! `RUNPATH` depending on the [`RpathMode`], so installed binaries find their
+//! dependencies without `LD_LIBRARY_PATH`.
+//!
+//! [`PackageOutline`]: crate::package::outline::PackageOutline
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraint::Constraint,
+    package::names,
+    spec::{
+        ConcretePackage, SpecOptionValue,
+        target::{TARGET_OPTION, Target},
+    },
+    util::suggest,
+};
+
+/// The kind of flag, corresponding to a conventional environment variable
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagKind {
+    CFlags,
+    CxxFlags,
+    FFlags,
+    LdFlags,
+    LdLibs,
+}
+
+impl FlagKind {
+    pub const ALL: [Self; 5] = [
+        Self::CFlags,
+        Self::CxxFlags,
+        Self::FFlags,
+        Self::LdFlags,
+        Self::LdLibs,
+    ];
+
+    /// Kinds which are passed to compilers, and therefore select a target
+    pub const COMPILE: [Self; 3] = [Self::CFlags, Self::CxxFlags, Self::FFlags];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::CFlags => "cflags",
+            Self::CxxFlags => "cxxflags",
+            Self::FFlags => "fflags",
+            Self::LdFlags => "ldflags",
+            Self::LdLibs => "ldlibs",
+        }
+    }
+
+    /// The environment variable holding flags of this kind
+    #[must_use]
+    pub const fn env_var(self) -> &'static str {
+        match self {
+            Self::CFlags => "CFLAGS",
+            Self::CxxFlags => "CXXFLAGS",
+            Self::FFlags => "FFLAGS",
+            Self::LdFlags => "LDFLAGS",
+            Self::LdLibs => "LDLIBS",
+        }
+    }
+}
+
+impl std::fmt::Display for FlagKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FlagKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == s).ok_or_else(|| {
+            let suggestion =
+                suggest::closest(s, Self::ALL.iter().map(|k| k.as_str()));
+
+            format!(
+                "unknown flag kind '{s}'{}",
+                suggest::did_you_mean(suggestion)
+            )
+        })
+    }
+}
+
+/// How installed binaries find the shared libraries of their dependencies
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RpathMode {
+    /// Embed each dependency's library directories as an `RPATH`, which
+    /// takes precedence over `LD_LIBRARY_PATH`
+    #[default]
+    Rpath,
+
+    /// Embed each dependency's library directories as a `RUNPATH`, which
+    /// `LD_LIBRARY_PATH` can override
+    Runpath,
+
+    /// Embed nothing, leaving libraries to be found through the environment
+    None,
+}
+
+impl RpathMode {
+    pub const ALL: [Self; 3] = [Self::Rpath, Self::Runpath, Self::None];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Rpath => "rpath",
+            Self::Runpath => "runpath",
+            Self::None => "none",
+        }
+    }
+
+    /// The linker flags embedding `dirs` as search paths. Empty if there are
+    /// no directories or nothing is embedded.
+    #[must_use]
+    pub fn flags(self, dirs: &[PathBuf]) -> Vec<String> {
+        let dtags = match self {
+            Self::Rpath => "-Wl,--disable-new-dtags",
+            Self::Runpath => "-Wl,--enable-new-dtags",
+            Self::None => return Vec::new(),
+        };
+
+        if dirs.is_empty() {
+            return Vec::new();
+        }
+
+        std::iter::once(dtags.to_string())
+            .chain(
+                dirs.iter().map(|dir| format!("-Wl,-rpath,{}", dir.display())),
+            )
+            .collect()
+    }
+}
+
+impl std::fmt::Display for RpathMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RpathMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|mode| mode.name() == s).ok_or_else(|| {
+            format!(
+                "unknown rpath mode '{s}'; expected one of: {}",
+                Self::ALL.map(Self::name).join(", ")
+            )
+        })
+    }
+}
+
+/// The directories of an install prefix holding shared libraries
+pub const LIBRARY_DIRS: [&str; 2] = ["lib", "lib64"];
+
+/// Flags added to a package when a condition holds.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FlagMapping {
+    pub kind: FlagKind,
+    pub flags: Vec<String>,
+
+    /// The flags are only added if this evaluates to true
+    pub when: Option<Constraint>,
+}
+
+/// The flags of a concretized package, by kind
+pub type ConcreteFlags = BTreeMap<FlagKind, Vec<String>>;
+
+impl FlagMapping {
+    /// Name of the solver variable which is true if this mapping applies.
+    ///
+    /// * `package`: The package declaring the mapping
+    /// * `index`: The index of the mapping within the package
+    #[must_use]
+    pub fn flag_name(package: &str, index: usize) -> String {
+        names::condition(package, "flags", index)
+    }
+}
+
+impl std::fmt::Display for FlagMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Flags({}: {}", self.kind, self.flags.join(" "))?;
+
+        if let Some(when) = &self.when {
+            write!(f, " when {when}")?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+/// Assemble the build environment of a concrete package.
+///
+/// Compiler flag variables start with the flag selecting the package's
+/// microarchitecture target, followed by the package's own flags in the
+/// order they were declared. Variables with no flags are omitted.
+#[must_use]
+pub fn build_env(package: &ConcretePackage) -> BTreeMap<String, String> {
+    build_env_with_rpaths(package, RpathMode::None, Vec::<PathBuf>::new())
+}
+
+/// Assemble the build environment of a concrete package whose dependencies
+/// are installed to `prefixes`.
+///
+/// As [`build_env`], with `LDFLAGS` ending with the flags embedding the
+/// library directories of each prefix which exist (see [`LIBRARY_DIRS`]),
+/// as selected by `mode`.
+#[must_use]
+pub fn build_env_with_rpaths(
+    package: &ConcretePackage,
+    mode: RpathMode,
+    prefixes: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> BTreeMap<String, String> {
+    let mut flags = ConcreteFlags::new();
+
+    if let Some(SpecOptionValue::Str(name)) = package.options.get(TARGET_OPTION)
+        && let Some(target) = Target::lookup(name)
+    {
+        for kind in FlagKind::COMPILE {
+            flags.entry(kind).or_default().push(target.flag.to_string());
+        }
+    }
+
+    for (kind, package_flags) in &package.flags {
+        flags.entry(*kind).or_default().extend(package_flags.iter().cloned());
+    }
+
+    let dirs = prefixes
+        .into_iter()
+        .flat_map(|prefix| LIBRARY_DIRS.map(|dir| prefix.as_ref().join(dir)))
+        .filter(|dir| dir.is_dir())
+        .collect::<Vec<_>>();
+
+    flags.entry(FlagKind::LdFlags).or_default().extend(mode.flags(&dirs));
+
+    flags
+        .into_iter()
+        .filter(|(_, flags)| !flags.is_empty())
+        .map(|(kind, flags)| (kind.env_var().to_string(), flags.join(" ")))
+        .collect()
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl FlagMapping {
+    /// * `kind`: One of `cflags`, `cxxflags`, `fflags`, `ldflags` or `ldlibs`
+    /// * `flags`: The flags to add
+    /// * `when`: Only add the flags if this holds
+    ///
+    /// # Errors
+    /// Errors if `kind` is not a valid flag kind.
+    #[new]
+    #[pyo3(signature = (kind, flags, when = None))]
+    fn py_new(
+        kind: &str,
+        flags: Vec<String>,
+        when: Option<Constraint>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            kind: kind.parse().map_err(PyValueError::new_err)?,
+            flags,
+            when,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
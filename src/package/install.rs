@@ -0,0 +1,913 @@
+//! Resumable installation of concretized packages.
+//!
+//! Every package in a [`ConcreteSpec`] is installed to its own prefix inside
+//! an install root, dependencies first. The state of each package, keyed by
+//! its DAG hash, is recorded in the [`InstallDb`] at [`INSTALL_DB_FILE`]
+//! inside the root as soon as it is known, so an interrupted or failed
+//! installation can be resumed: packages which are already installed are
+//! skipped and only those which failed or were never attempted are built.
+//!
+//! The install root is configured with [`InstallTreeConfig`]. It may be
+//! padded to a fixed length with placeholder directories, so binaries built
+//! into a short root, such as for a build cache, have room to be relocated by
+//! rewriting their embedded paths with those of a longer one.
+//!
+//! Each package is built into a staging directory, `<prefix>.staging`, which
+//! is renamed to its prefix only once it is complete, so a prefix never holds
+//! a partial installation. The database records an install as in progress
+//! while it is staged. If zpack is interrupted, the record is left behind,
+//! so the interrupted install can be found and cleaned up with
+//! [`InstallDb::interrupted`] and [`InstallDb::repair`] (see `zpack doctor`),
+//! and is otherwise cleaned up by the next attempt.
+//!
+//! A package which fails is quarantined. Whatever was written to its staging
+//! directory or prefix is moved aside to `<prefix>.failed`, keeping its build
+//! log (see [`log`](crate::package::log)) for inspection, so the next attempt
+//! starts from an empty prefix. By default installation stops at the first
+//! failure. With `keep_going`, every package which does not depend on a
+//! failed one is still installed.
+//!
+//! Several zpack processes may install to the same root at once. The
+//! database is only read and written while holding a lock on
+//! [`INSTALL_DB_LOCK_FILE`], and each package is built while holding a lock
+//! on `<prefix>.lock`, so a package being built by one process is waited for,
+//! then found to be installed, by another. How long to wait is set by
+//! [`InstallTreeConfig::lock_timeout`].
+//!
+//! How a package is built is up to the caller, which provides a step run
+//! once for each package to install. The `pre_install` and `post_install`
+//! [hooks](crate::hooks) are run around it. Once a package is built, its
+//! [spec file](crate::spec::spec_file) is written into its prefix, so the
+//! installation can be read back as a concrete spec.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hooks::{HookConfig, HookEvent, HookPayload},
+    package::version::Version,
+    spec::{
+        concrete::{ConcretePackage, ConcreteSpec},
+        parse::SpecRequest,
+        spec_file::{self, SpecFile, SpecFileError},
+    },
+    util::{lock::FileLock, paths},
+};
+
+/// File, relative to the install root, recording the state of every package
+/// installed to it
+pub const INSTALL_DB_FILE: &str = ".zpack/db.json";
+
+/// File, relative to the install root, locked while the database is read or
+/// written
+pub const INSTALL_DB_LOCK_FILE: &str = ".zpack/db.lock";
+
+/// Suffix added to the prefix of a package to name the file locked while it
+/// is built
+pub const PREFIX_LOCK_SUFFIX: &str = ".lock";
+
+/// Suffix added to the prefix of a package which failed to install
+pub const QUARANTINE_SUFFIX: &str = "failed";
+
+/// Suffix added to the prefix of a package to name the directory it is built
+/// into
+pub const STAGING_SUFFIX: &str = "staging";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstallState {
+    Installed,
+    Failed,
+
+    /// Being built into its staging directory, or interrupted while it was
+    /// if no process holds the lock on its prefix
+    InProgress,
+}
+
+/// The last attempt to install a package
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallRecord {
+    pub name: String,
+    pub version: Option<Version>,
+    pub prefix: PathBuf,
+    pub state: InstallState,
+
+    /// Why the last attempt failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Where the prefix of the last failed attempt was moved to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine: Option<PathBuf>,
+
+    /// The directory an install in progress is being built into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<PathBuf>,
+
+    /// Number of attempts, including the last
+    pub attempts: u32,
+
+    /// Seconds since the Unix epoch when the last attempt finished
+    pub timestamp: u64,
+}
+
+/// The packages installed to an install root, keyed by DAG hash
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallDb {
+    #[serde(skip)]
+    root: PathBuf,
+
+    /// How long to wait for another process to release a lock
+    #[serde(skip)]
+    lock_timeout: Option<Duration>,
+
+    /// Packages recorded since the database was last saved
+    #[serde(skip)]
+    changed: BTreeSet<String>,
+
+    pub packages: BTreeMap<String, InstallRecord>,
+}
+
+/// An installed package, as found by [`InstallDb::find`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Installed {
+    /// The DAG hash of the package
+    pub hash: String,
+    pub record: InstallRecord,
+
+    /// The package and everything it depends on, as recorded in its prefix
+    pub spec: SpecFile,
+}
+
+impl Installed {
+    /// The installed package itself
+    #[must_use]
+    pub fn package(&self) -> Option<&ConcretePackage> {
+        self.spec.spec.get(&self.spec.root)
+    }
+}
+
+/// What happened to a package during [`install`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Installed,
+
+    /// Installed by an earlier invocation
+    AlreadyInstalled,
+
+    /// The install step failed with the given error
+    Failed(String),
+
+    /// Not attempted, since the given dependency was not installed
+    Skipped(String),
+}
+
+/// The outcome of every package visited by [`install`], in the order they
+/// were visited
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstallReport {
+    pub outcomes: Vec<(String, Outcome)>,
+
+    /// Packages which were never visited because an earlier one failed
+    pub remaining: Vec<String>,
+}
+
+/// The name of the directories padding an install root, truncated as needed
+pub const PADDING: &str = "__zpack_path_placeholder__";
+
+/// Where packages are installed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstallTreeConfig {
+    /// The directory packages are installed to, each in its own prefix
+    pub root: PathBuf,
+
+    /// Pad the root with placeholder directories to this many bytes, so
+    /// installed binaries can be relocated to a root at most this long
+    pub padded_length: Option<usize>,
+
+    /// Seconds to wait for another zpack process to release its lock on the
+    /// install database or a prefix before giving up. `None` waits
+    /// indefinitely
+    pub lock_timeout: Option<u64>,
+}
+
+impl Default for InstallTreeConfig {
+    fn default() -> Self {
+        Self {
+            root: paths::install_root(),
+            padded_length: None,
+            lock_timeout: None,
+        }
+    }
+}
+
+impl InstallTreeConfig {
+    /// The install root, padded to [`Self::padded_length`] if set
+    #[must_use]
+    pub fn padded_root(&self) -> PathBuf {
+        self.padded_length
+            .map_or_else(|| self.root.clone(), |length| pad(&self.root, length))
+    }
+}
+
+/// Pad `root` with directories named [`PADDING`] until it is `length` bytes
+/// long. The result is one byte short if a directory of one byte does not
+/// fit, and `root` itself if it is already long enough.
+#[must_use]
+pub fn pad(root: &Path, length: usize) -> PathBuf {
+    let mut res = root.to_path_buf();
+
+    loop {
+        // Each directory also needs a separator
+        let remaining =
+            length.saturating_sub(res.as_os_str().len()).saturating_sub(1);
+
+        if remaining == 0 {
+            return res;
+        }
+
+        res.push(&PADDING[..remaining.min(PADDING.len())]);
+    }
+}
+
+/// The prefix `package` is installed to inside `root`.
+///
+/// * `hash`: The DAG hash of the package
+#[must_use]
+pub fn prefix(root: &Path, package: &ConcretePackage, hash: &str) -> PathBuf {
+    let name = package.base_name();
+
+    root.join(package.version.as_ref().map_or_else(
+        || format!("{name}-{hash}"),
+        |version| format!("{name}-{version}-{hash}"),
+    ))
+}
+
+/// The prefixes inside `root` which the transitive dependencies of the
+/// package `name` in `spec` are installed to.
+///
+/// These are the prefixes to embed as RPATHs when building the package (see
+/// [`crate::package::flags::build_env_with_rpaths`]). Empty if `spec` has no
+/// such package.
+#[must_use]
+pub fn dependency_prefixes(
+    root: &Path,
+    spec: &ConcreteSpec,
+    name: &str,
+) -> Vec<PathBuf> {
+    spec.sub_spec(name).map_or_else(Vec::new, |sub| {
+        sub.packages
+            .values()
+            .filter(|dep| dep.name != name)
+            .map(|dep| prefix(root, dep, &dep.dag_hash(spec)))
+            .collect()
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// The file locked while the package installed to `prefix` is built
+#[must_use]
+pub fn prefix_lock_path(prefix: &Path) -> PathBuf {
+    let mut path = prefix.as_os_str().to_owned();
+    path.push(PREFIX_LOCK_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// The packages recorded in the database file of `root`
+fn read_packages(
+    root: &Path,
+) -> std::io::Result<BTreeMap<String, InstallRecord>> {
+    let path = root.join(INSTALL_DB_FILE);
+
+    if !path.is_file() {
+        return Ok(BTreeMap::new());
+    }
+
+    let db: InstallDb = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    Ok(db.packages)
+}
+
+impl InstallDb {
+    /// Read the database of the install root `root`. A root without one has
+    /// nothing installed.
+    ///
+    /// # Errors
+    /// Errors if the database exists but cannot be read or parsed.
+    pub fn load(root: &Path) -> std::io::Result<Self> {
+        Self::load_with_timeout(root, None)
+    }
+
+    /// Read the database of the install root configured by `tree`, waiting
+    /// at most its [`InstallTreeConfig::lock_timeout`] for locks.
+    ///
+    /// # Errors
+    /// Errors if the database exists but cannot be read or parsed, or is
+    /// locked for longer than the timeout.
+    pub fn open(tree: &InstallTreeConfig) -> std::io::Result<Self> {
+        Self::load_with_timeout(
+            &tree.padded_root(),
+            tree.lock_timeout.map(Duration::from_secs),
+        )
+    }
+
+    fn load_with_timeout(
+        root: &Path,
+        lock_timeout: Option<Duration>,
+    ) -> std::io::Result<Self> {
+        let mut db =
+            Self { root: root.to_path_buf(), lock_timeout, ..Self::default() };
+
+        db.reload()?;
+
+        Ok(db)
+    }
+
+    /// How long to wait for another process to release a lock. `None` waits
+    /// indefinitely
+    #[must_use]
+    pub const fn lock_timeout(&self) -> Option<Duration> {
+        self.lock_timeout
+    }
+
+    /// Read the records written by other processes since the database was
+    /// loaded, keeping any which have not been saved yet.
+    ///
+    /// # Errors
+    /// Errors if the database cannot be locked, read or parsed.
+    pub fn reload(&mut self) -> std::io::Result<()> {
+        let _lock = FileLock::shared(
+            &self.root.join(INSTALL_DB_LOCK_FILE),
+            self.lock_timeout,
+        )?;
+
+        self.merge(read_packages(&self.root)?);
+
+        Ok(())
+    }
+
+    /// Replace every record with those in `packages`, except those which
+    /// have not been saved yet.
+    fn merge(&mut self, mut packages: BTreeMap<String, InstallRecord>) {
+        for hash in &self.changed {
+            if let Some(record) = self.packages.remove(hash) {
+                packages.insert(hash.clone(), record);
+            }
+        }
+
+        self.packages = packages;
+    }
+
+    /// Write the packages recorded since the database was last saved back to
+    /// its install root, keeping those written by other processes in the
+    /// meantime.
+    ///
+    /// # Errors
+    /// Errors if the database cannot be locked, read or written.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let _lock = FileLock::exclusive(
+            &self.root.join(INSTALL_DB_LOCK_FILE),
+            self.lock_timeout,
+        )?;
+
+        self.merge(read_packages(&self.root)?);
+
+        let path = self.root.join(INSTALL_DB_FILE);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp = path.with_extension("tmp");
+
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(tmp, path)?;
+
+        self.changed.clear();
+
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    #[must_use]
+    pub fn get(&self, hash: &str) -> Option<&InstallRecord> {
+        self.packages.get(hash)
+    }
+
+    /// Whether the package with `hash` is installed and its prefix still
+    /// exists
+    #[must_use]
+    pub fn is_installed(&self, hash: &str) -> bool {
+        self.get(hash).is_some_and(|record| {
+            record.state == InstallState::Installed && record.prefix.is_dir()
+        })
+    }
+
+    /// The spec file of the package with `hash`, or `None` if it is not
+    /// installed.
+    ///
+    /// # Errors
+    /// Errors if the spec file cannot be read or is invalid.
+    pub fn spec_file(
+        &self,
+        hash: &str,
+    ) -> Result<Option<SpecFile>, SpecFileError> {
+        if !self.is_installed(hash) {
+            return Ok(None);
+        }
+
+        let record = &self.packages[hash];
+        SpecFile::load(&spec_file::path(&record.prefix)).map(Some)
+    }
+
+    /// Every installed package which matches any of `queries` (see
+    /// [`crate::spec::query`]), or every installed package if there are
+    /// none, ordered by name and then version.
+    ///
+    /// A package whose spec file cannot be read is skipped with a warning
+    /// (see `zpack doctor`).
+    #[must_use]
+    pub fn find(&self, queries: &[SpecRequest]) -> Vec<Installed> {
+        let mut res = self
+            .packages
+            .iter()
+            .filter_map(|(hash, record)| {
+                let spec = match self.spec_file(hash) {
+                    Ok(spec) => spec?,
+                    Err(e) => {
+                        tracing::warn!("skipping '{}': {e}", record.name);
+                        return None;
+                    }
+                };
+
+                let installed = Installed {
+                    hash: hash.clone(),
+                    record: record.clone(),
+                    spec,
+                };
+
+                (queries.is_empty()
+                    || queries
+                        .iter()
+                        .any(|q| q.satisfied_by(&installed.spec.spec)))
+                .then_some(installed)
+            })
+            .collect::<Vec<_>>();
+
+        res.sort_by(|a, b| {
+            (&a.record.name, &a.record.version)
+                .cmp(&(&b.record.name, &b.record.version))
+        });
+
+        res
+    }
+
+    /// Packages whose last attempt failed, by DAG hash
+    pub fn failed(&self) -> impl Iterator<Item = (&String, &InstallRecord)> {
+        self.packages
+            .iter()
+            .filter(|(_, record)| record.state == InstallState::Failed)
+    }
+
+    /// Packages whose install was interrupted: recorded as in progress, but
+    /// not being built by any process. By DAG hash
+    pub fn interrupted(
+        &self,
+    ) -> impl Iterator<Item = (&String, &InstallRecord)> {
+        self.packages.iter().filter(|(_, record)| {
+            record.state == InstallState::InProgress
+                && FileLock::shared(
+                    &prefix_lock_path(&record.prefix),
+                    Some(Duration::ZERO),
+                )
+                .is_ok()
+        })
+    }
+
+    /// Packages recorded as installed whose prefix no longer exists. By DAG
+    /// hash
+    pub fn missing(&self) -> impl Iterator<Item = (&String, &InstallRecord)> {
+        self.packages.iter().filter(|(_, record)| {
+            record.state == InstallState::Installed && !record.prefix.is_dir()
+        })
+    }
+
+    /// Clean up after the interrupted install of the package with `hash`,
+    /// removing its staging directory and partial prefix and recording it as
+    /// failed, or forget it if it is installed but its prefix is missing.
+    /// Does nothing if neither is the case.
+    ///
+    /// # Errors
+    /// Errors if the prefix is locked by another process, or the directories
+    /// cannot be removed or the database written.
+    pub fn repair(&mut self, hash: &str) -> std::io::Result<()> {
+        let Some(record) = self.packages.get(hash) else { return Ok(()) };
+
+        let _lock = FileLock::exclusive(
+            &prefix_lock_path(&record.prefix),
+            Some(Duration::ZERO),
+        )?;
+
+        self.reload()?;
+
+        let Some(record) = self.packages.get_mut(hash) else { return Ok(()) };
+
+        match record.state {
+            InstallState::InProgress => {
+                for dir in record.stage.iter().chain([&record.prefix]) {
+                    if dir.exists() {
+                        std::fs::remove_dir_all(dir)?;
+                    }
+                }
+
+                tracing::info!(
+                    "cleaned up the interrupted install of {}",
+                    record.name
+                );
+
+                record.state = InstallState::Failed;
+                record.error = Some("the install was interrupted".to_string());
+                record.stage = None;
+                record.timestamp = now();
+            }
+            InstallState::Installed if !record.prefix.is_dir() => {
+                tracing::info!(
+                    "forgetting {}, whose prefix is missing",
+                    record.name
+                );
+                self.packages.remove(hash);
+            }
+            InstallState::Installed | InstallState::Failed => return Ok(()),
+        }
+
+        self.changed.insert(hash.to_string());
+        self.save()
+    }
+
+    /// Move the prefix of the installed package with `hash` aside and record
+    /// it as failed with `reason`, so it is installed again. Does nothing if
+    /// it is not installed.
+    ///
+    /// # Errors
+    /// Errors if the prefix is locked by another process, or cannot be moved
+    /// or the database written.
+    pub fn invalidate(
+        &mut self,
+        hash: &str,
+        reason: &str,
+    ) -> std::io::Result<()> {
+        let Some(record) = self.packages.get(hash) else { return Ok(()) };
+
+        let _lock = FileLock::exclusive(
+            &prefix_lock_path(&record.prefix),
+            Some(Duration::ZERO),
+        )?;
+
+        self.reload()?;
+
+        let Some(record) = self.packages.get_mut(hash) else { return Ok(()) };
+
+        if record.state != InstallState::Installed {
+            return Ok(());
+        }
+
+        record.quarantine = quarantine(&record.prefix, &record.prefix)?;
+        record.state = InstallState::Failed;
+        record.error = Some(reason.to_string());
+        record.timestamp = now();
+
+        self.changed.insert(hash.to_string());
+        self.save()
+    }
+
+    /// Record that `package` is being built into `stage`.
+    fn begin(
+        &mut self,
+        package: &ConcretePackage,
+        hash: &str,
+        prefix: PathBuf,
+        stage: PathBuf,
+    ) {
+        let attempts =
+            self.packages.get(hash).map_or(0, |record| record.attempts) + 1;
+
+        self.changed.insert(hash.to_string());
+        self.packages.insert(
+            hash.to_string(),
+            InstallRecord {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                prefix,
+                state: InstallState::InProgress,
+                error: None,
+                quarantine: None,
+                stage: Some(stage),
+                attempts,
+                timestamp: now(),
+            },
+        );
+    }
+
+    /// Record the outcome of the install begun with [`Self::begin`].
+    fn finish(
+        &mut self,
+        hash: &str,
+        error: Option<String>,
+        quarantine: Option<PathBuf>,
+    ) {
+        let Some(record) = self.packages.get_mut(hash) else { return };
+
+        record.state = if error.is_some() {
+            InstallState::Failed
+        } else {
+            InstallState::Installed
+        };
+        record.error = error;
+        record.quarantine = quarantine;
+        record.stage = None;
+        record.timestamp = now();
+
+        self.changed.insert(hash.to_string());
+    }
+}
+
+impl InstallReport {
+    /// Names of the packages which failed to install
+    pub fn failures(&self) -> impl Iterator<Item = &str> {
+        self.outcomes.iter().filter_map(|(name, outcome)| {
+            matches!(outcome, Outcome::Failed(_)).then_some(name.as_str())
+        })
+    }
+
+    /// Whether every package is installed
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.remaining.is_empty()
+            && self.outcomes.iter().all(|(_, outcome)| {
+                matches!(
+                    outcome,
+                    Outcome::Installed | Outcome::AlreadyInstalled
+                )
+            })
+    }
+}
+
+/// Where the prefix of a package which failed to install is moved to
+#[must_use]
+pub fn quarantine_path(prefix: &Path) -> PathBuf {
+    let mut res = prefix.as_os_str().to_owned();
+    res.push(".");
+    res.push(QUARANTINE_SUFFIX);
+    PathBuf::from(res)
+}
+
+/// The directory the package installed to `prefix` is built into
+#[must_use]
+pub fn staging_path(prefix: &Path) -> PathBuf {
+    let mut res = prefix.as_os_str().to_owned();
+    res.push(".");
+    res.push(STAGING_SUFFIX);
+    PathBuf::from(res)
+}
+
+/// Move the staging directory or prefix `dir` of a failed installation to
+/// `prefix` aside, returning where it was moved to, if it existed.
+fn quarantine(dir: &Path, prefix: &Path) -> std::io::Result<Option<PathBuf>> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+
+    let dest = quarantine_path(prefix);
+
+    if dest.exists() {
+        std::fs::remove_dir_all(&dest)?;
+    }
+
+    std::fs::rename(dir, &dest)?;
+
+    tracing::warn!("quarantined '{}' to '{}'", dir.display(), dest.display());
+
+    Ok(Some(dest))
+}
+
+/// Say why a package which was attempted before is being installed again.
+fn log_retry(previous: &InstallRecord) {
+    match previous.state {
+        InstallState::Failed => tracing::info!(
+            "retrying {}, which failed after {} attempt(s)",
+            previous.name,
+            previous.attempts
+        ),
+        InstallState::InProgress => tracing::info!(
+            "retrying {}, whose last install was interrupted",
+            previous.name
+        ),
+        InstallState::Installed => {}
+    }
+}
+
+/// Packages in `spec` ordered so every package comes after its dependencies.
+fn dependencies_first(spec: &ConcreteSpec) -> Vec<&ConcretePackage> {
+    fn visit<'a>(
+        spec: &'a ConcreteSpec,
+        name: &str,
+        visited: &mut HashSet<String>,
+        res: &mut Vec<&'a ConcretePackage>,
+    ) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+
+        let Some(package) = spec.get(name) else { return };
+
+        for dep in &package.dependencies {
+            visit(spec, dep, visited, res);
+        }
+
+        res.push(package);
+    }
+
+    let mut visited = HashSet::new();
+    let mut res = Vec::with_capacity(spec.packages.len());
+
+    for name in spec.roots.iter().chain(spec.packages.keys()) {
+        visit(spec, name, &mut visited, &mut res);
+    }
+
+    res
+}
+
+/// Install every package in `spec` to the install root of `db`, dependencies
+/// first, recording the state of each in `db` as it is known.
+///
+/// Packages which are already installed are skipped. The rest are installed
+/// by calling `step` with the package, the prefix it is installed to and the
+/// staging directory to build it into, which is created beforehand. If `step`
+/// fails, the staging directory is quarantined and, unless `keep_going` is
+/// set, nothing more is installed. Otherwise, packages which depend on a
+/// failed package are skipped and every other package is installed.
+///
+/// Once `step` succeeds, the package's spec file (see
+/// [`crate::spec::spec_file`]) is written into the staging directory, which
+/// is then renamed to the prefix. The `pre_install` and `post_install` hooks
+/// (see [`crate::hooks`]) are run before `step` and once the prefix is in
+/// place, and fail the package if they fail.
+///
+/// # Errors
+/// Errors if a staging directory cannot be created or quarantined, a lock
+/// cannot be acquired or the database cannot be written. A failing `step` is
+/// not an error; see [`InstallReport::failures`].
+pub fn install(
+    spec: &ConcreteSpec,
+    db: &mut InstallDb,
+    hooks: &HookConfig,
+    keep_going: bool,
+    mut step: impl FnMut(&ConcretePackage, &Path, &Path) -> Result<(), String>,
+) -> std::io::Result<InstallReport> {
+    let mut report = InstallReport::default();
+
+    // The package which caused each package to not be installed
+    let mut unavailable = HashMap::<&str, String>::new();
+
+    let order = dependencies_first(spec);
+
+    for (idx, package) in order.iter().enumerate() {
+        let hash = package.dag_hash(spec);
+
+        let blocker = package
+            .dependencies
+            .iter()
+            .find_map(|dep| unavailable.get(dep.as_str()).cloned());
+
+        if let Some(blocker) = blocker {
+            tracing::info!(
+                "skipping {}: {blocker} was not installed",
+                package.name
+            );
+
+            unavailable.insert(&package.name, blocker.clone());
+            report
+                .outcomes
+                .push((package.name.clone(), Outcome::Skipped(blocker)));
+            continue;
+        }
+
+        let prefix = prefix(db.root(), package, &hash);
+
+        // Held until the package is recorded, so another process building it
+        // is waited for and then found to have installed it
+        let _lock =
+            FileLock::exclusive(&prefix_lock_path(&prefix), db.lock_timeout())?;
+
+        db.reload()?;
+
+        if db.is_installed(&hash) {
+            tracing::info!("{} is already installed", package.name);
+
+            report
+                .outcomes
+                .push((package.name.clone(), Outcome::AlreadyInstalled));
+            continue;
+        }
+
+        if let Some(previous) = db.get(&hash) {
+            log_retry(previous);
+        }
+
+        let stage = staging_path(&prefix);
+
+        // Anything left behind by an interrupted attempt is stale
+        for dir in [&prefix, &stage] {
+            if dir.exists() {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+
+        // Recorded first, so the staging directory is never mistaken for an
+        // orphan
+        db.begin(package, &hash, prefix.clone(), stage.clone());
+        db.save()?;
+
+        std::fs::create_dir_all(&stage)?;
+
+        let run_hooks = |event| {
+            hooks
+                .run(&HookPayload::install(event, spec, &package.name, &prefix))
+                .map_err(|e| e.to_string())
+        };
+
+        let write_spec_file = || {
+            SpecFile::new(spec, &package.name)
+                .ok_or_else(|| format!("{} is not in the spec", package.name))?
+                .save(&spec_file::path(&stage))
+                .map_err(|e| e.to_string())
+        };
+
+        let commit = || {
+            std::fs::rename(&stage, &prefix).map_err(|e| {
+                format!("failed to move '{}' into place: {e}", stage.display())
+            })
+        };
+
+        let res = run_hooks(HookEvent::PreInstall)
+            .and_then(|()| step(package, &prefix, &stage))
+            .and_then(|()| write_spec_file())
+            .and_then(|()| commit())
+            .and_then(|()| run_hooks(HookEvent::PostInstall));
+
+        match res {
+            Ok(()) => {
+                // The failed attempt is no longer of interest
+                let failed = quarantine_path(&prefix);
+                if failed.exists() {
+                    std::fs::remove_dir_all(failed)?;
+                }
+
+                db.finish(&hash, None, None);
+                db.save()?;
+
+                report
+                    .outcomes
+                    .push((package.name.clone(), Outcome::Installed));
+            }
+            Err(error) => {
+                tracing::error!("failed to install {}: {error}", package.name);
+
+                // The prefix is only in place if a post-install hook failed
+                let dir = if stage.exists() { &stage } else { &prefix };
+                let moved = quarantine(dir, &prefix)?;
+
+                db.finish(&hash, Some(error.clone()), moved);
+                db.save()?;
+
+                unavailable.insert(&package.name, package.name.clone());
+                report
+                    .outcomes
+                    .push((package.name.clone(), Outcome::Failed(error)));
+
+                if !keep_going {
+                    report.remaining = order[idx + 1..]
+                        .iter()
+                        .map(|package| package.name.clone())
+                        .collect();
+
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
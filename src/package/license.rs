@@ -0,0 +1,259 @@
+//! Site-wide policy on the licenses of packages.
+//!
+//! Packages declare their license as an SPDX license expression (see
+//! [`PackageOutline::license`]). The licenses a site does not allow are
+//! configured under `licenses` in the configuration file:
+//!
+//! ```yaml
+//! licenses:
+//!   forbidden: [GPL-3.0-only, GPL-3.0-or-later, AGPL-*]
+//!   action: exclude
+//! ```
+//!
+//! A license ending in `*` forbids every license starting with the rest of
+//! it. Expressions are evaluated as SPDX defines them: a package licensed
+//! under `MIT OR GPL-3.0-only` may be used under the MIT license, so is
+//! allowed, but one licensed under `MIT AND GPL-3.0-only` is not. Packages
+//! without a license are always allowed.
+//!
+//! With the `exclude` action, every package with a forbidden license is
+//! excluded from the solve by a [policy](crate::package::policy), so the
+//! solver picks an alternative where there is one and otherwise reports the
+//! license in the unsatisfiable core. With `warn`, the solve is unaffected
+//! and a warning is logged for every forbidden package in the result.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraint::{Cmp, CmpType, Depends, Value},
+    package::{outline::PackageOutline, policy::PolicyConstraint},
+    spec::concrete::ConcreteSpec,
+};
+
+/// What to do with packages whose license is forbidden
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseAction {
+    /// Exclude the package from every solve
+    #[default]
+    Exclude,
+
+    /// Allow the package but warn when it is concretized
+    Warn,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LicensePolicy {
+    /// SPDX license identifiers which may not be used
+    pub forbidden: Vec<String>,
+    pub action: LicenseAction,
+}
+
+impl LicenseAction {
+    pub const ALL: [Self; 2] = [Self::Exclude, Self::Warn];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Exclude => "exclude",
+            Self::Warn => "warn",
+        }
+    }
+}
+
+impl std::fmt::Display for LicenseAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Split an SPDX license expression into identifiers, operators and
+/// parentheses.
+fn tokenize(expression: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut start = None;
+
+    for (idx, c) in expression.char_indices() {
+        if c.is_whitespace() || c == '(' || c == ')' {
+            if let Some(begin) = start.take() {
+                res.push(&expression[begin..idx]);
+            }
+
+            if !c.is_whitespace() {
+                res.push(&expression[idx..=idx]);
+            }
+        } else if start.is_none() {
+            start = Some(idx);
+        }
+    }
+
+    if let Some(begin) = start {
+        res.push(&expression[begin..]);
+    }
+
+    res
+}
+
+/// Evaluates whether an SPDX license expression may be used, given whether
+/// each license identifier in it may be.
+struct Evaluator<'a, F> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+    allowed: F,
+}
+
+impl<'a, F: Fn(&str) -> bool> Evaluator<'a, F> {
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_is(&self, keyword: &str) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|token| token.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Option<bool> {
+        let mut res = self.and()?;
+
+        while self.peek_is("OR") {
+            self.pos += 1;
+            res |= self.and()?;
+        }
+
+        Some(res)
+    }
+
+    fn and(&mut self) -> Option<bool> {
+        let mut res = self.license()?;
+
+        while self.peek_is("AND") {
+            self.pos += 1;
+            res &= self.license()?;
+        }
+
+        Some(res)
+    }
+
+    fn license(&mut self) -> Option<bool> {
+        let token = self.next()?;
+
+        if token == "(" {
+            let res = self.or()?;
+            return (self.next()? == ")").then_some(res);
+        }
+
+        let res = (self.allowed)(token.trim_end_matches('+'));
+
+        // An exception grants additional permissions, so does not change
+        // whether the license is allowed
+        if self.peek_is("WITH") {
+            self.pos += 2;
+        }
+
+        Some(res)
+    }
+}
+
+impl LicensePolicy {
+    /// Whether the license identifier `license` is forbidden
+    #[must_use]
+    pub fn forbids(&self, license: &str) -> bool {
+        self.forbidden.iter().any(|forbidden| {
+            forbidden.strip_suffix('*').map_or_else(
+                || forbidden.eq_ignore_ascii_case(license),
+                |prefix| {
+                    license
+                        .get(..prefix.len())
+                        .is_some_and(|s| s.eq_ignore_ascii_case(prefix))
+                },
+            )
+        })
+    }
+
+    /// Whether a package licensed under the SPDX license expression
+    /// `expression` may be used.
+    ///
+    /// An expression which cannot be parsed is allowed only if none of the
+    /// identifiers in it are forbidden.
+    #[must_use]
+    pub fn allows(&self, expression: &str) -> bool {
+        let tokens = tokenize(expression);
+
+        let mut evaluator = Evaluator {
+            tokens: tokens.clone(),
+            pos: 0,
+            allowed: |license: &str| !self.forbids(license),
+        };
+
+        match evaluator.or() {
+            Some(res) if evaluator.pos == tokens.len() => res,
+            _ => {
+                tracing::warn!("invalid license expression '{expression}'");
+                !tokens.iter().any(|token| self.forbids(token))
+            }
+        }
+    }
+
+    /// Packages in `outlines` whose license is forbidden, with their license
+    pub fn violations<'a>(
+        &'a self,
+        outlines: &'a [PackageOutline],
+    ) -> impl Iterator<Item = (&'a str, &'a str)> {
+        outlines.iter().filter_map(|outline| {
+            let license = outline.license.as_deref()?;
+            (!self.allows(license)).then_some((outline.name.as_str(), license))
+        })
+    }
+
+    /// Policies excluding every package in `outlines` whose license is
+    /// forbidden. Empty unless the action is [`LicenseAction::Exclude`].
+    #[must_use]
+    pub fn constraints(
+        &self,
+        outlines: &[PackageOutline],
+    ) -> Vec<PolicyConstraint> {
+        if self.action != LicenseAction::Exclude {
+            return Vec::new();
+        }
+
+        self.violations(outlines)
+            .map(|(name, license)| PolicyConstraint {
+                description: format!(
+                    "license policy: '{name}' is licensed under '{license}', \
+                     which is forbidden"
+                ),
+                constraint: Cmp::new(
+                    Depends::new(name.to_string()),
+                    CmpType::Equal,
+                    Value::new(false),
+                )
+                .into(),
+            })
+            .collect()
+    }
+
+    /// Log a warning for every package in `spec` whose license is forbidden.
+    /// Does nothing unless the action is [`LicenseAction::Warn`].
+    pub fn check(&self, spec: &ConcreteSpec) {
+        if self.action != LicenseAction::Warn {
+            return;
+        }
+
+        for package in spec.packages.values() {
+            if let Some(license) = &package.license
+                && !self.allows(license)
+            {
+                tracing::warn!(
+                    "{} is licensed under '{license}', which is forbidden",
+                    package.name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! Static validation of package outlines.
+//!
+//! The solver only reports problems once a spec is concretized, and even then
+//! many mistakes in a package definition (a typo in a dependency name, an
+//! option that can never be typed, a comparison between two constants) either
+//! panic deep inside the solver or silently produce a useless constraint. The
+//! linter walks the outlines without building a solver and reports these
+//! problems up front, which makes it suitable for running in a repository's CI.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{
+    constraint::{CmpType, Constraint, ConstraintUtils, Matches},
+    package::outline::{self, PackageOutline},
+    spec::{SpecOptionType, SpecOptionValue, target::TARGET_OPTION},
+};
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A single problem found by the linter
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// Stable, machine-readable identifier for this kind of problem
+    pub code: &'static str,
+
+    /// The package whose definition contains the problem
+    pub package: String,
+
+    pub message: String,
+}
+
+/// Per-package state while walking constraints
+struct Walker<'a> {
+    package: &'a str,
+    known_packages: &'a HashSet<&'a str>,
+    inferred: &'a mut HashMap<(String, String), SpecOptionType>,
+    referenced: &'a mut Vec<(String, String, String)>,
+    objectives: &'a mut Vec<(String, String, String, String)>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Diagnostic {
+    fn new(
+        severity: Severity,
+        code: &'static str,
+        package: &str,
+        message: String,
+    ) -> Self {
+        Self { severity, code, package: package.to_string(), message }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            self.severity, self.code, self.package, self.message
+        )
+    }
+}
+
+/// Evaluate a comparison between two constant values, if possible.
+///
+/// Returns `None` if the values cannot be compared with `op`.
+fn eval_constant_cmp(
+    lhs: &SpecOptionValue,
+    rhs: &SpecOptionValue,
+    op: CmpType,
+) -> Option<bool> {
+    use std::cmp::Ordering;
+
+    let ordering = match (lhs, rhs) {
+        (SpecOptionValue::Bool(l), SpecOptionValue::Bool(r)) => l.cmp(r),
+        (SpecOptionValue::Int(l), SpecOptionValue::Int(r)) => l.cmp(r),
+        (SpecOptionValue::Float(l), SpecOptionValue::Float(r)) => {
+            l.partial_cmp(r)?
+        }
+        (SpecOptionValue::Str(l), SpecOptionValue::Str(r)) => l.cmp(r),
+        (SpecOptionValue::Version(l), SpecOptionValue::Version(r)) => {
+            return match op {
+                CmpType::Equal => Some(l == r),
+                CmpType::NotEqual => Some(l != r),
+                _ => None,
+            };
+        }
+        _ => return None,
+    };
+
+    Some(match op {
+        CmpType::Less => ordering == Ordering::Less,
+        CmpType::LessOrEqual => ordering != Ordering::Greater,
+        CmpType::NotEqual => ordering != Ordering::Equal,
+        CmpType::Equal => ordering == Ordering::Equal,
+        CmpType::GreaterOrEqual => ordering != Ordering::Less,
+        CmpType::Greater => ordering == Ordering::Greater,
+    })
+}
+
+const fn is_valid_objective(dtype: Option<SpecOptionType>) -> bool {
+    matches!(
+        dtype,
+        Some(
+            SpecOptionType::Int
+                | SpecOptionType::Float
+                | SpecOptionType::Version
+        )
+    )
+}
+
+impl Walker<'_> {
+    fn push(&mut self, severity: Severity, code: &'static str, msg: String) {
+        self.diagnostics.push(Diagnostic::new(
+            severity,
+            code,
+            self.package,
+            msg,
+        ));
+    }
+
+    fn infer(&mut self, constraint: &Constraint, dtype: SpecOptionType) {
+        if let Constraint::SpecOption(opt) = constraint
+            && dtype != SpecOptionType::Unknown
+        {
+            // Duplicate nodes share the options of their package
+            let (package, _) = outline::split_node(&opt.package_name);
+
+            self.inferred
+                .entry((package.to_string(), opt.option_name.clone()))
+                .or_insert(dtype);
+        }
+    }
+
+    /// Walk a constraint.
+    ///
+    /// * `boolean`: Whether the constraint appears in a position which must
+    ///   produce a Bool
+    fn visit(&mut self, constraint: &Constraint, boolean: bool) {
+        match constraint {
+            Constraint::SpecOption(opt) => {
+                let (package, _) = outline::split_node(&opt.package_name);
+
+                if !self.known_packages.contains(package) {
+                    self.push(
+                        Severity::Error,
+                        "unknown-package",
+                        format!(
+                            "option '{}:{}' refers to an unknown package",
+                            opt.package_name, opt.option_name
+                        ),
+                    );
+                }
+
+                self.referenced.push((
+                    self.package.to_string(),
+                    package.to_string(),
+                    opt.option_name.clone(),
+                ));
+
+                if boolean {
+                    self.infer(constraint, SpecOptionType::Bool);
+                }
+            }
+
+            Constraint::Cmp(cmp) => {
+                if let (Constraint::Value(lhs), Constraint::Value(rhs)) =
+                    (&cmp.lhs, &cmp.rhs)
+                {
+                    match eval_constant_cmp(&lhs.value, &rhs.value, cmp.op) {
+                        Some(true) => self.push(
+                            Severity::Info,
+                            "constant-true",
+                            format!("'{cmp}' is always true"),
+                        ),
+                        Some(false) => self.push(
+                            Severity::Error,
+                            "constant-false",
+                            format!("'{cmp}' is always false"),
+                        ),
+                        None => self.push(
+                            Severity::Error,
+                            "type-mismatch",
+                            format!("'{cmp}' compares incompatible values"),
+                        ),
+                    }
+                }
+
+                let lhs_type = cmp.lhs.get_value_type_default();
+                let rhs_type = cmp.rhs.get_value_type_default();
+
+                if let Some(t) = rhs_type {
+                    self.infer(&cmp.lhs, t);
+                }
+
+                if let Some(t) = lhs_type {
+                    self.infer(&cmp.rhs, t);
+                }
+
+                self.visit(&cmp.lhs, false);
+                self.visit(&cmp.rhs, false);
+            }
+
+            Constraint::Matches(matches) => self.visit_matches(matches),
+
+            Constraint::Arith(arith) => {
+                if let Some(t) = arith.rhs.get_value_type_default() {
+                    self.infer(&arith.lhs, t);
+                }
+
+                if let Some(t) = arith.lhs.get_value_type_default() {
+                    self.infer(&arith.rhs, t);
+                }
+
+                self.visit(&arith.lhs, false);
+                self.visit(&arith.rhs, false);
+            }
+
+            Constraint::IfThen(if_then) => {
+                self.visit(&if_then.cond, true);
+                self.visit(&if_then.then, true);
+            }
+
+            Constraint::NumOf(num_of) => {
+                if num_of.of.is_empty() {
+                    self.push(
+                        Severity::Warning,
+                        "empty-num-of",
+                        "NumOf over an empty list is always 0".to_string(),
+                    );
+                }
+
+                for c in &num_of.of {
+                    self.visit(c, true);
+                }
+            }
+
+            Constraint::Maximize(m) => {
+                self.visit_objective("Maximize", &m.item);
+            }
+            Constraint::Minimize(m) => {
+                self.visit_objective("Minimize", &m.item);
+            }
+            Constraint::Prefer(p) => self.visit(&p.item, true),
+
+            Constraint::Depends(_)
+            | Constraint::Value(_)
+            | Constraint::WhenPlatform(_)
+            | Constraint::Custom(_) => (),
+        }
+    }
+
+    fn visit_matches(&mut self, matches: &Matches) {
+        if let Err(e) = Matches::parse_pattern(&matches.pattern) {
+            self.push(
+                Severity::Error,
+                "invalid-pattern",
+                format!("'{matches}' has an invalid pattern: {e}"),
+            );
+        }
+
+        let option = matches.option.clone().into();
+        self.infer(&option, SpecOptionType::Str);
+        self.visit(&option, false);
+    }
+
+    fn visit_objective(&mut self, name: &str, item: &Constraint) {
+        if let Constraint::SpecOption(opt) = item {
+            // The option's type may only be inferred by a later constraint
+            self.objectives.push((
+                self.package.to_string(),
+                name.to_string(),
+                outline::split_node(&opt.package_name).0.to_string(),
+                opt.option_name.clone(),
+            ));
+        } else if !is_valid_objective(item.get_value_type_default()) {
+            self.push(
+                Severity::Error,
+                "invalid-objective",
+                format!("{name} of '{item}' must be an Int, Float or Version"),
+            );
+        }
+
+        self.visit(item, false);
+    }
+}
+
+/// Lint a set of package outlines.
+///
+/// Diagnostics are returned sorted by package name and then severity, most
+/// severe first.
+#[must_use]
+pub fn lint(outlines: &[PackageOutline]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut inferred = HashMap::new();
+    let mut referenced = Vec::new();
+    let mut objectives = Vec::new();
+
+    let mut known_packages = HashSet::new();
+
+    for outline in outlines {
+        if !known_packages.insert(outline.name.as_str()) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "duplicate-package",
+                &outline.name,
+                format!("package '{}' is defined more than once", outline.name),
+            ));
+        }
+    }
+
+    for outline in outlines {
+        for dep in outline.dependencies() {
+            if dep == outline.name {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "self-dependency",
+                    &outline.name,
+                    "package depends on itself".to_string(),
+                ));
+            } else if !known_packages.contains(outline::split_node(&dep).0) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    "unknown-dependency",
+                    &outline.name,
+                    format!("dependency '{dep}' is not defined"),
+                ));
+            }
+        }
+
+        inferred.insert(
+            (outline.name.clone(), TARGET_OPTION.to_string()),
+            SpecOptionType::Str,
+        );
+
+        for (name, value) in &outline.set_options {
+            inferred
+                .entry((outline.name.clone(), name.clone()))
+                .or_insert_with(|| value.to_type());
+        }
+
+        let mut walker = Walker {
+            package: &outline.name,
+            known_packages: &known_packages,
+            inferred: &mut inferred,
+            referenced: &mut referenced,
+            objectives: &mut objectives,
+            diagnostics: &mut diagnostics,
+        };
+
+        for constraint in &outline.constraints {
+            walker.visit(constraint, true);
+        }
+    }
+
+    let mut reported = HashSet::new();
+
+    for (owner, package, option) in referenced {
+        let key = (package, option);
+
+        if !inferred.contains_key(&key) && reported.insert(key.clone()) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "untyped-option",
+                &owner,
+                format!(
+                    "the type of option '{}:{}' cannot be inferred; compare it \
+                     with a value or use it as a condition",
+                    key.0, key.1
+                ),
+            ));
+        }
+    }
+
+    for (owner, name, package, option) in objectives {
+        if let Some(&dtype) = inferred.get(&(package.clone(), option.clone()))
+            && !is_valid_objective(Some(dtype))
+        {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                "invalid-objective",
+                &owner,
+                format!(
+                    "{name} of '{package}:{option}' ({dtype:?}) must be an \
+                     Int, Float or Version"
+                ),
+            ));
+        }
+    }
+
+    diagnostics.sort_by(|a, b| {
+        a.package.cmp(&b.package).then(b.severity.cmp(&a.severity))
+    });
+
+    diagnostics
+}
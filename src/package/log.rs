@@ -0,0 +1,307 @@
+//! Logs of the commands run to build a package.
+//!
+//! Every command run by the `configure`, `build` and `install` phases is run
+//! through a [`BuildLog`], which records it inside the install prefix:
+//!
+//! - [`BUILD_OUTPUT_FILE`] holds the output of every command, with standard
+//!   output and standard error interleaved in the order they were written. Each
+//!   command is preceded by a header giving its phase, working directory and
+//!   exact command line, and followed by its exit status.
+//! - [`BUILD_RECORD_FILE`] holds the same commands as JSON, together with the
+//!   complete environment each one ran in, so a failed build can be reproduced
+//!   by hand.
+//!
+//! A build run in an [isolated environment](crate::package::environment)
+//! records that environment, and every command starts from it rather than the
+//! user's environment.
+//!
+//! The record is rewritten after every command, so it is complete even if the
+//! build is interrupted. Starting a build replaces the logs of the previous
+//! one.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the build phase which configures the source tree
+pub const CONFIGURE_PHASE: &str = "configure";
+
+/// Name of the build phase which compiles the package
+pub const BUILD_PHASE: &str = "build";
+
+/// Name of the build phase which installs the package into its prefix
+pub const INSTALL_PHASE: &str = "install";
+
+/// File, relative to the install prefix, holding the output of the last build
+pub const BUILD_OUTPUT_FILE: &str = ".zpack/build-out.txt";
+
+/// File, relative to the install prefix, recording the commands run by the
+/// last build
+pub const BUILD_RECORD_FILE: &str = ".zpack/build.json";
+
+/// A command run during a build
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoggedCommand {
+    pub phase: String,
+
+    /// The program followed by its arguments
+    pub command: Vec<String>,
+    pub cwd: PathBuf,
+
+    /// Every environment variable the command ran with, not only those set
+    /// by the build
+    pub env: BTreeMap<String, String>,
+
+    /// Exit code of the command. `None` if it could not be run or was
+    /// terminated by a signal
+    pub status: Option<i32>,
+
+    /// Wall time taken, in milliseconds
+    pub duration_ms: u64,
+}
+
+/// The commands run by the last build of a package, as recorded in
+/// [`BUILD_RECORD_FILE`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildRecord {
+    pub package: String,
+
+    /// DAG hash of the built package
+    pub hash: String,
+
+    /// Seconds since the Unix epoch when the build started
+    pub timestamp: u64,
+
+    /// The environment every command started from, if it was not the user's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<BTreeMap<String, String>>,
+
+    pub commands: Vec<LoggedCommand>,
+}
+
+/// Records the commands run to build a package and their output.
+pub struct BuildLog {
+    prefix: PathBuf,
+    output: File,
+    record: BuildRecord,
+}
+
+/// Quote `arg` for a POSIX shell, if it needs to be.
+#[must_use]
+pub fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+impl LoggedCommand {
+    /// The command line, quoted so it can be pasted into a shell
+    #[must_use]
+    pub fn command_line(&self) -> String {
+        self.command
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.status == Some(0)
+    }
+}
+
+impl BuildRecord {
+    /// Read the record of the last build inside `prefix`, if there is one.
+    ///
+    /// # Errors
+    /// Errors if the file exists but cannot be read or parsed.
+    pub fn load(prefix: &Path) -> std::io::Result<Option<Self>> {
+        let path = prefix.join(BUILD_RECORD_FILE);
+
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let txt = std::fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&txt)?))
+    }
+
+    /// The first command which did not succeed, if any
+    #[must_use]
+    pub fn failure(&self) -> Option<&LoggedCommand> {
+        self.commands.iter().find(|c| !c.succeeded())
+    }
+}
+
+impl BuildLog {
+    /// Start logging a build of `package` installed to `prefix`, replacing the
+    /// logs of any previous build.
+    ///
+    /// * `hash`: The DAG hash of the package, recorded in the log
+    ///
+    /// # Errors
+    /// Errors if the log files cannot be created.
+    pub fn create(
+        prefix: &Path,
+        package: &str,
+        hash: String,
+    ) -> std::io::Result<Self> {
+        let output_path = prefix.join(BUILD_OUTPUT_FILE);
+
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let log = Self {
+            prefix: prefix.to_path_buf(),
+            output: File::create(output_path)?,
+            record: BuildRecord {
+                package: package.to_string(),
+                hash,
+                timestamp,
+                environment: None,
+                commands: Vec::new(),
+            },
+        };
+
+        log.save()?;
+
+        Ok(log)
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let path = self.prefix.join(BUILD_RECORD_FILE);
+        let tmp = path.with_extension("tmp");
+
+        std::fs::write(&tmp, serde_json::to_string_pretty(&self.record)?)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Start every command from `env` instead of the user's environment, such
+    /// as one from [`IsolationConfig::environment`], and record it.
+    ///
+    /// [`IsolationConfig::environment`]:
+    ///     crate::package::environment::IsolationConfig::environment
+    ///
+    /// # Errors
+    /// Errors if the record cannot be written.
+    pub fn with_environment(
+        mut self,
+        env: BTreeMap<String, String>,
+    ) -> std::io::Result<Self> {
+        self.record.environment = Some(env);
+        self.save()?;
+        Ok(self)
+    }
+
+    #[must_use]
+    pub const fn record(&self) -> &BuildRecord {
+        &self.record
+    }
+
+    /// Run `command` from `cwd` as part of `phase`, with `env` added to the
+    /// recorded environment, or the current one if there is none, and log
+    /// it.
+    ///
+    /// A command which cannot be started is logged with no status, and the
+    /// reason is written to the output log.
+    ///
+    /// # Errors
+    /// Errors if the command is empty or the logs cannot be written. A command
+    /// which fails is not an error; check the returned [`LoggedCommand`].
+    pub fn run(
+        &mut self,
+        phase: &str,
+        command: &[String],
+        cwd: &Path,
+        env: &BTreeMap<String, String>,
+    ) -> std::io::Result<&LoggedCommand> {
+        let Some((program, args)) = command.split_first() else {
+            return Err(std::io::Error::other("the command is empty"));
+        };
+
+        let mut full_env = self
+            .record
+            .environment
+            .clone()
+            .unwrap_or_else(|| std::env::vars().collect());
+        full_env.extend(env.clone());
+
+        let mut logged = LoggedCommand {
+            phase: phase.to_string(),
+            command: command.to_vec(),
+            cwd: cwd.to_path_buf(),
+            env: full_env,
+            status: None,
+            duration_ms: 0,
+        };
+
+        writeln!(self.output, "==> [{phase}] {}", cwd.display())?;
+        writeln!(self.output, "==> $ {}", logged.command_line())?;
+        self.output.flush()?;
+
+        tracing::info!("[{phase}] {}", logged.command_line());
+
+        let start = Instant::now();
+
+        // Both streams share the log file, so their output is interleaved in
+        // the order it was written
+        let status = std::process::Command::new(program)
+            .args(args)
+            .current_dir(cwd)
+            .env_clear()
+            .envs(&logged.env)
+            .stdin(Stdio::null())
+            .stdout(self.output.try_clone()?)
+            .stderr(self.output.try_clone()?)
+            .status();
+
+        logged.duration_ms =
+            u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+        match status {
+            Ok(status) => {
+                logged.status = status.code();
+                writeln!(self.output, "==> {}", describe_status(status))?;
+            }
+            Err(e) => {
+                tracing::warn!("failed to run '{program}': {e}");
+                writeln!(self.output, "==> failed to run '{program}': {e}")?;
+            }
+        }
+
+        writeln!(self.output)?;
+        self.output.flush()?;
+
+        self.record.commands.push(logged);
+        self.save()?;
+
+        Ok(&self.record.commands[self.record.commands.len() - 1])
+    }
+}
+
+fn describe_status(status: ExitStatus) -> String {
+    status.code().map_or_else(
+        || format!("terminated: {status}"),
+        |code| format!("exited with status {code}"),
+    )
+}
@@ -27,10 +27,40 @@
 
 // pub mod spec;
 
+pub mod builder;
+#[cfg(feature = "python")]
+pub mod define;
+pub mod doctor;
+pub mod environment;
+pub mod flags;
+pub mod install;
+pub mod license;
+pub mod lint;
+pub mod log;
+pub mod names;
 pub mod outline;
+pub mod patch;
+pub mod policy;
+#[cfg(feature = "solver-z3")]
+pub mod proof;
+pub mod propagate;
+pub mod provider;
+#[cfg(feature = "solver-z3")]
 pub mod registry;
+pub mod repo;
+pub mod resolver;
+pub mod schema;
+pub mod settings;
+#[cfg(feature = "solver-z3")]
+pub mod solver;
+pub mod source;
+pub mod spack;
+pub mod stats;
+pub mod template;
+pub mod test;
 pub mod version;
 
-pub type WipRegistry<'a> = registry::Registry<'a, registry::WipVersionRegistry>;
-pub type BuiltRegistry<'a> =
-    registry::Registry<'a, registry::BuiltVersionRegistry>;
+#[cfg(feature = "solver-z3")]
+pub type WipRegistry = registry::Registry<registry::WipVersionRegistry>;
+#[cfg(feature = "solver-z3")]
+pub type BuiltRegistry = registry::Registry<registry::BuiltVersionRegistry>;
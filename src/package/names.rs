@@ -0,0 +1,86 @@
+//! Names of solver variables.
+//!
+//! Every named solver variable is named by one of these functions. Each kind
+//! of variable has its own namespace, a prefix no other kind shares, and the
+//! package, option and platform names within a name are escaped, so two
+//! different variables never share a name however their packages and options
+//! are named. Otherwise the option `b/c` of the package `a` would alias the
+//! option `c` of the package `a/b`, and a package named `0` would alias the
+//! first tracked constraint.
+//!
+//! Tracked constraints are named by their ID alone (see
+//! [`Registry::new_constraint_id`]), which cannot collide with any name here
+//! since each of these contains a [`SEPARATOR`].
+//!
+//! [`Registry::new_constraint_id`]: crate::package::registry::Registry::new_constraint_id
+
+use std::{borrow::Cow, fmt::Write};
+
+/// Separates the parts of a name. Never appears in an escaped part
+pub const SEPARATOR: char = '/';
+
+/// Whether `c` is written as itself by [`escape`]
+const fn is_plain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+/// Escape `part` of a name.
+///
+/// Every byte of a character other than an ASCII letter or digit, `_`, `-`,
+/// `.` or `+` is written as `%XX`, so the result never contains a
+/// [`SEPARATOR`] and different parts never escape to the same text.
+#[must_use]
+pub fn escape(part: &str) -> Cow<'_, str> {
+    if part.chars().all(is_plain) {
+        return Cow::Borrowed(part);
+    }
+
+    let mut res = String::with_capacity(part.len() * 3);
+
+    for c in part.chars() {
+        if is_plain(c) {
+            res.push(c);
+        } else {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                let _ = write!(res, "%{byte:02X}");
+            }
+        }
+    }
+
+    Cow::Owned(res)
+}
+
+/// The activation toggle of `package`, which is true when it is part of the
+/// spec
+#[must_use]
+pub fn package(package: &str) -> String {
+    format!("pkg{SEPARATOR}{}", escape(package))
+}
+
+/// The value of the option `option` of `package`
+#[must_use]
+pub fn option(package: &str, option: &str) -> String {
+    format!("opt{SEPARATOR}{}{SEPARATOR}{}", escape(package), escape(option))
+}
+
+/// The flag which is true when the condition of the `index`th item of `kind`
+/// (such as `patch`) in `package` holds
+#[must_use]
+pub fn condition(package: &str, kind: &str, index: usize) -> String {
+    format!(
+        "cond{SEPARATOR}{}{SEPARATOR}{kind}{SEPARATOR}{index}",
+        escape(package)
+    )
+}
+
+/// The `id`th version component variable
+#[must_use]
+pub fn version_component(id: usize) -> String {
+    format!("ver{SEPARATOR}{id}")
+}
+
+/// The fact that the platform's `key` is `value`
+#[must_use]
+pub fn platform_fact(key: impl std::fmt::Display, value: &str) -> String {
+    format!("platform{SEPARATOR}{key}{SEPARATOR}{}", escape(value))
+}
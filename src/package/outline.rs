@@ -6,32 +6,130 @@
 //! This outline definition is then passed to the `Planner`, which solves for
 //! a concrete, satisfiable set of dependencies and options which can then be
 //! built and installed.
-
-use std::collections::HashMap;
-
-use petgraph::{algo::Cycle, graph::DiGraph, visit::EdgeRef};
-use pyo3::prelude::*;
+//!
+//! Outlines and constraints hold no solver state: z3 objects, which belong to
+//! the thread that created them, are only created when a solver is generated
+//! from a [`SpecOutline`]. Outlines are therefore `Send + Sync`, so packages
+//! can be loaded on one thread and solved on another, or several specs solved
+//! in parallel from the same outlines.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+#[cfg(feature = "solver-z3")]
+use std::{path::Path, str::FromStr};
+
+use petgraph::{
+    algo::Cycle,
+    graph::{DiGraph, NodeIndex},
+};
+#[cfg(feature = "python")]
+use pyo3::{
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "solver-z3")]
 use z3::{Optimize, SortKind};
 
+#[cfg(feature = "python")]
+use crate::{constraint::OptionalDepends, interface::pickle};
+use crate::{
+    constraint::{self, Constraint, ConstraintUtils, SpecOption, Value},
+    package::{
+        builder::PackageOutlineBuilder,
+        flags::FlagMapping,
+        patch::Patch,
+        policy::PolicyConstraint,
+        propagate::{Origins, Propagator},
+        provider::VersionSource,
+        schema::OptionSchema,
+        source::Source,
+        test::SmokeTest,
+        version::Version,
+    },
+    spec::{
+        self, SpecOptionType, concrete::VERSION_OPTION, platform::Platform,
+        target::Target,
+    },
+    util::suggest,
+};
+#[cfg(feature = "solver-z3")]
 use crate::{
-    constraint::{
-        self, Constraint, ConstraintUtils, SOFT_PACKAGE_WEIGHT, SpecOption,
-        Value,
+    constraint::{SOFT_PACKAGE_WEIGHT, SOFT_TARGET_WEIGHT},
+    package::{
+        self,
+        resolver::{Conflict, ConflictKind},
+        stats::SolveStats,
     },
-    package::{self},
-    spec::{self, SpecOptionType},
+    spec::target::TARGET_OPTION,
 };
 
 pub type PackageDiGraph = DiGraph<PackageOutline, u8>;
 pub type SpecMap = HashMap<String, Option<spec::SpecOptionValue>>;
 
-#[pyclass]
-#[derive(Clone, Debug, Default)]
+/// Separates a package name from the tag of a duplicate node, as in
+/// `gcc#build`.
+///
+/// Depending on `name#tag` instead of `name` creates a separate node for the
+/// package with its own solver variables, so it can be concretized
+/// differently from other uses of the same package. For example, a build-time
+/// `gcc#build` can have a different version to the `gcc` used at run time.
+/// Options of the duplicate are referred to through the full node name, such
+/// as `SpecOption("gcc#build", "version")`.
+pub const NODE_TAG_SEPARATOR: char = '#';
+
+/// Split a node name into its package name and duplicate tag, if any.
+#[must_use]
+pub fn split_node(node: &str) -> (&str, Option<&str>) {
+    match node.split_once(NODE_TAG_SEPARATOR) {
+        Some((name, tag)) => (name, Some(tag)),
+        None => (node, None),
+    }
+}
+
+#[cfg_attr(feature = "python", pyclass(module = "zpack.package"))]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PackageOutline {
     pub name: String,
     pub constraints: Vec<Constraint>,
     pub set_options: HashMap<String, spec::SpecOptionValue>,
     pub set_defaults: HashMap<String, Option<spec::SpecOptionValue>>,
+    pub patches: Vec<Patch>,
+    pub sources: Vec<Source>,
+
+    /// Compiler and linker flags added when their conditions hold
+    #[serde(default)]
+    pub flags: Vec<FlagMapping>,
+
+    /// Smoke tests run against the installed package
+    #[serde(default)]
+    pub tests: Vec<SmokeTest>,
+
+    /// The options in [`Self::set_options`] which were requested by the user
+    /// rather than set by the package itself. See
+    /// [`SpecRequest::apply`](spec::parse::SpecRequest::apply)
+    #[serde(default)]
+    pub requested_options: BTreeSet<String>,
+
+    /// Namespace of the repository this package was loaded from. Set by
+    /// [`RepoStack::push`](package::repo::RepoStack::push)
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// Where to discover the available versions of this package. See
+    /// [`package::provider`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_source: Option<VersionSource>,
+
+    /// The license of the package, as an SPDX license expression such as
+    /// `"MIT OR Apache-2.0"`. Reported in SBOMs (see [`spec::sbom`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
+    /// The declared options of the package. If empty, option types are
+    /// inferred from the constraints which use them. See
+    /// [`crate::package::schema`]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub schema: HashMap<String, OptionSchema>,
 }
 
 impl std::fmt::Display for PackageOutline {
@@ -41,6 +139,34 @@ impl std::fmt::Display for PackageOutline {
 }
 
 impl PackageOutline {
+    /// Start building an outline for the package `name`.
+    pub fn builder(name: impl Into<String>) -> PackageOutlineBuilder {
+        PackageOutlineBuilder::new(name)
+    }
+
+    /// Require the package's version to be exactly one of `versions`.
+    pub fn push_version_domain(
+        &mut self,
+        versions: impl IntoIterator<Item = Version>,
+    ) {
+        let version = SpecOption::new(self.name.clone(), VERSION_OPTION);
+
+        let of = versions
+            .into_iter()
+            .map(|v| version.clone().equals(v).into())
+            .collect::<Vec<_>>();
+
+        self.constraints.push(
+            constraint::Cmp::new(
+                constraint::NumOf::new(of),
+                constraint::CmpType::Equal,
+                Value::new(1_i64),
+            )
+            .into(),
+        );
+    }
+
+    /// The packages this package may depend on, sorted by name.
     #[must_use]
     pub fn dependencies(&self) -> Vec<String> {
         let mut res = Vec::new();
@@ -49,16 +175,187 @@ impl PackageOutline {
             res.extend(constraint.extract_dependencies());
         }
 
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    /// The explicitly set options of this package, sorted by name.
+    pub fn sorted_set_options(
+        &self,
+    ) -> impl Iterator<Item = (&String, &spec::SpecOptionValue)> {
+        let mut options = self.set_options.iter().collect::<Vec<_>>();
+        options.sort_by_key(|(name, _)| *name);
+        options.into_iter()
+    }
+
+    /// Every constraint in this package, including patch, flag and test
+    /// conditions.
+    pub fn all_constraints(&self) -> impl Iterator<Item = &Constraint> {
+        self.constraints.iter().chain(self.conditions().map(|(_, c)| c))
+    }
+
+    /// The condition of every conditional patch, flag mapping and test, with
+    /// the name of the solver variable which is true when it holds (see
+    /// [`Patch::flag_name`], [`FlagMapping::flag_name`] and
+    /// [`SmokeTest::flag_name`]).
+    pub fn conditions(&self) -> impl Iterator<Item = (String, &Constraint)> {
+        let patches = self.patches.iter().enumerate().filter_map(|(i, p)| {
+            Some((Patch::flag_name(&self.name, i), p.when.as_ref()?))
+        });
+
+        let flags = self.flags.iter().enumerate().filter_map(|(i, f)| {
+            Some((FlagMapping::flag_name(&self.name, i), f.when.as_ref()?))
+        });
+
+        let tests = self.tests.iter().enumerate().filter_map(|(i, t)| {
+            Some((SmokeTest::flag_name(&self.name, i), t.when.as_ref()?))
+        });
+
+        patches.chain(flags).chain(tests)
+    }
+
+    /// A copy of this package for the duplicate node `node`, with every
+    /// reference to its own options renamed to the node.
+    #[must_use]
+    pub fn duplicate(&self, node: &str) -> Self {
+        let mut res = self.clone();
+        res.name = node.to_string();
+
+        let conditions = res
+            .patches
+            .iter_mut()
+            .filter_map(|p| p.when.as_mut())
+            .chain(res.flags.iter_mut().filter_map(|f| f.when.as_mut()))
+            .chain(res.tests.iter_mut().filter_map(|t| t.when.as_mut()));
+
+        for constraint in res.constraints.iter_mut().chain(conditions) {
+            constraint.rename_package(&self.name, node);
+        }
+
         res
     }
 }
 
+/// Add a duplicate node for every tagged dependency (see
+/// [`NODE_TAG_SEPARATOR`]), including those of other duplicates.
+fn expand_duplicate_nodes(
+    mut outlines: Vec<PackageOutline>,
+) -> Result<Vec<PackageOutline>, Box<SolverError>> {
+    let mut idx = 0;
+
+    while idx < outlines.len() {
+        let mut deps = outlines[idx].dependencies();
+        deps.sort();
+
+        for dep in deps {
+            let (name, Some(_)) = split_node(&dep) else { continue };
+
+            if outlines.iter().any(|o| o.name == dep) {
+                continue;
+            }
+
+            let Some(base) = outlines.iter().find(|o| o.name == name) else {
+                tracing::error!("missing package '{name}' for node '{dep}'");
+
+                return Err(Box::new(SolverError::MissingPackage {
+                    name: name.to_string(),
+                    suggestion: suggest::closest(
+                        name,
+                        outlines.iter().map(|o| o.name.as_str()),
+                    )
+                    .map(str::to_string),
+                }));
+            };
+
+            tracing::info!("adding duplicate node '{dep}'");
+
+            let duplicate = base.duplicate(&dep);
+            outlines.push(duplicate);
+        }
+
+        idx += 1;
+    }
+
+    Ok(outlines)
+}
+
+/// Write a solver rendered in SMT-LIB2 format to `path`.
+#[cfg(feature = "solver-z3")]
+pub(crate) fn write_smtlib(
+    path: &Path,
+    smtlib: &str,
+) -> Result<(), Box<SolverError>> {
+    tracing::info!("exporting solver to '{}'", path.display());
+
+    std::fs::write(path, smtlib).map_err(|e| {
+        tracing::error!("failed to write '{}': {e}", path.display());
+
+        Box::new(SolverError::Export {
+            path: path.display().to_string(),
+            error: e.to_string(),
+        })
+    })
+}
+
+/// The seed used for z3's random number generators in deterministic mode
+pub const DETERMINISTIC_SEED: u32 = 0;
+
+/// Fix the seeds of z3's random number generators and disable parallel
+/// solving, so identical problems always produce identical models.
+///
+/// z3's parameters are global, so this affects every solver created
+/// afterwards.
+#[cfg(feature = "solver-z3")]
+pub fn fix_random_seeds() {
+    tracing::info!("fixing solver random seeds to {DETERMINISTIC_SEED}");
+
+    let seed = DETERMINISTIC_SEED.to_string();
+
+    z3::set_global_param("smt.random_seed", &seed);
+    z3::set_global_param("sat.random_seed", &seed);
+    z3::set_global_param("parallel.enable", "false");
+}
+
 pub struct SpecOutline {
     pub graph: PackageDiGraph,
     pub lookup: HashMap<String, petgraph::graph::NodeIndex>,
     pub required: Vec<String>,
+
+    /// The platform to concretize for. Defaults to [`Platform::host`]
+    pub platform: Platform,
+
+    /// Fix the solver's random seeds, so identical inputs always produce
+    /// identical concretizations
+    pub deterministic: bool,
+
+    /// Allow constraints and requests to refer to options no package
+    /// declares, creating a new solver variable for each, rather than
+    /// reporting them. See [`crate::package::schema`]
+    pub permissive: bool,
+
+    /// Where each inherited default came from. Filled in by
+    /// [`Self::propagate_defaults`]
+    pub origins: Origins,
+
+    /// Site-wide constraints which hold regardless of which packages are
+    /// active (see [`crate::package::policy`])
+    pub policies: Vec<PolicyConstraint>,
+
+    /// The options of each package fixed by its settings in the user
+    /// configuration (see [`crate::package::settings`]), by package
+    pub configured: HashMap<String, HashSet<String>>,
 }
 
+// Outlines must stay free of solver state (see the module documentation)
+const _: () = {
+    const fn send_sync<T: Send + Sync>() {}
+
+    send_sync::<Constraint>();
+    send_sync::<PackageOutline>();
+    send_sync::<SpecOutline>();
+};
+
 #[derive(Clone, Debug)]
 pub enum GenSpecSolverError {
     DuplicateOption(String),
@@ -71,11 +368,17 @@ pub enum SolverError {
 
     MissingPackage {
         name: String,
+
+        /// A similarly named package which may have been intended
+        suggestion: Option<String>,
     },
 
     MissingVariable {
         package: String,
         name: String,
+
+        /// A similarly named option of the same package
+        suggestion: Option<String>,
     },
 
     InvalidNonValueConstraint,
@@ -85,8 +388,25 @@ pub enum SolverError {
         received: SpecOptionType,
     },
 
+    /// An option was compared with a value of a different type
+    OptionValueType {
+        package: String,
+        option: String,
+        expected: SpecOptionType,
+        value: spec::SpecOptionValue,
+    },
+
+    /// A package or option was given two different types
+    ConflictingOptionType {
+        package: String,
+        option: Option<String>,
+        first: SpecOptionType,
+        second: SpecOptionType,
+    },
+
     InvalidConstraint(String),
 
+    #[cfg(feature = "solver-z3")]
     IncorrectSolverType {
         expected: SortKind,
         received: SortKind,
@@ -111,6 +431,147 @@ pub enum SolverError {
     },
 
     InvalidNumberOfClauses(usize),
+
+    /// The solver could not be written to a file
+    Export {
+        path: String,
+        error: String,
+    },
+
+    /// The outline uses a feature the resolver does not implement
+    Unsupported {
+        resolver: &'static str,
+        reason: String,
+    },
+
+    /// A value is not valid for an option declared in its package's schema
+    InvalidOptionValue {
+        package: String,
+        option: String,
+        value: spec::SpecOptionValue,
+        schema: OptionSchema,
+    },
+}
+
+impl std::fmt::Display for SolverError {
+    #[allow(clippy::too_many_lines)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateOption(name) => {
+                write!(f, "option '{name}' is defined more than once")
+            }
+            Self::MissingPackage { name, suggestion } => write!(
+                f,
+                "unknown package '{name}'{}",
+                suggest::did_you_mean(suggestion.as_deref())
+            ),
+            Self::MissingVariable { package, name, suggestion } => write!(
+                f,
+                "package '{package}' has no option '{name}'{}",
+                suggest::did_you_mean(suggestion.as_deref())
+            ),
+            Self::InvalidNonValueConstraint => {
+                f.write_str("constraint cannot be used as a value")
+            }
+            Self::IncorrectValueType { expected, received } => {
+                write!(
+                    f,
+                    "expected a value of type {expected:?}, found {received:?}"
+                )
+            }
+            Self::OptionValueType { package, option, expected, value } => {
+                write!(
+                    f,
+                    "'{package}:{option}' has type {expected}, but is compared \
+                     with '{value}' of type {}",
+                    value.to_type()
+                )
+            }
+            Self::ConflictingOptionType {
+                package,
+                option: Some(option),
+                first,
+                second,
+            } => write!(
+                f,
+                "'{package}:{option}' is used as both {first} and {second}"
+            ),
+            Self::ConflictingOptionType {
+                package,
+                option: None,
+                first,
+                second,
+            } => {
+                write!(
+                    f,
+                    "package '{package}' is used as both {first} and {second}"
+                )
+            }
+            Self::InvalidConstraint(msg) => {
+                write!(f, "invalid constraint: {msg}")
+            }
+            #[cfg(feature = "solver-z3")]
+            Self::IncorrectSolverType { expected, received } => write!(
+                f,
+                "expected a solver value of kind {expected:?}, found {received:?}"
+            ),
+            Self::DuplicatePackageEntry(name) => {
+                write!(f, "package '{name}' is defined more than once")
+            }
+            Self::NoSolverVariable { package, option: Some(option) } => {
+                write!(f, "'{package}:{option}' has no solver variable")
+            }
+            Self::NoSolverVariable { package, option: None } => {
+                write!(f, "package '{package}' has no solver variable")
+            }
+            Self::Cycle(_) => {
+                f.write_str("the package dependency graph contains a cycle")
+            }
+            Self::DefaultConflict {
+                package_name,
+                default_name,
+                first_setter,
+                first_value,
+                conflict_setter,
+                conflict_value,
+            } => write!(
+                f,
+                "conflicting defaults for '{package_name}:{default_name}': \
+                 '{first_setter}' sets {first_value} but '{conflict_setter}' \
+                 sets {conflict_value}"
+            ),
+            Self::InvalidNumberOfClauses(n) => {
+                write!(f, "expected a single clause, found {n}")
+            }
+            Self::Export { path, error } => {
+                write!(f, "failed to export solver to '{path}': {error}")
+            }
+            Self::Unsupported { resolver, reason } => {
+                write!(f, "not supported by the {resolver} resolver: {reason}")
+            }
+            Self::InvalidOptionValue { package, option, value, schema } => {
+                write!(f, "invalid value '{value}' for '{package}:{option}'")?;
+
+                if let Some(description) = &schema.description {
+                    write!(f, " ({description})")?;
+                }
+
+                f.write_str(": ")?;
+
+                if value.is_type(schema.dtype) {
+                    let values = schema
+                        .values
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>();
+
+                    write!(f, "expected one of {}", values.join(", "))
+                } else {
+                    write!(f, "expected a value of type {}", schema.dtype)
+                }
+            }
+        }
+    }
 }
 
 impl SpecOutline {
@@ -120,7 +581,7 @@ impl SpecOutline {
         let mut lookup = HashMap::new();
         let mut graph = PackageDiGraph::new();
 
-        for outline in outlines {
+        for outline in expand_duplicate_nodes(outlines)? {
             let name = outline.name.clone();
             let idx = graph.add_node(outline);
             lookup.insert(name, idx);
@@ -140,7 +601,14 @@ impl SpecOutline {
                             src_name
                         );
 
-                        SolverError::MissingPackage { name: dep.clone() }
+                        SolverError::MissingPackage {
+                            name: dep.clone(),
+                            suggestion: suggest::closest(
+                                dep,
+                                lookup.keys().map(String::as_str),
+                            )
+                            .map(str::to_string),
+                        }
                     })?,
                 ));
             }
@@ -149,17 +617,137 @@ impl SpecOutline {
         graph.extend_with_edges(edges);
 
         let required = Vec::new();
+        let platform = Platform::host();
+
+        Ok(Self {
+            graph,
+            lookup,
+            required,
+            platform,
+            deterministic: false,
+            permissive: false,
+            origins: Origins::default(),
+            policies: Vec::new(),
+            configured: HashMap::new(),
+        })
+    }
+
+    /// Every target which runs on [`Self::platform`], with the targets it
+    /// supports in turn. The platform's own microarchitecture is first.
+    #[must_use]
+    pub fn target_domain(&self) -> Vec<(String, Vec<String>)> {
+        let microarch = &self.platform.microarchitecture;
+
+        Target::lookup(microarch).map_or_else(
+            || vec![(microarch.clone(), vec![microarch.clone()])],
+            |platform| {
+                platform
+                    .ancestors()
+                    .into_iter()
+                    .map(|t| {
+                        let supported = t
+                            .ancestors()
+                            .iter()
+                            .map(|a| a.name.to_string())
+                            .collect();
+
+                        (t.name.to_string(), supported)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// The packages reachable from [`Self::required`] through their
+    /// dependencies, in graph order.
+    ///
+    /// Only these packages can be activated, so the solver only grounds their
+    /// constraints; every other package is left out of the problem entirely.
+    #[must_use]
+    pub fn grounded(&self) -> Vec<NodeIndex> {
+        let mut reachable = vec![false; self.graph.node_count()];
+
+        let mut stack = self
+            .required
+            .iter()
+            .filter_map(|r| self.lookup.get(r).copied())
+            .collect::<Vec<_>>();
+
+        while let Some(idx) = stack.pop() {
+            if std::mem::replace(&mut reachable[idx.index()], true) {
+                continue;
+            }
 
-        Ok(Self { graph, lookup, required })
+            stack.extend(
+                self.graph[idx]
+                    .all_constraints()
+                    .flat_map(ConstraintUtils::extract_dependencies)
+                    .filter_map(|dep| self.lookup.get(&dep).copied()),
+            );
+        }
+
+        tracing::debug!(
+            "grounding {} of {} packages",
+            reachable.iter().filter(|r| **r).count(),
+            reachable.len()
+        );
+
+        self.graph.node_indices().filter(|idx| reachable[idx.index()]).collect()
+    }
+
+    /// The [`Self::policies`] which apply to this solve: those which only
+    /// refer to grounded packages (see [`Self::grounded`]).
+    #[must_use]
+    pub fn active_policies(&self) -> Vec<&PolicyConstraint> {
+        let grounded = self
+            .grounded()
+            .into_iter()
+            .map(|idx| self.graph[idx].name.as_str())
+            .collect::<HashSet<_>>();
+
+        self.policies
+            .iter()
+            .filter(|policy| {
+                let applies = policy
+                    .packages()
+                    .iter()
+                    .all(|package| grounded.contains(package.as_str()));
+
+                if !applies {
+                    tracing::info!(
+                        "ignoring {}, which refers to packages outside this \
+                         solve",
+                        policy.description
+                    );
+                }
+
+                applies
+            })
+            .collect()
+    }
+
+    /// The constraints of every grounded package (see [`Self::grounded`]),
+    /// normalized with [`constraint::normalize`].
+    #[must_use]
+    pub fn grounded_constraints(&self) -> Vec<constraint::Normalized<'_>> {
+        constraint::normalize(self.grounded().into_iter().flat_map(|idx| {
+            let package = &self.graph[idx];
+
+            package.constraints.iter().map(|c| (package.name.as_str(), c))
+        }))
     }
 
-    /// Propagate default values throughout the DAG.
+    /// Propagate default values throughout the DAG with the default
+    /// [`Propagator`].
     ///
     /// Defaults are propagated as follows:
     /// - old value does not exist => use current default
     /// - new value is None => remove from defaults
     /// - new value set explicitly => use explicit value
-    /// - new value is inherited and conflicts with an inherited value => error
+    /// - new value is inherited and conflicts with an inherited value => merge
+    ///   them with the option's
+    ///   [`Lattice`](crate::package::propagate::Lattice), or error if they
+    ///   cannot be merged
     ///
     /// The return value of this function indicates either successful
     /// propagation or an error for one of two reasons:
@@ -167,101 +755,19 @@ impl SpecOutline {
     ///   propagate default values
     /// - Two inherited defaults conflict
     pub fn propagate_defaults(&mut self) -> Result<(), Box<SolverError>> {
-        use petgraph::algo::toposort;
-
         tracing::info!("propagating default values");
 
-        let mut reason_tracker = HashMap::<(String, String), String>::new();
-
-        let sorted = toposort(&self.graph, None).map_err(SolverError::Cycle)?;
-
-        for idx in sorted {
-            let src_name = self.graph[idx].name.clone();
-            let src_defaults = self.graph[idx].set_defaults.clone();
-
-            tracing::info!("propagating default values for {src_name}");
-
-            let deps: Vec<_> = self
-                .graph
-                .edges_directed(idx, petgraph::Direction::Outgoing)
-                .map(|e| e.target())
-                .collect();
-
-            for dep in deps {
-                let dep = &mut self.graph[dep];
-
-                for (opt_name, src_val) in &src_defaults {
-                    tracing::info!(
-                        "propagating default value {src_name}:{opt_name}"
-                    );
-
-                    // Skip None values
-                    let Some(src_val) = src_val else {
-                        continue;
-                    };
-
-                    match &dep.set_defaults.get(opt_name) {
-                        Some(Some(old_val)) => {
-                            if let Some(reason) = reason_tracker
-                                .get(&(dep.name.clone(), opt_name.clone()))
-                                && old_val != src_val
-                            {
-                                tracing::error!(
-                                    "conflicting default values detected"
-                                );
-
-                                let e = SolverError::DefaultConflict {
-                                    package_name: dep.name.clone(),
-                                    default_name: opt_name.clone(),
-                                    first_setter: reason.clone(),
-                                    first_value: old_val.clone(),
-                                    conflict_setter: match reason_tracker.get(
-                                        &(src_name.clone(), opt_name.clone()),
-                                    ) {
-                                        Some(val) => val.clone(),
-                                        None => src_name,
-                                    },
-                                    conflict_value: src_val.clone(),
-                                };
-
-                                return Err(Box::new(e));
-                            }
-                        }
-                        Some(None) => {
-                            dep.set_defaults.remove(opt_name);
-                        }
-
-                        None => {
-                            // Insert and track default
-
-                            dep.set_defaults.insert(
-                                opt_name.clone(),
-                                Some(src_val.clone()),
-                            );
-
-                            let reason = match reason_tracker
-                                .get(&(src_name.clone(), opt_name.clone()))
-                            {
-                                Some(prev) => prev.clone(),
-                                None => src_name.clone(),
-                            };
-
-                            reason_tracker.insert(
-                                (dep.name.clone(), opt_name.clone()),
-                                reason,
-                            );
-                        }
-                    }
-                }
-            }
-        }
+        self.origins = Propagator::default().run(&mut self.graph)?;
 
         Ok(())
     }
+}
 
-    pub fn type_check<'a>(
-        &'a self,
-        wip_registry: &mut package::WipRegistry<'a>,
+#[cfg(feature = "solver-z3")]
+impl SpecOutline {
+    pub fn type_check(
+        &self,
+        wip_registry: &mut package::WipRegistry,
     ) -> Result<(), Box<SolverError>> {
         for idx in self.graph.node_indices() {
             let package = &self.graph[idx];
@@ -273,29 +779,65 @@ impl SpecOutline {
 
                 constraint.type_check(wip_registry)?;
             }
+
+            for (_, when) in package.conditions() {
+                tracing::info!("checking types for condition '{when}'");
+
+                match when.get_value_type(Some(wip_registry)) {
+                    Some(SpecOptionType::Unknown | SpecOptionType::Bool) => {
+                        when.set_value_type(wip_registry, SpecOptionType::Bool);
+                    }
+
+                    Some(other) => {
+                        tracing::error!("condition '{when}' must be a Bool");
+
+                        return Err(Box::new(
+                            SolverError::IncorrectValueType {
+                                expected: SpecOptionType::Bool,
+                                received: other,
+                            },
+                        ));
+                    }
+
+                    None => {
+                        return Err(Box::new(
+                            SolverError::InvalidNonValueConstraint,
+                        ));
+                    }
+                }
+
+                when.type_check(wip_registry)?;
+            }
+        }
+
+        for policy in self.active_policies() {
+            tracing::info!("checking types for {}", policy.description);
+
+            policy.constraint.type_check(wip_registry)?;
         }
 
         Ok(())
     }
 
-    /// Ensure every [`SpecOption`] in the outline has a corresponding solver
-    /// variable. Additionally, ensure every package has a package toggle.
+    /// Ensure every [`SpecOption`] in the constraints of a grounded package
+    /// (see [`Self::grounded`]) has a corresponding solver variable.
+    /// Additionally, ensure every grounded package has a package toggle.
     ///
     /// # Panics
     /// Panics if there is an internal solver error
-    pub fn create_solver_variables<'a>(
-        &'a self,
+    pub fn create_solver_variables(
+        &self,
         optimizer: &Optimize,
-        wip_registry: &mut package::WipRegistry<'a>,
-    ) where
-        Self: 'a,
-    {
-        for idx in self.graph.node_indices() {
+        wip_registry: &mut package::WipRegistry,
+    ) {
+        for idx in self.grounded() {
             let package = &self.graph[idx];
 
             tracing::info!("creating activation toggle for {}", package.name);
 
-            let package_toggle = z3::ast::Bool::new_const(package.name.clone());
+            let package_toggle = z3::ast::Bool::new_const(
+                crate::package::names::package(&package.name),
+            );
 
             optimizer.assert_soft(
                 &package_toggle.not(),
@@ -312,57 +854,91 @@ impl SpecOutline {
                 )
                 .unwrap();
 
-            for (package_name, option_name, value) in package
-                .constraints
-                .iter()
-                .flat_map(|c| c.extract_spec_options())
+            if let Some(idx) =
+                wip_registry.lookup_option(&package.name, Some(TARGET_OPTION))
+                && wip_registry.spec_options()[idx].1.is_none()
             {
-                tracing::info!(
-                    "creating variable for {}:{}",
-                    package_name,
-                    option_name
+                let target = spec::SpecOption::default().to_empty_z3_dynamic(
+                    &package.name,
+                    TARGET_OPTION,
+                    wip_registry,
                 );
 
-                // Cannot skip this call since registry must be updated
-                let val = value.to_empty_z3_dynamic(
+                wip_registry
+                    .set_option_value(
+                        &package.name,
+                        Some(TARGET_OPTION),
+                        target,
+                    )
+                    .unwrap();
+            }
+
+            for (package_name, option_name, value) in
+                package.all_constraints().flat_map(|c| c.extract_spec_options())
+            {
+                Self::create_option_variable(
+                    wip_registry,
                     package_name,
                     option_name,
+                    &value,
+                );
+            }
+        }
+
+        for policy in self.active_policies() {
+            for (package_name, option_name, value) in
+                policy.constraint.extract_spec_options()
+            {
+                Self::create_option_variable(
                     wip_registry,
+                    package_name,
+                    option_name,
+                    &value,
                 );
+            }
+        }
+    }
 
-                if let Some(idx) =
-                    wip_registry.lookup_option(package_name, Some(option_name))
-                {
-                    if wip_registry.spec_options()[idx].1.is_some() {
-                        tracing::info!(
-                            "solver variable {package_name}:{option_name} already exists. Continuing"
-                        );
-                    } else {
-                        wip_registry
-                            .set_option_value(
-                                package_name,
-                                Some(option_name),
-                                val,
-                            )
-                            .unwrap();
-                    }
-                }
+    fn create_option_variable(
+        wip_registry: &mut package::WipRegistry,
+        package_name: &str,
+        option_name: &str,
+        value: &spec::SpecOption,
+    ) {
+        tracing::info!(
+            "creating variable for {}:{}",
+            package_name,
+            option_name
+        );
+
+        // Cannot skip this call since registry must be updated
+        let val =
+            value.to_empty_z3_dynamic(package_name, option_name, wip_registry);
+
+        if let Some(idx) =
+            wip_registry.lookup_option(package_name, Some(option_name))
+        {
+            if wip_registry.spec_options()[idx].1.is_some() {
+                tracing::info!(
+                    "solver variable {package_name}:{option_name} already exists. Continuing"
+                );
+            } else {
+                wip_registry
+                    .set_option_value(package_name, Some(option_name), val)
+                    .unwrap();
             }
         }
     }
 
-    pub fn handle_explicit_options<'a>(
-        &'a self,
+    pub fn handle_explicit_options(
+        &self,
         optimizer: &Optimize,
-        registry: &mut package::BuiltRegistry<'a>,
-    ) -> Result<(), Box<SolverError>>
-    where
-        Self: 'a,
-    {
-        for idx in self.graph.node_indices() {
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        for idx in self.grounded() {
             let package = &self.graph[idx];
 
-            for (name, value) in &package.set_options {
+            for (name, value) in package.sorted_set_options() {
                 tracing::info!(
                     "adding explicit value {}:{name} -> {value}",
                     package.name
@@ -400,9 +976,13 @@ impl SpecOutline {
                             .as_bool()
                             .unwrap(),
                     ),
-                    &z3::ast::Bool::new_const(
-                        registry.new_constraint_id(eq.to_string()),
-                    ),
+                    &z3::ast::Bool::new_const(registry.new_constraint_id(
+                        Conflict::new(
+                            ConflictKind::Value,
+                            &[&package.name],
+                            eq.to_string(),
+                        ),
+                    )),
                 );
             }
         }
@@ -410,20 +990,15 @@ impl SpecOutline {
         Ok(())
     }
 
-    pub fn require_packages<'a>(
-        &'a self,
+    pub fn require_packages(
+        &self,
         optimizer: &Optimize,
-        registry: &mut package::BuiltRegistry<'a>,
-    ) -> Result<(), Box<SolverError>>
-    where
-        Self: 'a,
-    {
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<(), Box<SolverError>> {
         for r in &self.required {
             let Some(idx) = registry.lookup_option(r, None) else {
                 tracing::error!("missing explicitly required dependency '{r}'");
-                return Err(Box::new(SolverError::MissingPackage {
-                    name: r.clone(),
-                }));
+                return Err(Box::new(registry.missing_error(r, None)));
             };
 
             let Some(dynamic) = &registry.spec_options()[idx].1 else {
@@ -434,10 +1009,13 @@ impl SpecOutline {
 
             let assertion = &dynamic.as_bool().unwrap();
 
-            let boolean = z3::ast::Bool::new_const(
-                registry
-                    .new_constraint_id(format!("'{r}' required explicitly")),
-            );
+            let boolean = z3::ast::Bool::new_const(registry.new_constraint_id(
+                Conflict::new(
+                    ConflictKind::Required,
+                    &[r],
+                    format!("'{r}' required explicitly"),
+                ),
+            ));
 
             optimizer.assert_and_track(assertion, &boolean);
         }
@@ -445,77 +1023,464 @@ impl SpecOutline {
         Ok(())
     }
 
-    pub fn push_constraints<'a>(
-        &'a self,
+    /// Assert the grounded constraints (see [`Self::grounded_constraints`]).
+    /// A constraint declared by several packages is asserted once, and must
+    /// hold if any of them is active.
+    ///
+    /// # Errors
+    /// Errors if a package has no activation toggle or a constraint cannot be
+    /// converted to solver clauses.
+    ///
+    /// # Panics
+    /// Panics if a package's activation toggle has no solver variable
+    pub fn push_constraints(
+        &self,
+        optimizer: &Optimize,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        for normalized in self.grounded_constraints() {
+            let toggles = normalized
+                .packages
+                .iter()
+                .map(|name| Self::package_toggle(registry, name))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let toggle = match toggles.as_slice() {
+                [toggle] => toggle.clone(),
+                toggles => z3::ast::Bool::or(toggles),
+            };
+
+            tracing::info!(
+                "adding constraint {} -> {}",
+                normalized.packages.join(", "),
+                normalized.constraint
+            );
+
+            let start = registry.num_tracked();
+
+            normalized
+                .constraint
+                .add_to_solver(&toggle, optimizer, registry)?;
+
+            registry.attribute_tracked(start, &normalized.packages);
+        }
+
+        Ok(())
+    }
+
+    /// The activation toggle of the package `name`
+    fn package_toggle(
+        registry: &package::BuiltRegistry,
+        name: &str,
+    ) -> Result<z3::ast::Bool, Box<SolverError>> {
+        let Some(idx) = registry.lookup_option(name, None) else {
+            tracing::error!("package '{name}' not found");
+            return Err(Box::new(registry.missing_error(name, None)));
+        };
+
+        let Some(dynamic) = &registry.spec_options()[idx].1 else {
+            panic!(
+                "activation toggle for package '{name}' not assigned variable in solver"
+            );
+        };
+
+        Ok(dynamic.as_bool().unwrap())
+    }
+
+    /// Assert every active policy (see [`Self::active_policies`]),
+    /// unconditionally. Each is tracked with its own description, so a policy
+    /// which causes a conflict is reported by name. A policy which is a
+    /// [`constraint::Prefer`] is added as a preference instead, and cannot
+    /// conflict.
+    ///
+    /// # Errors
+    /// Errors if a policy refers to an option with no solver variable.
+    pub fn push_policies(
+        &self,
+        optimizer: &Optimize,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        for policy in self.active_policies() {
+            tracing::info!("adding {}", policy.description);
+
+            if let Constraint::Prefer(prefer) = &policy.constraint {
+                let always = z3::ast::Bool::from_bool(true);
+                prefer.add_to_solver(&always, optimizer, registry)?;
+                continue;
+            }
+
+            for clause in policy.constraint.to_z3_clauses(registry)? {
+                let Some(assertion) = clause.as_bool() else {
+                    let msg = format!("{} is not a Bool", policy.description);
+                    tracing::error!("{msg}");
+                    return Err(Box::new(SolverError::InvalidConstraint(msg)));
+                };
+
+                optimizer.assert_and_track(
+                    &assertion,
+                    &z3::ast::Bool::new_const(registry.new_constraint_id(
+                        Conflict::new(
+                            ConflictKind::Policy,
+                            &[],
+                            policy.description.clone(),
+                        ),
+                    )),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Define a solver variable for every conditional patch and flag mapping
+    /// which is true exactly when its condition holds. The variable is named
+    /// as in [`PackageOutline::conditions`] so it can be evaluated in the
+    /// solved model.
+    ///
+    /// # Errors
+    /// Errors if a condition does not produce a single boolean clause.
+    pub fn push_conditions(
+        &self,
         optimizer: &Optimize,
-        registry: &mut package::BuiltRegistry<'a>,
-    ) -> Result<(), Box<SolverError>>
-    where
-        Self: 'a,
-    {
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        for idx in self.grounded() {
+            let package = &self.graph[idx];
+
+            for (name, when) in package.conditions() {
+                tracing::info!("adding condition {name} -> {when}");
+
+                let clauses = when.to_z3_clauses(registry)?;
+
+                let [clause] = clauses.as_slice() else {
+                    tracing::error!("invalid condition '{when}'");
+
+                    return Err(Box::new(SolverError::InvalidNumberOfClauses(
+                        clauses.len(),
+                    )));
+                };
+
+                let Some(cond) = clause.as_bool() else {
+                    let msg = format!("condition '{when}' is not a Bool");
+                    tracing::error!("{msg}");
+                    return Err(Box::new(SolverError::InvalidConstraint(msg)));
+                };
+
+                let flag = z3::ast::Bool::new_const(name);
+
+                optimizer.assert(&flag.eq(cond));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Declare the [`TARGET_OPTION`] of every package as a string, so
+    /// constraints which refer to it are typed correctly.
+    ///
+    /// # Errors
+    /// Errors if the option cannot be inserted into the registry.
+    pub fn declare_targets(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        for idx in self.graph.node_indices() {
+            wip_registry.insert_option_type(
+                &self.graph[idx].name,
+                Some(TARGET_OPTION),
+                SpecOptionType::Str,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Declare the type of every option in a package's schema (see
+    /// [`crate::package::schema`]), so constraints are checked against the
+    /// declared types rather than inferring them.
+    ///
+    /// # Errors
+    /// Errors if the option cannot be inserted into the registry.
+    pub fn declare_options(
+        &self,
+        wip_registry: &mut package::WipRegistry,
+    ) -> Result<(), Box<SolverError>> {
         for idx in self.graph.node_indices() {
             let package = &self.graph[idx];
 
-            tracing::info!("adding constraints for {}", package.name);
+            for (name, schema) in package.sorted_schema() {
+                if wip_registry
+                    .lookup_option(&package.name, Some(name))
+                    .is_none()
+                {
+                    wip_registry.insert_option_type(
+                        &package.name,
+                        Some(name),
+                        schema.dtype,
+                    )?;
+                }
+            }
+        }
 
-            let Some(idx) = registry.lookup_option(&package.name, None) else {
-                tracing::error!("package '{}' not found", package.name);
+        Ok(())
+    }
 
-                return Err(Box::new(SolverError::MissingPackage {
-                    name: package.name.clone(),
-                }));
-            };
+    /// The activation toggle and target variable of a package.
+    fn target_vars(
+        &self,
+        registry: &package::BuiltRegistry,
+        idx: petgraph::graph::NodeIndex,
+    ) -> Result<(z3::ast::Bool, z3::ast::String), Box<SolverError>> {
+        let name = &self.graph[idx].name;
+
+        let var = |option| {
+            registry
+                .lookup_option(name, option)
+                .and_then(|idx| registry.spec_options()[idx].1.as_ref())
+        };
+
+        let toggle = var(None).and_then(z3::ast::Dynamic::as_bool);
+        let target =
+            var(Some(TARGET_OPTION)).and_then(z3::ast::Dynamic::as_string);
+
+        let (Some(toggle), Some(target)) = (toggle, target) else {
+            tracing::error!("package '{name}' has no target variable");
+
+            return Err(Box::new(SolverError::NoSolverVariable {
+                package: name.clone(),
+                option: Some(TARGET_OPTION.to_string()),
+            }));
+        };
+
+        Ok((toggle, target))
+    }
 
-            let Some(dynamic) = &registry.spec_options()[idx].1 else {
-                panic!(
-                    "activation toggle for package '{}' not assigned variable in solver",
-                    package.name
-                );
-            };
+    /// Restrict the [`TARGET_OPTION`] of every package to targets which run on
+    /// [`Self::platform`], and prevent an active package from targeting a
+    /// newer microarchitecture than any of its active dependencies. The
+    /// platform's own microarchitecture is preferred.
+    ///
+    /// # Errors
+    /// Errors if a package has no target variable.
+    ///
+    /// # Panics
+    /// Panics if a target name contains a nul byte
+    pub fn push_target_constraints(
+        &self,
+        optimizer: &Optimize,
+        registry: &mut package::BuiltRegistry,
+    ) -> Result<(), Box<SolverError>> {
+        use z3::ast::Bool;
 
-            let package_toggle = &dynamic.as_bool().unwrap();
+        let microarch = &self.platform.microarchitecture;
+        let domain = self.target_domain();
 
-            for constraint in &package.constraints {
-                tracing::info!(
-                    "adding constraint {} -> {}",
-                    package.name,
-                    constraint
-                );
+        let lit = |name: &str| z3::ast::String::from_str(name).unwrap();
+
+        for idx in self.grounded() {
+            let name = &self.graph[idx].name;
+            let (toggle, target) = self.target_vars(registry, idx)?;
+
+            tracing::info!("adding target constraints for {name}");
+
+            let valid = domain
+                .iter()
+                .map(|(t, _)| target.eq(lit(t)))
+                .collect::<Vec<_>>();
+
+            optimizer.assert_and_track(
+                &Bool::or(&valid),
+                &Bool::new_const(registry.new_constraint_id(Conflict::new(
+                    ConflictKind::Target,
+                    &[name],
+                    format!("target of '{name}' must run on '{microarch}'"),
+                ))),
+            );
+
+            optimizer.assert_soft(
+                &target.eq(lit(microarch)),
+                SOFT_TARGET_WEIGHT,
+                None,
+            );
+
+            for dep in self
+                .graph
+                .neighbors_directed(idx, petgraph::Direction::Outgoing)
+            {
+                let (dep_toggle, dep_target) =
+                    self.target_vars(registry, dep)?;
+
+                let not_newer = domain
+                    .iter()
+                    .map(|(t, supported)| {
+                        let allowed = supported
+                            .iter()
+                            .map(|s| target.eq(lit(s)))
+                            .collect::<Vec<_>>();
 
-                constraint.add_to_solver(
-                    package_toggle,
-                    optimizer,
-                    registry,
-                )?;
+                        dep_target.eq(lit(t)).implies(Bool::or(&allowed))
+                    })
+                    .collect::<Vec<_>>();
+
+                let assertion = Bool::and(&[toggle.clone(), dep_toggle])
+                    .implies(Bool::and(&not_newer));
+
+                optimizer.assert_and_track(
+                    &assertion,
+                    &Bool::new_const(registry.new_constraint_id(Conflict::new(
+                        ConflictKind::Target,
+                        &[name, &self.graph[dep].name],
+                        format!(
+                            "'{name}' cannot target a newer microarchitecture \
+                             than its dependency '{}'",
+                            self.graph[dep].name
+                        ),
+                    ))),
+                );
             }
         }
 
         Ok(())
     }
 
+    /// Fix the value of every platform fact referenced by a
+    /// [`WhenPlatform`](constraint::WhenPlatform) constraint according to
+    /// [`Self::platform`].
+    ///
+    /// The assertions are tracked, so an unsatisfiable spec caused by the
+    /// platform reports the fact responsible.
+    pub fn push_platform_facts(
+        &self,
+        optimizer: &Optimize,
+        registry: &mut package::BuiltRegistry,
+    ) {
+        let facts = registry
+            .platform_facts()
+            .map(|(key, value, fact)| (key, value.to_string(), fact.clone()))
+            .collect::<Vec<_>>();
+
+        for (key, value, fact) in facts {
+            let actual = self.platform.get(key);
+
+            tracing::info!("fixing platform fact {key}={value} ({actual})");
+
+            let holds = z3::ast::Bool::from_bool(actual == value);
+
+            optimizer.assert_and_track(
+                &fact.eq(&holds),
+                &z3::ast::Bool::new_const(registry.new_constraint_id(
+                    Conflict::new(
+                        ConflictKind::Platform,
+                        &[],
+                        format!("platform {key} is '{actual}'"),
+                    ),
+                )),
+            );
+        }
+    }
+
+    /// Generate the solver and registry for this outline.
+    ///
+    /// Only packages reachable from the required roots are grounded (see
+    /// [`Self::grounded`]), although every package is type checked. Default
+    /// values must already have been propagated with
+    /// [`Self::propagate_defaults`]. The returned registry owns its names, so
+    /// it can be stored independently of the outline. To keep both together,
+    /// use [`SpecSolver`](package::solver::SpecSolver).
     pub fn gen_spec_solver(
-        &mut self,
-    ) -> Result<(Optimize, package::BuiltRegistry<'_>), Box<SolverError>> {
-        tracing::info!("generating spec solver");
+        &self,
+    ) -> Result<(Optimize, package::BuiltRegistry), Box<SolverError>> {
+        self.gen_spec_solver_profiled(&mut SolveStats::new())
+    }
 
-        let optimizer = Optimize::new();
-        let mut wip_registry = package::WipRegistry::default();
+    /// Render the solver generated for this outline in SMT-LIB2 format.
+    ///
+    /// The output starts with comments recording the roots and platform, and
+    /// ends with `(check-sat)` and `(get-model)`, so it can be replayed with
+    /// a standalone `z3`. As with [`Self::gen_spec_solver`], default values
+    /// must already have been propagated.
+    ///
+    /// # Errors
+    /// Errors if the solver cannot be generated.
+    pub fn to_smtlib(&self) -> Result<String, Box<SolverError>> {
+        let (optimizer, _registry) = self.gen_spec_solver()?;
+        Ok(self.render_smtlib(&optimizer))
+    }
+
+    /// Render `optimizer`, generated from this outline, in SMT-LIB2 format.
+    pub(crate) fn render_smtlib(&self, optimizer: &Optimize) -> String {
+        use std::fmt::Write;
 
-        self.propagate_defaults()?;
-        self.type_check(&mut wip_registry)?;
+        let mut res = String::new();
 
-        self.create_solver_variables(&optimizer, &mut wip_registry);
+        // Writing to a String cannot fail
+        let _ = writeln!(res, "; zpack spec solver");
+        let _ = writeln!(res, "; roots: {}", self.required.join(" "));
+        let _ = writeln!(res, "; platform: {}", self.platform);
+        let _ = writeln!(res, "{optimizer}");
+        let _ = writeln!(res, "(get-model)");
 
-        let mut registry = wip_registry.build();
+        res
+    }
 
-        self.handle_explicit_options(&optimizer, &mut registry)?;
-        self.require_packages(&optimizer, &mut registry)?;
-        self.push_constraints(&optimizer, &mut registry)?;
+    /// Write [`Self::to_smtlib`] to `path`, for reproducing solver issues
+    /// outside of zpack.
+    ///
+    /// # Errors
+    /// Errors if the solver cannot be generated or the file cannot be
+    /// written.
+    pub fn export_smtlib(&self, path: &Path) -> Result<(), Box<SolverError>> {
+        write_smtlib(path, &self.to_smtlib()?)
+    }
+
+    /// [`Self::gen_spec_solver`], recording the time spent in each phase in
+    /// `stats`.
+    ///
+    /// # Errors
+    /// Errors if the outline fails to type check or a constraint cannot be
+    /// converted to solver clauses.
+    pub fn gen_spec_solver_profiled(
+        &self,
+        stats: &mut SolveStats,
+    ) -> Result<(Optimize, package::BuiltRegistry), Box<SolverError>> {
+        tracing::info!("generating spec solver");
+
+        if self.deterministic {
+            fix_random_seeds();
+        }
+
+        let optimizer = Optimize::new();
+        let mut wip_registry = package::WipRegistry::default();
+
+        stats.time("type check", || {
+            self.check_schemas()?;
+            self.declare_targets(&mut wip_registry)?;
+            self.declare_options(&mut wip_registry)?;
+            self.type_check(&mut wip_registry)
+        })?;
+
+        let mut registry = stats.time("variable creation", || {
+            self.create_solver_variables(&optimizer, &mut wip_registry);
+            wip_registry.build()
+        });
+
+        stats.time("constraint adding", || {
+            self.handle_explicit_options(&optimizer, &mut registry)?;
+            self.require_packages(&optimizer, &mut registry)?;
+            self.push_constraints(&optimizer, &mut registry)?;
+            self.push_policies(&optimizer, &mut registry)?;
+            self.push_target_constraints(&optimizer, &mut registry)?;
+            self.push_conditions(&optimizer, &mut registry)?;
+            self.push_platform_facts(&optimizer, &mut registry);
+
+            Ok::<_, Box<SolverError>>(())
+        })?;
 
         Ok((optimizer, registry))
     }
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl PackageOutline {
     #[new]
@@ -526,6 +1491,15 @@ impl PackageOutline {
             constraints: Vec::new(),
             set_options: HashMap::new(),
             set_defaults: HashMap::new(),
+            patches: Vec::new(),
+            sources: Vec::new(),
+            flags: Vec::new(),
+            tests: Vec::new(),
+            requested_options: BTreeSet::new(),
+            namespace: None,
+            version_source: None,
+            license: None,
+            schema: HashMap::new(),
         }
     }
 
@@ -536,4 +1510,114 @@ impl PackageOutline {
     pub fn push_constraints(&mut self, constraints: Vec<Constraint>) {
         self.constraints.extend(constraints);
     }
+
+    pub fn push_patch(&mut self, patch: Patch) {
+        self.patches.push(patch);
+    }
+
+    pub fn push_source(&mut self, source: Source) {
+        self.sources.push(source);
+    }
+
+    pub fn push_flags(&mut self, flags: FlagMapping) {
+        self.flags.push(flags);
+    }
+
+    pub fn push_test(&mut self, test: SmokeTest) {
+        self.tests.push(test);
+    }
+
+    pub fn set_version_source(&mut self, source: Option<VersionSource>) {
+        self.version_source = source;
+    }
+
+    pub fn set_license(&mut self, license: Option<String>) {
+        self.license = license;
+    }
+
+    /// Set the default value of an option, which is inherited by
+    /// dependencies.
+    pub fn set_default(&mut self, name: String, value: spec::SpecOptionValue) {
+        self.set_defaults.insert(name, Some(value));
+    }
+
+    /// Declare an option. See [`crate::package::schema`]
+    #[pyo3(name = "declare_option")]
+    pub fn py_declare_option(&mut self, name: String, schema: OptionSchema) {
+        self.declare_option(name, schema);
+    }
+
+    /// Add an optional dependency, declaring its option. See
+    /// [`Self::push_optional_depends`]
+    #[pyo3(name = "push_optional_depends")]
+    fn py_push_optional_depends(&mut self, dep: OptionalDepends) {
+        self.push_optional_depends(dep);
+    }
+
+    /// The option `name` of this package, to build constraints from, as in
+    /// `outline.option("static") & (outline.version >= "5.0")`
+    #[pyo3(name = "option")]
+    fn py_option(&self, name: &str) -> SpecOption {
+        SpecOption::new(&self.name, name)
+    }
+
+    /// The version of this package, to build constraints from
+    #[getter]
+    #[pyo3(name = "version")]
+    fn py_version(&self) -> SpecOption {
+        SpecOption::new(&self.name, VERSION_OPTION)
+    }
+
+    /// Outlines are equal if their serialized forms are. Outlines can be
+    /// modified, so are not hashable
+    fn __eq__(&self, other: &Self) -> PyResult<bool> {
+        pickle::same_state(self, other)
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialization_ignores_insertion_order() {
+        let options = ["cuda", "debug", "mpi", "openmp", "shared", "static"];
+
+        let mut forward = PackageOutline::default();
+        forward.requested_options.extend(options.map(String::from));
+
+        let mut backward = PackageOutline::default();
+        backward
+            .requested_options
+            .extend(options.iter().rev().map(ToString::to_string));
+
+        assert_eq!(
+            serde_json::to_string(&forward).unwrap(),
+            serde_json::to_string(&backward).unwrap()
+        );
+    }
 }
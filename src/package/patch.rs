@@ -0,0 +1,292 @@
+//! Source patches for packages.
+//!
+//! A [`Patch`] is declared on a [`PackageOutline`] with an optional condition.
+//! The condition is an ordinary [`Constraint`] which is evaluated by the
+//! solver, so a patch can depend on anything a constraint can refer to (the
+//! package version, options of other packages, etc.). Patches whose condition
+//! holds in the solved model are recorded on the
+//! [`ConcretePackage`](crate::spec::ConcretePackage) as [`ConcretePatch`]es,
+//! and therefore contribute to its hash.
+//!
+//! Applying patches is the `patch` build phase. Every applied patch is
+//! recorded in a stamp directory inside the source tree, so running the phase
+//! multiple times on the same tree is a no-op.
+//!
+//! [`PackageOutline`]: crate::package::outline::PackageOutline
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "python")]
+use crate::util::digest::ParseError;
+use crate::{
+    constraint::Constraint, package::names, spec::ConcretePackage,
+    util::digest::Checksum,
+};
+
+/// Name of the build phase which applies patches
+pub const PATCH_PHASE: &str = "patch";
+
+/// Directory, relative to the source root, recording applied patches
+pub const PATCH_STAMP_DIR: &str = ".zpack-patches";
+
+/// Where the contents of a patch come from
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum PatchSource {
+    /// A file, relative to the package repository
+    File(PathBuf),
+
+    /// A remote file which must be fetched before it is applied
+    Url(String),
+}
+
+/// A patch declared by a package.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Patch {
+    pub source: PatchSource,
+
+    /// Checksum of the patch file
+    pub checksum: Checksum,
+
+    /// Number of leading path components to strip (`patch -p<strip>`)
+    pub strip: usize,
+
+    /// The patch is only applied if this evaluates to true
+    pub when: Option<Constraint>,
+}
+
+/// A patch which applies to a concretized package.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ConcretePatch {
+    pub source: PatchSource,
+    pub checksum: Checksum,
+    pub strip: usize,
+}
+
+#[derive(Debug)]
+pub enum PatchError {
+    Io(std::io::Error),
+
+    ChecksumMismatch {
+        source: PatchSource,
+        expected: Checksum,
+        actual: Checksum,
+    },
+
+    Fetch {
+        url: String,
+        reason: String,
+    },
+
+    CommandFailed {
+        source: PatchSource,
+        status: std::process::ExitStatus,
+    },
+}
+
+impl From<std::io::Error> for PatchError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl std::fmt::Display for PatchSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Url(url) => f.write_str(url),
+        }
+    }
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::ChecksumMismatch { source, expected, actual } => write!(
+                f,
+                "checksum mismatch for patch '{source}': expected {expected}, \
+                 found {actual}"
+            ),
+            Self::Fetch { url, reason } => {
+                write!(f, "failed to fetch patch '{url}': {reason}")
+            }
+            Self::CommandFailed { source, status } => {
+                write!(f, "failed to apply patch '{source}': {status}")
+            }
+        }
+    }
+}
+
+impl From<&str> for PatchSource {
+    fn from(value: &str) -> Self {
+        if value.starts_with("http://")
+            || value.starts_with("https://")
+            || value.starts_with("ftp://")
+        {
+            Self::Url(value.to_string())
+        } else {
+            Self::File(PathBuf::from(value))
+        }
+    }
+}
+
+impl Patch {
+    /// Name of the solver variable which is true if this patch applies.
+    ///
+    /// * `package`: The package declaring the patch
+    /// * `index`: The index of the patch within the package
+    #[must_use]
+    pub fn flag_name(package: &str, index: usize) -> String {
+        names::condition(package, "patch", index)
+    }
+
+    #[must_use]
+    pub fn to_concrete(&self) -> ConcretePatch {
+        ConcretePatch {
+            source: self.source.clone(),
+            checksum: self.checksum.clone(),
+            strip: self.strip,
+        }
+    }
+}
+
+impl std::fmt::Display for Patch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Patch({}", self.source)?;
+
+        if let Some(when) = &self.when {
+            write!(f, " when {when}")?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+fn run_patch(
+    patch: &ConcretePatch,
+    file: &Path,
+    source_dir: &Path,
+    dry_run: bool,
+) -> Result<(), PatchError> {
+    let mut cmd = std::process::Command::new("patch");
+
+    cmd.current_dir(source_dir)
+        .arg(format!("-p{}", patch.strip))
+        .arg("--forward")
+        .arg("--batch")
+        .arg("--input")
+        .arg(file);
+
+    if dry_run {
+        cmd.arg("--dry-run");
+    }
+
+    let status = cmd.status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(PatchError::CommandFailed { source: patch.source.clone(), status })
+    }
+}
+
+/// Run the `patch` phase for a concrete package.
+///
+/// Patches are applied in the order they were declared. A patch whose stamp
+/// already exists in `source_dir` is skipped. Every patch is verified against
+/// its checksum and dry-run before being applied, so a failing patch does not
+/// leave the source tree partially modified.
+///
+/// Returns the checksums of the patches which were applied by this call.
+///
+/// * `package`: The package to patch
+/// * `source_dir`: The root of the package's unpacked source
+/// * `repo_dir`: Directory [`PatchSource::File`] paths are relative to
+/// * `fetch`: Downloads a [`PatchSource::Url`] and returns the local path
+///
+/// # Errors
+/// Errors if a patch cannot be fetched, does not match its checksum or fails
+/// to apply.
+pub fn apply_patches(
+    package: &ConcretePackage,
+    source_dir: &Path,
+    repo_dir: &Path,
+    fetch: &dyn Fn(&str) -> Result<PathBuf, PatchError>,
+) -> Result<Vec<String>, PatchError> {
+    let stamp_dir = source_dir.join(PATCH_STAMP_DIR);
+    let mut applied = Vec::new();
+
+    for patch in &package.patches {
+        let stamp = stamp_dir.join(patch.checksum.hex());
+
+        if stamp.exists() {
+            tracing::info!(
+                "patch '{}' already applied to {}",
+                patch.source,
+                package.name
+            );
+            continue;
+        }
+
+        let file = match &patch.source {
+            PatchSource::File(path) => repo_dir.join(path),
+            PatchSource::Url(url) => fetch(url)?,
+        };
+
+        let (matches, actual) = patch.checksum.check_file(&file)?;
+
+        if !matches {
+            tracing::error!("checksum mismatch for patch '{}'", patch.source);
+
+            return Err(PatchError::ChecksumMismatch {
+                source: patch.source.clone(),
+                expected: patch.checksum.clone(),
+                actual,
+            });
+        }
+
+        tracing::info!("applying patch '{}' to {}", patch.source, package.name);
+
+        run_patch(patch, &file, source_dir, true)?;
+        run_patch(patch, &file, source_dir, false)?;
+
+        std::fs::create_dir_all(&stamp_dir)?;
+        std::fs::write(&stamp, patch.source.to_string())?;
+
+        applied.push(patch.checksum.to_string());
+    }
+
+    Ok(applied)
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Patch {
+    #[new]
+    #[pyo3(signature = (source, checksum, strip = 1, when = None))]
+    fn py_new(
+        source: &str,
+        checksum: &str,
+        strip: usize,
+        when: Option<Constraint>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            source: source.into(),
+            checksum: checksum.parse().map_err(|e: ParseError| {
+                PyValueError::new_err(e.to_string())
+            })?,
+            strip,
+            when,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
@@ -0,0 +1,86 @@
+//! Site-wide policies applied to every solve.
+//!
+//! A policy is a constraint, written in constraint syntax (see
+//! [`crate::constraint::syntax`]), configured under `policies` in the
+//! configuration file:
+//!
+//! ```yaml
+//! policies:
+//!   - name: modern-openssl
+//!     constraint: option(openssl:version) >= @3 when depends(openssl)
+//!     reason: OpenSSL 1.x is no longer supported
+//!   - name: no-intelmpi
+//!     constraint: depends(intelmpi) == false
+//!   - name: prefer-openmpi
+//!     constraint: prefer(option(mpi:openmpi) == true, 10)
+//! ```
+//!
+//! Unlike the constraints of a package, a policy holds whether or not any
+//! package is active. It only applies to a solve which can involve every
+//! package it names, so a policy about a package which is not a dependency
+//! of any root is ignored. Each policy is tracked on its own, so a policy
+//! which conflicts with the requested specs is reported by name, with its
+//! reason, in the unsatisfiable core. A policy written as `prefer(...)` (see
+//! [`crate::constraint::Prefer`]) only nudges the solve, and never conflicts.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constraint::{Constraint, ConstraintUtils};
+
+/// A policy as written in the configuration file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    pub name: String,
+
+    /// The constraint, in constraint syntax
+    pub constraint: String,
+
+    /// Why the policy exists, reported when it conflicts with a solve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// A parsed [`Policy`], ready to be added to a solve
+#[derive(Clone, Debug)]
+pub struct PolicyConstraint {
+    /// Reported in the unsatisfiable core if the policy conflicts
+    pub description: String,
+    pub constraint: Constraint,
+}
+
+impl Policy {
+    /// Parse the constraint of this policy.
+    ///
+    /// # Errors
+    /// Errors with a rendered message if the constraint is invalid.
+    pub fn parse(&self) -> Result<PolicyConstraint, String> {
+        let constraint = self.constraint.parse::<Constraint>()?;
+
+        let description = format!(
+            "policy '{}': {}",
+            self.name,
+            self.reason.clone().unwrap_or_else(|| constraint.to_string())
+        );
+
+        Ok(PolicyConstraint { description, constraint })
+    }
+}
+
+impl PolicyConstraint {
+    /// Every package the constraint refers to
+    #[must_use]
+    pub fn packages(&self) -> HashSet<String> {
+        let mut res = self.constraint.extract_dependencies();
+
+        res.extend(
+            self.constraint
+                .extract_spec_options()
+                .into_iter()
+                .map(|(package, _, _)| package.to_string()),
+        );
+
+        res
+    }
+}
@@ -0,0 +1,269 @@
+//! Human-readable z3 proofs of unsatisfiability.
+//!
+//! An unsat core names the constraints which conflict, but not how they
+//! conflict. For deep conflicts, the proof z3 derives can show the chain of
+//! reasoning, but it is printed as one large term mentioning only the
+//! solver's tracking literals. [`SpecOutline::explain_proof`] re-checks an
+//! outline with proof generation enabled and translates the proof into
+//! numbered steps, keeping only the steps which introduce or combine facts,
+//! and naming each constraint by its description rather than its literal.
+
+use std::collections::HashMap;
+
+use z3::{
+    Config, SatResult, Solver,
+    ast::{Ast, Dynamic},
+};
+
+use crate::package::{
+    BuiltRegistry,
+    outline::{SolverError, SpecOutline},
+};
+
+/// Proof rules which introduce or combine facts. Steps using any other rule,
+/// such as rewriting, are folded into the steps which use them.
+const RELEVANT_RULES: [&str; 6] = [
+    "asserted",
+    "hypothesis",
+    "lemma",
+    "unit-resolution",
+    "hyper-res",
+    "th-lemma",
+];
+
+/// The most steps shown, since proofs of large outlines can be enormous
+const MAX_STEPS: usize = 200;
+
+/// A single step of a [`Proof`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofStep {
+    /// The z3 proof rule, such as `unit-resolution`
+    pub rule: String,
+
+    /// What the step proves, with tracked constraints replaced by their
+    /// descriptions
+    pub conclusion: String,
+
+    /// Indices of the earlier steps this step uses
+    pub premises: Vec<usize>,
+}
+
+/// A proof that a spec outline is unsatisfiable. The last step proves
+/// `false`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Proof {
+    pub steps: Vec<ProofStep>,
+
+    /// Whether steps were dropped to stay within the limit
+    pub truncated: bool,
+}
+
+/// Render `conclusion`, naming tracked constraints by their descriptions.
+///
+/// A tracked constraint is asserted as `(=> literal constraint)`, which is
+/// shown as just the description. Other conclusions are printed as SMT-LIB
+/// with each literal replaced by `[description]`.
+fn describe(conclusion: &Dynamic, registry: &BuiltRegistry) -> String {
+    let text = conclusion.to_string();
+
+    if let Some(desc) = registry.constraint_description_by_name(&text) {
+        return desc.clone();
+    }
+
+    if conclusion.decl().name() == "=>"
+        && let Some(lit) = conclusion.children().first()
+        && let Some(desc) =
+            registry.constraint_description_by_name(&lit.to_string())
+    {
+        return desc.clone();
+    }
+
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+
+    while let Some(start) = rest.find('|') {
+        res.push_str(&rest[..start]);
+
+        let quoted = &rest[start..];
+        let end = quoted[1..].find('|').map_or(quoted.len(), |e| e + 2);
+
+        match registry.constraint_description_by_name(&quoted[..end]) {
+            Some(desc) => {
+                res.push('[');
+                res.push_str(desc);
+                res.push(']');
+            }
+            None => res.push_str(&quoted[..end]),
+        }
+
+        rest = &quoted[end..];
+    }
+
+    res.push_str(rest);
+    res
+}
+
+/// Translate the proof term `proof` into its relevant steps.
+fn collect_steps(proof: &impl Ast, registry: &BuiltRegistry) -> Proof {
+    // The relevant steps each visited proof term stands for: itself if it is
+    // relevant, otherwise the relevant steps of its premises
+    let mut memo: HashMap<Dynamic, Vec<usize>> = HashMap::new();
+    let mut res = Proof::default();
+
+    // The premises of a proof term are its children, except the last, which
+    // is the conclusion
+    let premises = |term: &Dynamic| {
+        let mut children = term.children();
+        children.pop();
+        children
+    };
+
+    let root_children = proof.children();
+    let (root_conclusion, root_premises) = match root_children.split_last() {
+        Some((conclusion, premises)) => (Some(conclusion), premises),
+        None => (None, &[][..]),
+    };
+
+    let mut stack =
+        root_premises.iter().map(|p| (p.clone(), false)).collect::<Vec<_>>();
+
+    while let Some((term, expanded)) = stack.pop() {
+        if memo.contains_key(&term) {
+            continue;
+        }
+
+        if !expanded {
+            stack.push((term.clone(), true));
+            stack.extend(
+                premises(&term)
+                    .into_iter()
+                    .filter(|p| !memo.contains_key(p))
+                    .map(|p| (p, false)),
+            );
+            continue;
+        }
+
+        let mut uses = premises(&term)
+            .iter()
+            .flat_map(|p| memo.get(p).cloned().unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        uses.sort_unstable();
+        uses.dedup();
+
+        let rule = term.decl().name();
+
+        if !RELEVANT_RULES.contains(&rule.as_str()) {
+            memo.insert(term, uses);
+            continue;
+        }
+
+        if res.steps.len() >= MAX_STEPS {
+            res.truncated = true;
+            memo.insert(term, Vec::new());
+            continue;
+        }
+
+        let conclusion = term
+            .children()
+            .last()
+            .map(|c| describe(c, registry))
+            .unwrap_or_default();
+
+        res.steps.push(ProofStep { rule, conclusion, premises: uses });
+        memo.insert(term, vec![res.steps.len() - 1]);
+    }
+
+    let mut uses = root_premises
+        .iter()
+        .flat_map(|p| memo.get(p).cloned().unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    uses.sort_unstable();
+    uses.dedup();
+
+    res.steps.push(ProofStep {
+        rule: proof.decl().name(),
+        conclusion: root_conclusion
+            .map(|c| describe(c, registry))
+            .unwrap_or_default(),
+        premises: uses,
+    });
+
+    res
+}
+
+impl SpecOutline {
+    /// Prove that this outline is unsatisfiable.
+    ///
+    /// The solver is regenerated in a fresh z3 context with proof generation
+    /// enabled, and its hard constraints are checked with every tracked
+    /// constraint assumed. Soft constraints and objectives cannot cause a
+    /// conflict, so they are ignored. As with
+    /// [`Self::gen_spec_solver`], default values must already have been
+    /// propagated.
+    ///
+    /// Returns `None` if the outline is satisfiable, or z3 could not decide
+    /// it.
+    ///
+    /// # Errors
+    /// Errors if the solver cannot be generated.
+    pub fn explain_proof(&self) -> Result<Option<Proof>, Box<SolverError>> {
+        tracing::info!("generating proof of unsatisfiability");
+
+        let mut config = Config::new();
+        config.set_proof_generation(true);
+
+        z3::with_z3_config(&config, || {
+            let (optimizer, registry) = self.gen_spec_solver()?;
+
+            let solver = Solver::new();
+
+            for assertion in optimizer.get_assertions() {
+                solver.assert(&assertion);
+            }
+
+            let result =
+                solver.check_assumptions(&registry.constraint_literals());
+
+            if result != SatResult::Unsat {
+                tracing::warn!("cannot prove unsatisfiability: {result:?}");
+                return Ok(None);
+            }
+
+            Ok(solver.get_proof().map(|proof| collect_steps(&proof, &registry)))
+        })
+    }
+}
+
+impl std::fmt::Display for Proof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.steps.len().to_string().len();
+
+        for (idx, step) in self.steps.iter().enumerate() {
+            write!(f, "[{:>width$}] {}", idx + 1, step.rule)?;
+
+            if !step.premises.is_empty() {
+                let premises = step
+                    .premises
+                    .iter()
+                    .map(|p| (p + 1).to_string())
+                    .collect::<Vec<_>>();
+
+                write!(f, " from {}", premises.join(", "))?;
+            }
+
+            writeln!(f, ": {}", step.conclusion)?;
+        }
+
+        if self.truncated {
+            writeln!(
+                f,
+                "(only the first {MAX_STEPS} steps are shown; premises of \
+                 later steps may be missing)"
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+//! Propagation of default option values through the package DAG.
+//!
+//! Packages are visited in topological order, so every dependent has
+//! inherited its own defaults before passing them on. Each default a package
+//! sets, or has inherited, is offered to its direct dependencies:
+//! - a dependency without a value for the option inherits it
+//! - a dependency which blocks the option (sets it to `None`) does not, and the
+//!   block is lifted for later dependents
+//! - a value the dependency sets itself always wins
+//! - a value the dependency has already inherited is merged with the new one
+//!
+//! How two inherited values merge depends on the option. Each option is
+//! governed by a [`Lattice`], which either combines the values or reports a
+//! conflict. For example, two different microarchitecture targets merge to
+//! the newest target both can run on (see [`TargetLattice`]), whereas most
+//! options only merge if they are equal (see [`Exact`]). New propagated
+//! attributes only need a lattice, not a new traversal.
+//!
+//! Every inherited value remembers the chain of packages it was passed down
+//! (see [`Origins`]), so a conflict names the packages responsible rather
+//! than the intermediate packages the values passed through.
+
+use std::collections::HashMap;
+
+use petgraph::{Direction, algo::toposort, visit::EdgeRef};
+
+use crate::{
+    package::outline::{PackageDiGraph, SolverError},
+    spec::{
+        SpecOptionValue,
+        target::{TARGET_OPTION, Target},
+    },
+};
+
+/// The name of the option holding a package's build type
+pub const BUILD_TYPE_OPTION: &str = "build_type";
+
+/// Build types, from the most to the least debuggable
+pub const BUILD_TYPES: [&str; 4] =
+    ["debug", "relwithdebinfo", "release", "minsizerel"];
+
+/// How values of a propagated option combine when a package inherits the
+/// option from more than one dependent.
+pub trait Lattice: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Whether this lattice decides how values of `option` merge
+    fn governs(&self, option: &str) -> bool;
+
+    /// Combine the inherited value `current` with `incoming`, inherited from
+    /// another package. Returns `None` if the values conflict.
+    fn merge(
+        &self,
+        current: &SpecOptionValue,
+        incoming: &SpecOptionValue,
+    ) -> Option<SpecOptionValue>;
+}
+
+/// Values only merge if they are equal.
+///
+/// Used for every option without a more specific lattice, such as a compiler
+/// choice, where there is no sensible value between two different ones.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Exact;
+
+impl Lattice for Exact {
+    fn name(&self) -> &'static str {
+        "exact"
+    }
+
+    fn governs(&self, _option: &str) -> bool {
+        true
+    }
+
+    fn merge(
+        &self,
+        current: &SpecOptionValue,
+        incoming: &SpecOptionValue,
+    ) -> Option<SpecOptionValue> {
+        (current == incoming).then(|| current.clone())
+    }
+}
+
+/// Microarchitecture targets merge to the newest target which both can run on.
+///
+/// For example, `zen4` and `zen3` merge to `zen3`, and `zen2` and `haswell`
+/// merge to `x86_64_v3`. Unknown targets, or targets of different families,
+/// conflict.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TargetLattice;
+
+impl Lattice for TargetLattice {
+    fn name(&self) -> &'static str {
+        "target"
+    }
+
+    fn governs(&self, option: &str) -> bool {
+        option == TARGET_OPTION
+    }
+
+    fn merge(
+        &self,
+        current: &SpecOptionValue,
+        incoming: &SpecOptionValue,
+    ) -> Option<SpecOptionValue> {
+        let (SpecOptionValue::Str(a), SpecOptionValue::Str(b)) =
+            (current, incoming)
+        else {
+            return None;
+        };
+
+        let b = Target::lookup(b)?;
+
+        // Ancestors are ordered nearest first, so the first shared ancestor
+        // is the newest
+        Target::lookup(a)?
+            .ancestors()
+            .into_iter()
+            .find(|t| b.supports(t))
+            .map(|t| SpecOptionValue::Str(t.name.to_string()))
+    }
+}
+
+/// String values drawn from a fixed, ordered list merge to whichever comes
+/// first. Values outside the list conflict unless they are equal.
+#[derive(Clone, Debug)]
+pub struct Ordered {
+    pub option: &'static str,
+    pub order: Vec<&'static str>,
+}
+
+impl Ordered {
+    #[must_use]
+    pub fn new(
+        option: &'static str,
+        order: impl IntoIterator<Item = &'static str>,
+    ) -> Self {
+        Self { option, order: order.into_iter().collect() }
+    }
+
+    /// Build types merge to the more debuggable one, so a dependency keeps
+    /// its debug information if any dependent asks for it (see
+    /// [`BUILD_TYPES`]).
+    #[must_use]
+    pub fn build_type() -> Self {
+        Self::new(BUILD_TYPE_OPTION, BUILD_TYPES)
+    }
+
+    fn rank(&self, value: &SpecOptionValue) -> Option<usize> {
+        match value {
+            SpecOptionValue::Str(s) => self.order.iter().position(|o| o == s),
+            _ => None,
+        }
+    }
+}
+
+impl Lattice for Ordered {
+    fn name(&self) -> &'static str {
+        self.option
+    }
+
+    fn governs(&self, option: &str) -> bool {
+        option == self.option
+    }
+
+    fn merge(
+        &self,
+        current: &SpecOptionValue,
+        incoming: &SpecOptionValue,
+    ) -> Option<SpecOptionValue> {
+        match (self.rank(current), self.rank(incoming)) {
+            (Some(a), Some(b)) if b < a => Some(incoming.clone()),
+            (Some(_), Some(_)) => Some(current.clone()),
+            _ => Exact.merge(current, incoming),
+        }
+    }
+}
+
+/// The packages each inherited value was passed down, by the package which
+/// inherited it and the option.
+///
+/// A chain starts with the package which set the value and ends with the
+/// dependent it was inherited from directly. When a value is merged with
+/// another by a [`Lattice`], the chain of the first value is kept.
+#[derive(Clone, Debug, Default)]
+pub struct Origins(HashMap<(String, String), Vec<String>>);
+
+impl Origins {
+    /// The packages `package`'s value of `option` was passed down, if it was
+    /// inherited.
+    #[must_use]
+    pub fn chain(&self, package: &str, option: &str) -> Option<&[String]> {
+        self.0
+            .get(&(package.to_string(), option.to_string()))
+            .map(Vec::as_slice)
+    }
+
+    /// The package which originally set `package`'s value of `option`, if it
+    /// was inherited.
+    #[must_use]
+    pub fn get(&self, package: &str, option: &str) -> Option<&str> {
+        self.chain(package, option)?.first().map(String::as_str)
+    }
+
+    /// The package responsible for `package`'s value of `option`: the origin
+    /// if it was inherited, otherwise `package` itself.
+    #[must_use]
+    pub fn source(&self, package: &str, option: &str) -> String {
+        self.get(package, option).unwrap_or(package).to_string()
+    }
+
+    /// Record that `package` inherited `option` from `parent`
+    fn inherit(&mut self, package: &str, option: &str, parent: &str) {
+        let mut chain =
+            self.chain(parent, option).map(<[_]>::to_vec).unwrap_or_default();
+
+        chain.push(parent.to_string());
+
+        self.0.insert((package.to_string(), option.to_string()), chain);
+    }
+}
+
+/// Propagates defaults using a set of lattices.
+pub struct Propagator {
+    lattices: Vec<Box<dyn Lattice>>,
+}
+
+impl Default for Propagator {
+    /// The [`TargetLattice`] and [`Ordered::build_type`] lattices, with every
+    /// other option merged with [`Exact`].
+    fn default() -> Self {
+        Self::new().with(TargetLattice).with(Ordered::build_type())
+    }
+}
+
+impl Propagator {
+    /// A propagator which merges every option with [`Exact`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self { lattices: Vec::new() }
+    }
+
+    /// Add a lattice. Lattices added earlier take priority when more than
+    /// one governs an option.
+    #[must_use]
+    pub fn with(mut self, lattice: impl Lattice + 'static) -> Self {
+        self.lattices.push(Box::new(lattice));
+        self
+    }
+
+    /// The lattice governing `option`
+    #[must_use]
+    pub fn lattice(&self, option: &str) -> &dyn Lattice {
+        self.lattices
+            .iter()
+            .find(|l| l.governs(option))
+            .map_or(&Exact as &dyn Lattice, AsRef::as_ref)
+    }
+
+    /// Propagate the defaults of every package in `graph` to its
+    /// dependencies, returning where each inherited value came from.
+    ///
+    /// # Errors
+    /// Errors if the graph contains a cycle, or two inherited values of an
+    /// option conflict.
+    pub fn run(
+        &self,
+        graph: &mut PackageDiGraph,
+    ) -> Result<Origins, Box<SolverError>> {
+        let mut origins = Origins::default();
+
+        let sorted = toposort(&*graph, None).map_err(SolverError::Cycle)?;
+
+        for idx in sorted {
+            let src_name = graph[idx].name.clone();
+
+            let mut src_defaults = graph[idx]
+                .set_defaults
+                .iter()
+                .filter_map(|(opt, val)| Some((opt.clone(), val.clone()?)))
+                .collect::<Vec<_>>();
+
+            // Sorted so the same conflict is reported on every run
+            src_defaults.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            tracing::info!("propagating default values for {src_name}");
+
+            let deps = graph
+                .edges_directed(idx, Direction::Outgoing)
+                .map(|e| e.target())
+                .collect::<Vec<_>>();
+
+            for dep in deps {
+                let dep = &mut graph[dep];
+
+                for (opt_name, src_val) in &src_defaults {
+                    match dep.set_defaults.get(opt_name) {
+                        Some(Some(old_val)) => {
+                            // Values set by the dependency itself always win
+                            let Some(first) = origins.get(&dep.name, opt_name)
+                            else {
+                                continue;
+                            };
+
+                            let lattice = self.lattice(opt_name);
+
+                            let Some(merged) = lattice.merge(old_val, src_val)
+                            else {
+                                tracing::error!(
+                                    "conflicting default values detected"
+                                );
+
+                                return Err(Box::new(
+                                    SolverError::DefaultConflict {
+                                        package_name: dep.name.clone(),
+                                        default_name: opt_name.clone(),
+                                        first_setter: first.to_string(),
+                                        first_value: old_val.clone(),
+                                        conflict_setter: origins
+                                            .source(&src_name, opt_name),
+                                        conflict_value: src_val.clone(),
+                                    },
+                                ));
+                            };
+
+                            if &merged != old_val {
+                                tracing::info!(
+                                    "merged {}:{opt_name} to {merged} with \
+                                     the {} lattice",
+                                    dep.name,
+                                    lattice.name()
+                                );
+
+                                dep.set_defaults
+                                    .insert(opt_name.clone(), Some(merged));
+                            }
+                        }
+
+                        Some(None) => {
+                            dep.set_defaults.remove(opt_name);
+                        }
+
+                        None => {
+                            tracing::info!(
+                                "propagating default value \
+                                 {src_name}:{opt_name} to {}",
+                                dep.name
+                            );
+
+                            dep.set_defaults.insert(
+                                opt_name.clone(),
+                                Some(src_val.clone()),
+                            );
+
+                            origins.inherit(&dep.name, opt_name, &src_name);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(origins)
+    }
+}
@@ -0,0 +1,332 @@
+//! Versions discovered from remote indexes.
+//!
+//! Rather than hard-coding its versions, a package may declare a
+//! [`VersionSource`], such as a project's GitHub releases or its page on the
+//! Python Package Index.
+//! When repositories are loaded, the versions listed by each source are added
+//! to the package's outline as a version domain, so the solver only selects
+//! versions which actually exist.
+//!
+//! Listing versions requires network access, so results are stored in a
+//! [`VersionCache`] and reused until they expire. If a source cannot be
+//! queried, for example when offline, an expired entry is used instead. If
+//! there is no entry at all, the package's version domain is left as declared
+//! and a warning is logged.
+//!
+//! Custom sources can be written in Rust by implementing [`VersionProvider`].
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fetch::{FetchError, Fetcher},
+    package::{outline::PackageOutline, version::Version},
+    util::paths,
+};
+
+/// How long listed versions are cached before the source is queried again:
+/// one day
+pub const VERSION_CACHE_TTL: Duration = Duration::from_secs(86_400);
+
+/// A source of the versions available for a package.
+pub trait VersionProvider {
+    /// Uniquely identifies the index being queried. Providers with the same
+    /// key share a cache entry
+    fn cache_key(&self) -> String;
+
+    /// List every available version.
+    ///
+    /// # Errors
+    /// Errors if the index cannot be fetched or is invalid.
+    fn list_versions(
+        &self,
+        fetcher: &Fetcher,
+    ) -> Result<Vec<Version>, FetchError>;
+}
+
+/// The built-in [`VersionProvider`]s, which can be declared on a
+/// [`PackageOutline`].
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VersionSource {
+    /// The tags of a GitHub repository's published releases, with
+    /// `tag_prefix` (such as `v`) removed
+    GithubReleases { owner: String, repo: String, tag_prefix: String },
+
+    /// The releases of a project on the Python Package Index
+    Pypi { project: String },
+}
+
+impl VersionSource {
+    fn url(&self) -> String {
+        match self {
+            Self::GithubReleases { owner, repo, .. } => format!(
+                "https://api.github.com/repos/{owner}/{repo}/releases?per_page=100"
+            ),
+            Self::Pypi { project } => {
+                format!("https://pypi.org/pypi/{project}/json")
+            }
+        }
+    }
+}
+
+/// Parse the versions in `names`, skipping any which are not valid versions.
+fn parse_versions<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> Vec<Version> {
+    names
+        .into_iter()
+        .filter_map(|name| match Version::new(name) {
+            Ok(version) => Some(version),
+            Err(e) => {
+                tracing::debug!("ignoring invalid version '{name}': {e:?}");
+                None
+            }
+        })
+        .collect()
+}
+
+impl VersionProvider for VersionSource {
+    fn cache_key(&self) -> String {
+        self.url()
+    }
+
+    fn list_versions(
+        &self,
+        fetcher: &Fetcher,
+    ) -> Result<Vec<Version>, FetchError> {
+        let url = self.url();
+        let json = fetcher.get_json(&url)?;
+
+        let invalid = || FetchError::InvalidResponse {
+            url: url.clone(),
+            error: "unexpected layout".into(),
+        };
+
+        match self {
+            Self::GithubReleases { tag_prefix, .. } => {
+                let releases = json.as_array().ok_or_else(invalid)?;
+
+                Ok(parse_versions(
+                    releases
+                        .iter()
+                        .filter(|r| r["draft"] != true)
+                        .filter_map(|r| r["tag_name"].as_str())
+                        .map(|tag| {
+                            tag.strip_prefix(tag_prefix.as_str()).unwrap_or(tag)
+                        }),
+                ))
+            }
+
+            Self::Pypi { .. } => {
+                let releases =
+                    json["releases"].as_object().ok_or_else(invalid)?;
+
+                // Releases without any files cannot be installed
+                Ok(parse_versions(
+                    releases
+                        .iter()
+                        .filter(|(_, files)| {
+                            files.as_array().is_some_and(|f| !f.is_empty())
+                        })
+                        .map(|(name, _)| name.as_str()),
+                ))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GithubReleases { owner, repo, .. } => {
+                write!(f, "GithubReleases({owner}/{repo})")
+            }
+            Self::Pypi { project } => write!(f, "Pypi({project})"),
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl VersionSource {
+    /// The releases of `owner/repo` on GitHub
+    #[staticmethod]
+    #[pyo3(signature = (owner, repo, tag_prefix = "v"))]
+    fn github(owner: &str, repo: &str, tag_prefix: &str) -> Self {
+        Self::GithubReleases {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            tag_prefix: tag_prefix.to_string(),
+        }
+    }
+
+    /// The releases of `project` on the Python Package Index
+    #[staticmethod]
+    fn pypi(project: &str) -> Self {
+        Self::Pypi { project: project.to_string() }
+    }
+
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    fetched: SystemTime,
+    versions: Vec<Version>,
+}
+
+/// On-disk cache of the versions listed by [`VersionProvider`]s.
+pub struct VersionCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl Default for VersionCache {
+    fn default() -> Self {
+        Self::new(paths::cache_dir().join("versions"), VERSION_CACHE_TTL)
+    }
+}
+
+impl VersionCache {
+    /// A cache in `dir` whose entries expire after `ttl`. A `ttl` of zero
+    /// always queries the provider, only falling back to the cache if that
+    /// fails.
+    #[must_use]
+    pub const fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// This cache with entries expiring after `ttl` instead
+    #[must_use]
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        let key = blake3::hash(key.as_bytes());
+        self.dir.join(format!("{}.json", &key.to_hex()[..32]))
+    }
+
+    fn read_entry(path: &Path) -> Option<Entry> {
+        let txt = std::fs::read_to_string(path).ok()?;
+
+        serde_json::from_str(&txt)
+            .inspect_err(|e| {
+                tracing::warn!("ignoring invalid version cache entry: {e}");
+            })
+            .ok()
+    }
+
+    fn write_entry(&self, path: &Path, entry: &Entry) -> std::io::Result<()> {
+        let tmp = path.with_extension("tmp");
+
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// The versions listed by `provider`, from the cache if they have not
+    /// expired.
+    ///
+    /// If the provider fails, expired versions are returned instead. Failing
+    /// to write the cache is not an error.
+    ///
+    /// # Errors
+    /// Errors if the provider fails and nothing is cached.
+    pub fn get_or_fetch(
+        &self,
+        provider: &dyn VersionProvider,
+        fetcher: &Fetcher,
+    ) -> Result<Vec<Version>, FetchError> {
+        let key = provider.cache_key();
+        let path = self.entry_path(&key);
+        let cached = Self::read_entry(&path);
+
+        if let Some(entry) = &cached
+            && entry.fetched.elapsed().is_ok_and(|age| age < self.ttl)
+        {
+            tracing::info!("using cached versions for '{key}'");
+            return Ok(entry.versions.clone());
+        }
+
+        match provider.list_versions(fetcher) {
+            Ok(versions) => {
+                let entry = Entry { fetched: SystemTime::now(), versions };
+
+                if let Err(e) = self.write_entry(&path, &entry) {
+                    tracing::warn!("failed to write version cache: {e}");
+                }
+
+                Ok(entry.versions)
+            }
+
+            Err(e) => match cached {
+                Some(entry) => {
+                    tracing::warn!(
+                        "failed to list versions from '{key}', using \
+                         expired cache: {e}"
+                    );
+                    Ok(entry.versions)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Remove every cached entry.
+    ///
+    /// # Errors
+    /// Errors if the cache directory exists but cannot be removed.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+}
+
+/// Add the versions listed by each outline's [`VersionSource`] to its
+/// version domain.
+///
+/// Sources which cannot be queried and have nothing cached are skipped with a
+/// warning, leaving the package's versions unconstrained by the source.
+pub fn discover_versions(
+    outlines: &mut [PackageOutline],
+    cache: &VersionCache,
+    fetcher: &Fetcher,
+) {
+    for outline in outlines {
+        let Some(source) = &outline.version_source else {
+            continue;
+        };
+
+        match cache.get_or_fetch(source, fetcher) {
+            Ok(versions) if versions.is_empty() => {
+                tracing::warn!("{source} lists no versions for {outline}");
+            }
+
+            Ok(versions) => outline.push_version_domain(versions),
+
+            Err(e) => {
+                tracing::warn!(
+                    "failed to list versions of {outline} from {source}: {e}"
+                );
+            }
+        }
+    }
+}
@@ -1,31 +1,43 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     str::FromStr,
+    sync::Arc,
 };
 
 use crate::{
     package::{
-        BuiltRegistry,
+        BuiltRegistry, names,
         outline::SolverError,
+        resolver::Conflict,
         version::{self, Part, Version},
     },
-    spec,
+    spec::{self, platform::PlatformKey},
+    util::{
+        intern::{Interner, Symbol},
+        suggest,
+    },
 };
 
 #[derive(Debug, Default, Clone)]
-pub struct Registry<'a, VersionRegistryType> {
-    // // Tracking variables for better error messages and debug information
-    // current_package: Option<&'a str>,
-    // current_option: Option<&'a str>,
+pub struct Registry<VersionRegistryType> {
+    // Each tracked constraint, indexed by its ID
+    tracked: Vec<Conflict>,
 
-    // Map from constraint ID to human-readable description
-    constraint_descriptions: HashMap<String, String>,
-    constraint_id: usize,
+    // Package and option names
+    names: Interner<Arc<str>>,
 
     // Lookup tables for type checking and solver generation
-    spec_option_map: HashMap<(&'a str, Option<&'a str>), usize>,
+    spec_option_map: HashMap<(Symbol, Option<Symbol>), usize>,
+    spec_option_keys: Vec<(Symbol, Option<Symbol>)>,
     spec_options: Vec<(spec::SpecOptionType, Option<z3::ast::Dynamic>)>,
 
+    // Platform facts referenced by constraints
+    platform_facts: BTreeMap<(PlatformKey, String), z3::ast::Bool>,
+
+    // Clauses of built-in constraints, keyed by their canonical syntax, so
+    // repeated constraints and subexpressions share one solver term
+    clauses: HashMap<String, Vec<z3::ast::Dynamic>>,
+
     version_registry: VersionRegistryType,
 }
 
@@ -37,13 +49,16 @@ pub struct WipVersionRegistry {
 #[derive(Debug, Clone)]
 pub struct BuiltVersionRegistry {
     offset: usize,
-    string_to_id: HashMap<String, usize>,
-    id_to_string: HashMap<usize, String>,
+
+    /// String version components. The index of each symbol is the value of
+    /// the component in the solver
+    strings: Interner<String>,
 
     /// Alternates between values and separators. Values are always integers
     /// and separators are always strings.
     ///
-    /// E.g. 1.2.3 => [1, ".", 2, ".", 3] (ignoring string versions)
+    /// E.g. 1.2.3 => [0, ":", 1, ".", 2, ".", 3] (ignoring string versions),
+    /// where the leading 0 is the epoch
     versions: HashMap<usize, usize>,
     solver_vars: Vec<Vec<z3::ast::Dynamic>>,
     current_id: usize,
@@ -76,7 +91,13 @@ impl WipVersionRegistry {
         self,
         versions: HashMap<usize, usize>,
     ) -> BuiltVersionRegistry {
-        let mut strings: Vec<String> = self.strings.into_iter().collect();
+        // Strings with a static order are appended afterwards, so they must
+        // not also be interned at their lexicographic position
+        let mut strings: Vec<String> = self
+            .strings
+            .into_iter()
+            .filter(|s| !version::STATIC_STRING_VERSIONS.contains(&s.as_str()))
+            .collect();
         strings.sort();
         strings.extend(
             version::STATIC_STRING_VERSIONS
@@ -85,20 +106,17 @@ impl WipVersionRegistry {
         );
 
         let offset = strings.len();
-        let mut string_to_id = HashMap::with_capacity(offset);
-        let mut id_to_string = HashMap::with_capacity(offset);
+        let mut interner = Interner::new();
 
-        strings.into_iter().enumerate().for_each(|(idx, v)| {
-            string_to_id.insert(v.clone(), idx);
-            id_to_string.insert(idx, v);
-        });
+        for string in strings {
+            interner.intern(string);
+        }
 
         let num_versions = versions.len();
         let solver_vars = (0..num_versions).map(|_| Vec::new()).collect();
 
         let mut res = BuiltVersionRegistry {
-            string_to_id,
-            id_to_string,
+            strings: interner,
             offset,
             versions,
             solver_vars,
@@ -116,13 +134,13 @@ impl WipVersionRegistry {
 
 impl BuiltVersionRegistry {
     #[must_use]
-    pub fn lookup_str(&self, txt: &str) -> Option<&usize> {
-        self.string_to_id.get(txt)
+    pub fn lookup_str(&self, txt: &str) -> Option<usize> {
+        self.strings.get(txt).map(Symbol::index)
     }
 
     #[must_use]
-    pub fn lookup_id(&self, id: &usize) -> Option<&String> {
-        self.id_to_string.get(id)
+    pub fn lookup_id(&self, id: usize) -> Option<&str> {
+        self.strings.resolve_index(id).map(String::as_str)
     }
 
     #[must_use]
@@ -142,7 +160,7 @@ impl BuiltVersionRegistry {
             }
 
             Part::Str(s) => z3::ast::Int::from_u64(
-                *self.lookup_str(&s).expect("Internal solver error") as u64,
+                self.lookup_str(&s).expect("Internal solver error") as u64,
             )
             .into(),
 
@@ -159,15 +177,14 @@ impl BuiltVersionRegistry {
         if int >= self.offset() {
             version::Part::Int(int - self.offset())
         } else {
-            version::Part::Str(self.lookup_id(&int).unwrap().clone())
+            version::Part::Str(self.lookup_id(int).unwrap().to_string())
         }
     }
 
     pub fn next_id_name(&mut self) -> String {
-        // TODO: Ensure names cannot collided
         let old = self.current_id;
         self.current_id += 1;
-        format!("version_component_{old}")
+        names::version_component(old)
     }
 
     /// Expand a solver variable collection to hold `parts` components.
@@ -189,7 +206,10 @@ impl BuiltVersionRegistry {
             current_parts += 1;
         }
 
-        println!("Resized: {:?}", self.solver_vars[idx]);
+        tracing::debug!(
+            "resized solver variables to {:?}",
+            self.solver_vars[idx]
+        );
     }
 
     #[must_use]
@@ -198,78 +218,143 @@ impl BuiltVersionRegistry {
     }
 }
 
-impl<'a> Registry<'a, WipVersionRegistry> {
+impl Registry<WipVersionRegistry> {
     #[must_use]
-    pub fn build(self) -> Registry<'a, BuiltVersionRegistry> {
+    pub fn build(self) -> Registry<BuiltVersionRegistry> {
         let mut versions = HashMap::new();
         let mut count = 0;
 
-        for idx in self.spec_option_map.values() {
-            if matches!(
-                self.spec_options[*idx].0,
-                spec::SpecOptionType::Version
-            ) {
-                versions.insert(*idx, count);
+        // Number versions in declaration order rather than hash order, so the
+        // solver variables are the same between runs
+        for (idx, (option_type, _)) in self.spec_options.iter().enumerate() {
+            if matches!(option_type, spec::SpecOptionType::Version) {
+                versions.insert(idx, count);
                 count += 1;
             }
         }
 
         Registry {
-            constraint_descriptions: self.constraint_descriptions,
-            constraint_id: self.constraint_id,
+            tracked: self.tracked,
+            names: self.names,
             spec_option_map: self.spec_option_map,
+            spec_option_keys: self.spec_option_keys,
             spec_options: self.spec_options,
+            platform_facts: self.platform_facts,
+            clauses: HashMap::new(),
             version_registry: self.version_registry.build(versions),
         }
     }
 }
 
-impl Registry<'_, BuiltVersionRegistry> {
+impl Registry<BuiltVersionRegistry> {
     #[must_use]
-    pub fn lookup_version_solver_vars<'b>(
+    pub fn lookup_version_solver_vars(
         &self,
-        package: &'b str,
-        option: Option<&'b str>,
+        package: &str,
+        option: Option<&str>,
     ) -> Option<&[z3::ast::Dynamic]> {
         let idx = self.lookup_option(package, option)?;
         self.version_registry().lookup_solver_vars(idx)
     }
 
-    pub fn expand_version_to_fit<'b>(
+    pub fn expand_version_to_fit(
         &mut self,
-        package: &'b str,
-        option: Option<&'b str>,
+        package: &str,
+        option: Option<&str>,
         parts: usize,
     ) -> Result<(), Box<SolverError>> {
-        let idx = self.lookup_option(package, option).ok_or_else(|| {
-            Box::new(option.map_or_else(
-                || SolverError::MissingPackage { name: package.to_string() },
-                |name| SolverError::MissingVariable {
-                    package: package.to_string(),
-                    name: name.to_string(),
-                },
-            ))
-        })?;
+        let idx = self
+            .lookup_option(package, option)
+            .ok_or_else(|| Box::new(self.missing_error(package, option)))?;
 
+        let len = |reg: &BuiltVersionRegistry| {
+            reg.lookup_solver_vars(idx).map_or(0, <[_]>::len)
+        };
+
+        let before = len(&self.version_registry);
         self.version_registry.expand_to_fit(idx, parts);
 
+        // Clauses built from the old variables do not cover the new
+        // components, so must be rebuilt
+        if len(&self.version_registry) != before {
+            self.clauses.clear();
+        }
+
         Ok(())
     }
+
+    /// The clauses previously built for the constraint written as `key`
+    #[must_use]
+    pub fn cached_clauses(&self, key: &str) -> Option<&[z3::ast::Dynamic]> {
+        self.clauses.get(key).map(Vec::as_slice)
+    }
+
+    /// Remember the clauses built for the constraint written as `key`
+    pub fn cache_clauses(
+        &mut self,
+        key: String,
+        clauses: Vec<z3::ast::Dynamic>,
+    ) {
+        self.clauses.insert(key, clauses);
+    }
 }
 
-impl<'a, T> Registry<'a, T> {
+impl<T> Registry<T> {
+    /// The error for a package or option which does not exist, suggesting a
+    /// similarly named one which does.
+    #[must_use]
+    pub fn missing_error(
+        &self,
+        package: &str,
+        option: Option<&str>,
+    ) -> SolverError {
+        let keys = self.spec_option_names().into_iter();
+
+        let package_exists =
+            keys.clone().any(|(p, o)| p == package && o.is_none());
+
+        match option {
+            Some(name) if package_exists => SolverError::MissingVariable {
+                package: package.to_string(),
+                name: name.to_string(),
+                suggestion: suggest::closest(
+                    name,
+                    keys.filter(|(p, _)| *p == package).filter_map(|(_, o)| o),
+                )
+                .map(str::to_string),
+            },
+            _ => SolverError::MissingPackage {
+                name: package.to_string(),
+                suggestion: suggest::closest(
+                    package,
+                    keys.filter(|(_, o)| o.is_none()).map(|(p, _)| p),
+                )
+                .map(str::to_string),
+            },
+        }
+    }
+
+    /// The index of `package:option`, or of the package's activation toggle
+    /// if `option` is `None`.
     pub fn lookup_option(
         &self,
-        package: &'a str,
-        option: Option<&'a str>,
+        package: &str,
+        option: Option<&str>,
     ) -> Option<usize> {
+        let package = self.names.get(package)?;
+
+        let option = match option {
+            Some(option) => Some(self.names.get(option)?),
+            None => None,
+        };
+
         self.spec_option_map.get(&(package, option)).copied()
     }
 
     pub fn insert_option_type(
         &mut self,
-        package: &'a str,
-        option: Option<&'a str>,
+        package: &str,
+        option: Option<&str>,
         dtype: spec::SpecOptionType,
     ) -> Result<(), Box<SolverError>> {
         self.insert_option(package, option, dtype, None)
@@ -277,22 +362,14 @@ impl<'a, T> Registry<'a, T> {
 
     pub fn set_option_value(
         &mut self,
-        package: &'a str,
-        option: Option<&'a str>,
+        package: &str,
+        option: Option<&str>,
         value: z3::ast::Dynamic,
     ) -> Result<(), Box<SolverError>> {
         let Some(idx) = self.lookup_option(package, option) else {
             tracing::error!("Option {package}:{option:?} does not exist");
 
-            return Err(match option {
-                Some(name) => Box::new(SolverError::MissingVariable {
-                    package: package.to_string(),
-                    name: name.to_string(),
-                }),
-                None => Box::new(SolverError::MissingPackage {
-                    name: package.to_string(),
-                }),
-            });
+            return Err(Box::new(self.missing_error(package, option)));
         };
 
         if self.spec_options[idx].1.is_some() {
@@ -308,27 +385,88 @@ impl<'a, T> Registry<'a, T> {
 
     pub fn insert_option(
         &mut self,
-        package: &'a str,
-        option: Option<&'a str>,
+        package: &str,
+        option: Option<&str>,
         dtype: spec::SpecOptionType,
         value: Option<z3::ast::Dynamic>,
     ) -> Result<(), Box<SolverError>> {
         tracing::info!("Inserting {package}:{option:?} with type {dtype:?}");
 
         if let Some(idx) = self.lookup_option(package, option) {
-            // TODO: Proper error handling
-            assert!(self.spec_options[idx].0 == dtype, "Conflicting datatypes");
+            return self.reinsert_option(idx, package, option, dtype, value);
         }
 
-        let idx = self.spec_option_map.len();
-        self.spec_option_map.insert((package, option), idx);
+        let key = (
+            self.names.intern_str(package),
+            option.map(|option| self.names.intern_str(option)),
+        );
+
+        let idx = self.spec_options.len();
+        self.spec_option_map.insert(key, idx);
+        self.spec_option_keys.push(key);
         self.spec_options.push((dtype, value));
 
         Ok(())
     }
 
-    pub fn spec_option_names(&self) -> Vec<&(&'a str, Option<&'a str>)> {
-        self.spec_option_map.keys().collect()
+    /// Insert the package or option at `idx` again, which keeps its solver
+    /// variable.
+    ///
+    /// A variable of unknown type takes the type `dtype`. It is an error for
+    /// the types to differ otherwise, or to give a second solver variable.
+    fn reinsert_option(
+        &mut self,
+        idx: usize,
+        package: &str,
+        option: Option<&str>,
+        dtype: spec::SpecOptionType,
+        value: Option<z3::ast::Dynamic>,
+    ) -> Result<(), Box<SolverError>> {
+        use spec::SpecOptionType::Unknown;
+
+        let (current, existing) = &mut self.spec_options[idx];
+
+        match (*current, dtype) {
+            (Unknown, _) => *current = dtype,
+            (_, Unknown) => (),
+            (first, second) if first != second => {
+                return Err(Box::new(SolverError::ConflictingOptionType {
+                    package: package.to_string(),
+                    option: option.map(str::to_string),
+                    first,
+                    second,
+                }));
+            }
+            _ => (),
+        }
+
+        match (existing.is_some(), value) {
+            (true, Some(_)) => {
+                Err(Box::new(SolverError::DuplicateOption(option.map_or_else(
+                    || package.to_string(),
+                    |option| format!("{package}:{option}"),
+                ))))
+            }
+            (false, value @ Some(_)) => {
+                *existing = value;
+                Ok(())
+            }
+            (_, None) => Ok(()),
+        }
+    }
+
+    /// The name of every package and option, in the order they were
+    /// inserted.
+    pub fn spec_option_names(&self) -> Vec<(&str, Option<&str>)> {
+        self.spec_option_keys
+            .iter()
+            .map(|(package, option)| {
+                (
+                    &**self.names.resolve(*package),
+                    option.map(|option| &**self.names.resolve(option)),
+                )
+            })
+            .collect()
     }
 
     pub fn spec_options(
@@ -337,6 +475,30 @@ impl<'a, T> Registry<'a, T> {
         &self.spec_options
     }
 
+    /// The solver constant for the platform fact `key == value`, created on
+    /// first use. Its value is fixed by
+    /// [`SpecOutline::push_platform_facts`](crate::package::outline::SpecOutline::push_platform_facts).
+    pub fn platform_fact(
+        &mut self,
+        key: PlatformKey,
+        value: &str,
+    ) -> z3::ast::Bool {
+        self.platform_facts
+            .entry((key, value.to_string()))
+            .or_insert_with(|| {
+                z3::ast::Bool::new_const(names::platform_fact(key, value))
+            })
+            .clone()
+    }
+
+    pub fn platform_facts(
+        &self,
+    ) -> impl Iterator<Item = (PlatformKey, &str, &z3::ast::Bool)> {
+        self.platform_facts
+            .iter()
+            .map(|((key, value), fact)| (*key, value.as_str(), fact))
+    }
+
     pub const fn version_registry(&self) -> &T {
         &self.version_registry
     }
@@ -345,45 +507,74 @@ impl<'a, T> Registry<'a, T> {
         &mut self.version_registry
     }
 
-    pub fn new_constraint_id(&mut self, description: String) -> String {
-        let idx = format!("{}", self.constraint_id);
-        self.constraint_id += 1;
-        self.constraint_descriptions.insert(idx.clone(), description);
+    pub fn new_constraint_id(&mut self, conflict: Conflict) -> String {
+        let idx = self.tracked.len().to_string();
+        self.tracked.push(conflict);
         idx
     }
 
+    /// The number of constraints tracked so far, which is the ID of the next
+    pub const fn num_tracked(&self) -> usize {
+        self.tracked.len()
+    }
+
+    /// Attribute every constraint tracked since ID `start` to `packages`.
+    pub fn attribute_tracked(&mut self, start: usize, packages: &[&str]) {
+        for conflict in self.tracked.iter_mut().skip(start) {
+            conflict.packages =
+                packages.iter().map(ToString::to_string).collect();
+        }
+    }
+
+    /// The constraint tracked by `lit`
+    pub fn tracked_constraint(&self, lit: &z3::ast::Bool) -> Option<&Conflict> {
+        self.tracked_by_name(&lit.to_string())
+    }
+
     pub fn constraint_description(
         &self,
         lit: &z3::ast::Bool,
     ) -> Option<&String> {
-        let name = lit.to_string();
+        self.constraint_description_by_name(&lit.to_string())
+    }
 
-        let id = if name.starts_with('|') {
-            &name[1..name.len() - 1]
-        } else {
-            &name
-        };
+    /// The constraint tracked by the literal printed as `name`, which may be
+    /// quoted as `|name|`
+    fn tracked_by_name(&self, name: &str) -> Option<&Conflict> {
+        let id = name
+            .strip_prefix('|')
+            .and_then(|n| n.strip_suffix('|'))
+            .unwrap_or(name);
 
-        self.constraint_descriptions.get(id)
+        self.tracked.get(id.parse::<usize>().ok()?)
+    }
+
+    /// The description of the constraint tracked by the literal printed as
+    /// `name`, which may be quoted as `|name|`
+    pub fn constraint_description_by_name(
+        &self,
+        name: &str,
+    ) -> Option<&String> {
+        self.tracked_by_name(name).map(|conflict| &conflict.description)
+    }
+
+    /// The literals tracking each constraint, in order of their IDs
+    pub fn constraint_literals(&self) -> Vec<z3::ast::Bool> {
+        (0..self.tracked.len())
+            .map(|id| z3::ast::Bool::new_const(id.to_string()))
+            .collect()
     }
 
     pub fn eval_option(
         &self,
-        package: &'a str,
-        option: Option<&'a str>,
+        package: &str,
+        option: Option<&str>,
         model: &z3::Model,
-        registry: &'a BuiltRegistry,
+        registry: &BuiltRegistry,
     ) -> Result<spec::SpecOptionValue, Box<SolverError>> {
         let idx = self.lookup_option(package, option).ok_or_else(|| {
             tracing::error!("missing option {package}:{option:?}");
-
-            option.map_or_else(
-                || SolverError::MissingPackage { name: package.to_string() },
-                |name| SolverError::MissingVariable {
-                    package: package.to_string(),
-                    name: name.to_string(),
-                },
-            )
+            self.missing_error(package, option)
         })?;
 
         let val = &self.spec_options()[idx];
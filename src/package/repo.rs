@@ -0,0 +1,382 @@
+//! Namespaced package repositories.
+//!
+//! Every package file is loaded as a repository with a namespace, such as
+//! `builtin` or `mysite`. Repositories are searched in priority order, so a
+//! package defined in a higher-priority repository shadows any package with the
+//! same name in a lower-priority one. This lets a site override individual
+//! built-in packages without copying the whole repository.
+//!
+//! Stacks are built from the lowest layer up: the built-in repository, then
+//! site repositories above it, and finally any environment overrides, which
+//! are pushed in front of everything else (see [`RepoStack::push_front`]).
+//! Two stacks can also be merged, with the packages of the first taking
+//! precedence (see [`RepoStack::merge`]). Every package hidden by another is
+//! reported by [`RepoStack::shadowed`].
+//!
+//! A spec may name a package with its namespace (`builtin.openmpi`) to pick
+//! that repository's definition regardless of priority. A prefix is only
+//! treated as a namespace if a repository with that namespace was loaded, so
+//! package names containing a `.` still work.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::package::outline::PackageOutline;
+
+/// Where to load a repository from and the namespace to give it.
+///
+/// Written as `NAMESPACE=PATH`, or just `PATH`, in which case the namespace is
+/// the file stem of the path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Repository {
+    pub namespace: String,
+    pub path: PathBuf,
+}
+
+/// The packages of every loaded repository, highest priority first.
+#[derive(Clone, Debug, Default)]
+pub struct RepoStack {
+    repos: Vec<(String, Vec<PackageOutline>)>,
+}
+
+/// A definition of a package hidden by one in a higher-priority repository.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Shadowed {
+    pub name: String,
+
+    /// The namespace of the hidden definition
+    pub namespace: String,
+
+    /// The namespace of the definition used instead
+    pub by: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoError {
+    InvalidNamespace(String),
+    DuplicateNamespace(String),
+
+    DuplicatePackage { namespace: String, name: String },
+
+    UnknownPackage { namespace: String, name: String },
+
+    ConflictingNamespaces { name: String, first: String, second: String },
+}
+
+impl std::fmt::Display for RepoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidNamespace(namespace) => {
+                write!(f, "invalid repository namespace '{namespace}'")
+            }
+            Self::DuplicateNamespace(namespace) => {
+                write!(
+                    f,
+                    "more than one repository has namespace '{namespace}'"
+                )
+            }
+            Self::DuplicatePackage { namespace, name } => write!(
+                f,
+                "package '{name}' is defined more than once in '{namespace}'"
+            ),
+            Self::UnknownPackage { namespace, name } => {
+                write!(f, "repository '{namespace}' has no package '{name}'")
+            }
+            Self::ConflictingNamespaces { name, first, second } => write!(
+                f,
+                "'{name}' was requested from both '{first}' and '{second}'"
+            ),
+        }
+    }
+}
+
+/// Whether `namespace` can be used as a repository namespace.
+fn is_valid_namespace(namespace: &str) -> bool {
+    !namespace.is_empty()
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+impl FromStr for Repository {
+    type Err = RepoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (namespace, path) =
+            if let Some((namespace, path)) = s.split_once('=') {
+                (namespace.to_string(), PathBuf::from(path))
+            } else {
+                let path = PathBuf::from(s);
+                let stem = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                (stem, path)
+            };
+
+        if !is_valid_namespace(&namespace) {
+            return Err(RepoError::InvalidNamespace(namespace));
+        }
+
+        Ok(Self { namespace, path })
+    }
+}
+
+impl TryFrom<String> for Repository {
+    type Error = RepoError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Repository> for String {
+    fn from(value: Repository) -> Self {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for Repository {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.namespace, self.path.display())
+    }
+}
+
+impl RepoStack {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a repository with a lower priority than every repository already
+    /// added. Each outline's namespace is set to `namespace`.
+    ///
+    /// # Errors
+    /// Errors if the namespace is invalid or already used, or the repository
+    /// defines a package more than once.
+    pub fn push(
+        &mut self,
+        namespace: &str,
+        outlines: Vec<PackageOutline>,
+    ) -> Result<(), RepoError> {
+        let outlines = self.check(namespace, outlines)?;
+        self.repos.push((namespace.to_string(), outlines));
+
+        Ok(())
+    }
+
+    /// Add a repository with a higher priority than every repository already
+    /// added, such as the overrides of an environment. Each outline's
+    /// namespace is set to `namespace`.
+    ///
+    /// # Errors
+    /// Errors as in [`Self::push`].
+    pub fn push_front(
+        &mut self,
+        namespace: &str,
+        outlines: Vec<PackageOutline>,
+    ) -> Result<(), RepoError> {
+        let outlines = self.check(namespace, outlines)?;
+        self.repos.insert(0, (namespace.to_string(), outlines));
+
+        Ok(())
+    }
+
+    /// Add every repository of `other` with a lower priority than every
+    /// repository already added, keeping their order.
+    ///
+    /// # Errors
+    /// Errors if `other` has a namespace this stack already uses, in which
+    /// case neither stack's repositories are changed.
+    pub fn merge(&mut self, other: Self) -> Result<(), RepoError> {
+        if let Some(namespace) =
+            other.namespaces().find(|ns| self.namespaces().any(|n| n == *ns))
+        {
+            tracing::error!("duplicate repository namespace '{namespace}'");
+            return Err(RepoError::DuplicateNamespace(namespace.to_string()));
+        }
+
+        self.repos.extend(other.repos);
+
+        Ok(())
+    }
+
+    /// Check a repository can be added, and set each outline's namespace.
+    fn check(
+        &self,
+        namespace: &str,
+        mut outlines: Vec<PackageOutline>,
+    ) -> Result<Vec<PackageOutline>, RepoError> {
+        if !is_valid_namespace(namespace) {
+            tracing::error!("invalid repository namespace '{namespace}'");
+            return Err(RepoError::InvalidNamespace(namespace.to_string()));
+        }
+
+        if self.namespaces().any(|ns| ns == namespace) {
+            tracing::error!("duplicate repository namespace '{namespace}'");
+            return Err(RepoError::DuplicateNamespace(namespace.to_string()));
+        }
+
+        let mut seen = HashSet::new();
+
+        for outline in &mut outlines {
+            if !seen.insert(outline.name.clone()) {
+                tracing::error!(
+                    "package '{}' defined twice in '{namespace}'",
+                    outline.name
+                );
+
+                return Err(RepoError::DuplicatePackage {
+                    namespace: namespace.to_string(),
+                    name: outline.name.clone(),
+                });
+            }
+
+            outline.namespace = Some(namespace.to_string());
+        }
+
+        Ok(outlines)
+    }
+
+    /// Namespaces of every repository, highest priority first.
+    pub fn namespaces(&self) -> impl Iterator<Item = &str> {
+        self.repos.iter().map(|(namespace, _)| namespace.as_str())
+    }
+
+    /// Split a possibly qualified package name into its namespace and name.
+    #[must_use]
+    pub fn split<'a>(&self, name: &'a str) -> (Option<&'a str>, &'a str) {
+        match name.split_once('.') {
+            Some((namespace, rest))
+                if self.namespaces().any(|ns| ns == namespace) =>
+            {
+                (Some(namespace), rest)
+            }
+            _ => (None, name),
+        }
+    }
+
+    /// Select one definition of every package.
+    ///
+    /// A package is taken from the highest-priority repository defining it,
+    /// unless one of `roots` qualifies it with a namespace. The roots are
+    /// returned with their namespaces removed.
+    ///
+    /// # Errors
+    /// Errors if a qualified root does not exist in the given repository, or
+    /// the same package is qualified with two different namespaces.
+    pub fn resolve(
+        &self,
+        roots: &[String],
+    ) -> Result<(Vec<PackageOutline>, Vec<String>), RepoError> {
+        let mut pinned: HashMap<&str, &str> = HashMap::new();
+        let mut names = Vec::with_capacity(roots.len());
+
+        for root in roots {
+            let (namespace, name) = self.split(root);
+            names.push(name.to_string());
+
+            let Some(namespace) = namespace else { continue };
+
+            if let Some(first) = pinned.insert(name, namespace)
+                && first != namespace
+            {
+                tracing::error!(
+                    "'{name}' requested from '{first}' and '{namespace}'"
+                );
+
+                return Err(RepoError::ConflictingNamespaces {
+                    name: name.to_string(),
+                    first: first.to_string(),
+                    second: namespace.to_string(),
+                });
+            }
+        }
+
+        for (name, namespace) in &pinned {
+            let exists = self.repos.iter().any(|(ns, outlines)| {
+                ns == namespace && outlines.iter().any(|o| o.name == *name)
+            });
+
+            if !exists {
+                tracing::error!("'{namespace}' has no package '{name}'");
+
+                return Err(RepoError::UnknownPackage {
+                    namespace: (*namespace).to_string(),
+                    name: (*name).to_string(),
+                });
+            }
+        }
+
+        Ok((self.select(&pinned).0, names))
+    }
+
+    /// The definition of every package to use, and every definition hidden
+    /// by it.
+    fn select(
+        &self,
+        pinned: &HashMap<&str, &str>,
+    ) -> (Vec<PackageOutline>, Vec<Shadowed>) {
+        let mut chosen = HashMap::new();
+        let mut res = Vec::new();
+
+        for (namespace, outlines) in &self.repos {
+            for outline in outlines {
+                let wanted = pinned
+                    .get(outline.name.as_str())
+                    .is_none_or(|ns| ns == namespace);
+
+                if wanted && !chosen.contains_key(outline.name.as_str()) {
+                    chosen.insert(outline.name.as_str(), namespace.as_str());
+                    res.push(outline.clone());
+                }
+            }
+        }
+
+        let mut shadowed = Vec::new();
+
+        for (namespace, outlines) in &self.repos {
+            for outline in outlines {
+                let Some(by) = chosen.get(outline.name.as_str()) else {
+                    continue;
+                };
+
+                if by != namespace {
+                    tracing::info!(
+                        "{namespace}.{} is shadowed by {by}.{}",
+                        outline.name,
+                        outline.name
+                    );
+
+                    shadowed.push(Shadowed {
+                        name: outline.name.clone(),
+                        namespace: namespace.clone(),
+                        by: (*by).to_string(),
+                    });
+                }
+            }
+        }
+
+        (res, shadowed)
+    }
+
+    /// Every package visible without namespace qualification.
+    #[must_use]
+    pub fn outlines(&self) -> Vec<PackageOutline> {
+        self.select(&HashMap::new()).0
+    }
+
+    /// Every definition hidden by one with the same name in a
+    /// higher-priority repository, in priority order.
+    #[must_use]
+    pub fn shadowed(&self) -> Vec<Shadowed> {
+        self.select(&HashMap::new()).1
+    }
+}
@@ -0,0 +1,750 @@
+//! A small conflict-driven clause learning (CDCL) SAT solver.
+//!
+//! Problems produced by package resolution are large but easy: almost every
+//! variable is fixed by unit propagation once the roots are activated. This
+//! solver therefore keeps to the essentials, namely two watched literals,
+//! first-UIP clause learning and non-chronological backjumping, and makes
+//! decisions in a fixed order. Each variable is created with a preferred
+//! value, its phase, which is tried first. Variables are decided in the order
+//! they were created, so a caller controls which solution is found by creating
+//! its most important variables first.
+//!
+//! Clauses can be enabled by assumptions, in which case an unsatisfiable
+//! result reports the assumptions responsible for the conflict.
+
+/// A variable, numbered from zero in creation order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Var(u32);
+
+impl Var {
+    const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The literal which is true when this variable is
+    #[must_use]
+    pub const fn pos(self) -> Lit {
+        Lit(self.0 << 1)
+    }
+
+    /// The literal which is true when this variable is false
+    #[must_use]
+    pub const fn neg(self) -> Lit {
+        Lit((self.0 << 1) | 1)
+    }
+}
+
+/// A variable or its negation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lit(u32);
+
+impl Lit {
+    #[must_use]
+    pub const fn var(self) -> Var {
+        Var(self.0 >> 1)
+    }
+
+    /// Whether this is the negation of its variable
+    #[must_use]
+    pub const fn is_neg(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0 ^ 1)
+    }
+}
+
+/// The result of [`Solver::solve`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every variable has a value, available through [`Solver::value`]
+    Sat,
+
+    /// No assignment satisfies the clauses under the assumptions. Holds the
+    /// subset of the assumptions which are in conflict, which is empty if the
+    /// clauses are unsatisfiable on their own
+    Unsat(Vec<Lit>),
+}
+
+/// Counters describing the work done by [`Solver::solve`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub decisions: u64,
+    pub conflicts: u64,
+    pub propagations: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+
+    /// Clauses watching each literal, indexed by literal. A clause is visited
+    /// when its watched literal becomes false
+    watches: Vec<Vec<usize>>,
+
+    /// Clauses with a single literal, asserted when solving starts
+    units: Vec<Lit>,
+
+    /// Whether an empty clause was added
+    empty: bool,
+
+    phase: Vec<bool>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    seen: Vec<bool>,
+
+    trail: Vec<Lit>,
+
+    /// The length of the trail when each decision level started
+    trail_lim: Vec<usize>,
+
+    /// Index of the next literal on the trail to propagate
+    qhead: usize,
+
+    /// No variable before this index is unassigned
+    next_decision: usize,
+
+    counters: Counters,
+}
+
+impl Solver {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a variable which is decided as `phase` unless propagation
+    /// requires otherwise.
+    ///
+    /// # Panics
+    /// Panics if more than `u32::MAX` variables are created
+    pub fn new_var(&mut self, phase: bool) -> Var {
+        let var =
+            Var(u32::try_from(self.phase.len())
+                .expect("too many solver variables"));
+
+        self.phase.push(phase);
+        self.assigns.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.seen.push(false);
+        self.watches.push(Vec::new());
+        self.watches.push(Vec::new());
+
+        var
+    }
+
+    #[must_use]
+    pub const fn num_vars(&self) -> usize {
+        self.phase.len()
+    }
+
+    #[must_use]
+    pub const fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    /// Add a clause, which holds if any of its literals does. A clause may be
+    /// added between calls to [`Self::solve`], which discards the current
+    /// assignment.
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        // Literals fixed by an earlier solve never change, and a new clause
+        // must not watch one which is already false
+        self.backjump(0);
+
+        if lits.iter().any(|&lit| self.value(lit) == Some(true)) {
+            return;
+        }
+
+        let mut lits = lits
+            .iter()
+            .copied()
+            .filter(|&lit| self.value(lit).is_none())
+            .collect::<Vec<_>>();
+        lits.sort_unstable();
+        lits.dedup();
+
+        // A clause containing both a literal and its negation always holds
+        if lits.windows(2).any(|w| w[0].var() == w[1].var()) {
+            return;
+        }
+
+        match lits.as_slice() {
+            [] => self.empty = true,
+            [unit] => self.units.push(*unit),
+            _ => {
+                self.attach(lits);
+            }
+        }
+    }
+
+    fn attach(&mut self, lits: Vec<Lit>) -> usize {
+        let cref = self.clauses.len();
+
+        self.watches[lits[0].index()].push(cref);
+        self.watches[lits[1].index()].push(cref);
+        self.clauses.push(lits);
+
+        cref
+    }
+
+    /// The value of `lit` in the current assignment
+    #[must_use]
+    pub fn value(&self, lit: Lit) -> Option<bool> {
+        self.assigns[lit.var().index()].map(|v| v != lit.is_neg())
+    }
+
+    const fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var().index();
+
+        self.assigns[var] = Some(!lit.is_neg());
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Propagate every literal on the trail, returning a conflicting clause
+    /// if one becomes false.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = !self.trail[self.qhead];
+            self.qhead += 1;
+            self.counters.propagations += 1;
+
+            let mut watchers =
+                std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut keep = 0;
+            let mut conflict = None;
+
+            for i in 0..watchers.len() {
+                let cref = watchers[i];
+
+                if conflict.is_some() {
+                    watchers[keep] = cref;
+                    keep += 1;
+                    continue;
+                }
+
+                let clause = &mut self.clauses[cref];
+
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+
+                let first = clause[0];
+
+                if self.assigns[first.var().index()]
+                    .is_some_and(|v| v != first.is_neg())
+                {
+                    watchers[keep] = cref;
+                    keep += 1;
+                    continue;
+                }
+
+                let replacement = (2..clause.len()).find(|&k| {
+                    let lit = clause[k];
+                    self.assigns[lit.var().index()]
+                        .is_none_or(|v| v != lit.is_neg())
+                });
+
+                if let Some(k) = replacement {
+                    clause.swap(1, k);
+                    let watch = clause[1];
+                    self.watches[watch.index()].push(cref);
+                    continue;
+                }
+
+                watchers[keep] = cref;
+                keep += 1;
+
+                if self.value(first) == Some(false) {
+                    conflict = Some(cref);
+                } else {
+                    self.enqueue(first, Some(cref));
+                }
+            }
+
+            watchers.truncate(keep);
+            self.watches[false_lit.index()] = watchers;
+
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+
+        None
+    }
+
+    /// Derive a clause from the conflict `cref` which is asserting after
+    /// backjumping, returning it with the level to backjump to.
+    fn analyze(&mut self, mut cref: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![Lit(0)];
+        let mut pending = 0;
+        let mut idx = self.trail.len();
+        let mut implied = None;
+
+        loop {
+            // The first literal of a reason clause is the one it implied
+            let skip = usize::from(implied.is_some());
+
+            for k in skip..self.clauses[cref].len() {
+                let lit = self.clauses[cref][k];
+                let var = lit.var().index();
+
+                if self.seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+
+                self.seen[var] = true;
+
+                if self.level[var] == self.decision_level() {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+
+            loop {
+                idx -= 1;
+
+                if self.seen[self.trail[idx].var().index()] {
+                    break;
+                }
+            }
+
+            let lit = self.trail[idx];
+            self.seen[lit.var().index()] = false;
+            implied = Some(lit);
+            pending -= 1;
+
+            if pending == 0 {
+                break;
+            }
+
+            cref = self.reason[lit.var().index()]
+                .expect("implied literal has a reason");
+        }
+
+        learnt[0] = !implied.expect("conflict has a literal at this level");
+
+        for lit in &learnt[1..] {
+            self.seen[lit.var().index()] = false;
+        }
+
+        // The literal with the highest level after the first is watched, so
+        // it is the first to become unassigned
+        let level = |lit: &Lit| self.level[lit.var().index()];
+
+        let Some(k) = (1..learnt.len()).max_by_key(|&k| level(&learnt[k]))
+        else {
+            return (learnt, 0);
+        };
+
+        learnt.swap(1, k);
+        let backjump = level(&learnt[1]);
+
+        (learnt, backjump)
+    }
+
+    /// The assumptions which imply `lit`, which is true, together with
+    /// `failed`, the assumption `lit` falsifies.
+    fn analyze_final(&mut self, lit: Lit, failed: Lit) -> Vec<Lit> {
+        let mut core = vec![failed];
+
+        if self.level[lit.var().index()] == 0 {
+            return core;
+        }
+
+        self.seen[lit.var().index()] = true;
+
+        for idx in (self.trail_lim[0]..self.trail.len()).rev() {
+            let lit = self.trail[idx];
+            let var = lit.var().index();
+
+            if !self.seen[var] {
+                continue;
+            }
+
+            self.seen[var] = false;
+
+            match self.reason[var] {
+                // Only assumptions are decided before the final conflict
+                None => core.push(lit),
+                Some(cref) => {
+                    for &other in &self.clauses[cref][1..] {
+                        if self.level[other.var().index()] > 0 {
+                            self.seen[other.var().index()] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        core
+    }
+
+    fn backjump(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+
+        let start = self.trail_lim[level];
+
+        for lit in self.trail.drain(start..) {
+            let var = lit.var();
+            self.assigns[var.index()] = None;
+            self.reason[var.index()] = None;
+            self.next_decision = self.next_decision.min(var.index());
+        }
+
+        self.trail_lim.truncate(level);
+        self.qhead = start;
+    }
+
+    /// The next decision: the first unassigned variable, in its phase.
+    fn pick(&mut self) -> Option<Lit> {
+        while self.next_decision < self.num_vars() {
+            let idx = self.next_decision;
+
+            if self.assigns[idx].is_none() {
+                let var = Var(u32::try_from(idx).ok()?);
+                return Some(if self.phase[idx] {
+                    var.pos()
+                } else {
+                    var.neg()
+                });
+            }
+
+            self.next_decision += 1;
+        }
+
+        None
+    }
+
+    /// Find an assignment satisfying every clause in which each of the
+    /// `assumptions` is true.
+    pub fn solve(&mut self, assumptions: &[Lit]) -> Outcome {
+        self.backjump(0);
+
+        if self.empty {
+            return Outcome::Unsat(Vec::new());
+        }
+
+        for unit in std::mem::take(&mut self.units) {
+            match self.value(unit) {
+                Some(true) => (),
+                Some(false) => {
+                    self.empty = true;
+                    return Outcome::Unsat(Vec::new());
+                }
+                None => self.enqueue(unit, None),
+            }
+        }
+
+        loop {
+            if let Some(cref) = self.propagate() {
+                self.counters.conflicts += 1;
+
+                if self.decision_level() == 0 {
+                    self.empty = true;
+                    return Outcome::Unsat(Vec::new());
+                }
+
+                let (learnt, level) = self.analyze(cref);
+                self.backjump(level);
+
+                let asserting = learnt[0];
+
+                if learnt.len() == 1 {
+                    self.enqueue(asserting, None);
+                } else {
+                    let cref = self.attach(learnt);
+                    self.enqueue(asserting, Some(cref));
+                }
+
+                continue;
+            }
+
+            let lit = if let Some(&assumption) =
+                assumptions.get(self.decision_level())
+            {
+                match self.value(assumption) {
+                    // Already implied, so the level is left empty
+                    Some(true) => {
+                        self.trail_lim.push(self.trail.len());
+                        continue;
+                    }
+                    Some(false) => {
+                        let core = self.analyze_final(!assumption, assumption);
+                        return Outcome::Unsat(core);
+                    }
+                    None => assumption,
+                }
+            } else {
+                let Some(lit) = self.pick() else {
+                    return Outcome::Sat;
+                };
+
+                self.counters.decisions += 1;
+                lit
+            };
+
+            self.trail_lim.push(self.trail.len());
+            self.enqueue(lit, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(solver: &mut Solver, phase: bool, n: usize) -> Vec<Var> {
+        (0..n).map(|_| solver.new_var(phase)).collect()
+    }
+
+    #[test]
+    fn propagates_without_deciding() {
+        let mut solver = Solver::new();
+        let [x, y, z] = vars(&mut solver, false, 3)[..] else { unreachable!() };
+
+        solver.add_clause(&[x.pos()]);
+        solver.add_clause(&[x.neg(), y.pos()]);
+        solver.add_clause(&[y.neg(), z.pos()]);
+
+        assert_eq!(solver.solve(&[]), Outcome::Sat);
+        assert_eq!(solver.counters().decisions, 0);
+
+        for var in [x, y, z] {
+            assert_eq!(solver.value(var.pos()), Some(true));
+        }
+    }
+
+    #[test]
+    fn decides_in_phase() {
+        let mut solver = Solver::new();
+        let on = solver.new_var(true);
+        let off = solver.new_var(false);
+
+        // Tautologies are dropped, so do not constrain anything
+        solver.add_clause(&[on.neg(), on.pos()]);
+
+        assert_eq!(solver.solve(&[]), Outcome::Sat);
+        assert_eq!(solver.value(on.pos()), Some(true));
+        assert_eq!(solver.value(off.pos()), Some(false));
+    }
+
+    #[test]
+    fn backjumps_past_unrelated_decisions() {
+        let mut solver = Solver::new();
+        let [early, mid, late, bad, forced] = vars(&mut solver, true, 5)[..]
+        else {
+            unreachable!()
+        };
+
+        // Deciding `bad` after `early` forces `forced` both ways
+        solver.add_clause(&[early.neg(), bad.neg(), forced.pos()]);
+        solver.add_clause(&[early.neg(), bad.neg(), forced.neg()]);
+
+        assert_eq!(solver.solve(&[]), Outcome::Sat);
+        assert_eq!(solver.counters().conflicts, 1);
+
+        for var in [early, mid, late, forced] {
+            assert_eq!(solver.value(var.pos()), Some(true));
+        }
+
+        // The learnt clause (~early | ~bad) is asserted at the level of
+        // `early`, not at the level of `late`, the most recent decision
+        assert_eq!(solver.value(bad.pos()), Some(false));
+        assert_eq!(solver.level[bad.index()], 1);
+    }
+
+    #[test]
+    fn reports_conflicting_assumptions() {
+        let mut solver = Solver::new();
+        let [s1, s2, s3, x, y] = vars(&mut solver, false, 5)[..] else {
+            unreachable!()
+        };
+
+        solver.add_clause(&[s1.neg(), x.pos()]);
+        solver.add_clause(&[s2.neg(), x.neg()]);
+        solver.add_clause(&[s3.neg(), y.pos()]);
+
+        let Outcome::Unsat(mut core) =
+            solver.solve(&[s1.pos(), s2.pos(), s3.pos()])
+        else {
+            panic!("expected unsat");
+        };
+
+        core.sort_unstable();
+        assert_eq!(core, [s1.pos(), s2.pos()]);
+
+        // The solver can be reused with other assumptions
+        assert_eq!(solver.solve(&[s1.pos(), s3.pos()]), Outcome::Sat);
+        assert_eq!(solver.value(x.pos()), Some(true));
+        assert_eq!(solver.value(y.pos()), Some(true));
+        assert_eq!(solver.value(s2.pos()), Some(false));
+    }
+
+    #[test]
+    fn adds_clauses_between_solves() {
+        let mut solver = Solver::new();
+        let [x, y, z] = vars(&mut solver, true, 3)[..] else { unreachable!() };
+
+        solver.add_clause(&[x.neg()]);
+        assert_eq!(solver.solve(&[]), Outcome::Sat);
+
+        // `x` is false at level 0, so the clause is effectively `y | z`,
+        // and must not watch `x`
+        solver.add_clause(&[x.pos(), y.neg()]);
+        solver.add_clause(&[x.pos(), y.pos(), z.neg()]);
+
+        assert_eq!(solver.solve(&[]), Outcome::Sat);
+        assert_eq!(solver.value(y.pos()), Some(false));
+        assert_eq!(solver.value(z.pos()), Some(false));
+
+        solver.add_clause(&[x.pos(), z.pos()]);
+        assert_eq!(solver.solve(&[]), Outcome::Unsat(Vec::new()));
+    }
+
+    #[test]
+    fn core_follows_implications() {
+        let mut solver = Solver::new();
+        let [s1, s2, x, y] = vars(&mut solver, false, 4)[..] else {
+            unreachable!()
+        };
+
+        // s1 -> x -> y, and s2 -> ~y
+        solver.add_clause(&[s1.neg(), x.pos()]);
+        solver.add_clause(&[x.neg(), y.pos()]);
+        solver.add_clause(&[s2.neg(), y.neg()]);
+
+        let Outcome::Unsat(mut core) = solver.solve(&[s1.pos(), s2.pos()])
+        else {
+            panic!("expected unsat");
+        };
+
+        core.sort_unstable();
+        assert_eq!(core, [s1.pos(), s2.pos()]);
+    }
+
+    #[test]
+    fn unsat_without_assumptions_has_an_empty_core() {
+        let mut solver = Solver::new();
+        let [x, s] = vars(&mut solver, false, 2)[..] else { unreachable!() };
+
+        solver.add_clause(&[x.pos()]);
+        solver.add_clause(&[x.neg()]);
+
+        assert_eq!(solver.solve(&[s.pos()]), Outcome::Unsat(Vec::new()));
+
+        let mut solver = Solver::new();
+        solver.add_clause(&[]);
+
+        assert_eq!(solver.solve(&[]), Outcome::Unsat(Vec::new()));
+    }
+
+    #[test]
+    fn pigeonhole_is_unsat() {
+        // Three pigeons, each in one of two holes, and no hole holds two
+        let mut solver = Solver::new();
+        let pigeons =
+            (0..3).map(|_| vars(&mut solver, true, 2)).collect::<Vec<_>>();
+
+        for (i, pigeon) in pigeons.iter().enumerate() {
+            solver.add_clause(&[pigeon[0].pos(), pigeon[1].pos()]);
+
+            for other in &pigeons[i + 1..] {
+                for (x, y) in pigeon.iter().zip(other) {
+                    solver.add_clause(&[x.neg(), y.neg()]);
+                }
+            }
+        }
+
+        assert_eq!(solver.solve(&[]), Outcome::Unsat(Vec::new()));
+        assert!(solver.counters().conflicts > 0);
+    }
+
+    /// Whether `clauses` over `n` variables have a satisfying assignment, by
+    /// trying every one
+    fn brute_force(n: u32, clauses: &[Vec<Lit>]) -> bool {
+        (0..1_u32 << n).any(|bits| {
+            clauses.iter().all(|clause| {
+                clause
+                    .iter()
+                    .any(|lit| (bits >> lit.var().0 & 1 == 1) != lit.is_neg())
+            })
+        })
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        const VARS: u32 = 8;
+
+        // A fixed linear congruential generator, so failures reproduce
+        let mut state = 0x2545_f491_u64;
+        let mut next = |bound: u32| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            u32::try_from(state >> 33).unwrap() % bound
+        };
+
+        let mut outcomes = [0; 2];
+
+        for _ in 0..500 {
+            let mut solver = Solver::new();
+            let vars = (0..VARS)
+                .map(|_| solver.new_var(next(2) == 0))
+                .collect::<Vec<_>>();
+
+            let clauses = (0..30 + next(10))
+                .map(|_| {
+                    (0..3)
+                        .map(|_| {
+                            let var = vars[next(VARS) as usize];
+                            if next(2) == 0 { var.pos() } else { var.neg() }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            for clause in &clauses {
+                solver.add_clause(clause);
+            }
+
+            let sat = solver.solve(&[]) == Outcome::Sat;
+            assert_eq!(sat, brute_force(VARS, &clauses), "{clauses:?}");
+
+            if sat {
+                for clause in &clauses {
+                    assert!(
+                        clause
+                            .iter()
+                            .any(|&lit| solver.value(lit) == Some(true)),
+                        "{clause:?} does not hold"
+                    );
+                }
+            }
+
+            outcomes[usize::from(sat)] += 1;
+        }
+
+        // Both outcomes are exercised
+        assert!(outcomes.iter().all(|&n| n > 0), "{outcomes:?}");
+    }
+}
@@ -0,0 +1,294 @@
+//! Pluggable backends which concretize a [`SpecOutline`].
+//!
+//! A [`Resolver`] turns an outline into a [`Resolution`]. Two backends are
+//! provided:
+//!
+//! - [`SatResolver`]: a pure-Rust SAT solver (see [`cdcl`]). It handles the
+//!   common case of boolean options and finite version domains, including
+//!   preferences and goals over them, and needs no native dependencies
+//! - [`Z3Resolver`]: the z3 solver, which handles every constraint. Requires
+//!   the `solver-z3` feature
+//!
+//! [`AutoResolver`] uses the SAT backend when it supports the outline and
+//! falls back to z3 when it does not, for example because an option holds an
+//! integer, float or string. The fallback is logged as a warning, since it can
+//! change which versions are chosen (see below).
+//!
+//! The backends may choose different solutions when several satisfy the
+//! outline equally well. Both prefer to leave packages inactive and to target
+//! the platform's own microarchitecture, weighed against any preferences, and
+//! then minimize each goal in turn, but only the SAT backend then prefers
+//! newer versions. Use [`ResolverKind::Z3`] to reproduce older concretizations
+//! exactly.
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    package::{
+        outline::{SolverError, SpecOutline},
+        stats::SolveStats,
+    },
+    spec::ConcreteSpec,
+};
+
+pub mod cdcl;
+mod sat;
+#[cfg(feature = "solver-z3")]
+mod smt;
+
+pub use sat::SatResolver;
+#[cfg(feature = "solver-z3")]
+pub use smt::Z3Resolver;
+
+/// Where a tracked constraint came from
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// A root spec was required
+    Required,
+
+    /// A spec set an option to a value
+    Value,
+
+    /// A constraint declared by a package
+    Constraint,
+
+    /// A site policy
+    Policy,
+
+    /// A platform fact
+    Platform,
+
+    /// A package must target a microarchitecture the platform runs, and no
+    /// newer than its dependencies target
+    Target,
+}
+
+impl ConflictKind {
+    pub const ALL: [Self; 6] = [
+        Self::Required,
+        Self::Value,
+        Self::Constraint,
+        Self::Policy,
+        Self::Platform,
+        Self::Target,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Required => "required",
+            Self::Value => "value",
+            Self::Constraint => "constraint",
+            Self::Policy => "policy",
+            Self::Platform => "platform",
+            Self::Target => "target",
+        }
+    }
+}
+
+impl std::fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A constraint in the conflict which makes an outline unsatisfiable
+#[cfg_attr(feature = "python", pyclass(get_all, frozen))]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+
+    /// The packages the constraint belongs to, or applies to, if any
+    pub packages: Vec<String>,
+
+    /// A human-readable description of the constraint
+    pub description: String,
+}
+
+impl Conflict {
+    #[must_use]
+    pub fn new(
+        kind: ConflictKind,
+        packages: &[&str],
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            packages: packages.iter().map(ToString::to_string).collect(),
+            description: description.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Conflict {
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Conflict({}, {:?})", self.kind, self.description)
+    }
+}
+
+/// The result of [`Resolver::resolve`]
+#[derive(Clone, Debug)]
+pub enum Resolution {
+    Sat(ConcreteSpec),
+
+    /// The constraints which conflict
+    Unsat(Vec<Conflict>),
+
+    /// The solver gave up without deciding satisfiability, for the reason
+    /// given
+    Unknown(String),
+}
+
+/// The outcome of solving a set of specs from Python, with the statistics
+/// of the solve. Each variant is its own class, so callers can branch with
+/// `match` or `isinstance`.
+#[cfg(feature = "python")]
+#[pyclass]
+#[derive(Clone, Debug)]
+pub enum SolveResult {
+    Sat { spec: ConcreteSpec, stats: SolveStats },
+    Unsat { conflicts: Vec<Conflict>, stats: SolveStats },
+    Unknown { reason: String, stats: SolveStats },
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl SolveResult {
+    /// `"sat"`, `"unsat"` or `"unknown"`
+    #[getter]
+    const fn status(&self) -> &'static str {
+        match self {
+            Self::Sat { .. } => "sat",
+            Self::Unsat { .. } => "unsat",
+            Self::Unknown { .. } => "unknown",
+        }
+    }
+
+    /// Whether the specs were satisfied
+    const fn __bool__(&self) -> bool {
+        matches!(self, Self::Sat { .. })
+    }
+}
+
+/// A backend which concretizes spec outlines.
+pub trait Resolver {
+    /// Name of the backend, as used in errors and logs
+    fn name(&self) -> &'static str;
+
+    /// Concretize `outline`, recording the time spent in each phase and the
+    /// solver's statistics in `stats`.
+    ///
+    /// Default values must already have been propagated with
+    /// [`SpecOutline::propagate_defaults`].
+    ///
+    /// # Errors
+    /// Errors if the outline is invalid. Returns
+    /// [`SolverError::Unsupported`] if the outline uses a feature this
+    /// backend does not implement.
+    fn resolve(
+        &self,
+        outline: &SpecOutline,
+        stats: &mut SolveStats,
+    ) -> Result<Resolution, Box<SolverError>>;
+}
+
+/// Which [`Resolver`] to concretize with
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolverKind {
+    /// [`AutoResolver`]
+    #[default]
+    Auto,
+
+    /// [`SatResolver`]
+    Sat,
+
+    /// [`Z3Resolver`]
+    Z3,
+}
+
+impl ResolverKind {
+    pub const ALL: [Self; 3] = [Self::Auto, Self::Sat, Self::Z3];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Sat => "sat",
+            Self::Z3 => "z3",
+        }
+    }
+}
+
+impl std::fmt::Display for ResolverKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for ResolverKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|kind| kind.name() == s).ok_or_else(|| {
+            format!(
+                "unknown resolver '{s}'; expected one of: {}",
+                Self::ALL.map(Self::name).join(", ")
+            )
+        })
+    }
+}
+
+/// Resolves with [`SatResolver`] where it is supported, and with
+/// [`Z3Resolver`] otherwise.
+#[derive(Clone, Debug, Default)]
+pub struct AutoResolver {
+    pub sat: SatResolver,
+
+    #[cfg(feature = "solver-z3")]
+    pub z3: Z3Resolver,
+}
+
+impl Resolver for AutoResolver {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn resolve(
+        &self,
+        outline: &SpecOutline,
+        stats: &mut SolveStats,
+    ) -> Result<Resolution, Box<SolverError>> {
+        match self.sat.resolve(outline, stats) {
+            #[cfg(feature = "solver-z3")]
+            Err(e) if matches!(*e, SolverError::Unsupported { .. }) => {
+                // z3 does not prefer newer versions, so the solution may
+                // differ from what the SAT backend would have chosen
+                tracing::warn!(
+                    "{e}; falling back to z3, which may choose older versions"
+                );
+                self.z3.resolve(outline, stats)
+            }
+
+            res => res,
+        }
+    }
+}
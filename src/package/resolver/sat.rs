@@ -0,0 +1,1751 @@
+//! The pure-Rust SAT backend.
+//!
+//! Every package toggle, boolean option and microarchitecture target becomes a
+//! SAT variable, as does every version in a finite domain (see
+//! [`PackageOutline::push_version_domain`]). Constraints are converted to
+//! clauses with the Tseitin transformation, and counting constraints with a
+//! sequential counter. Options of any other type cannot be encoded, so
+//! [`SolverError::Unsupported`] is returned for outlines which use them.
+//!
+//! Otherwise, solutions are chosen through the order in which variables are
+//! decided (see [`cdcl`](super::cdcl)): packages are inactive unless something
+//! requires them, the newest version in a domain is tried first and the
+//! platform's own microarchitecture is preferred.
+//!
+//! [`Prefer`](crate::constraint::Prefer) constraints and optimization goals,
+//! `maximize` and `minimize` of a count or a version, are minimized in turn as
+//! a lexicographic objective, each by a linear search over the cost of the
+//! solutions found. As in the z3 backend, the first objective weighs the
+//! broken preferences together with the active packages and the packages not
+//! targeting the platform's microarchitecture, and each goal follows in the
+//! order it was declared. Variables are still decided in the same order, so
+//! the newest versions are chosen among the best solutions.
+//!
+//! [`PackageOutline::push_version_domain`]: crate::package::outline::PackageOutline::push_version_domain
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+};
+
+use crate::{
+    constraint::{
+        Cmp, CmpType, Constraint, ConstraintUtils, NumOf, SOFT_PACKAGE_WEIGHT,
+        SOFT_TARGET_WEIGHT, SpecOption,
+    },
+    package::{
+        outline::{SolverError, SpecOutline},
+        resolver::{
+            Conflict, ConflictKind, Resolution, Resolver,
+            cdcl::{Lit, Outcome, Solver},
+        },
+        stats::{self, SolveStats},
+        version::Version,
+    },
+    spec::{
+        ConcreteSpec, SpecOptionType, SpecOptionValue, concrete::Assignment,
+        platform::PlatformKey, target::TARGET_OPTION,
+    },
+    util::suggest,
+};
+
+/// Concretizes with a pure-Rust SAT solver. Supports outlines whose options
+/// are all booleans, versions with a finite domain or targets.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SatResolver;
+
+impl Resolver for SatResolver {
+    fn name(&self) -> &'static str {
+        "sat"
+    }
+
+    fn resolve(
+        &self,
+        outline: &SpecOutline,
+        stats: &mut SolveStats,
+    ) -> Result<Resolution, Box<SolverError>> {
+        let mut encoding = stats.time("encoding", || Encoding::new(outline))?;
+
+        let selectors =
+            encoding.tracked.iter().map(|(lit, _)| *lit).collect::<Vec<_>>();
+
+        let outcome = stats.time("check", || encoding.optimize(&selectors));
+
+        let counters = encoding.solver.counters();
+
+        for (name, count) in [
+            (stats::CONFLICTS, counters.conflicts),
+            (stats::DECISIONS, counters.decisions),
+            (stats::PROPAGATIONS, counters.propagations),
+        ] {
+            // Counts never come close to losing precision
+            #[allow(clippy::cast_precision_loss)]
+            stats.solver.insert(name.to_string(), count as f64);
+        }
+
+        tracing::info!("solver result: {outcome:?}");
+
+        match outcome {
+            Outcome::Sat => stats
+                .time("model extraction", || {
+                    ConcreteSpec::from_assignment(outline, &encoding)
+                })
+                .map(Resolution::Sat),
+
+            Outcome::Unsat(core) => Ok(Resolution::Unsat(
+                encoding
+                    .tracked
+                    .iter()
+                    .filter(|(selector, _)| core.contains(selector))
+                    .map(|(_, conflict)| conflict.clone())
+                    .collect(),
+            )),
+        }
+    }
+}
+
+fn unsupported(reason: impl Into<String>) -> Box<SolverError> {
+    let e = SolverError::Unsupported { resolver: "sat", reason: reason.into() };
+    tracing::info!("{e}");
+    Box::new(e)
+}
+
+/// A package name and option name
+type OptionKey = (String, String);
+
+fn option_key(option: &SpecOption) -> OptionKey {
+    (option.package_name.clone(), option.option_name.clone())
+}
+
+/// Infers the type of every option from the constraints which refer to it,
+/// since the SAT backend has no registry to record them in.
+#[derive(Default)]
+struct Inference {
+    types: BTreeMap<OptionKey, SpecOptionType>,
+
+    /// Options which are compared with each other, so have the same type
+    links: Vec<(OptionKey, OptionKey)>,
+
+    /// The versions of options declared with
+    /// [`PackageOutline::push_version_domain`](crate::package::outline::PackageOutline::push_version_domain),
+    /// newest first
+    domains: BTreeMap<OptionKey, Vec<Version>>,
+}
+
+impl Inference {
+    /// The types of every option in `outline`.
+    fn infer(outline: &SpecOutline) -> Result<Self, Box<SolverError>> {
+        let mut res = Self::default();
+
+        outline.check_schemas()?;
+
+        for idx in outline.grounded() {
+            let package = &outline.graph[idx];
+
+            res.note(
+                (package.name.clone(), TARGET_OPTION.to_string()),
+                SpecOptionType::Str,
+            )?;
+
+            for constraint in &package.constraints {
+                if let Some((key, versions)) = version_domain(constraint) {
+                    res.domains.entry(key).or_insert(versions);
+                }
+
+                res.visit(constraint, true)?;
+            }
+
+            for (_, when) in package.conditions() {
+                res.visit(when, true)?;
+            }
+
+            for (name, value) in package.sorted_set_options() {
+                res.note(
+                    (package.name.clone(), name.clone()),
+                    value.to_type(),
+                )?;
+            }
+        }
+
+        for policy in outline.active_policies() {
+            res.visit(&policy.constraint, true)?;
+        }
+
+        // Declared types, for the options which are used
+        let declared = res
+            .types
+            .keys()
+            .filter_map(|(package, option)| {
+                let idx = outline.lookup.get(package)?;
+                let schema = outline.graph[*idx].schema.get(option)?;
+
+                Some(((package.clone(), option.clone()), schema.dtype))
+            })
+            .collect::<Vec<_>>();
+
+        for (key, dtype) in declared {
+            res.note(key, dtype)?;
+        }
+
+        res.unify()?;
+
+        Ok(res)
+    }
+
+    fn note(
+        &mut self,
+        key: OptionKey,
+        value_type: SpecOptionType,
+    ) -> Result<(), Box<SolverError>> {
+        let current = self.types.entry(key).or_insert(SpecOptionType::Unknown);
+
+        match *current {
+            _ if value_type == SpecOptionType::Unknown => Ok(()),
+            SpecOptionType::Unknown => {
+                *current = value_type;
+                Ok(())
+            }
+            known if known == value_type => Ok(()),
+            known => {
+                tracing::error!(
+                    "cannot compare differing types {known:?} and \
+                     {value_type:?}"
+                );
+
+                Err(Box::new(SolverError::IncorrectValueType {
+                    expected: known,
+                    received: value_type,
+                }))
+            }
+        }
+    }
+
+    /// Record the types implied by `constraint`, which is used as a boolean
+    /// if `is_bool`.
+    fn visit(
+        &mut self,
+        constraint: &Constraint,
+        is_bool: bool,
+    ) -> Result<(), Box<SolverError>> {
+        match constraint {
+            Constraint::SpecOption(option) => self.note(
+                option_key(option),
+                if is_bool {
+                    SpecOptionType::Bool
+                } else {
+                    SpecOptionType::Unknown
+                },
+            ),
+
+            Constraint::Cmp(cmp) => {
+                match (&cmp.lhs, &cmp.rhs) {
+                    (Constraint::SpecOption(a), Constraint::SpecOption(b)) => {
+                        self.links.push((option_key(a), option_key(b)));
+                    }
+
+                    (Constraint::SpecOption(option), other)
+                    | (other, Constraint::SpecOption(option)) => {
+                        if let Some(value_type) = other.get_value_type_default()
+                        {
+                            self.note(option_key(option), value_type)?;
+                        }
+                    }
+
+                    _ => (),
+                }
+
+                self.visit(&cmp.lhs, false)?;
+                self.visit(&cmp.rhs, false)
+            }
+
+            Constraint::IfThen(if_then) => {
+                self.visit(&if_then.cond, true)?;
+                self.visit(&if_then.then, true)
+            }
+
+            Constraint::NumOf(num_of) => {
+                for c in &num_of.of {
+                    self.visit(c, true)?;
+                }
+
+                Ok(())
+            }
+
+            Constraint::Depends(_)
+            | Constraint::Value(_)
+            | Constraint::WhenPlatform(_) => Ok(()),
+
+            Constraint::Maximize(goal) => self.visit_goal(&goal.item),
+            Constraint::Minimize(goal) => self.visit_goal(&goal.item),
+
+            Constraint::Prefer(prefer) => {
+                if prefer.weight == 0 {
+                    let msg =
+                        format!("'{constraint}' must have a positive weight");
+                    tracing::error!("{msg}");
+                    return Err(Box::new(SolverError::InvalidConstraint(msg)));
+                }
+
+                self.visit(&prefer.item, true)
+            }
+
+            Constraint::Arith(_) => {
+                Err(unsupported(format!("'{constraint}' is arithmetic")))
+            }
+
+            Constraint::Matches(_) => Err(unsupported(format!(
+                "'{constraint}' matches a string option"
+            ))),
+
+            Constraint::Custom(_) => Err(unsupported(format!(
+                "'{constraint}' is a custom constraint"
+            ))),
+        }
+    }
+
+    /// Record the types implied by the item of an optimization goal, which
+    /// is a count of boolean constraints or an option.
+    fn visit_goal(
+        &mut self,
+        item: &Constraint,
+    ) -> Result<(), Box<SolverError>> {
+        match item {
+            Constraint::NumOf(num_of) => {
+                for c in &num_of.of {
+                    self.visit(c, true)?;
+                }
+
+                Ok(())
+            }
+
+            Constraint::SpecOption(_) => self.visit(item, false),
+
+            _ => Err(unsupported(format!("'{item}' cannot be optimized"))),
+        }
+    }
+
+    /// Give linked options the same type.
+    fn unify(&mut self) -> Result<(), Box<SolverError>> {
+        let links = std::mem::take(&mut self.links);
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (a, b) in &links {
+                let type_of = |key| {
+                    self.types
+                        .get(key)
+                        .copied()
+                        .unwrap_or(SpecOptionType::Unknown)
+                };
+
+                let (ta, tb) = (type_of(a), type_of(b));
+
+                if ta != tb {
+                    changed |= ta == SpecOptionType::Unknown
+                        || tb == SpecOptionType::Unknown;
+
+                    self.note(a.clone(), tb)?;
+                    self.note(b.clone(), ta)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The option and versions of a constraint created by
+/// [`PackageOutline::push_version_domain`](crate::package::outline::PackageOutline::push_version_domain),
+/// newest first.
+fn version_domain(
+    constraint: &Constraint,
+) -> Option<(OptionKey, Vec<Version>)> {
+    let Constraint::Cmp(cmp) = constraint else { return None };
+
+    let (Constraint::NumOf(num_of), CmpType::Equal, Constraint::Value(one)) =
+        (&cmp.lhs, cmp.op, &cmp.rhs)
+    else {
+        return None;
+    };
+
+    if one.value != SpecOptionValue::Int(1) {
+        return None;
+    }
+
+    let mut key = None;
+    let mut versions = Vec::new();
+
+    for c in &num_of.of {
+        let Constraint::Cmp(eq) = c else { return None };
+
+        let (
+            Constraint::SpecOption(option),
+            CmpType::Equal,
+            Constraint::Value(v),
+        ) = (&eq.lhs, eq.op, &eq.rhs)
+        else {
+            return None;
+        };
+
+        let SpecOptionValue::Version(version) = &v.value else { return None };
+
+        if *key.get_or_insert_with(|| option_key(option)) != option_key(option)
+        {
+            return None;
+        }
+
+        versions.push(version.clone());
+    }
+
+    versions.sort_by(|a, b| b.cmp(a));
+    versions.dedup();
+
+    Some((key?, versions))
+}
+
+/// Whether `value op bound` holds, with the same semantics as in the z3
+/// backend.
+fn satisfies(
+    value: &SpecOptionValue,
+    op: CmpType,
+    bound: &SpecOptionValue,
+) -> Result<bool, Box<SolverError>> {
+    let ord = match (value, bound) {
+        (SpecOptionValue::Version(v), SpecOptionValue::Version(b)) => {
+            return Ok(v.satisfies(op, b));
+        }
+        (SpecOptionValue::Str(v), SpecOptionValue::Str(b)) => v.cmp(b),
+        _ => {
+            return Err(Box::new(SolverError::IncorrectValueType {
+                expected: value.to_type(),
+                received: bound.to_type(),
+            }));
+        }
+    };
+
+    Ok(match op {
+        CmpType::Less => ord == Ordering::Less,
+        CmpType::LessOrEqual => ord != Ordering::Greater,
+        CmpType::NotEqual => ord != Ordering::Equal,
+        CmpType::Equal => ord == Ordering::Equal,
+        CmpType::GreaterOrEqual => ord != Ordering::Less,
+        CmpType::Greater => ord == Ordering::Greater,
+    })
+}
+
+/// `op` with its operands swapped
+const fn flip(op: CmpType) -> CmpType {
+    match op {
+        CmpType::Less => CmpType::Greater,
+        CmpType::LessOrEqual => CmpType::GreaterOrEqual,
+        CmpType::GreaterOrEqual => CmpType::LessOrEqual,
+        CmpType::Greater => CmpType::Less,
+        CmpType::NotEqual | CmpType::Equal => op,
+    }
+}
+
+/// Whether `constraint` is a preference or an optimization goal, which is
+/// minimized rather than asserted
+const fn is_objective(constraint: &Constraint) -> bool {
+    matches!(
+        constraint,
+        Constraint::Prefer(_)
+            | Constraint::Maximize(_)
+            | Constraint::Minimize(_)
+    )
+}
+
+/// One variable per possible value of an option, exactly one of which is
+/// true
+type Domain = Vec<(SpecOptionValue, Lit)>;
+
+/// The solver variables of an option
+#[derive(Clone)]
+enum OptionVars {
+    Bool(Lit),
+    Domain(Domain),
+}
+
+/// A cost to minimize: the sum of the weights of the literals which hold
+type Objective = Vec<(Lit, u64)>;
+
+/// A [`SpecOutline`] converted to clauses.
+struct Encoding<'a> {
+    outline: &'a SpecOutline,
+    solver: Solver,
+
+    /// A literal which is always true
+    truth: Lit,
+
+    toggles: HashMap<String, Lit>,
+
+    /// Option variables by package and option name
+    options: BTreeMap<String, BTreeMap<String, OptionVars>>,
+
+    facts: HashMap<(PlatformKey, String), Lit>,
+
+    /// Variables of patch and flag conditions, by flag name
+    conditions: HashMap<String, Lit>,
+
+    /// Assumptions enabling each tracked constraint, with the constraint
+    tracked: Vec<(Lit, Conflict)>,
+
+    /// Minimized in order by [`Self::optimize`]
+    objectives: Vec<Objective>,
+}
+
+impl<'a> Encoding<'a> {
+    fn new(outline: &'a SpecOutline) -> Result<Self, Box<SolverError>> {
+        let Inference { types, domains, .. } = Inference::infer(outline)?;
+
+        // Variables are decided in the order they are created, so the most
+        // important ones come first
+        let mut solver = Solver::new();
+
+        let truth = solver.new_var(true).pos();
+        solver.add_clause(&[truth]);
+
+        let mut toggles = HashMap::new();
+
+        for idx in outline.grounded() {
+            let name = outline.graph[idx].name.clone();
+            toggles.insert(name, solver.new_var(false).pos());
+        }
+
+        let mut options = BTreeMap::<String, BTreeMap<_, _>>::new();
+
+        for ((package, option), versions) in domains {
+            let domain = versions
+                .into_iter()
+                .map(|v| {
+                    (SpecOptionValue::Version(v), solver.new_var(true).pos())
+                })
+                .collect();
+
+            options
+                .entry(package)
+                .or_default()
+                .insert(option, OptionVars::Domain(domain));
+        }
+
+        let target_domain = outline.target_domain();
+
+        for idx in outline.grounded() {
+            let domain = target_domain
+                .iter()
+                .map(|(t, _)| {
+                    (
+                        SpecOptionValue::Str(t.clone()),
+                        solver.new_var(true).pos(),
+                    )
+                })
+                .collect();
+
+            options
+                .entry(outline.graph[idx].name.clone())
+                .or_default()
+                .insert(TARGET_OPTION.to_string(), OptionVars::Domain(domain));
+        }
+
+        for ((package, option), value_type) in types {
+            let exists = options
+                .get(&package)
+                .is_some_and(|vars| vars.contains_key(&option));
+
+            match value_type {
+                SpecOptionType::Bool => {
+                    options.entry(package).or_default().insert(
+                        option,
+                        OptionVars::Bool(solver.new_var(false).pos()),
+                    );
+                }
+
+                _ if exists => (),
+
+                SpecOptionType::Unknown => {
+                    return Err(unsupported(format!(
+                        "the type of '{package}:{option}' cannot be inferred"
+                    )));
+                }
+
+                SpecOptionType::Version => {
+                    return Err(unsupported(format!(
+                        "'{package}:{option}' has no finite version domain"
+                    )));
+                }
+
+                other => {
+                    return Err(unsupported(format!(
+                        "'{package}:{option}' holds a value of type {other:?}"
+                    )));
+                }
+            }
+        }
+
+        let mut encoding = Self {
+            outline,
+            solver,
+            truth,
+            toggles,
+            options,
+            facts: HashMap::new(),
+            conditions: HashMap::new(),
+            tracked: Vec::new(),
+            objectives: Vec::new(),
+        };
+
+        encoding.restrict_domains();
+        encoding.handle_explicit_options()?;
+        encoding.require_packages()?;
+        encoding.push_constraints()?;
+        encoding.push_target_constraints()?;
+        encoding.push_conditions()?;
+        encoding.push_objectives()?;
+
+        Ok(encoding)
+    }
+
+    fn value(&self, lit: Lit) -> bool {
+        self.solver.value(lit) == Some(true)
+    }
+
+    /// The cost of `objective` in the current assignment
+    fn cost(&self, objective: &Objective) -> u64 {
+        objective
+            .iter()
+            .filter(|(lit, _)| self.value(*lit))
+            .fold(0, |sum, (_, weight)| sum.saturating_add(*weight))
+    }
+
+    /// Solve under `assumptions`, then minimize each objective in turn
+    /// without raising the cost of those before it. The solver is left
+    /// holding the best solution found.
+    fn optimize(&mut self, assumptions: &[Lit]) -> Outcome {
+        let mut assumptions = assumptions.to_vec();
+
+        let mut outcome = self.solver.solve(&assumptions);
+        let objectives = std::mem::take(&mut self.objectives);
+
+        for objective in &objectives {
+            if outcome != Outcome::Sat {
+                break;
+            }
+
+            let mut cost = self.cost(objective);
+            let at_least = self.at_least(objective, cost.saturating_add(1));
+
+            // `at_least` only holds totals up to `cost + 1`, and any total
+            // at or above a key holds the literal of the next key
+            let at_least = |total: u64| {
+                at_least.range(total..).next().map_or(!self.truth, |(_, l)| *l)
+            };
+
+            while cost > 0 {
+                assumptions.push(!at_least(cost));
+                let improved = self.solver.solve(&assumptions);
+                assumptions.pop();
+
+                if improved != Outcome::Sat {
+                    break;
+                }
+
+                cost = self.cost(objective);
+            }
+
+            tracing::info!("minimized objective to {cost}");
+            assumptions.push(!at_least(cost.saturating_add(1)));
+
+            // Find a solution of the best cost again, which the next
+            // objective starts from
+            outcome = self.solver.solve(&assumptions);
+        }
+
+        outcome
+    }
+
+    /// Literals which hold when the total weight of the true literals in
+    /// `objective` is at least each key, for every total up to `cap`. Totals
+    /// above `cap` count as `cap`.
+    fn at_least(
+        &mut self,
+        objective: &Objective,
+        cap: u64,
+    ) -> BTreeMap<u64, Lit> {
+        let mut totals = BTreeMap::from([(0_u64, self.truth)]);
+
+        for &(lit, weight) in objective {
+            let ge = |totals: &BTreeMap<u64, Lit>, total: u64| {
+                totals.range(total..).next().map(|(_, lit)| *lit)
+            };
+
+            let keys = totals
+                .keys()
+                .flat_map(|&k| [k, k.saturating_add(weight).min(cap)])
+                .collect::<std::collections::BTreeSet<_>>();
+
+            let mut next = BTreeMap::new();
+
+            for key in keys {
+                let stay = ge(&totals, key).unwrap_or(!self.truth);
+
+                let carry = match key.checked_sub(weight) {
+                    None | Some(0) => lit,
+                    Some(rest) => match ge(&totals, rest) {
+                        Some(reached) => self.and(&[reached, lit]),
+                        None => !self.truth,
+                    },
+                };
+
+                next.insert(key, self.or(&[stay, carry]));
+            }
+
+            totals = next;
+        }
+
+        totals
+    }
+
+    fn constant(&self, value: bool) -> Lit {
+        if value { self.truth } else { !self.truth }
+    }
+
+    /// A literal which is true exactly when any of `lits` is.
+    fn or(&mut self, lits: &[Lit]) -> Lit {
+        let mut inputs = Vec::new();
+
+        for &lit in lits {
+            if lit == self.truth {
+                return self.truth;
+            }
+
+            if lit != !self.truth {
+                inputs.push(lit);
+            }
+        }
+
+        inputs.sort_unstable();
+        inputs.dedup();
+
+        if inputs.windows(2).any(|w| w[0].var() == w[1].var()) {
+            return self.truth;
+        }
+
+        if let [lit] = inputs.as_slice() {
+            return *lit;
+        }
+
+        if inputs.is_empty() {
+            return !self.truth;
+        }
+
+        let out = self.solver.new_var(false).pos();
+
+        for &lit in &inputs {
+            self.solver.add_clause(&[!lit, out]);
+        }
+
+        inputs.push(!out);
+        self.solver.add_clause(&inputs);
+
+        out
+    }
+
+    /// A literal which is true exactly when all of `lits` are.
+    fn and(&mut self, lits: &[Lit]) -> Lit {
+        let negated = lits.iter().map(|&lit| !lit).collect::<Vec<_>>();
+        !self.or(&negated)
+    }
+
+    /// A literal which is true exactly when `a` and `b` are equal.
+    fn iff(&mut self, a: Lit, b: Lit) -> Lit {
+        if a == b {
+            return self.truth;
+        }
+
+        if a == !b {
+            return !self.truth;
+        }
+
+        let both = self.and(&[a, b]);
+        let neither = self.and(&[!a, !b]);
+        self.or(&[both, neither])
+    }
+
+    /// Add `clause`, enabled by an assumption which is reported in the unsat
+    /// core as `conflict`.
+    fn track(&mut self, conflict: Conflict, clause: &[Lit]) {
+        let selector = self.selector(conflict);
+
+        let mut clause = clause.to_vec();
+        clause.push(!selector);
+        self.solver.add_clause(&clause);
+    }
+
+    fn selector(&mut self, conflict: Conflict) -> Lit {
+        let selector = self.solver.new_var(true).pos();
+        self.tracked.push((selector, conflict));
+        selector
+    }
+
+    fn toggle(&self, name: &str) -> Result<Lit, Box<SolverError>> {
+        self.toggles.get(name).copied().ok_or_else(|| {
+            tracing::error!("unknown package '{name}'");
+
+            Box::new(SolverError::MissingPackage {
+                name: name.to_string(),
+                suggestion: suggest::closest(
+                    name,
+                    self.outline.lookup.keys().map(String::as_str),
+                )
+                .map(str::to_string),
+            })
+        })
+    }
+
+    fn option(
+        &self,
+        package: &str,
+        option: &str,
+    ) -> Result<&OptionVars, Box<SolverError>> {
+        self.options.get(package).and_then(|o| o.get(option)).ok_or_else(|| {
+            tracing::error!("'{package}:{option}' has no solver variable");
+
+            Box::new(SolverError::NoSolverVariable {
+                package: package.to_string(),
+                option: Some(option.to_string()),
+            })
+        })
+    }
+
+    /// The variable which is true when the platform fact `key` is `value`.
+    /// Its value is fixed by a tracked assertion.
+    fn fact(&mut self, key: PlatformKey, value: &str) -> Lit {
+        if let Some(&lit) = self.facts.get(&(key, value.to_string())) {
+            return lit;
+        }
+
+        let lit = self.solver.new_var(false).pos();
+        let actual = self.outline.platform.get(key);
+
+        tracing::info!("fixing platform fact {key}={value} ({actual})");
+
+        let holds = if actual == value { lit } else { !lit };
+        self.track(
+            Conflict::new(
+                ConflictKind::Platform,
+                &[],
+                format!("platform {key} is '{actual}'"),
+            ),
+            &[holds],
+        );
+
+        self.facts.insert((key, value.to_string()), lit);
+        lit
+    }
+
+    /// Ensure every option with a domain takes exactly one value. A target
+    /// must also run on the platform, which is tracked in
+    /// [`Self::push_target_constraints`].
+    fn restrict_domains(&mut self) {
+        let mut domains = Vec::new();
+
+        for vars in self.options.values() {
+            for (option, vars) in vars {
+                if let OptionVars::Domain(domain) = vars {
+                    let lits = domain.iter().map(|(_, lit)| *lit);
+                    domains.push((
+                        option == TARGET_OPTION,
+                        lits.collect::<Vec<_>>(),
+                    ));
+                }
+            }
+        }
+
+        for (is_target, lits) in domains {
+            if !is_target {
+                self.solver.add_clause(&lits);
+            }
+
+            self.at_most_one(&lits);
+        }
+    }
+
+    /// Allow at most one of `lits` to be true, with a ladder of auxiliary
+    /// variables which are true once a literal has been.
+    fn at_most_one(&mut self, lits: &[Lit]) {
+        let mut prev: Option<Lit> = None;
+
+        for &lit in lits {
+            let seen = self.solver.new_var(false).pos();
+
+            self.solver.add_clause(&[!lit, seen]);
+
+            if let Some(prev) = prev {
+                self.solver.add_clause(&[!prev, seen]);
+                self.solver.add_clause(&[!prev, !lit]);
+            }
+
+            prev = Some(seen);
+        }
+    }
+
+    fn encode(
+        &mut self,
+        constraint: &Constraint,
+    ) -> Result<Lit, Box<SolverError>> {
+        match constraint {
+            Constraint::Value(value) => match value.value {
+                SpecOptionValue::Bool(b) => Ok(self.constant(b)),
+                ref other => Err(Box::new(SolverError::IncorrectValueType {
+                    expected: SpecOptionType::Bool,
+                    received: other.to_type(),
+                })),
+            },
+
+            Constraint::SpecOption(option) => {
+                match self.option(&option.package_name, &option.option_name)? {
+                    OptionVars::Bool(lit) => Ok(*lit),
+                    OptionVars::Domain(_) => Err(unsupported(format!(
+                        "'{option}' is only supported when compared with a \
+                         value"
+                    ))),
+                }
+            }
+
+            Constraint::Depends(depends) => self.toggle(depends.on()),
+
+            Constraint::IfThen(if_then) => {
+                let cond = self.encode(&if_then.cond)?;
+                let then = self.encode(&if_then.then)?;
+                Ok(self.or(&[!cond, then]))
+            }
+
+            Constraint::WhenPlatform(when) => {
+                Ok(self.fact(when.key, &when.value))
+            }
+
+            Constraint::Cmp(cmp) => self.cmp(cmp),
+
+            Constraint::NumOf(_) => Err(unsupported(format!(
+                "'{constraint}' is only supported when compared with an \
+                 integer"
+            ))),
+
+            Constraint::Maximize(_)
+            | Constraint::Minimize(_)
+            | Constraint::Prefer(_) => {
+                let msg = format!(
+                    "'{constraint}' cannot be part of another constraint"
+                );
+                tracing::error!("{msg}");
+                Err(Box::new(SolverError::InvalidConstraint(msg)))
+            }
+
+            Constraint::Arith(_)
+            | Constraint::Matches(_)
+            | Constraint::Custom(_) => {
+                Err(unsupported(format!("'{constraint}' cannot be encoded")))
+            }
+        }
+    }
+
+    fn is_domain(&self, option: &SpecOption) -> bool {
+        matches!(
+            self.option(&option.package_name, &option.option_name),
+            Ok(OptionVars::Domain(_))
+        )
+    }
+
+    fn cmp(&mut self, cmp: &Cmp) -> Result<Lit, Box<SolverError>> {
+        match (&cmp.lhs, &cmp.rhs) {
+            (Constraint::NumOf(num_of), Constraint::Value(value)) => {
+                self.count(num_of, cmp.op, &value.value)
+            }
+
+            (Constraint::Value(value), Constraint::NumOf(num_of)) => {
+                self.count(num_of, flip(cmp.op), &value.value)
+            }
+
+            (Constraint::SpecOption(option), Constraint::Value(value))
+                if self.is_domain(option) =>
+            {
+                self.select(option, cmp.op, &value.value)
+            }
+
+            (Constraint::Value(value), Constraint::SpecOption(option))
+                if self.is_domain(option) =>
+            {
+                self.select(option, flip(cmp.op), &value.value)
+            }
+
+            (lhs, rhs) => {
+                let lhs = self.encode(lhs)?;
+                let rhs = self.encode(rhs)?;
+
+                match cmp.op {
+                    CmpType::Equal => Ok(self.iff(lhs, rhs)),
+                    CmpType::NotEqual => Ok(!self.iff(lhs, rhs)),
+                    op => {
+                        let msg = format!(
+                            "Cannot compare type {:?} with operator '{op}'",
+                            SpecOptionType::Bool
+                        );
+                        tracing::error!("{msg}");
+                        Err(Box::new(SolverError::InvalidConstraint(msg)))
+                    }
+                }
+            }
+        }
+    }
+
+    /// A literal which is true when the option, which has a domain, compares
+    /// to `bound` with `op`.
+    fn select(
+        &mut self,
+        option: &SpecOption,
+        op: CmpType,
+        bound: &SpecOptionValue,
+    ) -> Result<Lit, Box<SolverError>> {
+        let OptionVars::Domain(domain) =
+            self.option(&option.package_name, &option.option_name)?.clone()
+        else {
+            unreachable!("option has a domain");
+        };
+
+        let mut lits = Vec::new();
+
+        for (value, lit) in domain {
+            if satisfies(&value, op, bound)? {
+                lits.push(lit);
+            }
+        }
+
+        Ok(self.or(&lits))
+    }
+
+    /// A literal which is true when the number of true constraints in
+    /// `num_of` compares to `bound` with `op`, built from a sequential
+    /// counter.
+    fn count(
+        &mut self,
+        num_of: &NumOf,
+        op: CmpType,
+        bound: &SpecOptionValue,
+    ) -> Result<Lit, Box<SolverError>> {
+        let &SpecOptionValue::Int(k) = bound else {
+            return Err(Box::new(SolverError::IncorrectValueType {
+                expected: SpecOptionType::Int,
+                received: bound.to_type(),
+            }));
+        };
+
+        let lits = num_of
+            .of
+            .iter()
+            .map(|c| self.encode(c))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let n = lits.len();
+        let need = usize::try_from(k.saturating_add(1)).unwrap_or(0).min(n);
+
+        // `at_least[j]` holds when at least `j` of the literals seen so far
+        // are true
+        let mut at_least = vec![!self.truth; need + 1];
+        at_least[0] = self.truth;
+
+        for &lit in &lits {
+            for j in (1..=need).rev() {
+                let carry = self.and(&[at_least[j - 1], lit]);
+                at_least[j] = self.or(&[at_least[j], carry]);
+            }
+        }
+
+        let truth = self.truth;
+
+        let ge = |j: i64| match usize::try_from(j) {
+            Err(_) | Ok(0) => truth,
+            Ok(j) if j > n => !truth,
+            Ok(j) => at_least[j],
+        };
+
+        let (ge_k, ge_next) = (ge(k), ge(k.saturating_add(1)));
+
+        Ok(match op {
+            CmpType::Less => !ge_k,
+            CmpType::LessOrEqual => !ge_next,
+            CmpType::NotEqual => !self.and(&[ge_k, !ge_next]),
+            CmpType::Equal => self.and(&[ge_k, !ge_next]),
+            CmpType::GreaterOrEqual => ge_k,
+            CmpType::Greater => ge_next,
+        })
+    }
+
+    fn handle_explicit_options(&mut self) -> Result<(), Box<SolverError>> {
+        let outline = self.outline;
+
+        for idx in outline.grounded() {
+            let package = &outline.graph[idx];
+            let toggle = self.toggle(&package.name)?;
+
+            for (name, value) in package.sorted_set_options() {
+                tracing::info!(
+                    "adding explicit value {}:{name} -> {value}",
+                    package.name
+                );
+
+                let eq = SpecOption::new(package.name.clone(), name.clone())
+                    .equals(value.clone());
+
+                let lit = self.cmp(&eq)?;
+                self.track(
+                    Conflict::new(
+                        ConflictKind::Value,
+                        &[&package.name],
+                        eq.to_string(),
+                    ),
+                    &[!toggle, lit],
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn require_packages(&mut self) -> Result<(), Box<SolverError>> {
+        for r in &self.outline.required {
+            let toggle = self.toggle(r).inspect_err(|_| {
+                tracing::error!("missing explicitly required dependency '{r}'");
+            })?;
+
+            self.track(
+                Conflict::new(
+                    ConflictKind::Required,
+                    &[r],
+                    format!("'{r}' required explicitly"),
+                ),
+                &[toggle],
+            );
+        }
+
+        Ok(())
+    }
+
+    fn push_constraints(&mut self) -> Result<(), Box<SolverError>> {
+        let outline = self.outline;
+
+        // A constraint shared by several packages is encoded once, and each
+        // package's clause is tracked by the same selector
+        for normalized in outline.grounded_constraints() {
+            if is_objective(&normalized.constraint) {
+                continue;
+            }
+
+            tracing::info!(
+                "adding constraint {} -> {}",
+                normalized.packages.join(", "),
+                normalized.constraint
+            );
+
+            let lit = self.encode(&normalized.constraint)?;
+            let selector = self.selector(Conflict::new(
+                ConflictKind::Constraint,
+                &normalized.packages,
+                normalized.constraint.to_string(),
+            ));
+
+            for package in &normalized.packages {
+                let toggle = self.toggle(package)?;
+                self.solver.add_clause(&[!toggle, lit, !selector]);
+            }
+        }
+
+        for policy in outline.active_policies() {
+            if let Constraint::Prefer(_) = policy.constraint {
+                continue;
+            }
+
+            tracing::info!("adding {}", policy.description);
+
+            let lit = self.encode(&policy.constraint)?;
+            self.track(
+                Conflict::new(
+                    ConflictKind::Policy,
+                    &[],
+                    policy.description.clone(),
+                ),
+                &[lit],
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The activation toggle and target variables of a package, in the order
+    /// of [`SpecOutline::target_domain`].
+    fn target_vars(
+        &self,
+        name: &str,
+    ) -> Result<(Lit, Domain), Box<SolverError>> {
+        let toggle = self.toggle(name)?;
+
+        let OptionVars::Domain(targets) = self.option(name, TARGET_OPTION)?
+        else {
+            unreachable!("targets have a domain");
+        };
+
+        Ok((toggle, targets.clone()))
+    }
+
+    /// As in the z3 backend, restrict every target to one which runs on the
+    /// platform and prevent an active package from targeting a newer
+    /// microarchitecture than any of its active dependencies.
+    fn push_target_constraints(&mut self) -> Result<(), Box<SolverError>> {
+        let outline = self.outline;
+        let microarch = &outline.platform.microarchitecture;
+        let domain = outline.target_domain();
+
+        for idx in outline.grounded() {
+            let name = &outline.graph[idx].name;
+            let (toggle, targets) = self.target_vars(name)?;
+
+            tracing::info!("adding target constraints for {name}");
+
+            let valid = targets.iter().map(|(_, lit)| *lit).collect::<Vec<_>>();
+
+            self.track(
+                Conflict::new(
+                    ConflictKind::Target,
+                    &[name],
+                    format!("target of '{name}' must run on '{microarch}'"),
+                ),
+                &valid,
+            );
+
+            for dep in outline
+                .graph
+                .neighbors_directed(idx, petgraph::Direction::Outgoing)
+            {
+                let (dep_toggle, dep_targets) =
+                    self.target_vars(&outline.graph[dep].name)?;
+
+                let dep_name = &outline.graph[dep].name;
+
+                let selector = self.selector(Conflict::new(
+                    ConflictKind::Target,
+                    &[name, dep_name],
+                    format!(
+                        "'{name}' cannot target a newer microarchitecture \
+                         than its dependency '{dep_name}'"
+                    ),
+                ));
+
+                for ((_, supported), (_, dep_target)) in
+                    domain.iter().zip(dep_targets)
+                {
+                    let mut clause =
+                        vec![!selector, !toggle, !dep_toggle, !dep_target];
+
+                    clause.extend(
+                        targets
+                            .iter()
+                            .filter(|(t, _)| {
+                                matches!(t, SpecOptionValue::Str(t) if supported.contains(t))
+                            })
+                            .map(|(_, lit)| *lit),
+                    );
+
+                    self.solver.add_clause(&clause);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_conditions(&mut self) -> Result<(), Box<SolverError>> {
+        let outline = self.outline;
+
+        for idx in outline.grounded() {
+            for (name, when) in outline.graph[idx].conditions() {
+                tracing::info!("adding condition {name} -> {when}");
+
+                let lit = self.encode(when)?;
+                self.conditions.insert(name, lit);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Encoding<'_> {
+    /// Collect the preferences and optimization goals into
+    /// [`Self::objectives`], in the order the z3 backend minimizes them.
+    fn push_objectives(&mut self) -> Result<(), Box<SolverError>> {
+        let outline = self.outline;
+
+        let mut preferences = Objective::new();
+        let mut goals = Vec::new();
+
+        for normalized in outline.grounded_constraints() {
+            let toggles = normalized
+                .packages
+                .iter()
+                .map(|package| self.toggle(package))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match &normalized.constraint {
+                Constraint::Prefer(prefer) => {
+                    tracing::info!(
+                        "adding preference {} -> {prefer}",
+                        normalized.packages.join(", ")
+                    );
+
+                    let active = self.or(&toggles);
+                    let holds = self.encode(&prefer.item)?;
+                    let broken = self.and(&[active, !holds]);
+
+                    preferences.push((broken, prefer.weight));
+                }
+
+                Constraint::Maximize(goal) => {
+                    tracing::info!("adding goal {goal}");
+                    goals.push(self.goal(&goal.item, true)?);
+                }
+
+                Constraint::Minimize(goal) => {
+                    tracing::info!("adding goal {goal}");
+                    goals.push(self.goal(&goal.item, false)?);
+                }
+
+                _ => (),
+            }
+        }
+
+        for policy in outline.active_policies() {
+            if let Constraint::Prefer(prefer) = &policy.constraint {
+                tracing::info!("adding {}", policy.description);
+
+                let holds = self.encode(&prefer.item)?;
+                preferences.push((!holds, prefer.weight));
+            }
+        }
+
+        if preferences.is_empty() && goals.is_empty() {
+            return Ok(());
+        }
+
+        // Preferences share an objective with the z3 backend's preferences
+        // for inactive packages and the platform's microarchitecture
+        let microarch = &outline.platform.microarchitecture;
+        let mut soft = Objective::new();
+
+        for idx in outline.grounded() {
+            let (toggle, targets) =
+                self.target_vars(&outline.graph[idx].name)?;
+
+            soft.push((toggle, SOFT_PACKAGE_WEIGHT as u64));
+
+            if let Some((_, native)) = targets.iter().find(
+                |(t, _)| matches!(t, SpecOptionValue::Str(t) if t == microarch),
+            ) {
+                soft.push((!*native, SOFT_TARGET_WEIGHT as u64));
+            }
+        }
+
+        soft.extend(preferences);
+
+        self.objectives.push(soft);
+        self.objectives.extend(goals);
+
+        Ok(())
+    }
+
+    /// The objective of `maximize(item)` if `maximize`, or `minimize(item)`
+    /// otherwise, for a count of boolean constraints or an option with a
+    /// version domain.
+    fn goal(
+        &mut self,
+        item: &Constraint,
+        maximize: bool,
+    ) -> Result<Objective, Box<SolverError>> {
+        match item {
+            Constraint::NumOf(num_of) => num_of
+                .of
+                .iter()
+                .map(|c| {
+                    let lit = self.encode(c)?;
+                    Ok((if maximize { !lit } else { lit }, 1))
+                })
+                .collect(),
+
+            Constraint::SpecOption(option) if self.is_domain(option) => {
+                let OptionVars::Domain(domain) =
+                    self.option(&option.package_name, &option.option_name)?
+                else {
+                    unreachable!("option has a domain");
+                };
+
+                // Versions are newest first
+                let mut ranks = (0..)
+                    .zip(domain)
+                    .map(|(rank, (value, lit))| match value {
+                        SpecOptionValue::Version(_) => Ok((*lit, rank)),
+                        other => {
+                            Err(Box::new(SolverError::IncorrectValueType {
+                                expected: SpecOptionType::Version,
+                                received: other.to_type(),
+                            }))
+                        }
+                    })
+                    .collect::<Result<Objective, _>>()?;
+
+                if !maximize {
+                    let last = ranks.len().saturating_sub(1) as u64;
+
+                    for (_, rank) in &mut ranks {
+                        *rank = last - *rank;
+                    }
+                }
+
+                Ok(ranks)
+            }
+
+            _ => Err(unsupported(format!("'{item}' cannot be optimized"))),
+        }
+    }
+}
+
+impl Assignment for Encoding<'_> {
+    fn is_active(&self, name: &str) -> Result<bool, Box<SolverError>> {
+        Ok(self.value(self.toggle(name)?))
+    }
+
+    fn options(
+        &self,
+        name: &str,
+    ) -> Result<Vec<(String, SpecOptionValue)>, Box<SolverError>> {
+        let Some(options) = self.options.get(name) else {
+            return Ok(Vec::new());
+        };
+
+        let mut res = Vec::new();
+
+        for (option, vars) in options {
+            let value = match vars {
+                OptionVars::Bool(lit) => {
+                    SpecOptionValue::Bool(self.value(*lit))
+                }
+                OptionVars::Domain(domain) => domain
+                    .iter()
+                    .find(|(_, lit)| self.value(*lit))
+                    .map(|(value, _)| value.clone())
+                    .ok_or_else(|| SolverError::NoSolverVariable {
+                        package: name.to_string(),
+                        option: Some(option.clone()),
+                    })?,
+            };
+
+            res.push((option.clone(), value));
+        }
+
+        Ok(res)
+    }
+
+    fn holds(&self, flag_name: &str) -> bool {
+        self.conditions.get(flag_name).is_some_and(|&lit| self.value(lit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constraint::{IfThen, Maximize, Minimize, Value},
+        package::outline::PackageOutline,
+        spec::concrete::VERSION_OPTION,
+    };
+
+    fn version(v: &str) -> Version {
+        Version::new(v).unwrap()
+    }
+
+    /// A linear algebra stack in which `blas` is provided by exactly one of
+    /// `openblas` and `mkl`, and prefers `openblas`
+    fn example() -> Vec<PackageOutline> {
+        vec![
+            PackageOutline::builder("hpl")
+                .depends("blas")
+                .versions([version("2.2"), version("2.3")])
+                .build(),
+            PackageOutline::builder("blas")
+                .one_of(["openblas", "mkl"])
+                .depends_if("openblas", "openblas")
+                .depends_if("mkl", "mkl")
+                .option("openblas", true)
+                .build(),
+            PackageOutline::builder("openblas")
+                .versions([version("0.3.21"), version("0.3.26")])
+                .build(),
+            PackageOutline::builder("mkl").build(),
+            PackageOutline::builder("unused").build(),
+        ]
+    }
+
+    /// [`example`], with both `blas` providers forced on
+    fn conflicting() -> Vec<PackageOutline> {
+        let mut outlines = example();
+        outlines[1] = PackageOutline::builder("blas")
+            .one_of(["openblas", "mkl"])
+            .option("openblas", true)
+            .option("mkl", true)
+            .build();
+
+        outlines
+    }
+
+    /// [`example`], minimizing the version of `hpl` and maximizing the
+    /// version of `openblas`, so every version is decided by a goal
+    fn pinned() -> Vec<PackageOutline> {
+        let mut outlines = example();
+
+        outlines[0] = PackageOutline::builder("hpl")
+            .depends("blas")
+            .versions([version("2.2"), version("2.3")])
+            .constraint(Minimize::new(SpecOption::new("hpl", VERSION_OPTION)))
+            .build();
+
+        outlines[2] = PackageOutline::builder("openblas")
+            .versions([version("0.3.21"), version("0.3.26")])
+            .constraint(Maximize::new(SpecOption::new(
+                "openblas",
+                VERSION_OPTION,
+            )))
+            .build();
+
+        outlines
+    }
+
+    /// `app`, which prefers to enable `mpi` with `weight`, although enabling
+    /// it activates two more packages
+    fn preferring_mpi(weight: u64) -> Vec<PackageOutline> {
+        vec![
+            PackageOutline::builder("app")
+                .depends_if("mpi", "openmpi")
+                .prefer(SpecOption::new("app", "mpi").equals(true), weight)
+                .build(),
+            PackageOutline::builder("openmpi").depends("hwloc").build(),
+            PackageOutline::builder("hwloc").build(),
+        ]
+    }
+
+    fn outline(
+        outlines: Vec<PackageOutline>,
+        required: &[&str],
+    ) -> SpecOutline {
+        let mut outline = SpecOutline::new(outlines).unwrap();
+        outline.required = required.iter().map(ToString::to_string).collect();
+        outline.propagate_defaults().unwrap();
+        outline
+    }
+
+    fn resolve(outline: &SpecOutline) -> Result<Resolution, Box<SolverError>> {
+        SatResolver.resolve(outline, &mut SolveStats::new())
+    }
+
+    #[test]
+    fn activates_only_what_is_required() {
+        let Resolution::Sat(spec) =
+            resolve(&outline(example(), &["hpl"])).unwrap()
+        else {
+            panic!("expected a solution");
+        };
+
+        assert_eq!(
+            spec.packages.keys().collect::<Vec<_>>(),
+            ["blas", "hpl", "openblas"]
+        );
+        assert_eq!(
+            spec.packages["blas"].options["mkl"],
+            SpecOptionValue::Bool(false)
+        );
+    }
+
+    #[test]
+    fn prefers_newer_versions() {
+        let Resolution::Sat(spec) =
+            resolve(&outline(example(), &["hpl"])).unwrap()
+        else {
+            panic!("expected a solution");
+        };
+
+        assert_eq!(spec.packages["hpl"].version, Some(version("2.3")));
+        assert_eq!(spec.packages["openblas"].version, Some(version("0.3.26")));
+    }
+
+    #[test]
+    fn keeps_newer_versions_with_preferences() {
+        let mut outlines = example();
+        outlines[0] = PackageOutline::builder("hpl")
+            .depends("blas")
+            .versions([version("2.2"), version("2.3")])
+            .prefer(SpecOption::new("hpl", "shared").equals(true), 1)
+            .build();
+
+        let Resolution::Sat(spec) =
+            resolve(&outline(outlines, &["hpl"])).unwrap()
+        else {
+            panic!("expected a solution");
+        };
+
+        assert_eq!(
+            spec.packages["hpl"].options["shared"],
+            SpecOptionValue::Bool(true)
+        );
+        assert_eq!(spec.packages["hpl"].version, Some(version("2.3")));
+        assert_eq!(spec.packages["openblas"].version, Some(version("0.3.26")));
+    }
+
+    #[test]
+    fn weighs_preferences_against_packages() {
+        for (weight, mpi) in [(1, false), (3, true)] {
+            let Resolution::Sat(spec) =
+                resolve(&outline(preferring_mpi(weight), &["app"])).unwrap()
+            else {
+                panic!("expected a solution");
+            };
+
+            assert_eq!(
+                spec.packages["app"].options["mpi"],
+                SpecOptionValue::Bool(mpi),
+                "weight {weight}"
+            );
+            assert_eq!(spec.packages.contains_key("hwloc"), mpi);
+        }
+    }
+
+    #[test]
+    fn minimizes_goals_in_order() {
+        let Resolution::Sat(spec) =
+            resolve(&outline(pinned(), &["hpl"])).unwrap()
+        else {
+            panic!("expected a solution");
+        };
+
+        assert_eq!(spec.packages["hpl"].version, Some(version("2.2")));
+        assert_eq!(spec.packages["openblas"].version, Some(version("0.3.26")));
+
+        // The first goal enables one of `a` and `b`, and the second decides
+        // which
+        let one_of = |names: &[&str]| {
+            NumOf::new(
+                names.iter().map(|name| SpecOption::new("app", *name).into()),
+            )
+        };
+
+        let outlines = vec![
+            PackageOutline::builder("app")
+                .constraint(Maximize::new(one_of(&["a", "b"])))
+                .constraint(Minimize::new(one_of(&["c"])))
+                .constraint(Cmp::new(
+                    one_of(&["a", "b"]),
+                    CmpType::LessOrEqual,
+                    Value::new(1_i64),
+                ))
+                .constraint(IfThen::new(
+                    SpecOption::new("app", "a"),
+                    SpecOption::new("app", "c"),
+                ))
+                .build(),
+        ];
+
+        let Resolution::Sat(spec) =
+            resolve(&outline(outlines, &["app"])).unwrap()
+        else {
+            panic!("expected a solution");
+        };
+
+        let options = &spec.packages["app"].options;
+        assert_eq!(options["a"], SpecOptionValue::Bool(false));
+        assert_eq!(options["b"], SpecOptionValue::Bool(true));
+        assert_eq!(options["c"], SpecOptionValue::Bool(false));
+    }
+
+    #[test]
+    fn reports_the_conflict() {
+        let Resolution::Unsat(conflicts) =
+            resolve(&outline(conflicting(), &["hpl"])).unwrap()
+        else {
+            panic!("expected a conflict");
+        };
+
+        assert!(!conflicts.is_empty());
+        assert!(
+            conflicts.iter().all(|c| c.packages.iter().all(|p| p != "unused")),
+            "{conflicts:?}"
+        );
+
+        let kinds = conflicts.iter().map(|c| c.kind).collect::<Vec<_>>();
+        assert!(kinds.contains(&ConflictKind::Value), "{conflicts:?}");
+        assert!(kinds.contains(&ConflictKind::Constraint), "{conflicts:?}");
+    }
+
+    #[test]
+    fn rejects_options_it_cannot_encode() {
+        let outlines = vec![
+            PackageOutline::builder("hpl").option("threads", 4_i64).build(),
+        ];
+
+        let err = resolve(&outline(outlines, &["hpl"])).unwrap_err();
+        assert!(
+            matches!(*err, SolverError::Unsupported { resolver: "sat", .. }),
+            "{err}"
+        );
+    }
+
+    /// The backends prefer different versions, so are compared on everything
+    /// else, unless an objective decides every version
+    #[cfg(feature = "solver-z3")]
+    #[test]
+    fn agrees_with_z3() {
+        use crate::package::resolver::Z3Resolver;
+
+        for (outlines, required, versions) in [
+            (example(), &["hpl"][..], false),
+            (example(), &["hpl", "mkl"][..], false),
+            (example(), &["openblas", "unused"][..], false),
+            (conflicting(), &["hpl"][..], false),
+            (preferring_mpi(1), &["app"][..], true),
+            (pinned(), &["hpl"][..], true),
+        ] {
+            let outline = outline(outlines, required);
+
+            let sat = resolve(&outline).unwrap();
+            let z3 = Z3Resolver::default()
+                .resolve(&outline, &mut SolveStats::new())
+                .unwrap();
+
+            match (sat, z3) {
+                (Resolution::Sat(sat), Resolution::Sat(z3)) => {
+                    let strip = |spec: ConcreteSpec| {
+                        spec.packages
+                            .into_values()
+                            .map(|p| {
+                                let version = p.version.filter(|_| versions);
+                                (p.name, version, p.options, p.dependencies)
+                            })
+                            .collect::<Vec<_>>()
+                    };
+
+                    assert_eq!(strip(sat), strip(z3), "{required:?}");
+                }
+                (Resolution::Unsat(sat), Resolution::Unsat(z3)) => {
+                    assert!(!sat.is_empty() && !z3.is_empty());
+                }
+                (sat, z3) => panic!("{required:?}: sat {sat:?}, z3 {z3:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! The z3 backend.
+
+use std::path::PathBuf;
+
+use z3::SatResult;
+
+use crate::{
+    package::{
+        outline::{SolverError, SpecOutline, write_smtlib},
+        resolver::{Resolution, Resolver},
+        solver::{reason_unknown, unsat_core},
+        stats::SolveStats,
+    },
+    spec::ConcreteSpec,
+};
+
+/// Concretizes with the z3 solver, which supports every constraint.
+#[derive(Clone, Debug, Default)]
+pub struct Z3Resolver {
+    /// Write the solver to this file in SMT-LIB2 format before checking it
+    pub dump_smt: Option<PathBuf>,
+}
+
+impl Resolver for Z3Resolver {
+    fn name(&self) -> &'static str {
+        "z3"
+    }
+
+    fn resolve(
+        &self,
+        outline: &SpecOutline,
+        stats: &mut SolveStats,
+    ) -> Result<Resolution, Box<SolverError>> {
+        let (optimizer, registry) = outline.gen_spec_solver_profiled(stats)?;
+
+        if let Some(path) = &self.dump_smt {
+            write_smtlib(path, &outline.render_smtlib(&optimizer))?;
+        }
+
+        let result = stats.time("check", || optimizer.check(&[]));
+        stats.record_solver(&optimizer.get_statistics());
+
+        tracing::info!("solver result: {result:?}");
+
+        match result {
+            SatResult::Unsat => {
+                Ok(Resolution::Unsat(unsat_core(&optimizer, &registry)))
+            }
+            SatResult::Unknown => {
+                Ok(Resolution::Unknown(reason_unknown(&optimizer)))
+            }
+            SatResult::Sat => {
+                let Some(model) = optimizer.get_model() else {
+                    return Ok(Resolution::Unknown(
+                        "the solver returned no model".to_string(),
+                    ));
+                };
+
+                stats
+                    .time("model extraction", || {
+                        ConcreteSpec::from_model(outline, &model, &registry)
+                    })
+                    .map(Resolution::Sat)
+            }
+        }
+    }
+}
@@ -0,0 +1,539 @@
+//! Options declared by a package, with their types and valid values.
+//!
+//! Without a schema, the type of every option is inferred from the
+//! constraints which use it, so a typo in an option name or a value of the
+//! wrong type silently creates a new option. A package which declares its
+//! options (see [`PackageOutline::declare_option`]) is checked against its
+//! schema before solving (see [`SpecOutline::check_schemas`]):
+//! - every option of the package referred to by a constraint, an explicit
+//!   value or one of its own defaults must be declared
+//! - every value set or compared for equality with a declared option must
+//!   have its type, and be one of its valid values if it has any
+//!
+//! The solver is then given the declared types rather than inferring them,
+//! so constraints which use an option with the wrong type are reported by
+//! the type checker.
+//!
+//! The options every package has, listed in [`IMPLICIT_OPTIONS`], do not
+//! need to be declared.
+//!
+//! Packages without a schema declare their options implicitly: an option is
+//! declared if the package gives it a default, sets it itself or refers to it
+//! in one of its own constraints. Referring to any other option, or to a
+//! package which does not exist, is an error unless the outline is
+//! [`permissive`](SpecOutline::permissive), in which case it creates a new
+//! solver variable as it did before schemas existed.
+
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraint::{CmpType, Constraint, SpecOption},
+    package::outline::{PackageOutline, SolverError, SpecOutline},
+    spec::{
+        SpecOptionType, SpecOptionValue, concrete::VERSION_OPTION,
+        target::TARGET_OPTION,
+    },
+    util::suggest,
+};
+
+/// Options every package has, which do not need to be declared
+pub const IMPLICIT_OPTIONS: [&str; 2] = [VERSION_OPTION, TARGET_OPTION];
+
+/// The options each package declares without a schema, by package name
+type Implicit<'a> = HashMap<&'a str, HashSet<&'a str>>;
+
+/// Push every option `constraint` refers to onto `res`.
+fn references<'a>(constraint: &'a Constraint, res: &mut Vec<&'a SpecOption>) {
+    match constraint {
+        Constraint::SpecOption(opt) => res.push(opt),
+        Constraint::Matches(matches) => res.push(&matches.option),
+        Constraint::Arith(arith) => {
+            references(&arith.lhs, res);
+            references(&arith.rhs, res);
+        }
+        Constraint::Cmp(cmp) => {
+            references(&cmp.lhs, res);
+            references(&cmp.rhs, res);
+        }
+        Constraint::IfThen(if_then) => {
+            references(&if_then.cond, res);
+            references(&if_then.then, res);
+        }
+        Constraint::NumOf(num_of) => {
+            for c in &num_of.of {
+                references(c, res);
+            }
+        }
+        Constraint::Maximize(m) => references(&m.item, res),
+        Constraint::Minimize(m) => references(&m.item, res),
+        Constraint::Prefer(p) => references(&p.item, res),
+        Constraint::Custom(_)
+        | Constraint::Depends(_)
+        | Constraint::Value(_)
+        | Constraint::WhenPlatform(_) => (),
+    }
+}
+
+/// The declaration of an option of a package
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionSchema {
+    /// The type of the option's value
+    #[serde(rename = "type")]
+    pub dtype: SpecOptionType,
+
+    /// The value of the option if nothing else sets it, which is inherited by
+    /// dependencies as with [`PackageOutline::set_defaults`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<SpecOptionValue>,
+
+    /// The values the option may take. Any value of the right type is valid
+    /// if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<SpecOptionValue>,
+
+    /// What the option does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl OptionSchema {
+    /// An option of type `dtype`, with no default and any value valid.
+    #[must_use]
+    pub const fn new(dtype: SpecOptionType) -> Self {
+        Self { dtype, default: None, values: Vec::new(), description: None }
+    }
+
+    /// Set the value of the option if nothing else sets it.
+    #[must_use]
+    pub fn with_default(mut self, value: impl Into<SpecOptionValue>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    /// Restrict the option to `values`.
+    #[must_use]
+    pub fn with_values<V: Into<SpecOptionValue>>(
+        mut self,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        self.values = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Describe what the option does.
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Whether `value` has the declared type and is one of the valid values,
+    /// if any are given
+    #[must_use]
+    pub fn accepts(&self, value: &SpecOptionValue) -> bool {
+        value.is_type(self.dtype)
+            && (self.values.is_empty() || self.values.contains(value))
+    }
+
+    /// Check that `value` is valid for the option `package:option`.
+    ///
+    /// # Errors
+    /// Errors if [`Self::accepts`] does not hold.
+    pub fn check(
+        &self,
+        package: &str,
+        option: &str,
+        value: &SpecOptionValue,
+    ) -> Result<(), Box<SolverError>> {
+        if self.accepts(value) {
+            return Ok(());
+        }
+
+        tracing::error!("invalid value {value} for '{package}:{option}'");
+
+        Err(Box::new(SolverError::InvalidOptionValue {
+            package: package.to_string(),
+            option: option.to_string(),
+            value: value.clone(),
+            schema: self.clone(),
+        }))
+    }
+
+    /// Check that the declaration of `package:option` is consistent: the
+    /// type can be declared, and the default and valid values have the type.
+    fn validate(
+        &self,
+        package: &str,
+        option: &str,
+    ) -> Result<(), Box<SolverError>> {
+        if !SpecOptionType::DECLARABLE.contains(&self.dtype) {
+            let msg = format!(
+                "'{package}:{option}' cannot be declared with type {}",
+                self.dtype
+            );
+            tracing::error!("{msg}");
+            return Err(Box::new(SolverError::InvalidConstraint(msg)));
+        }
+
+        for value in &self.values {
+            if !value.is_type(self.dtype) {
+                tracing::error!(
+                    "valid value {value} of '{package}:{option}' is not a {}",
+                    self.dtype
+                );
+
+                return Err(Box::new(SolverError::IncorrectValueType {
+                    expected: self.dtype,
+                    received: value.to_type(),
+                }));
+            }
+        }
+
+        if let Some(default) = &self.default {
+            self.check(package, option, default)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl OptionSchema {
+    #[new]
+    #[pyo3(signature = (dtype, default=None, values=None, description=None))]
+    fn py_new(
+        dtype: &str,
+        default: Option<SpecOptionValue>,
+        values: Option<Vec<SpecOptionValue>>,
+        description: Option<String>,
+    ) -> PyResult<Self> {
+        let dtype =
+            dtype.parse().map_err(pyo3::exceptions::PyValueError::new_err)?;
+
+        Ok(Self {
+            dtype,
+            default,
+            values: values.unwrap_or_default(),
+            description,
+        })
+    }
+
+    #[getter]
+    #[pyo3(name = "dtype")]
+    const fn py_dtype(&self) -> &'static str {
+        self.dtype.name()
+    }
+
+    #[getter]
+    #[pyo3(name = "default")]
+    fn py_default(&self) -> Option<SpecOptionValue> {
+        self.default.clone()
+    }
+
+    #[getter]
+    #[pyo3(name = "values")]
+    fn py_values(&self) -> Vec<SpecOptionValue> {
+        self.values.clone()
+    }
+
+    #[getter]
+    #[pyo3(name = "description")]
+    fn py_description(&self) -> Option<String> {
+        self.description.clone()
+    }
+}
+
+impl PackageOutline {
+    /// Declare the option `name`. A declared default is also set as the
+    /// package's default (see [`Self::set_defaults`]), unless it already has
+    /// one.
+    pub fn declare_option(
+        &mut self,
+        name: impl Into<String>,
+        schema: OptionSchema,
+    ) {
+        let name = name.into();
+
+        if let Some(default) = &schema.default {
+            self.set_defaults
+                .entry(name.clone())
+                .or_insert_with(|| Some(default.clone()));
+        }
+
+        self.schema.insert(name, schema);
+    }
+
+    /// The declared options of this package, sorted by name.
+    pub fn sorted_schema(
+        &self,
+    ) -> impl Iterator<Item = (&String, &OptionSchema)> {
+        let mut options = self.schema.iter().collect::<Vec<_>>();
+        options.sort_by_key(|(name, _)| *name);
+        options.into_iter()
+    }
+
+    /// The declaration of `option`, or an error if this package has a
+    /// schema which does not declare it. `None` if the package has no schema
+    /// or the option is implicit.
+    ///
+    /// # Errors
+    /// Errors if the option is not declared.
+    pub fn declared(
+        &self,
+        option: &str,
+    ) -> Result<Option<&OptionSchema>, Box<SolverError>> {
+        if self.schema.is_empty() || IMPLICIT_OPTIONS.contains(&option) {
+            return Ok(None);
+        }
+
+        self.schema.get(option).map(Some).ok_or_else(|| {
+            tracing::error!(
+                "'{}' does not declare option '{option}'",
+                self.name
+            );
+
+            Box::new(SolverError::MissingVariable {
+                package: self.name.clone(),
+                name: option.to_string(),
+                suggestion: suggest::closest(
+                    option,
+                    self.schema.keys().map(String::as_str),
+                )
+                .map(str::to_string),
+            })
+        })
+    }
+}
+
+impl SpecOutline {
+    /// The options each package without a schema declares implicitly: those
+    /// it gives a default, sets itself or refers to in its own constraints.
+    fn implicit_options(&self) -> Implicit<'_> {
+        self.graph
+            .node_weights()
+            .filter(|package| package.schema.is_empty())
+            .map(|package| {
+                let name = package.name.as_str();
+
+                let defaults = package
+                    .set_defaults
+                    .keys()
+                    .filter(|option| self.origins.get(name, option).is_none());
+
+                let set = package.set_options.keys().filter(|option| {
+                    !package.requested_options.contains(*option)
+                });
+
+                let mut used = Vec::new();
+
+                for constraint in package.all_constraints() {
+                    references(constraint, &mut used);
+                }
+
+                let used = used
+                    .into_iter()
+                    .filter(|opt| opt.package_name == name)
+                    .map(|opt| opt.option_name.as_str());
+
+                let options = defaults
+                    .chain(set)
+                    .map(String::as_str)
+                    .chain(used)
+                    .collect();
+
+                (name, options)
+            })
+            .collect()
+    }
+
+    /// The declaration of `package:option`, or `None` if the package has no
+    /// schema or the option is implicit.
+    ///
+    /// # Errors
+    /// Unless the outline is [`permissive`](Self::permissive), errors if the
+    /// package does not exist or does not declare the option.
+    fn declared<'a>(
+        &'a self,
+        implicit: &Implicit<'_>,
+        package: &str,
+        option: &str,
+    ) -> Result<Option<&'a OptionSchema>, Box<SolverError>> {
+        let Some(idx) = self.lookup.get(package) else {
+            if self.permissive {
+                return Ok(None);
+            }
+
+            tracing::error!("'{package}:{option}' refers to a missing package");
+
+            return Err(Box::new(SolverError::MissingPackage {
+                name: package.to_string(),
+                suggestion: suggest::closest(
+                    package,
+                    self.lookup.keys().map(String::as_str),
+                )
+                .map(str::to_string),
+            }));
+        };
+
+        let outline = &self.graph[*idx];
+
+        if self.permissive {
+            return Ok(outline.schema.get(option));
+        }
+
+        let Some(options) = implicit.get(package) else {
+            return outline.declared(option);
+        };
+
+        if IMPLICIT_OPTIONS.contains(&option) || options.contains(option) {
+            return Ok(None);
+        }
+
+        tracing::error!("'{package}' does not declare option '{option}'");
+
+        Err(Box::new(SolverError::MissingVariable {
+            package: package.to_string(),
+            name: option.to_string(),
+            suggestion: suggest::closest(option, options.iter().copied())
+                .map(str::to_string),
+        }))
+    }
+
+    /// Check the options referred to by every grounded package (see
+    /// [`Self::grounded`]) and active policy against the schemas of their
+    /// packages (see [`crate::package::schema`]).
+    ///
+    /// # Errors
+    /// Errors if a value is invalid or a declaration is inconsistent, or, if
+    /// the outline is not [`permissive`](Self::permissive), an option or
+    /// package is unknown.
+    pub fn check_schemas(&self) -> Result<(), Box<SolverError>> {
+        let implicit = self.implicit_options();
+
+        for idx in self.grounded() {
+            let package = &self.graph[idx];
+
+            for (name, schema) in package.sorted_schema() {
+                schema.validate(&package.name, name)?;
+            }
+
+            for (name, value) in package.sorted_set_options() {
+                if let Some(schema) =
+                    self.declared(&implicit, &package.name, name)?
+                {
+                    schema.check(&package.name, name, value)?;
+                }
+            }
+
+            for (name, value) in &package.set_defaults {
+                // Inherited defaults may be for options the package does not
+                // have, but must be valid for those it does
+                let own = self.origins.get(&package.name, name).is_none();
+
+                let schema = if own {
+                    self.declared(&implicit, &package.name, name)?
+                } else {
+                    package.schema.get(name)
+                };
+
+                if let (Some(schema), Some(value)) = (schema, value) {
+                    schema.check(&package.name, name, value)?;
+                }
+            }
+
+            for constraint in package.all_constraints() {
+                self.check_constraint(&implicit, constraint)?;
+            }
+        }
+
+        for policy in self.active_policies() {
+            self.check_constraint(&implicit, &policy.constraint)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the options `constraint` refers to, and the values they are
+    /// compared for equality with, against their packages' schemas.
+    fn check_constraint(
+        &self,
+        implicit: &Implicit<'_>,
+        constraint: &Constraint,
+    ) -> Result<(), Box<SolverError>> {
+        let declared = |opt: &SpecOption| {
+            self.declared(implicit, &opt.package_name, &opt.option_name)
+        };
+
+        match constraint {
+            Constraint::SpecOption(opt) => {
+                declared(opt)?;
+            }
+            Constraint::Matches(matches) => {
+                declared(&matches.option)?;
+            }
+
+            Constraint::Cmp(cmp) => {
+                if matches!(cmp.op, CmpType::Equal | CmpType::NotEqual) {
+                    let operands = [(&cmp.lhs, &cmp.rhs), (&cmp.rhs, &cmp.lhs)];
+
+                    for (lhs, rhs) in operands {
+                        if let (
+                            Constraint::SpecOption(opt),
+                            Constraint::Value(v),
+                        ) = (lhs, rhs)
+                            && let Some(schema) = declared(opt)?
+                        {
+                            schema.check(
+                                &opt.package_name,
+                                &opt.option_name,
+                                &v.value,
+                            )?;
+                        }
+                    }
+                }
+
+                self.check_constraint(implicit, &cmp.lhs)?;
+                self.check_constraint(implicit, &cmp.rhs)?;
+            }
+
+            Constraint::Arith(arith) => {
+                self.check_constraint(implicit, &arith.lhs)?;
+                self.check_constraint(implicit, &arith.rhs)?;
+            }
+
+            Constraint::IfThen(if_then) => {
+                self.check_constraint(implicit, &if_then.cond)?;
+                self.check_constraint(implicit, &if_then.then)?;
+            }
+
+            Constraint::NumOf(num_of) => {
+                for c in &num_of.of {
+                    self.check_constraint(implicit, c)?;
+                }
+            }
+
+            Constraint::Maximize(m) => {
+                self.check_constraint(implicit, &m.item)?;
+            }
+            Constraint::Minimize(m) => {
+                self.check_constraint(implicit, &m.item)?;
+            }
+            Constraint::Prefer(p) => {
+                self.check_constraint(implicit, &p.item)?;
+            }
+
+            Constraint::Custom(_)
+            | Constraint::Depends(_)
+            | Constraint::Value(_)
+            | Constraint::WhenPlatform(_) => (),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! Per-package settings from the user configuration.
+//!
+//! Settings are configured under `packages` in the configuration file, by
+//! package name, and change how a package is concretized without editing its
+//! package file:
+//!
+//! ```yaml
+//! packages:
+//!   openmpi:
+//!     version: "4.1.>"
+//!     options: +cuda fabrics=ofi
+//!   intelmpi:
+//!     buildable: false
+//! ```
+//!
+//! - `version` pins the version of the package, and may be a wildcard (see
+//!   [`Version`]). It must be quoted, since YAML would read `4.10` as a number
+//! - `options` forces options of the package, written as in a spec string
+//!   (see [`crate::spec::parse`]) without the package name. A `name=min:max`
+//!   range constrains the option rather than setting it
+//! - `buildable: false` stops zpack building the package. Since zpack cannot
+//!   yet use a package it did not build, such a package is excluded from
+//!   solves entirely
+//!
+//! Each setting is added to solves as a policy (see [`crate::package::policy`])
+//! naming the setting, so a setting which conflicts with the requested specs
+//! is reported as `packages.openmpi.version` in the unsatisfiable core. The
+//! options and versions a setting fixes are reported as set by the user
+//! configuration (see [`Provenance`](crate::spec::provenance::Provenance)).
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraint::{
+        Cmp, CmpType, Depends, InRange, SpecOption, Value, syntax::render,
+    },
+    package::{policy::PolicyConstraint, version::Version},
+    spec::{concrete::VERSION_OPTION, parse::SpecRequest},
+};
+
+/// The settings of a package, as written in the configuration file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackageSettings {
+    /// The version to pin the package to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Options to force, in spec syntax, such as `+cuda fabrics=ofi`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<String>,
+
+    /// Whether zpack may build the package
+    pub buildable: bool,
+}
+
+impl Default for PackageSettings {
+    fn default() -> Self {
+        Self { version: None, options: None, buildable: true }
+    }
+}
+
+/// Parsed [`PackageSettings`], ready to be added to a solve
+#[derive(Clone, Debug, Default)]
+pub struct ConfiguredPackage {
+    /// One policy for each version, option, range and restriction set
+    pub policies: Vec<PolicyConstraint>,
+
+    /// The options the settings fix, including the version
+    pub options: HashSet<String>,
+}
+
+impl PackageSettings {
+    /// Parse the settings of the package `name` into policies.
+    ///
+    /// # Errors
+    /// Errors with a rendered message if the version or options are invalid.
+    pub fn parse(&self, name: &str) -> Result<ConfiguredPackage, String> {
+        let mut res = ConfiguredPackage::default();
+
+        if let Some(version) = &self.version {
+            let parsed = Version::new(version)
+                .map_err(|e| format!("invalid version '{version}': {e:?}"))?;
+
+            res.options.insert(VERSION_OPTION.to_string());
+            res.policies.push(PolicyConstraint {
+                description: format!(
+                    "packages.{name}.version: pinned to {version} by the user \
+                     configuration"
+                ),
+                constraint: Cmp::new(
+                    SpecOption::new(name, VERSION_OPTION),
+                    CmpType::Equal,
+                    Value::new(parsed),
+                )
+                .into(),
+            });
+        }
+
+        if let Some(options) = &self.options {
+            let txt = format!("{name} {options}");
+
+            let request = SpecRequest::parse(&txt).map_err(render)?;
+
+            if request.version.is_some() || request.version_range.is_some() {
+                return Err(format!(
+                    "'{options}' gives a version; set 'version' instead"
+                ));
+            }
+
+            if !request.dependencies.is_empty() {
+                return Err(format!(
+                    "'{options}' gives a dependency spec; configure each \
+                     package separately"
+                ));
+            }
+
+            for opt in &request.options {
+                if opt.propagate {
+                    return Err(format!(
+                        "'{}' applies to every package, so cannot be set for \
+                         '{name}' alone",
+                        opt.name
+                    ));
+                }
+
+                res.options.insert(opt.name.clone());
+                res.policies.push(PolicyConstraint {
+                    description: format!(
+                        "packages.{name}.options: {} set to {} by the user \
+                         configuration",
+                        opt.name, opt.value
+                    ),
+                    constraint: Cmp::new(
+                        SpecOption::new(name, &opt.name),
+                        CmpType::Equal,
+                        Value::new(opt.value.clone()),
+                    )
+                    .into(),
+                });
+            }
+
+            for range in &request.ranges {
+                res.policies.push(PolicyConstraint {
+                    description: format!(
+                        "packages.{name}.options: {} limited to {}:{} by the \
+                         user configuration",
+                        range.name, range.min, range.max
+                    ),
+                    constraint: InRange::new(
+                        name,
+                        &range.name,
+                        range.min.clone(),
+                        range.max.clone(),
+                        true,
+                    )
+                    .into(),
+                });
+            }
+        }
+
+        if !self.buildable {
+            res.policies.push(PolicyConstraint {
+                description: format!(
+                    "packages.{name}.buildable: '{name}' may not be built, \
+                     and zpack cannot use a package it does not build"
+                ),
+                constraint: Cmp::new(
+                    Depends::new(name.to_string()),
+                    CmpType::Equal,
+                    Value::new(false),
+                )
+                .into(),
+            });
+        }
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,311 @@
+//! Long-lived spec solvers.
+//!
+//! [`SpecSolver`] owns a [`SpecOutline`] together with the solver and
+//! registry generated from it. Since the registry owns its names, the whole
+//! solver can be stored, checked and inspected long after it was generated,
+//! including from Python.
+
+use std::path::Path;
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+#[cfg(feature = "solver-z3")]
+use z3::{Optimize, SatResult};
+
+use crate::{
+    package::{
+        BuiltRegistry,
+        outline::{SolverError, SpecOutline, write_smtlib},
+        resolver::{Conflict, ConflictKind},
+        stats::SolveStats,
+    },
+    spec::ConcreteSpec,
+};
+#[cfg(feature = "python")]
+use crate::{
+    package::{outline::PackageOutline, resolver::SolveResult},
+    spec::platform::Platform,
+};
+
+/// The constraints in the unsat core of `optimizer`, which was generated
+/// with `registry`.
+pub(crate) fn unsat_core(
+    optimizer: &Optimize,
+    registry: &BuiltRegistry,
+) -> Vec<Conflict> {
+    optimizer
+        .get_unsat_core()
+        .iter()
+        .map(|lit| {
+            registry.tracked_constraint(lit).cloned().unwrap_or_else(|| {
+                Conflict::new(ConflictKind::Constraint, &[], lit.to_string())
+            })
+        })
+        .collect()
+}
+
+/// Why `optimizer` could not decide satisfiability, after its check returned
+/// [`SatResult::Unknown`].
+pub(crate) fn reason_unknown(optimizer: &Optimize) -> String {
+    optimizer
+        .get_reason_unknown()
+        .unwrap_or_else(|| "no reason given".to_string())
+}
+
+/// Run `f` with the GIL released.
+///
+/// z3's objects belong to the thread which created them, so they are not
+/// `Send` and cannot be given to [`Python::detach`] directly. `detach` runs
+/// `f` on the calling thread, however, so they never leave it.
+#[cfg(feature = "python")]
+fn detached<T>(py: Python<'_>, f: impl FnOnce() -> T) -> T {
+    struct SameThread<T>(T);
+
+    // SAFETY: `Python::detach` calls its closure on the current thread, so
+    // neither `f` nor its result is sent to another thread
+    #[allow(clippy::non_send_fields_in_send_ty)]
+    unsafe impl<T> Send for SameThread<T> {}
+
+    impl<T> SameThread<T> {
+        fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    let f = SameThread(f);
+    py.detach(move || SameThread(f.into_inner()())).into_inner()
+}
+
+#[cfg_attr(feature = "python", pyclass(unsendable))]
+pub struct SpecSolver {
+    outline: SpecOutline,
+    optimizer: Optimize,
+    registry: BuiltRegistry,
+    stats: SolveStats,
+}
+
+impl SpecSolver {
+    /// Propagate the default values of `outline` and generate its solver.
+    ///
+    /// # Errors
+    /// Errors if default values conflict, the outline fails to type check or
+    /// a constraint cannot be converted to solver clauses.
+    pub fn new(mut outline: SpecOutline) -> Result<Self, Box<SolverError>> {
+        let mut stats = SolveStats::new();
+
+        stats.time("default propagation", || outline.propagate_defaults())?;
+
+        let (optimizer, registry) =
+            outline.gen_spec_solver_profiled(&mut stats)?;
+
+        Ok(Self { outline, optimizer, registry, stats })
+    }
+
+    #[must_use]
+    pub const fn outline(&self) -> &SpecOutline {
+        &self.outline
+    }
+
+    #[must_use]
+    pub const fn optimizer(&self) -> &Optimize {
+        &self.optimizer
+    }
+
+    #[must_use]
+    pub const fn registry(&self) -> &BuiltRegistry {
+        &self.registry
+    }
+
+    /// Time spent in each phase so far, and the statistics of the last check
+    #[must_use]
+    pub const fn stats(&self) -> &SolveStats {
+        &self.stats
+    }
+
+    #[must_use]
+    pub fn into_stats(self) -> SolveStats {
+        self.stats
+    }
+
+    /// Check whether the outline is satisfiable.
+    pub fn check(&mut self) -> SatResult {
+        let result = self.stats.time("check", || self.optimizer.check(&[]));
+        self.stats.record_solver(&self.optimizer.get_statistics());
+
+        tracing::info!("solver result: {result:?}");
+
+        result
+    }
+
+    /// The conflicting constraints, after [`Self::check`] found the outline
+    /// unsatisfiable.
+    #[must_use]
+    pub fn unsat_core(&self) -> Vec<Conflict> {
+        unsat_core(&self.optimizer, &self.registry)
+    }
+
+    /// Why [`Self::check`] could not decide satisfiability, after it returned
+    /// [`SatResult::Unknown`].
+    #[must_use]
+    pub fn reason_unknown(&self) -> String {
+        reason_unknown(&self.optimizer)
+    }
+
+    /// The concrete spec solved by [`Self::check`], or `None` if there is no
+    /// model because the outline has not been found satisfiable.
+    ///
+    /// # Errors
+    /// Errors if a package or option has no solver variable.
+    pub fn concrete_spec(
+        &mut self,
+    ) -> Result<Option<ConcreteSpec>, Box<SolverError>> {
+        let Some(model) = self.optimizer.get_model() else {
+            return Ok(None);
+        };
+
+        let (outline, registry) = (&self.outline, &self.registry);
+
+        self.stats
+            .time("model extraction", || {
+                ConcreteSpec::from_model(outline, &model, registry)
+            })
+            .map(Some)
+    }
+
+    /// Render the solver in SMT-LIB2 format, as with
+    /// [`SpecOutline::to_smtlib`].
+    #[must_use]
+    pub fn to_smtlib(&self) -> String {
+        self.outline.render_smtlib(&self.optimizer)
+    }
+
+    /// Write [`Self::to_smtlib`] to `path`.
+    ///
+    /// # Errors
+    /// Errors if the file cannot be written.
+    pub fn export_smtlib(&self, path: &Path) -> Result<(), Box<SolverError>> {
+        write_smtlib(path, &self.to_smtlib())
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl SpecSolver {
+    /// * `outlines`: Every package which may be part of the spec
+    /// * `roots`: The packages which must be part of the spec
+    /// * `platform`: The platform to concretize for. Defaults to the host
+    /// * `deterministic`: Fix the solver's random seeds
+    /// * `permissive`: Allow references to options no package declares
+    ///
+    /// The GIL is released while the solver is generated, and while it is
+    /// checked by `check` and `solve`.
+    ///
+    /// # Errors
+    /// Errors if the outlines are invalid.
+    #[new]
+    #[pyo3(signature = (
+        outlines, roots, platform = None, deterministic = false,
+        permissive = false
+    ))]
+    fn py_new(
+        py: Python<'_>,
+        outlines: Vec<PackageOutline>,
+        roots: Vec<String>,
+        platform: Option<Platform>,
+        deterministic: bool,
+        permissive: bool,
+    ) -> PyResult<Self> {
+        let to_py =
+            |e: Box<SolverError>| PyRuntimeError::new_err(e.to_string());
+
+        let mut outline = SpecOutline::new(outlines).map_err(to_py)?;
+        outline.required = roots;
+        outline.platform = platform.unwrap_or_default();
+        outline.deterministic = deterministic;
+        outline.permissive = permissive;
+
+        detached(py, || Self::new(outline)).map_err(to_py)
+    }
+
+    /// Check whether the spec is satisfiable, returning `"sat"`, `"unsat"`
+    /// or `"unknown"`.
+    #[pyo3(name = "check")]
+    fn py_check(&mut self, py: Python<'_>) -> &'static str {
+        match detached(py, || self.check()) {
+            SatResult::Sat => "sat",
+            SatResult::Unsat => "unsat",
+            SatResult::Unknown => "unknown",
+        }
+    }
+
+    /// Descriptions of the conflicting constraints, after `check` returned
+    /// `"unsat"`. See `conflicts` for their kinds and packages.
+    #[pyo3(name = "unsat_core")]
+    fn py_unsat_core(&self) -> Vec<String> {
+        self.unsat_core().iter().map(ToString::to_string).collect()
+    }
+
+    /// The conflicting constraints, after `check` returned `"unsat"`.
+    #[pyo3(name = "conflicts")]
+    fn py_conflicts(&self) -> Vec<Conflict> {
+        self.unsat_core()
+    }
+
+    /// Why the solver gave up, after `check` returned `"unknown"`.
+    #[pyo3(name = "reason_unknown")]
+    fn py_reason_unknown(&self) -> String {
+        self.reason_unknown()
+    }
+
+    /// Check the spec and return the outcome: a `SolveResult.Sat` with the
+    /// solved spec, a `SolveResult.Unsat` with the conflicting constraints or
+    /// a `SolveResult.Unknown` with the reason the solver gave up.
+    ///
+    /// # Errors
+    /// Errors if a package or option has no solver variable.
+    #[pyo3(name = "solve")]
+    fn py_solve(&mut self, py: Python<'_>) -> PyResult<SolveResult> {
+        let res = detached(py, || self.check());
+
+        let spec = match res {
+            SatResult::Sat => self.py_concrete_spec()?,
+            SatResult::Unsat | SatResult::Unknown => None,
+        };
+
+        let stats = self.stats.clone();
+
+        Ok(match (res, spec) {
+            (SatResult::Sat, Some(spec)) => SolveResult::Sat { spec, stats },
+            (SatResult::Sat, None) => SolveResult::Unknown {
+                reason: "the solver returned no model".to_string(),
+                stats,
+            },
+            (SatResult::Unsat, _) => {
+                SolveResult::Unsat { conflicts: self.unsat_core(), stats }
+            }
+            (SatResult::Unknown, _) => {
+                SolveResult::Unknown { reason: self.reason_unknown(), stats }
+            }
+        })
+    }
+
+    /// The solved spec, or `None` if `check` has not returned `"sat"`.
+    ///
+    /// # Errors
+    /// Errors if a package or option has no solver variable.
+    #[pyo3(name = "concrete_spec")]
+    fn py_concrete_spec(&mut self) -> PyResult<Option<ConcreteSpec>> {
+        self.concrete_spec().map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
+    #[getter]
+    #[pyo3(name = "stats")]
+    fn py_stats(&self) -> SolveStats {
+        self.stats.clone()
+    }
+
+    #[pyo3(name = "to_smtlib")]
+    fn py_to_smtlib(&self) -> String {
+        self.to_smtlib()
+    }
+}
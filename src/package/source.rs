@@ -0,0 +1,83 @@
+//! Source archives for packages.
+//!
+//! A package declares one [`Source`] per version it can be built from, plus
+//! optionally one without a version which is used as a fallback. The source
+//! matching the concretized version is recorded on the
+//! [`ConcretePackage`](crate::spec::ConcretePackage).
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "python")]
+use crate::util::digest::ParseError;
+use crate::{package::version::Version, util::digest::Checksum};
+
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Source {
+    pub url: String,
+
+    /// Checksum of the archive
+    pub checksum: Checksum,
+
+    /// The version this is the source for. `None` matches any version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+}
+
+impl Source {
+    /// Select the source for `version` from `sources`.
+    ///
+    /// An exact version match is preferred over a source without a version.
+    #[must_use]
+    pub fn select<'a>(
+        sources: &'a [Self],
+        version: Option<&Version>,
+    ) -> Option<&'a Self> {
+        sources
+            .iter()
+            .find(|s| s.version.is_some() && s.version.as_ref() == version)
+            .or_else(|| sources.iter().find(|s| s.version.is_none()))
+    }
+
+    /// The file name of the archive, taken from the last component of the
+    /// URL.
+    #[must_use]
+    pub fn file_name(&self) -> &str {
+        crate::fetch::url_file_name(&self.url)
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "Source({}@{version})", self.url),
+            None => write!(f, "Source({})", self.url),
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Source {
+    #[new]
+    #[pyo3(signature = (url, checksum, version = None))]
+    fn py_new(
+        url: &str,
+        checksum: &str,
+        version: Option<Version>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            url: url.to_string(),
+            checksum: checksum.parse().map_err(|e: ParseError| {
+                PyValueError::new_err(e.to_string())
+            })?,
+            version,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
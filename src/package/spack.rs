@@ -0,0 +1,949 @@
+//! Conversion of Spack packages into package outlines.
+//!
+//! Spack packages are Python classes whose bodies call directives, such as
+//! `version("1.3", sha256="...")` or `depends_on("zlib@1.2:", when="+zlib")`.
+//! The directives of a package file are recorded by executing it against
+//! stand-in Spack modules (see [`crate::interface::spack`]) and converted here,
+//! on a best-effort basis:
+//!
+//! - `version` adds the version to the package's version domain, and a
+//!   [`Source`] if it has a `sha256` checksum and a URL, either its own or the
+//!   package's `url` with the version replaced
+//! - `variant` sets the option's default and, for variants with a list of
+//!   `values`, restricts the option to one of them. Conditional variants are
+//!   always available
+//! - `depends_on` and `extends` add a dependency, with the versions and
+//!   variants of the dependency spec as constraints on the dependency
+//! - `conflicts` and `requires` add a constraint forbidding or requiring the
+//!   spec, when the `when` spec holds
+//! - `provides` makes the package a provider of a virtual package. A package
+//!   with an option selecting one provider is created for each virtual package
+//!   which is not itself imported, as in hand-written repositories
+//! - `license` sets the package's license
+//!
+//! Version ranges follow Spack's semantics, so `@1.2` matches `1.2.7` and
+//! `@:1.4` matches `1.4.2`. Compiler (`%gcc`) and most architecture
+//! constraints cannot be expressed, so directives using them are skipped, as
+//! are the remaining directives, such as `patch` and `resource`. Everything
+//! skipped is reported as an [`ImportWarning`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    constraint::Constraint,
+    package::{
+        outline::PackageOutline, source::Source, template, version::Version,
+    },
+    spec::{SpecOptionValue, concrete::VERSION_OPTION},
+    util::digest::{Algorithm, Checksum},
+};
+
+/// A directive called in the body of a Spack package class
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct SpackDirective {
+    pub name: String,
+
+    #[serde(default)]
+    pub args: Vec<Value>,
+
+    #[serde(default)]
+    pub kwargs: BTreeMap<String, Value>,
+
+    /// Conditions of the enclosing `with when(...)` blocks, outermost first
+    #[serde(default)]
+    pub when: Vec<String>,
+}
+
+/// A Spack package class and the directives called in its body, in order
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct SpackClass {
+    pub name: String,
+
+    #[serde(default)]
+    pub url: Option<String>,
+
+    #[serde(default)]
+    pub homepage: Option<String>,
+
+    #[serde(default)]
+    pub directives: Vec<SpackDirective>,
+}
+
+/// A Spack package read from a repository
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpackPackage {
+    /// The package name, taken from its directory
+    pub name: String,
+
+    /// The `package.py` file defining it
+    pub path: PathBuf,
+
+    pub class: SpackClass,
+
+    /// Names the package file used which Spack would have provided, but which
+    /// are not known to the importer
+    pub unknown: Vec<String>,
+}
+
+/// Something which could not be imported
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportWarning {
+    pub package: String,
+    pub message: String,
+}
+
+/// The result of [`convert`]
+#[derive(Clone, Debug, Default)]
+pub struct Import {
+    /// One outline per package, followed by one per virtual package
+    pub outlines: Vec<PackageOutline>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl std::fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.package, self.message)
+    }
+}
+
+/// A parsed Spack spec, such as `hdf5@1.10: +mpi api=v18 ^zlib`
+#[derive(Clone, Debug, Default)]
+struct SpackSpec {
+    name: Option<String>,
+
+    /// Each `@` clause, without the `@`
+    versions: Vec<String>,
+
+    /// `+name`, `~name` and `name=value`, with `+` and `~` as `true` and
+    /// `false`
+    variants: Vec<(String, String)>,
+
+    /// Each `%` clause, without the `%`
+    compilers: Vec<String>,
+
+    /// Each `^` clause
+    dependencies: Vec<Self>,
+}
+
+const fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+impl SpackSpec {
+    fn parse(txt: &str) -> Result<Self, String> {
+        let chars = txt.chars().collect::<Vec<_>>();
+        // The spec being parsed, and those before it: the root, then each
+        // `^` dependency
+        let mut spec = Self::default();
+        let mut done = Vec::new();
+        let mut idx = 0;
+
+        let read = |idx: &mut usize, stop: &dyn Fn(char) -> bool| {
+            let start = *idx;
+            while *idx < chars.len() && !stop(chars[*idx]) {
+                *idx += 1;
+            }
+            chars[start..*idx].iter().collect::<String>()
+        };
+
+        while idx < chars.len() {
+            let c = chars[idx];
+
+            if c.is_whitespace() {
+                idx += 1;
+                continue;
+            }
+
+            if c == '^' {
+                done.push(std::mem::take(&mut spec));
+                idx += 1;
+                continue;
+            }
+
+            let word_start = idx == 0 || chars[idx - 1].is_whitespace();
+
+            match c {
+                '@' => {
+                    idx += 1;
+                    spec.versions.push(read(&mut idx, &|c| {
+                        c.is_whitespace() || "+~%^".contains(c)
+                    }));
+                }
+                '+' | '~' => {
+                    // `++name` propagates the variant to dependencies
+                    while idx < chars.len() && chars[idx] == c {
+                        idx += 1;
+                    }
+
+                    let name = read(&mut idx, &|c| !is_name_char(c));
+                    spec.variants.push((name, (c == '+').to_string()));
+                }
+                '-' if word_start => {
+                    idx += 1;
+                    let name = read(&mut idx, &|c| !is_name_char(c));
+                    spec.variants.push((name, "false".to_string()));
+                }
+                '%' => {
+                    idx += 1;
+                    spec.compilers.push(read(&mut idx, &|c| {
+                        c.is_whitespace() || "+~^".contains(c)
+                    }));
+                }
+                c if is_name_char(c) => {
+                    let word = read(&mut idx, &|c| !is_name_char(c));
+
+                    if chars.get(idx) == Some(&'=') {
+                        // `name==value` propagates the value to dependencies
+                        while chars.get(idx) == Some(&'=') {
+                            idx += 1;
+                        }
+
+                        let value = read(&mut idx, &|c| {
+                            c.is_whitespace() || "^%".contains(c)
+                        });
+                        spec.variants.push((word, value));
+                    } else if spec.name.is_none() {
+                        spec.name = Some(word);
+                    } else {
+                        return Err(format!("unexpected '{word}' in '{txt}'"));
+                    }
+                }
+                c => return Err(format!("unexpected '{c}' in '{txt}'")),
+            }
+
+            if spec.variants.last().is_some_and(|(name, _)| name.is_empty())
+                || spec.versions.last().is_some_and(String::is_empty)
+            {
+                return Err(format!("invalid spec '{txt}'"));
+            }
+        }
+
+        done.push(spec);
+
+        let mut specs = done.into_iter();
+        let mut root = specs.next().unwrap_or_default();
+        root.dependencies.extend(specs);
+
+        Ok(root)
+    }
+}
+
+/// `version` with its last component incremented, such as `1.3` for `1.2`
+fn next_version(version: &str) -> Option<String> {
+    let (head, last) = version.rsplit_once('.').unwrap_or(("", version));
+    let next = last.parse::<u64>().ok()? + 1;
+
+    Some(if head.is_empty() {
+        next.to_string()
+    } else {
+        format!("{head}.{next}")
+    })
+}
+
+fn check_version(version: &str) -> Result<(), String> {
+    Version::new(version)
+        .map(|_| ())
+        .map_err(|_| format!("invalid version '{version}'"))
+}
+
+/// A single constraint requiring every constraint in `atoms`
+fn all(atoms: &[String]) -> Option<String> {
+    match atoms {
+        [] => None,
+        [atom] => Some(atom.clone()),
+        atoms => {
+            Some(format!("num_of({}) == {}", atoms.join(", "), atoms.len()))
+        }
+    }
+}
+
+/// Constraints on the version of `package` matching the Spack version
+/// clause `clause`, such as `1.2:1.4,2.0`
+fn version_atoms(package: &str, clause: &str) -> Result<Vec<String>, String> {
+    let option = format!("option({package}:{VERSION_OPTION})");
+    let mut alternatives = Vec::new();
+
+    for range in clause.split(',') {
+        let mut atoms = Vec::new();
+
+        if let Some(exact) = range.strip_prefix('=') {
+            check_version(exact)?;
+            atoms.push(format!("{option} == @{exact}"));
+        } else if let Some((lo, hi)) = range.split_once(':') {
+            if !lo.is_empty() {
+                check_version(lo)?;
+                atoms.push(format!("{option} >= @{lo}"));
+            }
+
+            if !hi.is_empty() {
+                check_version(hi)?;
+                atoms.push(next_version(hi).map_or_else(
+                    || format!("{option} <= @{hi}"),
+                    |next| format!("{option} < @{next}"),
+                ));
+            }
+        } else {
+            check_version(range)?;
+
+            match next_version(range) {
+                Some(next) => {
+                    atoms.push(format!("{option} >= @{range}"));
+                    atoms.push(format!("{option} < @{next}"));
+                }
+                None => atoms.push(format!("{option} == @{range}")),
+            }
+        }
+
+        alternatives.push(atoms);
+    }
+
+    if let [atoms] = alternatives.as_slice() {
+        return Ok(atoms.clone());
+    }
+
+    let any =
+        alternatives.iter().filter_map(|atoms| all(atoms)).collect::<Vec<_>>();
+
+    Ok(vec![format!("num_of({}) >= 1", any.join(", "))])
+}
+
+/// The constraint for the Spack variant or architecture setting `name=value`
+/// of `package`
+fn variant_atom(
+    package: &str,
+    name: &str,
+    value: &str,
+) -> Result<String, String> {
+    match name {
+        "platform" => {
+            let os = match value {
+                "darwin" => "macos",
+                os => os,
+            };
+
+            return Ok(format!("platform(os={os})"));
+        }
+        "target" if !value.contains([':', ',']) => {
+            return Ok(format!("platform(arch={value})"));
+        }
+        "os" | "target" | "arch" => {
+            return Err(format!("'{name}={value}' is not supported"));
+        }
+        _ => {}
+    }
+
+    if value.contains(',') {
+        return Err(format!("multi-valued '{name}={value}' is not supported"));
+    }
+
+    let value = match value {
+        "true" | "True" => "true".to_string(),
+        "false" | "False" => "false".to_string(),
+        _ if name == "build_type" => format!("{:?}", value.to_lowercase()),
+        _ => format!("{value:?}"),
+    };
+
+    Ok(format!("option({package}:{name}) == {value}"))
+}
+
+impl SpackSpec {
+    /// Constraints which hold when this spec is satisfied.
+    ///
+    /// * `package`: The package the spec is about if it does not name one
+    /// * `virtuals`: Virtual packages, which have no versions
+    fn atoms(
+        &self,
+        package: &str,
+        virtuals: &BTreeSet<String>,
+    ) -> Result<Vec<String>, String> {
+        let package = self.name.as_deref().unwrap_or(package);
+        let mut res = Vec::new();
+
+        if let Some(compiler) = self.compilers.first() {
+            return Err(format!(
+                "compiler constraints ('%{compiler}') are not supported"
+            ));
+        }
+
+        if !virtuals.contains(package) {
+            for clause in &self.versions {
+                res.extend(version_atoms(package, clause)?);
+            }
+        }
+
+        for (name, value) in &self.variants {
+            res.push(variant_atom(package, name, value)?);
+        }
+
+        for dep in &self.dependencies {
+            let Some(name) = &dep.name else {
+                return Err("dependency specs must name a package".into());
+            };
+
+            res.push(format!("depends({name})"));
+            res.extend(dep.atoms(name, virtuals)?);
+        }
+
+        Ok(res)
+    }
+}
+
+fn as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+struct Converter<'a> {
+    package: &'a SpackPackage,
+    virtuals: &'a BTreeSet<String>,
+    outline: PackageOutline,
+    warnings: Vec<ImportWarning>,
+}
+
+impl Converter<'_> {
+    fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(ImportWarning {
+            package: self.package.name.clone(),
+            message: message.into(),
+        });
+    }
+
+    fn name(&self) -> &str {
+        &self.package.name
+    }
+
+    /// Parse a constraint written by the converter, reporting it if it is
+    /// somehow invalid
+    fn push(&mut self, txt: &str) {
+        match txt.parse::<Constraint>() {
+            Ok(constraint) => self.outline.constraints.push(constraint),
+            Err(e) => self.warn(format!("could not convert '{txt}': {e}")),
+        }
+    }
+
+    /// Constraints which hold when `directive` applies
+    fn conditions(
+        &self,
+        directive: &SpackDirective,
+    ) -> Result<Vec<String>, String> {
+        let mut res = Vec::new();
+
+        let when = directive.kwargs.get("when").and_then(as_string);
+
+        for spec in directive.when.iter().chain(when.as_ref()) {
+            let spec = SpackSpec::parse(spec)?;
+
+            if spec.name.as_deref().is_some_and(|name| name != self.name()) {
+                return Err(format!(
+                    "conditions on other packages ('{}') are not supported",
+                    spec.name.unwrap_or_default()
+                ));
+            }
+
+            res.extend(spec.atoms(self.name(), self.virtuals)?);
+        }
+
+        Ok(res)
+    }
+
+    fn version(
+        &mut self,
+        directive: &SpackDirective,
+        versions: &mut Vec<Version>,
+    ) {
+        let Some(txt) = directive.args.first().and_then(as_string) else {
+            self.warn("version without a version");
+            return;
+        };
+
+        let Ok(version) = Version::new(&txt) else {
+            self.warn(format!("invalid version '{txt}'"));
+            return;
+        };
+
+        let kwargs = &directive.kwargs;
+
+        if ["git", "branch", "tag", "commit", "submodules"]
+            .iter()
+            .any(|key| kwargs.contains_key(*key))
+        {
+            self.warn(format!(
+                "version '{txt}' is fetched from a repository, which is not \
+                 supported"
+            ));
+            return;
+        }
+
+        versions.push(version.clone());
+
+        let checksum = kwargs
+            .get("sha256")
+            .and_then(as_string)
+            .map(|hex| Checksum::new(Algorithm::Sha256, &hex));
+
+        let Some(Ok(checksum)) = checksum else {
+            self.warn(format!(
+                "version '{txt}' has no sha256 checksum, so has no source"
+            ));
+            return;
+        };
+
+        let url = kwargs.get("url").and_then(as_string).or_else(|| {
+            let url = self.package.class.url.as_deref()?;
+            let original = template::guess_version(url)?;
+            Some(url.replace(original, &txt))
+        });
+
+        match url {
+            Some(url) => self.outline.sources.push(Source {
+                url,
+                checksum,
+                version: Some(version),
+            }),
+            None => self.warn(format!("no URL for version '{txt}'")),
+        }
+    }
+
+    fn variant(&mut self, directive: &SpackDirective) {
+        let Some(name) = directive.args.first().and_then(as_string) else {
+            self.warn("variant without a name");
+            return;
+        };
+
+        let kwargs = &directive.kwargs;
+
+        if kwargs.get("multi").is_some_and(|multi| multi == &Value::Bool(true))
+        {
+            self.warn(format!(
+                "multi-valued variant '{name}' is not supported"
+            ));
+            return;
+        }
+
+        let lower = |s: &str| {
+            if name == "build_type" { s.to_lowercase() } else { s.to_string() }
+        };
+
+        let default = match kwargs.get("default") {
+            None | Some(Value::Bool(false)) => SpecOptionValue::Bool(false),
+            Some(Value::Bool(true)) => SpecOptionValue::Bool(true),
+            Some(Value::String(s)) => SpecOptionValue::Str(lower(s)),
+            Some(Value::Number(n)) => n.as_i64().map_or_else(
+                || SpecOptionValue::Str(n.to_string()),
+                SpecOptionValue::Int,
+            ),
+            Some(other) => {
+                self.warn(format!(
+                    "variant '{name}' has an unsupported default {other}"
+                ));
+                return;
+            }
+        };
+
+        let values = match kwargs.get("values") {
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(as_string)
+                .collect::<Option<Vec<_>>>()
+                .map(|values| values.iter().map(|v| lower(v)).collect()),
+            _ => None,
+        };
+
+        if let (SpecOptionValue::Str(_), Some(values)) = (&default, values) {
+            let values: Vec<String> = values;
+            let of = values
+                .iter()
+                .map(|v| format!("option({}:{name}) == {v:?}", self.name()))
+                .collect::<Vec<_>>();
+
+            self.push(&format!("num_of({}) == 1", of.join(", ")));
+        }
+
+        self.outline.set_defaults.insert(name, Some(default));
+    }
+
+    fn depends_on(&mut self, directive: &SpackDirective) -> Result<(), String> {
+        let spec = directive
+            .args
+            .first()
+            .and_then(as_string)
+            .ok_or("dependency without a spec")?;
+
+        let conditions = self.conditions(directive)?;
+        let mut spec = SpackSpec::parse(&spec)?;
+
+        let name = spec.name.clone().ok_or("dependency without a name")?;
+
+        if !spec.compilers.is_empty() {
+            self.warn(format!(
+                "compiler constraints on dependency '{name}' are ignored"
+            ));
+            spec.compilers.clear();
+        }
+
+        if !spec.dependencies.is_empty() {
+            self.warn(format!(
+                "constraints on the dependencies of '{name}' are ignored"
+            ));
+            spec.dependencies.clear();
+        }
+
+        if self.virtuals.contains(&name) && !spec.versions.is_empty() {
+            self.warn(format!(
+                "version constraints on virtual package '{name}' are ignored"
+            ));
+        }
+
+        let atoms = spec.atoms(&name, self.virtuals)?;
+        let when = all(&conditions);
+
+        for atom in std::iter::once(format!("depends({name})")).chain(atoms) {
+            match &when {
+                Some(when) => self.push(&format!("{atom} when {when}")),
+                None => self.push(&atom),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `conflicts` if `forbid`, otherwise `requires`
+    fn restrict(
+        &mut self,
+        directive: &SpackDirective,
+        forbid: bool,
+    ) -> Result<(), String> {
+        let [spec] = directive.args.as_slice() else {
+            return Err("only a single spec is supported".into());
+        };
+
+        let spec = as_string(spec).ok_or("expected a spec")?;
+        let parsed = SpackSpec::parse(&spec)?;
+
+        if parsed.name.as_deref().is_some_and(|name| name != self.name()) {
+            return Err(format!("specs naming '{spec}' are not supported"));
+        }
+
+        let atoms = parsed.atoms(self.name(), self.virtuals)?;
+        let conditions = self.conditions(directive)?;
+
+        if forbid {
+            let every =
+                atoms.iter().chain(&conditions).cloned().collect::<Vec<_>>();
+
+            self.push(&format!(
+                "num_of({}) < {}",
+                every.join(", "),
+                every.len()
+            ));
+        } else {
+            let Some(required) = all(&atoms) else { return Ok(()) };
+
+            match all(&conditions) {
+                Some(when) => self.push(&format!("{required} when {when}")),
+                None => self.push(&required),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn convert(mut self) -> (PackageOutline, Vec<ImportWarning>) {
+        let mut versions = Vec::new();
+
+        for unknown in &self.package.unknown {
+            self.warn(format!("'{unknown}' is not known to the importer"));
+        }
+
+        for directive in &self.package.class.directives {
+            let res = match directive.name.as_str() {
+                "version" => {
+                    self.version(directive, &mut versions);
+                    Ok(())
+                }
+                "variant" => {
+                    self.variant(directive);
+                    Ok(())
+                }
+                "depends_on" | "extends" => self.depends_on(directive),
+                "conflicts" => self.restrict(directive, true),
+                "requires" => self.restrict(directive, false),
+                "license" => {
+                    if self.outline.license.is_none() {
+                        self.outline.license =
+                            directive.args.first().and_then(as_string);
+                    }
+                    Ok(())
+                }
+                // Handled by `convert`, or with no bearing on the outline
+                "provides" | "maintainers" | "redistribute" | "can_splice" => {
+                    Ok(())
+                }
+                other => Err(format!("'{other}' is not supported")),
+            };
+
+            if let Err(e) = res {
+                let args = directive
+                    .args
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                self.warn(format!("skipped {}({args}): {e}", directive.name));
+            }
+        }
+
+        if !versions.is_empty() {
+            self.outline.push_version_domain(versions);
+        }
+
+        (self.outline, self.warnings)
+    }
+}
+
+/// The virtual packages provided by `package`
+fn provided(package: &SpackPackage) -> impl Iterator<Item = String> + '_ {
+    package
+        .class
+        .directives
+        .iter()
+        .filter(|d| d.name == "provides")
+        .flat_map(|d| d.args.iter().filter_map(as_string))
+        .filter_map(|spec| SpackSpec::parse(&spec).ok()?.name)
+}
+
+/// A package selecting one of `providers` to provide the virtual package
+/// `name`, such as `mpi`
+fn virtual_outline(name: &str, providers: &BTreeSet<String>) -> PackageOutline {
+    let mut outline =
+        PackageOutline { name: name.to_string(), ..Default::default() };
+
+    let selected = providers
+        .iter()
+        .map(|provider| format!("option({name}:{provider})"))
+        .collect::<Vec<_>>();
+
+    let constraints = providers
+        .iter()
+        .zip(&selected)
+        .map(|(provider, selected)| {
+            format!("depends({provider}) when {selected}")
+        })
+        .chain(std::iter::once(format!(
+            "num_of({}) == 1",
+            selected.join(", ")
+        )));
+
+    for txt in constraints {
+        // Provider names are package names, so this always parses
+        if let Ok(constraint) = txt.parse::<Constraint>() {
+            outline.constraints.push(constraint);
+        }
+    }
+
+    outline
+}
+
+/// Convert Spack packages into package outlines.
+#[must_use]
+pub fn convert(packages: &[SpackPackage]) -> Import {
+    let names =
+        packages.iter().map(|p| p.name.as_str()).collect::<BTreeSet<_>>();
+
+    let mut providers = BTreeMap::<String, BTreeSet<String>>::new();
+
+    for package in packages {
+        for virtual_name in provided(package) {
+            if !names.contains(virtual_name.as_str()) {
+                providers
+                    .entry(virtual_name)
+                    .or_default()
+                    .insert(package.name.clone());
+            }
+        }
+    }
+
+    let virtuals = providers.keys().cloned().collect::<BTreeSet<_>>();
+    let mut res = Import::default();
+
+    for package in packages {
+        let converter = Converter {
+            package,
+            virtuals: &virtuals,
+            outline: PackageOutline {
+                name: package.name.clone(),
+                ..Default::default()
+            },
+            warnings: Vec::new(),
+        };
+
+        let (outline, warnings) = converter.convert();
+
+        res.outlines.push(outline);
+        res.warnings.extend(warnings);
+    }
+
+    res.outlines.extend(
+        providers
+            .iter()
+            .map(|(name, providers)| virtual_outline(name, providers)),
+    );
+
+    let defined =
+        res.outlines.iter().map(|o| o.name.clone()).collect::<BTreeSet<_>>();
+
+    for outline in &res.outlines {
+        for dep in outline.dependencies() {
+            if !defined.contains(&dep) {
+                res.warnings.push(ImportWarning {
+                    package: outline.name.clone(),
+                    message: format!(
+                        "depends on '{dep}', which was not imported"
+                    ),
+                });
+            }
+        }
+    }
+
+    res
+}
+
+/// A Python expression for `value`
+fn py_value(value: &SpecOptionValue) -> String {
+    match value {
+        SpecOptionValue::Bool(true) => "True".to_string(),
+        SpecOptionValue::Bool(false) => "False".to_string(),
+        SpecOptionValue::Int(v) => v.to_string(),
+        SpecOptionValue::Float(v) => format!("{v:?}"),
+        SpecOptionValue::Str(v) => template::py_str(v),
+        SpecOptionValue::Version(v) => {
+            format!("Version({})", template::py_str(&v.to_string()))
+        }
+    }
+}
+
+impl Import {
+    /// Render a package file defining every imported package, in the same
+    /// form as a hand-written repository.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut res = String::new();
+
+        // Writing to a String cannot fail
+        let _ = self.write(&mut res);
+
+        res
+    }
+
+    fn write(&self, f: &mut String) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        writeln!(f, "# Imported from Spack by `zpack import spack`")?;
+        writeln!(f)?;
+        writeln!(f, "from zpack.constraint import parse")?;
+        writeln!(
+            f,
+            "from zpack.package import PackageOutline, Source, Version"
+        )?;
+
+        for outline in &self.outlines {
+            writeln!(f)?;
+            writeln!(f)?;
+            writeln!(f, "class {}:", template::class_name(&outline.name))?;
+            writeln!(f, "    def outline(self):")?;
+            writeln!(
+                f,
+                "        outline = PackageOutline({})",
+                template::py_str(&outline.name)
+            )?;
+
+            if let Some(license) = &outline.license {
+                writeln!(
+                    f,
+                    "        outline.set_license({})",
+                    template::py_str(license)
+                )?;
+            }
+
+            let mut defaults = outline
+                .set_defaults
+                .iter()
+                .filter_map(|(name, value)| Some((name, value.as_ref()?)))
+                .collect::<Vec<_>>();
+            defaults.sort_by_key(|(name, _)| *name);
+
+            if !defaults.is_empty() {
+                writeln!(f)?;
+            }
+
+            for (name, value) in defaults {
+                writeln!(
+                    f,
+                    "        outline.set_default({}, {})",
+                    template::py_str(name),
+                    py_value(value)
+                )?;
+            }
+
+            if !outline.constraints.is_empty() {
+                writeln!(f)?;
+                writeln!(f, "        outline.push_constraints([")?;
+
+                for constraint in &outline.constraints {
+                    writeln!(
+                        f,
+                        "            parse({}),",
+                        template::py_str(&constraint.to_string())
+                    )?;
+                }
+
+                writeln!(f, "        ])")?;
+            }
+
+            if !outline.sources.is_empty() {
+                writeln!(f)?;
+            }
+
+            for source in &outline.sources {
+                let version =
+                    source.version.as_ref().map_or_else(String::new, |v| {
+                        format!(
+                            ", Version({})",
+                            template::py_str(&v.to_string())
+                        )
+                    });
+
+                writeln!(
+                    f,
+                    "        outline.push_source(Source({}, {}{version}))",
+                    template::py_str(&source.url),
+                    template::py_str(&source.checksum.to_string())
+                )?;
+            }
+
+            writeln!(f)?;
+            writeln!(f, "        return outline")?;
+        }
+
+        let classes = self
+            .outlines
+            .iter()
+            .map(|o| format!("{}()", template::class_name(&o.name)))
+            .collect::<Vec<_>>();
+
+        writeln!(f)?;
+        writeln!(f)?;
+        writeln!(f, "def zpack_packages():")?;
+        writeln!(f, "    return [{}]", classes.join(", "))
+    }
+}
@@ -0,0 +1,196 @@
+//! Solver statistics and profiling.
+//!
+//! [`SolveStats`] records the time spent in each phase of concretization and
+//! the statistics the solver reports after checking, such as the number of
+//! conflicts and decisions. It is printed as a profile table with
+//! `--solver-stats`.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+/// Name of the statistic counting conflicts
+pub const CONFLICTS: &str = "conflicts";
+
+/// Name of the statistic counting decisions
+pub const DECISIONS: &str = "decisions";
+
+/// Name of the statistic counting unit propagations
+pub const PROPAGATIONS: &str = "propagations";
+
+/// Name of the z3 statistic holding peak memory use, in megabytes
+pub const MEMORY: &str = "max memory";
+
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default)]
+pub struct SolveStats {
+    /// Time spent in each phase, in the order the phases ran
+    pub phases: Vec<(String, Duration)>,
+
+    /// Statistics reported by the solver, by name
+    pub solver: BTreeMap<String, f64>,
+}
+
+impl SolveStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f`, recording the time it takes as `phase`.
+    pub fn time<T>(&mut self, phase: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = f();
+        let elapsed = start.elapsed();
+
+        tracing::info!("{phase} took {elapsed:?}");
+
+        self.phases.push((phase.to_string(), elapsed));
+        res
+    }
+
+    /// Record the statistics of a checked z3 solver.
+    #[cfg(feature = "solver-z3")]
+    pub fn record_solver(&mut self, stats: &z3::Statistics) {
+        for entry in stats.entries() {
+            let value = match entry.value {
+                z3::StatisticsValue::UInt(v) => f64::from(v),
+                z3::StatisticsValue::Double(v) => v,
+            };
+
+            self.solver.insert(entry.key, value);
+        }
+    }
+
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, t)| *t).sum()
+    }
+
+    #[must_use]
+    pub fn conflicts(&self) -> Option<f64> {
+        self.solver.get(CONFLICTS).copied()
+    }
+
+    #[must_use]
+    pub fn decisions(&self) -> Option<f64> {
+        self.solver.get(DECISIONS).copied()
+    }
+
+    /// Peak memory use in megabytes
+    #[must_use]
+    pub fn memory(&self) -> Option<f64> {
+        self.solver.get(MEMORY).copied()
+    }
+}
+
+impl std::fmt::Display for SolveStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.total();
+
+        let width = self
+            .phases
+            .iter()
+            .map(|(phase, _)| phase.len())
+            .chain(self.solver.keys().map(String::len))
+            .chain(std::iter::once("total".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f, "{:<width$}  {:>10}  {:>6}", "phase", "time (ms)", "%")?;
+
+        for (phase, time) in &self.phases {
+            let percent = if total.is_zero() {
+                0.0
+            } else {
+                100.0 * time.as_secs_f64() / total.as_secs_f64()
+            };
+
+            writeln!(
+                f,
+                "{phase:<width$}  {:>10.3}  {percent:>6.1}",
+                time.as_secs_f64() * 1000.0
+            )?;
+        }
+
+        writeln!(
+            f,
+            "{:<width$}  {:>10.3}  {:>6.1}",
+            "total",
+            total.as_secs_f64() * 1000.0,
+            100.0
+        )?;
+
+        if !self.solver.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:<width$}  {:>10}", "statistic", "value")?;
+
+            for (name, value) in &self.solver {
+                writeln!(f, "{name:<width$}  {value:>10}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl SolveStats {
+    /// Seconds spent in each phase, in the order the phases ran
+    #[pyo3(name = "phases")]
+    fn py_phases(&self) -> Vec<(String, f64)> {
+        self.phases
+            .iter()
+            .map(|(phase, time)| (phase.clone(), time.as_secs_f64()))
+            .collect()
+    }
+
+    /// Every statistic reported by the solver, by name
+    #[pyo3(name = "solver")]
+    fn py_solver(&self) -> BTreeMap<String, f64> {
+        self.solver.clone()
+    }
+
+    /// Total seconds spent concretizing
+    #[getter]
+    #[pyo3(name = "total")]
+    fn py_total(&self) -> f64 {
+        self.total().as_secs_f64()
+    }
+
+    #[getter]
+    #[pyo3(name = "conflicts")]
+    fn py_conflicts(&self) -> Option<f64> {
+        self.conflicts()
+    }
+
+    #[getter]
+    #[pyo3(name = "decisions")]
+    fn py_decisions(&self) -> Option<f64> {
+        self.decisions()
+    }
+
+    #[getter]
+    #[pyo3(name = "memory")]
+    fn py_memory(&self) -> Option<f64> {
+        self.memory()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SolveStats(total={:?}, conflicts={:?}, decisions={:?})",
+            self.total(),
+            self.conflicts(),
+            self.decisions()
+        )
+    }
+
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
@@ -0,0 +1,294 @@
+//! Scaffolding for new package files.
+//!
+//! A [`Scaffold`] renders a package file defining a single package, in the
+//! same form as a hand-written repository: one class per package and a
+//! `zpack_packages` function returning them. The [`Template`] decides which
+//! build tools the package depends on. The result is a starting point to be
+//! edited, so anything which could not be determined is marked `FIXME`.
+
+use std::fmt::Write;
+
+use crate::{package::version::Version, util::digest::Checksum};
+
+/// The build system a new package is assumed to use
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Template {
+    /// No particular build system
+    #[default]
+    Generic,
+
+    /// `./configure && make && make install`
+    Autotools,
+    Cmake,
+    Meson,
+
+    /// A Python project installed with pip
+    Python,
+}
+
+impl Template {
+    pub const ALL: [Self; 5] = [
+        Self::Generic,
+        Self::Autotools,
+        Self::Cmake,
+        Self::Meson,
+        Self::Python,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Generic => "generic",
+            Self::Autotools => "autotools",
+            Self::Cmake => "cmake",
+            Self::Meson => "meson",
+            Self::Python => "python",
+        }
+    }
+
+    /// The packages needed to build a package using this template
+    #[must_use]
+    pub const fn build_dependencies(self) -> &'static [&'static str] {
+        match self {
+            Self::Generic => &[],
+            Self::Autotools => &["gmake", "c-compiler"],
+            Self::Cmake => &["cmake", "c-compiler"],
+            Self::Meson => &["meson", "ninja", "c-compiler"],
+            Self::Python => &["python", "py-pip"],
+        }
+    }
+}
+
+impl std::fmt::Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|t| t.name() == s).ok_or_else(|| {
+            format!(
+                "unknown template '{s}'; expected one of: {}",
+                Self::ALL.map(Self::name).join(", ")
+            )
+        })
+    }
+}
+
+/// Replaced by the version in a package's `url`, with Python's `str.format`
+const VERSION_PLACEHOLDER: &str = "{version}";
+
+/// Archive extensions, longest first so `.tar.gz` is removed before `.gz`
+const ARCHIVE_EXTENSIONS: [&str; 12] = [
+    ".tar.gz", ".tar.bz2", ".tar.xz", ".tar.zst", ".tar.lz", ".tgz", ".tbz2",
+    ".txz", ".tar", ".zip", ".gz", ".xz",
+];
+
+/// Guess the version of the archive at `url` from its file name, such as
+/// `1.2.3` from `https://example.com/mylib-1.2.3.tar.gz` or
+/// `https://github.com/org/mylib/archive/refs/tags/v1.2.3.zip`.
+///
+/// The version is returned as written in the URL, which is always a valid
+/// [`Version`].
+#[must_use]
+pub fn guess_version(url: &str) -> Option<&str> {
+    let file = crate::fetch::url_file_name(url);
+
+    let stem = ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|ext| file.strip_suffix(ext))
+        .unwrap_or(file);
+
+    // The version is usually the last component of the name which starts
+    // with a digit, possibly after a leading `v`
+    stem.rsplit(['-', '_'])
+        .map(|part| part.strip_prefix('v').unwrap_or(part))
+        .filter(|part| part.starts_with(|c: char| c.is_ascii_digit()))
+        .find(|part| Version::new(part).is_ok())
+}
+
+/// The Python class name for the package `name`, such as `PyNumpy` for
+/// `py-numpy`.
+#[must_use]
+pub fn class_name(name: &str) -> String {
+    let mut res = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect::<String>();
+
+    if !res.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        res.insert_str(0, "Package");
+    }
+
+    res
+}
+
+/// A Python string literal containing `s`
+pub(crate) fn py_str(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A new package, ready to be rendered with [`Self::render`]
+#[derive(Clone, Debug)]
+pub struct Scaffold {
+    pub name: String,
+    pub template: Template,
+
+    /// The source archive of [`Self::version`]
+    pub url: Option<String>,
+
+    /// The version of the archive at [`Self::url`], as written in the URL
+    pub version: Option<String>,
+
+    /// Checksum of the archive at [`Self::url`]
+    pub checksum: Option<Checksum>,
+}
+
+impl Scaffold {
+    #[must_use]
+    pub fn new(name: impl Into<String>, template: Template) -> Self {
+        Self {
+            name: name.into(),
+            template,
+            url: None,
+            version: None,
+            checksum: None,
+        }
+    }
+
+    /// The URL escaped for Python's `str.format`, with the version
+    /// replaced by a `{version}` placeholder so the source of other versions
+    /// can be derived from it. Also returns whether the version was found
+    fn url_pattern(&self) -> (String, bool) {
+        let url = self
+            .url
+            .as_deref()
+            .unwrap_or_default()
+            .replace('{', "{{")
+            .replace('}', "}}");
+
+        match &self.version {
+            Some(version) if url.contains(version.as_str()) => {
+                (url.replace(version, VERSION_PLACEHOLDER), true)
+            }
+            _ => (url, false),
+        }
+    }
+
+    /// Render the package file.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut res = String::new();
+
+        // Writing to a String cannot fail
+        let _ = self.write(&mut res);
+
+        res
+    }
+
+    fn write(&self, f: &mut String) -> std::fmt::Result {
+        let class = class_name(&self.name);
+        let name = py_str(&self.name);
+
+        writeln!(f, "import zpack")?;
+        writeln!(f, "from zpack.constraint import *")?;
+        writeln!(
+            f,
+            "from zpack.package import PackageOutline, Source, Version"
+        )?;
+        writeln!(f)?;
+        writeln!(f)?;
+        writeln!(f, "class {class}:")?;
+
+        if self.template != Template::Generic {
+            writeln!(f, "    # Created from the '{}' template", self.template)?;
+        }
+
+        let (url, templated) = self.url_pattern();
+
+        if !templated {
+            writeln!(
+                f,
+                "    # FIXME: the URL of the source archive, with \
+                 {VERSION_PLACEHOLDER} in place of the version"
+            )?;
+        }
+
+        writeln!(f, "    url = {}", py_str(&url))?;
+
+        writeln!(f)?;
+        writeln!(f, "    versions = {{")?;
+
+        match (&self.version, &self.checksum) {
+            (Some(version), Some(checksum)) => writeln!(
+                f,
+                "        {}: {},",
+                py_str(version),
+                py_str(&checksum.to_string())
+            )?,
+            (None, Some(checksum)) => writeln!(
+                f,
+                "        # FIXME: the version of the archive at url\n        \
+                 # \"VERSION\": {},",
+                py_str(&checksum.to_string())
+            )?,
+            _ => writeln!(
+                f,
+                "        # FIXME: \"VERSION\": \"sha256:CHECKSUM\", for each \
+                 version"
+            )?,
+        }
+
+        writeln!(f, "    }}")?;
+        writeln!(f)?;
+        writeln!(f, "    def outline(self):")?;
+        writeln!(f, "        outline = PackageOutline({name})")?;
+        writeln!(f)?;
+        writeln!(f, "        version = SpecOption({name}, \"version\")")?;
+        writeln!(f)?;
+        writeln!(f, "        constraints = [")?;
+        writeln!(
+            f,
+            "            NumOf([version == Version(v) for v in \
+             self.versions]) == 1,"
+        )?;
+
+        let deps = self.template.build_dependencies();
+
+        if !deps.is_empty() {
+            writeln!(f)?;
+
+            for dep in deps {
+                writeln!(f, "            Depends({}),", py_str(dep))?;
+            }
+        }
+
+        writeln!(f, "        ]")?;
+        writeln!(f)?;
+        writeln!(f, "        outline.push_constraints(constraints)")?;
+        writeln!(f)?;
+        writeln!(f, "        for v, checksum in self.versions.items():")?;
+        writeln!(f, "            url = self.url.format(version=v)")?;
+        writeln!(
+            f,
+            "            outline.push_source(Source(url, checksum, \
+             Version(v)))"
+        )?;
+        writeln!(f)?;
+        writeln!(f, "        return outline")?;
+        writeln!(f)?;
+        writeln!(f)?;
+        writeln!(f, "def zpack_packages():")?;
+        writeln!(f, "    return [{class}()]")
+    }
+}
@@ -0,0 +1,328 @@
+//! Smoke tests for installed packages.
+//!
+//! A [`SmokeTest`] is declared on a [`PackageOutline`] with an optional
+//! condition, in the same way as a [`Patch`](crate::package::patch::Patch).
+//! Tests whose condition holds in the solved model are recorded on the
+//! [`ConcretePackage`] as [`ConcreteTest`]s. Since they do not change what is
+//! built, they do not contribute to its hash.
+//!
+//! Running the tests is the `test` phase. Each test is a command run from the
+//! package's install prefix in its runtime environment (see [`runtime_env`]),
+//! and passes if it exits successfully. The results of the last run are
+//! recorded inside the prefix (see [`TEST_RESULTS_FILE`]).
+//!
+//! [`PackageOutline`]: crate::package::outline::PackageOutline
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{constraint::Constraint, package::names, spec::ConcretePackage};
+
+/// Name of the build phase which runs smoke tests
+pub const TEST_PHASE: &str = "test";
+
+/// Replaced by the install prefix in the arguments of a test
+pub const PREFIX_PLACEHOLDER: &str = "{prefix}";
+
+/// File, relative to the install prefix, recording the last test results
+pub const TEST_RESULTS_FILE: &str = ".zpack/tests.json";
+
+/// A smoke test declared by a package.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmokeTest {
+    pub name: String,
+
+    /// The program to run followed by its arguments. Every occurrence of
+    /// [`PREFIX_PLACEHOLDER`] is replaced by the install prefix
+    pub command: Vec<String>,
+
+    /// The test is only run if this evaluates to true
+    pub when: Option<Constraint>,
+}
+
+/// A smoke test which applies to a concretized package.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ConcreteTest {
+    pub name: String,
+    pub command: Vec<String>,
+}
+
+/// The outcome of running a [`ConcreteTest`]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+
+    /// Exit code of the command. `None` if it could not be run or was
+    /// terminated by a signal
+    pub status: Option<i32>,
+
+    /// Wall time taken, in seconds
+    pub duration: f64,
+
+    /// Standard output followed by standard error, or the reason the command
+    /// could not be run
+    pub output: String,
+}
+
+/// The results of testing an installed package, as recorded in
+/// [`TEST_RESULTS_FILE`]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestReport {
+    pub package: String,
+
+    /// DAG hash of the tested package
+    pub hash: String,
+
+    /// Seconds since the Unix epoch when the tests were run
+    pub timestamp: u64,
+
+    pub results: Vec<TestResult>,
+}
+
+impl SmokeTest {
+    /// Name of the solver variable which is true if this test applies.
+    ///
+    /// * `package`: The package declaring the test
+    /// * `index`: The index of the test within the package
+    #[must_use]
+    pub fn flag_name(package: &str, index: usize) -> String {
+        names::condition(package, "test", index)
+    }
+
+    #[must_use]
+    pub fn to_concrete(&self) -> ConcreteTest {
+        ConcreteTest { name: self.name.clone(), command: self.command.clone() }
+    }
+}
+
+impl std::fmt::Display for SmokeTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SmokeTest({}: {}", self.name, self.command.join(" "))?;
+
+        if let Some(when) = &self.when {
+            write!(f, " when {when}")?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+impl TestReport {
+    /// The number of tests which failed
+    #[must_use]
+    pub fn failures(&self) -> usize {
+        self.results.iter().filter(|r| !r.passed).count()
+    }
+
+    /// Write this report to [`TEST_RESULTS_FILE`] inside `prefix`, replacing
+    /// any previous report, and return the path written to.
+    ///
+    /// # Errors
+    /// Errors if the file cannot be written.
+    pub fn record(&self, prefix: &Path) -> std::io::Result<PathBuf> {
+        let path = prefix.join(TEST_RESULTS_FILE);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(path)
+    }
+
+    /// Read the report recorded inside `prefix`, if there is one.
+    ///
+    /// # Errors
+    /// Errors if the file exists but cannot be read or parsed.
+    pub fn load(prefix: &Path) -> std::io::Result<Option<Self>> {
+        let path = prefix.join(TEST_RESULTS_FILE);
+
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let txt = std::fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&txt)?))
+    }
+}
+
+/// The variable searched for shared libraries at runtime
+const LIBRARY_PATH_VAR: &str = if cfg!(target_os = "macos") {
+    "DYLD_LIBRARY_PATH"
+} else {
+    "LD_LIBRARY_PATH"
+};
+
+/// Assemble the runtime environment of a package installed to `prefix`.
+///
+/// The prefix's `bin`, library, `pkgconfig` and `man` directories which exist
+/// are prepended to the corresponding search path variables of the current
+/// environment, and the prefix itself to `CMAKE_PREFIX_PATH`. Only the
+/// variables which change are returned.
+#[must_use]
+pub fn runtime_env(prefix: &Path) -> BTreeMap<String, String> {
+    let vars: [(&str, &[&str]); 5] = [
+        ("PATH", &["bin"]),
+        (LIBRARY_PATH_VAR, &["lib", "lib64"]),
+        ("PKG_CONFIG_PATH", &["lib/pkgconfig", "share/pkgconfig"]),
+        ("MANPATH", &["share/man"]),
+        ("CMAKE_PREFIX_PATH", &[""]),
+    ];
+
+    let mut env = BTreeMap::new();
+
+    for (var, subdirs) in vars {
+        let dirs = subdirs
+            .iter()
+            .map(|subdir| prefix.join(subdir))
+            .filter(|dir| dir.is_dir())
+            .collect::<Vec<_>>();
+
+        if dirs.is_empty() {
+            continue;
+        }
+
+        let existing = std::env::var_os(var).unwrap_or_default();
+
+        let paths = dirs
+            .into_iter()
+            .chain(std::env::split_paths(&existing))
+            .filter(|path| !path.as_os_str().is_empty());
+
+        match std::env::join_paths(paths) {
+            Ok(value) => {
+                env.insert(var.to_string(), value.to_string_lossy().into());
+            }
+            Err(e) => tracing::warn!("cannot set {var}: {e}"),
+        }
+    }
+
+    env
+}
+
+fn run_test(test: &ConcreteTest, prefix: &Path) -> TestResult {
+    let prefix_str = prefix.to_string_lossy();
+
+    let mut args = test
+        .command
+        .iter()
+        .map(|arg| arg.replace(PREFIX_PLACEHOLDER, &prefix_str));
+
+    let start = Instant::now();
+
+    let output = args.next().map_or_else(
+        || Err(std::io::Error::other("the command is empty")),
+        |program| {
+            std::process::Command::new(program)
+                .args(args)
+                .current_dir(prefix)
+                .envs(runtime_env(prefix))
+                .output()
+        },
+    );
+
+    let duration = start.elapsed().as_secs_f64();
+
+    match output {
+        Ok(output) => TestResult {
+            name: test.name.clone(),
+            passed: output.status.success(),
+            status: output.status.code(),
+            duration,
+            output: format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        },
+        Err(e) => TestResult {
+            name: test.name.clone(),
+            passed: false,
+            status: None,
+            duration,
+            output: format!("failed to run '{}': {e}", test.command.join(" ")),
+        },
+    }
+}
+
+/// Run the `test` phase for a concrete package installed to `prefix`.
+///
+/// Tests are run in the order they were declared, and every test is run
+/// even if an earlier one fails.
+///
+/// * `package`: The package to test
+/// * `hash`: The DAG hash of the package, recorded in the report
+/// * `prefix`: The directory the package is installed to
+#[must_use]
+pub fn run_tests(
+    package: &ConcretePackage,
+    hash: String,
+    prefix: &Path,
+) -> TestReport {
+    let results = package
+        .tests
+        .iter()
+        .map(|test| {
+            tracing::info!("running test '{}' of {}", test.name, package.name);
+
+            let result = run_test(test, prefix);
+
+            if !result.passed {
+                tracing::warn!(
+                    "test '{}' of {} failed",
+                    test.name,
+                    package.name
+                );
+            }
+
+            result
+        })
+        .collect();
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    TestReport { package: package.name.clone(), hash, timestamp, results }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl SmokeTest {
+    /// * `name`: Name of the test, unique within the package
+    /// * `command`: The program to run followed by its arguments. `{prefix}` is
+    ///   replaced by the install prefix
+    /// * `when`: Only run the test if this holds
+    ///
+    /// # Errors
+    /// Errors if `command` is empty.
+    #[new]
+    #[pyo3(signature = (name, command, when = None))]
+    fn py_new(
+        name: &str,
+        command: Vec<String>,
+        when: Option<Constraint>,
+    ) -> PyResult<Self> {
+        if command.is_empty() {
+            return Err(PyValueError::new_err(format!(
+                "test '{name}' has an empty command"
+            )));
+        }
+
+        Ok(Self { name: name.to_string(), command, when })
+    }
+
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
@@ -6,6 +6,21 @@
 //! A version is stored as a list of [`Part`]s, which include integers, strings,
 //! wildcards and separators.
 //!
+//! Every version starts with an integer epoch, separated from the rest of the
+//! version by a colon (`2:1.4.0`). The epoch defaults to zero and is hidden
+//! when displayed, so `1.4.0` and `0:1.4.0` are the same version. Since the
+//! epoch is an ordinary leading component, it takes precedence over the rest
+//! of the version in every comparison, allowing a package to restart its
+//! version numbering. A wildcard epoch (`*:1.4.0`) matches any epoch.
+//!
+//! Calendar versions, such as 2025.06 or 2024.1.0, start with a four-digit
+//! year (see [`Version::is_calendar`]). Components are compared by value, so
+//! calendar versions are naturally ordered after every semantic version in the
+//! same epoch, which is the expected order for a project switching from
+//! semantic to calendar versioning. A project switching the other way should
+//! bump its epoch. Note that zero padding is not significant: 2025.06 and
+//! 2025.6 are equal.
+//!
 //! To operate within the bounds of the solver, we specify some comparison rules
 //! that are logically consistent and have beneficial properties:
 //!
@@ -22,6 +37,12 @@
 //!     - Rest matches the rest of a version
 //!         - Regardless of remaining separators
 //!
+//! Versions are ordered lexicographically by component, so 1.2.4 < 1.3.2 even
+//! though 4 > 2. [`Version::satisfies`] applies these rules, including
+//! wildcards, without a solver. The [`Ord`] implementation extends them to a
+//! total order for sorting: shorter versions sort before longer versions
+//! they prefix, and wildcards sort after every string and number.
+//!
 //! Before adding versions to the solver, we track them in a
 //! [`WipVersionRegistry`]. Explicit string version orderings will all be added
 //! by default, and any other strings found during the outlining phase will also
@@ -33,11 +54,24 @@
 //! errors in cases where the specification is unsatisfiable, but the UNSAT core
 //! should provide enough context to identify the cause of the issue.
 
-use std::{fmt::Write, str::FromStr};
-
-use pyo3::{exceptions::PyValueError, prelude::*};
-
-use crate::{constraint::CmpType, package::registry::BuiltVersionRegistry};
+#[cfg(feature = "solver-z3")]
+use std::str::FromStr;
+use std::{cmp::Ordering, fmt::Write};
+
+#[cfg(feature = "python")]
+use pyo3::{
+    basic::CompareOp,
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyType},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::constraint::CmpType;
+#[cfg(feature = "python")]
+use crate::interface::pickle;
+#[cfg(feature = "solver-z3")]
+use crate::package::registry::BuiltVersionRegistry;
 
 /// Version strings with a specified, non-lexicographic order
 pub const STATIC_STRING_VERSIONS: [&str; 9] = [
@@ -48,6 +82,13 @@ pub const STATIC_STRING_VERSIONS: [&str; 9] = [
 /// Valid version separators
 pub const VERSION_SEPARATORS: [char; 3] = ['.', '-', '+'];
 
+/// Separates the epoch from the rest of a version
+pub const EPOCH_SEPARATOR: char = ':';
+
+/// Leading components in this range are treated as years by
+/// [`Version::is_calendar`]
+pub const CALENDAR_YEARS: std::ops::RangeInclusive<usize> = 1900..=9999;
+
 /// Wildcard specifier in a version.
 ///
 /// - [`WildcardType::Single`] is an asterisk ('*') and represents any value
@@ -78,7 +119,7 @@ pub enum Part {
 /// A generic version.
 ///
 /// See the documentation for this module for more information.
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass(module = "zpack.package"))]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Version {
     parts: Vec<Part>,
@@ -91,9 +132,58 @@ pub enum ParseError {
     InvalidPart(String),
     EmptyPart,
     PartAfterRest,
+    InvalidEpoch(String),
 }
 
 impl Part {
+    /// Compare two string parts. Strings in [`STATIC_STRING_VERSIONS`] are
+    /// greater than all other strings, and ordered by their position in it.
+    #[must_use]
+    pub fn cmp_str(lhs: &str, rhs: &str) -> Ordering {
+        let rank =
+            |s: &str| STATIC_STRING_VERSIONS.iter().position(|v| *v == s);
+
+        match (rank(lhs), rank(rhs)) {
+            (None, None) => lhs.cmp(rhs),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(lhs), Some(rhs)) => lhs.cmp(&rhs),
+        }
+    }
+
+    /// Parse a single component of a version, between separators
+    fn parse(seg: &str) -> Result<Self, ParseError> {
+        match seg {
+            "" => Err(ParseError::EmptyPart),
+            "*" => Ok(Self::Wildcard(WildcardType::Single)),
+            ">" => Ok(Self::Wildcard(WildcardType::Rest)),
+            _ if seg.bytes().all(|b| b.is_ascii_digit()) => {
+                // Numbers too large for a `usize` are kept as strings
+                Ok(seg
+                    .bytes()
+                    .try_fold(0usize, |n, b| {
+                        n.checked_mul(10)?.checked_add(usize::from(b - b'0'))
+                    })
+                    .map_or_else(|| Self::Str(seg.to_string()), Self::Int))
+            }
+            _ if seg.bytes().all(|b| b.is_ascii_alphanumeric()) => {
+                Ok(Self::Str(seg.to_string()))
+            }
+            _ => Err(ParseError::InvalidPart(seg.to_string())),
+        }
+    }
+
+    /// Sort key of each kind of part
+    const fn kind_rank(&self) -> u8 {
+        match self {
+            Self::Str(_) => 0,
+            Self::Int(_) => 1,
+            Self::Wildcard(WildcardType::Single) => 2,
+            Self::Wildcard(WildcardType::Rest) => 3,
+            Self::Sep(_) => 4,
+        }
+    }
+
     /// Convert a [`Part`] into a [`z3::ast::Dynamic`], if possible.
     ///
     /// - [`Part::Int`] => [`z3::ast::Int`]
@@ -103,6 +193,7 @@ impl Part {
     /// # Panics
     /// Panics if this is a [`Part::Str`] but the string does not appear in the
     /// provided [`BuiltVersionRegistry`]
+    #[cfg(feature = "solver-z3")]
     #[must_use]
     pub fn to_z3_dynamic(
         &self,
@@ -119,7 +210,7 @@ impl Part {
                 let idx = version_registry
                     .lookup_str(v)
                     .expect("Internal solver error");
-                Some(Int::from_u64(*idx as u64).into())
+                Some(Int::from_u64(idx as u64).into())
             }
 
             Self::Sep(v) => {
@@ -131,47 +222,71 @@ impl Part {
     }
 }
 
+impl PartialOrd for Part {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Part {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Int(lhs), Self::Int(rhs)) => lhs.cmp(rhs),
+            (Self::Str(lhs), Self::Str(rhs)) => Self::cmp_str(lhs, rhs),
+            (Self::Sep(lhs), Self::Sep(rhs)) => lhs.cmp(rhs),
+            _ => self.kind_rank().cmp(&other.kind_rank()),
+        }
+    }
+}
+
 impl Version {
     pub fn new(txt: &str) -> Result<Self, ParseError> {
-        let mut segments = Vec::new();
+        let (epoch, txt) = match txt.split_once(EPOCH_SEPARATOR) {
+            Some(("*", rest)) => (Part::Wildcard(WildcardType::Single), rest),
+            Some((epoch, rest)) => (
+                Part::Int(
+                    epoch
+                        .parse()
+                        .map_err(|_| ParseError::InvalidEpoch(epoch.into()))?,
+                ),
+                rest,
+            ),
+            None => (Part::Int(0), txt),
+        };
+
+        let mut parts = Vec::with_capacity(
+            2 + 2 * txt.matches(VERSION_SEPARATORS).count() + 1,
+        );
+
+        parts.push(epoch);
+        parts.push(Part::Sep(EPOCH_SEPARATOR));
 
+        let mut rest = txt;
         let mut seen_rest = false;
 
-        let mut parse_seg = |seg: &str| -> Result<Part, ParseError> {
-            if seen_rest {
-                Err(ParseError::PartAfterRest)
-            } else if seg == "*" {
-                Ok(Part::Wildcard(WildcardType::Single))
-            } else if seg == ">" {
-                seen_rest = true;
-                Ok(Part::Wildcard(WildcardType::Rest))
-            } else if let Ok(num) = seg.parse::<usize>() {
-                Ok(Part::Int(num))
-            } else if seg.chars().all(|c| c.is_ascii_alphanumeric()) {
-                if seg.is_empty() {
-                    Err(ParseError::EmptyPart)
-                } else {
-                    Ok(Part::Str(seg.to_string()))
-                }
-            } else {
-                Err(ParseError::InvalidPart(seg.to_string()))
+        loop {
+            if rest.is_empty() {
+                return Err(ParseError::TrailingSeparator);
+            } else if seen_rest {
+                return Err(ParseError::PartAfterRest);
             }
-        };
 
-        let mut last = 0;
-        for (idx, m) in txt.match_indices(|c| VERSION_SEPARATORS.contains(&c)) {
-            segments.push(parse_seg(&txt[last..idx])?);
-            segments.push(Part::Sep(m.chars().next().unwrap()));
-            last = idx + 1;
-        }
+            let end = rest.find(VERSION_SEPARATORS).unwrap_or(rest.len());
+            let (seg, tail) = rest.split_at(end);
+            let part = Part::parse(seg)?;
 
-        if last == txt.len() {
-            return Err(ParseError::TrailingSeparator);
-        }
+            seen_rest = part == Part::Wildcard(WildcardType::Rest);
+            parts.push(part);
+
+            let mut tail = tail.chars();
+
+            let Some(sep) = tail.next() else { break };
 
-        segments.push(parse_seg(&txt[last..])?);
+            parts.push(Part::Sep(sep));
+            rest = tail.as_str();
+        }
 
-        Ok(Self { parts: segments })
+        Ok(Self { parts })
     }
 
     #[must_use]
@@ -190,6 +305,36 @@ impl Version {
         &self.parts
     }
 
+    /// The epoch of this version, or `None` if it is a wildcard
+    #[must_use]
+    pub fn epoch(&self) -> Option<usize> {
+        match self.parts.first() {
+            Some(Part::Int(epoch)) => Some(*epoch),
+            _ => None,
+        }
+    }
+
+    /// The parts of this version following the epoch
+    #[must_use]
+    pub fn release(&self) -> &[Part] {
+        match self.parts.get(1) {
+            Some(Part::Sep(EPOCH_SEPARATOR)) => &self.parts[2..],
+            _ => &self.parts,
+        }
+    }
+
+    /// Whether this is a calendar version: its first component after the
+    /// epoch is a year in [`CALENDAR_YEARS`], followed by at least one more
+    /// integer component.
+    #[must_use]
+    pub fn is_calendar(&self) -> bool {
+        matches!(
+            self.release(),
+            [Part::Int(year), Part::Sep(_), Part::Int(_), ..]
+                if CALENDAR_YEARS.contains(year)
+        )
+    }
+
     #[must_use]
     pub fn num_segments(&self) -> usize {
         assert_eq!(
@@ -210,6 +355,65 @@ impl Version {
         self.parts.len() / 2
     }
 
+    /// Compare this version against `pattern`, which may contain wildcards.
+    ///
+    /// Returns `None` if the versions cannot be compared, because their
+    /// separators differ or they have different lengths without a
+    /// [`WildcardType::Rest`] to absorb the difference.
+    #[must_use]
+    pub fn cmp_pattern(&self, pattern: &Self) -> Option<Ordering> {
+        let mut parts = self.parts.iter();
+
+        for part in &pattern.parts {
+            match part {
+                Part::Wildcard(WildcardType::Rest) => {
+                    return Some(Ordering::Equal);
+                }
+
+                Part::Wildcard(WildcardType::Single) => {
+                    parts.next()?;
+                }
+
+                Part::Sep(sep) => {
+                    if parts.next()? != &Part::Sep(*sep) {
+                        return None;
+                    }
+                }
+
+                Part::Int(_) | Part::Str(_) => match parts.next()?.cmp(part) {
+                    Ordering::Equal => {}
+                    ord => return Some(ord),
+                },
+            }
+        }
+
+        parts.next().is_none().then_some(Ordering::Equal)
+    }
+
+    /// Whether `self op bound` holds, with the same semantics as a version
+    /// comparison in the solver.
+    ///
+    /// Versions which cannot be compared (see [`Self::cmp_pattern`]) are only
+    /// considered not equal.
+    #[must_use]
+    pub fn satisfies(&self, op: CmpType, bound: &Self) -> bool {
+        let Some(ord) = self.cmp_pattern(bound) else {
+            return matches!(op, CmpType::NotEqual);
+        };
+
+        match op {
+            CmpType::Less => ord.is_lt(),
+            CmpType::LessOrEqual => ord.is_le(),
+            CmpType::NotEqual => ord.is_ne(),
+            CmpType::Equal => ord.is_eq(),
+            CmpType::GreaterOrEqual => ord.is_ge(),
+            CmpType::Greater => ord.is_gt(),
+        }
+    }
+}
+
+#[cfg(feature = "solver-z3")]
+impl Version {
     #[must_use]
     pub fn cmp_dynamic(
         &self,
@@ -253,35 +457,65 @@ impl Version {
         vars: &[z3::ast::Dynamic],
         registry: &BuiltVersionRegistry,
     ) -> z3::ast::Bool {
-        let mut bools = Vec::new();
-
-        for (var, val) in vars.iter().zip(self.parts()) {
-            let cond = match val {
-                Part::Int(i) => var.as_int().unwrap().le(registry
-                    .part_to_dynamic(Part::Int(*i))
-                    .as_int()
-                    .unwrap()),
-
-                Part::Str(s) => var.as_int().unwrap().le(registry
-                    .part_to_dynamic(Part::Str(s.clone()))
-                    .as_int()
-                    .unwrap()),
+        unsafe { self.cmp_order_dynamic(vars, registry, Ordering::Less) }
+    }
 
-                Part::Sep(c) => var.as_string().unwrap().eq(registry
-                    .part_to_dynamic(Part::Sep(*c))
-                    .as_string()
-                    .unwrap()),
+    /// Lexicographic comparison of `vars` against this version, which holds
+    /// if `vars` is equal to the version or ordered `strict` relative to it.
+    ///
+    /// The expression is built from the last component backwards: each value
+    /// component holds if it is strictly ordered, or if it is equal and the
+    /// remaining components hold.
+    ///
+    /// # Safety
+    /// `len(vars) == self.parts().len()`.
+    ///
+    /// # Panics
+    /// Any panics are internal solver errors.
+    unsafe fn cmp_order_dynamic(
+        &self,
+        vars: &[z3::ast::Dynamic],
+        registry: &BuiltVersionRegistry,
+        strict: Ordering,
+    ) -> z3::ast::Bool {
+        use z3::ast::Bool;
+
+        let rest = self
+            .parts()
+            .iter()
+            .position(|p| *p == Part::Wildcard(WildcardType::Rest))
+            .unwrap_or_else(|| self.parts().len());
+
+        let mut res = Bool::from_bool(true);
+
+        for (var, val) in vars.iter().zip(&self.parts()[..rest]).rev() {
+            match val {
+                Part::Int(_) | Part::Str(_) => {
+                    let var = var.as_int().unwrap();
+                    let val =
+                        registry.part_to_dynamic(val.clone()).as_int().unwrap();
+
+                    let ordered = if strict.is_lt() {
+                        var.lt(&val)
+                    } else {
+                        var.gt(&val)
+                    };
+
+                    res = Bool::or(&[ordered, Bool::and(&[var.eq(&val), res])]);
+                }
 
-                Part::Wildcard(w) => match w {
-                    WildcardType::Single => continue,
-                    WildcardType::Rest => break,
-                },
-            };
+                Part::Sep(c) => {
+                    res = Bool::and(&[
+                        var.eq(registry.part_to_dynamic(Part::Sep(*c))),
+                        res,
+                    ]);
+                }
 
-            bools.push(cond);
+                Part::Wildcard(_) => {}
+            }
         }
 
-        z3::ast::Bool::and(&bools)
+        res
     }
 
     /// # Safety
@@ -294,12 +528,7 @@ impl Version {
     ) -> z3::ast::Bool {
         let mut bools = Vec::new();
 
-        println!("vars: {vars:?}");
-        println!("parts: {:?}", self.parts());
-
         for (var, val) in vars.iter().zip(self.parts()) {
-            println!("{var:?} {val:?}");
-
             let cond = match val {
                 Part::Int(i) => var.as_int().unwrap().eq(registry
                     .part_to_dynamic(Part::Int(*i))
@@ -333,35 +562,19 @@ impl Version {
         vars: &[z3::ast::Dynamic],
         registry: &BuiltVersionRegistry,
     ) -> z3::ast::Bool {
-        let mut bools = Vec::new();
-
-        for (var, val) in vars.iter().zip(self.parts()) {
-            let cond = match val {
-                Part::Int(i) => var.as_int().unwrap().ge(registry
-                    .part_to_dynamic(Part::Int(*i))
-                    .as_int()
-                    .unwrap()),
-
-                Part::Str(s) => var.as_int().unwrap().ge(registry
-                    .part_to_dynamic(Part::Str(s.clone()))
-                    .as_int()
-                    .unwrap()),
-
-                Part::Sep(c) => var.as_string().unwrap().eq(registry
-                    .part_to_dynamic(Part::Sep(*c))
-                    .as_string()
-                    .unwrap()),
-
-                Part::Wildcard(w) => match w {
-                    WildcardType::Single => continue,
-                    WildcardType::Rest => break,
-                },
-            };
+        unsafe { self.cmp_order_dynamic(vars, registry, Ordering::Greater) }
+    }
+}
 
-            bools.push(cond);
-        }
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-        z3::ast::Bool::and(&bools)
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.parts.cmp(&other.parts)
     }
 }
 
@@ -387,7 +600,10 @@ impl std::fmt::Display for Part {
 
 impl std::fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for part in &self.parts {
+        let parts =
+            if self.epoch() == Some(0) { self.release() } else { &self.parts };
+
+        for part in parts {
             f.write_str(&part.to_string())?;
         }
 
@@ -395,6 +611,94 @@ impl std::fmt::Display for Version {
     }
 }
 
+/// Versions between two bounds, either of which may be missing, written as
+/// `min:max`, `min:` or `:max`.
+///
+/// Unlike comparisons in the solver, bounds of a different length to the
+/// version are allowed: a version is within the range if it is at least `min`
+/// in the [`Ord`] of versions, and at most `max` or prefixed by it, so
+/// `5.0.3` is within both `5:` and `:5`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VersionRange {
+    pub min: Option<Version>,
+    pub max: Option<Version>,
+}
+
+impl VersionRange {
+    /// Parse a range, or return `None` if `txt` is not one. The bounds are
+    /// split at the first `:` which leaves two valid bounds, so a bound may
+    /// have an epoch, as in `1:2.0:`.
+    #[must_use]
+    pub fn parse(txt: &str) -> Option<Self> {
+        let bound = |txt: &str| {
+            if txt.is_empty() {
+                Some(None)
+            } else {
+                Version::new(txt).ok().map(Some)
+            }
+        };
+
+        txt.match_indices(EPOCH_SEPARATOR).find_map(|(idx, _)| {
+            let min = bound(&txt[..idx])?;
+            let max = bound(&txt[idx + 1..])?;
+
+            (min.is_some() || max.is_some()).then_some(Self { min, max })
+        })
+    }
+
+    /// Whether `version` is within the range
+    #[must_use]
+    pub fn contains(&self, version: &Version) -> bool {
+        self.min.as_ref().is_none_or(|min| version >= min)
+            && self.max.as_ref().is_none_or(|max| {
+                version <= max || version.parts.starts_with(&max.parts)
+            })
+    }
+}
+
+impl std::fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let min = self.min.as_ref().map(ToString::to_string);
+        let mut max = self.max.as_ref().map(ToString::to_string);
+
+        // `1:2` would be read back as a version with an epoch, so give the
+        // maximum its epoch explicitly
+        if let (Some(min), Some(max)) = (&min, &mut max)
+            && Version::new(&format!("{min}{EPOCH_SEPARATOR}{max}")).is_ok()
+        {
+            max.insert_str(0, "0:");
+        }
+
+        write!(
+            f,
+            "{}{EPOCH_SEPARATOR}{}",
+            min.unwrap_or_default(),
+            max.unwrap_or_default()
+        )
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let txt = String::deserialize(deserializer)?;
+        Self::new(&txt).map_err(|e| {
+            serde::de::Error::custom(format!("invalid version '{txt}': {e:?}"))
+        })
+    }
+}
+
+#[cfg(feature = "python")]
 #[pymethods]
 impl Version {
     #[new]
@@ -409,4 +713,57 @@ impl Version {
     fn __str__(&self) -> String {
         format!("{self}")
     }
+
+    #[getter]
+    #[pyo3(name = "epoch")]
+    fn py_epoch(&self) -> Option<usize> {
+        self.epoch()
+    }
+
+    #[pyo3(name = "is_calendar")]
+    fn py_is_calendar(&self) -> bool {
+        self.is_calendar()
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> bool {
+        op.matches(self.cmp(other))
+    }
+
+    fn __hash__(&self) -> u64 {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether `self op bound` holds, where `bound` may contain wildcards
+    #[pyo3(name = "satisfies")]
+    fn py_satisfies(&self, op: CmpType, bound: &Self) -> bool {
+        self.satisfies(op, bound)
+    }
+
+    fn __getstate__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        pickle::state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = pickle::from_state(state)?;
+        Ok(())
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<pickle::Reduced<'py>> {
+        pickle::reduce(slf)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "_from_state")]
+    fn py_from_state(_cls: &Bound<'_, PyType>, state: &[u8]) -> PyResult<Self> {
+        pickle::from_state(state)
+    }
 }
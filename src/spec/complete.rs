@@ -0,0 +1,162 @@
+//! Completion of spec strings (see [`crate::spec::parse`]) for shells.
+//!
+//! The word being completed is a spec string up to the cursor, which may hold
+//! several whitespace separated parts. Only the last part is completed:
+//!
+//! - The first part is completed with package names, qualified with their
+//!   namespace once a `.` has been typed
+//! - `+name`, `~name`, `++name` and `~~name` are completed with the boolean
+//!   options of the package, or of every package when propagated
+//! - `name=value` is completed with the values the option is known to take
+//! - Anything else is completed with option names, followed by `=`
+//!
+//! Versions are not completed.
+//!
+//! Every candidate is the whole word, so shells can replace the word with it.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    constraint::ConstraintUtils,
+    package::{outline::PackageOutline, repo::RepoStack},
+    spec::{
+        SpecOptionType, SpecOptionValue,
+        concrete::VERSION_OPTION,
+        parse::known_options,
+        target::{TARGET_OPTION, TARGETS},
+    },
+};
+
+/// Every value `option` of `package` is known to take: its valid values,
+/// defaults and values set by packages.
+fn known_values(
+    outlines: &[PackageOutline],
+    package: &str,
+    option: &str,
+) -> Vec<SpecOptionValue> {
+    let mut values = Vec::new();
+
+    for outline in outlines {
+        for constraint in outline.all_constraints() {
+            for (_, _, spec_option) in constraint
+                .extract_spec_options()
+                .into_iter()
+                .filter(|(pkg, opt, _)| *pkg == package && *opt == option)
+            {
+                values.extend(spec_option.valid.into_iter().flatten());
+                values.extend(spec_option.value);
+                values.extend(spec_option.default);
+            }
+        }
+
+        if outline.name == package {
+            values.extend(outline.set_defaults.get(option).cloned().flatten());
+            values.extend(outline.set_options.get(option).cloned());
+        }
+    }
+
+    values
+}
+
+/// Whether `option` of `package` is a boolean, as far as can be told from its
+/// known values.
+fn is_bool(outlines: &[PackageOutline], package: &str, option: &str) -> bool {
+    known_values(outlines, package, option)
+        .iter()
+        .any(|v| v.to_type() == SpecOptionType::Bool)
+}
+
+/// The values to offer for `option` of `package`.
+fn value_candidates(
+    outlines: &[PackageOutline],
+    package: &str,
+    option: &str,
+) -> BTreeSet<String> {
+    if option == TARGET_OPTION {
+        return TARGETS.iter().map(|t| t.name.to_string()).collect();
+    }
+
+    let values = known_values(outlines, package, option);
+
+    if values.iter().any(|v| v.to_type() == SpecOptionType::Bool) {
+        return BTreeSet::from(["true".to_string(), "false".to_string()]);
+    }
+
+    values.iter().map(ToString::to_string).collect()
+}
+
+/// Package names matching `prefix`, qualified with their namespace if
+/// `prefix` names one.
+fn package_candidates(repos: &RepoStack, prefix: &str) -> BTreeSet<String> {
+    let outlines = repos.outlines();
+
+    if let (Some(namespace), _) = repos.split(prefix) {
+        return outlines
+            .iter()
+            .filter(|o| o.namespace.as_deref() == Some(namespace))
+            .map(|o| format!("{namespace}.{}", o.name))
+            .collect();
+    }
+
+    outlines
+        .iter()
+        .map(|o| o.name.clone())
+        .chain(repos.namespaces().map(|ns| format!("{ns}.")))
+        .collect()
+}
+
+/// Complete the last part of the spec string `word`, returning every
+/// candidate for the whole word which starts with it.
+#[must_use]
+pub fn complete(repos: &RepoStack, word: &str) -> Vec<String> {
+    let (head, part) = word
+        .rfind(char::is_whitespace)
+        .map_or(("", word), |idx| word.split_at(idx + 1));
+
+    let candidates = if head.trim().is_empty() {
+        package_candidates(repos, part)
+    } else {
+        let root = head.split_whitespace().next().unwrap_or_default();
+        let root = root.split_once('@').map_or(root, |(name, _)| name);
+
+        let (_, name) = repos.split(root);
+        let (outlines, _) =
+            repos.resolve(&[root.to_string()]).unwrap_or_default();
+
+        let sigil_len = part.len() - part.trim_start_matches(['+', '~']).len();
+
+        if sigil_len > 0 {
+            let (sigil, _) = part.split_at(sigil_len);
+            let propagate = sigil.len() > 1;
+
+            outlines
+                .iter()
+                .filter(|o| propagate || o.name == name)
+                .flat_map(|o| {
+                    known_options(&outlines, &o.name)
+                        .into_iter()
+                        .filter(|opt| is_bool(&outlines, &o.name, opt))
+                        .map(|opt| format!("{sigil}{opt}"))
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        } else if let Some((option, _)) = part.split_once('=') {
+            value_candidates(&outlines, name, option)
+                .into_iter()
+                .map(|value| format!("{option}={value}"))
+                .collect()
+        } else {
+            known_options(&outlines, name)
+                .into_iter()
+                .filter(|opt| *opt != VERSION_OPTION)
+                .map(|opt| format!("{opt}="))
+                .collect()
+        }
+    };
+
+    candidates
+        .into_iter()
+        .filter(|c| c.starts_with(part))
+        .map(|c| format!("{head}{c}"))
+        .collect()
+}
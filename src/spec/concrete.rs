@@ -0,0 +1,977 @@
+//! Fully solved package specifications.
+//!
+//! A concrete spec is the fully solved result of concretizing a
+//! [`SpecOutline`]. Every active package has a known version (if it has one),
+//! every option referenced by a constraint has a value and the dependency
+//! graph only contains packages which were activated by the solver.
+//!
+//! Concrete specs are what later stages (building, installing, exporting)
+//! operate on, so they are deliberately plain data: they do not hold on to the
+//! solver or the registry once constructed.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+#[cfg(feature = "python")]
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "solver-z3")]
+use crate::package;
+use crate::{
+    constraint::Constraint,
+    package::{
+        flags::{ConcreteFlags, FlagMapping},
+        outline::{self, SolverError, SpecOutline},
+        patch::{ConcretePatch, Patch},
+        source::Source,
+        test::{ConcreteTest, SmokeTest},
+        version::Version,
+    },
+    spec::{SpecOptionValue, provenance::Provenance},
+};
+#[cfg(feature = "python")]
+use crate::{constraint::syntax, package::flags, spec::parse::SpecRequest};
+
+/// The name of the option holding a package's version
+pub const VERSION_OPTION: &str = "version";
+
+/// A single, fully concretized package.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcretePackage {
+    pub name: String,
+
+    /// Namespace of the repository the package was taken from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    pub version: Option<Version>,
+    pub options: BTreeMap<String, SpecOptionValue>,
+    pub dependencies: BTreeSet<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+
+    /// SPDX license expression of the package, if it declares one. Not part
+    /// of the hash, since it does not change what is built
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patches: Vec<ConcretePatch>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub flags: ConcreteFlags,
+
+    /// Smoke tests to run once installed. Not part of the hash, since they
+    /// do not change what is built
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<ConcreteTest>,
+
+    /// Why each option, including the version, has its value. Not part of
+    /// the hash, since it does not change what is built
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub provenance: BTreeMap<String, Provenance>,
+}
+
+/// A set of concretized packages and the roots they were solved for.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcreteSpec {
+    pub roots: Vec<String>,
+    pub packages: BTreeMap<String, ConcretePackage>,
+}
+
+/// 64-bit FNV-1a. Used instead of [`std::hash::DefaultHasher`] because the
+/// result must be stable across runs, platforms and compiler versions.
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+impl ConcretePackage {
+    /// The name of the package this node is an instance of, without any
+    /// duplicate node tag (see [`outline::NODE_TAG_SEPARATOR`]).
+    #[must_use]
+    pub fn base_name(&self) -> &str {
+        outline::split_node(&self.name).0
+    }
+
+    /// The value of `option`, including the version, with the reason it has
+    /// that value. `None` if the package has no such option.
+    #[must_use]
+    pub fn explain(
+        &self,
+        option: &str,
+    ) -> Option<(SpecOptionValue, Option<&Provenance>)> {
+        let value = if option == VERSION_OPTION {
+            SpecOptionValue::Version(self.version.clone()?)
+        } else {
+            self.options.get(option)?.clone()
+        };
+
+        Some((value, self.provenance.get(option)))
+    }
+
+    /// Hash of this package and, recursively, everything it depends on.
+    ///
+    /// Two packages with the same hash were concretized identically, including
+    /// their entire dependency sub-DAG. To hash every package in a spec, use
+    /// [`ConcreteSpec::dag_hashes`], which hashes each package only once.
+    ///
+    /// * `spec`: The concrete spec this package belongs to. Used to resolve
+    ///   dependencies
+    #[must_use]
+    pub fn dag_hash(&self, spec: &ConcreteSpec) -> String {
+        self.dag_hash_with(spec, &mut HashMap::new())
+    }
+
+    /// [`Self::dag_hash`], reusing and filling `memo`, the hashes of packages
+    /// in `spec` by name.
+    ///
+    /// Dependencies are hashed bottom-up with an explicit stack, so a deep
+    /// graph cannot overflow the call stack. A dependency which leads back to
+    /// a package still being hashed is hashed by name, so a spec with a cycle
+    /// (see [`ConcreteSpec::find_cycle`]) still terminates.
+    fn dag_hash_with<'a>(
+        &'a self,
+        spec: &'a ConcreteSpec,
+        memo: &mut HashMap<&'a str, String>,
+    ) -> String {
+        let mut visiting = HashSet::new();
+        let mut stack = vec![(self, false)];
+
+        while let Some((package, expanded)) = stack.pop() {
+            let name = package.name.as_str();
+
+            if memo.contains_key(name) {
+                continue;
+            }
+
+            if expanded {
+                visiting.remove(name);
+                let hash = package.node_hash(memo);
+                memo.insert(name, hash);
+                continue;
+            }
+
+            if !visiting.insert(name) {
+                continue;
+            }
+
+            stack.push((package, true));
+            stack.extend(
+                package
+                    .dependencies
+                    .iter()
+                    .filter_map(|dep| spec.packages.get(dep))
+                    .filter(|dep| {
+                        !memo.contains_key(dep.name.as_str())
+                            && !visiting.contains(dep.name.as_str())
+                    })
+                    .map(|dep| (dep, false)),
+            );
+        }
+
+        memo.get(self.name.as_str()).cloned().unwrap_or_default()
+    }
+
+    /// Hash of this package, given the hashes of its dependencies in `memo`.
+    /// A dependency without a hash is hashed by name.
+    fn node_hash(&self, memo: &HashMap<&str, String>) -> String {
+        let mut canonical = self.to_string();
+
+        if let Some(namespace) = &self.namespace {
+            canonical.insert_str(0, &format!("{namespace}."));
+        }
+
+        if let Some(source) = &self.source {
+            canonical.push('#');
+            canonical.push_str(&source.checksum.to_string());
+        }
+
+        for dep in &self.dependencies {
+            canonical.push('^');
+            canonical.push_str(memo.get(dep.as_str()).map_or(dep, |h| h));
+        }
+
+        format!("{:016x}", stable_hash(canonical.as_bytes()))
+    }
+}
+
+/// A solution to a [`SpecOutline`], from which a [`ConcreteSpec`] is
+/// extracted by [`ConcreteSpec::from_assignment`].
+pub trait Assignment {
+    /// Whether the package `name` is active.
+    ///
+    /// # Errors
+    /// Errors if the package has no solver variable.
+    fn is_active(&self, name: &str) -> Result<bool, Box<SolverError>>;
+
+    /// The value of every option of the package `name` with a solver
+    /// variable, including its version.
+    ///
+    /// # Errors
+    /// Errors if an option cannot be evaluated.
+    fn options(
+        &self,
+        name: &str,
+    ) -> Result<Vec<(String, SpecOptionValue)>, Box<SolverError>>;
+
+    /// Whether the variable of a patch or flag condition (see
+    /// [`PackageOutline::conditions`]) is true.
+    ///
+    /// [`PackageOutline::conditions`]: crate::package::outline::PackageOutline::conditions
+    fn holds(&self, flag_name: &str) -> bool;
+}
+
+/// A solved z3 model and the registry of the solver which produced it
+#[cfg(feature = "solver-z3")]
+struct Model<'a> {
+    model: &'a z3::Model,
+    registry: &'a package::BuiltRegistry,
+}
+
+#[cfg(feature = "solver-z3")]
+impl Assignment for Model<'_> {
+    fn is_active(&self, name: &str) -> Result<bool, Box<SolverError>> {
+        let value =
+            self.registry.eval_option(name, None, self.model, self.registry)?;
+
+        Ok(value == SpecOptionValue::Bool(true))
+    }
+
+    fn options(
+        &self,
+        name: &str,
+    ) -> Result<Vec<(String, SpecOptionValue)>, Box<SolverError>> {
+        let mut res = Vec::new();
+
+        for &(package, option) in &self.registry.spec_option_names() {
+            let Some(option) = option else { continue };
+
+            // Options only referred to by packages which were not grounded
+            // have a type but no solver variable
+            let has_var =
+                self.registry.lookup_option(package, Some(option)).is_some_and(
+                    |idx| self.registry.spec_options()[idx].1.is_some(),
+                );
+
+            if package != name || !has_var {
+                continue;
+            }
+
+            let value = self.registry.eval_option(
+                package,
+                Some(option),
+                self.model,
+                self.registry,
+            )?;
+
+            res.push((option.to_string(), value));
+        }
+
+        Ok(res)
+    }
+
+    fn holds(&self, flag_name: &str) -> bool {
+        self.model
+            .eval(&z3::ast::Bool::new_const(flag_name), true)
+            .and_then(|b| b.as_bool())
+            .unwrap_or(false)
+    }
+}
+
+impl ConcreteSpec {
+    /// Extract a concrete spec from a solved model.
+    ///
+    /// # Errors
+    /// Errors if a package or option in the outline has no corresponding
+    /// solver variable in the registry.
+    #[cfg(feature = "solver-z3")]
+    pub fn from_model(
+        outline: &SpecOutline,
+        model: &z3::Model,
+        registry: &package::BuiltRegistry,
+    ) -> Result<Self, Box<SolverError>> {
+        Self::from_assignment(outline, &Model { model, registry })
+    }
+
+    /// Extract a concrete spec from a solution to `outline`.
+    ///
+    /// A package is included if it is grounded (see
+    /// [`SpecOutline::grounded`]) and active in the assignment. Dependencies
+    /// are the outgoing edges of the package in the outline graph which lead
+    /// to other active packages. Unconditional patches, flag mappings and
+    /// tests are always included; conditional ones are included if their
+    /// condition holds.
+    ///
+    /// # Errors
+    /// Errors if a package or option in the outline has no value in the
+    /// assignment.
+    pub fn from_assignment(
+        outline: &SpecOutline,
+        assignment: &impl Assignment,
+    ) -> Result<Self, Box<SolverError>> {
+        let mut active = BTreeSet::new();
+
+        for idx in outline.grounded() {
+            let name = outline.graph[idx].name.as_str();
+
+            if assignment.is_active(name)? {
+                active.insert(name);
+            }
+        }
+
+        let mut packages = BTreeMap::new();
+
+        for &name in &active {
+            let mut version = None;
+            let mut options = BTreeMap::new();
+
+            for (option, value) in assignment.options(name)? {
+                match value {
+                    SpecOptionValue::Version(v) if option == VERSION_OPTION => {
+                        version = Some(v);
+                    }
+                    value => {
+                        options.insert(option, value);
+                    }
+                }
+            }
+
+            let provenance = version
+                .iter()
+                .map(|v| (VERSION_OPTION, SpecOptionValue::Version(v.clone())))
+                .chain(options.iter().map(|(o, v)| (o.as_str(), v.clone())))
+                .map(|(option, value)| {
+                    let why = Provenance::of(outline, name, option, &value);
+                    (option.to_string(), why)
+                })
+                .collect();
+
+            let idx = outline.lookup[name];
+
+            let source =
+                Source::select(&outline.graph[idx].sources, version.as_ref())
+                    .cloned();
+
+            let holds = |when: Option<&Constraint>, flag_name: String| {
+                when.is_none() || assignment.holds(&flag_name)
+            };
+
+            let patches = outline.graph[idx]
+                .patches
+                .iter()
+                .enumerate()
+                .filter(|(index, patch)| {
+                    holds(patch.when.as_ref(), Patch::flag_name(name, *index))
+                })
+                .map(|(_, patch)| patch.to_concrete())
+                .collect();
+
+            let mut flags = ConcreteFlags::new();
+
+            for (index, mapping) in outline.graph[idx].flags.iter().enumerate()
+            {
+                if holds(
+                    mapping.when.as_ref(),
+                    FlagMapping::flag_name(name, index),
+                ) {
+                    flags
+                        .entry(mapping.kind)
+                        .or_default()
+                        .extend(mapping.flags.iter().cloned());
+                }
+            }
+
+            let tests = outline.graph[idx]
+                .tests
+                .iter()
+                .enumerate()
+                .filter(|(index, test)| {
+                    holds(
+                        test.when.as_ref(),
+                        SmokeTest::flag_name(name, *index),
+                    )
+                })
+                .map(|(_, test)| test.to_concrete())
+                .collect();
+
+            let dependencies = outline
+                .graph
+                .neighbors_directed(idx, petgraph::Direction::Outgoing)
+                .map(|dep| outline.graph[dep].name.as_str())
+                .filter(|dep| active.contains(dep))
+                .map(str::to_string)
+                .collect();
+
+            packages.insert(
+                name.to_string(),
+                ConcretePackage {
+                    name: name.to_string(),
+                    namespace: outline.graph[idx].namespace.clone(),
+                    version,
+                    options,
+                    dependencies,
+                    source,
+                    license: outline.graph[idx].license.clone(),
+                    patches,
+                    flags,
+                    tests,
+                    provenance,
+                },
+            );
+        }
+
+        Ok(Self { roots: outline.required.clone(), packages })
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ConcretePackage> {
+        self.packages.get(name)
+    }
+
+    /// Serialize this spec to a JSON string.
+    ///
+    /// # Errors
+    /// Errors if serialization fails, which should not happen for a valid
+    /// spec.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Parse a spec previously written by [`Self::to_json`].
+    ///
+    /// # Errors
+    /// Errors if `txt` is not a valid JSON representation of a spec, or its
+    /// packages depend on each other in a cycle.
+    pub fn from_json(txt: &str) -> serde_json::Result<Self> {
+        let spec: Self = serde_json::from_str(txt)?;
+
+        if let Some(cycle) = spec.find_cycle() {
+            return Err(serde::de::Error::custom(format!(
+                "dependency cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        Ok(spec)
+    }
+
+    /// The DAG hash (see [`ConcretePackage::dag_hash`]) of every package, by
+    /// name. Each package is hashed once, after everything it depends on.
+    #[must_use]
+    pub fn dag_hashes(&self) -> HashMap<&str, String> {
+        let mut memo = HashMap::new();
+
+        for package in self.packages.values() {
+            package.dag_hash_with(self, &mut memo);
+        }
+
+        memo
+    }
+
+    /// A dependency cycle among the packages, as the names along it with the
+    /// first repeated at the end, or `None` if the packages form a DAG.
+    ///
+    /// A solved spec never has a cycle, but one read from a file might.
+    #[must_use]
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut done = HashSet::new();
+
+        for (root, package) in &self.packages {
+            if done.contains(root.as_str()) {
+                continue;
+            }
+
+            // The path from `root`, with the dependencies left to visit from
+            // each package along it, and the position of each on the path
+            let mut path = vec![(root.as_str(), package.dependencies.iter())];
+            let mut on_path = HashMap::from([(root.as_str(), 0)]);
+
+            while let Some((name, deps)) = path.last_mut() {
+                let name = *name;
+
+                let Some(dep) = deps.next() else {
+                    done.insert(name);
+                    on_path.remove(name);
+                    path.pop();
+                    continue;
+                };
+
+                if let Some(&start) = on_path.get(dep.as_str()) {
+                    let mut cycle = path[start..]
+                        .iter()
+                        .map(|(n, _)| (*n).to_string())
+                        .collect::<Vec<_>>();
+                    cycle.push(dep.clone());
+
+                    return Some(cycle);
+                }
+
+                if done.contains(dep.as_str()) {
+                    continue;
+                }
+
+                if let Some(package) = self.packages.get(dep) {
+                    on_path.insert(dep, path.len());
+                    path.push((dep, package.dependencies.iter()));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl std::fmt::Display for ConcretePackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+
+        if let Some(version) = &self.version {
+            write!(f, "@{version}")?;
+        }
+
+        for (name, value) in &self.options {
+            match value {
+                SpecOptionValue::Bool(true) => write!(f, " +{name}")?,
+                SpecOptionValue::Bool(false) => write!(f, " ~{name}")?,
+                other => write!(f, " {name}={other}")?,
+            }
+        }
+
+        if !self.patches.is_empty() {
+            let hashes = self
+                .patches
+                .iter()
+                .map(|p| &p.checksum.hex()[..7])
+                .collect::<Vec<_>>();
+
+            write!(f, " patches={}", hashes.join(","))?;
+        }
+
+        for (kind, flags) in &self.flags {
+            write!(f, " {kind}=\"{}\"", flags.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ConcreteSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hashes = self.dag_hashes();
+
+        for package in self.packages.values() {
+            let hash =
+                hashes.get(package.name.as_str()).map_or("", |h| &h[..7]);
+            writeln!(f, "[{hash}] {package}")?;
+
+            for dep in &package.dependencies {
+                writeln!(f, "          ^{dep}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "python")]
+fn flags_to_dict(flags: &ConcreteFlags) -> BTreeMap<String, Vec<String>> {
+    flags
+        .iter()
+        .map(|(kind, flags)| (kind.to_string(), flags.clone()))
+        .collect()
+}
+
+#[cfg(feature = "python")]
+fn provenance_to_dict(
+    provenance: &BTreeMap<String, Provenance>,
+) -> BTreeMap<String, String> {
+    provenance
+        .iter()
+        .map(|(option, why)| (option.clone(), why.to_string()))
+        .collect()
+}
+
+#[cfg(feature = "python")]
+fn package_to_dict<'py>(
+    py: Python<'py>,
+    package: &ConcretePackage,
+    hash: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+
+    dict.set_item("name", &package.name)?;
+    dict.set_item("namespace", &package.namespace)?;
+    dict.set_item(
+        "version",
+        package.version.as_ref().map(ToString::to_string),
+    )?;
+    dict.set_item("options", package.options.clone())?;
+    dict.set_item(
+        "dependencies",
+        package.dependencies.iter().collect::<Vec<_>>(),
+    )?;
+
+    dict.set_item("source", package.source.as_ref().map(|s| &s.url))?;
+    dict.set_item("license", &package.license)?;
+    dict.set_item(
+        "patches",
+        package
+            .patches
+            .iter()
+            .map(|p| p.checksum.to_string())
+            .collect::<Vec<_>>(),
+    )?;
+    dict.set_item("flags", flags_to_dict(&package.flags))?;
+    dict.set_item(
+        "tests",
+        package.tests.iter().map(|t| &t.name).collect::<Vec<_>>(),
+    )?;
+    dict.set_item("provenance", provenance_to_dict(&package.provenance))?;
+
+    if let Some(hash) = hash {
+        dict.set_item("hash", hash)?;
+    }
+
+    Ok(dict)
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl ConcretePackage {
+    #[getter]
+    #[pyo3(name = "name")]
+    fn py_name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[getter]
+    #[pyo3(name = "namespace")]
+    fn py_namespace(&self) -> Option<String> {
+        self.namespace.clone()
+    }
+
+    /// SPDX license expression of the package, if it declares one
+    #[getter]
+    #[pyo3(name = "license")]
+    fn py_license(&self) -> Option<String> {
+        self.license.clone()
+    }
+
+    #[pyo3(name = "version")]
+    fn py_version(&self) -> Option<Version> {
+        self.version.clone()
+    }
+
+    #[pyo3(name = "options")]
+    fn py_options(&self) -> BTreeMap<String, SpecOptionValue> {
+        self.options.clone()
+    }
+
+    #[pyo3(name = "option")]
+    fn py_option(&self, name: &str) -> Option<SpecOptionValue> {
+        self.options.get(name).cloned()
+    }
+
+    #[pyo3(name = "dependencies")]
+    fn py_dependencies(&self) -> Vec<String> {
+        self.dependencies.iter().cloned().collect()
+    }
+
+    /// SHA-256 checksums of the patches applied to this package
+    #[pyo3(name = "patches")]
+    fn py_patches(&self) -> Vec<String> {
+        self.patches.iter().map(|p| p.checksum.to_string()).collect()
+    }
+
+    /// Compiler and linker flags, by kind (`cflags`, `ldflags`, etc.)
+    #[pyo3(name = "flags")]
+    fn py_flags(&self) -> BTreeMap<String, Vec<String>> {
+        flags_to_dict(&self.flags)
+    }
+
+    /// Names of the smoke tests to run once installed
+    #[pyo3(name = "tests")]
+    fn py_tests(&self) -> Vec<String> {
+        self.tests.iter().map(|t| t.name.clone()).collect()
+    }
+
+    /// Why each option, including the version, has its value
+    #[pyo3(name = "provenance")]
+    fn py_provenance(&self) -> BTreeMap<String, String> {
+        provenance_to_dict(&self.provenance)
+    }
+
+    /// Why `option` has its value, such as "default inherited from 'hpl'"
+    ///
+    /// # Errors
+    /// Errors if the package has no such option.
+    #[pyo3(name = "explain")]
+    fn py_explain(&self, option: &str) -> PyResult<String> {
+        match self.explain(option) {
+            Some((_, Some(why))) => Ok(why.to_string()),
+            Some((_, None)) => Ok("unknown".to_string()),
+            None => Err(PyKeyError::new_err(option.to_string())),
+        }
+    }
+
+    /// Environment variables for building this package, such as `CFLAGS`
+    ///
+    /// * `rpath`: One of `rpath`, `runpath` or `none`; how to embed the
+    ///   library directories of `prefixes` in what is linked
+    /// * `prefixes`: The prefixes the package's dependencies are installed to
+    ///
+    /// # Errors
+    /// Errors if `rpath` is not a valid rpath mode.
+    #[pyo3(name = "build_env", signature = (rpath = "rpath", prefixes = Vec::new()))]
+    fn py_build_env(
+        &self,
+        rpath: &str,
+        prefixes: Vec<std::path::PathBuf>,
+    ) -> PyResult<BTreeMap<String, String>> {
+        let mode = rpath.parse().map_err(PyValueError::new_err)?;
+        Ok(flags::build_env_with_rpaths(self, mode, prefixes))
+    }
+
+    /// Hash of this package alone, without its dependencies. Use
+    /// `ConcreteSpec.hash(name)` for the full DAG hash.
+    #[pyo3(name = "hash")]
+    fn py_hash(&self) -> String {
+        format!("{:016x}", stable_hash(self.to_string().as_bytes()))
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        package_to_dict(py, self, None)
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ConcretePackage({self})")
+    }
+
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl ConcreteSpec {
+    #[pyo3(name = "roots")]
+    fn py_roots(&self) -> Vec<String> {
+        self.roots.clone()
+    }
+
+    #[pyo3(name = "packages")]
+    fn py_packages(&self) -> Vec<ConcretePackage> {
+        self.packages.values().cloned().collect()
+    }
+
+    /// The DAG hash of the package `name`
+    #[pyo3(name = "hash")]
+    fn py_hash(&self, name: &str) -> PyResult<String> {
+        self.get(name)
+            .map(|p| p.dag_hash(self))
+            .ok_or_else(|| PyKeyError::new_err(name.to_string()))
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        let packages = PyDict::new(py);
+        let hashes = self.dag_hashes();
+
+        for (name, package) in &self.packages {
+            let hash = hashes.get(name.as_str()).map(String::as_str);
+            packages.set_item(name, package_to_dict(py, package, hash)?)?;
+        }
+
+        dict.set_item("roots", self.roots.clone())?;
+        dict.set_item("packages", packages)?;
+
+        Ok(dict)
+    }
+
+    /// Whether a root of this spec satisfies the spec string `spec`, such as
+    /// `"openmpi@5: +cuda ^hwloc"` (see [`crate::spec::query`])
+    ///
+    /// # Errors
+    /// Errors if `spec` is not a valid spec string.
+    fn satisfies(&self, spec: &str) -> PyResult<bool> {
+        let request = SpecRequest::parse(spec)
+            .map_err(|e| PyValueError::new_err(syntax::render(e)))?;
+
+        Ok(request.satisfied_by(self))
+    }
+
+    #[pyo3(name = "to_json")]
+    fn py_to_json(&self) -> PyResult<String> {
+        self.to_json().map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_json")]
+    fn py_from_json(txt: &str) -> PyResult<Self> {
+        Self::from_json(txt).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn __getitem__(&self, name: &str) -> PyResult<ConcretePackage> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| PyKeyError::new_err(name.to_string()))
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.packages.contains_key(name)
+    }
+
+    fn __len__(&self) -> usize {
+        self.packages.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ConcreteSpec(roots={:?}, packages={:?})",
+            self.roots,
+            self.packages.keys().collect::<Vec<_>>()
+        )
+    }
+
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, dependencies: &[&str]) -> ConcretePackage {
+        ConcretePackage {
+            name: name.to_string(),
+            namespace: None,
+            version: None,
+            options: BTreeMap::new(),
+            dependencies: dependencies
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            source: None,
+            license: None,
+            patches: Vec::new(),
+            flags: BTreeMap::new(),
+            tests: Vec::new(),
+            provenance: BTreeMap::new(),
+        }
+    }
+
+    fn spec(
+        packages: impl IntoIterator<Item = ConcretePackage>,
+    ) -> ConcreteSpec {
+        ConcreteSpec {
+            roots: Vec::new(),
+            packages: packages
+                .into_iter()
+                .map(|p| (p.name.clone(), p))
+                .collect(),
+        }
+    }
+
+    fn diamond() -> ConcreteSpec {
+        spec([
+            package("app", &["left", "right"]),
+            package("left", &["zlib"]),
+            package("right", &["zlib"]),
+            package("zlib", &[]),
+        ])
+    }
+
+    #[test]
+    fn dag_hashes_match_dag_hash() {
+        let spec = diamond();
+        let hashes = spec.dag_hashes();
+
+        assert_eq!(hashes.len(), spec.packages.len());
+
+        for (name, package) in &spec.packages {
+            assert_eq!(hashes[name.as_str()], package.dag_hash(&spec));
+        }
+    }
+
+    #[test]
+    fn dag_hash_covers_dependencies() {
+        let before = diamond();
+        let mut after = diamond();
+        after.packages.get_mut("zlib").unwrap().namespace =
+            Some("builtin".to_string());
+
+        let before = before.dag_hashes();
+        let after = after.dag_hashes();
+
+        for name in ["app", "left", "right", "zlib"] {
+            assert_ne!(before[name], after[name], "{name} did not change");
+        }
+    }
+
+    #[test]
+    fn dag_hash_of_a_deep_chain() {
+        let depth = 100_000;
+        let spec = spec((0..depth).map(|i| {
+            let dep = format!("p{}", i + 1);
+            let deps = if i + 1 < depth { vec![dep.as_str()] } else { vec![] };
+            package(&format!("p{i}"), &deps)
+        }));
+
+        assert_eq!(spec.find_cycle(), None);
+        assert_eq!(spec.dag_hashes().len(), depth);
+        assert!(!spec.packages["p0"].dag_hash(&spec).is_empty());
+    }
+
+    #[test]
+    fn finds_cycles() {
+        assert_eq!(diamond().find_cycle(), None);
+
+        let cyclic = spec([
+            package("a", &["b"]),
+            package("b", &["c", "zlib"]),
+            package("c", &["a"]),
+            package("zlib", &[]),
+        ]);
+
+        assert_eq!(cyclic.find_cycle().unwrap(), ["a", "b", "c", "a"]);
+        assert_eq!(
+            spec([package("a", &["a"])]).find_cycle().unwrap(),
+            ["a", "a"]
+        );
+
+        // Hashing still terminates
+        assert_eq!(cyclic.dag_hashes().len(), 4);
+    }
+
+    #[test]
+    fn from_json_rejects_cycles() {
+        let json = diamond().to_json().unwrap();
+        assert_eq!(ConcreteSpec::from_json(&json).unwrap(), diamond());
+
+        let cyclic = spec([package("a", &["b"]), package("b", &["a"])]);
+        let err = ConcreteSpec::from_json(&cyclic.to_json().unwrap())
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("a -> b -> a"), "{err}");
+    }
+}
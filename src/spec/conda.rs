@@ -0,0 +1,338 @@
+//! Export of concretized environments as conda environments.
+//!
+//! Packages which are also available from conda channels, such as
+//! conda-forge, can be installed with conda or pixi instead of being built by
+//! zpack. A [`CondaExport`] pins each such package in a [`ConcreteSpec`] to
+//! its concretized version and is rendered as either:
+//!
+//! - An `environment.yml`, for `conda env create -f environment.yml`
+//! - A `pixi.toml` manifest, for `pixi install`
+//!
+//! zpack cannot know which packages a channel provides, so a package is
+//! assumed to be available under its own name, with any `py-` prefix removed,
+//! if it has a version. Packages without one, such as virtual packages, are
+//! left out. The `conda` configuration option overrides this:
+//!
+//! ```yaml
+//! conda:
+//!   channels: [conda-forge]
+//!   packages:
+//!     libjpeg-turbo: libjpeg-turbo
+//!     gmake: make
+//!     my-internal-lib: null  # never exported
+//! ```
+//!
+//! Packages which are not exported are reported so they can be built with
+//! zpack.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    package::version::Version,
+    spec::{concrete::ConcreteSpec, platform::Platform},
+};
+
+/// The conda channel packages are assumed to come from by default
+pub const DEFAULT_CHANNEL: &str = "conda-forge";
+
+/// The files a [`CondaExport`] can be rendered as
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CondaFormat {
+    /// A conda `environment.yml`
+    Conda,
+
+    /// A `pixi.toml` manifest
+    Pixi,
+}
+
+impl CondaFormat {
+    pub const ALL: [Self; 2] = [Self::Conda, Self::Pixi];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Conda => "conda",
+            Self::Pixi => "pixi",
+        }
+    }
+
+    /// The conventional name of the file
+    #[must_use]
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Self::Conda => "environment.yml",
+            Self::Pixi => "pixi.toml",
+        }
+    }
+}
+
+impl std::fmt::Display for CondaFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for CondaFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|f| f.name() == s).ok_or_else(|| {
+            format!("unknown export format '{s}', expected conda or pixi")
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CondaConfig {
+    /// Channels to install packages from, highest priority first
+    pub channels: Vec<String>,
+
+    /// The conda package providing each zpack package, or `None` if the
+    /// package is not available from the channels
+    pub packages: BTreeMap<String, Option<String>>,
+}
+
+impl Default for CondaConfig {
+    fn default() -> Self {
+        Self {
+            channels: vec![DEFAULT_CHANNEL.to_string()],
+            packages: BTreeMap::new(),
+        }
+    }
+}
+
+impl CondaConfig {
+    /// The conda package providing the zpack package `name`, if any.
+    #[must_use]
+    pub fn conda_name(&self, name: &str) -> Option<String> {
+        self.packages.get(name).map_or_else(
+            || Some(name.strip_prefix("py-").unwrap_or(name).to_string()),
+            Clone::clone,
+        )
+    }
+}
+
+/// The conda subdirectory for `platform`, such as `linux-64`, if conda
+/// supports it.
+#[must_use]
+pub fn subdir(platform: &Platform) -> Option<&'static str> {
+    Some(match (platform.os.as_str(), platform.arch.as_str()) {
+        ("linux", "x86_64") => "linux-64",
+        ("linux", "aarch64") => "linux-aarch64",
+        ("linux", "powerpc64le" | "ppc64le") => "linux-ppc64le",
+        ("macos", "x86_64") => "osx-64",
+        ("macos", "aarch64") => "osx-arm64",
+        ("windows", "x86_64") => "win-64",
+        ("windows", "aarch64") => "win-arm64",
+        _ => return None,
+    })
+}
+
+/// A conda package pinned to a concretized version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CondaPackage {
+    pub name: String,
+    pub version: Version,
+
+    /// The zpack package it replaces
+    pub package: String,
+}
+
+/// The packages of a concretized environment which conda can install
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CondaExport {
+    /// The name of the environment
+    pub name: String,
+    pub channels: Vec<String>,
+
+    /// Sorted by name
+    pub packages: Vec<CondaPackage>,
+
+    /// zpack packages which are not exported, and so are left to zpack
+    pub skipped: Vec<String>,
+}
+
+impl CondaExport {
+    /// Select the packages of `spec` available from conda.
+    ///
+    /// A package concretized more than once (see
+    /// [`crate::package::outline::NODE_TAG_SEPARATOR`]) is exported once, at
+    /// the first of its versions.
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>,
+        spec: &ConcreteSpec,
+        config: &CondaConfig,
+    ) -> Self {
+        let mut packages = BTreeMap::<String, CondaPackage>::new();
+        let mut skipped = Vec::new();
+
+        for package in spec.packages.values() {
+            let conda = package
+                .version
+                .clone()
+                .zip(config.conda_name(package.base_name()));
+
+            let Some((version, conda)) = conda else {
+                skipped.push(package.name.clone());
+                continue;
+            };
+
+            if let Some(existing) = packages.get(&conda) {
+                if existing.version != version {
+                    tracing::warn!(
+                        "conda can only install one version of '{conda}'; \
+                         exporting {} instead of {version}",
+                        existing.version
+                    );
+                }
+
+                continue;
+            }
+
+            packages.insert(
+                conda.clone(),
+                CondaPackage {
+                    name: conda,
+                    version,
+                    package: package.name.clone(),
+                },
+            );
+        }
+
+        Self {
+            name: name.into(),
+            channels: config.channels.clone(),
+            packages: packages.into_values().collect(),
+            skipped,
+        }
+    }
+
+    /// Render the environment in `format`.
+    ///
+    /// * `platform`: The platform the environment is for, used by pixi
+    #[must_use]
+    pub fn render(&self, format: CondaFormat, platform: &Platform) -> String {
+        let mut res = String::new();
+
+        // Writing to a String cannot fail
+        let _ = match format {
+            CondaFormat::Conda => self.write_conda(&mut res),
+            CondaFormat::Pixi => self.write_pixi(&mut res, platform),
+        };
+
+        res
+    }
+
+    /// Comment lines listing the packages which are not exported
+    fn write_skipped(&self, f: &mut String) -> std::fmt::Result {
+        writeln!(f, "# Exported by zpack.")?;
+
+        if !self.skipped.is_empty() {
+            writeln!(f, "# Not exported, so left to zpack:")?;
+
+            for name in &self.skipped {
+                writeln!(f, "#   {name}")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_conda(&self, f: &mut String) -> std::fmt::Result {
+        self.write_skipped(f)?;
+
+        writeln!(f, "name: {}", quoted(&self.name))?;
+        writeln!(f, "channels:")?;
+
+        for channel in &self.channels {
+            writeln!(f, "  - {}", quoted(channel))?;
+        }
+
+        writeln!(f, "dependencies:")?;
+
+        for package in &self.packages {
+            writeln!(f, "  - {}=={}", package.name, package.version)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_pixi(
+        &self,
+        f: &mut String,
+        platform: &Platform,
+    ) -> std::fmt::Result {
+        self.write_skipped(f)?;
+
+        let channels = self
+            .channels
+            .iter()
+            .map(|c| quoted(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        writeln!(f, "[workspace]")?;
+        writeln!(f, "name = {}", quoted(&self.name))?;
+        writeln!(f, "channels = [{channels}]")?;
+
+        let platforms = subdir(platform).map_or_else(
+            || {
+                tracing::warn!(
+                    "conda does not support the platform {platform}"
+                );
+                String::new()
+            },
+            quoted,
+        );
+
+        writeln!(f, "platforms = [{platforms}]")?;
+
+        writeln!(f)?;
+        writeln!(f, "[dependencies]")?;
+
+        for package in &self.packages {
+            writeln!(
+                f,
+                "{} = {}",
+                toml_key(&package.name),
+                quoted(&format!("=={}", package.version))
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A double-quoted string, valid in both YAML and TOML
+fn quoted(s: &str) -> String {
+    let mut res = String::from('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(res, "\\u{:04X}", u32::from(c));
+            }
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+/// A TOML key, quoted unless it is a valid bare key
+fn toml_key(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        s.to_string()
+    } else {
+        quoted(s)
+    }
+}
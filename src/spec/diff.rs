@@ -0,0 +1,236 @@
+//! Structured differences between two [`ConcreteSpec`]s.
+//!
+//! This is mostly useful for answering "why did this rebuild?": diffing the
+//! previous concretization against the new one shows exactly which versions,
+//! options and dependencies changed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anstyle::{AnsiColor, Style};
+use serde::Serialize;
+
+use crate::{
+    package::version::Version,
+    spec::{ConcretePackage, ConcreteSpec, SpecOptionValue},
+};
+
+/// A single changed value
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change<T> {
+    Added { new: T },
+    Removed { old: T },
+    Changed { old: T, new: T },
+}
+
+/// Differences between two concretizations of the same package
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PackageDiff {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<Change<Version>>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, Change<SpecOptionValue>>,
+
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub added_dependencies: BTreeSet<String>,
+
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub removed_dependencies: BTreeSet<String>,
+}
+
+/// Differences between two concrete specs
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SpecDiff {
+    pub added: Vec<ConcretePackage>,
+    pub removed: Vec<ConcretePackage>,
+    pub changed: Vec<PackageDiff>,
+}
+
+fn diff_value<T: Clone + PartialEq>(
+    old: Option<&T>,
+    new: Option<&T>,
+) -> Option<Change<T>> {
+    match (old, new) {
+        (None, None) => None,
+        (None, Some(new)) => Some(Change::Added { new: new.clone() }),
+        (Some(old), None) => Some(Change::Removed { old: old.clone() }),
+        (Some(old), Some(new)) => (old != new)
+            .then(|| Change::Changed { old: old.clone(), new: new.clone() }),
+    }
+}
+
+impl PackageDiff {
+    /// Compare two concretizations of the same package.
+    #[must_use]
+    pub fn new(old: &ConcretePackage, new: &ConcretePackage) -> Self {
+        let version = diff_value(old.version.as_ref(), new.version.as_ref());
+
+        let options = old
+            .options
+            .keys()
+            .chain(new.options.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|name| {
+                diff_value(old.options.get(name), new.options.get(name))
+                    .map(|change| (name.clone(), change))
+            })
+            .collect();
+
+        Self {
+            name: new.name.clone(),
+            version,
+            options,
+            added_dependencies: new
+                .dependencies
+                .difference(&old.dependencies)
+                .cloned()
+                .collect(),
+            removed_dependencies: old
+                .dependencies
+                .difference(&new.dependencies)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.options.is_empty()
+            && self.added_dependencies.is_empty()
+            && self.removed_dependencies.is_empty()
+    }
+}
+
+impl SpecDiff {
+    /// Compute the difference between `old` and `new`.
+    #[must_use]
+    pub fn new(old: &ConcreteSpec, new: &ConcreteSpec) -> Self {
+        let mut res = Self::default();
+
+        for (name, package) in &new.packages {
+            match old.packages.get(name) {
+                Some(prev) => {
+                    let diff = PackageDiff::new(prev, package);
+
+                    if !diff.is_empty() {
+                        res.changed.push(diff);
+                    }
+                }
+                None => res.added.push(package.clone()),
+            }
+        }
+
+        res.removed.extend(
+            old.packages
+                .iter()
+                .filter(|(name, _)| !new.packages.contains_key(*name))
+                .map(|(_, package)| package.clone()),
+        );
+
+        res
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+    }
+
+    /// Render the diff in a human-readable, line-based format.
+    ///
+    /// * `color`: Whether to emit ANSI escape codes
+    #[must_use]
+    pub fn render(&self, color: bool) -> String {
+        use std::fmt::Write;
+
+        let paint = |style: Style, txt: &str| {
+            if color {
+                format!("{}{txt}{}", style.render(), style.render_reset())
+            } else {
+                txt.to_string()
+            }
+        };
+
+        let add = AnsiColor::Green.on_default();
+        let del = AnsiColor::Red.on_default();
+        let header = AnsiColor::BrightBlue.on_default().bold();
+
+        let mut out = String::new();
+
+        for package in &self.removed {
+            let _ = writeln!(out, "{}", paint(del, &format!("- {package}")));
+        }
+
+        for package in &self.added {
+            let _ = writeln!(out, "{}", paint(add, &format!("+ {package}")));
+        }
+
+        for diff in &self.changed {
+            let _ =
+                writeln!(out, "{}", paint(header, &format!("~ {}", diff.name)));
+
+            let mut change = |what: &str, c: &Change<String>| {
+                let (old, new) = match c {
+                    Change::Added { new } => (None, Some(new)),
+                    Change::Removed { old } => (Some(old), None),
+                    Change::Changed { old, new } => (Some(old), Some(new)),
+                };
+
+                if let Some(old) = old {
+                    let line = format!("    - {what}{old}");
+                    let _ = writeln!(out, "{}", paint(del, &line));
+                }
+
+                if let Some(new) = new {
+                    let line = format!("    + {what}{new}");
+                    let _ = writeln!(out, "{}", paint(add, &line));
+                }
+            };
+
+            if let Some(version) = &diff.version {
+                change("@", &version.map_ref(ToString::to_string));
+            }
+
+            for (name, value) in &diff.options {
+                change(
+                    &format!("{name}="),
+                    &value.map_ref(ToString::to_string),
+                );
+            }
+
+            for dep in &diff.removed_dependencies {
+                change("^", &Change::Removed { old: dep.clone() });
+            }
+
+            for dep in &diff.added_dependencies {
+                change("^", &Change::Added { new: dep.clone() });
+            }
+        }
+
+        out
+    }
+}
+
+impl<T> Change<T> {
+    fn map_ref<U>(&self, f: impl Fn(&T) -> U) -> Change<U> {
+        match self {
+            Self::Added { new } => Change::Added { new: f(new) },
+            Self::Removed { old } => Change::Removed { old: f(old) },
+            Self::Changed { old, new } => {
+                Change::Changed { old: f(old), new: f(new) }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SpecDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(false))
+    }
+}
@@ -0,0 +1,431 @@
+//! A stable, versioned representation of concrete specs for other tools.
+//!
+//! [`ConcreteSpec`] serializes the solver's internal data model, which
+//! changes as zpack does. A [`SolveDocument`] is a separate schema intended
+//! for language bindings and tools which read solve results, such as Julia
+//! or R packages, written as JSON or [MessagePack](https://msgpack.org):
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "specs": [
+//!     {
+//!       "roots": ["hpl"],
+//!       "packages": [
+//!         {
+//!           "name": "hpl",
+//!           "namespace": "builtin",
+//!           "version": "2.3",
+//!           "hash": "5c1mj2c3...",
+//!           "options": { "static": { "type": "bool", "value": true } },
+//!           "dependencies": ["openblas"],
+//!           "source": { "url": "https://...", "checksum": "sha256:..." },
+//!           "license": "BSD-3-Clause",
+//!           "flags": { "cflags": ["-O3"] }
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Keys are `snake_case` and packages are sorted by name. Every key is always
+//! present, with `null` for anything a package does not have. Option values
+//! are tagged with their type, one of `bool`, `int`, `float`, `str` or
+//! `version`, and versions are written as strings.
+//!
+//! [`SCHEMA_VERSION`] is incremented whenever a key is removed, renamed or
+//! changes meaning. New keys may be added without incrementing it, so readers
+//! should ignore keys they do not recognise.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::spec::{
+    SpecOptionValue,
+    concrete::{ConcretePackage, ConcreteSpec},
+};
+
+/// The version of the schema written by [`SolveDocument::new`]
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The formats a [`SolveDocument`] can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DocumentFormat {
+    Json,
+    Msgpack,
+}
+
+impl DocumentFormat {
+    pub const ALL: [Self; 2] = [Self::Json, Self::Msgpack];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Msgpack => "msgpack",
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for DocumentFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|f| f.name() == s).ok_or_else(|| {
+            format!("unknown format '{s}', expected json or msgpack")
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+    Json(serde_json::Error),
+    MsgpackEncode(rmp_serde::encode::Error),
+    MsgpackDecode(rmp_serde::decode::Error),
+
+    /// The document was written with a schema this version cannot read
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "invalid json: {e}"),
+            Self::MsgpackEncode(e) => write!(f, "cannot encode msgpack: {e}"),
+            Self::MsgpackDecode(e) => write!(f, "invalid msgpack: {e}"),
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported schema version {v}, expected {SCHEMA_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+/// Concrete specs in the stable schema. See the [module documentation](self)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SolveDocument {
+    pub schema_version: u32,
+    pub specs: Vec<SpecDocument>,
+}
+
+/// A single concrete spec
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpecDocument {
+    pub roots: Vec<String>,
+
+    /// Sorted by name
+    pub packages: Vec<PackageDocument>,
+}
+
+/// A single concrete package
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PackageDocument {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub version: Option<String>,
+
+    /// See [`ConcretePackage::dag_hash`]
+    pub hash: String,
+
+    pub options: BTreeMap<String, OptionDocument>,
+    pub dependencies: Vec<String>,
+    pub source: Option<SourceDocument>,
+    pub license: Option<String>,
+
+    /// Flags by kind, such as `cflags` or `ldlibs`
+    pub flags: BTreeMap<String, Vec<String>>,
+}
+
+/// The value of an option, tagged with its type
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum OptionDocument {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Version(String),
+}
+
+/// Where a package's source archive is fetched from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceDocument {
+    pub url: String,
+
+    /// The checksum of the archive, as `algorithm:hex`
+    pub checksum: String,
+}
+
+impl From<&SpecOptionValue> for OptionDocument {
+    fn from(value: &SpecOptionValue) -> Self {
+        match value {
+            SpecOptionValue::Bool(v) => Self::Bool(*v),
+            SpecOptionValue::Int(v) => Self::Int(*v),
+            SpecOptionValue::Float(v) => Self::Float(*v),
+            SpecOptionValue::Str(v) => Self::Str(v.clone()),
+            SpecOptionValue::Version(v) => Self::Version(v.to_string()),
+        }
+    }
+}
+
+impl PackageDocument {
+    fn new(package: &ConcretePackage, hash: String) -> Self {
+        Self {
+            name: package.name.clone(),
+            namespace: package.namespace.clone(),
+            version: package.version.as_ref().map(ToString::to_string),
+            hash,
+            options: package
+                .options
+                .iter()
+                .map(|(name, value)| (name.clone(), value.into()))
+                .collect(),
+            dependencies: package.dependencies.iter().cloned().collect(),
+            source: package.source.as_ref().map(|source| SourceDocument {
+                url: source.url.clone(),
+                checksum: source.checksum.to_string(),
+            }),
+            license: package.license.clone(),
+            flags: package
+                .flags
+                .iter()
+                .map(|(kind, flags)| {
+                    (kind.as_str().to_lowercase(), flags.clone())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SpecDocument {
+    #[must_use]
+    pub fn new(spec: &ConcreteSpec) -> Self {
+        let mut hashes = spec.dag_hashes();
+
+        Self {
+            roots: spec.roots.clone(),
+            packages: spec
+                .packages
+                .values()
+                .map(|package| {
+                    let hash = hashes
+                        .remove(package.name.as_str())
+                        .unwrap_or_default();
+
+                    PackageDocument::new(package, hash)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SolveDocument {
+    /// The document for `specs`, at the current [`SCHEMA_VERSION`].
+    #[must_use]
+    pub fn new(specs: &[ConcreteSpec]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            specs: specs.iter().map(SpecDocument::new).collect(),
+        }
+    }
+
+    /// Write the document in `format`. JSON is pretty printed.
+    ///
+    /// # Errors
+    /// Errors if the document cannot be serialized.
+    pub fn to_bytes(
+        &self,
+        format: DocumentFormat,
+    ) -> Result<Vec<u8>, DocumentError> {
+        match format {
+            DocumentFormat::Json => {
+                serde_json::to_vec_pretty(self).map_err(DocumentError::Json)
+            }
+            DocumentFormat::Msgpack => rmp_serde::to_vec_named(self)
+                .map_err(DocumentError::MsgpackEncode),
+        }
+    }
+
+    /// Read a document previously written by [`Self::to_bytes`].
+    ///
+    /// # Errors
+    /// Errors if `bytes` is not a valid document in `format` or was written
+    /// with a different [`SCHEMA_VERSION`].
+    pub fn from_bytes(
+        bytes: &[u8],
+        format: DocumentFormat,
+    ) -> Result<Self, DocumentError> {
+        let document: Self = match format {
+            DocumentFormat::Json => {
+                serde_json::from_slice(bytes).map_err(DocumentError::Json)?
+            }
+            DocumentFormat::Msgpack => rmp_serde::from_slice(bytes)
+                .map_err(DocumentError::MsgpackDecode)?,
+        };
+
+        if document.schema_version != SCHEMA_VERSION {
+            return Err(DocumentError::UnsupportedVersion(
+                document.schema_version,
+            ));
+        }
+
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{
+        package::{flags::FlagKind, source::Source, version::Version},
+        util::digest::Checksum,
+    };
+
+    fn package(name: &str, dependencies: &[&str]) -> ConcretePackage {
+        ConcretePackage {
+            name: name.to_string(),
+            namespace: Some("builtin".to_string()),
+            version: Some(Version::new("2.3.1").unwrap()),
+            options: BTreeMap::from([
+                ("static".to_string(), SpecOptionValue::Bool(true)),
+                ("threads".to_string(), SpecOptionValue::Int(-4)),
+                ("ratio".to_string(), SpecOptionValue::Float(0.25)),
+                ("blas".to_string(), SpecOptionValue::Str("open blas".into())),
+                (
+                    "cmake".to_string(),
+                    SpecOptionValue::Version(Version::new("3.>").unwrap()),
+                ),
+            ]),
+            dependencies: dependencies
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            source: Some(Source {
+                url: format!("https://example.com/{name}.tar.gz"),
+                checksum: format!("sha256:{}", "ab".repeat(32))
+                    .parse::<Checksum>()
+                    .unwrap(),
+                version: None,
+            }),
+            license: Some("BSD-3-Clause".to_string()),
+            patches: Vec::new(),
+            flags: BTreeMap::from([(
+                FlagKind::CFlags,
+                vec!["-O3".to_string(), "-g".to_string()],
+            )]),
+            tests: Vec::new(),
+            provenance: BTreeMap::new(),
+        }
+    }
+
+    fn spec() -> ConcreteSpec {
+        let mut bare = package("zlib", &[]);
+        bare.namespace = None;
+        bare.version = None;
+        bare.options.clear();
+        bare.source = None;
+        bare.license = None;
+        bare.flags.clear();
+
+        ConcreteSpec {
+            roots: vec!["hpl".to_string()],
+            packages: [
+                package("hpl", &["openblas", "zlib"]),
+                package("openblas", &["zlib"]),
+                bare,
+            ]
+            .into_iter()
+            .map(|p| (p.name.clone(), p))
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trips_in_every_format() {
+        let document = SolveDocument::new(&[spec(), ConcreteSpec::default()]);
+
+        for format in DocumentFormat::ALL {
+            let bytes = document.to_bytes(format).unwrap();
+            let read = SolveDocument::from_bytes(&bytes, format).unwrap();
+
+            assert_eq!(read, document, "{format} did not round trip");
+            assert_eq!(read.to_bytes(format).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn writes_every_key() {
+        let document = SolveDocument::new(&[spec()]);
+        let bytes = document.to_bytes(DocumentFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        let zlib = &value["specs"][0]["packages"][2];
+        assert_eq!(zlib["name"], "zlib");
+
+        for key in ["namespace", "version", "source", "license"] {
+            assert!(zlib[key].is_null(), "{key} should be null");
+        }
+
+        let hpl = &value["specs"][0]["packages"][0];
+        assert_eq!(hpl["version"], "2.3.1");
+        assert_eq!(hpl["options"]["cmake"]["type"], "version");
+        assert_eq!(hpl["options"]["cmake"]["value"], "3.>");
+        assert_eq!(hpl["options"]["threads"]["value"], -4);
+        assert_eq!(hpl["flags"]["cflags"][0], "-O3");
+        assert_eq!(
+            hpl["hash"],
+            spec().packages["hpl"].dag_hash(&spec()).as_str()
+        );
+    }
+
+    #[test]
+    fn packages_are_sorted_by_name() {
+        let document = SpecDocument::new(&spec());
+        let names = document
+            .packages
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["hpl", "openblas", "zlib"]);
+    }
+
+    #[test]
+    fn rejects_other_schema_versions() {
+        let mut document = SolveDocument::new(&[spec()]);
+        document.schema_version = SCHEMA_VERSION + 1;
+
+        for format in DocumentFormat::ALL {
+            let bytes = document.to_bytes(format).unwrap();
+
+            assert!(matches!(
+                SolveDocument::from_bytes(&bytes, format),
+                Err(DocumentError::UnsupportedVersion(v)) if v == SCHEMA_VERSION + 1
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_documents() {
+        assert!(matches!(
+            SolveDocument::from_bytes(b"{\"specs\": []}", DocumentFormat::Json),
+            Err(DocumentError::Json(_))
+        ));
+        assert!(matches!(
+            SolveDocument::from_bytes(b"\xc1", DocumentFormat::Msgpack),
+            Err(DocumentError::MsgpackDecode(_))
+        ));
+    }
+}
@@ -0,0 +1,154 @@
+//! Evaluating constraints against a concrete spec.
+//!
+//! Once solved, every constraint of an active package has a definite value,
+//! which is useful when explaining a solution. Packages which are not in the
+//! spec are inactive, so `Depends` on them is false and their options have no
+//! value. Constraints which cannot be evaluated from the spec alone, such as
+//! platform conditions and objectives, evaluate to `None`.
+
+use std::cmp::Ordering;
+
+use crate::{
+    constraint::{ArithOp, CmpType, Constraint, ConstraintUtils},
+    spec::{ConcreteSpec, SpecOptionValue, concrete::VERSION_OPTION},
+};
+
+/// Whether `lhs op rhs` holds, or `None` if the values cannot be compared.
+/// Versions are compared as in the solver (see [`Version::satisfies`]).
+///
+/// [`Version::satisfies`]: crate::package::version::Version::satisfies
+pub(crate) fn compare(
+    lhs: &SpecOptionValue,
+    op: CmpType,
+    rhs: &SpecOptionValue,
+) -> Option<bool> {
+    let ord = match (lhs, rhs) {
+        (SpecOptionValue::Version(l), SpecOptionValue::Version(r)) => {
+            return Some(l.satisfies(op, r));
+        }
+        (SpecOptionValue::Bool(l), SpecOptionValue::Bool(r)) => l.cmp(r),
+        (SpecOptionValue::Int(l), SpecOptionValue::Int(r)) => l.cmp(r),
+        (SpecOptionValue::Float(l), SpecOptionValue::Float(r)) => {
+            l.partial_cmp(r)?
+        }
+        (SpecOptionValue::Str(l), SpecOptionValue::Str(r)) => l.cmp(r),
+        _ => return None,
+    };
+
+    Some(match op {
+        CmpType::Less => ord == Ordering::Less,
+        CmpType::LessOrEqual => ord != Ordering::Greater,
+        CmpType::NotEqual => ord != Ordering::Equal,
+        CmpType::Equal => ord == Ordering::Equal,
+        CmpType::GreaterOrEqual => ord != Ordering::Less,
+        CmpType::Greater => ord == Ordering::Greater,
+    })
+}
+
+/// `lhs op rhs`, or `None` if the values are not numbers of the same type or
+/// the result overflows.
+pub(crate) fn arith(
+    lhs: &SpecOptionValue,
+    op: ArithOp,
+    rhs: &SpecOptionValue,
+) -> Option<SpecOptionValue> {
+    match (lhs, rhs) {
+        (SpecOptionValue::Int(l), SpecOptionValue::Int(r)) => match op {
+            ArithOp::Add => l.checked_add(*r),
+            ArithOp::Sub => l.checked_sub(*r),
+            ArithOp::Mul => l.checked_mul(*r),
+        }
+        .map(SpecOptionValue::Int),
+        (SpecOptionValue::Float(l), SpecOptionValue::Float(r)) => {
+            Some(SpecOptionValue::Float(match op {
+                ArithOp::Add => l + r,
+                ArithOp::Sub => l - r,
+                ArithOp::Mul => l * r,
+            }))
+        }
+        _ => None,
+    }
+}
+
+impl ConcreteSpec {
+    /// The value of `constraint` in this spec, or `None` if it cannot be
+    /// determined.
+    #[must_use]
+    pub fn eval(&self, constraint: &Constraint) -> Option<SpecOptionValue> {
+        match constraint {
+            Constraint::Value(v) => Some(v.value.clone()),
+
+            Constraint::SpecOption(opt) => {
+                let package = self.get(&opt.package_name)?;
+
+                if opt.option_name == VERSION_OPTION {
+                    package.version.clone().map(SpecOptionValue::Version)
+                } else {
+                    package.options.get(&opt.option_name).cloned()
+                }
+            }
+
+            Constraint::Matches(matches) => {
+                let value = self.eval(&matches.option.clone().into())?;
+
+                let SpecOptionValue::Str(value) = value else { return None };
+
+                matches.is_match(&value).map(SpecOptionValue::Bool)
+            }
+
+            Constraint::Depends(dep) => Some(SpecOptionValue::Bool(
+                dep.extract_dependencies()
+                    .iter()
+                    .all(|name| self.packages.contains_key(name)),
+            )),
+
+            Constraint::Arith(a) => {
+                let lhs = self.eval(&a.lhs)?;
+                let rhs = self.eval(&a.rhs)?;
+
+                arith(&lhs, a.op, &rhs)
+            }
+
+            Constraint::Cmp(cmp) => {
+                let lhs = self.eval(&cmp.lhs)?;
+                let rhs = self.eval(&cmp.rhs)?;
+
+                compare(&lhs, cmp.op, &rhs).map(SpecOptionValue::Bool)
+            }
+
+            Constraint::IfThen(if_then) => {
+                if self.holds(&if_then.cond)? {
+                    self.eval(&if_then.then)
+                } else {
+                    Some(SpecOptionValue::Bool(true))
+                }
+            }
+
+            Constraint::NumOf(num_of) => {
+                let mut count = 0;
+
+                for c in &num_of.of {
+                    count += i64::from(self.holds(c)?);
+                }
+
+                Some(SpecOptionValue::Int(count))
+            }
+
+            Constraint::Maximize(_)
+            | Constraint::Minimize(_)
+            | Constraint::Prefer(_)
+            | Constraint::WhenPlatform(_)
+            | Constraint::Custom(_) => None,
+        }
+    }
+
+    /// Whether the boolean `constraint` holds in this spec, or `None` if it
+    /// cannot be determined or is not boolean.
+    #[must_use]
+    pub fn holds(&self, constraint: &Constraint) -> Option<bool> {
+        match self.eval(constraint)? {
+            SpecOptionValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+}
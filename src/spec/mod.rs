@@ -1,5 +1,17 @@
-// pub mod parse;
-
+pub mod complete;
+pub mod concrete;
+pub mod conda;
+pub mod diff;
+pub mod document;
+pub mod eval;
+pub mod parse;
+pub mod platform;
+pub mod provenance;
+pub mod query;
+pub mod sbom;
+pub mod spec_file;
 mod spec_option;
+pub mod target;
 
+pub use concrete::{ConcretePackage, ConcreteSpec};
 pub use spec_option::{SpecOption, SpecOptionType, SpecOptionValue};
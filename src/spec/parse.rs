@@ -0,0 +1,607 @@
+//! Parsing of spec strings given on the command line.
+//!
+//! A spec string names a package, optionally followed by a version and any
+//! number of options, separated by whitespace:
+//!
+//! ```text
+//! hpl@2.3 +static ~shared ++openmp blas_impl=openblas
+//! ```
+//!
+//! - `@version` requires a version of the package
+//! - `@min:max`, `@min:` and `@:max` describe a range of versions (see
+//!   [`VersionRange`]). A range which is also a version with an epoch, such
+//!   as `@1:2`, is read as the version. `~` and `^` may directly follow a
+//!   version, as in `@2.3~shared`, but `+` only ends a range with no maximum,
+//!   as in `@5:+cuda`, since it may also separate the parts of a version.
+//!   Ranges can only be used to query installed packages (see
+//!   [`crate::spec::query`]), not in solves
+//! - `+name` and `~name` enable and disable a boolean option
+//! - `++name` and `~~name` do the same, and also apply the option to every
+//!   other package which has an option with that name
+//! - `name=value` sets an option to a bool, integer, float or string, in that
+//!   order of preference
+//! - `name="value"` sets an option to a string, which may contain whitespace
+//!   and the escapes Rust uses when debug printing a string
+//! - `name=min:max` constrains a numeric option to lie between `min` and
+//!   `max`, inclusive (see [`InRange`]), rather than setting it
+//! - `target=name` sets the microarchitecture target (see
+//!   [`crate::spec::target`]). It always applies to every package
+//! - `^name` begins the spec of a package the package depends on, directly
+//!   or indirectly, followed by its own version and options, as in
+//!   `hpl ^openblas@0.3 threads=openmp`. Like ranges, dependency specs can
+//!   only be used to query concrete packages (see [`crate::spec::query`])
+//!
+//! Errors are collected rather than returned at the first problem, and are
+//! reported with [`ParserErrorWrapper`] so each one points at the offending
+//! part of the string.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    ops::Range,
+};
+
+use crate::{
+    constraint::{CmpType, ConstraintUtils, InRange},
+    package::{
+        outline::PackageOutline,
+        version::{Version, VersionRange},
+    },
+    spec::{
+        SpecOptionValue,
+        concrete::VERSION_OPTION,
+        eval::compare,
+        target::{TARGET_OPTION, Target},
+    },
+    util::{
+        error::ParserErrorWrapper,
+        num::{Number, parse_num},
+        parse::{Cursor, error},
+        suggest,
+    },
+};
+
+/// A spec string error, ready to be rendered with
+/// [`ParserErrorWrapper::build`].
+pub type SpecParseError<'a> = ParserErrorWrapper<'a, ariadne::Source<&'a str>>;
+
+/// An option set by a spec string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionRequest {
+    pub name: String,
+    pub value: SpecOptionValue,
+
+    /// Whether to also set the option on every other package which has it
+    pub propagate: bool,
+
+    span: Range<usize>,
+}
+
+/// An option constrained to a range by a spec string, as in `threads=1:64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeRequest {
+    pub name: String,
+    pub min: SpecOptionValue,
+    pub max: SpecOptionValue,
+
+    span: Range<usize>,
+}
+
+/// A parsed, but not yet validated, spec string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecRequest {
+    /// The package name, possibly qualified with a repository namespace
+    pub name: String,
+    pub version: Option<Version>,
+
+    /// A range of versions, given instead of a version
+    pub version_range: Option<VersionRange>,
+    pub options: Vec<OptionRequest>,
+    pub ranges: Vec<RangeRequest>,
+
+    /// Specs which some dependency of the package must match, given with `^`
+    pub dependencies: Vec<Self>,
+
+    text: String,
+    name_span: Range<usize>,
+    version_span: Range<usize>,
+}
+
+pub(crate) const fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Parse the value of a `name=value` option.
+fn parse_value(txt: &str) -> SpecOptionValue {
+    match txt {
+        "true" => SpecOptionValue::Bool(true),
+        "false" => SpecOptionValue::Bool(false),
+        _ => parse_num(txt).map_or_else(
+            |_| SpecOptionValue::Str(txt.to_string()),
+            SpecOptionValue::from,
+        ),
+    }
+}
+
+/// Parse the bounds of a `name=min:max` range, or `None` if `txt` is not two
+/// numbers separated by `:`. Integer bounds are widened to floats if the
+/// other bound is a float.
+fn parse_range(txt: &str) -> Option<(SpecOptionValue, SpecOptionValue)> {
+    let (min, max) = txt.split_once(':')?;
+    let (min, max) = (parse_num(min).ok()?, parse_num(max).ok()?);
+
+    Some(match (min, max) {
+        (Number::Integer(min), Number::Integer(max)) => {
+            (min.into(), max.into())
+        }
+        (min, max) => (min.as_f64().into(), max.as_f64().into()),
+    })
+}
+
+/// Whether the string value `txt` must be quoted to be parsed back as itself
+fn needs_quotes(txt: &str) -> bool {
+    txt.is_empty()
+        || txt.starts_with('"')
+        || txt.contains(char::is_whitespace)
+        || parse_range(txt).is_some()
+        || parse_value(txt) != SpecOptionValue::Str(txt.to_string())
+}
+
+/// Every option name which can be set on `package`.
+///
+/// An option can only be set if some constraint refers to it, since otherwise
+/// it has no solver variable.
+pub(crate) fn known_options<'a>(
+    outlines: &'a [PackageOutline],
+    package: &str,
+) -> BTreeSet<&'a str> {
+    // Every package has a target
+    let mut res = BTreeSet::from([TARGET_OPTION]);
+
+    for outline in outlines {
+        for constraint in outline.all_constraints() {
+            res.extend(
+                constraint
+                    .extract_spec_options()
+                    .into_iter()
+                    .filter(|(pkg, _, _)| *pkg == package)
+                    .map(|(_, opt, _)| opt),
+            );
+        }
+
+        if outline.name == package {
+            res.extend(outline.set_defaults.keys().map(String::as_str));
+            res.extend(outline.set_options.keys().map(String::as_str));
+        }
+    }
+
+    res
+}
+
+/// The state of the spec string parser, for the package being parsed
+#[derive(Default)]
+struct State {
+    version: Option<Version>,
+    version_range: Option<VersionRange>,
+    version_span: Range<usize>,
+    options: Vec<OptionRequest>,
+    ranges: Vec<RangeRequest>,
+}
+
+type Parser<'a> = Cursor<'a, State>;
+
+impl Parser<'_> {
+    /// `@version`, or a range of versions
+    fn version(&mut self, start: usize) {
+        self.pos += 1;
+
+        let (mut txt, span) =
+            self.take_while(|c| !c.is_whitespace() && !matches!(c, '~' | '^'));
+
+        // An option may directly follow a range with no maximum, as in
+        // `@5:+cuda`, since '+' cannot begin a version
+        if let Some(i) = txt.find(":+") {
+            txt.truncate(i + 1);
+            self.pos = span.start + txt.chars().count();
+        }
+
+        let span = start..self.pos;
+
+        if txt.is_empty() {
+            self.push(span, "expected a version after '@'");
+        } else if self.state.version.is_some()
+            || self.state.version_range.is_some()
+        {
+            self.push(span, "version given more than once");
+        } else {
+            match Version::new(&txt) {
+                Ok(v) => {
+                    self.state.version = Some(v);
+                    self.state.version_span = span;
+                }
+                Err(e) => match VersionRange::parse(&txt) {
+                    Some(range) => {
+                        self.state.version_range = Some(range);
+                        self.state.version_span = span;
+                    }
+                    None => self.push(span, format!("invalid version: {e:?}")),
+                },
+            }
+        }
+    }
+
+    /// `+name`, `~name`, `++name` or `~~name`
+    fn flag(&mut self, c: char, start: usize) {
+        self.pos += 1;
+
+        let propagate = self.peek() == Some(c);
+
+        if propagate {
+            self.pos += 1;
+        }
+
+        let (name, span) = self.take_while(is_name_char);
+
+        if name.is_empty() {
+            self.expected(format!("an option name after '{c}'"));
+        } else {
+            self.state.options.push(OptionRequest {
+                name,
+                value: SpecOptionValue::Bool(c == '+'),
+                propagate,
+                span: start..span.end,
+            });
+        }
+    }
+
+    /// `name=value`
+    fn assignment(&mut self, start: usize) {
+        let (name, _) = self.take_while(is_name_char);
+
+        if self.peek() != Some('=') {
+            self.push(
+                start..self.pos,
+                format!(
+                    "expected an option, found '{name}'; each spec must \
+                     contain only one package"
+                ),
+            );
+            return;
+        }
+
+        self.pos += 1;
+
+        let value_start = self.pos;
+
+        // A quoted value is always a string, and may contain whitespace
+        let (value, parsed) = if self.peek() == Some('"') {
+            let Some(value) = self.string() else { return };
+            let parsed = SpecOptionValue::Str(value.clone());
+            (value, Some(parsed))
+        } else {
+            (self.take_while(|c| !c.is_whitespace()).0, None)
+        };
+
+        let span = value_start..self.pos;
+
+        let range = if parsed.is_none() { parse_range(&value) } else { None };
+
+        if span.is_empty() {
+            self.expected(format!("a value for '{name}'"));
+        } else if name == TARGET_OPTION && Target::lookup(&value).is_none() {
+            self.push(
+                span,
+                format!(
+                    "unknown target '{value}'{}",
+                    suggest::did_you_mean(Target::suggest(&value))
+                ),
+            );
+        } else if let Some((min, max)) = range {
+            if compare(&min, CmpType::Greater, &max) == Some(true) {
+                self.push(span, format!("range '{value}' is empty"));
+            } else {
+                self.state.ranges.push(RangeRequest {
+                    name,
+                    min,
+                    max,
+                    span: start..span.end,
+                });
+            }
+        } else {
+            self.state.options.push(OptionRequest {
+                propagate: name == TARGET_OPTION,
+                value: parsed.unwrap_or_else(|| parse_value(&value)),
+                name,
+                span: start..span.end,
+            });
+        }
+    }
+}
+
+impl SpecRequest {
+    /// Parse a spec string.
+    ///
+    /// # Errors
+    /// Errors if `txt` is not a syntactically valid spec. Every problem found
+    /// is reported, not just the first.
+    pub fn parse(txt: &str) -> Result<Self, SpecParseError<'_>> {
+        let mut parser = Parser::new(txt, State::default());
+        let mut root = None::<Self>;
+        let mut dependencies = Vec::new();
+
+        // The package, followed by each dependency spec
+        loop {
+            parser.skip_whitespace();
+
+            let (name, name_span) = parser.take_while(is_name_char);
+
+            if name.is_empty() {
+                parser.expected(if root.is_none() {
+                    "a package name"
+                } else {
+                    "a package name after '^'"
+                });
+            }
+
+            loop {
+                parser.skip_whitespace();
+
+                let start = parser.pos;
+
+                match parser.peek() {
+                    None | Some('^') => break,
+                    Some('@') => parser.version(start),
+                    Some(c @ ('+' | '~')) => parser.flag(c, start),
+                    Some(c) if is_name_char(c) => parser.assignment(start),
+                    Some(c) => {
+                        parser.pos += 1;
+                        parser.push(
+                            start..parser.pos,
+                            format!("unexpected '{c}'"),
+                        );
+                    }
+                }
+            }
+
+            let state = std::mem::take(&mut parser.state);
+            let mut seen = HashSet::new();
+
+            let names = state.options.iter().map(|o| (&o.name, &o.span));
+            let ranges = state.ranges.iter().map(|r| (&r.name, &r.span));
+
+            for (name, span) in names.chain(ranges) {
+                if !seen.insert(name.as_str()) {
+                    parser.push(
+                        span.clone(),
+                        format!("option '{name}' given more than once"),
+                    );
+                }
+            }
+
+            let node = Self {
+                name,
+                version: state.version,
+                version_range: state.version_range,
+                options: state.options,
+                ranges: state.ranges,
+                dependencies: Vec::new(),
+                text: txt.to_string(),
+                name_span,
+                version_span: state.version_span,
+            };
+
+            if root.is_none() {
+                root = Some(node);
+            } else {
+                dependencies.push(node);
+            }
+
+            if !parser.eat("^") {
+                break;
+            }
+        }
+
+        match root {
+            Some(root) if parser.errors.is_empty() => {
+                Ok(Self { dependencies, ..root })
+            }
+            _ => Err(ParserErrorWrapper::new(
+                "spec",
+                ariadne::Source::from(txt),
+                parser.errors,
+            )),
+        }
+    }
+
+    /// Check that the package and every option exist in `outlines`.
+    ///
+    /// * `name`: The package name with any namespace removed
+    ///
+    /// # Errors
+    /// Errors if the package or an option does not exist, with a suggestion
+    /// for the intended name where one is close enough, or if a range of
+    /// versions or a dependency spec is given.
+    pub fn validate<'a>(
+        &'a self,
+        name: &str,
+        outlines: &[PackageOutline],
+    ) -> Result<(), SpecParseError<'a>> {
+        let mut errors = Vec::new();
+
+        for dep in &self.dependencies {
+            errors.push(error(
+                dep.name_span.clone(),
+                "dependency specs ('^') can only be used to query installed \
+                 packages; give each package as a separate spec",
+            ));
+        }
+
+        if outlines.iter().any(|o| o.name == name) {
+            let options = known_options(outlines, name);
+
+            if self.version.is_some() && !options.contains(VERSION_OPTION) {
+                errors.push(error(
+                    self.version_span.clone(),
+                    format!("'{name}' does not have a version"),
+                ));
+            }
+
+            if self.version_range.is_some() {
+                errors.push(error(
+                    self.version_span.clone(),
+                    "version ranges can only be used to query installed \
+                     packages; give a single version or a wildcard, such as \
+                     '@5.>'",
+                ));
+            }
+
+            let requested = self
+                .options
+                .iter()
+                .map(|o| (&o.name, &o.span, o.propagate))
+                .chain(self.ranges.iter().map(|r| (&r.name, &r.span, false)));
+
+            for (option, span, propagate) in requested {
+                let exists = if propagate {
+                    outlines.iter().any(|o| {
+                        known_options(outlines, &o.name)
+                            .contains(option.as_str())
+                    })
+                } else {
+                    options.contains(option.as_str())
+                };
+
+                if !exists {
+                    let suggestion =
+                        suggest::closest(option, options.iter().copied());
+
+                    errors.push(error(
+                        span.clone(),
+                        format!(
+                            "'{name}' has no option '{option}'{}",
+                            suggest::did_you_mean(suggestion)
+                        ),
+                    ));
+                }
+            }
+        } else {
+            let suggestion = suggest::closest(
+                name,
+                outlines.iter().map(|o| o.name.as_str()),
+            );
+
+            errors.push(error(
+                self.name_span.clone(),
+                format!(
+                    "unknown package '{name}'{}",
+                    suggest::did_you_mean(suggestion)
+                ),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ParserErrorWrapper::new(
+                "spec",
+                ariadne::Source::from(self.text.as_str()),
+                errors,
+            ))
+        }
+    }
+
+    /// Apply the version and options to `outlines` as explicit values, and
+    /// ranges as constraints of the package.
+    ///
+    /// * `name`: The package name with any namespace removed
+    pub fn apply(&self, name: &str, outlines: &mut [PackageOutline]) {
+        for opt in self.options.iter().filter(|o| o.propagate) {
+            let targets = outlines
+                .iter()
+                .filter(|o| {
+                    known_options(outlines, &o.name).contains(opt.name.as_str())
+                })
+                .map(|o| o.name.clone())
+                .collect::<HashSet<_>>();
+
+            for outline in
+                outlines.iter_mut().filter(|o| targets.contains(&o.name))
+            {
+                outline.set_options.insert(opt.name.clone(), opt.value.clone());
+                outline.requested_options.insert(opt.name.clone());
+            }
+        }
+
+        let Some(outline) = outlines.iter_mut().find(|o| o.name == name) else {
+            return;
+        };
+
+        if let Some(version) = &self.version {
+            outline.set_options.insert(
+                VERSION_OPTION.to_string(),
+                SpecOptionValue::Version(version.clone()),
+            );
+            outline.requested_options.insert(VERSION_OPTION.to_string());
+        }
+
+        for opt in self.options.iter().filter(|o| !o.propagate) {
+            outline.set_options.insert(opt.name.clone(), opt.value.clone());
+            outline.requested_options.insert(opt.name.clone());
+        }
+
+        for range in &self.ranges {
+            outline.constraints.push(
+                InRange::new(
+                    name,
+                    &range.name,
+                    range.min.clone(),
+                    range.max.clone(),
+                    true,
+                )
+                .into(),
+            );
+        }
+    }
+}
+
+impl std::fmt::Display for SpecRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+
+        if let Some(version) = &self.version {
+            write!(f, "@{version}")?;
+        }
+
+        if let Some(range) = &self.version_range {
+            write!(f, "@{range}")?;
+        }
+
+        for opt in &self.options {
+            let prefix = |c: char| {
+                if opt.propagate { format!("{c}{c}") } else { c.to_string() }
+            };
+
+            match &opt.value {
+                SpecOptionValue::Bool(true) => {
+                    write!(f, " {}{}", prefix('+'), opt.name)?;
+                }
+                SpecOptionValue::Bool(false) => {
+                    write!(f, " {}{}", prefix('~'), opt.name)?;
+                }
+                SpecOptionValue::Str(s) if needs_quotes(s) => {
+                    write!(f, " {}={s:?}", opt.name)?;
+                }
+                value => write!(f, " {}={value}", opt.name)?,
+            }
+        }
+
+        for range in &self.ranges {
+            write!(f, " {}={}:{}", range.name, range.min, range.max)?;
+        }
+
+        for dep in &self.dependencies {
+            write!(f, " ^{dep}")?;
+        }
+
+        Ok(())
+    }
+}
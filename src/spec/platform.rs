@@ -0,0 +1,236 @@
+//! Facts about the platform packages are concretized for.
+//!
+//! Packages refer to these facts with the
+//! [`WhenPlatform`](crate::constraint::WhenPlatform) constraint, for example
+//! to depend on a library only on `aarch64` or to restrict an option to
+//! Linux. The facts are fixed before solving, so the solver never chooses a
+//! platform; it defaults to the host and can be overridden in the
+//! configuration file or with `--platform KEY=VALUE`.
+
+use std::str::FromStr;
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::util::suggest;
+
+/// A single platform fact
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformKey {
+    /// Operating system, such as `linux` or `macos`
+    Os,
+
+    /// Instruction set architecture, such as `x86_64` or `aarch64`
+    Arch,
+
+    /// C standard library, such as `glibc` or `musl`
+    Libc,
+
+    /// Specific processor family, such as `zen4`. Defaults to the
+    /// architecture
+    Microarchitecture,
+}
+
+impl PlatformKey {
+    pub const ALL: [Self; 4] =
+        [Self::Os, Self::Arch, Self::Libc, Self::Microarchitecture];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Os => "os",
+            Self::Arch => "arch",
+            Self::Libc => "libc",
+            Self::Microarchitecture => "microarchitecture",
+        }
+    }
+}
+
+impl std::fmt::Display for PlatformKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PlatformKey {
+    type Err = PlatformError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|key| key.as_str() == s).ok_or_else(|| {
+            PlatformError::UnknownKey {
+                key: s.to_string(),
+                suggestion: suggest::closest(
+                    s,
+                    Self::ALL.iter().map(|k| k.as_str()),
+                )
+                .map(str::to_string),
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlatformError {
+    UnknownKey { key: String, suggestion: Option<String> },
+    MissingValue(String),
+}
+
+impl std::fmt::Display for PlatformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownKey { key, suggestion } => write!(
+                f,
+                "unknown platform fact '{key}'{}",
+                suggest::did_you_mean(suggestion.as_deref())
+            ),
+            Self::MissingValue(txt) => {
+                write!(f, "expected KEY=VALUE for platform fact, found '{txt}'")
+            }
+        }
+    }
+}
+
+/// The platform packages are concretized for
+#[cfg_attr(feature = "python", pyclass(get_all, set_all))]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Platform {
+    pub os: String,
+
+    pub arch: String,
+
+    pub libc: String,
+
+    pub microarchitecture: String,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+impl Platform {
+    /// The platform zpack is running on.
+    ///
+    /// The microarchitecture is not detected and is reported as the generic
+    /// architecture.
+    #[must_use]
+    pub fn host() -> Self {
+        let libc = if cfg!(target_env = "musl") {
+            "musl"
+        } else if cfg!(target_env = "gnu") {
+            "glibc"
+        } else if cfg!(target_env = "msvc") {
+            "msvcrt"
+        } else {
+            "unknown"
+        };
+
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            libc: libc.to_string(),
+            microarchitecture: std::env::consts::ARCH.to_string(),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, key: PlatformKey) -> &str {
+        match key {
+            PlatformKey::Os => &self.os,
+            PlatformKey::Arch => &self.arch,
+            PlatformKey::Libc => &self.libc,
+            PlatformKey::Microarchitecture => &self.microarchitecture,
+        }
+    }
+
+    pub fn set(&mut self, key: PlatformKey, value: String) {
+        match key {
+            PlatformKey::Os => self.os = value,
+            PlatformKey::Arch => self.arch = value,
+            PlatformKey::Libc => self.libc = value,
+            PlatformKey::Microarchitecture => self.microarchitecture = value,
+        }
+    }
+
+    /// Parse a `KEY=VALUE` platform fact, such as `os=linux`.
+    ///
+    /// # Errors
+    /// Errors if there is no `=` or the key is not a [`PlatformKey`].
+    pub fn parse_fact(
+        txt: &str,
+    ) -> Result<(PlatformKey, String), PlatformError> {
+        let Some((key, value)) = txt.split_once('=') else {
+            return Err(PlatformError::MissingValue(txt.to_string()));
+        };
+
+        Ok((key.trim().parse()?, value.trim().to_string()))
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, key) in PlatformKey::ALL.into_iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" ")?;
+            }
+
+            write!(f, "{key}={}", self.get(key))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Platform {
+    #[new]
+    #[must_use]
+    pub fn py_new() -> Self {
+        Self::host()
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "host")]
+    #[must_use]
+    pub fn py_host() -> Self {
+        Self::host()
+    }
+
+    /// Look up a fact by name, such as `"os"`.
+    ///
+    /// # Errors
+    /// Errors if `key` is not a valid platform fact.
+    #[pyo3(name = "get")]
+    pub fn py_get(&self, key: &str) -> PyResult<String> {
+        let key = key
+            .parse()
+            .map_err(|e: PlatformError| PyValueError::new_err(e.to_string()))?;
+
+        Ok(self.get(key).to_string())
+    }
+
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Platform({self})")
+    }
+}
@@ -0,0 +1,118 @@
+//! Why each value in a concrete spec has the value it does.
+//!
+//! Every option of a concrete package, including its version, is given a
+//! [`Provenance`] once solved. Values are attributed in order of precedence:
+//! a value requested by the user, then one fixed by the package settings of
+//! the user configuration (see [`crate::package::settings`]), then one set by
+//! the package, then a default which the solver kept. Anything else was chosen freely by the
+//! solver, possibly overriding a default which could not be satisfied.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{package::outline::SpecOutline, spec::SpecOptionValue};
+
+/// The reason an option has its concrete value
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Provenance {
+    /// Set by a spec requested by the user, such as on the command line
+    Requested,
+
+    /// Fixed by the package settings of the user configuration
+    Config,
+
+    /// Set by the package itself, with `set_options`
+    Package,
+
+    /// A default which the solver kept. `chain` lists the packages the
+    /// default was inherited through, starting with the package which set
+    /// it, and is empty if the package set the default itself
+    Default { chain: Vec<String> },
+
+    /// Chosen freely by the solver. `overridden` is the default it did not
+    /// keep, if there was one
+    Solver { overridden: Option<SpecOptionValue> },
+}
+
+impl Provenance {
+    /// The provenance of `package`'s value of `option` in a solution to
+    /// `outline`, whose defaults must already have been propagated.
+    ///
+    /// * `value`: The concrete value of the option
+    #[must_use]
+    pub fn of(
+        outline: &SpecOutline,
+        package: &str,
+        option: &str,
+        value: &SpecOptionValue,
+    ) -> Self {
+        let Some(outline_pkg) =
+            outline.lookup.get(package).map(|idx| &outline.graph[*idx])
+        else {
+            return Self::Solver { overridden: None };
+        };
+
+        let set = outline_pkg.set_options.contains_key(option);
+
+        if set && outline_pkg.requested_options.contains(option) {
+            return Self::Requested;
+        }
+
+        if outline
+            .configured
+            .get(package)
+            .is_some_and(|options| options.contains(option))
+        {
+            return Self::Config;
+        }
+
+        if set {
+            return Self::Package;
+        }
+
+        match outline_pkg.set_defaults.get(option) {
+            Some(Some(default)) if default == value => Self::Default {
+                chain: outline
+                    .origins
+                    .chain(package, option)
+                    .map(<[_]>::to_vec)
+                    .unwrap_or_default(),
+            },
+            Some(Some(default)) => {
+                Self::Solver { overridden: Some(default.clone()) }
+            }
+            _ => Self::Solver { overridden: None },
+        }
+    }
+}
+
+impl std::fmt::Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Requested => f.write_str("requested by the user"),
+            Self::Config => f.write_str("set by the user configuration"),
+            Self::Package => f.write_str("set by the package"),
+            Self::Default { chain } => match chain.split_first() {
+                None => f.write_str("default set by the package"),
+                Some((origin, [])) => {
+                    write!(f, "default inherited from '{origin}'")
+                }
+                Some((origin, via)) => write!(
+                    f,
+                    "default inherited from '{origin}' via {}",
+                    via.iter()
+                        .map(|p| format!("'{p}'"))
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                ),
+            },
+            Self::Solver { overridden: None } => {
+                f.write_str("chosen by the solver")
+            }
+            Self::Solver { overridden: Some(default) } => write!(
+                f,
+                "chosen by the solver, overriding the default {default}"
+            ),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Matching concrete specs against spec strings.
+//!
+//! A [`SpecRequest`] is an abstract spec, and can be used as a query over
+//! concrete specs, such as those of the packages installed to an install root
+//! (see [`InstallDb::find`](crate::package::install::InstallDb::find)):
+//!
+//! ```text
+//! openmpi@5: +internal-pmix ^hwloc@:2.9
+//! ```
+//!
+//! is satisfied by every `openmpi` with a version of at least 5 and the
+//! `internal-pmix` option enabled, which depends on an `hwloc` no newer than
+//! 2.9. A package matches if it has every version
+//! and option the spec gives, so a spec with only a name matches every
+//! package with that name. The name may be qualified with the namespace of
+//! the repository the package was taken from. Versions are compared as in the
+//! solver (see [`Version::satisfies`]), so `@5` only matches version 5
+//! exactly, and a range such as `@5:` matches any version from 5 onwards (see
+//! [`VersionRange`]). Each dependency spec must match some package the
+//! package depends on, directly or indirectly.
+//!
+//! [`Version::satisfies`]: crate::package::version::Version::satisfies
+//! [`VersionRange`]: crate::package::version::VersionRange
+
+use std::collections::HashSet;
+
+use crate::{
+    constraint::CmpType,
+    spec::{
+        SpecOptionValue,
+        concrete::{ConcretePackage, ConcreteSpec},
+        eval::compare,
+        parse::SpecRequest,
+    },
+};
+
+impl SpecRequest {
+    /// Whether a root of `spec` satisfies this spec (see
+    /// [`Self::satisfied_at`]).
+    #[must_use]
+    pub fn satisfied_by(&self, spec: &ConcreteSpec) -> bool {
+        spec.roots
+            .iter()
+            .filter_map(|root| spec.get(root))
+            .any(|package| self.satisfied_at(spec, package))
+    }
+
+    /// Whether `package`, a package of `spec`, matches this spec and each
+    /// dependency spec matches some package it depends on.
+    #[must_use]
+    pub fn satisfied_at(
+        &self,
+        spec: &ConcreteSpec,
+        package: &ConcretePackage,
+    ) -> bool {
+        if !self.matches(package) {
+            return false;
+        }
+
+        if self.dependencies.is_empty() {
+            return true;
+        }
+
+        // Everything the package depends on, directly or indirectly
+        let mut seen = HashSet::new();
+        let mut stack = package.dependencies.iter().collect::<Vec<_>>();
+        let mut deps = Vec::new();
+
+        while let Some(name) = stack.pop() {
+            if !seen.insert(name) {
+                continue;
+            }
+
+            if let Some(dep) = spec.get(name) {
+                stack.extend(&dep.dependencies);
+                deps.push(dep);
+            }
+        }
+
+        self.dependencies
+            .iter()
+            .all(|request| deps.iter().any(|dep| request.matches(dep)))
+    }
+
+    /// Whether `package` has the name, version and options of this spec,
+    /// ignoring any dependency specs.
+    #[must_use]
+    pub fn matches(&self, package: &ConcretePackage) -> bool {
+        let name = package.base_name();
+
+        let named = self.name == name
+            || package
+                .namespace
+                .as_ref()
+                .is_some_and(|ns| self.name == format!("{ns}.{name}"));
+
+        let version = package.version.as_ref();
+
+        let versioned = self.version.as_ref().is_none_or(|v| {
+            version.is_some_and(|version| version.satisfies(CmpType::Equal, v))
+        }) && self
+            .version_range
+            .as_ref()
+            .is_none_or(|range| version.is_some_and(|v| range.contains(v)));
+
+        let option = |name: &str, op, value: &SpecOptionValue| {
+            package
+                .options
+                .get(name)
+                .is_some_and(|v| compare(v, op, value) == Some(true))
+        };
+
+        named
+            && versioned
+            && self
+                .options
+                .iter()
+                .all(|opt| option(&opt.name, CmpType::Equal, &opt.value))
+            && self.ranges.iter().all(|range| {
+                option(&range.name, CmpType::GreaterOrEqual, &range.min)
+                    && option(&range.name, CmpType::LessOrEqual, &range.max)
+            })
+    }
+}
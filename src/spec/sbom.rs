@@ -0,0 +1,546 @@
+//! Software bills of materials for concretized environments.
+//!
+//! Every package in a [`ConcreteSpec`] is reported with its name, version,
+//! license (see [`PackageOutline::license`]), the checksum of its source
+//! archive and its DAG hash, along with the dependencies between packages.
+//! Two formats are supported, both as JSON:
+//!
+//! - [CycloneDX 1.5](https://cyclonedx.org/docs/1.5/json/)
+//! - [SPDX 2.3](https://spdx.github.io/spdx-spec/v2.3/)
+//!
+//! Packages are identified by a [package URL](https://github.com/package-url/purl-spec)
+//! of type `generic`, qualified with the checksum and location of the source.
+//!
+//! [`PackageOutline::license`]: crate::package::outline::PackageOutline::license
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::{
+    spec::concrete::{ConcretePackage, ConcreteSpec, stable_hash},
+    util::digest::Algorithm,
+};
+
+/// Written in place of anything which is not known, as SPDX requires
+const NO_ASSERTION: &str = "NOASSERTION";
+
+/// The SBOM formats zpack can write
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SbomFormat {
+    CycloneDx,
+    Spdx,
+}
+
+impl SbomFormat {
+    pub const ALL: [Self; 2] = [Self::CycloneDx, Self::Spdx];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::CycloneDx => "cyclonedx",
+            Self::Spdx => "spdx",
+        }
+    }
+}
+
+impl std::fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for SbomFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|f| f.name() == s).ok_or_else(|| {
+            format!("unknown SBOM format '{s}', expected cyclonedx or spdx")
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDx {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    version: u32,
+    metadata: CycloneDxMetadata,
+    components: Vec<CycloneDxComponent>,
+    dependencies: Vec<CycloneDxDependency>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxMetadata {
+    timestamp: String,
+    tools: CycloneDxTools,
+}
+
+#[derive(Serialize)]
+struct CycloneDxTools {
+    components: Vec<CycloneDxTool>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxTool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+
+    name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<CycloneDxHash>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<CycloneDxLicense>,
+
+    purl: String,
+
+    #[serde(
+        rename = "externalReferences",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    external_references: Vec<CycloneDxReference>,
+
+    properties: Vec<CycloneDxProperty>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxHash {
+    alg: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct CycloneDxLicense {
+    expression: String,
+}
+
+#[derive(Serialize)]
+struct CycloneDxReference {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct CycloneDxProperty {
+    name: &'static str,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Spdx {
+    #[serde(rename = "spdxVersion")]
+    version: &'static str,
+
+    data_license: &'static str,
+
+    #[serde(rename = "SPDXID")]
+    id: &'static str,
+
+    name: String,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    relationships: Vec<SpdxRelationship>,
+}
+
+#[derive(Serialize)]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    id: String,
+
+    name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version_info: Option<String>,
+
+    download_location: String,
+    files_analyzed: bool,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checksums: Vec<SpdxChecksum>,
+
+    license_concluded: String,
+    license_declared: String,
+    copyright_text: &'static str,
+    external_refs: Vec<SpdxExternalRef>,
+    comment: String,
+}
+
+#[derive(Serialize)]
+struct SpdxChecksum {
+    algorithm: &'static str,
+
+    #[serde(rename = "checksumValue")]
+    checksum_value: String,
+}
+
+#[derive(Serialize)]
+struct SpdxExternalRef {
+    #[serde(rename = "referenceCategory")]
+    category: &'static str,
+
+    #[serde(rename = "referenceType")]
+    kind: &'static str,
+
+    #[serde(rename = "referenceLocator")]
+    locator: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: &'static str,
+    related_spdx_element: String,
+}
+
+/// Percent-encode everything but the characters left unreserved by RFC 3986.
+fn percent_encode(txt: &str) -> String {
+    use std::fmt::Write;
+
+    txt.bytes().fold(String::with_capacity(txt.len()), |mut res, byte| {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            res.push(char::from(byte));
+        } else {
+            let _ = write!(res, "%{byte:02X}");
+        }
+
+        res
+    })
+}
+
+/// The package URL of `package`
+fn purl(package: &ConcretePackage) -> String {
+    let mut res = String::from("pkg:generic/");
+
+    if let Some(namespace) = &package.namespace {
+        res.push_str(&percent_encode(namespace));
+        res.push('/');
+    }
+
+    res.push_str(&percent_encode(package.base_name()));
+
+    if let Some(version) = &package.version {
+        res.push('@');
+        res.push_str(&percent_encode(&version.to_string()));
+    }
+
+    if let Some(source) = &package.source {
+        res.push_str("?checksum=");
+        res.push_str(&percent_encode(&format!(
+            "{}:{}",
+            source.checksum.algorithm(),
+            source.checksum.hex()
+        )));
+        res.push_str("&download_url=");
+        res.push_str(&percent_encode(&source.url));
+    }
+
+    res
+}
+
+/// Reference to `package` unique within the SBOM. Duplicate nodes share a
+/// base name, so the node name is used instead.
+fn reference(package: &ConcretePackage, hash: &str) -> String {
+    format!("{}@{hash}", package.name)
+}
+
+/// `txt` with every character not allowed in an SPDX identifier replaced
+fn spdx_id(txt: &str) -> String {
+    let id = txt
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' })
+        .collect::<String>();
+
+    format!("SPDXRef-Package-{id}")
+}
+
+const fn cyclonedx_algorithm(algorithm: Algorithm) -> &'static str {
+    match algorithm {
+        Algorithm::Sha256 => "SHA-256",
+        Algorithm::Sha512 => "SHA-512",
+        Algorithm::Blake3 => "BLAKE3",
+    }
+}
+
+const fn spdx_algorithm(algorithm: Algorithm) -> &'static str {
+    match algorithm {
+        Algorithm::Sha256 => "SHA256",
+        Algorithm::Sha512 => "SHA512",
+        Algorithm::Blake3 => "BLAKE3",
+    }
+}
+
+/// `secs` since the Unix epoch as an RFC 3339 UTC timestamp, such as
+/// `2024-01-31T12:00:00Z`
+fn rfc3339(secs: u64) -> String {
+    // Howard Hinnant's days_from_civil, inverted
+    let days = secs / 86_400;
+    let time = secs % 86_400;
+
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+fn now() -> String {
+    rfc3339(
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+    )
+}
+
+/// Every package in `spec` with its DAG hash
+fn hashed(spec: &ConcreteSpec) -> Vec<(&ConcretePackage, String)> {
+    let mut hashes = spec.dag_hashes();
+
+    spec.packages
+        .values()
+        .map(|p| (p, hashes.remove(p.name.as_str()).unwrap_or_default()))
+        .collect()
+}
+
+fn cyclonedx(spec: &ConcreteSpec) -> CycloneDx {
+    let packages = hashed(spec);
+
+    let reference_of = |name: &str| {
+        packages
+            .iter()
+            .find(|(p, _)| p.name == name)
+            .map(|(p, hash)| reference(p, hash))
+    };
+
+    let components = packages
+        .iter()
+        .map(|(package, hash)| CycloneDxComponent {
+            kind: "library",
+            bom_ref: reference(package, hash),
+            name: package.base_name().to_string(),
+            group: package.namespace.clone(),
+            version: package.version.as_ref().map(ToString::to_string),
+            hashes: package
+                .source
+                .iter()
+                .map(|source| CycloneDxHash {
+                    alg: cyclonedx_algorithm(source.checksum.algorithm()),
+                    content: source.checksum.hex().to_string(),
+                })
+                .collect(),
+            licenses: package
+                .license
+                .iter()
+                .map(|license| CycloneDxLicense { expression: license.clone() })
+                .collect(),
+            purl: purl(package),
+            external_references: package
+                .source
+                .iter()
+                .map(|source| CycloneDxReference {
+                    kind: "distribution",
+                    url: source.url.clone(),
+                })
+                .collect(),
+            properties: vec![CycloneDxProperty {
+                name: "zpack:hash",
+                value: hash.clone(),
+            }],
+        })
+        .collect();
+
+    let dependencies = packages
+        .iter()
+        .map(|(package, hash)| CycloneDxDependency {
+            reference: reference(package, hash),
+            depends_on: package
+                .dependencies
+                .iter()
+                .filter_map(|dep| reference_of(dep))
+                .collect(),
+        })
+        .collect();
+
+    CycloneDx {
+        bom_format: "CycloneDX",
+        spec_version: "1.5",
+        version: 1,
+        metadata: CycloneDxMetadata {
+            timestamp: now(),
+            tools: CycloneDxTools {
+                components: vec![CycloneDxTool {
+                    kind: "application",
+                    name: "zpack",
+                    version: env!("CARGO_PKG_VERSION"),
+                }],
+            },
+        },
+        components,
+        dependencies,
+    }
+}
+
+fn spdx(spec: &ConcreteSpec) -> Spdx {
+    let packages = hashed(spec);
+
+    let id_of = |name: &str| {
+        packages
+            .iter()
+            .find(|(p, _)| p.name == name)
+            .map(|(p, hash)| spdx_id(&reference(p, hash)))
+    };
+
+    let spdx_packages = packages
+        .iter()
+        .map(|(package, hash)| {
+            let license =
+                package.license.clone().unwrap_or_else(|| NO_ASSERTION.into());
+
+            SpdxPackage {
+                id: spdx_id(&reference(package, hash)),
+                name: package.base_name().to_string(),
+                version_info: package.version.as_ref().map(ToString::to_string),
+                download_location: package
+                    .source
+                    .as_ref()
+                    .map_or_else(|| NO_ASSERTION.into(), |s| s.url.clone()),
+                files_analyzed: false,
+                checksums: package
+                    .source
+                    .iter()
+                    .map(|source| SpdxChecksum {
+                        algorithm: spdx_algorithm(source.checksum.algorithm()),
+                        checksum_value: source.checksum.hex().to_string(),
+                    })
+                    .collect(),
+                license_concluded: license.clone(),
+                license_declared: license,
+                copyright_text: NO_ASSERTION,
+                external_refs: vec![SpdxExternalRef {
+                    category: "PACKAGE-MANAGER",
+                    kind: "purl",
+                    locator: purl(package),
+                }],
+                comment: format!("zpack hash {hash}"),
+            }
+        })
+        .collect();
+
+    let described =
+        spec.roots.iter().filter_map(|root| id_of(root)).map(|id| {
+            SpdxRelationship {
+                spdx_element_id: "SPDXRef-DOCUMENT".into(),
+                relationship_type: "DESCRIBES",
+                related_spdx_element: id,
+            }
+        });
+
+    let depends = packages.iter().flat_map(|(package, hash)| {
+        let id = spdx_id(&reference(package, hash));
+
+        package.dependencies.iter().filter_map(|dep| id_of(dep)).map(
+            move |dep| SpdxRelationship {
+                spdx_element_id: id.clone(),
+                relationship_type: "DEPENDS_ON",
+                related_spdx_element: dep,
+            },
+        )
+    });
+
+    let relationships = described.chain(depends).collect();
+
+    // Identifies this exact environment, so the same environment always has
+    // the same namespace
+    let fingerprint = stable_hash(
+        packages
+            .iter()
+            .map(|(_, hash)| hash.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+            .as_bytes(),
+    );
+
+    let name = spec.roots.join("-");
+
+    Spdx {
+        version: "SPDX-2.3",
+        data_license: "CC0-1.0",
+        id: "SPDXRef-DOCUMENT",
+        document_namespace: format!(
+            "https://spdx.org/spdxdocs/zpack/{}-{fingerprint:016x}",
+            percent_encode(&name)
+        ),
+        name,
+        creation_info: SpdxCreationInfo {
+            created: now(),
+            creators: vec![format!(
+                "Tool: zpack-{}",
+                env!("CARGO_PKG_VERSION")
+            )],
+        },
+        packages: spdx_packages,
+        relationships,
+    }
+}
+
+/// Write an SBOM of every package in `spec` in `format`, as pretty-printed
+/// JSON.
+///
+/// # Errors
+/// Errors if the SBOM cannot be serialized.
+pub fn to_json(
+    spec: &ConcreteSpec,
+    format: SbomFormat,
+) -> serde_json::Result<String> {
+    match format {
+        SbomFormat::CycloneDx => serde_json::to_string_pretty(&cyclonedx(spec)),
+        SbomFormat::Spdx => serde_json::to_string_pretty(&spdx(spec)),
+    }
+}
@@ -0,0 +1,447 @@
+//! The on-disk record of a concrete spec, read back to reuse what it
+//! describes.
+//!
+//! Every install prefix holds a [`SpecFile`] at [`SPEC_FILE`], describing the
+//! package installed there and everything it depends on, so an installation
+//! can be reconstructed as a [`ConcreteSpec`] without the repository or the
+//! solver which produced it: to reuse installed packages, to build views of
+//! them, or to relocate them from a build cache.
+//!
+//! Spec files are written as JSON or YAML:
+//!
+//! ```json
+//! {
+//!   "spec_file_version": 1,
+//!   "root": "hpl",
+//!   "hashes": { "hpl": "5c1mj2c3...", "openblas": "0f3a9d1e..." },
+//!   "spec": {
+//!     "roots": ["hpl"],
+//!     "packages": { "hpl": { ... }, "openblas": { ... } }
+//!   }
+//! }
+//! ```
+//!
+//! `spec` is a [`ConcreteSpec`] rooted at the installed package, holding
+//! nothing it does not depend on. `hashes` records the DAG hash of every
+//! package (see [`ConcretePackage::dag_hash`]) when the file was written, and
+//! is checked when it is read, so a file which was edited, or which describes
+//! packages this version of zpack would build differently, is rejected rather
+//! than silently reused.
+//!
+//! [`SPEC_FILE_VERSION`] is incremented whenever the format changes in a way
+//! older versions cannot read.
+//!
+//! [`ConcretePackage::dag_hash`]: crate::spec::ConcretePackage::dag_hash
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use saphyr::{LoadableYamlNode, Mapping, Scalar, Yaml, YamlEmitter};
+use serde::{Deserialize, Serialize};
+
+use crate::spec::concrete::ConcreteSpec;
+
+/// The version of the format written by [`SpecFile::new`]
+pub const SPEC_FILE_VERSION: u32 = 1;
+
+/// File, relative to an install prefix, holding the [`SpecFile`] of the
+/// package installed there
+pub const SPEC_FILE: &str = ".zpack/spec.json";
+
+/// The formats a [`SpecFile`] can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpecFileFormat {
+    Json,
+    Yaml,
+}
+
+impl SpecFileFormat {
+    pub const ALL: [Self; 2] = [Self::Json, Self::Yaml];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+        }
+    }
+
+    /// The format of the file at `path`, from its extension. Anything other
+    /// than `.yaml` or `.yml` is JSON.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+impl std::fmt::Display for SpecFileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for SpecFileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|f| f.name() == s).ok_or_else(|| {
+            format!("unknown format '{s}', expected json or yaml")
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum SpecFileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Yaml(String),
+
+    /// The file was written with a format this version cannot read
+    UnsupportedVersion(u32),
+
+    /// The root is not one of the packages in the spec
+    MissingRoot(String),
+
+    /// The packages depend on each other in a cycle, given as the names
+    /// along it
+    Cycle(Vec<String>),
+
+    /// A package does not have the DAG hash recorded for it
+    HashMismatch {
+        package: String,
+        expected: String,
+        found: String,
+    },
+}
+
+impl std::fmt::Display for SpecFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot access spec file: {e}"),
+            Self::Json(e) => write!(f, "invalid json: {e}"),
+            Self::Yaml(e) => write!(f, "invalid yaml: {e}"),
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported spec file version {v}, expected \
+                 {SPEC_FILE_VERSION}"
+            ),
+            Self::MissingRoot(name) => {
+                write!(f, "spec file does not contain its root '{name}'")
+            }
+            Self::Cycle(cycle) => {
+                write!(f, "dependency cycle: {}", cycle.join(" -> "))
+            }
+            Self::HashMismatch { package, expected, found } => write!(
+                f,
+                "'{package}' hashes to {found}, but the spec file records \
+                 {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpecFileError {}
+
+impl From<std::io::Error> for SpecFileError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for SpecFileError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+/// A package and everything it depends on, as recorded in its install
+/// prefix. See the [module documentation](self)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecFile {
+    pub spec_file_version: u32,
+
+    /// The package the file describes
+    pub root: String,
+
+    /// The DAG hash of every package in [`Self::spec`], by name
+    pub hashes: BTreeMap<String, String>,
+
+    /// The root and its dependencies, rooted at [`Self::root`]
+    pub spec: ConcreteSpec,
+}
+
+/// The path of the spec file of the package installed to `prefix`
+#[must_use]
+pub fn path(prefix: &Path) -> PathBuf {
+    prefix.join(SPEC_FILE)
+}
+
+fn json_to_yaml(value: &serde_json::Value) -> Yaml<'static> {
+    match value {
+        serde_json::Value::Null => Yaml::Value(Scalar::Null),
+        serde_json::Value::Bool(b) => Yaml::Value(Scalar::Boolean(*b)),
+        serde_json::Value::Number(n) => {
+            Yaml::value_from_cow(Cow::Owned(n.to_string()))
+        }
+        serde_json::Value::String(s) => {
+            Yaml::Value(Scalar::String(Cow::Owned(s.clone())))
+        }
+        serde_json::Value::Array(items) => {
+            Yaml::Sequence(items.iter().map(json_to_yaml).collect())
+        }
+        serde_json::Value::Object(entries) => Yaml::Mapping(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        Yaml::Value(Scalar::String(Cow::Owned(key.clone()))),
+                        json_to_yaml(value),
+                    )
+                })
+                .collect::<Mapping<'static>>(),
+        ),
+    }
+}
+
+fn yaml_to_json(yaml: &Yaml) -> Result<serde_json::Value, SpecFileError> {
+    Ok(match yaml {
+        Yaml::Value(Scalar::Null) => serde_json::Value::Null,
+        Yaml::Value(Scalar::Boolean(b)) => serde_json::Value::Bool(*b),
+        Yaml::Value(Scalar::Integer(i)) => serde_json::Value::from(*i),
+        Yaml::Value(Scalar::FloatingPoint(f)) => serde_json::Value::from(**f),
+        Yaml::Value(Scalar::String(s)) => serde_json::Value::from(s.as_ref()),
+        Yaml::Sequence(items) => serde_json::Value::Array(
+            items.iter().map(yaml_to_json).collect::<Result<_, _>>()?,
+        ),
+        Yaml::Mapping(entries) => serde_json::Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let Yaml::Value(Scalar::String(key)) = key else {
+                        return Err(SpecFileError::Yaml(format!(
+                            "expected a string key, found {key:?}"
+                        )));
+                    };
+
+                    Ok((key.to_string(), yaml_to_json(value)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        Yaml::Tagged(_, inner) => yaml_to_json(inner)?,
+        Yaml::Representation(..) | Yaml::Alias(_) | Yaml::BadValue => {
+            return Err(SpecFileError::Yaml(format!(
+                "unsupported value {yaml:?}"
+            )));
+        }
+    })
+}
+
+impl ConcreteSpec {
+    /// The package `name` and everything it depends on, rooted at `name`.
+    /// `None` if the spec has no such package.
+    #[must_use]
+    pub fn sub_spec(&self, name: &str) -> Option<Self> {
+        self.get(name)?;
+
+        let mut packages = BTreeMap::new();
+        let mut stack = vec![name];
+
+        while let Some(name) = stack.pop() {
+            let Some(package) = self.get(name) else { continue };
+
+            if packages.insert(name.to_string(), package.clone()).is_none() {
+                stack.extend(package.dependencies.iter().map(String::as_str));
+            }
+        }
+
+        Some(Self { roots: vec![name.to_string()], packages })
+    }
+}
+
+impl SpecFile {
+    /// The spec file of the package `name` in `spec`, or `None` if the spec
+    /// has no such package.
+    #[must_use]
+    pub fn new(spec: &ConcreteSpec, name: &str) -> Option<Self> {
+        let spec = spec.sub_spec(name)?;
+
+        let hashes = spec
+            .dag_hashes()
+            .into_iter()
+            .map(|(name, hash)| (name.to_string(), hash))
+            .collect();
+
+        Some(Self {
+            spec_file_version: SPEC_FILE_VERSION,
+            root: name.to_string(),
+            hashes,
+            spec,
+        })
+    }
+
+    /// Write the spec file in `format`. JSON is pretty printed.
+    ///
+    /// # Errors
+    /// Errors if the spec file cannot be serialized.
+    pub fn to_string(
+        &self,
+        format: SpecFileFormat,
+    ) -> Result<String, SpecFileError> {
+        match format {
+            SpecFileFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            SpecFileFormat::Yaml => {
+                let yaml = json_to_yaml(&serde_json::to_value(self)?);
+
+                let mut res = String::new();
+                YamlEmitter::new(&mut res)
+                    .dump(&yaml)
+                    .map_err(|e| SpecFileError::Yaml(e.to_string()))?;
+                res.push('\n');
+
+                Ok(res)
+            }
+        }
+    }
+
+    /// Read a spec file previously written by [`Self::to_string`], checking
+    /// the hash of every package.
+    ///
+    /// # Errors
+    /// Errors if `txt` is not a valid spec file in `format`, was written with
+    /// a different [`SPEC_FILE_VERSION`] or a package does not have its
+    /// recorded hash.
+    pub fn parse(
+        txt: &str,
+        format: SpecFileFormat,
+    ) -> Result<Self, SpecFileError> {
+        let value = match format {
+            SpecFileFormat::Json => serde_json::from_str(txt)?,
+            SpecFileFormat::Yaml => {
+                let docs = Yaml::load_from_str(txt)
+                    .map_err(|e| SpecFileError::Yaml(e.to_string()))?;
+
+                let [doc] = docs.as_slice() else {
+                    return Err(SpecFileError::Yaml(format!(
+                        "expected a single document, found {}",
+                        docs.len()
+                    )));
+                };
+
+                yaml_to_json(doc)?
+            }
+        };
+
+        let version = value
+            .get("spec_file_version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or_default();
+
+        if version != u64::from(SPEC_FILE_VERSION) {
+            return Err(SpecFileError::UnsupportedVersion(
+                u32::try_from(version).unwrap_or(u32::MAX),
+            ));
+        }
+
+        let res: Self = serde_json::from_value(value)?;
+        res.verify()?;
+
+        Ok(res)
+    }
+
+    /// Check that the root is in the spec, the packages form a DAG and every
+    /// package has its recorded hash.
+    fn verify(&self) -> Result<(), SpecFileError> {
+        if self.spec.get(&self.root).is_none() {
+            return Err(SpecFileError::MissingRoot(self.root.clone()));
+        }
+
+        if let Some(cycle) = self.spec.find_cycle() {
+            return Err(SpecFileError::Cycle(cycle));
+        }
+
+        let mut hashes = self.spec.dag_hashes();
+
+        for name in self.spec.packages.keys() {
+            let found = hashes.remove(name.as_str()).unwrap_or_default();
+            let expected = self.hashes.get(name).cloned().unwrap_or_default();
+
+            if found != expected {
+                tracing::error!(
+                    "'{name}' hashes to {found}, but {expected} was recorded"
+                );
+
+                return Err(SpecFileError::HashMismatch {
+                    package: name.clone(),
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the spec file to `path`, in the format given by its extension
+    /// (see [`SpecFileFormat::from_path`]), creating its directory if needed.
+    ///
+    /// # Errors
+    /// Errors if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), SpecFileError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let txt = self.to_string(SpecFileFormat::from_path(path))?;
+        std::fs::write(path, txt)?;
+
+        Ok(())
+    }
+
+    /// Read the spec file at `path`, in the format given by its extension
+    /// (see [`SpecFileFormat::from_path`]).
+    ///
+    /// # Errors
+    /// Errors if the file cannot be read or is not a valid spec file.
+    pub fn load(path: &Path) -> Result<Self, SpecFileError> {
+        Self::parse(
+            &std::fs::read_to_string(path)?,
+            SpecFileFormat::from_path(path),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rejects_cycles() {
+        let json = r#"{
+            "roots": ["a"],
+            "packages": {
+                "a": { "name": "a", "version": null, "options": {},
+                       "dependencies": ["b"] },
+                "b": { "name": "b", "version": null, "options": {},
+                       "dependencies": ["a"] }
+            }
+        }"#;
+
+        let spec: ConcreteSpec = serde_json::from_str(json).unwrap();
+        let file = SpecFile::new(&spec, "a").unwrap();
+
+        for format in SpecFileFormat::ALL {
+            let txt = file.to_string(format).unwrap();
+
+            assert!(matches!(
+                SpecFile::parse(&txt, format),
+                Err(SpecFileError::Cycle(cycle)) if cycle == ["a", "b", "a"]
+            ));
+        }
+    }
+}
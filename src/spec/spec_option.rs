@@ -1,10 +1,20 @@
-use std::{hash::Hash, str::FromStr};
+use std::hash::Hash;
+#[cfg(feature = "solver-z3")]
+use std::str::FromStr;
 
+#[cfg(feature = "python")]
 use pyo3::{IntoPyObjectExt, exceptions::PyTypeError, prelude::*};
+use serde::{Deserialize, Serialize};
 
-use crate::package::{self, version, version::Version};
+#[cfg(feature = "solver-z3")]
+use crate::package::{self, version};
+use crate::{
+    package::{names, version::Version},
+    util::num::Number,
+};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SpecOptionType {
     Unknown,
     Bool,
@@ -15,7 +25,45 @@ pub enum SpecOptionType {
     // List, // TODO: How best to handle this?
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl SpecOptionType {
+    /// The types an option can be declared with
+    pub const DECLARABLE: [Self; 5] =
+        [Self::Bool, Self::Int, Self::Float, Self::Str, Self::Version];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Bool => "bool",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Str => "str",
+            Self::Version => "version",
+        }
+    }
+}
+
+impl std::fmt::Display for SpecOptionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for SpecOptionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::DECLARABLE.into_iter().find(|t| t.name() == s).ok_or_else(|| {
+            format!(
+                "unknown option type '{s}', expected bool, int, float, str or \
+                 version"
+            )
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum SpecOptionValue {
     Bool(bool),
     Int(i64),
@@ -24,6 +72,51 @@ pub enum SpecOptionValue {
     Version(Version),
 }
 
+impl From<bool> for SpecOptionValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for SpecOptionValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for SpecOptionValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<Number> for SpecOptionValue {
+    fn from(value: Number) -> Self {
+        match value {
+            Number::Integer(i) => Self::Int(i),
+            Number::Float(f) => Self::Float(f),
+        }
+    }
+}
+
+impl From<String> for SpecOptionValue {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<&str> for SpecOptionValue {
+    fn from(value: &str) -> Self {
+        Self::Str(value.to_string())
+    }
+}
+
+impl From<Version> for SpecOptionValue {
+    fn from(value: Version) -> Self {
+        Self::Version(value)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub struct SpecOption {
     pub value: Option<SpecOptionValue>,
@@ -58,6 +151,7 @@ impl SpecOptionValue {
     ///
     /// The dynamic type of the returned value matches the enum variant held by
     /// [`Self`]
+    #[cfg(feature = "solver-z3")]
     #[must_use]
     pub fn to_z3_dynamic(
         &self,
@@ -80,6 +174,7 @@ impl SpecOptionValue {
         }
     }
 
+    #[cfg(feature = "solver-z3")]
     #[must_use]
     pub fn from_z3_dynamic(
         package: &str,
@@ -105,8 +200,6 @@ impl SpecOptionValue {
                 Self::Str(dynamic.as_string().unwrap().as_string().unwrap())
             }
             SpecOptionType::Version => {
-                println!("Evaluating version");
-
                 let mut version = Version::empty();
 
                 let solved = registry
@@ -180,14 +273,15 @@ impl SpecOption {
 
     #[must_use]
     pub fn serialize_name(&self, package: &str, name: &str) -> String {
-        format!("{package}/{name}")
+        names::option(package, name)
     }
 
-    pub fn to_empty_z3_dynamic<'a>(
+    #[cfg(feature = "solver-z3")]
+    pub fn to_empty_z3_dynamic(
         &self,
-        package: &'a str,
-        name: &'a str,
-        wip_registry: &mut package::WipRegistry<'a>,
+        package: &str,
+        name: &str,
+        wip_registry: &mut package::WipRegistry,
     ) -> z3::ast::Dynamic {
         use z3::ast::{Bool, Float, Int, String};
 
@@ -222,6 +316,7 @@ impl SpecOption {
     }
 }
 
+#[cfg(feature = "python")]
 impl<'a, 'py> FromPyObject<'a, 'py> for SpecOptionValue {
     type Error = PyErr;
 
@@ -248,6 +343,7 @@ impl<'a, 'py> FromPyObject<'a, 'py> for SpecOptionValue {
     }
 }
 
+#[cfg(feature = "python")]
 impl<'py> IntoPyObject<'py> for SpecOptionValue {
     type Target = PyAny;
     type Output = Bound<'py, Self::Target>;
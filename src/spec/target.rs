@@ -0,0 +1,120 @@
+//! Microarchitecture targets.
+//!
+//! Every package has a `target` option naming the microarchitecture it is
+//! built for. Targets form a partial order: a target is compatible with, and
+//! newer than, every target it descends from. For example, `zen4` descends
+//! from `zen3` and `x86_64_v4`, which both descend from `x86_64`.
+//!
+//! The solver ensures that:
+//! - every target can run on the platform's microarchitecture (see
+//!   [`Platform`](crate::spec::platform::Platform))
+//! - no package targets a newer microarchitecture than one of its dependencies
+//!
+//! and otherwise prefers the platform's microarchitecture itself.
+
+use std::collections::VecDeque;
+
+use crate::util::suggest;
+
+/// The name of the option holding the target of every package
+pub const TARGET_OPTION: &str = "target";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub name: &'static str,
+
+    /// The generic architecture this target belongs to, such as `x86_64`
+    pub family: &'static str,
+
+    /// The targets this one directly extends
+    pub parents: &'static [&'static str],
+
+    /// Compiler flag selecting this target, understood by GCC and Clang
+    pub flag: &'static str,
+}
+
+const fn target(
+    name: &'static str,
+    family: &'static str,
+    parents: &'static [&'static str],
+    flag: &'static str,
+) -> Target {
+    Target { name, family, parents, flag }
+}
+
+/// Every known target, with parents listed before their children.
+pub const TARGETS: &[Target] = &[
+    target("x86_64", "x86_64", &[], "-march=x86-64"),
+    target("x86_64_v2", "x86_64", &["x86_64"], "-march=x86-64-v2"),
+    target("x86_64_v3", "x86_64", &["x86_64_v2"], "-march=x86-64-v3"),
+    target("x86_64_v4", "x86_64", &["x86_64_v3"], "-march=x86-64-v4"),
+    target("nehalem", "x86_64", &["x86_64_v2"], "-march=nehalem"),
+    target("haswell", "x86_64", &["x86_64_v3", "nehalem"], "-march=haswell"),
+    target("skylake", "x86_64", &["haswell"], "-march=skylake"),
+    target(
+        "skylake_avx512",
+        "x86_64",
+        &["skylake", "x86_64_v4"],
+        "-march=skylake-avx512",
+    ),
+    target("icelake", "x86_64", &["skylake_avx512"], "-march=icelake-server"),
+    target("sapphirerapids", "x86_64", &["icelake"], "-march=sapphirerapids"),
+    target("zen", "x86_64", &["x86_64_v3"], "-march=znver1"),
+    target("zen2", "x86_64", &["zen"], "-march=znver2"),
+    target("zen3", "x86_64", &["zen2"], "-march=znver3"),
+    target("zen4", "x86_64", &["zen3", "x86_64_v4"], "-march=znver4"),
+    target("zen5", "x86_64", &["zen4"], "-march=znver5"),
+    target("aarch64", "aarch64", &[], "-march=armv8-a"),
+    target("neoverse_n1", "aarch64", &["aarch64"], "-mcpu=neoverse-n1"),
+    target("neoverse_v1", "aarch64", &["neoverse_n1"], "-mcpu=neoverse-v1"),
+    target("neoverse_v2", "aarch64", &["neoverse_v1"], "-mcpu=neoverse-v2"),
+    target("a64fx", "aarch64", &["aarch64"], "-mcpu=a64fx"),
+    target("m1", "aarch64", &["aarch64"], "-mcpu=apple-m1"),
+    target("m2", "aarch64", &["m1"], "-mcpu=apple-m2"),
+];
+
+impl Target {
+    #[must_use]
+    pub fn lookup(name: &str) -> Option<&'static Self> {
+        TARGETS.iter().find(|t| t.name == name)
+    }
+
+    /// The known target closest to `name`, for "did you mean" suggestions.
+    #[must_use]
+    pub fn suggest(name: &str) -> Option<&'static str> {
+        suggest::closest(name, TARGETS.iter().map(|t| t.name))
+    }
+
+    /// This target and every target it descends from, nearest first.
+    #[must_use]
+    pub fn ancestors(&self) -> Vec<&'static Self> {
+        let mut res: Vec<&'static Self> = Vec::new();
+        let mut queue = VecDeque::from([self.name]);
+
+        while let Some(name) = queue.pop_front() {
+            let Some(target) = Self::lookup(name) else { continue };
+
+            if res.iter().any(|t| t.name == name) {
+                continue;
+            }
+
+            res.push(target);
+            queue.extend(target.parents);
+        }
+
+        res
+    }
+
+    /// Whether code built for `other` runs on this target, which is the case
+    /// if `other` is this target or one of its ancestors.
+    #[must_use]
+    pub fn supports(&self, other: &Self) -> bool {
+        self.ancestors().iter().any(|t| t.name == other.name)
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
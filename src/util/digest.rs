@@ -0,0 +1,313 @@
+//! Checksums with multiple hash algorithms.
+//!
+//! A [`Checksum`] is written as `<algorithm>:<hex digest>`, for example
+//! `sha512:cf83e1...`. For convenience, a bare 64 character digest is parsed as
+//! SHA-256 and a bare 128 character digest as SHA-512, since those are the
+//! forms most upstream projects publish.
+//!
+//! Hashing is done incrementally with a [`Hasher`]. [`HashingReader`] and
+//! [`HashingWriter`] wrap any reader or writer and hash the data as it passes
+//! through, so large downloads can be verified while they are streamed to disk
+//! instead of being read back afterwards.
+
+use std::{
+    io::{Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Digest;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Algorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    algorithm: Algorithm,
+
+    /// Lowercase, hex-encoded digest
+    hex: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    UnknownAlgorithm(String),
+    InvalidDigest { algorithm: Algorithm, digest: String },
+    AmbiguousDigest(String),
+}
+
+/// An incremental hasher for any [`Algorithm`].
+pub enum Hasher {
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+/// Wraps a reader, hashing everything read through it.
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+/// Wraps a writer, hashing everything written through it.
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Hasher,
+}
+
+impl Algorithm {
+    pub const ALL: [Self; 3] = [Self::Sha256, Self::Sha512, Self::Blake3];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Blake3 => "blake3",
+        }
+    }
+
+    /// Length of a hex-encoded digest
+    #[must_use]
+    pub const fn hex_len(self) -> usize {
+        match self {
+            Self::Sha256 | Self::Blake3 => 64,
+            Self::Sha512 => 128,
+        }
+    }
+}
+
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseError::UnknownAlgorithm(s.to_string()))
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownAlgorithm(name) => {
+                write!(f, "unknown hash algorithm '{name}'")
+            }
+            Self::InvalidDigest { algorithm, digest } => write!(
+                f,
+                "'{digest}' is not a valid {algorithm} digest; expected {} \
+                 hex characters",
+                algorithm.hex_len()
+            ),
+            Self::AmbiguousDigest(digest) => write!(
+                f,
+                "cannot infer the algorithm of '{digest}'; prefix it with \
+                 'sha256:', 'sha512:' or 'blake3:'"
+            ),
+        }
+    }
+}
+
+impl Checksum {
+    /// Create a checksum from an algorithm and a hex-encoded digest.
+    ///
+    /// # Errors
+    /// Errors if `hex` is not a valid digest for `algorithm`.
+    pub fn new(algorithm: Algorithm, hex: &str) -> Result<Self, ParseError> {
+        if hex.len() != algorithm.hex_len()
+            || !hex.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(ParseError::InvalidDigest {
+                algorithm,
+                digest: hex.to_string(),
+            });
+        }
+
+        Ok(Self { algorithm, hex: hex.to_ascii_lowercase() })
+    }
+
+    #[must_use]
+    pub const fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// The lowercase, hex-encoded digest
+    #[must_use]
+    pub fn hex(&self) -> &str {
+        &self.hex
+    }
+
+    /// Hash the file at `path` with the same algorithm as `self` and check the
+    /// result matches.
+    ///
+    /// Returns the actual checksum of the file.
+    ///
+    /// # Errors
+    /// Errors if the file cannot be read.
+    pub fn check_file(&self, path: &Path) -> std::io::Result<(bool, Self)> {
+        let actual = digest_file(path, self.algorithm)?;
+        Ok((actual == *self, actual))
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((algorithm, hex)) = s.split_once(':') {
+            return Self::new(algorithm.parse()?, hex);
+        }
+
+        match s.len() {
+            64 => Self::new(Algorithm::Sha256, s),
+            128 => Self::new(Algorithm::Sha512, s),
+            _ => Err(ParseError::AmbiguousDigest(s.to_string())),
+        }
+    }
+}
+
+impl Serialize for Checksum {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Checksum {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let txt = String::deserialize(deserializer)?;
+        txt.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Hasher {
+    #[must_use]
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+            Algorithm::Sha512 => Self::Sha512(sha2::Sha512::new()),
+            Algorithm::Blake3 => Self::Blake3(Box::default()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(h) => h.update(data),
+            Self::Sha512(h) => h.update(data),
+            Self::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn finalize(self) -> Checksum {
+        let (algorithm, hex) = match self {
+            Self::Sha256(h) => {
+                (Algorithm::Sha256, format!("{:x}", h.finalize()))
+            }
+            Self::Sha512(h) => {
+                (Algorithm::Sha512, format!("{:x}", h.finalize()))
+            }
+            Self::Blake3(h) => {
+                (Algorithm::Blake3, h.finalize().to_hex().to_string())
+            }
+        };
+
+        Checksum { algorithm, hex }
+    }
+}
+
+/// Hashes everything written, so a reader can be hashed with
+/// [`std::io::copy`].
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<R> HashingReader<R> {
+    pub fn new(inner: R, algorithm: Algorithm) -> Self {
+        Self { inner, hasher: Hasher::new(algorithm) }
+    }
+
+    /// The checksum of everything read so far.
+    #[must_use]
+    pub fn finalize(self) -> Checksum {
+        self.hasher.finalize()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<W> HashingWriter<W> {
+    pub fn new(inner: W, algorithm: Algorithm) -> Self {
+        Self { inner, hasher: Hasher::new(algorithm) }
+    }
+
+    /// The checksum of everything written so far, and the inner writer.
+    #[must_use]
+    pub fn finalize(self) -> (Checksum, W) {
+        (self.hasher.finalize(), self.inner)
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Hash the contents of the file at `path`.
+///
+/// # Errors
+/// Errors if the file cannot be read.
+pub fn digest_file(
+    path: &Path,
+    algorithm: Algorithm,
+) -> std::io::Result<Checksum> {
+    let mut reader = HashingReader::new(std::fs::File::open(path)?, algorithm);
+
+    std::io::copy(&mut reader, &mut std::io::sink())?;
+
+    Ok(reader.finalize())
+}
@@ -0,0 +1,152 @@
+//! Disk usage accounting and age-based cleanup of cache directories.
+//!
+//! Caches record when an entry was last used as its modification time, so
+//! entries can be removed once they have not been used for a while (see
+//! [`remove_unused`]), regardless of what the cache holds.
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+
+/// The number and total size of a set of files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, metadata: &std::fs::Metadata) {
+        self.files += 1;
+        self.bytes += metadata.len();
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, rhs: Self) {
+        self.files += rhs.files;
+        self.bytes += rhs.bytes;
+    }
+}
+
+/// Format `bytes` with a binary unit, such as `1.5 MiB`.
+#[must_use]
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    // Precision is only lost for sizes far larger than any disk
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} file(s), {}", self.files, format_bytes(self.bytes))
+    }
+}
+
+/// Visit every file under `dir`, which need not exist.
+fn walk(
+    dir: &Path,
+    f: &mut impl FnMut(&Path, &std::fs::Metadata) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            walk(&entry.path(), f)?;
+        } else {
+            f(&entry.path(), &metadata)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The files under `dir`. A missing directory uses nothing.
+///
+/// # Errors
+/// Errors if the directory cannot be read.
+pub fn usage(dir: &Path) -> std::io::Result<Usage> {
+    let mut res = Usage::default();
+
+    walk(dir, &mut |_, metadata| {
+        res.add(metadata);
+        Ok(())
+    })?;
+
+    Ok(res)
+}
+
+/// Remove every file under `dir` which was last modified more than `age`
+/// ago, returning what was removed.
+///
+/// # Errors
+/// Errors if the directory cannot be read or a file cannot be removed.
+pub fn remove_unused(dir: &Path, age: Duration) -> std::io::Result<Usage> {
+    let cutoff =
+        SystemTime::now().checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut res = Usage::default();
+
+    walk(dir, &mut |path, metadata| {
+        if metadata.modified()? < cutoff {
+            tracing::info!("removing '{}'", path.display());
+            std::fs::remove_file(path)?;
+            res.add(metadata);
+        }
+
+        Ok(())
+    })?;
+
+    Ok(res)
+}
+
+/// Remove `dir` and everything in it, returning what was removed. A missing
+/// directory is not an error.
+///
+/// # Errors
+/// Errors if the directory cannot be read or removed.
+pub fn remove_all(dir: &Path) -> std::io::Result<Usage> {
+    let res = usage(dir)?;
+
+    match std::fs::remove_dir_all(dir) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(res),
+        Err(e) => Err(e),
+        Ok(()) => Ok(res),
+    }
+}
+
+/// Mark the file at `path` as used now, so [`remove_unused`] keeps it.
+/// Failure is ignored, since the file may be read-only.
+pub fn touch(path: &Path) {
+    let res = std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+
+    if let Err(e) = res {
+        tracing::debug!("failed to touch '{}': {e}", path.display());
+    }
+}
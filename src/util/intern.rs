@@ -0,0 +1,123 @@
+//! String interning.
+//!
+//! An [`Interner`] maps each distinct string to a small integer
+//! [`Symbol`], so tables keyed by names can hash and compare integers
+//! instead of strings. Symbols are allocated in insertion order, starting at
+//! zero, so they can also index a `Vec`.
+//!
+//! Only the solver registry interns strings: the package and option names of
+//! its solver variables, and the string components of versions. Constraints
+//! still hold their names as `String`s, which the registry interns as it looks
+//! them up.
+
+use std::{borrow::Borrow, collections::HashMap, hash::Hash};
+
+/// An interned string. Only meaningful for the [`Interner`] which created it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// The position of this symbol in its interner
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A set of strings, each identified by a [`Symbol`].
+///
+/// `S` is the stored string type. Each string is held by both a `Vec` and a
+/// `HashMap`, so a cheaply cloned type such as `Arc<str>` is preferable.
+#[derive(Debug, Clone)]
+pub struct Interner<S> {
+    strings: Vec<S>,
+    symbols: HashMap<S, Symbol>,
+}
+
+impl<S> Default for Interner<S> {
+    fn default() -> Self {
+        Self { strings: Vec::new(), symbols: HashMap::new() }
+    }
+}
+
+impl<S> Interner<S>
+where
+    S: Borrow<str> + Clone + Eq + Hash,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The symbol for `string`, allocating a new one if it has not been seen
+    /// before.
+    ///
+    /// # Panics
+    /// Panics if more than `u32::MAX` strings are interned.
+    pub fn intern(&mut self, string: S) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string.borrow()) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(
+            u32::try_from(self.strings.len()).expect("too many symbols"),
+        );
+
+        self.strings.push(string.clone());
+        self.symbols.insert(string, symbol);
+
+        symbol
+    }
+
+    /// [`Self::intern`], only allocating if `string` has not been seen
+    /// before.
+    ///
+    /// # Panics
+    /// Panics if more than `u32::MAX` strings are interned.
+    pub fn intern_str(&mut self, string: &str) -> Symbol
+    where
+        S: for<'s> From<&'s str>,
+    {
+        if let Some(symbol) = self.get(string) {
+            return symbol;
+        }
+
+        self.intern(S::from(string))
+    }
+
+    /// The symbol for `string`, if it has been interned.
+    #[must_use]
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).copied()
+    }
+
+    /// The string for `symbol`.
+    ///
+    /// # Panics
+    /// Panics if `symbol` was not created by this interner.
+    #[must_use]
+    pub fn resolve(&self, symbol: Symbol) -> &S {
+        &self.strings[symbol.index()]
+    }
+
+    /// The string whose symbol has the given [`Symbol::index`], if any.
+    #[must_use]
+    pub fn resolve_index(&self, index: usize) -> Option<&S> {
+        self.strings.get(index)
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Every interned string with its symbol, in the order they were interned
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &S)> {
+        (0..).map(Symbol).zip(&self.strings)
+    }
+}
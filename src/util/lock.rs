@@ -0,0 +1,134 @@
+//! Advisory file locks shared between zpack processes.
+//!
+//! A [`FileLock`] is held until it is dropped. The process holding an
+//! exclusive lock writes its PID to the lock file, so a process waiting for
+//! the lock can say which process it is waiting for. Locks are released by
+//! the operating system if the process holding them exits, so a crashed
+//! process never leaves a stale lock behind.
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// How often a lock held by another process is checked while waiting for it
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An advisory lock on a file, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+}
+
+/// The process holding the lock on `file`, as written when it was locked
+fn holder(file: &mut File) -> String {
+    let mut txt = String::new();
+
+    let pid = file
+        .rewind()
+        .and_then(|()| file.read_to_string(&mut txt))
+        .ok()
+        .and_then(|_| txt.trim().parse::<u32>().ok());
+
+    pid.map_or_else(
+        || "another zpack process".to_string(),
+        |pid| format!("another zpack process (pid {pid})"),
+    )
+}
+
+impl FileLock {
+    /// Lock `path` so no other process can lock it, creating it if needed.
+    ///
+    /// * `timeout`: How long to wait for another process to release the
+    ///   lock. `None` waits indefinitely
+    ///
+    /// # Errors
+    /// Errors if the file cannot be created or locked, or with
+    /// [`std::io::ErrorKind::TimedOut`] if it is still locked after
+    /// `timeout`.
+    pub fn exclusive(
+        path: &Path,
+        timeout: Option<Duration>,
+    ) -> std::io::Result<Self> {
+        let mut lock = Self::acquire(path, timeout, true)?;
+
+        lock.file.set_len(0)?;
+        lock.file.rewind()?;
+        write!(lock.file, "{}", std::process::id())?;
+        lock.file.flush()?;
+
+        Ok(lock)
+    }
+
+    /// Lock `path` so no other process can lock it exclusively, creating it if
+    /// needed. See [`Self::exclusive`].
+    ///
+    /// # Errors
+    /// As [`Self::exclusive`].
+    pub fn shared(
+        path: &Path,
+        timeout: Option<Duration>,
+    ) -> std::io::Result<Self> {
+        Self::acquire(path, timeout, false)
+    }
+
+    fn acquire(
+        path: &Path,
+        timeout: Option<Duration>,
+        exclusive: bool,
+    ) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let start = Instant::now();
+        let mut waiting = false;
+
+        loop {
+            let res = if exclusive {
+                file.try_lock()
+            } else {
+                file.try_lock_shared()
+            };
+
+            match res {
+                Ok(()) => return Ok(Self { file }),
+                Err(TryLockError::Error(e)) => return Err(e),
+                Err(TryLockError::WouldBlock) => {}
+            }
+
+            if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!(
+                        "{} holds the lock on '{}'; gave up after {:.1}s",
+                        holder(&mut file),
+                        path.display(),
+                        start.elapsed().as_secs_f64()
+                    ),
+                ));
+            }
+
+            if !waiting {
+                tracing::warn!(
+                    "{} holds the lock on '{}'; waiting for it to finish",
+                    holder(&mut file),
+                    path.display()
+                );
+
+                waiting = true;
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
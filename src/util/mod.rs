@@ -1,4 +1,12 @@
+pub mod digest;
+pub mod disk;
 pub mod error;
+pub mod intern;
+pub mod lock;
 pub mod num;
+pub mod parse;
 pub mod parsers;
+pub mod paths;
 pub mod subscriber;
+pub mod suggest;
+pub mod timings;
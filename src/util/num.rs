@@ -1,10 +1,30 @@
 use anyhow::{Result, anyhow};
 
+/// A number which is an integer if it can be
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Number {
     Integer(i64),
     Float(f64),
 }
 
+impl Number {
+    /// The number as a float, rounding integers which have no exact
+    /// representation
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn as_f64(self) -> f64 {
+        match self {
+            Self::Integer(i) => i as f64,
+            Self::Float(f) => f,
+        }
+    }
+}
+
+/// Parse an integer, or failing that a float. Underscores between digits are
+/// ignored, so `1_000_000` is an integer.
+///
+/// # Errors
+/// Errors if `num` is neither an integer nor a float.
 pub fn parse_num(num: &str) -> Result<Number> {
     let num = num.replace('_', "");
 
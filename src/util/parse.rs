@@ -0,0 +1,476 @@
+//! The cursor shared by the hand-written parsers of spec strings (see
+//! [`crate::spec::parse`]) and constraints (see [`crate::constraint::syntax`]).
+//!
+//! A [`Cursor`] walks the characters of a string, collecting errors with the
+//! span of the text they refer to, so every parser reports problems the same
+//! way and handles quoted strings and their escapes identically. An error is
+//! either a message, or what was expected and what was found instead, and
+//! both are rendered by [`ParserErrorWrapper`](super::error::ParserErrorWrapper)
+//! as a label on the offending text.
+
+use std::{marker::PhantomData, ops::Range};
+
+#[cfg(feature = "cheap_errors")]
+use chumsky::error::Cheap;
+#[cfg(not(feature = "cheap_errors"))]
+use chumsky::error::{LabelError, Rich};
+use chumsky::{error::RichPattern, span::SimpleSpan, util::MaybeRef};
+
+use crate::util::{
+    error::ParserErrorType,
+    num::{Number, parse_num},
+};
+
+#[cfg(not(feature = "cheap_errors"))]
+pub(crate) fn error<'a>(
+    span: Range<usize>,
+    msg: impl ToString,
+) -> ParserErrorType<'a> {
+    Rich::custom(SimpleSpan::from(span), msg)
+}
+
+#[cfg(feature = "cheap_errors")]
+pub(crate) fn error<'a>(
+    span: Range<usize>,
+    _msg: impl ToString,
+) -> ParserErrorType<'a> {
+    Cheap::new(SimpleSpan::from(span))
+}
+
+/// An error for finding `found`, or the end of the text, where one of
+/// `expected` should be.
+#[cfg(not(feature = "cheap_errors"))]
+pub(crate) fn expected_found<'a>(
+    span: Range<usize>,
+    expected: impl IntoIterator<Item = RichPattern<'a, char>>,
+    found: Option<char>,
+) -> ParserErrorType<'a> {
+    <Rich<'a, char> as LabelError<'a, &'a str, _>>::expected_found(
+        expected,
+        found.map(MaybeRef::Val),
+        SimpleSpan::from(span),
+    )
+}
+
+#[cfg(feature = "cheap_errors")]
+pub(crate) fn expected_found<'a>(
+    span: Range<usize>,
+    _expected: impl IntoIterator<Item = RichPattern<'a, char>>,
+    _found: Option<char>,
+) -> ParserErrorType<'a> {
+    Cheap::new(SimpleSpan::from(span))
+}
+
+/// A position in a string being parsed, the errors found so far and the
+/// parser's own `state`.
+///
+/// Parsers implement their grammar as methods on `Cursor<S>` for their state
+/// type `S`. Spans are in characters, not bytes.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a, S = ()> {
+    pub chars: Vec<char>,
+    pub pos: usize,
+    pub errors: Vec<ParserErrorType<'a>>,
+    pub state: S,
+
+    /// With `cheap_errors`, the errors do not borrow from the text
+    marker: PhantomData<&'a str>,
+}
+
+impl<S> Cursor<'_, S> {
+    pub fn new(txt: &str, state: S) -> Self {
+        Self {
+            chars: txt.chars().collect(),
+            pos: 0,
+            errors: Vec::new(),
+            state,
+            marker: PhantomData,
+        }
+    }
+
+    #[must_use]
+    pub fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    pub fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume characters while `pred` holds, returning them and their span.
+    pub fn take_while(
+        &mut self,
+        pred: impl Fn(char) -> bool,
+    ) -> (String, Range<usize>) {
+        let start = self.pos;
+
+        while self.peek().is_some_and(&pred) {
+            self.pos += 1;
+        }
+
+        (self.chars[start..self.pos].iter().collect(), start..self.pos)
+    }
+
+    pub fn push(&mut self, span: Range<usize>, msg: impl ToString) {
+        self.errors.push(error(span, msg));
+    }
+
+    /// Whether the text at the cursor starts with `txt`
+    #[must_use]
+    pub fn at(&self, txt: &str) -> bool {
+        txt.chars()
+            .enumerate()
+            .all(|(idx, c)| self.chars.get(self.pos + idx) == Some(&c))
+    }
+
+    /// Consume `txt`, after any whitespace, if the text at the cursor starts
+    /// with it
+    pub fn eat(&mut self, txt: &str) -> bool {
+        self.skip_whitespace();
+
+        let found = self.at(txt);
+
+        if found {
+            self.pos += txt.chars().count();
+        }
+
+        found
+    }
+
+    /// The span of the character at the cursor, or of the last character if
+    /// the cursor is at the end
+    #[must_use]
+    pub const fn here(&self) -> Range<usize> {
+        if self.pos < self.chars.len() {
+            self.pos..self.pos + 1
+        } else {
+            self.pos.saturating_sub(1)..self.pos
+        }
+    }
+
+    /// Report that `what`, such as "a package name", was expected at the
+    /// cursor
+    pub fn expected(&mut self, what: impl Into<String>) {
+        let err = expected_found(
+            self.here(),
+            [RichPattern::Label(what.into().into())],
+            self.peek(),
+        );
+
+        self.errors.push(err);
+    }
+
+    /// Consume `c`, or report that it was expected
+    pub fn expect(&mut self, c: char) -> Option<()> {
+        if self.eat(&c.to_string()) {
+            return Some(());
+        }
+
+        let err = expected_found(
+            self.here(),
+            [RichPattern::Token(MaybeRef::Val(c))],
+            self.peek(),
+        );
+
+        self.errors.push(err);
+
+        None
+    }
+
+    /// A signed integer or a float, such as `-12`, `1_000` or `2.5e-3`, parsed
+    /// with [`parse_num`]
+    pub fn number(&mut self) -> Option<Number> {
+        let start = self.pos;
+        let digits = |c: char| c.is_ascii_digit() || c == '_';
+
+        if matches!(self.peek(), Some('+' | '-')) {
+            self.pos += 1;
+        }
+
+        self.take_while(digits);
+
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            self.take_while(digits);
+        }
+
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.pos += 1;
+
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+
+            self.take_while(digits);
+        }
+
+        let txt = self.chars[start..self.pos].iter().collect::<String>();
+
+        // Anything attached to the number, as in `12abc`, makes it invalid
+        let (rest, _) =
+            self.take_while(|c| c.is_ascii_alphanumeric() || c == '.');
+
+        match parse_num(&txt) {
+            Ok(num) if rest.is_empty() => Some(num),
+            _ => {
+                self.push(
+                    start..self.pos,
+                    format!("invalid number '{txt}{rest}'"),
+                );
+                None
+            }
+        }
+    }
+
+    /// `inner`, between `open` and `close`. Whitespace is allowed after
+    /// `open` and before `close`.
+    pub fn delimited_by<T>(
+        &mut self,
+        open: char,
+        close: char,
+        inner: impl FnOnce(&mut Self) -> Option<T>,
+    ) -> Option<T> {
+        self.expect(open)?;
+        let res = inner(self)?;
+        self.expect(close)?;
+
+        Some(res)
+    }
+
+    /// Any number of `item`, separated by `sep`, up to but not including
+    /// `end`. A separator is allowed after the last item.
+    ///
+    /// Stops at the first item which fails, with its errors reported.
+    pub fn separated_by<T>(
+        &mut self,
+        sep: char,
+        end: char,
+        mut item: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        let mut items = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() == Some(end) {
+                break;
+            }
+
+            items.push(item(self)?);
+
+            if !self.eat(&sep.to_string()) {
+                break;
+            }
+        }
+
+        Some(items)
+    }
+
+    /// A string in double quotes, with the escapes Rust uses when debug
+    /// printing a string. The cursor must be at the opening quote.
+    ///
+    /// After an invalid escape the rest of the string is still consumed, so
+    /// parsing can continue after it.
+    pub fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let mut res = Some(String::new());
+
+        loop {
+            let Some(c) = self.peek() else {
+                self.push(start..self.pos, "unterminated string");
+                return None;
+            };
+
+            self.pos += 1;
+
+            let c = match c {
+                '"' => return res,
+                '\\' => {
+                    let escape = self.peek();
+
+                    if escape.is_some() {
+                        self.pos += 1;
+                    }
+
+                    match escape {
+                        Some('n') => Some('\n'),
+                        Some('t') => Some('\t'),
+                        Some('r') => Some('\r'),
+                        Some('0') => Some('\0'),
+                        Some(c @ ('\\' | '"' | '\'')) => Some(c),
+                        Some('u') => self.unicode_escape(),
+                        _ => {
+                            self.push(
+                                self.pos - 1 - usize::from(escape.is_some())
+                                    ..self.pos,
+                                "unknown escape sequence",
+                            );
+                            None
+                        }
+                    }
+                }
+                c => Some(c),
+            };
+
+            match (c, &mut res) {
+                (Some(c), Some(res)) => res.push(c),
+                (Some(_), None) => (),
+                (None, _) => res = None,
+            }
+        }
+    }
+
+    /// The `{XXXX}` of a `\u{XXXX}` escape
+    fn unicode_escape(&mut self) -> Option<char> {
+        let start = self.pos - 2;
+
+        if self.peek() == Some('{') {
+            self.pos += 1;
+
+            let (hex, _) = self.take_while(|c| c.is_ascii_hexdigit());
+
+            if self.peek() == Some('}') {
+                self.pos += 1;
+
+                if let Some(c) =
+                    u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                {
+                    return Some(c);
+                }
+            }
+        }
+
+        self.push(start..self.pos, "invalid unicode escape");
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An integer, or a bracketed list of trees
+    #[derive(Debug, PartialEq)]
+    enum Tree {
+        Leaf(i64),
+        List(Vec<Self>),
+    }
+
+    impl Cursor<'_> {
+        fn tree(&mut self) -> Option<Tree> {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some('[') => return self.list().map(Tree::List),
+                Some(c) if c.is_ascii_digit() || c == '-' => (),
+                _ => {
+                    self.expected("an integer or a list");
+                    return None;
+                }
+            }
+
+            match self.number()? {
+                Number::Integer(n) => Some(Tree::Leaf(n)),
+                Number::Float(_) => {
+                    self.expected("an integer");
+                    None
+                }
+            }
+        }
+
+        fn list(&mut self) -> Option<Vec<Tree>> {
+            self.delimited_by('[', ']', |c| {
+                c.separated_by(',', ']', Cursor::tree)
+            })
+        }
+    }
+
+    /// The parsed list, or the span of the one error reported
+    fn parse(txt: &str) -> Result<Vec<Tree>, Range<usize>> {
+        let mut cursor = Cursor::new(txt, ());
+        let list = cursor.list();
+
+        match cursor.errors.as_slice() {
+            [] => Ok(list.unwrap()),
+            [err] => Err(err.span().into_range()),
+            errors => panic!("{txt}: more than one error: {errors:?}"),
+        }
+    }
+
+    fn leaves(values: &[i64]) -> Vec<Tree> {
+        values.iter().copied().map(Tree::Leaf).collect()
+    }
+
+    #[test]
+    fn empty_lists() {
+        assert_eq!(parse("[]"), Ok(Vec::new()));
+        assert_eq!(parse("[  ]"), Ok(Vec::new()));
+        assert_eq!(parse("[ [] ]"), Ok(vec![Tree::List(Vec::new())]));
+    }
+
+    #[test]
+    fn separated_items() {
+        assert_eq!(parse("[1]"), Ok(leaves(&[1])));
+        assert_eq!(parse("[ 1 , 2,3 ]"), Ok(leaves(&[1, 2, 3])));
+    }
+
+    #[test]
+    fn trailing_separator() {
+        assert_eq!(parse("[1, 2,]"), Ok(leaves(&[1, 2])));
+        assert_eq!(parse("[1, 2 , ]"), Ok(leaves(&[1, 2])));
+
+        // A separator needs an item before it
+        assert_eq!(parse("[,]"), Err(1..2));
+        assert_eq!(parse("[1,,]"), Err(3..4));
+    }
+
+    #[test]
+    fn missing_close_delimiter() {
+        // Reported at the last character when the text ends
+        assert_eq!(parse("[1, 2"), Err(4..5));
+        assert_eq!(parse("[1, 2,"), Err(5..6));
+        assert_eq!(parse("[1, 2 "), Err(5..6));
+        assert_eq!(parse("["), Err(0..1));
+
+        // And at whatever is found instead otherwise
+        assert_eq!(parse("[1 2]"), Err(3..4));
+        assert_eq!(parse("[1; 2]"), Err(2..3));
+    }
+
+    #[test]
+    fn missing_open_delimiter() {
+        assert_eq!(parse("1, 2]"), Err(0..1));
+        assert_eq!(parse(""), Err(0..0));
+    }
+
+    #[test]
+    fn nested_delimiters() {
+        assert_eq!(
+            parse("[1, [2, [3, []]], [4,],]"),
+            Ok(vec![
+                Tree::Leaf(1),
+                Tree::List(vec![
+                    Tree::Leaf(2),
+                    Tree::List(vec![Tree::Leaf(3), Tree::List(Vec::new())]),
+                ]),
+                Tree::List(leaves(&[4])),
+            ])
+        );
+
+        // An unclosed inner list is reported where its close was expected,
+        // and stops the outer list
+        assert_eq!(parse("[1, [2, 3]"), Err(9..10));
+        assert_eq!(parse("[[1, 2]"), Err(6..7));
+        assert_eq!(parse("[[1 2]]"), Err(4..5));
+    }
+
+    #[test]
+    fn failed_items_stop_the_list() {
+        assert_eq!(parse("[1, 2.5, 3]"), Err(7..8));
+        assert_eq!(parse("[1, 2x, 3]"), Err(4..6));
+    }
+}
@@ -1,4 +1,4 @@
-use std::borrow::Borrow;
+use std::{borrow::Borrow, time::Duration};
 
 use num_traits::{Signed, Unsigned};
 
@@ -13,6 +13,37 @@ pub fn parse_number<T: std::str::FromStr>(s: &str) -> Option<T> {
         .ok()
 }
 
+/// Parse a duration written as a whole number followed by a unit: `s`, `m`,
+/// `h`, `d` or `w`, such as `30d`. A number without a unit is in seconds.
+///
+/// # Errors
+/// Errors if the number or unit is invalid.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (num, unit) =
+        s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+
+    let num =
+        num.parse::<u64>().map_err(|_| format!("invalid duration '{s}'"))?;
+
+    let secs = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        unit => {
+            return Err(format!(
+                "unknown unit '{unit}' in '{s}'; expected s, m, h, d or w"
+            ));
+        }
+    };
+
+    num.checked_mul(secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{s}' is too long"))
+}
+
 pub struct ParseUnsigned<'a, T, I = std::str::Bytes<'a>>
 where
     T: Unsigned,
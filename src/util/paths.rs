@@ -0,0 +1,61 @@
+//! Standard locations for zpack's files.
+//!
+//! Every location can be overridden with an environment variable, which is
+//! checked first. Otherwise the XDG base directory variables are respected,
+//! falling back to the usual defaults under `$HOME`.
+
+use std::path::PathBuf;
+
+fn home() -> PathBuf {
+    std::env::var_os("HOME").map_or_else(|| PathBuf::from("."), PathBuf::from)
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    std::env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(|| home().join(fallback), PathBuf::from)
+        .join("zpack")
+}
+
+/// The user configuration file. Overridden by `ZPACK_CONFIG`.
+#[must_use]
+pub fn config_file() -> PathBuf {
+    std::env::var_os("ZPACK_CONFIG").map_or_else(
+        || xdg_dir("XDG_CONFIG_HOME", ".config").join("config.yaml"),
+        PathBuf::from,
+    )
+}
+
+/// Directory for data which can be regenerated. Overridden by
+/// `ZPACK_CACHE_DIR`.
+#[must_use]
+pub fn cache_dir() -> PathBuf {
+    std::env::var_os("ZPACK_CACHE_DIR")
+        .map_or_else(|| xdg_dir("XDG_CACHE_HOME", ".cache"), PathBuf::from)
+}
+
+/// The default root packages are installed to. Overridden by
+/// `ZPACK_INSTALL_ROOT`.
+#[must_use]
+pub fn install_root() -> PathBuf {
+    std::env::var_os("ZPACK_INSTALL_ROOT").map_or_else(
+        || xdg_dir("XDG_DATA_HOME", ".local/share").join("opt"),
+        PathBuf::from,
+    )
+}
+
+/// The socket `zpack serve` listens on by default. Overridden by
+/// `ZPACK_SOCKET`, otherwise placed in `XDG_RUNTIME_DIR` if it is set and the
+/// cache directory if not.
+#[must_use]
+pub fn socket_file() -> PathBuf {
+    std::env::var_os("ZPACK_SOCKET").map_or_else(
+        || {
+            std::env::var_os("XDG_RUNTIME_DIR")
+                .filter(|dir| !dir.is_empty())
+                .map_or_else(cache_dir, |dir| PathBuf::from(dir).join("zpack"))
+                .join("zpack.sock")
+        },
+        PathBuf::from,
+    )
+}
@@ -0,0 +1,52 @@
+//! "Did you mean ...?" suggestions for misspelled names.
+
+/// The Levenshtein edit distance between two strings, in characters.
+#[must_use]
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+
+            row[j + 1] =
+                if ca == *cb { diag } else { 1 + diag.min(above).min(row[j]) };
+
+            diag = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// The candidate closest to `name`, if any is close enough to plausibly be a
+/// typo of it.
+///
+/// A candidate is considered close if it is within one edit for every three
+/// characters of `name`, with a minimum of one edit. Ties are broken by the
+/// order of `candidates`.
+pub fn closest<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (levenshtein(name, c), c))
+        .filter(|(distance, _)| *distance <= max)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c)
+}
+
+/// Format a suggestion as a suffix for an error message, such as
+/// `"; did you mean 'static'?"`, or an empty string if there is none.
+#[must_use]
+pub fn did_you_mean(suggestion: Option<&str>) -> String {
+    suggestion.map_or_else(String::new, |s| format!("; did you mean '{s}'?"))
+}
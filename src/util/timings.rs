@@ -0,0 +1,210 @@
+//! Wall time spent in each stage of a command.
+//!
+//! Unlike [`SolveStats`], which profiles a single concretization, [`Timings`]
+//! aggregates every occurrence of a stage over the whole command, such as the
+//! time spent fetching across every package. Nothing is recorded until
+//! [`enable`] is called, so instrumented code costs nothing otherwise.
+//!
+//! Stages may be nested: [`PYTHON_EXTRACTION`] is part of [`REPO_LOAD`], for
+//! example. Each stage's time includes its nested stages and is shown as a
+//! share of the command's wall time, so the shares need not sum to 100%.
+//!
+//! [`SolveStats`]: crate::package::stats::SolveStats
+
+use std::{
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::package::stats::SolveStats;
+
+/// Loading package outlines from repositories, including the cache and
+/// version discovery
+pub const REPO_LOAD: &str = "repo load";
+
+/// Executing package files to extract their outlines
+pub const PYTHON_EXTRACTION: &str = "python extraction";
+
+/// Propagating default option values before solving
+pub const DEFAULT_PROPAGATION: &str = "default propagation";
+
+/// Encoding the spec outline for the solver
+pub const SOLVER_GROUNDING: &str = "solver grounding";
+
+/// Checking satisfiability and optimizing
+pub const SOLVER_CHECK: &str = "solver check";
+
+/// Building the concrete spec from the solver's model
+pub const MODEL_EXTRACTION: &str = "model extraction";
+
+/// Downloading sources and other remote resources
+pub const FETCH: &str = "fetch";
+
+/// The stage a phase of [`SolveStats`] belongs to
+fn solve_stage(phase: &str) -> &str {
+    match phase {
+        "type check" | "variable creation" | "constraint adding"
+        | "encoding" => SOLVER_GROUNDING,
+        "check" => SOLVER_CHECK,
+        phase => phase,
+    }
+}
+
+/// The aggregated time spent in each stage
+#[derive(Clone, Debug)]
+pub struct Timings {
+    /// Each stage with its total time and the number of times it ran, in the
+    /// order the stages first started
+    pub stages: Vec<(String, Duration, usize)>,
+
+    start: Instant,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timings {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { stages: Vec::new(), start: Instant::now() }
+    }
+
+    fn entry(&mut self, stage: &str) -> &mut (String, Duration, usize) {
+        if let Some(idx) = self.stages.iter().position(|(s, ..)| s == stage) {
+            &mut self.stages[idx]
+        } else {
+            self.stages.push((stage.to_string(), Duration::ZERO, 0));
+            self.stages.last_mut().unwrap()
+        }
+    }
+
+    /// Add one occurrence of `stage` taking `elapsed`
+    pub fn record(&mut self, stage: &str, elapsed: Duration) {
+        let entry = self.entry(stage);
+        entry.1 += elapsed;
+        entry.2 += 1;
+    }
+
+    /// Add the phases of a concretization to their stages
+    pub fn record_solve(&mut self, stats: &SolveStats) {
+        for (phase, elapsed) in &stats.phases {
+            self.record(solve_stage(phase), *elapsed);
+        }
+    }
+
+    /// Wall time since these timings were created
+    #[must_use]
+    pub fn wall_time(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl std::fmt::Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let wall = self.wall_time();
+
+        let width = self
+            .stages
+            .iter()
+            .map(|(stage, ..)| stage.len())
+            .chain(std::iter::once("wall time".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(
+            f,
+            "{:<width$}  {:>6}  {:>10}  {:>6}",
+            "stage", "count", "time (ms)", "%"
+        )?;
+
+        for (stage, time, count) in &self.stages {
+            let percent = if wall.is_zero() {
+                0.0
+            } else {
+                100.0 * time.as_secs_f64() / wall.as_secs_f64()
+            };
+
+            writeln!(
+                f,
+                "{stage:<width$}  {count:>6}  {:>10.3}  {percent:>6.1}",
+                time.as_secs_f64() * 1000.0
+            )?;
+        }
+
+        writeln!(
+            f,
+            "{:<width$}  {:>6}  {:>10.3}  {:>6.1}",
+            "wall time",
+            "",
+            wall.as_secs_f64() * 1000.0,
+            100.0
+        )
+    }
+}
+
+static TIMINGS: LazyLock<Mutex<Option<Timings>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+fn with_timings(f: impl FnOnce(&mut Timings)) {
+    if let Some(timings) =
+        TIMINGS.lock().expect("timings lock poisoned").as_mut()
+    {
+        f(timings);
+    }
+}
+
+/// Start recording timings, discarding any recorded so far.
+///
+/// # Panics
+/// Panics if the timings lock is poisoned.
+pub fn enable() {
+    *TIMINGS.lock().expect("timings lock poisoned") = Some(Timings::new());
+}
+
+/// Stop recording and return the timings recorded since [`enable`], if it
+/// was called.
+///
+/// # Panics
+/// Panics if the timings lock is poisoned.
+#[must_use]
+pub fn take() -> Option<Timings> {
+    TIMINGS.lock().expect("timings lock poisoned").take()
+}
+
+/// Add one occurrence of `stage` taking `elapsed`, if recording.
+///
+/// # Panics
+/// Panics if the timings lock is poisoned.
+pub fn record(stage: &str, elapsed: Duration) {
+    with_timings(|timings| timings.record(stage, elapsed));
+}
+
+/// Add the phases of a concretization to their stages, if recording.
+///
+/// # Panics
+/// Panics if the timings lock is poisoned.
+pub fn record_solve(stats: &SolveStats) {
+    with_timings(|timings| timings.record_solve(stats));
+}
+
+/// Run `f`, adding the time it takes to `stage` if recording.
+///
+/// The stage is listed from when it starts, so stages containing others are
+/// listed before them.
+///
+/// # Panics
+/// Panics if the timings lock is poisoned.
+pub fn time<T>(stage: &str, f: impl FnOnce() -> T) -> T {
+    with_timings(|timings| {
+        timings.entry(stage);
+    });
+
+    let start = Instant::now();
+    let res = f();
+    record(stage, start.elapsed());
+
+    res
+}
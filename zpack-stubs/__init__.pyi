@@ -0,0 +1,53 @@
+# Generated by `zpack stubs`. Do not edit.
+
+import typing
+
+from . import constraint as constraint
+from . import package as package
+from . import spec as spec
+
+
+class PluginRegistry:
+    """Registers the hooks of a single plugin. Passed to the plugin's `register`
+    function
+    """
+    def builder(self, name: str, func: typing.Any) -> None:
+        """Register a build system called `name`"""
+    def constraint(self, name: str, factory: typing.Any) -> None:
+        """Register a constraint factory, called by package files with
+        `zpack.plugin_constraint(name, ...)`
+        """
+    def fetcher(self, scheme: str, func: typing.Any) -> None:
+        """Fetch URLs starting with `scheme://` by calling `func(url, dest)`"""
+    @property
+    def plugin(self) -> str: ...
+
+
+def init_tracing() -> None:
+    """Initialize the tracing subscriber in Python so internal logs are printed
+
+    # Panics
+    Panics if the subscriber cannot be created or set as the default
+    """
+
+
+def main_entry() -> typing.Any:
+    """The main python entry point
+
+    # Errors
+    Errors here will contain information from the root cause of the issue.
+    It may also be worth calling
+    [`zpack.init_tracing()`](py_tracing::init_tracing), as additional
+    information is logged throughout the execution of `zpack`.
+
+    The GIL is only held while Python code runs, such as package files and
+    hooks, and not while solving.
+    """
+
+
+def plugin_constraint(name: str, *args, **kwargs) -> typing.Any:
+    """Create a constraint with the factory a plugin registered as `name`.
+
+    # Errors
+    Errors if no plugin registered the factory or the factory raises.
+    """
@@ -0,0 +1,679 @@
+# Generated by `zpack stubs`. Do not edit.
+
+import typing
+import zpack.spec
+
+
+class Arith:
+    """`lhs op rhs`, for numeric `lhs` and `rhs`"""
+    def __init__(self, lhs: typing.Any, rhs: typing.Any, op: ArithOp) -> None: ...
+    def __add__(self, value, /) -> typing.Any:
+        """Return self+value."""
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __mul__(self, value, /) -> typing.Any:
+        """Return self*value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __radd__(self, value, /) -> typing.Any:
+        """Return value+self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __rmul__(self, value, /) -> typing.Any:
+        """Return value*self."""
+    def __rsub__(self, value, /) -> typing.Any:
+        """Return value-self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    def __sub__(self, value, /) -> typing.Any:
+        """Return self-value."""
+    @property
+    def lhs(self) -> typing.Any: ...
+    @lhs.setter
+    def lhs(self, value: typing.Any) -> None: ...
+    @property
+    def op(self) -> ArithOp: ...
+    @op.setter
+    def op(self, value: ArithOp) -> None: ...
+    @property
+    def rhs(self) -> typing.Any: ...
+    @rhs.setter
+    def rhs(self, value: typing.Any) -> None: ...
+
+
+class ArithOp:
+    Add: ArithOp
+    Mul: ArithOp
+    Sub: ArithOp
+    def __eq__(self, value, /) -> bool:
+        """Return self==value."""
+    def __ge__(self, value, /):
+        """Return self>=value."""
+    def __gt__(self, value, /):
+        """Return self>value."""
+    def __hash__(self) -> int:
+        """Return hash(self)."""
+    def __int__(self):
+        """int(self)"""
+    def __le__(self, value, /):
+        """Return self<=value."""
+    def __lt__(self, value, /):
+        """Return self<value."""
+    def __ne__(self, value, /) -> bool:
+        """Return self!=value."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+class Cmp:
+    def __init__(self, lhs: typing.Any, rhs: typing.Any, op: CmpType) -> None: ...
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __invert__(self) -> typing.Any:
+        """~self"""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    def if_then(self, then: typing.Any) -> IfThen:
+        """Wrap this condition in an `IfThen` constraint.
+
+        `cond.if_then(then)` => If ( cond ) Then ( then )
+        """
+    @property
+    def lhs(self) -> typing.Any: ...
+    @lhs.setter
+    def lhs(self, value: typing.Any) -> None: ...
+    @property
+    def op(self) -> CmpType: ...
+    @op.setter
+    def op(self, value: CmpType) -> None: ...
+    @property
+    def rhs(self) -> typing.Any: ...
+    @rhs.setter
+    def rhs(self, value: typing.Any) -> None: ...
+
+
+class CmpType:
+    Equal: CmpType
+    Greater: CmpType
+    GreaterOrEqual: CmpType
+    Less: CmpType
+    LessOrEqual: CmpType
+    NotEqual: CmpType
+    def __eq__(self, value, /) -> bool:
+        """Return self==value."""
+    def __ge__(self, value, /):
+        """Return self>=value."""
+    def __gt__(self, value, /):
+        """Return self>value."""
+    def __hash__(self) -> int:
+        """Return hash(self)."""
+    def __int__(self):
+        """int(self)"""
+    def __le__(self, value, /):
+        """Return self<=value."""
+    def __lt__(self, value, /):
+        """Return self<value."""
+    def __ne__(self, value, /) -> bool:
+        """Return self!=value."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+class Depends:
+    def __init__(self, name: str) -> None: ...
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __invert__(self) -> typing.Any:
+        """~self"""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def on(self) -> str: ...
+    @on.setter
+    def on(self, value: str) -> None: ...
+
+
+class Equal:
+    """True if an option of a package has a value, such as `mpi:shared` being
+    `false`. Shorthand for comparing the option with `==`
+    """
+    def __init__(self, package: str, option: str, value: typing.Any) -> None: ...
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __invert__(self) -> NotEqual:
+        """~self"""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def option(self) -> SpecOption: ...
+    @option.setter
+    def option(self, value: SpecOption) -> None: ...
+    @property
+    def value(self) -> typing.Any: ...
+    @value.setter
+    def value(self, value: typing.Any) -> None: ...
+
+
+class IfThen:
+    def __init__(self, cond: typing.Any, then: typing.Any) -> None: ...
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def cond(self) -> typing.Any: ...
+    @cond.setter
+    def cond(self, value: typing.Any) -> None: ...
+    @property
+    def then(self) -> typing.Any: ...
+    @then.setter
+    def then(self, value: typing.Any) -> None: ...
+
+
+class InRange:
+    """True if an option of a package is between `min` and `max`, including
+    them if `inclusive`
+    """
+    def __init__(self, package: str, option: str, min: typing.Any, max: typing.Any, inclusive: bool = ...) -> None: ...
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __invert__(self) -> typing.Any:
+        """~self"""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def inclusive(self) -> bool: ...
+    @inclusive.setter
+    def inclusive(self, value: bool) -> None: ...
+    @property
+    def max(self) -> typing.Any: ...
+    @max.setter
+    def max(self, value: typing.Any) -> None: ...
+    @property
+    def min(self) -> typing.Any: ...
+    @min.setter
+    def min(self, value: typing.Any) -> None: ...
+    @property
+    def option(self) -> SpecOption: ...
+    @option.setter
+    def option(self, value: SpecOption) -> None: ...
+
+
+class Matches:
+    """True if a string option of a package matches `pattern`"""
+    def __init__(self, package: str, option: str, pattern: str) -> None: ...
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __invert__(self) -> typing.Any:
+        """~self"""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def option(self) -> SpecOption: ...
+    @option.setter
+    def option(self, value: SpecOption) -> None: ...
+    @property
+    def pattern(self) -> str: ...
+    @pattern.setter
+    def pattern(self, value: str) -> None: ...
+
+
+class Maximize:
+    def __init__(self, item: typing.Any) -> None: ...
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def item(self) -> typing.Any: ...
+    @item.setter
+    def item(self, value: typing.Any) -> None: ...
+
+
+class Minimize:
+    def __init__(self, item: typing.Any) -> None: ...
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def item(self) -> typing.Any: ...
+    @item.setter
+    def item(self, value: typing.Any) -> None: ...
+
+
+class NotEqual:
+    """True if an option of a package does not have a value. Shorthand for
+    comparing the option with `!=`
+    """
+    def __init__(self, package: str, option: str, value: typing.Any) -> None: ...
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __invert__(self) -> Equal:
+        """~self"""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def option(self) -> SpecOption: ...
+    @option.setter
+    def option(self, value: SpecOption) -> None: ...
+    @property
+    def value(self) -> typing.Any: ...
+    @value.setter
+    def value(self, value: typing.Any) -> None: ...
+
+
+class NumOf:
+    def __init__(self, of: typing.Any) -> None: ...
+    def __add__(self, value, /) -> typing.Any:
+        """Return self+value."""
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __mul__(self, value, /) -> typing.Any:
+        """Return self*value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __radd__(self, value, /) -> typing.Any:
+        """Return value+self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __rmul__(self, value, /) -> typing.Any:
+        """Return value*self."""
+    def __rsub__(self, value, /) -> typing.Any:
+        """Return value-self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    def __sub__(self, value, /) -> typing.Any:
+        """Return self-value."""
+    @property
+    def of(self) -> typing.Any: ...
+    @of.setter
+    def of(self, value: typing.Any) -> None: ...
+
+
+class OptionalDepends:
+    """A dependency on `package` if the boolean `option` is enabled, which is off
+    by default
+    """
+    def __init__(self, package: str, option: str, dependency: str) -> None: ...
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def option(self) -> SpecOption: ...
+    @option.setter
+    def option(self, value: SpecOption) -> None: ...
+    @property
+    def package(self) -> str: ...
+    @package.setter
+    def package(self, value: str) -> None: ...
+
+
+class Prefer:
+    """Prefer solutions where `item` holds, with a `weight` relative to other
+    preferences
+    """
+    def __init__(self, item: typing.Any, weight: int = ...) -> None: ...
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def item(self) -> typing.Any: ...
+    @item.setter
+    def item(self, value: typing.Any) -> None: ...
+    @property
+    def weight(self) -> int: ...
+    @weight.setter
+    def weight(self, value: int) -> None: ...
+
+
+class SpecOption:
+    def __init__(self, package_name: str, option_name: str) -> None: ...
+    def __add__(self, value, /) -> typing.Any:
+        """Return self+value."""
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __invert__(self) -> typing.Any:
+        """~self"""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __mul__(self, value, /) -> typing.Any:
+        """Return self*value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __radd__(self, value, /) -> typing.Any:
+        """Return value+self."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __rmul__(self, value, /) -> typing.Any:
+        """Return value*self."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __rsub__(self, value, /) -> typing.Any:
+        """Return value-self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    def __sub__(self, value, /) -> typing.Any:
+        """Return self-value."""
+    def if_then(self, then: typing.Any) -> IfThen: ...
+    @property
+    def option_name(self) -> str: ...
+    @option_name.setter
+    def option_name(self, value: str) -> None: ...
+    @property
+    def package_name(self) -> str: ...
+    @package_name.setter
+    def package_name(self, value: str) -> None: ...
+
+
+class Value:
+    def __init__(self, value: typing.Any) -> None: ...
+    def __add__(self, value, /) -> typing.Any:
+        """Return self+value."""
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __invert__(self) -> typing.Any:
+        """~self"""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __mul__(self, value, /) -> typing.Any:
+        """Return self*value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __radd__(self, value, /) -> typing.Any:
+        """Return value+self."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __rmul__(self, value, /) -> typing.Any:
+        """Return value*self."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __rsub__(self, value, /) -> typing.Any:
+        """Return value-self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    def __sub__(self, value, /) -> typing.Any:
+        """Return self-value."""
+    @property
+    def value(self) -> typing.Any: ...
+    @value.setter
+    def value(self, value: typing.Any) -> None: ...
+
+
+class WhenPlatform:
+    """True if the platform fact `key` is `value`, such as `os` being `linux`.
+
+    Platform facts are fixed before solving (see
+    [`Platform`](crate::spec::platform::Platform)), so this is effectively a
+    constant which can be used as the condition of an
+    [`IfThen`](super::IfThen).
+    """
+    def __init__(self, key: str, value: str) -> None: ...
+    def __and__(self, value, /) -> typing.Any:
+        """Return self&value."""
+    def __eq__(self, value, /) -> typing.Any:
+        """Return self==value."""
+    def __ge__(self, value, /) -> typing.Any:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> typing.Any:
+        """Return self>value."""
+    def __invert__(self) -> typing.Any:
+        """~self"""
+    def __le__(self, value, /) -> typing.Any:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> typing.Any:
+        """Return self<value."""
+    def __ne__(self, value, /) -> typing.Any:
+        """Return self!=value."""
+    def __or__(self, value, /) -> typing.Any:
+        """Return self|value."""
+    def __rand__(self, value, /) -> typing.Any:
+        """Return value&self."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __ror__(self, value, /) -> typing.Any:
+        """Return value|self."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    @property
+    def key(self) -> zpack.spec.PlatformKey: ...
+    @key.setter
+    def key(self, value: zpack.spec.PlatformKey) -> None: ...
+    @property
+    def value(self) -> str: ...
+    @value.setter
+    def value(self, value: str) -> None: ...
+
+
+def depends(name: str) -> Depends:
+    """A dependency on the package `name`"""
+
+
+def if_then(cond: typing.Any, then: typing.Any) -> IfThen:
+    """`then` must hold if `cond` holds"""
+
+
+def maximize(item: typing.Any) -> Maximize:
+    """Prefer solutions where `item` is as large as possible"""
+
+
+def minimize(item: typing.Any) -> Minimize:
+    """Prefer solutions where `item` is as small as possible"""
+
+
+def num_of(*of) -> NumOf:
+    """The number of the given constraints which hold, as in
+    `num_of(option("mpi", "openmpi"), option("mpi", "mpich")) == 1`
+    """
+
+
+def option(package: str, name: str | None = ...) -> SpecOption:
+    """The option `name` of `package`, as in `option("mpi", "shared")`, or
+    `option("mpi:shared")`. Compare it to a value to build a constraint:
+    `option("mpi", "shared") == False`.
+
+    # Errors
+    Errors if `name` is not given and `package` has no `:`.
+    """
+
+
+def parse(txt: str, package: str | None = ...) -> typing.Any:
+    """Parse a constraint written in constraint syntax, such as
+    `"depends(openmpi) when +mpi"` (see [`crate::constraint::syntax`]).
+
+    * `package`: The package `+name` and `~name` refer to
+
+    # Errors
+    Errors if `txt` is not a valid constraint.
+    """
+
+
+def prefer(item: typing.Any, weight: int = ...) -> Prefer:
+    """Prefer solutions where `item` holds, without requiring it, as in
+    `prefer(option("mpi", "openmpi") == True, 10)`
+
+    # Errors
+    Errors if `weight` is zero.
+    """
+
+
+def value(value: typing.Any) -> Value:
+    """A constant, such as `value(3)` or `value(Version("1.2"))`"""
+
+
+def when_platform(**fact) -> WhenPlatform:
+    """True on a platform with the given fact, as in `when_platform(os="linux")`
+
+    # Errors
+    Errors unless exactly one fact is given, or if the fact is unknown.
+    """
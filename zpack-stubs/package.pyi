@@ -0,0 +1,333 @@
+# Generated by `zpack stubs`. Do not edit.
+
+import typing
+import zpack.constraint
+import zpack.spec
+
+
+class Conflict:
+    """A constraint in the conflict which makes an outline unsatisfiable"""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+    def __str__(self) -> str:
+        """Return str(self)."""
+    @property
+    def description(self) -> str: ...
+    @property
+    def kind(self) -> ConflictKind: ...
+    @property
+    def packages(self) -> typing.Any: ...
+
+
+class ConflictKind:
+    """Where a tracked constraint came from"""
+    Constraint: ConflictKind
+    Platform: ConflictKind
+    Policy: ConflictKind
+    Required: ConflictKind
+    Target: ConflictKind
+    Value: ConflictKind
+    def __eq__(self, value, /) -> bool:
+        """Return self==value."""
+    def __ge__(self, value, /):
+        """Return self>=value."""
+    def __gt__(self, value, /):
+        """Return self>value."""
+    def __int__(self):
+        """int(self)"""
+    def __le__(self, value, /):
+        """Return self<=value."""
+    def __lt__(self, value, /):
+        """Return self<value."""
+    def __ne__(self, value, /) -> bool:
+        """Return self!=value."""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+class FlagMapping:
+    """Flags added to a package when a condition holds."""
+    def __init__(self, kind: str, flags: typing.Any, when: typing.Any | None = ...) -> None: ...
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+class PackageDefinition:
+    """A package being defined in a `with` block. Created with
+    `zpack.package.define(name)`
+    """
+    def __init__(self, name: str) -> None: ...
+    def __enter__(self) -> PackageDefinition: ...
+    def __exit__(self, exc_type: typing.Any | None, _exc_value: typing.Any | None, _traceback: typing.Any | None) -> bool:
+        """Register the package, unless the block raised an exception.
+
+        # Panics
+        Panics if the registry lock is poisoned.
+        """
+    def conflicts(self, constraint: typing.Any, when: typing.Any | None = ...) -> typing.Any:
+        """Forbid `constraint` from holding, or only if `when` holds.
+
+        # Errors
+        Errors if `constraint` or `when` is not a valid condition.
+        """
+    def constraint(self, constraint: typing.Any) -> typing.Any:
+        """Add an arbitrary constraint.
+
+        # Errors
+        Errors if `constraint` is not a valid constraint.
+        """
+    def depends_on(self, package: str, when: typing.Any | None = ...) -> typing.Any:
+        """Depend on `package`, or only if `when` holds.
+
+        # Errors
+        Errors if `when` is not a valid constraint.
+        """
+    @property
+    def name(self) -> str: ...
+    def option(self, name: str) -> zpack.constraint.SpecOption:
+        """The option `name` of this package, to build constraints from"""
+    def optional_depends(self, option: str, package: str, description: str | None = ...) -> None:
+        """Declare the boolean option `option`, disabled by default, and depend
+        on `package` if it is enabled.
+
+        * `description`: What the option does
+        """
+    def outline(self) -> PackageOutline:
+        """The outline of the package defined so far"""
+    def prefer(self, constraint: typing.Any, weight: int = ...) -> typing.Any:
+        """Prefer solutions where `constraint` holds, without requiring it. See
+        [`Prefer`]
+
+        * `weight`: How much breaking the preference costs, relative to other
+          preferences
+
+        # Errors
+        Errors if `constraint` is not a valid condition, or `weight` is zero.
+        """
+    def variant(self, name: str, default: typing.Any, values: typing.Any | None = ..., description: str | None = ...) -> None:
+        """Declare the option `name`, with the type of `default`.
+
+        * `values`: The values the option may take. Any value of the right
+          type if not given
+        * `description`: What the option does
+        """
+    def version(self, *versions) -> typing.Any:
+        """Allow the package to have the versions `versions`.
+
+        # Errors
+        Errors if a version is invalid.
+        """
+
+
+class PackageOutline:
+    def __init__(self, name: str) -> None: ...
+    def __eq__(self, value, /) -> bool:
+        """Return self==value."""
+    def __ge__(self, value, /):
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /):
+        """Return self>value."""
+    def __le__(self, value, /):
+        """Return self<=value."""
+    def __lt__(self, value, /):
+        """Return self<value."""
+    def __ne__(self, value, /):
+        """Return self!=value."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    def declare_option(self, name: str, schema: OptionSchema) -> None:
+        """Declare an option. See [`crate::package::schema`]"""
+    def option(self, name: str) -> zpack.constraint.SpecOption:
+        """The option `name` of this package, to build constraints from, as in
+        `outline.option("static") & (outline.version >= "5.0")`
+        """
+    def push_constraint(self, constraint: typing.Any) -> None: ...
+    def push_constraints(self, constraints: typing.Any) -> None: ...
+    def push_flags(self, flags: FlagMapping) -> None: ...
+    def push_optional_depends(self, dep: zpack.constraint.OptionalDepends) -> None:
+        """Add an optional dependency, declaring its option. See
+        [`Self::push_optional_depends`]
+        """
+    def push_patch(self, patch: Patch) -> None: ...
+    def push_source(self, source: Source) -> None: ...
+    def push_test(self, test: SmokeTest) -> None: ...
+    def set_default(self, name: str, value: typing.Any) -> None:
+        """Set the default value of an option, which is inherited by
+        dependencies.
+        """
+    def set_license(self, license: str | None) -> None: ...
+    def set_version_source(self, source: VersionSource | None) -> None: ...
+    @property
+    def version(self) -> zpack.constraint.SpecOption: ...
+
+
+class Patch:
+    """A patch declared by a package."""
+    def __init__(self, source: str, checksum: str, strip: int = ..., when: typing.Any | None = ...) -> None: ...
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+class SmokeTest:
+    """A smoke test declared by a package."""
+    def __init__(self, name: str, command: typing.Any, when: typing.Any | None = ...) -> None: ...
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+class SolveResult:
+    """The outcome of solving a set of specs from Python, with the statistics
+    of the solve. Each variant is its own class, so callers can branch with
+    `match` or `isinstance`.
+    """
+    class Sat(SolveResult):
+        def __init__(self, spec, stats) -> None: ...
+        @property
+        def spec(self): ...
+        @property
+        def stats(self): ...
+    class Unknown(SolveResult):
+        def __init__(self, reason, stats) -> None: ...
+        @property
+        def reason(self): ...
+        @property
+        def stats(self): ...
+    class Unsat(SolveResult):
+        def __init__(self, conflicts, stats) -> None: ...
+        @property
+        def conflicts(self): ...
+        @property
+        def stats(self): ...
+    def __bool__(self) -> bool:
+        """True if self else False"""
+    @property
+    def status(self) -> str: ...
+
+
+class SolveStats:
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+    def __str__(self) -> str:
+        """Return str(self)."""
+    @property
+    def conflicts(self) -> typing.Any: ...
+    @property
+    def decisions(self) -> typing.Any: ...
+    @property
+    def memory(self) -> typing.Any: ...
+    def phases(self) -> typing.Any:
+        """Seconds spent in each phase, in the order the phases ran"""
+    def solver(self) -> typing.Any:
+        """Every statistic reported by the solver, by name"""
+    @property
+    def total(self) -> float: ...
+
+
+class Source:
+    def __init__(self, url: str, checksum: str, version: Version | None = ...) -> None: ...
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+class SpecSolver:
+    def __init__(self, outlines: typing.Any, roots: typing.Any, platform: zpack.spec.Platform | None = ..., deterministic: bool = ..., permissive: bool = ...) -> None: ...
+    def check(self) -> str:
+        """Check whether the spec is satisfiable, returning `"sat"`, `"unsat"`
+        or `"unknown"`.
+        """
+    def concrete_spec(self) -> typing.Any:
+        """The solved spec, or `None` if `check` has not returned `"sat"`.
+
+        # Errors
+        Errors if a package or option has no solver variable.
+        """
+    def conflicts(self) -> typing.Any:
+        """The conflicting constraints, after `check` returned `"unsat"`."""
+    def reason_unknown(self) -> str:
+        """Why the solver gave up, after `check` returned `"unknown"`."""
+    def solve(self) -> SolveResult:
+        """Check the spec and return the outcome: a `SolveResult.Sat` with the
+        solved spec, a `SolveResult.Unsat` with the conflicting constraints or
+        a `SolveResult.Unknown` with the reason the solver gave up.
+
+        # Errors
+        Errors if a package or option has no solver variable.
+        """
+    @property
+    def stats(self) -> SolveStats: ...
+    def to_smtlib(self) -> str: ...
+    def unsat_core(self) -> typing.Any:
+        """Descriptions of the conflicting constraints, after `check` returned
+        `"unsat"`. See `conflicts` for their kinds and packages.
+        """
+
+
+class Version:
+    """A generic version.
+
+    See the documentation for this module for more information.
+    """
+    def __init__(self, ver: str) -> None: ...
+    def __eq__(self, value, /) -> bool:
+        """Return self==value."""
+    def __ge__(self, value, /) -> bool:
+        """Return self>=value."""
+    def __getstate__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __gt__(self, value, /) -> bool:
+        """Return self>value."""
+    def __hash__(self) -> int:
+        """Return hash(self)."""
+    def __le__(self, value, /) -> bool:
+        """Return self<=value."""
+    def __lt__(self, value, /) -> bool:
+        """Return self<value."""
+    def __ne__(self, value, /) -> bool:
+        """Return self!=value."""
+    def __reduce__(self) -> typing.Any:
+        """Helper for pickle."""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+    def __setstate__(self, state: typing.Any) -> typing.Any: ...
+    def __str__(self) -> str:
+        """Return str(self)."""
+    @property
+    def epoch(self) -> typing.Any: ...
+    def is_calendar(self) -> bool: ...
+    def satisfies(self, op: zpack.constraint.CmpType, bound: Version) -> bool:
+        """Whether `self op bound` holds, where `bound` may contain wildcards"""
+
+
+class VersionSource:
+    """The built-in [`VersionProvider`]s, which can be declared on a
+    [`PackageOutline`].
+    """
+    class GithubReleases(VersionSource):
+        def __init__(self, owner, repo, tag_prefix) -> None: ...
+        @property
+        def owner(self): ...
+        @property
+        def repo(self): ...
+        @property
+        def tag_prefix(self): ...
+    class Pypi(VersionSource):
+        def __init__(self, project) -> None: ...
+        @property
+        def project(self): ...
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+    @staticmethod
+    def github(owner: str, repo: str, tag_prefix: str = ...) -> VersionSource:
+        """The releases of `owner/repo` on GitHub"""
+    @staticmethod
+    def pypi(project: str) -> VersionSource:
+        """The releases of `project` on the Python Package Index"""
+
+
+def define(name: str) -> PackageDefinition:
+    """Define the package `name` in a `with` block, as in
+    `with define("openmpi") as p:`. See [`crate::package::define`]
+    """
@@ -0,0 +1,157 @@
+# Generated by `zpack stubs`. Do not edit.
+
+import typing
+import zpack.package
+
+
+class ConcretePackage:
+    """A single, fully concretized package."""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+    def __str__(self) -> str:
+        """Return str(self)."""
+    def build_env(self, rpath: str = ..., prefixes: typing.Any = ...) -> typing.Any:
+        """Environment variables for building this package, such as `CFLAGS`
+
+        * `rpath`: One of `rpath`, `runpath` or `none`; how to embed the
+          library directories of `prefixes` in what is linked
+        * `prefixes`: The prefixes the package's dependencies are installed to
+
+        # Errors
+        Errors if `rpath` is not a valid rpath mode.
+        """
+    def dependencies(self) -> typing.Any: ...
+    def explain(self, option: str) -> str:
+        """Why `option` has its value, such as "default inherited from 'hpl'"
+
+        # Errors
+        Errors if the package has no such option.
+        """
+    def flags(self) -> typing.Any:
+        """Compiler and linker flags, by kind (`cflags`, `ldflags`, etc.)"""
+    def hash(self) -> str:
+        """Hash of this package alone, without its dependencies. Use
+        `ConcreteSpec.hash(name)` for the full DAG hash.
+        """
+    @property
+    def license(self) -> typing.Any: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def namespace(self) -> typing.Any: ...
+    def option(self, name: str) -> typing.Any: ...
+    def options(self) -> typing.Any: ...
+    def patches(self) -> typing.Any:
+        """SHA-256 checksums of the patches applied to this package"""
+    def provenance(self) -> typing.Any:
+        """Why each option, including the version, has its value"""
+    def tests(self) -> typing.Any:
+        """Names of the smoke tests to run once installed"""
+    def to_dict(self) -> typing.Any: ...
+    def to_json(self) -> str: ...
+    def version(self) -> typing.Any: ...
+
+
+class ConcreteSpec:
+    """A set of concretized packages and the roots they were solved for."""
+    def __contains__(self, key, /) -> bool:
+        """Return key in self."""
+    def __getitem__(self, key, /) -> ConcretePackage:
+        """Return self[key]."""
+    def __len__(self) -> int:
+        """Return len(self)."""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+    def __str__(self) -> str:
+        """Return str(self)."""
+    @staticmethod
+    def from_json(txt: str) -> ConcreteSpec: ...
+    def hash(self, name: str) -> str:
+        """The DAG hash of the package `name`"""
+    def packages(self) -> typing.Any: ...
+    def roots(self) -> typing.Any: ...
+    def satisfies(self, spec: str) -> bool:
+        """Whether a root of this spec satisfies the spec string `spec`, such as
+        `"openmpi@5: +cuda ^hwloc"` (see [`crate::spec::query`])
+
+        # Errors
+        Errors if `spec` is not a valid spec string.
+        """
+    def to_dict(self) -> typing.Any: ...
+    def to_json(self) -> str: ...
+
+
+class Platform:
+    """The platform packages are concretized for"""
+    def __init__(self) -> None: ...
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+    def __str__(self) -> str:
+        """Return str(self)."""
+    @property
+    def arch(self) -> str: ...
+    @arch.setter
+    def arch(self, value: str) -> None: ...
+    def get(self, key: str) -> str:
+        """Look up a fact by name, such as `"os"`.
+
+        # Errors
+        Errors if `key` is not a valid platform fact.
+        """
+    @staticmethod
+    def host() -> Platform: ...
+    @property
+    def libc(self) -> str: ...
+    @libc.setter
+    def libc(self, value: str) -> None: ...
+    @property
+    def microarchitecture(self) -> str: ...
+    @microarchitecture.setter
+    def microarchitecture(self, value: str) -> None: ...
+    @property
+    def os(self) -> str: ...
+    @os.setter
+    def os(self, value: str) -> None: ...
+
+
+class PlatformKey:
+    """A single platform fact"""
+    Arch: PlatformKey
+    Libc: PlatformKey
+    Microarchitecture: PlatformKey
+    Os: PlatformKey
+    def __int__(self):
+        """int(self)"""
+    def __repr__(self) -> str:
+        """Return repr(self)."""
+
+
+def concretize(outlines: typing.Any, roots: typing.Any, platform: Platform | None = ..., deterministic: bool = ..., permissive: bool = ...) -> typing.Any:
+    """Concretize `roots` against `outlines`, returning the concrete spec and
+    statistics about how it was solved.
+
+    * `platform`: The platform to concretize for. Defaults to the host
+    * `deterministic`: Fix the solver's random seeds
+    * `permissive`: Allow references to options no package declares
+
+    The GIL is released while solving, so other Python threads keep
+    running and solves may be run in background threads.
+
+    # Errors
+    Errors if the outlines are invalid or the roots cannot be satisfied.
+    """
+
+
+def solve(outlines: typing.Any, roots: typing.Any, platform: Platform | None = ..., deterministic: bool = ..., permissive: bool = ...) -> zpack.package.SolveResult:
+    """Concretize `roots` against `outlines`, returning the outcome.
+
+    The outcome is a `SolveResult.Sat` with the concrete spec, a
+    `SolveResult.Unsat` with the conflicting constraints or a
+    `SolveResult.Unknown` with the reason the solver gave up. Each also
+    holds statistics about the solve. Takes the same arguments as
+    [`concretize`], and likewise releases the GIL while solving.
+
+    # Errors
+    Errors if the outlines are invalid. Unsatisfiable roots are not an
+    error.
+    """